@@ -0,0 +1,68 @@
+//! Compares rendering descriptors as Java types with `DisplayDescriptor` against building
+//! a `String` first. Run with `cargo bench --bench descriptor`.
+
+use std::{
+    fmt::Write,
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use java_classfile::types::{DisplayDescriptor, DisplayMethodDescriptor};
+
+const FIELD_DESCRIPTORS: [&str; 4] = ["I", "[[J", "Ljava/lang/String;", "[Ljava/util/Map$Entry;"];
+const METHOD_DESCRIPTORS: [&str; 3] = ["()V", "(I[Ljava/lang/String;)Ljava/util/List;", "(JDLjava/lang/Object;[[B)Z"];
+const ITERATIONS: u32 = 1_000_000;
+
+/// Renders a field descriptor the allocating way, for comparison.
+fn to_java_type(descriptor: &str) -> String {
+    let element = descriptor.trim_start_matches('[');
+    let mut java_type = match element {
+        "B" => "byte".to_string(),
+        "C" => "char".to_string(),
+        "D" => "double".to_string(),
+        "F" => "float".to_string(),
+        "I" => "int".to_string(),
+        "J" => "long".to_string(),
+        "S" => "short".to_string(),
+        "Z" => "boolean".to_string(),
+        _ => element[1..element.len() - 1].replace('/', "."),
+    };
+    java_type.push_str(&"[]".repeat(descriptor.len() - element.len()));
+    java_type
+}
+
+/// Runs `render` over every descriptor `ITERATIONS` times into a reused buffer, as a
+/// logger writing to its own buffer would, and returns the time per descriptor.
+fn measure(descriptors: &[&str], mut render: impl FnMut(&mut String, &str)) -> Duration {
+    let mut buffer = String::with_capacity(256);
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        for descriptor in descriptors {
+            buffer.clear();
+            render(&mut buffer, black_box(descriptor));
+            black_box(&buffer);
+        }
+    }
+    start.elapsed() / (ITERATIONS * descriptors.len() as u32)
+}
+
+fn main() {
+    let display = measure(&FIELD_DESCRIPTORS, |buffer, descriptor| {
+        write!(buffer, "{}", DisplayDescriptor(descriptor)).unwrap();
+    });
+    let allocating = measure(&FIELD_DESCRIPTORS, |buffer, descriptor| {
+        write!(buffer, "{}", to_java_type(descriptor)).unwrap();
+    });
+    println!("field descriptor:  DisplayDescriptor {:?}, String {:?}", display, allocating);
+
+    let display = measure(&METHOD_DESCRIPTORS, |buffer, descriptor| {
+        write!(buffer, "{}", DisplayMethodDescriptor::named("run", descriptor)).unwrap();
+    });
+    let allocating = measure(&METHOD_DESCRIPTORS, |buffer, descriptor| {
+        let (parameters, return_descriptor) = java_classfile::types::split_method_descriptor(descriptor).unwrap();
+        let return_type = if return_descriptor == "V" { "void".to_string() } else { to_java_type(return_descriptor) };
+        let parameters: Vec<String> = parameters.into_iter().map(to_java_type).collect();
+        write!(buffer, "{} run({})", return_type, parameters.join(", ")).unwrap();
+    });
+    println!("method descriptor: DisplayMethodDescriptor {:?}, String {:?}", display, allocating);
+}
@@ -0,0 +1,208 @@
+//! A miniature class loader: finds a class in a jar, decodes and verifies it, resolves a method
+//! and prints its code as javap does, split into the blocks of its control flow graph.
+//!
+//! ```text
+//! cargo run --example mini_loader --features jar -- app.jar com.example.Main [method]
+//! ```
+//!
+//! Without a method, all methods of the class are printed.
+
+use std::{env, error::Error, fmt::Write, process::ExitCode};
+
+use java_classfile::{
+    cfg::ControlFlowGraph,
+    decode_with_diagnostics,
+    disasm::disassemble_code,
+    jar::Jar,
+    metrics::{opcode_histogram, Scope},
+    operand::{resolve_operands, ResolvedOperand},
+    types::*,
+    validate::{RuleSet, ValidationMode},
+    ParseOptions,
+};
+
+/// Loads the class `class_name`, in binary or internal form, from `jar` and describes its
+/// methods named `method_name`, or all of them.
+fn load(jar: &Jar, class_name: &str, method_name: Option<&str>) -> Result<String, Box<dyn Error>> {
+    let mut out = String::new();
+
+    // Locate the class and decode it.
+    let entry_name = format!("{}.class", class_name.replace('.', "/"));
+    let entry = jar.entry(&entry_name).ok_or_else(|| format!("{} is not in the jar", entry_name))?;
+    let bytes = jar.read(entry)?;
+    let (class_file, diagnostics) = decode_with_diagnostics(&bytes, &ParseOptions::default())?;
+    writeln!(out, "{}: {} bytes, version {}", entry_name, bytes.len(), class_file.version())?;
+    for diagnostic in diagnostics.iter() {
+        writeln!(out, "  {}", diagnostic)?;
+    }
+
+    // Verify it as a class loader would before linking.
+    let issues = RuleSet::new().validate(&class_file, ValidationMode::Strict);
+    for issue in &issues {
+        writeln!(out, "  {}", issue)?;
+    }
+    if !issues.is_empty() {
+        return Err(format!("{} failed verification with {} issues", entry_name, issues.len()).into());
+    }
+
+    // Resolve the methods and walk their code.
+    let constant_pool = &class_file.constant_pool;
+    let mut found = false;
+    for method in &class_file.methods {
+        let name = get_utf8(constant_pool, method.name_index).ok_or("method name is not a CONSTANT_Utf8")?;
+        if method_name.is_some_and(|method_name| method_name != name) {
+            continue;
+        }
+        found = true;
+        let descriptor = get_utf8(constant_pool, method.descriptor_index).ok_or("bad method descriptor")?;
+        writeln!(out, "\n{}{}", name, descriptor)?;
+        let Some(code) = CodeView::new(method, constant_pool)? else {
+            writeln!(out, "  no code")?;
+            continue;
+        };
+
+        let graph = ControlFlowGraph::from_code(&code)?;
+        let histogram = opcode_histogram(Scope::Method(&class_file, method));
+        writeln!(
+            out,
+            "  stack={}, locals={}, instructions={}, blocks={}",
+            code.max_stack,
+            code.max_locals,
+            histogram.total(),
+            graph.blocks().len()
+        )?;
+        for instruction in disassemble_code(code.code, constant_pool) {
+            if let Some(block) = graph.blocks().iter().find(|block| block.start == instruction.pc) {
+                let successors: Vec<_> = block.successors.iter().map(|edge| edge.target.to_string()).collect();
+                writeln!(out, "  block {} -> [{}]", block.start, successors.join(", "))?;
+            }
+            writeln!(out, "    {:>4}: {}", instruction.pc, instruction.javap(constant_pool))?;
+        }
+
+        let mut calls = Vec::new();
+        for (_, operand) in resolve_operands(&class_file, code.code) {
+            match operand {
+                ResolvedOperand::Member(member) if member.descriptor.starts_with('(') => {
+                    calls.push(format!("{}.{}{}", member.owner, member.name, member.descriptor))
+                }
+                ResolvedOperand::InvokeDynamic(call) => {
+                    calls.push(format!("invokedynamic {}{}", call.name, call.descriptor))
+                }
+                _ => {}
+            }
+        }
+        calls.sort();
+        calls.dedup();
+        for call in calls {
+            writeln!(out, "  calls {}", call)?;
+        }
+    }
+    if !found {
+        return Err(format!("{} has no method {}", entry_name, method_name.unwrap_or_default()).into());
+    }
+    Ok(out)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (jar, class_name, method_name) = match args.as_slice() {
+        [jar, class_name] => (jar, class_name, None),
+        [jar, class_name, method_name] => (jar, class_name, Some(method_name.as_str())),
+        _ => {
+            eprintln!("usage: mini_loader <jar> <class> [method]");
+            return ExitCode::from(2);
+        }
+    };
+    let result = Jar::open(jar).map_err(Box::from).and_then(|jar| load(&jar, class_name, method_name));
+    match result {
+        Ok(out) => {
+            print!("{}", out);
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("mini_loader: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::*;
+
+    fn crc32(data: &[u8]) -> u32 {
+        let mut crc = !0u32;
+        for &byte in data {
+            crc ^= byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            }
+        }
+        !crc
+    }
+
+    /// Packs `entries` into a jar without compression.
+    fn stored_jar(entries: &[(String, Vec<u8>)]) -> Vec<u8> {
+        let mut jar = Vec::new();
+        let mut central_directory = Vec::new();
+        for (name, data) in entries {
+            let offset = jar.len() as u32;
+            let header = |signature: u32, central: bool| {
+                let mut header = signature.to_le_bytes().to_vec();
+                if central {
+                    header.extend(20u16.to_le_bytes()); // version made by
+                }
+                header.extend([20, 0, 0, 0, 0, 0, 0, 0, 0, 0]); // version, flags, method, time, date
+                header.extend(crc32(data).to_le_bytes());
+                header.extend((data.len() as u32).to_le_bytes());
+                header.extend((data.len() as u32).to_le_bytes());
+                header.extend((name.len() as u16).to_le_bytes());
+                header.extend(0u16.to_le_bytes()); // extra field length
+                if central {
+                    header.extend([0; 10]); // comment length, disk, internal and external attributes
+                    header.extend(offset.to_le_bytes());
+                }
+                header.extend(name.as_bytes());
+                header
+            };
+            jar.extend(header(0x0403_4B50, false));
+            jar.extend(data);
+            central_directory.extend(header(0x0201_4B50, true));
+        }
+        let central_directory_offset = jar.len() as u32;
+        jar.extend(&central_directory);
+        jar.extend(0x0605_4B50u32.to_le_bytes());
+        jar.extend([0; 4]); // disks
+        jar.extend((entries.len() as u16).to_le_bytes());
+        jar.extend((entries.len() as u16).to_le_bytes());
+        jar.extend((central_directory.len() as u32).to_le_bytes());
+        jar.extend(central_directory_offset.to_le_bytes());
+        jar.extend(0u16.to_le_bytes()); // comment length
+        jar
+    }
+
+    #[test]
+    fn loads_from_jar() {
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/corpus");
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(directory).unwrap() {
+            let path = entry.unwrap().path();
+            let name = format!("corpus/{}", path.file_name().unwrap().to_str().unwrap());
+            entries.push((name, fs::read(path).unwrap()));
+        }
+        let jar = Jar::from_bytes(stored_jar(&entries)).unwrap();
+
+        let out = load(&jar, "corpus.Shapes", Some("total")).unwrap();
+        assert!(out.starts_with("corpus/Shapes.class: "), "{}", out);
+        assert!(out.contains("\ntotal()D\n"), "{}", out);
+        assert!(out.contains("invokeinterface #"), "{}", out);
+        assert!(out.contains("calls java/util/List.iterator()Ljava/util/Iterator;"), "{}", out);
+        assert!(out.contains("  block 0 -> ["), "{}", out);
+
+        assert!(load(&jar, "corpus/Shapes$Circle", None).unwrap().contains("\narea()D\n"));
+        assert!(load(&jar, "corpus.Missing", None).is_err());
+        assert!(load(&jar, "corpus.Shapes", Some("missing")).is_err());
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{encode, error::EncodeError, types::*};
+
+/// Describes a Java agent class to generate.
+///
+/// Class names are in internal form, e.g. `com/example/Agent`.
+#[derive(Debug, Clone, Copy)]
+pub struct AgentSpec<'s> {
+    /// Name of the generated agent class.
+    pub agent_class: &'s str,
+    /// Name of the `java.lang.instrument.ClassFileTransformer` implementation to register.
+    /// It must have a public no-argument constructor.
+    pub transformer_class: &'s str,
+    /// Also generates `agentmain`, so the agent can be attached to a running JVM.
+    pub agentmain: bool,
+}
+
+impl AgentSpec<'_> {
+    /// Returns the manifest attributes declaring the agent class, one per line.
+    pub fn manifest(&self) -> String {
+        let agent_class = self.agent_class.replace('/', ".");
+        let mut manifest = format!("Premain-Class: {}\n", agent_class);
+        if self.agentmain {
+            manifest.push_str(&format!("Agent-Class: {}\n", agent_class));
+        }
+        manifest
+    }
+}
+
+/// Generates the bytes of a Java agent class.
+///
+/// The class has a public no-argument constructor and a
+/// `public static void premain(String, Instrumentation)` method (and `agentmain` if requested)
+/// which registers a new instance of the transformer class through
+/// `Instrumentation.addTransformer`. Fails if a class name is too long to encode.
+///
+/// ref. https://docs.oracle.com/javase/8/docs/api/java/lang/instrument/package-summary.html
+pub fn generate_agent_class(spec: &AgentSpec) -> Result<Vec<u8>, EncodeError> {
+    let mut constant_pool = ConstantPoolBuilder::new();
+    let this_class = constant_pool.class(spec.agent_class);
+    let super_class = constant_pool.class("java/lang/Object");
+    let code_name = constant_pool.utf8("Code") as u16;
+
+    let object_init = constant_pool.method_ref("java/lang/Object", "<init>", "()V") as u16;
+    let transformer_class = constant_pool.class(spec.transformer_class) as u16;
+    let transformer_init = constant_pool.method_ref(spec.transformer_class, "<init>", "()V") as u16;
+    let add_transformer = constant_pool.interface_method_ref(
+        "java/lang/instrument/Instrumentation",
+        "addTransformer",
+        "(Ljava/lang/instrument/ClassFileTransformer;)V",
+    ) as u16;
+
+    let [object_init_high, object_init_low] = object_init.to_be_bytes();
+    let init_code = [
+        0x2A, // aload_0
+        0xB7, object_init_high, object_init_low, // invokespecial java/lang/Object.<init>()V
+        0xB1, // return
+    ];
+
+    let [class_high, class_low] = transformer_class.to_be_bytes();
+    let [init_high, init_low] = transformer_init.to_be_bytes();
+    let [add_high, add_low] = add_transformer.to_be_bytes();
+    let entry_code = [
+        0x2B, // aload_1
+        0xBB, class_high, class_low, // new <transformer>
+        0x59, // dup
+        0xB7, init_high, init_low, // invokespecial <transformer>.<init>()V
+        0xB9, add_high, add_low, 2, 0, // invokeinterface Instrumentation.addTransformer
+        0xB1, // return
+    ];
+
+    let mut methods = vec![MethodInfo {
+        access_flags: MethodAccessFlag::Public as u16,
+        name_index: constant_pool.utf8("<init>"),
+        descriptor_index: constant_pool.utf8("()V"),
+        attributes: Attributes::from(vec![(code_name, code_attribute(1, 1, &init_code))]),
+    }];
+
+    let entry_descriptor = "(Ljava/lang/String;Ljava/lang/instrument/Instrumentation;)V";
+    let entry_points: &[&str] = if spec.agentmain { &["premain", "agentmain"] } else { &["premain"] };
+    for entry_point in entry_points {
+        methods.push(MethodInfo {
+            access_flags: MethodAccessFlag::Public as u16 | MethodAccessFlag::Static as u16,
+            name_index: constant_pool.utf8(*entry_point),
+            descriptor_index: constant_pool.utf8(entry_descriptor),
+            attributes: Attributes::from(vec![(code_name, code_attribute(3, 2, &entry_code))]),
+        });
+    }
+
+    let class_file = JavaClassFile {
+        // Java 8: no StackMapTable is needed as the methods have no branches.
+        major_version: 52,
+        constant_pool: constant_pool.build(),
+        access_flags: ClassAccessFlag::Public as u16 | ClassAccessFlag::Final as u16 | ClassAccessFlag::Super as u16,
+        this_class,
+        super_class,
+        methods,
+        ..JavaClassFile::empty()
+    };
+
+    encode(&class_file)
+}
+
+pub(crate) fn code_attribute(max_stack: u16, max_locals: u16, code: &[u8]) -> AttributeInfo<'_> {
+    AttributeInfo::Code(CodeAttribute {
+        max_stack,
+        max_locals,
+        code_length: code.len(),
+        code,
+        exception_table_length: 0,
+        exception_table: Vec::new(),
+        attributes: Attributes::new(),
+    })
+}
@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+
+use crate::{opcode::instruction_length, types::*};
+
+/// Stable identifier of a class in an [`AnalysisContext`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ClassId(pub u32);
+
+/// Stable identifier of a method in an [`AnalysisContext`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MethodId(pub u32);
+
+/// Stable identifier of a field in an [`AnalysisContext`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FieldId(pub u32);
+
+/// A method or field identified by its owner, name and descriptor.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MemberRef {
+    pub owner: ClassId,
+    pub name: String,
+    pub descriptor: String,
+}
+
+/// Assigns stable integer IDs to the classes, methods and fields of a corpus.
+///
+/// IDs are assigned in interning order and never change, so analyses running in several
+/// passes can store compact ID-based results and map them back to names when reporting.
+#[derive(Debug, Default)]
+pub struct AnalysisContext {
+    class_names: Vec<String>,
+    class_ids: HashMap<String, ClassId>,
+    methods: Vec<MemberRef>,
+    method_ids: HashMap<MemberRef, MethodId>,
+    fields: Vec<MemberRef>,
+    field_ids: HashMap<MemberRef, FieldId>,
+}
+
+impl AnalysisContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the ID of the class named `name` (in internal form), assigning one if needed.
+    pub fn intern_class(&mut self, name: &str) -> ClassId {
+        if let Some(id) = self.class_ids.get(name) {
+            return *id;
+        }
+        let id = ClassId(self.class_names.len() as u32);
+        self.class_names.push(name.to_string());
+        self.class_ids.insert(name.to_string(), id);
+        id
+    }
+
+    /// Returns the ID of a method, assigning one if needed.
+    pub fn intern_method(&mut self, owner: ClassId, name: &str, descriptor: &str) -> MethodId {
+        let member = MemberRef {
+            owner,
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+        };
+        if let Some(id) = self.method_ids.get(&member) {
+            return *id;
+        }
+        let id = MethodId(self.methods.len() as u32);
+        self.methods.push(member.clone());
+        self.method_ids.insert(member, id);
+        id
+    }
+
+    /// Returns the ID of a field, assigning one if needed.
+    pub fn intern_field(&mut self, owner: ClassId, name: &str, descriptor: &str) -> FieldId {
+        let member = MemberRef {
+            owner,
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+        };
+        if let Some(id) = self.field_ids.get(&member) {
+            return *id;
+        }
+        let id = FieldId(self.fields.len() as u32);
+        self.fields.push(member.clone());
+        self.field_ids.insert(member, id);
+        id
+    }
+
+    /// Interns a class and all of its declared methods and fields.
+    ///
+    /// Returns `None` if this_class cannot be resolved through the constant pool.
+    pub fn add_class(&mut self, class_file: &JavaClassFile) -> Option<ClassId> {
+        let constant_pool = &class_file.constant_pool;
+        let id = self.intern_class(get_class_name(constant_pool, class_file.this_class)?);
+
+        for method in &class_file.methods {
+            if let (Some(name), Some(descriptor)) = (
+                get_utf8(constant_pool, method.name_index),
+                get_utf8(constant_pool, method.descriptor_index),
+            ) {
+                self.intern_method(id, name, descriptor);
+            }
+        }
+
+        for field in &class_file.fields {
+            if let (Some(name), Some(descriptor)) = (
+                get_utf8(constant_pool, field.name_index),
+                get_utf8(constant_pool, field.descriptor_index),
+            ) {
+                self.intern_field(id, name, descriptor);
+            }
+        }
+
+        Some(id)
+    }
+
+    pub fn class_id(&self, name: &str) -> Option<ClassId> {
+        self.class_ids.get(name).copied()
+    }
+
+    pub fn method_id(&self, owner: ClassId, name: &str, descriptor: &str) -> Option<MethodId> {
+        self.method_ids
+            .get(&MemberRef {
+                owner,
+                name: name.to_string(),
+                descriptor: descriptor.to_string(),
+            })
+            .copied()
+    }
+
+    pub fn field_id(&self, owner: ClassId, name: &str, descriptor: &str) -> Option<FieldId> {
+        self.field_ids
+            .get(&MemberRef {
+                owner,
+                name: name.to_string(),
+                descriptor: descriptor.to_string(),
+            })
+            .copied()
+    }
+
+    /// Returns the name of a class. Panics if the ID was not assigned by this context.
+    pub fn class_name(&self, id: ClassId) -> &str {
+        &self.class_names[id.0 as usize]
+    }
+
+    /// Returns a method. Panics if the ID was not assigned by this context.
+    pub fn method(&self, id: MethodId) -> &MemberRef {
+        &self.methods[id.0 as usize]
+    }
+
+    /// Returns a field. Panics if the ID was not assigned by this context.
+    pub fn field(&self, id: FieldId) -> &MemberRef {
+        &self.fields[id.0 as usize]
+    }
+
+    pub fn class_count(&self) -> usize {
+        self.class_names.len()
+    }
+
+    pub fn method_count(&self) -> usize {
+        self.methods.len()
+    }
+
+    pub fn field_count(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Iterates over the IDs of all methods owned by `owner`.
+    pub fn methods_of(&self, owner: ClassId) -> impl Iterator<Item = MethodId> + '_ {
+        self.methods
+            .iter()
+            .enumerate()
+            .filter(move |(_, method)| method.owner == owner)
+            .map(|(i, _)| MethodId(i as u32))
+    }
+
+    /// Iterates over the IDs of all fields owned by `owner`.
+    pub fn fields_of(&self, owner: ClassId) -> impl Iterator<Item = FieldId> + '_ {
+        self.fields
+            .iter()
+            .enumerate()
+            .filter(move |(_, field)| field.owner == owner)
+            .map(|(i, _)| FieldId(i as u32))
+    }
+}
+
+/// The value of a static final field, taken from its ConstantValue attribute.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.2
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstantFieldValue {
+    /// Also used for boolean, byte, char and short fields.
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    String(String),
+}
+
+/// A getstatic instruction whose field resolved to a constant value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedRead {
+    /// Index of the method in the `methods` of the class file.
+    pub method_index: usize,
+    /// Offset of the getstatic instruction in the code of the method.
+    pub pc: usize,
+    pub field: FieldId,
+    pub value: ConstantFieldValue,
+}
+
+/// Constant values of static final fields collected across classes.
+///
+/// javac inlines compile-time constants of other classes, but getstatic reads of them
+/// remain in code compiled against an older version of the owner, in code generated by
+/// other compilers, and wherever the owner was compiled separately. This table resolves
+/// such reads into concrete values.
+#[derive(Debug, Default)]
+pub struct ConstantTable {
+    values: HashMap<FieldId, ConstantFieldValue>,
+}
+
+impl ConstantTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the static final fields of `class_file` which have a ConstantValue attribute,
+    /// interning the class and its members in `context`.
+    ///
+    /// Returns the number of fields recorded, or `None` if this_class cannot be resolved.
+    pub fn add_class(&mut self, context: &mut AnalysisContext, class_file: &JavaClassFile) -> Option<usize> {
+        let constant_pool = &class_file.constant_pool;
+        let owner = context.add_class(class_file)?;
+        let mut count = 0;
+
+        for field in &class_file.fields {
+            let flags = FieldAccessFlag::Static as u16 | FieldAccessFlag::Final as u16;
+            if field.access_flags & flags != flags {
+                continue;
+            }
+            let (Some(name), Some(descriptor)) = (
+                get_utf8(constant_pool, field.name_index),
+                get_utf8(constant_pool, field.descriptor_index),
+            ) else {
+                continue;
+            };
+            let Some(value) = constant_value(constant_pool, field, descriptor) else {
+                continue;
+            };
+            if let Some(id) = context.field_id(owner, name, descriptor) {
+                self.values.insert(id, value);
+                count += 1;
+            }
+        }
+
+        Some(count)
+    }
+
+    /// Returns the value of a field, or `None` if it is not a recorded constant.
+    pub fn get(&self, field: FieldId) -> Option<&ConstantFieldValue> {
+        self.values.get(&field)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Finds the getstatic instructions in `class_file` which read a recorded constant.
+    ///
+    /// The field is looked up in the class named by the Fieldref only, so reads through a
+    /// subclass or subinterface of the declaring class are not resolved. Methods whose code
+    /// cannot be decoded are skipped.
+    pub fn resolve_reads(&self, context: &AnalysisContext, class_file: &JavaClassFile) -> Vec<ResolvedRead> {
+        let constant_pool = &class_file.constant_pool;
+        let mut reads = Vec::new();
+
+        for (method_index, method) in class_file.methods.iter().enumerate() {
+            let Ok(Some(code)) = CodeView::new(method, constant_pool) else {
+                continue;
+            };
+            let mut pc = 0;
+            while let Some(length) = instruction_length(code.code, pc) {
+                if code.code[pc] == Opcode::Getstatic as u8 {
+                    let index = u16::from_be_bytes([code.code[pc + 1], code.code[pc + 2]]) as usize;
+                    if let Some((field, value)) = self.resolve_field_ref(context, constant_pool, index) {
+                        reads.push(ResolvedRead {
+                            method_index,
+                            pc,
+                            field,
+                            value: value.clone(),
+                        });
+                    }
+                }
+                pc += length;
+            }
+        }
+
+        reads
+    }
+
+    /// Returns the value of the field `name` with `descriptor` declared by class `owner`,
+    /// or `None` if it is not a recorded constant.
+    pub fn lookup(&self, context: &AnalysisContext, owner: &str, name: &str, descriptor: &str) -> Option<&ConstantFieldValue> {
+        self.values.get(&context.field_id(context.class_id(owner)?, name, descriptor)?)
+    }
+
+    /// Resolves the CONSTANT_Fieldref at `index` to a recorded constant.
+    fn resolve_field_ref(
+        &self,
+        context: &AnalysisContext,
+        constant_pool: &[ConstantPoolInfo],
+        index: usize,
+    ) -> Option<(FieldId, &ConstantFieldValue)> {
+        let (owner, name, descriptor) = get_field_ref(constant_pool, index)?;
+        let field = context.field_id(context.class_id(owner)?, name, descriptor)?;
+        Some((field, self.values.get(&field)?))
+    }
+}
+
+/// Reads the ConstantValue attribute of a field, checking that the constant kind matches
+/// the field descriptor.
+fn constant_value(constant_pool: &[ConstantPoolInfo], field: &FieldInfo, descriptor: &str) -> Option<ConstantFieldValue> {
+    let index = field.attributes.iter().find_map(|(index, attribute)| {
+        match (get_utf8(constant_pool, *index as usize), attribute) {
+            (Some("ConstantValue"), AttributeInfo::ConstantValue(attribute)) => {
+                Some(attribute.constant_value_index as usize)
+            }
+            (Some("ConstantValue"), AttributeInfo::Unknown(&[high, low]))
+            | (Some("ConstantValue"), AttributeInfo::Lazy(LazyAttribute { data: &[high, low], .. })) => {
+                Some(u16::from_be_bytes([high, low]) as usize)
+            }
+            _ => None,
+        }
+    })?;
+
+    match (constant_pool.get(index)?, descriptor) {
+        (ConstantPoolInfo::Integer(info), "I" | "Z" | "B" | "C" | "S") => Some(ConstantFieldValue::Int(info.data)),
+        (ConstantPoolInfo::Long(info), "J") => Some(ConstantFieldValue::Long(info.data)),
+        (ConstantPoolInfo::Float(info), "F") => Some(ConstantFieldValue::Float(info.data)),
+        (ConstantPoolInfo::Double(info), "D") => Some(ConstantFieldValue::Double(info.data)),
+        (ConstantPoolInfo::String(info), "Ljava/lang/String;") => {
+            Some(ConstantFieldValue::String(get_utf8(constant_pool, info.string_index)?.to_string()))
+        }
+        _ => None,
+    }
+}
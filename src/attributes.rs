@@ -1,6 +1,6 @@
-use std::collections::HashMap;
+use std::{any::Any, borrow::Cow, fmt, ops::Range};
 
-use crate::{types::{utf8_info_as_str, ConstantPoolInfo}, utils::read_u16};
+use crate::{diagnostics::DiagnosticKind, encode::{encode_attribute_body, EncodeOptions}, error::{AttributeError, DecodeError, EncodeError}, options::{DuplicateAttributePolicy, ParseOptions}, reader::ClassReader, types::{get_utf8, utf8_info_as_str, AccessFlag, ConstantPoolInfo}, utils::*, validate::ValidationMode};
 
 #[derive(Debug)]
 pub enum AttributeInfo<'a> {
@@ -17,24 +17,453 @@ pub enum AttributeInfo<'a> {
     LineNumberTable(LineNumberTableAttribute),
     LocalVariableTable(LocalVariableTableAttribute),
     LocalVariableTypeTable(LocalVariableTypeTableAttribute),
-    // Deprecated(DeprecatedAttribute),
-    // RuntimeVisibleAnnotations(RuntimeVisibleAnnotationsAttribute),
-    // RuntimeInvisibleAnnotations(RuntimeInvisibleAnnotationsAttribute),
-    // RuntimeVisibleParameterAnnotations(RuntimeVisibleParameterAnnotationsAttribute),
-    // RuntimeInvisibleParameterAnnotations(RuntimeInvisibleParameterAnnotationsAttribute),
-    // RuntimeVisibleTypeAnnotationsAttribute(RuntimeVisibleTypeAnnotationsAttribute),
-    // RuntimeInvisibleTypeAnnotationsAttribute(RuntimeInvisibleTypeAnnotationsAttribute),
+    Deprecated(DeprecatedAttribute),
+    RuntimeVisibleAnnotations(RuntimeVisibleAnnotationsAttribute),
+    RuntimeInvisibleAnnotations(RuntimeInvisibleAnnotationsAttribute),
+    RuntimeVisibleParameterAnnotations(RuntimeVisibleParameterAnnotationsAttribute),
+    RuntimeInvisibleParameterAnnotations(RuntimeInvisibleParameterAnnotationsAttribute),
+    RuntimeVisibleTypeAnnotations(RuntimeVisibleTypeAnnotationsAttribute),
+    RuntimeInvisibleTypeAnnotations(RuntimeInvisibleTypeAnnotationsAttribute),
     // AnnotationDefault(AnnotationDefaultAttribute),
     BootstrapMethods(BootstrapMethodsAttribute),
     // MethodParameters(MethodParametersAttribute),
-    // Module,
-    // ModulePackages,
-    // ModuleMainClass,
+    Module(ModuleAttribute),
+    ModulePackages(ModulePackagesAttribute),
+    ModuleMainClass(ModuleMainClassAttribute),
     NestHost(NestHostAttribute),
     NestMembers(NestMembersAttribute),
     Record(RecordAttribute<'a>),
-    PermittedSubtypes(PermittedSubtypesAttribute),
-    Unknown,
+    PermittedSubclasses(PermittedSubclassesAttribute),
+    CharacterRangeTable(CharacterRangeTableAttribute),
+    CompilationID(CompilationIDAttribute),
+    SourceID(SourceIDAttribute),
+    ScalaSig(ScalaSigAttribute<'a>),
+    Scala(ScalaAttribute),
+    /// A predefined attribute which is not decoded, or whose body is malformed, holding its
+    /// raw body.
+    Unknown(&'a [u8]),
+    /// An attribute not defined by the JVMS, e.g. one of a vendor or custom tool, holding its
+    /// name and raw body so that it survives re-encoding.
+    Raw { name: String, data: &'a [u8] },
+    /// An attribute decoded by a decoder registered with
+    /// [`ParseOptions::register_attribute`](crate::ParseOptions::register_attribute), holding
+    /// the decoded value besides its name and raw body, which is what gets re-encoded.
+    User { name: String, data: &'a [u8], value: Box<dyn UserAttribute> },
+    /// An attribute left undecoded with [`ParseOptions::lazy_attributes`](crate::ParseOptions::lazy_attributes),
+    /// to be decoded on demand.
+    Lazy(LazyAttribute<'a>),
+}
+
+impl AttributeInfo<'_> {
+    /// Returns the JVMS name of a decoded attribute, or `None` for attributes not defined by
+    /// the JVMS and those kept undecoded, whose name is in the constant pool.
+    fn spec_name(&self) -> Option<&'static str> {
+        let name = match self {
+            AttributeInfo::ConstantValue(_) => "ConstantValue",
+            AttributeInfo::Code(_) => "Code",
+            AttributeInfo::StackMapTable(_) => "StackMapTable",
+            AttributeInfo::Exceptions(_) => "Exceptions",
+            AttributeInfo::InnerClasses(_) => "InnerClasses",
+            AttributeInfo::EnclosingMethod(_) => "EnclosingMethod",
+            AttributeInfo::Synthetic(_) => "Synthetic",
+            AttributeInfo::Signature(_) => "Signature",
+            AttributeInfo::SourceFile(_) => "SourceFile",
+            AttributeInfo::LineNumberTable(_) => "LineNumberTable",
+            AttributeInfo::LocalVariableTable(_) => "LocalVariableTable",
+            AttributeInfo::LocalVariableTypeTable(_) => "LocalVariableTypeTable",
+            AttributeInfo::Deprecated(_) => "Deprecated",
+            AttributeInfo::RuntimeVisibleAnnotations(_) => "RuntimeVisibleAnnotations",
+            AttributeInfo::RuntimeInvisibleAnnotations(_) => "RuntimeInvisibleAnnotations",
+            AttributeInfo::RuntimeVisibleParameterAnnotations(_) => "RuntimeVisibleParameterAnnotations",
+            AttributeInfo::RuntimeInvisibleParameterAnnotations(_) => "RuntimeInvisibleParameterAnnotations",
+            AttributeInfo::RuntimeVisibleTypeAnnotations(_) => "RuntimeVisibleTypeAnnotations",
+            AttributeInfo::RuntimeInvisibleTypeAnnotations(_) => "RuntimeInvisibleTypeAnnotations",
+            AttributeInfo::BootstrapMethods(_) => "BootstrapMethods",
+            AttributeInfo::Module(_) => "Module",
+            AttributeInfo::ModulePackages(_) => "ModulePackages",
+            AttributeInfo::ModuleMainClass(_) => "ModuleMainClass",
+            AttributeInfo::NestHost(_) => "NestHost",
+            AttributeInfo::NestMembers(_) => "NestMembers",
+            AttributeInfo::Record(_) => "Record",
+            AttributeInfo::PermittedSubclasses(_) => "PermittedSubclasses",
+            AttributeInfo::CharacterRangeTable(_)
+            | AttributeInfo::CompilationID(_)
+            | AttributeInfo::SourceID(_)
+            | AttributeInfo::ScalaSig(_)
+            | AttributeInfo::Scala(_)
+            | AttributeInfo::Unknown(_)
+            | AttributeInfo::Raw { .. }
+            | AttributeInfo::User { .. }
+            | AttributeInfo::Lazy(_) => return None,
+        };
+        Some(name)
+    }
+
+    /// Returns the first class file major version which defines this attribute, e.g. 55 for
+    /// NestMembers, or `None` if the attribute is not defined by the JVMS.
+    ///
+    /// The name of an undecoded attribute is in the constant pool, so `None` is returned for
+    /// those too; [`attribute_required_version`] takes the name instead.
+    pub fn required_version(&self) -> Option<u16> {
+        self.spec_name().and_then(attribute_required_version)
+    }
+
+    /// Returns the value of a [`AttributeInfo::User`] attribute if it is a `T`.
+    pub fn user_value<T: Any>(&self) -> Option<&T> {
+        match self {
+            AttributeInfo::User { value, .. } => (**value).as_any().downcast_ref(),
+            _ => None,
+        }
+    }
+}
+
+/// A value decoded from a non-standard attribute by a registered decoder.
+///
+/// Implemented for every type that is `Debug`, `Send` and `Sync` and holds no borrows.
+pub trait UserAttribute: Any + fmt::Debug + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: Any + fmt::Debug + Send + Sync> UserAttribute for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// An attribute kept as its name index and raw body until [`decode`](Self::decode) is
+/// called, for uses such as indexing which only need names and flags.
+#[derive(Debug, Clone, Copy)]
+pub struct LazyAttribute<'a> {
+    pub name_index: u16,
+    pub data: &'a [u8],
+}
+
+impl<'a> LazyAttribute<'a> {
+    pub fn name<'c>(&self, constant_pool: &'c [ConstantPoolInfo]) -> Option<&'c str> {
+        get_utf8(constant_pool, self.name_index as usize)
+    }
+
+    /// Decodes the attribute as a decode without
+    /// [`lazy_attributes`](crate::ParseOptions::lazy_attributes) would have.
+    pub fn decode(&self, constant_pool: &[ConstantPoolInfo]) -> Result<AttributeInfo<'a>, DecodeError> {
+        self.decode_with_options(constant_pool, &ParseOptions::default())
+    }
+
+    /// Decodes the attribute with `options`, e.g. to use the decoders registered with them.
+    /// [`lazy_attributes`](crate::ParseOptions::lazy_attributes) is ignored.
+    pub fn decode_with_options(
+        &self,
+        constant_pool: &[ConstantPoolInfo],
+        options: &ParseOptions,
+    ) -> Result<AttributeInfo<'a>, DecodeError> {
+        let mut context = DecodeContext::new(options);
+        context.lazy_attributes = false;
+        let name = self.name(constant_pool).unwrap_or_default();
+        decode_named_attribute(name, self.data, constant_pool, &mut context)
+    }
+}
+
+/// The attributes of a class, field, method, record component or Code attribute, each with
+/// the constant pool index of its name, in class file order.
+///
+/// Several attributes may share a name, e.g. the LineNumberTable attributes javac splits a
+/// table into, and are all kept so that the class file round-trips.
+///
+/// Decoded attributes also keep the body they were decoded from, see [`source`](Self::source).
+#[derive(Debug, Default)]
+pub struct Attributes<'a> {
+    entries: Vec<(u16, AttributeInfo<'a>)>,
+    /// The body each entry was decoded from, or `None` for entries added afterwards.
+    sources: Vec<Option<&'a [u8]>>,
+}
+
+impl<'a> Attributes<'a> {
+    pub fn new() -> Self {
+        Self { entries: Vec::new(), sources: Vec::new() }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { entries: Vec::with_capacity(capacity), sources: Vec::with_capacity(capacity) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterates over the name indices and attributes.
+    pub fn iter(&self) -> std::slice::Iter<'_, (u16, AttributeInfo<'a>)> {
+        self.entries.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, (u16, AttributeInfo<'a>)> {
+        self.entries.iter_mut()
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &AttributeInfo<'a>> {
+        self.entries.iter().map(|(_, attribute)| attribute)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut AttributeInfo<'a>> {
+        self.entries.iter_mut().map(|(_, attribute)| attribute)
+    }
+
+    /// Iterates over the name indices, repeated for attributes sharing a name.
+    pub fn name_indices(&self) -> impl Iterator<Item = u16> + '_ {
+        self.entries.iter().map(|(name_index, _)| *name_index)
+    }
+
+    /// Returns the first attribute whose name is at `name_index`.
+    pub fn get(&self, name_index: u16) -> Option<&AttributeInfo<'a>> {
+        self.entries.iter().find(|(index, _)| *index == name_index).map(|(_, attribute)| attribute)
+    }
+
+    pub fn get_mut(&mut self, name_index: u16) -> Option<&mut AttributeInfo<'a>> {
+        self.entries.iter_mut().find(|(index, _)| *index == name_index).map(|(_, attribute)| attribute)
+    }
+
+    pub fn contains(&self, name_index: u16) -> bool {
+        self.get(name_index).is_some()
+    }
+
+    /// Decodes the attributes left undecoded with
+    /// [`ParseOptions::lazy_attributes`](crate::ParseOptions::lazy_attributes).
+    pub fn decode_lazy(&mut self, constant_pool: &[ConstantPoolInfo]) -> Result<(), DecodeError> {
+        for (_, attribute) in &mut self.entries {
+            if let AttributeInfo::Lazy(lazy) = attribute {
+                *attribute = lazy.decode(constant_pool)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the first attribute named `name`. Unlike [`get`](Self::get), this also finds
+    /// attributes whose name is a duplicate CONSTANT_Utf8.
+    pub fn by_name(&self, name: &str, constant_pool: &[ConstantPoolInfo]) -> Option<&AttributeInfo<'a>> {
+        self.entries
+            .iter()
+            .find(|(index, _)| get_utf8(constant_pool, *index as usize) == Some(name))
+            .map(|(_, attribute)| attribute)
+    }
+
+    /// Returns every attribute named `name`, in order.
+    pub fn all_by_name<'s>(
+        &'s self,
+        name: &'s str,
+        constant_pool: &'s [ConstantPoolInfo],
+    ) -> impl Iterator<Item = &'s AttributeInfo<'a>> + 's {
+        self.entries
+            .iter()
+            .filter(move |(index, _)| get_utf8(constant_pool, *index as usize) == Some(name))
+            .map(|(_, attribute)| attribute)
+    }
+
+    /// Resolves the names of the attributes in `constant_pool`, to look attributes up by name,
+    /// e.g. `method.attributes.named(constant_pool).get("Code")`.
+    pub fn named<'s>(&'s self, constant_pool: &'s [ConstantPoolInfo]) -> NamedAttributes<'s, 'a> {
+        NamedAttributes { attributes: self, constant_pool }
+    }
+
+    /// Returns the body of the attribute at `position` as it was in the class file, or `None`
+    /// if the attribute was added or replaced after decoding.
+    ///
+    /// The body is kept as read even if the attribute is changed through a mutable reference,
+    /// and [`encode`](crate::encode) always writes the attribute as it is now.
+    pub fn source(&self, position: usize) -> Option<&'a [u8]> {
+        self.sources.get(position).copied().flatten()
+    }
+
+    /// Returns the range of the body of the attribute at `position` within `class_bytes`,
+    /// the class file it was decoded from.
+    ///
+    /// Returns `None` if the attribute has no [`source`](Self::source) or it is not part of
+    /// `class_bytes`.
+    pub fn source_range(&self, position: usize, class_bytes: &[u8]) -> Option<Range<usize>> {
+        let source = self.source(position)?;
+        let start = (source.as_ptr() as usize).checked_sub(class_bytes.as_ptr() as usize)?;
+        let end = start + source.len();
+        (end <= class_bytes.len()).then_some(start..end)
+    }
+
+    /// Appends an attribute, keeping those with the same name.
+    pub fn push(&mut self, name_index: u16, attribute: AttributeInfo<'a>) {
+        self.entries.push((name_index, attribute));
+        self.sources.push(None);
+    }
+
+    /// Appends an attribute decoded from `source`.
+    fn push_decoded(&mut self, name_index: u16, attribute: AttributeInfo<'a>, source: &'a [u8]) {
+        self.entries.push((name_index, attribute));
+        self.sources.push(Some(source));
+    }
+
+    /// Replaces the first attribute whose name is at `name_index` and returns it, or appends
+    /// the attribute if there is none.
+    pub fn insert(&mut self, name_index: u16, attribute: AttributeInfo<'a>) -> Option<AttributeInfo<'a>> {
+        match self.entries.iter().position(|(index, _)| *index == name_index) {
+            Some(position) => {
+                self.sources[position] = None;
+                Some(std::mem::replace(&mut self.entries[position].1, attribute))
+            }
+            None => {
+                self.push(name_index, attribute);
+                None
+            }
+        }
+    }
+
+    /// Removes every attribute whose name is at `name_index`, and returns the first of them.
+    pub fn remove(&mut self, name_index: u16) -> Option<AttributeInfo<'a>> {
+        let mut removed = None;
+        let mut kept = Vec::with_capacity(self.entries.len());
+        let mut kept_sources = Vec::with_capacity(self.sources.len());
+        for ((index, attribute), source) in self.entries.drain(..).zip(self.sources.drain(..)) {
+            if index != name_index {
+                kept.push((index, attribute));
+                kept_sources.push(source);
+            } else if removed.is_none() {
+                removed = Some(attribute);
+            }
+        }
+        self.entries = kept;
+        self.sources = kept_sources;
+        removed
+    }
+
+    /// Attaches `value`, decoded outside this crate from the body of the non-standard attribute
+    /// at `position`, turning it into an [`AttributeInfo::User`] whose value
+    /// [`AttributeInfo::user_value`] returns. The body is still what gets encoded.
+    ///
+    /// Returns `value` back if the attribute is not an [`AttributeInfo::Raw`] or
+    /// [`AttributeInfo::User`] one.
+    pub fn set_user_value(
+        &mut self,
+        position: usize,
+        value: Box<dyn UserAttribute>,
+    ) -> Result<(), Box<dyn UserAttribute>> {
+        let Some((_, attribute)) = self.entries.get_mut(position) else {
+            return Err(value);
+        };
+        match attribute {
+            AttributeInfo::Raw { name, data } | AttributeInfo::User { name, data, .. } => {
+                *attribute = AttributeInfo::User { name: std::mem::take(name), data, value };
+                Ok(())
+            }
+            _ => Err(value),
+        }
+    }
+
+    /// Keeps only the attributes for which `keep` returns true.
+    pub fn retain(&mut self, mut keep: impl FnMut(u16, &AttributeInfo<'a>) -> bool) {
+        let entries = std::mem::take(&mut self.entries);
+        let sources = std::mem::take(&mut self.sources);
+        for ((index, attribute), source) in entries.into_iter().zip(sources) {
+            if keep(index, &attribute) {
+                self.entries.push((index, attribute));
+                self.sources.push(source);
+            }
+        }
+    }
+}
+
+/// [`Attributes`] whose names are resolved in a constant pool, made by [`Attributes::named`].
+///
+/// Attributes whose name does not resolve to a CONSTANT_Utf8 are named `""`.
+#[derive(Debug, Clone, Copy)]
+pub struct NamedAttributes<'s, 'a> {
+    attributes: &'s Attributes<'a>,
+    constant_pool: &'s [ConstantPoolInfo<'s>],
+}
+
+impl<'s, 'a> NamedAttributes<'s, 'a> {
+    /// Iterates over the names and attributes, in class file order.
+    pub fn iter(&self) -> impl Iterator<Item = (&'s str, &'s AttributeInfo<'a>)> + 's {
+        let constant_pool = self.constant_pool;
+        self.attributes
+            .iter()
+            .map(move |(index, attribute)| (get_utf8(constant_pool, *index as usize).unwrap_or_default(), attribute))
+    }
+
+    /// Returns the first attribute named `name`.
+    pub fn get(&self, name: &str) -> Option<&'s AttributeInfo<'a>> {
+        self.iter().find(|(attribute_name, _)| *attribute_name == name).map(|(_, attribute)| attribute)
+    }
+
+    /// Returns every attribute named `name`, in order, e.g. all LineNumberTable attributes of
+    /// a Code attribute.
+    pub fn get_all<'n>(&self, name: &'n str) -> impl Iterator<Item = &'s AttributeInfo<'a>> + 'n
+    where
+        's: 'n,
+    {
+        self.iter().filter(move |(attribute_name, _)| *attribute_name == name).map(|(_, attribute)| attribute)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Iterates over the names and bodies of the attributes, in class file order, e.g. for
+    /// decoders outside this crate. Decoded attributes are encoded again, which fails if one
+    /// of their counts or indices has grown too large.
+    ///
+    /// The value decoded from a body can be attached to its attribute with
+    /// [`Attributes::set_user_value`] at the position the body was yielded at.
+    pub fn raw(&self) -> impl Iterator<Item = (&'s str, Result<Cow<'a, [u8]>, EncodeError>)> + 's {
+        let constant_pool = self.constant_pool;
+        self.iter().map(move |(name, attribute)| (name, attribute_body(attribute, constant_pool)))
+    }
+}
+
+/// Returns the body of `attribute`, encoding it if it was decoded.
+pub(crate) fn attribute_body<'a>(
+    attribute: &AttributeInfo<'a>,
+    constant_pool: &[ConstantPoolInfo],
+) -> Result<Cow<'a, [u8]>, EncodeError> {
+    match attribute {
+        AttributeInfo::Unknown(body)
+        | AttributeInfo::Raw { data: body, .. }
+        | AttributeInfo::User { data: body, .. }
+        | AttributeInfo::Lazy(LazyAttribute { data: body, .. }) => Ok(Cow::Borrowed(*body)),
+        attribute => {
+            let mut body = Vec::new();
+            encode_attribute_body(&mut body, attribute, constant_pool, &EncodeOptions::default())?;
+            Ok(Cow::Owned(body))
+        }
+    }
+}
+
+impl<'a> From<Vec<(u16, AttributeInfo<'a>)>> for Attributes<'a> {
+    fn from(entries: Vec<(u16, AttributeInfo<'a>)>) -> Self {
+        let sources = vec![None; entries.len()];
+        Self { entries, sources }
+    }
+}
+
+impl<'a> FromIterator<(u16, AttributeInfo<'a>)> for Attributes<'a> {
+    fn from_iter<I: IntoIterator<Item = (u16, AttributeInfo<'a>)>>(iter: I) -> Self {
+        Self::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl<'a> IntoIterator for Attributes<'a> {
+    type Item = (u16, AttributeInfo<'a>);
+    type IntoIter = std::vec::IntoIter<(u16, AttributeInfo<'a>)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<'s, 'a> IntoIterator for &'s Attributes<'a> {
+    type Item = &'s (u16, AttributeInfo<'a>);
+    type IntoIter = std::slice::Iter<'s, (u16, AttributeInfo<'a>)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
 }
 
 #[derive(Debug)]
@@ -42,7 +471,13 @@ pub struct ConstantValueAttribute {
    pub constant_value_index: u16,
 }
 
-#[derive(Debug)]
+impl ConstantValueAttribute {
+    pub fn new(constant_value_index: u16) -> Self {
+        Self { constant_value_index }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct ExceptionTableEntry {
     pub start_pc: u16,
     pub end_pc: u16,
@@ -50,6 +485,16 @@ pub struct ExceptionTableEntry {
     pub catch_type: u16,
 }
 
+impl ExceptionTableEntry {
+    /// Creates an entry; `start_pc` must be less than `end_pc`.
+    pub fn new(start_pc: u16, end_pc: u16, handler_pc: u16, catch_type: u16) -> Result<Self, AttributeError> {
+        if start_pc >= end_pc {
+            return Err(AttributeError::OutOfRange { while_building: "exception_table", value: start_pc as usize });
+        }
+        Ok(Self { start_pc, end_pc, handler_pc, catch_type })
+    }
+}
+
 #[derive(Debug)]
 pub struct CodeAttribute<'a> {
     pub max_stack: u16,
@@ -57,8 +502,35 @@ pub struct CodeAttribute<'a> {
     pub code_length: usize,
     pub code: &'a [u8],
     pub exception_table_length: usize,
-    pub exception_table: &'a [ExceptionTableEntry],
-    pub attributes: HashMap<u16, AttributeInfo<'a>>,
+    pub exception_table: Vec<ExceptionTableEntry>,
+    pub attributes: Attributes<'a>,
+}
+
+impl<'a> CodeAttribute<'a> {
+    /// Creates a Code attribute; `code` must hold between 1 and 65535 bytes.
+    pub fn new(
+        max_stack: u16,
+        max_locals: u16,
+        code: &'a [u8],
+        exception_table: Vec<ExceptionTableEntry>,
+        attributes: Attributes<'a>,
+    ) -> Result<Self, AttributeError> {
+        if code.is_empty() {
+            return Err(AttributeError::OutOfRange { while_building: "Code", value: 0 });
+        }
+        check_table_len(code.len(), u16::MAX as usize, "Code")?;
+        check_table_len(exception_table.len(), u16::MAX as usize, "exception_table")?;
+        check_table_len(attributes.len(), u16::MAX as usize, "Code")?;
+        Ok(Self {
+            max_stack,
+            max_locals,
+            code_length: code.len(),
+            code,
+            exception_table_length: exception_table.len(),
+            exception_table,
+            attributes,
+        })
+    }
 }
 
 #[derive(Debug)]
@@ -90,12 +562,28 @@ pub struct SameFrame {
     pub frame_type: u8,  // 0-63
 }
 
+impl SameFrame {
+    /// Creates a same_frame; `offset_delta` must be at most 63.
+    pub fn new(offset_delta: u8) -> Result<Self, AttributeError> {
+        check_frame_type(offset_delta, 0..=63)?;
+        Ok(Self { frame_type: offset_delta })
+    }
+}
+
 #[derive(Debug)]
 pub struct SameLocals1StackItemFrame {
     pub frame_type: u8,  // 64-127
     pub stack: VerificationTypeInfo,
 }
 
+impl SameLocals1StackItemFrame {
+    /// Creates a same_locals_1_stack_item_frame; `offset_delta` must be at most 63.
+    pub fn new(offset_delta: u8, stack: VerificationTypeInfo) -> Result<Self, AttributeError> {
+        check_frame_type(offset_delta, 0..=63)?;
+        Ok(Self { frame_type: 64 + offset_delta, stack })
+    }
+}
+
 #[derive(Debug)]
 pub struct SameLocals1StackItemFrameExtended {
     pub frame_type: u8,  // 247
@@ -103,18 +591,38 @@ pub struct SameLocals1StackItemFrameExtended {
     pub stack: VerificationTypeInfo,
 }
 
+impl SameLocals1StackItemFrameExtended {
+    pub fn new(offset_delta: u16, stack: VerificationTypeInfo) -> Self {
+        Self { frame_type: 247, offset_delta, stack }
+    }
+}
+
 #[derive(Debug)]
 pub struct ChopFrame {
     pub frame_type: u8,  // 248-250
     pub offset_delta: u16,
 }
 
+impl ChopFrame {
+    /// Creates a chop_frame removing between 1 and 3 locals.
+    pub fn new(chopped_locals: u8, offset_delta: u16) -> Result<Self, AttributeError> {
+        check_frame_type(chopped_locals, 1..=3)?;
+        Ok(Self { frame_type: 251 - chopped_locals, offset_delta })
+    }
+}
+
 #[derive(Debug)]
 pub struct SameFrameExtended {
     pub frame_type: u8,  // 251
     pub offset_delta: u16,
 }
 
+impl SameFrameExtended {
+    pub fn new(offset_delta: u16) -> Self {
+        Self { frame_type: 251, offset_delta }
+    }
+}
+
 #[derive(Debug)]
 pub struct AppendFrame {
     pub frame_type: u8,  // 252-254
@@ -122,6 +630,14 @@ pub struct AppendFrame {
     pub locals: Vec<VerificationTypeInfo>,
 }
 
+impl AppendFrame {
+    /// Creates an append_frame adding between 1 and 3 locals.
+    pub fn new(offset_delta: u16, locals: Vec<VerificationTypeInfo>) -> Result<Self, AttributeError> {
+        check_frame_type(locals.len().min(u8::MAX as usize) as u8, 1..=3)?;
+        Ok(Self { frame_type: 251 + locals.len() as u8, offset_delta, locals })
+    }
+}
+
 #[derive(Debug)]
 pub struct FullFrame {
     pub frame_type: u8,  // 255
@@ -132,6 +648,23 @@ pub struct FullFrame {
     pub stack: Vec<VerificationTypeInfo>,
 }
 
+impl FullFrame {
+    pub fn new(
+        offset_delta: u16,
+        locals: Vec<VerificationTypeInfo>,
+        stack: Vec<VerificationTypeInfo>,
+    ) -> Result<Self, AttributeError> {
+        Ok(Self {
+            frame_type: 255,
+            offset_delta,
+            number_of_locals: check_table_len(locals.len(), u16::MAX as usize, "full_frame")?,
+            locals,
+            number_of_stack_items: check_table_len(stack.len(), u16::MAX as usize, "full_frame")?,
+            stack,
+        })
+    }
+}
+
 #[derive(Debug)]
 pub enum StackMapFrame {
     SameFrame(SameFrame),
@@ -143,18 +676,43 @@ pub enum StackMapFrame {
     FullFrame(FullFrame),
 }
 
+/// The StackMapTable attribute of a Code attribute.
+///
+/// Each frame keeps its frame_type, so a table encodes back to the bytes it was decoded from,
+/// including frames written in a longer form than needed, e.g. a full_frame repeating the locals
+/// of the previous frame.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.4
 #[derive(Debug)]
 pub struct StackMapTableAttribute {
     pub number_of_entries: u16,
     pub entries: Vec<StackMapFrame>,
 }
 
+impl StackMapTableAttribute {
+    pub fn new(entries: Vec<StackMapFrame>) -> Result<Self, AttributeError> {
+        Ok(Self {
+            number_of_entries: check_table_len(entries.len(), u16::MAX as usize, "StackMapTable")?,
+            entries,
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct ExceptionsAttribute {
     pub number_of_exceptions: u16,
     pub exception_index_table: Vec<u16>,
 }
 
+impl ExceptionsAttribute {
+    pub fn new(exception_index_table: Vec<u16>) -> Result<Self, AttributeError> {
+        Ok(Self {
+            number_of_exceptions: check_table_len(exception_index_table.len(), u16::MAX as usize, "Exceptions")?,
+            exception_index_table,
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct InnerClassInfo {
     pub inner_class_info_index: u16,
@@ -163,18 +721,46 @@ pub struct InnerClassInfo {
     pub inner_class_access_flags: u16,
 }
 
+impl InnerClassInfo {
+    pub fn new(
+        inner_class_info_index: u16,
+        outer_class_info_index: u16,
+        inner_name_index: u16,
+        inner_class_access_flags: u16,
+    ) -> Self {
+        Self { inner_class_info_index, outer_class_info_index, inner_name_index, inner_class_access_flags }
+    }
+}
+
 #[derive(Debug)]
 pub struct InnerClassesAttribute {
     pub number_of_classes: u16,
     pub classes: Vec<InnerClassInfo>,
 }
 
+impl InnerClassesAttribute {
+    pub fn new(classes: Vec<InnerClassInfo>) -> Result<Self, AttributeError> {
+        Ok(Self {
+            number_of_classes: check_table_len(classes.len(), u16::MAX as usize, "InnerClasses")?,
+            classes,
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct EnclosingMethodAttribute {
     pub class_index: u16,
     pub method_index: u16,
 }
 
+impl EnclosingMethodAttribute {
+    /// Creates an EnclosingMethod attribute; `method_index` is 0 when the class is not
+    /// enclosed by a method.
+    pub fn new(class_index: u16, method_index: u16) -> Self {
+        Self { class_index, method_index }
+    }
+}
+
 #[derive(Debug)]
 pub struct SyntheticAttribute;
 
@@ -183,29 +769,62 @@ pub struct SignatureAttribute {
     pub signature_index: u16,
 }
 
+impl SignatureAttribute {
+    pub fn new(signature_index: u16) -> Self {
+        Self { signature_index }
+    }
+
+    /// Returns the signature the attribute refers to, e.g. `Ljava/util/List<Ljava/lang/String;>;`,
+    /// or `None` if `signature_index` is not a CONSTANT_Utf8.
+    pub fn signature<'c>(&self, constant_pool: &'c [ConstantPoolInfo]) -> Option<&'c str> {
+        get_utf8(constant_pool, self.signature_index as usize)
+    }
+}
+
 #[derive(Debug)]
 pub struct SourceFileAttribute {
     pub sourcefile_index: u16,
 }
 
+impl SourceFileAttribute {
+    pub fn new(sourcefile_index: u16) -> Self {
+        Self { sourcefile_index }
+    }
+}
+
 // #[derive(Debug)]
 // pub struct SourceDebugExtensionAttribute {
 //     pub debug_extension: Vec<u8>,
 // }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct LineNumberTableEntry {
     pub start_pc: u16,
     pub line_number: u16,
 }
 
+impl LineNumberTableEntry {
+    pub fn new(start_pc: u16, line_number: u16) -> Self {
+        Self { start_pc, line_number }
+    }
+}
+
 #[derive(Debug)]
 pub struct LineNumberTableAttribute {
     pub line_number_table_length: u16,
     pub line_number_table: Vec<LineNumberTableEntry>,
 }
 
-#[derive(Debug)]
+impl LineNumberTableAttribute {
+    pub fn new(line_number_table: Vec<LineNumberTableEntry>) -> Result<Self, AttributeError> {
+        Ok(Self {
+            line_number_table_length: check_table_len(line_number_table.len(), u16::MAX as usize, "LineNumberTable")?,
+            line_number_table,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct LocalVariableTableEntry {
     pub start_pc: u16,
     pub length: u16,
@@ -214,13 +833,34 @@ pub struct LocalVariableTableEntry {
     pub index: usize,
 }
 
+impl LocalVariableTableEntry {
+    pub fn new(start_pc: u16, length: u16, name_index: u16, descriptor_index: u16, index: u16) -> Self {
+        Self {
+            start_pc,
+            length,
+            name_index: name_index as usize,
+            descriptor_index: descriptor_index as usize,
+            index: index as usize,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct LocalVariableTableAttribute {
     pub local_variable_table_length: u16,
     pub local_variable_table: Vec<LocalVariableTableEntry>,
 }
 
-#[derive(Debug)]
+impl LocalVariableTableAttribute {
+    pub fn new(local_variable_table: Vec<LocalVariableTableEntry>) -> Result<Self, AttributeError> {
+        Ok(Self {
+            local_variable_table_length: check_table_len(local_variable_table.len(), u16::MAX as usize, "LocalVariableTable")?,
+            local_variable_table,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct LocalVariableTypeTableEntry {
     pub start_pc: u16,
     pub length: u16,
@@ -229,124 +869,750 @@ pub struct LocalVariableTypeTableEntry {
     pub index: usize,
 }
 
+impl LocalVariableTypeTableEntry {
+    pub fn new(start_pc: u16, length: u16, name_index: u16, signature_index: u16, index: u16) -> Self {
+        Self {
+            start_pc,
+            length,
+            name_index: name_index as usize,
+            signature_index: signature_index as usize,
+            index: index as usize,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct LocalVariableTypeTableAttribute {
     pub local_variable_type_table_length: u16,
     pub local_variable_type_table: Vec<LocalVariableTypeTableEntry>,
 }
 
-// #[derive(Debug)]
-// pub struct DeprecatedAttribute;
-//
-// #[derive(Debug)]
-// pub enum AnnotationElementValueEntryValue {
-//     ConstValueIndex(u16),
-//     EnumConstValue { type_name_index: u16, const_name_index: u16 },
-//     ClassInfoIndex(u16),
-//     AnnotationValue(AnnotationEntry),
-//     ArrayValue { num_values: u16, values: Vec<AnnotationElementValueEntryValue> },
-// }
-//
-// #[derive(Debug)]
-// pub struct AnnotationElementValue {
-//     pub tag: u8,
-//     pub value: AnnotationElementValueEntryValue,
-// }
-//
-// #[derive(Debug)]
-// pub struct AnnotationElementValueEntry {
-//     pub default_value: AnnotationElementValue,
-// }
-//
-// #[derive(Debug)]
-// pub struct AnnotationEntry {
-//     pub type_index: u16,
-//     pub num_element_value_pairs: u16,
-// }
-//
-// #[derive(Debug)]
-// pub struct RuntimeVisibleAnnotationsAttribute {
-//     pub num_annotations: u16,
-//     pub annotations: Vec<AnnotationEntry>,
-// }
-//
-// #[derive(Debug)]
-// pub struct RuntimeInvisibleAnnotationsAttribute {
-//     pub num_annotations: u16,
-//     pub annotations: Vec<AnnotationEntry>,
-// }
-//
-// #[derive(Debug)]
-// pub struct RuntimeVisibleParameterAnnotationsAttribute {
-//     pub num_parameters: u8,
-//     pub parameter_annotations: Vec<Vec<AnnotationEntry>>,
-// }
-//
-// #[derive(Debug)]
-// pub struct RuntimeInvisibleParameterAnnotationsAttribute {
-//     pub num_parameters: u8,
-//     pub parameter_annotations: Vec<Vec<AnnotationEntry>>,
-// }
-//
-// #[derive(Debug)]
-// pub struct RuntimeVisibleTypeAnnotationsAttribute {
-//     pub num_annotations: u16,
-//     pub annotations: Vec<AnnotationEntry>,
-// }
-//
-// #[derive(Debug)]
-// pub struct RuntimeInvisibleTypeAnnotationsAttribute {
-//     pub num_annotations: u16,
-//     pub annotations: Vec<AnnotationEntry>,
-// }
-//
-//
-// #[derive(Debug)]
-// pub struct AnnotationDefaultAttribute {
-//     pub default_value: AnnotationElementValue,
-// }
+impl LocalVariableTypeTableAttribute {
+    pub fn new(local_variable_type_table: Vec<LocalVariableTypeTableEntry>) -> Result<Self, AttributeError> {
+        Ok(Self {
+            local_variable_type_table_length: check_table_len(local_variable_type_table.len(), u16::MAX as usize, "LocalVariableTypeTable")?,
+            local_variable_type_table,
+        })
+    }
+}
 
 #[derive(Debug)]
-pub struct BootstrapMethodEntry {
-    pub bootstrap_method_ref: usize,
-    pub num_bootstrap_arguments: usize,
-    pub bootstrap_arguments: Vec<usize>,
+pub struct DeprecatedAttribute;
+
+/// The value of an element_value.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.16.1
+#[derive(Debug, Clone)]
+pub enum AnnotationElementValueEntryValue {
+    /// Index of a constant of the type given by the tag (B, C, D, F, I, J, S, Z or s).
+    ConstValueIndex(u16),
+    /// Indices of the field descriptor of an enum class and the simple name of its constant.
+    EnumConstValue { type_name_index: u16, const_name_index: u16 },
+    /// Index of a return descriptor, e.g. `Ljava/lang/Object;` or `V`.
+    ClassInfoIndex(u16),
+    AnnotationValue(AnnotationEntry),
+    ArrayValue { num_values: u16, values: Vec<AnnotationElementValue> },
 }
 
-#[derive(Debug)]
-pub struct BootstrapMethodsAttribute {
-    pub num_bootstrap_methods: u16,
-    pub bootstrap_methods: Vec<BootstrapMethodEntry>,
+impl AnnotationElementValueEntryValue {
+    /// Creates an array value.
+    pub fn array(values: Vec<AnnotationElementValue>) -> Result<Self, AttributeError> {
+        Ok(Self::ArrayValue {
+            num_values: check_table_len(values.len(), u16::MAX as usize, "element_value")?,
+            values,
+        })
+    }
 }
 
-// #[derive(Debug)]
-// pub struct MethodParametersEntry {
-//     pub name_index: u16,
-//     pub access_flags: u16,
-// }
-//
-// #[derive(Debug)]
-// pub struct MethodParametersAttribute {
-//     pub parameters_count: u8,
-//     pub parameters: Vec<MethodParametersEntry>,
-// }
+#[derive(Debug, Clone)]
+pub struct AnnotationElementValue {
+    pub tag: u8,
+    pub value: AnnotationElementValueEntryValue,
+}
 
-#[derive(Debug)]
-pub struct NestHostAttribute {
+impl AnnotationElementValue {
+    /// Creates an element_value; `tag` must match the kind of `value`.
+    pub fn new(tag: u8, value: AnnotationElementValueEntryValue) -> Result<Self, AttributeError> {
+        let consistent = match value {
+            AnnotationElementValueEntryValue::ConstValueIndex(_) => CONST_VALUE_TAGS.contains(&tag),
+            AnnotationElementValueEntryValue::EnumConstValue { .. } => tag == b'e',
+            AnnotationElementValueEntryValue::ClassInfoIndex(_) => tag == b'c',
+            AnnotationElementValueEntryValue::AnnotationValue(_) => tag == b'@',
+            AnnotationElementValueEntryValue::ArrayValue { .. } => tag == b'[',
+        };
+        if !consistent {
+            return Err(AttributeError::OutOfRange { while_building: "element_value", value: tag as usize });
+        }
+        Ok(Self { tag, value })
+    }
+}
+
+/// Tags of element values holding a constant pool index of a constant.
+const CONST_VALUE_TAGS: [u8; 9] = [b'B', b'C', b'D', b'F', b'I', b'J', b'S', b'Z', b's'];
+
+#[derive(Debug, Clone)]
+pub struct AnnotationElementValuePair {
+    pub element_name_index: u16,
+    pub value: AnnotationElementValue,
+}
+
+impl AnnotationElementValuePair {
+    pub fn new(element_name_index: u16, value: AnnotationElementValue) -> Self {
+        Self { element_name_index, value }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AnnotationEntry {
+    /// Index of the field descriptor of the annotation interface.
+    pub type_index: u16,
+    pub num_element_value_pairs: u16,
+    pub element_value_pairs: Vec<AnnotationElementValuePair>,
+}
+
+impl AnnotationEntry {
+    pub fn new(type_index: u16, element_value_pairs: Vec<AnnotationElementValuePair>) -> Result<Self, AttributeError> {
+        Ok(Self {
+            type_index,
+            num_element_value_pairs: check_table_len(element_value_pairs.len(), u16::MAX as usize, "annotation")?,
+            element_value_pairs,
+        })
+    }
+
+    /// Resolves the annotation interface and the element values against `constant_pool`.
+    ///
+    /// Returns `None` if the type does not resolve to a CONSTANT_Utf8; elements whose name or
+    /// value does not resolve are skipped.
+    pub fn resolve<'c>(&self, constant_pool: &'c [ConstantPoolInfo]) -> Option<ResolvedAnnotation<'c>> {
+        let elements = self.element_value_pairs.iter().filter_map(|pair| {
+            let name = get_utf8(constant_pool, pair.element_name_index as usize)?;
+            Some((name, pair.value.resolve(constant_pool)?))
+        });
+        Some(ResolvedAnnotation {
+            type_descriptor: get_utf8(constant_pool, self.type_index as usize)?,
+            elements: elements.collect(),
+        })
+    }
+}
+
+/// An annotation with its type and element values resolved, see [`AnnotationEntry::resolve`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedAnnotation<'c> {
+    /// Field descriptor of the annotation interface, e.g. `Ljava/lang/Deprecated;`.
+    pub type_descriptor: &'c str,
+    /// Element names and values, in class file order. Elements left to their defaults are absent.
+    pub elements: Vec<(&'c str, ElementValue<'c>)>,
+}
+
+impl<'c> ResolvedAnnotation<'c> {
+    /// Returns the value of the element named `name`.
+    pub fn element(&self, name: &str) -> Option<&ElementValue<'c>> {
+        self.elements.iter().find(|(element, _)| *element == name).map(|(_, value)| value)
+    }
+}
+
+/// A resolved element_value.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.16.1
+#[derive(Debug, Clone, PartialEq)]
+pub enum ElementValue<'c> {
+    Byte(i8),
+    Char(char),
+    Double(f64),
+    Float(f32),
+    Int(i32),
+    Long(i64),
+    Short(i16),
+    Boolean(bool),
+    String(&'c str),
+    /// Field descriptor of the enum class and the simple name of the constant.
+    Enum { type_descriptor: &'c str, constant: &'c str },
+    /// Return descriptor of a class literal, e.g. `Ljava/lang/Object;` or `V`.
+    Class(&'c str),
+    Annotation(ResolvedAnnotation<'c>),
+    Array(Vec<ElementValue<'c>>),
+}
+
+impl AnnotationElementValue {
+    /// Resolves the value against `constant_pool`, or returns `None` if an index does not
+    /// resolve to a constant of the kind given by the tag.
+    pub fn resolve<'c>(&self, constant_pool: &'c [ConstantPoolInfo]) -> Option<ElementValue<'c>> {
+        Some(match &self.value {
+            AnnotationElementValueEntryValue::ConstValueIndex(index) => {
+                let constant = constant_pool.get(*index as usize)?;
+                match (self.tag, constant) {
+                    (b'B', ConstantPoolInfo::Integer(info)) => ElementValue::Byte(info.data as i8),
+                    (b'C', ConstantPoolInfo::Integer(info)) => {
+                        ElementValue::Char(char::from_u32(info.data as u16 as u32)?)
+                    }
+                    (b'D', ConstantPoolInfo::Double(info)) => ElementValue::Double(info.data),
+                    (b'F', ConstantPoolInfo::Float(info)) => ElementValue::Float(info.data),
+                    (b'I', ConstantPoolInfo::Integer(info)) => ElementValue::Int(info.data),
+                    (b'J', ConstantPoolInfo::Long(info)) => ElementValue::Long(info.data),
+                    (b'S', ConstantPoolInfo::Integer(info)) => ElementValue::Short(info.data as i16),
+                    (b'Z', ConstantPoolInfo::Integer(info)) => ElementValue::Boolean(info.data != 0),
+                    (b's', _) => ElementValue::String(get_utf8(constant_pool, *index as usize)?),
+                    _ => return None,
+                }
+            }
+            AnnotationElementValueEntryValue::EnumConstValue { type_name_index, const_name_index } => {
+                ElementValue::Enum {
+                    type_descriptor: get_utf8(constant_pool, *type_name_index as usize)?,
+                    constant: get_utf8(constant_pool, *const_name_index as usize)?,
+                }
+            }
+            AnnotationElementValueEntryValue::ClassInfoIndex(index) => {
+                ElementValue::Class(get_utf8(constant_pool, *index as usize)?)
+            }
+            AnnotationElementValueEntryValue::AnnotationValue(annotation) => {
+                ElementValue::Annotation(annotation.resolve(constant_pool)?)
+            }
+            AnnotationElementValueEntryValue::ArrayValue { values, .. } => {
+                ElementValue::Array(values.iter().map(|value| value.resolve(constant_pool)).collect::<Option<_>>()?)
+            }
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct RuntimeVisibleAnnotationsAttribute {
+    pub num_annotations: u16,
+    pub annotations: Vec<AnnotationEntry>,
+}
+
+impl RuntimeVisibleAnnotationsAttribute {
+    pub fn new(annotations: Vec<AnnotationEntry>) -> Result<Self, AttributeError> {
+        Ok(Self {
+            num_annotations: check_table_len(annotations.len(), u16::MAX as usize, "RuntimeVisibleAnnotations")?,
+            annotations,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct RuntimeInvisibleAnnotationsAttribute {
+    pub num_annotations: u16,
+    pub annotations: Vec<AnnotationEntry>,
+}
+
+impl RuntimeInvisibleAnnotationsAttribute {
+    pub fn new(annotations: Vec<AnnotationEntry>) -> Result<Self, AttributeError> {
+        Ok(Self {
+            num_annotations: check_table_len(annotations.len(), u16::MAX as usize, "RuntimeInvisibleAnnotations")?,
+            annotations,
+        })
+    }
+}
+
+/// Annotations of the formal parameters of a method, one list per parameter.
+#[derive(Debug)]
+pub struct RuntimeVisibleParameterAnnotationsAttribute {
+    pub num_parameters: u8,
+    pub parameter_annotations: Vec<Vec<AnnotationEntry>>,
+}
+
+impl RuntimeVisibleParameterAnnotationsAttribute {
+    pub fn new(parameter_annotations: Vec<Vec<AnnotationEntry>>) -> Result<Self, AttributeError> {
+        Ok(Self {
+            num_parameters: check_parameter_annotations(&parameter_annotations, "RuntimeVisibleParameterAnnotations")?,
+            parameter_annotations,
+        })
+    }
+}
+
+/// Annotations of the formal parameters of a method, one list per parameter.
+#[derive(Debug)]
+pub struct RuntimeInvisibleParameterAnnotationsAttribute {
+    pub num_parameters: u8,
+    pub parameter_annotations: Vec<Vec<AnnotationEntry>>,
+}
+
+impl RuntimeInvisibleParameterAnnotationsAttribute {
+    pub fn new(parameter_annotations: Vec<Vec<AnnotationEntry>>) -> Result<Self, AttributeError> {
+        Ok(Self {
+            num_parameters: check_parameter_annotations(&parameter_annotations, "RuntimeInvisibleParameterAnnotations")?,
+            parameter_annotations,
+        })
+    }
+}
+
+/// Checks that the number of parameters fits in num_parameters and each parameter's
+/// annotations in num_annotations.
+fn check_parameter_annotations(parameter_annotations: &[Vec<AnnotationEntry>], while_building: &'static str) -> Result<u8, AttributeError> {
+    for annotations in parameter_annotations {
+        check_table_len(annotations.len(), u16::MAX as usize, while_building)?;
+    }
+    Ok(check_table_len(parameter_annotations.len(), u8::MAX as usize, while_building)? as u8)
+}
+
+/// The part of a declaration or expression a type annotation applies to, selected by
+/// target_type.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.20.1
+#[derive(Debug, Clone)]
+pub enum TargetInfo {
+    /// 0x00, 0x01: a type parameter of a generic class or method.
+    TypeParameter { type_parameter_index: u8 },
+    /// 0x10: the superclass (`65535`) or an index into the interfaces of the class.
+    Supertype { supertype_index: u16 },
+    /// 0x11, 0x12: a bound of a type parameter of a generic class or method.
+    TypeParameterBound { type_parameter_index: u8, bound_index: u8 },
+    /// 0x13, 0x14, 0x15: the type of a field or record component, the return type of a
+    /// method or the receiver type of a method.
+    Empty,
+    /// 0x16: a formal parameter, not counting the receiver.
+    FormalParameter { formal_parameter_index: u8 },
+    /// 0x17: an index into the Exceptions attribute of the method.
+    Throws { throws_type_index: u16 },
+    /// 0x40, 0x41: a local variable or resource variable, live in each of the ranges.
+    LocalVar { table_length: u16, table: Vec<LocalVarTargetEntry> },
+    /// 0x42: an index into the exception table of the Code attribute.
+    Catch { exception_table_index: u16 },
+    /// 0x43 to 0x46: the instanceof, new or method reference instruction at `offset`.
+    Offset { offset: u16 },
+    /// 0x47 to 0x4B: a type argument of the cast, constructor call, method call or method
+    /// reference instruction at `offset`.
+    TypeArgument { offset: u16, type_argument_index: u8 },
+}
+
+impl TargetInfo {
+    /// Returns true if `target_type` selects this kind of target_info.
+    pub fn accepts(&self, target_type: u8) -> bool {
+        match self {
+            TargetInfo::TypeParameter { .. } => matches!(target_type, 0x00 | 0x01),
+            TargetInfo::Supertype { .. } => target_type == 0x10,
+            TargetInfo::TypeParameterBound { .. } => matches!(target_type, 0x11 | 0x12),
+            TargetInfo::Empty => matches!(target_type, 0x13..=0x15),
+            TargetInfo::FormalParameter { .. } => target_type == 0x16,
+            TargetInfo::Throws { .. } => target_type == 0x17,
+            TargetInfo::LocalVar { .. } => matches!(target_type, 0x40 | 0x41),
+            TargetInfo::Catch { .. } => target_type == 0x42,
+            TargetInfo::Offset { .. } => matches!(target_type, 0x43..=0x46),
+            TargetInfo::TypeArgument { .. } => matches!(target_type, 0x47..=0x4B),
+        }
+    }
+
+    /// Creates a localvar_target.
+    pub fn local_var(table: Vec<LocalVarTargetEntry>) -> Result<Self, AttributeError> {
+        Ok(TargetInfo::LocalVar {
+            table_length: check_table_len(table.len(), u16::MAX as usize, "localvar_target")?,
+            table,
+        })
+    }
+}
+
+/// A range of code in which a local variable is live in slot `index`.
+#[derive(Debug, Clone)]
+pub struct LocalVarTargetEntry {
+    pub start_pc: u16,
+    pub length: u16,
+    pub index: u16,
+}
+
+impl LocalVarTargetEntry {
+    pub fn new(start_pc: u16, length: u16, index: u16) -> Self {
+        Self { start_pc, length, index }
+    }
+}
+
+/// One step of a type_path.
+///
+/// `type_path_kind` is 0 to go deeper in an array type, 1 into a nested type, 2 onto the
+/// bound of a wildcard type argument, or 3 onto the type argument `type_argument_index`
+/// of a parameterized type.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.20.2
+#[derive(Debug, Clone)]
+pub struct TypePathEntry {
+    pub type_path_kind: u8,
+    pub type_argument_index: u8,
+}
+
+impl TypePathEntry {
+    pub fn new(type_path_kind: u8, type_argument_index: u8) -> Result<Self, AttributeError> {
+        if type_path_kind > 3 || (type_path_kind != 3 && type_argument_index != 0) {
+            return Err(AttributeError::OutOfRange { while_building: "type_path", value: type_path_kind as usize });
+        }
+        Ok(Self { type_path_kind, type_argument_index })
+    }
+}
+
+/// The part of the type named by target_info which is annotated; empty for the type itself.
+#[derive(Debug, Clone)]
+pub struct TypePath {
+    pub path_length: u8,
+    pub path: Vec<TypePathEntry>,
+}
+
+impl TypePath {
+    pub fn new(path: Vec<TypePathEntry>) -> Result<Self, AttributeError> {
+        Ok(Self {
+            path_length: check_table_len(path.len(), u8::MAX as usize, "type_path")? as u8,
+            path,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeAnnotation {
+    pub target_type: u8,
+    pub target_info: TargetInfo,
+    pub target_path: TypePath,
+    /// Index of the field descriptor of the annotation interface.
+    pub type_index: u16,
+    pub num_element_value_pairs: u16,
+    pub element_value_pairs: Vec<AnnotationElementValuePair>,
+}
+
+impl TypeAnnotation {
+    /// Creates a type annotation; `target_type` must select the kind of `target_info`.
+    pub fn new(
+        target_type: u8,
+        target_info: TargetInfo,
+        target_path: TypePath,
+        type_index: u16,
+        element_value_pairs: Vec<AnnotationElementValuePair>,
+    ) -> Result<Self, AttributeError> {
+        if !target_info.accepts(target_type) {
+            return Err(AttributeError::OutOfRange { while_building: "type_annotation", value: target_type as usize });
+        }
+        Ok(Self {
+            target_type,
+            target_info,
+            target_path,
+            type_index,
+            num_element_value_pairs: check_table_len(element_value_pairs.len(), u16::MAX as usize, "type_annotation")?,
+            element_value_pairs,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct RuntimeVisibleTypeAnnotationsAttribute {
+    pub num_annotations: u16,
+    pub annotations: Vec<TypeAnnotation>,
+}
+
+impl RuntimeVisibleTypeAnnotationsAttribute {
+    pub fn new(annotations: Vec<TypeAnnotation>) -> Result<Self, AttributeError> {
+        Ok(Self {
+            num_annotations: check_table_len(annotations.len(), u16::MAX as usize, "RuntimeVisibleTypeAnnotations")?,
+            annotations,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct RuntimeInvisibleTypeAnnotationsAttribute {
+    pub num_annotations: u16,
+    pub annotations: Vec<TypeAnnotation>,
+}
+
+impl RuntimeInvisibleTypeAnnotationsAttribute {
+    pub fn new(annotations: Vec<TypeAnnotation>) -> Result<Self, AttributeError> {
+        Ok(Self {
+            num_annotations: check_table_len(annotations.len(), u16::MAX as usize, "RuntimeInvisibleTypeAnnotations")?,
+            annotations,
+        })
+    }
+}
+
+// #[derive(Debug)]
+// pub struct AnnotationDefaultAttribute {
+//     pub default_value: AnnotationElementValue,
+// }
+
+#[derive(Debug)]
+pub struct BootstrapMethodEntry {
+    pub bootstrap_method_ref: usize,
+    pub num_bootstrap_arguments: usize,
+    pub bootstrap_arguments: Vec<usize>,
+}
+
+impl BootstrapMethodEntry {
+    pub fn new(bootstrap_method_ref: u16, bootstrap_arguments: Vec<u16>) -> Result<Self, AttributeError> {
+        let num_bootstrap_arguments = check_table_len(bootstrap_arguments.len(), u16::MAX as usize, "bootstrap_methods")?;
+        Ok(Self {
+            bootstrap_method_ref: bootstrap_method_ref as usize,
+            num_bootstrap_arguments: num_bootstrap_arguments as usize,
+            bootstrap_arguments: bootstrap_arguments.into_iter().map(usize::from).collect(),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct BootstrapMethodsAttribute {
+    pub num_bootstrap_methods: u16,
+    pub bootstrap_methods: Vec<BootstrapMethodEntry>,
+}
+
+impl BootstrapMethodsAttribute {
+    pub fn new(bootstrap_methods: Vec<BootstrapMethodEntry>) -> Result<Self, AttributeError> {
+        Ok(Self {
+            num_bootstrap_methods: check_table_len(bootstrap_methods.len(), u16::MAX as usize, "BootstrapMethods")?,
+            bootstrap_methods,
+        })
+    }
+}
+
+// #[derive(Debug)]
+// pub struct MethodParametersEntry {
+//     pub name_index: u16,
+//     pub access_flags: u16,
+// }
+//
+// #[derive(Debug)]
+// pub struct MethodParametersAttribute {
+//     pub parameters_count: u8,
+//     pub parameters: Vec<MethodParametersEntry>,
+// }
+
+/// Flags of a module in module_flags.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.25
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModuleFlag {
+    Open = 0x0020,
+    Synthetic = 0x1000,
+    Mandated = 0x8000,
+}
+
+/// Flags of a module dependence in requires_flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequiresFlag {
+    Transitive = 0x0020,
+    StaticPhase = 0x0040,
+    Synthetic = 0x1000,
+    Mandated = 0x8000,
+}
+
+/// Flags of an exported or opened package in exports_flags and opens_flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportsFlag {
+    Synthetic = 0x1000,
+    Mandated = 0x8000,
+}
+
+impl AccessFlag for ModuleFlag {
+    /// Tests if the flag has a specific module flag.
+    fn test(&self, flag: u16) -> bool {
+        (*self as u16 & flag) != 0
+    }
+}
+
+impl AccessFlag for RequiresFlag {
+    /// Tests if the flag has a specific requires flag.
+    fn test(&self, flag: u16) -> bool {
+        (*self as u16 & flag) != 0
+    }
+}
+
+impl AccessFlag for ExportsFlag {
+    /// Tests if the flag has a specific exports or opens flag.
+    fn test(&self, flag: u16) -> bool {
+        (*self as u16 & flag) != 0
+    }
+}
+
+#[derive(Debug)]
+pub struct ModuleRequiresEntry {
+    /// A CONSTANT_Module_info.
+    pub requires_index: u16,
+    pub requires_flags: u16,
+    /// A CONSTANT_Utf8_info, or zero if no version is recorded.
+    pub requires_version_index: u16,
+}
+
+#[derive(Debug)]
+pub struct ModuleExportsEntry {
+    /// A CONSTANT_Package_info.
+    pub exports_index: u16,
+    pub exports_flags: u16,
+    pub exports_to_count: u16,
+    /// CONSTANT_Module_info entries; empty for an unqualified export.
+    pub exports_to_index: Vec<u16>,
+}
+
+impl ModuleExportsEntry {
+    pub fn new(exports_index: u16, exports_flags: u16, exports_to_index: Vec<u16>) -> Result<Self, AttributeError> {
+        Ok(Self {
+            exports_index,
+            exports_flags,
+            exports_to_count: check_table_len(exports_to_index.len(), u16::MAX as usize, "exports_to_index")?,
+            exports_to_index,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct ModuleOpensEntry {
+    /// A CONSTANT_Package_info.
+    pub opens_index: u16,
+    pub opens_flags: u16,
+    pub opens_to_count: u16,
+    /// CONSTANT_Module_info entries; empty for an unqualified open.
+    pub opens_to_index: Vec<u16>,
+}
+
+impl ModuleOpensEntry {
+    pub fn new(opens_index: u16, opens_flags: u16, opens_to_index: Vec<u16>) -> Result<Self, AttributeError> {
+        Ok(Self {
+            opens_index,
+            opens_flags,
+            opens_to_count: check_table_len(opens_to_index.len(), u16::MAX as usize, "opens_to_index")?,
+            opens_to_index,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct ModuleProvidesEntry {
+    /// A CONSTANT_Class_info naming the service interface.
+    pub provides_index: u16,
+    pub provides_with_count: u16,
+    /// CONSTANT_Class_info entries naming the implementations.
+    pub provides_with_index: Vec<u16>,
+}
+
+impl ModuleProvidesEntry {
+    pub fn new(provides_index: u16, provides_with_index: Vec<u16>) -> Result<Self, AttributeError> {
+        Ok(Self {
+            provides_index,
+            provides_with_count: check_table_len(provides_with_index.len(), u16::MAX as usize, "provides_with_index")?,
+            provides_with_index,
+        })
+    }
+}
+
+/// The module descriptor of a module-info class.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.25
+#[derive(Debug)]
+pub struct ModuleAttribute {
+    /// A CONSTANT_Module_info.
+    pub module_name_index: u16,
+    pub module_flags: u16,
+    /// A CONSTANT_Utf8_info, or zero if no version is recorded.
+    pub module_version_index: u16,
+    pub requires_count: u16,
+    pub requires: Vec<ModuleRequiresEntry>,
+    pub exports_count: u16,
+    pub exports: Vec<ModuleExportsEntry>,
+    pub opens_count: u16,
+    pub opens: Vec<ModuleOpensEntry>,
+    pub uses_count: u16,
+    /// CONSTANT_Class_info entries naming the services used.
+    pub uses_index: Vec<u16>,
+    pub provides_count: u16,
+    pub provides: Vec<ModuleProvidesEntry>,
+}
+
+impl ModuleAttribute {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        module_name_index: u16,
+        module_flags: u16,
+        module_version_index: u16,
+        requires: Vec<ModuleRequiresEntry>,
+        exports: Vec<ModuleExportsEntry>,
+        opens: Vec<ModuleOpensEntry>,
+        uses_index: Vec<u16>,
+        provides: Vec<ModuleProvidesEntry>,
+    ) -> Result<Self, AttributeError> {
+        Ok(Self {
+            module_name_index,
+            module_flags,
+            module_version_index,
+            requires_count: check_table_len(requires.len(), u16::MAX as usize, "requires")?,
+            requires,
+            exports_count: check_table_len(exports.len(), u16::MAX as usize, "exports")?,
+            exports,
+            opens_count: check_table_len(opens.len(), u16::MAX as usize, "opens")?,
+            opens,
+            uses_count: check_table_len(uses_index.len(), u16::MAX as usize, "uses_index")?,
+            uses_index,
+            provides_count: check_table_len(provides.len(), u16::MAX as usize, "provides")?,
+            provides,
+        })
+    }
+}
+
+/// The packages of a module, including those not exported or opened.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.26
+#[derive(Debug)]
+pub struct ModulePackagesAttribute {
+    pub package_count: u16,
+    /// CONSTANT_Package_info entries.
+    pub package_index: Vec<u16>,
+}
+
+impl ModulePackagesAttribute {
+    pub fn new(package_index: Vec<u16>) -> Result<Self, AttributeError> {
+        Ok(Self {
+            package_count: check_table_len(package_index.len(), u16::MAX as usize, "ModulePackages")?,
+            package_index,
+        })
+    }
+}
+
+/// The main class of a module.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.27
+#[derive(Debug)]
+pub struct ModuleMainClassAttribute {
+    /// A CONSTANT_Class_info.
+    pub main_class_index: u16,
+}
+
+impl ModuleMainClassAttribute {
+    pub fn new(main_class_index: u16) -> Self {
+        Self { main_class_index }
+    }
+}
+
+#[derive(Debug)]
+pub struct NestHostAttribute {
     pub host_class_index: u16,
 }
 
+impl NestHostAttribute {
+    pub fn new(host_class_index: u16) -> Self {
+        Self { host_class_index }
+    }
+}
+
 #[derive(Debug)]
 pub struct NestMembersAttribute {
     pub number_of_classes: u16,
     pub classes: Vec<u16>,
 }
 
+impl NestMembersAttribute {
+    pub fn new(classes: Vec<u16>) -> Result<Self, AttributeError> {
+        Ok(Self {
+            number_of_classes: check_table_len(classes.len(), u16::MAX as usize, "NestMembers")?,
+            classes,
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct RecordComponentInfo<'a> {
     pub name_index: u16,
     pub descriptor_index: u16,
-    pub attributes: HashMap<u16, AttributeInfo<'a>>,
+    pub attributes: Attributes<'a>,
+}
+
+impl<'a> RecordComponentInfo<'a> {
+    pub fn new(
+        name_index: u16,
+        descriptor_index: u16,
+        attributes: Attributes<'a>,
+    ) -> Result<Self, AttributeError> {
+        check_table_len(attributes.len(), u16::MAX as usize, "record_component_info")?;
+        Ok(Self { name_index, descriptor_index, attributes })
+    }
 }
 
 #[derive(Debug)]
@@ -355,121 +1621,1091 @@ pub struct RecordAttribute<'a> {
     pub components: Vec<RecordComponentInfo<'a>>,
 }
 
+impl<'a> RecordAttribute<'a> {
+    pub fn new(components: Vec<RecordComponentInfo<'a>>) -> Result<Self, AttributeError> {
+        Ok(Self {
+            components_count: check_table_len(components.len(), u16::MAX as usize, "Record")?,
+            components,
+        })
+    }
+}
+
 #[derive(Debug)]
-pub struct PermittedSubtypesAttribute {
+pub struct PermittedSubclassesAttribute {
     pub number_of_classes: u16,
     pub classes: Vec<u16>,
 }
 
+impl PermittedSubclassesAttribute {
+    pub fn new(classes: Vec<u16>) -> Result<Self, AttributeError> {
+        Ok(Self {
+            number_of_classes: check_table_len(classes.len(), u16::MAX as usize, "PermittedSubclasses")?,
+            classes,
+        })
+    }
+}
+
+/// An entry of CharacterRangeTable, mapping a range of code to a range of source
+/// characters. Source positions are encoded as `line << 10 | column`.
+#[derive(Debug, Clone)]
+pub struct CharacterRangeTableEntry {
+    pub start_pc: u16,
+    /// Offset of the last byte of the code range, which is inclusive.
+    pub end_pc: u16,
+    pub character_range_start: u32,
+    pub character_range_end: u32,
+    /// CRT_* flags of the range, e.g. 0x0001 for a statement.
+    pub flags: u16,
+}
+
+/// CharacterRangeTable, written into Code by javac with `-Xjcov`.
+#[derive(Debug)]
+pub struct CharacterRangeTableAttribute {
+    pub character_range_table_length: u16,
+    pub character_range_table: Vec<CharacterRangeTableEntry>,
+}
+
+impl CharacterRangeTableAttribute {
+    pub fn new(character_range_table: Vec<CharacterRangeTableEntry>) -> Result<Self, AttributeError> {
+        Ok(Self {
+            character_range_table_length: check_table_len(
+                character_range_table.len(),
+                u16::MAX as usize,
+                "CharacterRangeTable",
+            )?,
+            character_range_table,
+        })
+    }
+}
+
+/// CompilationID, written by javac with `-XDgenerateCompilationID` as used by the JDK build.
+#[derive(Debug)]
+pub struct CompilationIDAttribute {
+    pub compilation_id_index: u16,
+}
+
+impl CompilationIDAttribute {
+    pub fn new(compilation_id_index: u16) -> Self {
+        Self { compilation_id_index }
+    }
+}
+
+/// SourceID, written by javac with `-XDgenerateSourceID` as used by the JDK build.
+#[derive(Debug)]
+pub struct SourceIDAttribute {
+    pub source_id_index: u16,
+}
+
+impl SourceIDAttribute {
+    pub fn new(source_id_index: u16) -> Self {
+        Self { source_id_index }
+    }
+}
+
+/// ScalaSig, written by scalac on every top-level class it compiles.
+///
+/// Since Scala 2.8 the body is an empty pickle, `5 0 0`, and the pickle is held by a
+/// `scala.reflect.ScalaSignature` annotation; see [`crate::scala`].
+#[derive(Debug)]
+pub struct ScalaSigAttribute<'a> {
+    /// The pickle, starting with its major and minor version.
+    pub pickle: &'a [u8],
+}
+
+/// Scala, an empty attribute written by scalac on classes whose signature is pickled into
+/// another class, e.g. a companion object.
+#[derive(Debug)]
+pub struct ScalaAttribute;
+
+/// Checks that a table of `count` entries fits in its u16 count field.
+fn check_table_len(count: usize, max: usize, while_building: &'static str) -> Result<u16, AttributeError> {
+    if count > max {
+        return Err(AttributeError::TooManyEntries { while_building, count, max });
+    }
+    Ok(count as u16)
+}
+
+/// Checks that a value encoded in frame_type is within `range`.
+fn check_frame_type(value: u8, range: std::ops::RangeInclusive<u8>) -> Result<(), AttributeError> {
+    if !range.contains(&value) {
+        return Err(AttributeError::OutOfRange { while_building: "stack_map_frame", value: value as usize });
+    }
+    Ok(())
+}
+
+/// Attribute names in the order they are listed in the JVM specification.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7-310
+pub const SPEC_ATTRIBUTE_ORDER: [&str; 30] = [
+    "ConstantValue",
+    "Code",
+    "StackMapTable",
+    "Exceptions",
+    "InnerClasses",
+    "EnclosingMethod",
+    "Synthetic",
+    "Signature",
+    "SourceFile",
+    "SourceDebugExtension",
+    "LineNumberTable",
+    "LocalVariableTable",
+    "LocalVariableTypeTable",
+    "Deprecated",
+    "RuntimeVisibleAnnotations",
+    "RuntimeInvisibleAnnotations",
+    "RuntimeVisibleParameterAnnotations",
+    "RuntimeInvisibleParameterAnnotations",
+    "RuntimeVisibleTypeAnnotations",
+    "RuntimeInvisibleTypeAnnotations",
+    "AnnotationDefault",
+    "BootstrapMethods",
+    "MethodParameters",
+    "Module",
+    "ModulePackages",
+    "ModuleMainClass",
+    "NestHost",
+    "NestMembers",
+    "Record",
+    "PermittedSubclasses",
+];
+
+/// Returns the first class file major version which defines the predefined attribute
+/// `name`, e.g. 55 for NestMembers, or `None` if the JVMS does not define it.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7-310
+pub fn attribute_required_version(name: &str) -> Option<u16> {
+    let version = match name {
+        "ConstantValue" | "Code" | "Exceptions" | "InnerClasses" | "Synthetic" | "SourceFile" | "LineNumberTable"
+        | "LocalVariableTable" | "Deprecated" => 45,
+        "EnclosingMethod" | "Signature" | "SourceDebugExtension" | "LocalVariableTypeTable"
+        | "RuntimeVisibleAnnotations" | "RuntimeInvisibleAnnotations" | "RuntimeVisibleParameterAnnotations"
+        | "RuntimeInvisibleParameterAnnotations" | "AnnotationDefault" => 49,
+        "StackMapTable" => 50,
+        "BootstrapMethods" => 51,
+        "RuntimeVisibleTypeAnnotations" | "RuntimeInvisibleTypeAnnotations" | "MethodParameters" => 52,
+        "Module" | "ModulePackages" | "ModuleMainClass" => 53,
+        "NestHost" | "NestMembers" => 55,
+        "Record" => 60,
+        "PermittedSubclasses" => 61,
+        _ => return None,
+    };
+    Some(version)
+}
+
+/// Order in which the attributes of an owner are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AttributeOrder {
+    /// Keeps the order the attributes were decoded (or inserted) in.
+    #[default]
+    Preserve,
+    /// Sorts attributes by their position in the JVM specification, then
+    /// unknown attributes alphabetically by name. Useful for reproducible outputs.
+    Canonical,
+}
+
+/// Returns the attributes of an owner in the requested order.
+pub fn ordered_attributes<'s, 'a>(
+    attributes: &'s Attributes<'a>,
+    order: AttributeOrder,
+    constant_pool: &[ConstantPoolInfo],
+) -> Vec<&'s (u16, AttributeInfo<'a>)> {
+    let mut ordered: Vec<_> = attributes.iter().collect();
+    if order == AttributeOrder::Canonical {
+        ordered.sort_by_cached_key(|(index, _)| {
+            let name = utf8_info_as_str!(constant_pool, *index as usize);
+            let position = SPEC_ATTRIBUTE_ORDER
+                .iter()
+                .position(|spec_name| *spec_name == name)
+                .unwrap_or(SPEC_ATTRIBUTE_ORDER.len());
+            (position, name)
+        });
+    }
+    ordered
+}
+
+/// Attributes written by javac and the JDK build which the JVMS does not define, yet are
+/// decoded like the attributes it does.
+pub const JDK_ATTRIBUTES: [&str; 3] = ["CharacterRangeTable", "CompilationID", "SourceID"];
+
+/// Attributes written by scalac, decoded like those of the JVMS.
+pub const SCALA_ATTRIBUTES: [&str; 2] = ["ScalaSig", "Scala"];
+
+/// Predefined attributes which may appear more than once on the same owner.
+const REPEATABLE_ATTRIBUTES: [&str; 5] = ["Synthetic", "Deprecated", "LineNumberTable", "LocalVariableTable", "LocalVariableTypeTable"];
+
+/// The smallest attribute: attribute_name_index and attribute_length.
+const MIN_ATTRIBUTE_SIZE: usize = size_of::<u16>() + size_of::<u32>();
+
+/// Attributes within Code or Record attributes nested deeper than this are rejected, bounding
+/// the recursion of the decoder. Class files written by compilers nest them one level deep.
+const MAX_ATTRIBUTE_DEPTH: usize = 16;
+
 /// Decodes attributes
-/// TODO
-pub fn decode_attributes<'a>(buffer: &'a [u8], constant_pool: &[ConstantPoolInfo]) -> (HashMap<u16, AttributeInfo<'a>>, &'a [u8]) {
-    let (head, rest) = buffer.split_at(size_of::<u16>());
-    let attributes_count = read_u16(head) as usize;
-    let attributes: HashMap<u16, AttributeInfo<'a>> = HashMap::new();
+pub(crate) fn decode_attributes<'a>(
+    reader: &mut ClassReader<'a>,
+    constant_pool: &[ConstantPoolInfo],
+    context: &mut DecodeContext,
+) -> Result<Attributes<'a>, DecodeError> {
+    if context.attribute_depth > MAX_ATTRIBUTE_DEPTH {
+        return Err(DecodeError::NestingTooDeep { while_parsing: "attributes", max: MAX_ATTRIBUTE_DEPTH });
+    }
+    let attributes_count = reader.read_u16("attributes_count")? as usize;
+    reader.check_count(attributes_count, MIN_ATTRIBUTE_SIZE, "attributes")?;
+    context.budget.reserve::<(u16, AttributeInfo)>(attributes_count, "attributes")?;
+    let mut attributes = Attributes::with_capacity(attributes_count);
 
-    let mut buffer = rest;
     for _ in 0..attributes_count {
-        let (head, rest) = buffer.split_at(size_of::<u16>());
-        let attribute_name_index = read_u16(head);
-        let attribute_name = utf8_info_as_str!(constant_pool, attribute_name_index as usize);
-        let (head, rest) = rest.split_at(size_of::<u16>());
-        let _attribute_length = read_u16(head) as usize;
-        buffer = rest;
-
-        match attribute_name {
-            // "ConstantValue" => {
-            //     let attribute_info = decode_constant_value_attribute(buffer)?;
-            //     attributes.insert(attribute_name.to_string(), attribute_info);
-            // },
-
-            // "Code" => {
-            //     let attribute_info = decode_code_attribute(buffer, constant_pool)?;
-            //     attributes.insert(attribute_name.to_string(), attribute_info);
-            // },
-
-            // "StackMapTable" => {
-            //     let attribute_info = decode_stack_map_table(buffer)?;
-            //     attributes.insert(attribute_name.to_string(), attribute_info);
-            // },
-
-            // "BootstrapMethods" => {
-            //     let attribute_info = decode_bootstrap_methods_attribute(buffer)?;
-            //     attributes.insert(attribute_name.to_string(), attribute_info);
-            // },
-
-            // "NestHost" => {
-            //     let attribute_info = decode_nest_host_attribute(buffer)?;
-            //     attributes.insert(attribute_name.to_string(), attribute_info);
-            // },
-
-            // "NestMembers" => {
-            //     let attribute_info = decode_nest_members_attribute(buffer)?;
-            //     attributes.insert(attribute_name.to_string(), attribute_info);
-            // },
-
-            // "PermittedSubclasses" => {
-            //     let attribute_info = decode_permitted_subclasses_attribute(buffer)?;
-            //     attributes.insert(attribute_name.to_string(), attribute_info);
-            // },
-
-            // "Exceptions" => {
-            //     let attribute_info = decode_exceptions_attribute(buffer)?;
-            //     attributes.insert(attribute_name.to_string(), attribute_info);
-            // },
-
-            // "InnerClasses" => {
-            //     let attribute_info = decode_inner_classes_attribute(buffer)?;
-            //     attributes.insert(attribute_name.to_string(), attribute_info);
-            // },
-
-            // "EnclosingMethod" => {
-            //     let attribute_info = decode_enclosing_method_attribute(buffer)?;
-            //     attributes.insert(attribute_name.to_string(), attribute_info);
-            // },
-
-            // "Synthetic" => {
-            //     let attribute_info = decode_synthetic_attribute()?;
-            //     attributes.insert(attribute_name.to_string(), attribute_info);
-            // },
-
-            // "Signature" => {
-            //     let attribute_info = decode_signature_attribute(buffer)?;
-            //     attributes.insert(attribute_name.to_string(), attribute_info);
-            // },
-
-            // "Record" => {
-            //     let attribute_info = decode_record_attribute(buffer, constant_pool)?;
-            //     attributes.insert(attribute_name.to_string(), attribute_info);
-            // },
-
-            // "SourceFile" => {
-            //     let attribute_info = decode_source_file_attribute(buffer)?;
-            //     attributes.insert(attribute_name.to_string(), attribute_info);
-            // },
-
-            // "LineNumberTable" => {
-            //     let attribute_info = decode_line_number_table_attribute(buffer)?;
-            //     attributes.insert(attribute_name.to_string(), attribute_info);
-            // },
-
-            // "LocalVariableTable" => {
-            //     let attribute_info = decode_local_variable_table_attribute(buffer)?;
-            //     attributes.insert(attribute_name.to_string(), attribute_info);
-            // },
-
-            // "LocalVariableTypeTable" => {
-            //     let attribute_info = decode_local_variable_type_table_attribute(buffer)?;
-            //     attributes.insert(attribute_name.to_string(), attribute_info);
-            // },
-
-            _ => {
-                // let _attribute_info = AttributeInfo::Unknown;
-                // let _ = buffer.split_to(attribute_length as usize);
-                // attributes.insert(attribute_name.to_string(), attribute_info);
+        let attribute_name_index = reader.read_u16("attribute_info")?;
+        let attribute_length = reader.read_u32("attribute_info")? as usize;
+        let body = reader.read_bytes(attribute_length, "attribute_info")?;
+
+        let attribute_name = get_utf8(constant_pool, attribute_name_index as usize).unwrap_or_default();
+        let is_standard = is_standard(attribute_name);
+        if !is_standard {
+            // The attribute and the diagnostic about it each keep a copy of the name.
+            context.budget.reserve::<u8>(2 * attribute_name.len(), "attribute_info")?;
+        }
+        if !is_standard && context.attribute_decoders.get(attribute_name).is_none() {
+            context.diagnostics.push(
+                DiagnosticKind::UnknownAttribute,
+                context.location.as_str(),
+                format!("unknown attribute \"{}\"", attribute_name),
+            );
+        }
+
+        let attribute = if context.lazy_attributes {
+            AttributeInfo::Lazy(LazyAttribute { name_index: attribute_name_index, data: body })
+        } else {
+            decode_named_attribute(attribute_name, body, constant_pool, context)?
+        };
+        // Two CONSTANT_Utf8 entries may hold the same name, so duplicates are found by name.
+        let resolved_name = get_utf8(constant_pool, attribute_name_index as usize);
+        let existing = attributes.entries.iter().position(|(index, _)| match resolved_name {
+            Some(name) => get_utf8(constant_pool, *index as usize) == Some(name),
+            None => *index == attribute_name_index,
+        });
+        let Some(existing) = existing else {
+            attributes.push_decoded(attribute_name_index, attribute, body);
+            continue;
+        };
+
+        let policy = if REPEATABLE_ATTRIBUTES.contains(&attribute_name) || !is_standard {
+            DuplicateAttributePolicy::KeepAll
+        } else {
+            context.duplicate_attributes
+        };
+        match policy {
+            DuplicateAttributePolicy::Error => {
+                return Err(DecodeError::DuplicateAttribute {
+                    location: context.location.clone(),
+                    name: attribute_name.to_string(),
+                });
+            }
+            DuplicateAttributePolicy::KeepFirst => {}
+            DuplicateAttributePolicy::KeepLast => {
+                attributes.entries[existing].1 = attribute;
+                attributes.sources[existing] = Some(body);
             }
+            DuplicateAttributePolicy::KeepAll => attributes.push_decoded(attribute_name_index, attribute, body),
         }
+        context.diagnostics.push(
+            DiagnosticKind::DuplicateAttribute,
+            context.location.as_str(),
+            format!("duplicate attribute \"{}\"", attribute_name),
+        );
     }
 
-    (attributes, buffer)
-}
\ No newline at end of file
+    Ok(attributes)
+}
+
+/// Returns true if the attribute named `name` is decoded by this crate rather than kept raw
+/// or passed to a registered decoder.
+fn is_standard(name: &str) -> bool {
+    SPEC_ATTRIBUTE_ORDER.contains(&name) || JDK_ATTRIBUTES.contains(&name) || SCALA_ATTRIBUTES.contains(&name)
+}
+
+/// Decodes the body of the attribute named `name`, or passes it to the decoder registered
+/// for the name if the attribute is not standard, keeping it raw if there is none.
+fn decode_named_attribute<'a>(
+    name: &str,
+    body: &'a [u8],
+    constant_pool: &[ConstantPoolInfo],
+    context: &mut DecodeContext,
+) -> Result<AttributeInfo<'a>, DecodeError> {
+    let is_standard = is_standard(name);
+    let decoder = if is_standard { None } else { context.attribute_decoders.get(name).cloned() };
+    let decoded = match decoder {
+        _ if is_standard => decode_attribute(name, body, constant_pool, context),
+        Some(decoder) => match decoder(body, constant_pool) {
+            Ok(value) => Ok(AttributeInfo::User { name: name.to_string(), data: body, value }),
+            Err(message) => Err(DecodeError::UserAttribute { name: name.to_string(), message }),
+        },
+        None => Ok(AttributeInfo::Raw { name: name.to_string(), data: body }),
+    };
+    match decoded {
+        // A malformed body only affects its own attribute since its length is known,
+        // so lenient decoding keeps it raw and carries on.
+        Err(error) if context.mode == ValidationMode::Lenient && !matches!(error, DecodeError::AllocationLimitExceeded { .. }) => {
+            context.diagnostics.push(
+                DiagnosticKind::MalformedAttribute,
+                context.location.as_str(),
+                format!("attribute \"{}\" kept undecoded: {}", name, error),
+            );
+            if is_standard {
+                Ok(AttributeInfo::Unknown(body))
+            } else {
+                Ok(AttributeInfo::Raw { name: name.to_string(), data: body })
+            }
+        }
+        decoded => decoded,
+    }
+}
+
+/// Decodes the body of the attribute named `name`.
+///
+/// Attributes without a decoder are returned as [`AttributeInfo::Unknown`].
+fn decode_attribute<'a>(
+    name: &str,
+    body: &'a [u8],
+    constant_pool: &[ConstantPoolInfo],
+    context: &mut DecodeContext,
+) -> Result<AttributeInfo<'a>, DecodeError> {
+    let mut reader = ClassReader::new(body);
+    let attribute = match name {
+        "RuntimeVisibleAnnotations" => {
+            let annotations = decode_annotations(&mut reader, context, "RuntimeVisibleAnnotations")?;
+            AttributeInfo::RuntimeVisibleAnnotations(RuntimeVisibleAnnotationsAttribute {
+                num_annotations: annotations.len() as u16,
+                annotations,
+            })
+        }
+        "RuntimeInvisibleAnnotations" => {
+            let annotations = decode_annotations(&mut reader, context, "RuntimeInvisibleAnnotations")?;
+            AttributeInfo::RuntimeInvisibleAnnotations(RuntimeInvisibleAnnotationsAttribute {
+                num_annotations: annotations.len() as u16,
+                annotations,
+            })
+        }
+        "RuntimeVisibleParameterAnnotations" => {
+            let parameter_annotations = decode_parameter_annotations(&mut reader, context, "RuntimeVisibleParameterAnnotations")?;
+            AttributeInfo::RuntimeVisibleParameterAnnotations(RuntimeVisibleParameterAnnotationsAttribute {
+                num_parameters: parameter_annotations.len() as u8,
+                parameter_annotations,
+            })
+        }
+        "RuntimeInvisibleParameterAnnotations" => {
+            let parameter_annotations = decode_parameter_annotations(&mut reader, context, "RuntimeInvisibleParameterAnnotations")?;
+            AttributeInfo::RuntimeInvisibleParameterAnnotations(RuntimeInvisibleParameterAnnotationsAttribute {
+                num_parameters: parameter_annotations.len() as u8,
+                parameter_annotations,
+            })
+        }
+        "RuntimeVisibleTypeAnnotations" => {
+            let annotations = decode_type_annotations(&mut reader, context, "RuntimeVisibleTypeAnnotations")?;
+            AttributeInfo::RuntimeVisibleTypeAnnotations(RuntimeVisibleTypeAnnotationsAttribute {
+                num_annotations: annotations.len() as u16,
+                annotations,
+            })
+        }
+        "RuntimeInvisibleTypeAnnotations" => {
+            let annotations = decode_type_annotations(&mut reader, context, "RuntimeInvisibleTypeAnnotations")?;
+            AttributeInfo::RuntimeInvisibleTypeAnnotations(RuntimeInvisibleTypeAnnotationsAttribute {
+                num_annotations: annotations.len() as u16,
+                annotations,
+            })
+        }
+        "Code" => AttributeInfo::Code(decode_code(&mut reader, constant_pool, context)?),
+        "StackMapTable" => AttributeInfo::StackMapTable(decode_stack_map_table(&mut reader, context)?),
+        "ConstantValue" => AttributeInfo::ConstantValue(ConstantValueAttribute {
+            constant_value_index: reader.read_u16("ConstantValue")?,
+        }),
+        "Exceptions" => {
+            let exception_index_table = decode_indices(&mut reader, context, "Exceptions")?;
+            AttributeInfo::Exceptions(ExceptionsAttribute {
+                number_of_exceptions: exception_index_table.len() as u16,
+                exception_index_table,
+            })
+        }
+        "InnerClasses" => AttributeInfo::InnerClasses(decode_inner_classes(&mut reader, context)?),
+        "EnclosingMethod" => AttributeInfo::EnclosingMethod(EnclosingMethodAttribute {
+            class_index: reader.read_u16("EnclosingMethod")?,
+            method_index: reader.read_u16("EnclosingMethod")?,
+        }),
+        "Signature" => AttributeInfo::Signature(SignatureAttribute {
+            signature_index: reader.read_u16("Signature")?,
+        }),
+        "BootstrapMethods" => AttributeInfo::BootstrapMethods(decode_bootstrap_methods(&mut reader, context)?),
+        "SourceFile" => AttributeInfo::SourceFile(SourceFileAttribute {
+            sourcefile_index: reader.read_u16("SourceFile")?,
+        }),
+        "LineNumberTable" => AttributeInfo::LineNumberTable(decode_line_number_table(&mut reader, context)?),
+        "LocalVariableTable" => AttributeInfo::LocalVariableTable(decode_local_variable_table(&mut reader, context)?),
+        "LocalVariableTypeTable" => {
+            AttributeInfo::LocalVariableTypeTable(decode_local_variable_type_table(&mut reader, context)?)
+        }
+        "Synthetic" => AttributeInfo::Synthetic(SyntheticAttribute),
+        "Deprecated" => AttributeInfo::Deprecated(DeprecatedAttribute),
+        "Module" => AttributeInfo::Module(decode_module(&mut reader, context)?),
+        "ModulePackages" => {
+            let package_index = decode_indices(&mut reader, context, "ModulePackages")?;
+            AttributeInfo::ModulePackages(ModulePackagesAttribute {
+                package_count: package_index.len() as u16,
+                package_index,
+            })
+        }
+        "ModuleMainClass" => AttributeInfo::ModuleMainClass(ModuleMainClassAttribute {
+            main_class_index: reader.read_u16("ModuleMainClass")?,
+        }),
+        "NestHost" => AttributeInfo::NestHost(NestHostAttribute {
+            host_class_index: reader.read_u16("NestHost")?,
+        }),
+        "NestMembers" => {
+            let classes = decode_indices(&mut reader, context, "NestMembers")?;
+            AttributeInfo::NestMembers(NestMembersAttribute {
+                number_of_classes: classes.len() as u16,
+                classes,
+            })
+        }
+        // Record is only defined on ClassFile; one within a Code or Record attribute, e.g. on a
+        // record component, is kept raw rather than decoded recursively.
+        "Record" if context.attribute_depth == 0 => {
+            AttributeInfo::Record(decode_record(&mut reader, constant_pool, context)?)
+        }
+        "PermittedSubclasses" => {
+            let classes = decode_indices(&mut reader, context, "PermittedSubclasses")?;
+            AttributeInfo::PermittedSubclasses(PermittedSubclassesAttribute {
+                number_of_classes: classes.len() as u16,
+                classes,
+            })
+        }
+        "CharacterRangeTable" => {
+            AttributeInfo::CharacterRangeTable(decode_character_range_table(&mut reader, context)?)
+        }
+        "CompilationID" => AttributeInfo::CompilationID(CompilationIDAttribute {
+            compilation_id_index: reader.read_u16("CompilationID")?,
+        }),
+        "SourceID" => AttributeInfo::SourceID(SourceIDAttribute {
+            source_id_index: reader.read_u16("SourceID")?,
+        }),
+        "ScalaSig" => AttributeInfo::ScalaSig(ScalaSigAttribute {
+            pickle: reader.read_bytes(body.len(), "ScalaSig")?,
+        }),
+        "Scala" => AttributeInfo::Scala(ScalaAttribute),
+        _ => return Ok(AttributeInfo::Unknown(body)),
+    };
+
+    if !reader.remaining().is_empty() {
+        return Err(DecodeError::AttributeLengthMismatch {
+            name: name.to_string(),
+            length: body.len(),
+            consumed: body.len() - reader.remaining().len(),
+        });
+    }
+    Ok(attribute)
+}
+
+/// Decodes a table of u16 constant pool indices preceded by its u16 count.
+fn decode_indices(reader: &mut ClassReader, context: &mut DecodeContext, while_parsing: &'static str) -> Result<Vec<u16>, DecodeError> {
+    let count = reader.read_u16(while_parsing)? as usize;
+    reader.check_count(count, size_of::<u16>(), while_parsing)?;
+    context.budget.reserve::<u16>(count, while_parsing)?;
+
+    let mut indices = Vec::with_capacity(count);
+    for _ in 0..count {
+        indices.push(reader.read_u16(while_parsing)?);
+    }
+    Ok(indices)
+}
+
+/// Decodes the body of an InnerClasses attribute.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.6
+fn decode_inner_classes(reader: &mut ClassReader, context: &mut DecodeContext) -> Result<InnerClassesAttribute, DecodeError> {
+    let number_of_classes = reader.read_u16("InnerClasses")? as usize;
+    reader.check_count(number_of_classes, 4 * size_of::<u16>(), "InnerClasses")?;
+    context.budget.reserve::<InnerClassInfo>(number_of_classes, "InnerClasses")?;
+
+    let mut classes = Vec::with_capacity(number_of_classes);
+    for _ in 0..number_of_classes {
+        classes.push(InnerClassInfo {
+            inner_class_info_index: reader.read_u16("InnerClasses")?,
+            outer_class_info_index: reader.read_u16("InnerClasses")?,
+            inner_name_index: reader.read_u16("InnerClasses")?,
+            inner_class_access_flags: reader.read_u16("InnerClasses")?,
+        });
+    }
+    Ok(InnerClassesAttribute {
+        number_of_classes: classes.len() as u16,
+        classes,
+    })
+}
+
+/// Decodes the body of a BootstrapMethods attribute.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.23
+fn decode_bootstrap_methods(reader: &mut ClassReader, context: &mut DecodeContext) -> Result<BootstrapMethodsAttribute, DecodeError> {
+    let num_bootstrap_methods = reader.read_u16("BootstrapMethods")? as usize;
+    reader.check_count(num_bootstrap_methods, 2 * size_of::<u16>(), "BootstrapMethods")?;
+    context.budget.reserve::<BootstrapMethodEntry>(num_bootstrap_methods, "BootstrapMethods")?;
+
+    let mut bootstrap_methods = Vec::with_capacity(num_bootstrap_methods);
+    for _ in 0..num_bootstrap_methods {
+        let bootstrap_method_ref = reader.read_u16("bootstrap_methods")? as usize;
+        let bootstrap_arguments: Vec<usize> = decode_indices(reader, context, "bootstrap_arguments")?
+            .into_iter()
+            .map(usize::from)
+            .collect();
+        bootstrap_methods.push(BootstrapMethodEntry {
+            bootstrap_method_ref,
+            num_bootstrap_arguments: bootstrap_arguments.len(),
+            bootstrap_arguments,
+        });
+    }
+    Ok(BootstrapMethodsAttribute {
+        num_bootstrap_methods: bootstrap_methods.len() as u16,
+        bootstrap_methods,
+    })
+}
+
+/// Decodes the body of a CharacterRangeTable attribute, which javac writes with `-Xjcov` and
+/// the JVMS does not define.
+fn decode_character_range_table(
+    reader: &mut ClassReader,
+    context: &mut DecodeContext,
+) -> Result<CharacterRangeTableAttribute, DecodeError> {
+    let character_range_table_length = reader.read_u16("CharacterRangeTable")? as usize;
+    let entry_size = 3 * size_of::<u16>() + 2 * size_of::<u32>();
+    reader.check_count(character_range_table_length, entry_size, "CharacterRangeTable")?;
+    context.budget.reserve::<CharacterRangeTableEntry>(character_range_table_length, "CharacterRangeTable")?;
+
+    let mut character_range_table = Vec::with_capacity(character_range_table_length);
+    for _ in 0..character_range_table_length {
+        character_range_table.push(CharacterRangeTableEntry {
+            start_pc: reader.read_u16("CharacterRangeTable")?,
+            end_pc: reader.read_u16("CharacterRangeTable")?,
+            character_range_start: reader.read_u32("CharacterRangeTable")?,
+            character_range_end: reader.read_u32("CharacterRangeTable")?,
+            flags: reader.read_u16("CharacterRangeTable")?,
+        });
+    }
+    Ok(CharacterRangeTableAttribute {
+        character_range_table_length: character_range_table.len() as u16,
+        character_range_table,
+    })
+}
+
+/// Decodes the body of a LineNumberTable attribute.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.12
+fn decode_line_number_table(
+    reader: &mut ClassReader,
+    context: &mut DecodeContext,
+) -> Result<LineNumberTableAttribute, DecodeError> {
+    let line_number_table_length = reader.read_u16("LineNumberTable")? as usize;
+    reader.check_count(line_number_table_length, 2 * size_of::<u16>(), "LineNumberTable")?;
+    context.budget.reserve::<LineNumberTableEntry>(line_number_table_length, "LineNumberTable")?;
+
+    let mut line_number_table = Vec::with_capacity(line_number_table_length);
+    for _ in 0..line_number_table_length {
+        line_number_table.push(LineNumberTableEntry {
+            start_pc: reader.read_u16("LineNumberTable")?,
+            line_number: reader.read_u16("LineNumberTable")?,
+        });
+    }
+    Ok(LineNumberTableAttribute {
+        line_number_table_length: line_number_table.len() as u16,
+        line_number_table,
+    })
+}
+
+/// Decodes the body of a LocalVariableTable attribute.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.13
+fn decode_local_variable_table(
+    reader: &mut ClassReader,
+    context: &mut DecodeContext,
+) -> Result<LocalVariableTableAttribute, DecodeError> {
+    let local_variable_table_length = reader.read_u16("LocalVariableTable")? as usize;
+    reader.check_count(local_variable_table_length, 5 * size_of::<u16>(), "LocalVariableTable")?;
+    context.budget.reserve::<LocalVariableTableEntry>(local_variable_table_length, "LocalVariableTable")?;
+
+    let mut local_variable_table = Vec::with_capacity(local_variable_table_length);
+    for _ in 0..local_variable_table_length {
+        local_variable_table.push(LocalVariableTableEntry {
+            start_pc: reader.read_u16("LocalVariableTable")?,
+            length: reader.read_u16("LocalVariableTable")?,
+            name_index: reader.read_u16("LocalVariableTable")? as usize,
+            descriptor_index: reader.read_u16("LocalVariableTable")? as usize,
+            index: reader.read_u16("LocalVariableTable")? as usize,
+        });
+    }
+    Ok(LocalVariableTableAttribute {
+        local_variable_table_length: local_variable_table.len() as u16,
+        local_variable_table,
+    })
+}
+
+/// Decodes the body of a LocalVariableTypeTable attribute.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.14
+fn decode_local_variable_type_table(
+    reader: &mut ClassReader,
+    context: &mut DecodeContext,
+) -> Result<LocalVariableTypeTableAttribute, DecodeError> {
+    let local_variable_type_table_length = reader.read_u16("LocalVariableTypeTable")? as usize;
+    reader.check_count(local_variable_type_table_length, 5 * size_of::<u16>(), "LocalVariableTypeTable")?;
+    context.budget.reserve::<LocalVariableTypeTableEntry>(local_variable_type_table_length, "LocalVariableTypeTable")?;
+
+    let mut local_variable_type_table = Vec::with_capacity(local_variable_type_table_length);
+    for _ in 0..local_variable_type_table_length {
+        local_variable_type_table.push(LocalVariableTypeTableEntry {
+            start_pc: reader.read_u16("LocalVariableTypeTable")?,
+            length: reader.read_u16("LocalVariableTypeTable")?,
+            name_index: reader.read_u16("LocalVariableTypeTable")? as usize,
+            signature_index: reader.read_u16("LocalVariableTypeTable")? as usize,
+            index: reader.read_u16("LocalVariableTypeTable")? as usize,
+        });
+    }
+    Ok(LocalVariableTypeTableAttribute {
+        local_variable_type_table_length: local_variable_type_table.len() as u16,
+        local_variable_type_table,
+    })
+}
+
+/// Decodes the body of a Record attribute.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.30
+fn decode_record<'a>(
+    reader: &mut ClassReader<'a>,
+    constant_pool: &[ConstantPoolInfo],
+    context: &mut DecodeContext,
+) -> Result<RecordAttribute<'a>, DecodeError> {
+    let components_count = reader.read_u16("Record")? as usize;
+    // name_index, descriptor_index and attributes_count.
+    reader.check_count(components_count, 3 * size_of::<u16>(), "Record")?;
+    context.budget.reserve::<RecordComponentInfo>(components_count, "Record")?;
+
+    // Diagnostics of the component attributes are located at the component; the location of
+    // the class is restored even on error, as lenient decoding carries on with it.
+    let location = std::mem::take(&mut context.location);
+    context.attribute_depth += 1;
+    let mut components = Vec::with_capacity(components_count);
+    let result = (0..components_count).try_for_each(|i| {
+        let name_index = reader.read_u16("record_component_info")?;
+        let descriptor_index = reader.read_u16("record_component_info")?;
+        context.location = format!("record_components[{}]", i);
+        let attributes = decode_attributes(reader, constant_pool, context)?;
+        components.push(RecordComponentInfo { name_index, descriptor_index, attributes });
+        Ok(())
+    });
+    context.location = location;
+    context.attribute_depth -= 1;
+    result?;
+
+    Ok(RecordAttribute {
+        components_count: components_count as u16,
+        components,
+    })
+}
+
+pub(crate) fn decode_code<'a>(
+    reader: &mut ClassReader<'a>,
+    constant_pool: &[ConstantPoolInfo],
+    context: &mut DecodeContext,
+) -> Result<CodeAttribute<'a>, DecodeError> {
+    let max_stack = reader.read_u16("Code")?;
+    let max_locals = reader.read_u16("Code")?;
+    let code_length = reader.read_u32("Code")? as usize;
+    let code = reader.read_bytes(code_length, "Code")?;
+
+    let exception_table_length = reader.read_u16("exception_table")? as usize;
+    reader.check_count(exception_table_length, 4 * size_of::<u16>(), "exception_table")?;
+    context.budget.reserve::<ExceptionTableEntry>(exception_table_length, "exception_table")?;
+    let mut exception_table = Vec::with_capacity(exception_table_length);
+    for _ in 0..exception_table_length {
+        exception_table.push(ExceptionTableEntry {
+            start_pc: reader.read_u16("exception_table")?,
+            end_pc: reader.read_u16("exception_table")?,
+            handler_pc: reader.read_u16("exception_table")?,
+            catch_type: reader.read_u16("exception_table")?,
+        });
+    }
+
+    // Diagnostics of the nested attributes are located at the Code attribute of the owner.
+    let location = std::mem::take(&mut context.location);
+    context.location = format!("{}.Code", location);
+    context.attribute_depth += 1;
+    let attributes = decode_attributes(reader, constant_pool, context);
+    context.attribute_depth -= 1;
+    context.location = location;
+
+    Ok(CodeAttribute {
+        max_stack,
+        max_locals,
+        code_length,
+        code,
+        exception_table_length,
+        exception_table,
+        attributes: attributes?,
+    })
+}
+
+fn decode_stack_map_table(
+    reader: &mut ClassReader,
+    context: &mut DecodeContext,
+) -> Result<StackMapTableAttribute, DecodeError> {
+    let number_of_entries = reader.read_u16("StackMapTable")? as usize;
+    reader.check_count(number_of_entries, size_of::<u8>(), "StackMapTable")?;
+    context.budget.reserve::<StackMapFrame>(number_of_entries, "StackMapTable")?;
+    let mut entries = Vec::with_capacity(number_of_entries);
+    for _ in 0..number_of_entries {
+        let frame_type = reader.read_u8("stack_map_frame")?;
+        let frame = match frame_type {
+            0..=63 => StackMapFrame::SameFrame(SameFrame { frame_type }),
+            64..=127 => StackMapFrame::SameLocals1StackItemFrame(SameLocals1StackItemFrame {
+                frame_type,
+                stack: decode_verification_type_info(reader)?,
+            }),
+            247 => StackMapFrame::SameLocals1StackItemFrameExtended(SameLocals1StackItemFrameExtended {
+                frame_type,
+                offset_delta: reader.read_u16("stack_map_frame")?,
+                stack: decode_verification_type_info(reader)?,
+            }),
+            248..=250 => StackMapFrame::ChopFrame(ChopFrame {
+                frame_type,
+                offset_delta: reader.read_u16("stack_map_frame")?,
+            }),
+            251 => StackMapFrame::SameFrameExtended(SameFrameExtended {
+                frame_type,
+                offset_delta: reader.read_u16("stack_map_frame")?,
+            }),
+            252..=254 => {
+                let offset_delta = reader.read_u16("stack_map_frame")?;
+                let locals = decode_verification_types(reader, context, (frame_type - 251) as usize)?;
+                StackMapFrame::AppendFrame(AppendFrame { frame_type, offset_delta, locals })
+            }
+            255 => {
+                let offset_delta = reader.read_u16("stack_map_frame")?;
+                let number_of_locals = reader.read_u16("full_frame")?;
+                let locals = decode_verification_types(reader, context, number_of_locals as usize)?;
+                let number_of_stack_items = reader.read_u16("full_frame")?;
+                let stack = decode_verification_types(reader, context, number_of_stack_items as usize)?;
+                StackMapFrame::FullFrame(FullFrame {
+                    frame_type,
+                    offset_delta,
+                    number_of_locals,
+                    locals,
+                    number_of_stack_items,
+                    stack,
+                })
+            }
+            frame_type => return Err(DecodeError::UnknownFrameType { frame_type }),
+        };
+        entries.push(frame);
+    }
+
+    Ok(StackMapTableAttribute {
+        number_of_entries: number_of_entries as u16,
+        entries,
+    })
+}
+
+fn decode_verification_types(
+    reader: &mut ClassReader,
+    context: &mut DecodeContext,
+    count: usize,
+) -> Result<Vec<VerificationTypeInfo>, DecodeError> {
+    reader.check_count(count, size_of::<u8>(), "verification_type_info")?;
+    context.budget.reserve::<VerificationTypeInfo>(count, "verification_type_info")?;
+    (0..count).map(|_| decode_verification_type_info(reader)).collect()
+}
+
+fn decode_verification_type_info(reader: &mut ClassReader) -> Result<VerificationTypeInfo, DecodeError> {
+    Ok(match reader.read_u8("verification_type_info")? {
+        0 => VerificationTypeInfo::Top,
+        1 => VerificationTypeInfo::Integer,
+        2 => VerificationTypeInfo::Float,
+        3 => VerificationTypeInfo::Double,
+        4 => VerificationTypeInfo::Long,
+        5 => VerificationTypeInfo::Null,
+        6 => VerificationTypeInfo::UninitializedThis,
+        7 => VerificationTypeInfo::Object { cpool_index: reader.read_u16("verification_type_info")? },
+        8 => VerificationTypeInfo::Uninitialized { offset: reader.read_u16("verification_type_info")? },
+        tag => return Err(DecodeError::UnknownVerificationType { tag }),
+    })
+}
+
+/// Decodes the body of a Module attribute.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.25
+fn decode_module(reader: &mut ClassReader, context: &mut DecodeContext) -> Result<ModuleAttribute, DecodeError> {
+    let module_name_index = reader.read_u16("Module")?;
+    let module_flags = reader.read_u16("Module")?;
+    let module_version_index = reader.read_u16("Module")?;
+
+    let requires_count = reader.read_u16("requires")? as usize;
+    reader.check_count(requires_count, 3 * size_of::<u16>(), "requires")?;
+    context.budget.reserve::<ModuleRequiresEntry>(requires_count, "requires")?;
+    let mut requires = Vec::with_capacity(requires_count);
+    for _ in 0..requires_count {
+        requires.push(ModuleRequiresEntry {
+            requires_index: reader.read_u16("requires")?,
+            requires_flags: reader.read_u16("requires")?,
+            requires_version_index: reader.read_u16("requires")?,
+        });
+    }
+
+    let exports_count = reader.read_u16("exports")? as usize;
+    reader.check_count(exports_count, 3 * size_of::<u16>(), "exports")?;
+    context.budget.reserve::<ModuleExportsEntry>(exports_count, "exports")?;
+    let mut exports = Vec::with_capacity(exports_count);
+    for _ in 0..exports_count {
+        let exports_index = reader.read_u16("exports")?;
+        let exports_flags = reader.read_u16("exports")?;
+        let exports_to_index = decode_indices(reader, context, "exports_to_index")?;
+        exports.push(ModuleExportsEntry {
+            exports_index,
+            exports_flags,
+            exports_to_count: exports_to_index.len() as u16,
+            exports_to_index,
+        });
+    }
+
+    let opens_count = reader.read_u16("opens")? as usize;
+    reader.check_count(opens_count, 3 * size_of::<u16>(), "opens")?;
+    context.budget.reserve::<ModuleOpensEntry>(opens_count, "opens")?;
+    let mut opens = Vec::with_capacity(opens_count);
+    for _ in 0..opens_count {
+        let opens_index = reader.read_u16("opens")?;
+        let opens_flags = reader.read_u16("opens")?;
+        let opens_to_index = decode_indices(reader, context, "opens_to_index")?;
+        opens.push(ModuleOpensEntry {
+            opens_index,
+            opens_flags,
+            opens_to_count: opens_to_index.len() as u16,
+            opens_to_index,
+        });
+    }
+
+    let uses_index = decode_indices(reader, context, "uses_index")?;
+
+    let provides_count = reader.read_u16("provides")? as usize;
+    reader.check_count(provides_count, 2 * size_of::<u16>(), "provides")?;
+    context.budget.reserve::<ModuleProvidesEntry>(provides_count, "provides")?;
+    let mut provides = Vec::with_capacity(provides_count);
+    for _ in 0..provides_count {
+        let provides_index = reader.read_u16("provides")?;
+        let provides_with_index = decode_indices(reader, context, "provides_with_index")?;
+        provides.push(ModuleProvidesEntry {
+            provides_index,
+            provides_with_count: provides_with_index.len() as u16,
+            provides_with_index,
+        });
+    }
+
+    Ok(ModuleAttribute {
+        module_name_index,
+        module_flags,
+        module_version_index,
+        requires_count: requires.len() as u16,
+        requires,
+        exports_count: exports.len() as u16,
+        exports,
+        opens_count: opens.len() as u16,
+        opens,
+        uses_count: uses_index.len() as u16,
+        uses_index,
+        provides_count: provides.len() as u16,
+        provides,
+    })
+}
+
+/// Annotations and element values nested deeper than this are rejected, bounding the
+/// recursion of the decoder.
+const MAX_ANNOTATION_DEPTH: usize = 256;
+
+/// The smallest annotation: type_index and num_element_value_pairs.
+const MIN_ANNOTATION_SIZE: usize = 2 * size_of::<u16>();
+
+/// The smallest element_value: a tag and one u16.
+const MIN_ELEMENT_VALUE_SIZE: usize = size_of::<u8>() + size_of::<u16>();
+
+/// Decodes num_annotations and the annotations following it.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.16
+fn decode_annotations(
+    reader: &mut ClassReader,
+    context: &mut DecodeContext,
+    while_parsing: &'static str,
+) -> Result<Vec<AnnotationEntry>, DecodeError> {
+    let num_annotations = reader.read_u16(while_parsing)? as usize;
+    reader.check_count(num_annotations, MIN_ANNOTATION_SIZE, while_parsing)?;
+    context.budget.reserve::<AnnotationEntry>(num_annotations, while_parsing)?;
+
+    let mut annotations = Vec::with_capacity(num_annotations);
+    for _ in 0..num_annotations {
+        annotations.push(decode_annotation(reader, context, 0)?);
+    }
+    Ok(annotations)
+}
+
+/// Decodes num_parameters and the annotations of each parameter.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.18
+fn decode_parameter_annotations(
+    reader: &mut ClassReader,
+    context: &mut DecodeContext,
+    while_parsing: &'static str,
+) -> Result<Vec<Vec<AnnotationEntry>>, DecodeError> {
+    let num_parameters = reader.read_u8(while_parsing)? as usize;
+    reader.check_count(num_parameters, size_of::<u16>(), while_parsing)?;
+    context.budget.reserve::<Vec<AnnotationEntry>>(num_parameters, while_parsing)?;
+
+    let mut parameter_annotations = Vec::with_capacity(num_parameters);
+    for _ in 0..num_parameters {
+        parameter_annotations.push(decode_annotations(reader, context, while_parsing)?);
+    }
+    Ok(parameter_annotations)
+}
+
+/// Decodes num_annotations and the type annotations following it.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.20
+fn decode_type_annotations(
+    reader: &mut ClassReader,
+    context: &mut DecodeContext,
+    while_parsing: &'static str,
+) -> Result<Vec<TypeAnnotation>, DecodeError> {
+    let num_annotations = reader.read_u16(while_parsing)? as usize;
+    // target_type, an empty target_info, path_length and the annotation.
+    reader.check_count(num_annotations, 2 * size_of::<u8>() + MIN_ANNOTATION_SIZE, while_parsing)?;
+    context.budget.reserve::<TypeAnnotation>(num_annotations, while_parsing)?;
+
+    let mut annotations = Vec::with_capacity(num_annotations);
+    for _ in 0..num_annotations {
+        let target_type = reader.read_u8("type_annotation")?;
+        let target_info = decode_target_info(reader, context, target_type)?;
+
+        let path_length = reader.read_u8("type_path")?;
+        reader.check_count(path_length as usize, 2 * size_of::<u8>(), "type_path")?;
+        let mut path = Vec::with_capacity(path_length as usize);
+        for _ in 0..path_length {
+            path.push(TypePathEntry {
+                type_path_kind: reader.read_u8("type_path")?,
+                type_argument_index: reader.read_u8("type_path")?,
+            });
+        }
+
+        let (type_index, num_element_value_pairs, element_value_pairs) = decode_element_value_pairs(reader, context, 0)?;
+        annotations.push(TypeAnnotation {
+            target_type,
+            target_info,
+            target_path: TypePath { path_length, path },
+            type_index,
+            num_element_value_pairs,
+            element_value_pairs,
+        });
+    }
+    Ok(annotations)
+}
+
+/// Decodes the target_info selected by `target_type`.
+fn decode_target_info(reader: &mut ClassReader, context: &mut DecodeContext, target_type: u8) -> Result<TargetInfo, DecodeError> {
+    const WHILE_PARSING: &str = "target_info";
+    let target_info = match target_type {
+        0x00 | 0x01 => TargetInfo::TypeParameter { type_parameter_index: reader.read_u8(WHILE_PARSING)? },
+        0x10 => TargetInfo::Supertype { supertype_index: reader.read_u16(WHILE_PARSING)? },
+        0x11 | 0x12 => TargetInfo::TypeParameterBound {
+            type_parameter_index: reader.read_u8(WHILE_PARSING)?,
+            bound_index: reader.read_u8(WHILE_PARSING)?,
+        },
+        0x13..=0x15 => TargetInfo::Empty,
+        0x16 => TargetInfo::FormalParameter { formal_parameter_index: reader.read_u8(WHILE_PARSING)? },
+        0x17 => TargetInfo::Throws { throws_type_index: reader.read_u16(WHILE_PARSING)? },
+        0x40 | 0x41 => {
+            let table_length = reader.read_u16(WHILE_PARSING)?;
+            reader.check_count(table_length as usize, 3 * size_of::<u16>(), WHILE_PARSING)?;
+            context.budget.reserve::<LocalVarTargetEntry>(table_length as usize, WHILE_PARSING)?;
+            let mut table = Vec::with_capacity(table_length as usize);
+            for _ in 0..table_length {
+                table.push(LocalVarTargetEntry {
+                    start_pc: reader.read_u16(WHILE_PARSING)?,
+                    length: reader.read_u16(WHILE_PARSING)?,
+                    index: reader.read_u16(WHILE_PARSING)?,
+                });
+            }
+            TargetInfo::LocalVar { table_length, table }
+        }
+        0x42 => TargetInfo::Catch { exception_table_index: reader.read_u16(WHILE_PARSING)? },
+        0x43..=0x46 => TargetInfo::Offset { offset: reader.read_u16(WHILE_PARSING)? },
+        0x47..=0x4B => TargetInfo::TypeArgument {
+            offset: reader.read_u16(WHILE_PARSING)?,
+            type_argument_index: reader.read_u8(WHILE_PARSING)?,
+        },
+        target_type => return Err(DecodeError::UnknownTargetType { target_type }),
+    };
+    Ok(target_info)
+}
+
+/// Decodes the type_index and element value pairs shared by annotations and type
+/// annotations, nested `depth` levels deep.
+fn decode_element_value_pairs(
+    reader: &mut ClassReader,
+    context: &mut DecodeContext,
+    depth: usize,
+) -> Result<(u16, u16, Vec<AnnotationElementValuePair>), DecodeError> {
+    let type_index = reader.read_u16("annotation")?;
+    let num_element_value_pairs = reader.read_u16("annotation")?;
+    reader.check_count(num_element_value_pairs as usize, size_of::<u16>() + MIN_ELEMENT_VALUE_SIZE, "element_value_pairs")?;
+    context.budget.reserve::<AnnotationElementValuePair>(num_element_value_pairs as usize, "element_value_pairs")?;
+
+    let mut element_value_pairs = Vec::with_capacity(num_element_value_pairs as usize);
+    for _ in 0..num_element_value_pairs {
+        let element_name_index = reader.read_u16("element_value_pairs")?;
+        let value = decode_element_value(reader, context, depth + 1)?;
+        element_value_pairs.push(AnnotationElementValuePair { element_name_index, value });
+    }
+    Ok((type_index, num_element_value_pairs, element_value_pairs))
+}
+
+/// Decodes an annotation nested `depth` levels deep.
+fn decode_annotation(reader: &mut ClassReader, context: &mut DecodeContext, depth: usize) -> Result<AnnotationEntry, DecodeError> {
+    let (type_index, num_element_value_pairs, element_value_pairs) = decode_element_value_pairs(reader, context, depth)?;
+    Ok(AnnotationEntry { type_index, num_element_value_pairs, element_value_pairs })
+}
+
+/// Decodes an element_value nested `depth` levels deep.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.16.1
+fn decode_element_value(
+    reader: &mut ClassReader,
+    context: &mut DecodeContext,
+    depth: usize,
+) -> Result<AnnotationElementValue, DecodeError> {
+    if depth > MAX_ANNOTATION_DEPTH {
+        return Err(DecodeError::NestingTooDeep { while_parsing: "element_value", max: MAX_ANNOTATION_DEPTH });
+    }
+
+    let tag = reader.read_u8("element_value")?;
+    let value = match tag {
+        tag if CONST_VALUE_TAGS.contains(&tag) => AnnotationElementValueEntryValue::ConstValueIndex(reader.read_u16("element_value")?),
+        b'e' => AnnotationElementValueEntryValue::EnumConstValue {
+            type_name_index: reader.read_u16("element_value")?,
+            const_name_index: reader.read_u16("element_value")?,
+        },
+        b'c' => AnnotationElementValueEntryValue::ClassInfoIndex(reader.read_u16("element_value")?),
+        b'@' => AnnotationElementValueEntryValue::AnnotationValue(decode_annotation(reader, context, depth)?),
+        b'[' => {
+            let num_values = reader.read_u16("element_value")?;
+            reader.check_count(num_values as usize, MIN_ELEMENT_VALUE_SIZE, "element_value")?;
+            context.budget.reserve::<AnnotationElementValue>(num_values as usize, "element_value")?;
+            let mut values = Vec::with_capacity(num_values as usize);
+            for _ in 0..num_values {
+                values.push(decode_element_value(reader, context, depth + 1)?);
+            }
+            AnnotationElementValueEntryValue::ArrayValue { num_values, values }
+        }
+        tag => return Err(DecodeError::UnknownElementValueTag { tag }),
+    };
+    Ok(AnnotationElementValue { tag, value })
+}
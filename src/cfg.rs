@@ -0,0 +1,597 @@
+//! Control flow graphs of the code of methods, including the subroutines which class files
+//! before Java 6 and some obfuscators call with jsr, jsr_w and ret for `finally` blocks.
+//!
+//! ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.10.2.5
+
+use std::fmt;
+
+use crate::{
+    opcode::{instruction_length, LookupSwitch, Opcode, TableSwitch},
+    types::*,
+};
+
+/// Why an edge leaves a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    /// To the instruction following the block.
+    FallThrough,
+    /// From a goto, conditional branch or switch to one of its targets.
+    Branch,
+    /// From a jsr or jsr_w to the first instruction of its subroutine.
+    Call,
+    /// From a ret to the instruction following a jsr or jsr_w which calls its subroutine.
+    Return,
+    /// To a handler of an exception table entry covering the block.
+    Exception,
+}
+
+/// An edge to the block starting at `target`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Edge {
+    pub target: usize,
+    pub kind: EdgeKind,
+}
+
+/// A run of instructions entered only at its first one and left only after its last one,
+/// or by an exception.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    /// Offset of the first instruction.
+    pub start: usize,
+    /// Offset following the last instruction.
+    pub end: usize,
+    /// Offset of the last instruction.
+    pub last: usize,
+    /// The edges leaving the block, without duplicates, exception edges last.
+    pub successors: Vec<Edge>,
+}
+
+/// A subroutine called by jsr or jsr_w.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subroutine {
+    /// Offset of the first instruction, the target of the calls.
+    pub entry: usize,
+    /// Offsets of the jsr and jsr_w instructions calling the subroutine.
+    pub calls: Vec<usize>,
+    /// Starts of the blocks of the subroutine, in code order. The blocks of subroutines it
+    /// calls are not included.
+    pub blocks: Vec<usize>,
+    /// Offsets of the ret instructions returning from the subroutine.
+    pub rets: Vec<usize>,
+}
+
+/// Code whose control flow cannot be followed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CfgError {
+    /// Offset of the instruction or exception table entry target at fault.
+    pub pc: usize,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for CfgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "pc {}: {}", self.pc, self.reason)
+    }
+}
+
+impl std::error::Error for CfgError {}
+
+/// The basic blocks of some code and the edges between them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlFlowGraph {
+    /// In code order.
+    blocks: Vec<BasicBlock>,
+    /// In order of entry.
+    subroutines: Vec<Subroutine>,
+}
+
+impl ControlFlowGraph {
+    /// Builds the graph of `code` and its exception table, see also [`ControlFlowGraph::from_code`].
+    ///
+    /// A ret is assumed to return from the innermost subroutine it is reached from, as
+    /// compilers emit them, and so has an edge to the instruction following each call of
+    /// that subroutine. Unreachable blocks are kept, see [`ControlFlowGraph::reachable`].
+    pub fn new(code: &[u8], exception_table: &[ExceptionTableEntry]) -> Result<Self, CfgError> {
+        let mut instructions = Vec::new();
+        let mut pc = 0;
+        while pc < code.len() {
+            let length = instruction_length(code, pc).ok_or(CfgError { pc, reason: "cannot be decoded" })?;
+            instructions.push(pc);
+            pc += length;
+        }
+        let is_instruction = |pc: usize| instructions.binary_search(&pc).is_ok();
+        let check_target = |pc: usize, target: usize| match is_instruction(target) {
+            true => Ok(target),
+            false => Err(CfgError { pc, reason: "jumps to the middle of an instruction or out of the code" }),
+        };
+
+        let mut leaders = vec![false; code.len() + 1];
+        leaders[0] = true;
+        for &pc in &instructions {
+            let (targets, ends_block) = jump_targets(code, pc).ok_or(CfgError { pc, reason: "cannot be decoded" })?;
+            for target in targets {
+                leaders[check_target(pc, target)?] = true;
+            }
+            if ends_block {
+                leaders[pc + instruction_length(code, pc).unwrap_or_default()] = true;
+            }
+        }
+        for entry in exception_table {
+            let (start, end, handler) = (entry.start_pc as usize, entry.end_pc as usize, entry.handler_pc as usize);
+            let in_range = |pc: usize| pc == code.len() || is_instruction(pc);
+            if start >= end || !is_instruction(start) || !in_range(end) {
+                return Err(CfgError { pc: start, reason: "exception table entry covers no instructions" });
+            }
+            leaders[start] = true;
+            leaders[end] = true;
+            leaders[check_target(handler, handler)?] = true;
+        }
+
+        let mut blocks: Vec<BasicBlock> = Vec::new();
+        for (position, &pc) in instructions.iter().enumerate() {
+            if leaders[pc] {
+                blocks.push(BasicBlock { start: pc, end: pc, last: pc, successors: Vec::new() });
+            }
+            let block = blocks.last_mut().unwrap();
+            block.last = pc;
+            block.end = instructions.get(position + 1).copied().unwrap_or(code.len());
+        }
+        for block in &mut blocks {
+            let (targets, _) = jump_targets(code, block.last).unwrap_or_default();
+            let opcode = opcode_at(code, block.last);
+            let kind = match opcode {
+                Some(Opcode::Jsr | Opcode::JsrW) => EdgeKind::Call,
+                _ => EdgeKind::Branch,
+            };
+            let mut successors: Vec<Edge> = targets.into_iter().map(|target| Edge { target, kind }).collect();
+            if falls_through(opcode) && block.end < code.len() {
+                successors.insert(0, Edge { target: block.end, kind: EdgeKind::FallThrough });
+            }
+            for entry in exception_table {
+                if (entry.start_pc as usize) <= block.start && block.end <= entry.end_pc as usize {
+                    successors.push(Edge { target: entry.handler_pc as usize, kind: EdgeKind::Exception });
+                }
+            }
+            let mut unique = Vec::with_capacity(successors.len());
+            for edge in successors {
+                if !unique.contains(&edge) {
+                    unique.push(edge);
+                }
+            }
+            block.successors = unique;
+        }
+
+        let mut graph = Self { blocks, subroutines: Vec::new() };
+        graph.find_subroutines(code);
+        Ok(graph)
+    }
+
+    /// Finds the subroutines and adds the edges from their rets to the instructions
+    /// following their calls.
+    fn find_subroutines(&mut self, code: &[u8]) {
+        for block in &self.blocks {
+            let Some(Edge { target, .. }) = block.successors.iter().find(|edge| edge.kind == EdgeKind::Call) else {
+                continue;
+            };
+            match self.subroutines.iter_mut().find(|subroutine| subroutine.entry == *target) {
+                Some(subroutine) => subroutine.calls.push(block.last),
+                None => self.subroutines.push(Subroutine {
+                    entry: *target,
+                    calls: vec![block.last],
+                    blocks: Vec::new(),
+                    rets: Vec::new(),
+                }),
+            }
+        }
+
+        for subroutine in &mut self.subroutines {
+            let mut pending = vec![subroutine.entry];
+            while let Some(start) = pending.pop() {
+                if subroutine.blocks.contains(&start) {
+                    continue;
+                }
+                subroutine.blocks.push(start);
+                let block = &self.blocks[self.blocks.binary_search_by_key(&start, |block| block.start).unwrap()];
+                match opcode_at(code, block.last) {
+                    Some(Opcode::Ret) => subroutine.rets.push(block.last),
+                    // A nested call is assumed to return, to the instruction following it.
+                    Some(Opcode::Jsr | Opcode::JsrW) if block.end < code.len() => pending.push(block.end),
+                    _ => {}
+                }
+                let targets = block.successors.iter().filter(|edge| edge.kind != EdgeKind::Call);
+                pending.extend(targets.map(|edge| edge.target));
+            }
+            subroutine.blocks.sort_unstable();
+            subroutine.rets.sort_unstable();
+        }
+
+        for subroutine in &self.subroutines {
+            let return_sites = subroutine.calls.iter().filter_map(|&call| {
+                let block = self.blocks.iter().find(|block| block.last == call)?;
+                (block.end < code.len()).then_some(block.end)
+            });
+            let edges: Vec<Edge> = return_sites.map(|target| Edge { target, kind: EdgeKind::Return }).collect();
+            for &ret in &subroutine.rets {
+                let block = self.blocks.iter_mut().find(|block| block.last == ret).unwrap();
+                for &edge in &edges {
+                    if !block.successors.contains(&edge) {
+                        // Kept before the exception edges.
+                        let position = block.successors.iter().position(|edge| edge.kind == EdgeKind::Exception);
+                        block.successors.insert(position.unwrap_or(block.successors.len()), edge);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Builds the graph of the code of a method.
+    pub fn from_code(code: &CodeView) -> Result<Self, CfgError> {
+        Self::new(code.code, &code.exception_table)
+    }
+
+    /// Returns the blocks, in code order.
+    pub fn blocks(&self) -> &[BasicBlock] {
+        &self.blocks
+    }
+
+    /// Iterates over the edges with the start of the block they leave, in code order of
+    /// those blocks.
+    pub fn edges(&self) -> impl Iterator<Item = (usize, Edge)> + '_ {
+        self.blocks.iter().flat_map(|block| block.successors.iter().map(move |edge| (block.start, *edge)))
+    }
+
+    /// Returns the block holding the instruction at `pc`.
+    pub fn block_at(&self, pc: usize) -> Option<&BasicBlock> {
+        let position = self.blocks.partition_point(|block| block.start <= pc).checked_sub(1)?;
+        Some(&self.blocks[position]).filter(|block| pc < block.end)
+    }
+
+    /// Returns the blocks with an edge to the block starting at `start`, in code order.
+    pub fn predecessors(&self, start: usize) -> impl Iterator<Item = &BasicBlock> {
+        self.blocks
+            .iter()
+            .filter(move |block| block.successors.iter().any(|edge| edge.target == start))
+    }
+
+    /// Returns the subroutines, in order of their first call.
+    pub fn subroutines(&self) -> &[Subroutine] {
+        &self.subroutines
+    }
+
+    /// Returns the starts of the blocks reachable from the first instruction, in code order.
+    pub fn reachable(&self) -> Vec<usize> {
+        let mut reached = vec![false; self.blocks.len()];
+        let mut pending = if self.blocks.is_empty() { Vec::new() } else { vec![0] };
+        while let Some(position) = pending.pop() {
+            if std::mem::replace(&mut reached[position], true) {
+                continue;
+            }
+            for edge in &self.blocks[position].successors {
+                if let Ok(target) = self.blocks.binary_search_by_key(&edge.target, |block| block.start) {
+                    pending.push(target);
+                }
+            }
+        }
+        self.blocks.iter().zip(reached).filter(|(_, reached)| *reached).map(|(block, _)| block.start).collect()
+    }
+}
+
+/// The max_stack and max_locals of the Code attribute of a method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Maxs {
+    pub max_stack: u16,
+    pub max_locals: u16,
+}
+
+/// Computes the max_stack and max_locals of the code of a method with `descriptor`, e.g. for
+/// code which was generated or rewritten, so that they need not be maintained by hand.
+///
+/// The operand stack depth is followed along the edges of the [`ControlFlowGraph`] of the
+/// code, from a depth of 0 at the first instruction and 1 at exception handlers. Unreachable
+/// blocks do not count towards max_stack. max_locals covers the parameters, `this` unless
+/// `is_static`, and every local variable the code loads, stores or increments, reachable or
+/// not. Long and double take two slots of both.
+///
+/// Fails if the code has no graph, pops more than its stack holds, reaches an instruction with
+/// different depths, or refers to a member or constant which cannot be resolved.
+pub fn compute_maxs(
+    code: &[u8],
+    exception_table: &[ExceptionTableEntry],
+    constant_pool: &[ConstantPoolInfo],
+    descriptor: &str,
+    is_static: bool,
+) -> Result<Maxs, CfgError> {
+    let graph = ControlFlowGraph::new(code, exception_table)?;
+    let error = |pc: usize, reason: &'static str| CfgError { pc, reason };
+
+    let parameters = parameter_slots(descriptor).ok_or(error(0, "has a malformed method descriptor"))?;
+    let mut max_locals = parameters + usize::from(!is_static);
+    let mut pc = 0;
+    while let Some(length) = instruction_length(code, pc) {
+        max_locals = max_locals.max(local_slots(code, pc).map_or(0, |(index, size)| index + size));
+        pc += length;
+    }
+
+    let blocks = graph.blocks();
+    let mut depths: Vec<Option<usize>> = vec![None; blocks.len()];
+    let mut pending = Vec::new();
+    if !blocks.is_empty() {
+        depths[0] = Some(0);
+        pending.push(0);
+    }
+    let mut max_stack = 0;
+    while let Some(position) = pending.pop() {
+        let block = &blocks[position];
+        let mut depth = depths[position].unwrap_or_default();
+        max_stack = max_stack.max(depth);
+        let mut pc = block.start;
+        while pc < block.end {
+            let (pops, pushes) =
+                stack_effect(code, pc, constant_pool).ok_or(error(pc, "has an operand which cannot be resolved"))?;
+            depth = depth.checked_sub(pops).ok_or(error(pc, "pops more values than the stack holds"))? + pushes;
+            max_stack = max_stack.max(depth);
+            pc += instruction_length(code, pc).unwrap_or(block.end - pc);
+        }
+        for edge in &block.successors {
+            let target_depth = if edge.kind == EdgeKind::Exception { 1 } else { depth };
+            let Ok(target) = blocks.binary_search_by_key(&edge.target, |block| block.start) else {
+                continue;
+            };
+            match depths[target] {
+                Some(existing) if existing != target_depth => {
+                    return Err(error(edge.target, "is reached with different stack depths"));
+                }
+                Some(_) => {}
+                None => {
+                    depths[target] = Some(target_depth);
+                    pending.push(target);
+                }
+            }
+        }
+    }
+
+    let max_stack = u16::try_from(max_stack).map_err(|_| error(0, "needs a stack deeper than 65535"))?;
+    let max_locals = u16::try_from(max_locals).map_err(|_| error(0, "needs more than 65535 local variables"))?;
+    Ok(Maxs { max_stack, max_locals })
+}
+
+/// Returns the slots the instruction at `pc` pops from and pushes onto the operand stack, or
+/// `None` if it cannot be decoded or a member it refers to cannot be resolved.
+fn stack_effect(code: &[u8], pc: usize, constant_pool: &[ConstantPoolInfo]) -> Option<(usize, usize)> {
+    let u16_at = |at: usize| Some(u16::from_be_bytes([*code.get(at)?, *code.get(at + 1)?]) as usize);
+    let slots = |descriptor: &str| match descriptor {
+        "V" => 0,
+        "J" | "D" => 2,
+        _ => 1,
+    };
+    let opcode = Opcode::from_u8(*code.get(pc)?)?;
+    Some(match opcode {
+        Opcode::Nop | Opcode::Iinc | Opcode::Goto | Opcode::GotoW | Opcode::Ret | Opcode::Return => (0, 0),
+        Opcode::Wide => match opcode_at(code, pc)? {
+            Opcode::Iinc | Opcode::Ret => (0, 0),
+            Opcode::Iload | Opcode::Fload | Opcode::Aload => (0, 1),
+            Opcode::Lload | Opcode::Dload => (0, 2),
+            Opcode::Istore | Opcode::Fstore | Opcode::Astore => (1, 0),
+            Opcode::Lstore | Opcode::Dstore => (2, 0),
+            _ => return None,
+        },
+        Opcode::AconstNull | Opcode::IconstM1 | Opcode::Iconst0 | Opcode::Iconst1 | Opcode::Iconst2
+        | Opcode::Iconst3 | Opcode::Iconst4 | Opcode::Iconst5 | Opcode::Fconst0 | Opcode::Fconst1
+        | Opcode::Fconst2 | Opcode::Bipush | Opcode::Sipush | Opcode::Ldc | Opcode::LdcW | Opcode::Iload
+        | Opcode::Fload | Opcode::Aload | Opcode::Iload0 | Opcode::Iload1 | Opcode::Iload2 | Opcode::Iload3
+        | Opcode::Fload0 | Opcode::Fload1 | Opcode::Fload2 | Opcode::Fload3 | Opcode::Aload0 | Opcode::Aload1
+        | Opcode::Aload2 | Opcode::Aload3 | Opcode::Jsr | Opcode::JsrW | Opcode::New => (0, 1),
+        Opcode::Lconst0 | Opcode::Lconst1 | Opcode::Dconst0 | Opcode::Dconst1 | Opcode::Ldc2W | Opcode::Lload
+        | Opcode::Dload | Opcode::Lload0 | Opcode::Lload1 | Opcode::Lload2 | Opcode::Lload3 | Opcode::Dload0
+        | Opcode::Dload1 | Opcode::Dload2 | Opcode::Dload3 => (0, 2),
+        Opcode::Iaload | Opcode::Faload | Opcode::Aaload | Opcode::Baload | Opcode::Caload | Opcode::Saload => (2, 1),
+        Opcode::Laload | Opcode::Daload => (2, 2),
+        Opcode::Istore | Opcode::Fstore | Opcode::Astore | Opcode::Istore0 | Opcode::Istore1 | Opcode::Istore2
+        | Opcode::Istore3 | Opcode::Fstore0 | Opcode::Fstore1 | Opcode::Fstore2 | Opcode::Fstore3
+        | Opcode::Astore0 | Opcode::Astore1 | Opcode::Astore2 | Opcode::Astore3 | Opcode::Pop | Opcode::Ifeq
+        | Opcode::Ifne | Opcode::Iflt | Opcode::Ifge | Opcode::Ifgt | Opcode::Ifle | Opcode::Ifnull
+        | Opcode::Ifnonnull | Opcode::Tableswitch | Opcode::Lookupswitch | Opcode::Ireturn | Opcode::Freturn
+        | Opcode::Areturn | Opcode::Athrow | Opcode::Monitorenter | Opcode::Monitorexit => (1, 0),
+        Opcode::Lstore | Opcode::Dstore | Opcode::Lstore0 | Opcode::Lstore1 | Opcode::Lstore2 | Opcode::Lstore3
+        | Opcode::Dstore0 | Opcode::Dstore1 | Opcode::Dstore2 | Opcode::Dstore3 | Opcode::Pop2
+        | Opcode::IfIcmpeq | Opcode::IfIcmpne | Opcode::IfIcmplt | Opcode::IfIcmpge | Opcode::IfIcmpgt
+        | Opcode::IfIcmple | Opcode::IfAcmpeq | Opcode::IfAcmpne | Opcode::Lreturn | Opcode::Dreturn => (2, 0),
+        Opcode::Iastore | Opcode::Fastore | Opcode::Aastore | Opcode::Bastore | Opcode::Castore
+        | Opcode::Sastore => (3, 0),
+        Opcode::Lastore | Opcode::Dastore => (4, 0),
+        Opcode::Dup => (1, 2),
+        Opcode::DupX1 => (2, 3),
+        Opcode::DupX2 => (3, 4),
+        Opcode::Dup2 => (2, 4),
+        Opcode::Dup2X1 => (3, 5),
+        Opcode::Dup2X2 => (4, 6),
+        Opcode::Swap => (2, 2),
+        Opcode::Iadd | Opcode::Fadd | Opcode::Isub | Opcode::Fsub | Opcode::Imul | Opcode::Fmul | Opcode::Idiv
+        | Opcode::Fdiv | Opcode::Irem | Opcode::Frem | Opcode::Ishl | Opcode::Ishr | Opcode::Iushr
+        | Opcode::Iand | Opcode::Ior | Opcode::Ixor | Opcode::Fcmpl | Opcode::Fcmpg => (2, 1),
+        Opcode::Ladd | Opcode::Dadd | Opcode::Lsub | Opcode::Dsub | Opcode::Lmul | Opcode::Dmul | Opcode::Ldiv
+        | Opcode::Ddiv | Opcode::Lrem | Opcode::Drem | Opcode::Land | Opcode::Lor | Opcode::Lxor => (4, 2),
+        Opcode::Lshl | Opcode::Lshr | Opcode::Lushr => (3, 2),
+        Opcode::Ineg | Opcode::Fneg | Opcode::I2f | Opcode::F2i | Opcode::I2b | Opcode::I2c | Opcode::I2s
+        | Opcode::Newarray | Opcode::Anewarray | Opcode::Arraylength | Opcode::Checkcast | Opcode::Instanceof => {
+            (1, 1)
+        }
+        Opcode::Lneg | Opcode::Dneg | Opcode::L2d | Opcode::D2l => (2, 2),
+        Opcode::I2l | Opcode::I2d | Opcode::F2l | Opcode::F2d => (1, 2),
+        Opcode::L2i | Opcode::L2f | Opcode::D2i | Opcode::D2f => (2, 1),
+        Opcode::Lcmp | Opcode::Dcmpl | Opcode::Dcmpg => (4, 1),
+        Opcode::Getstatic | Opcode::Putstatic | Opcode::Getfield | Opcode::Putfield => {
+            let (_, _, descriptor) = get_member_ref(constant_pool, u16_at(pc + 1)?)?;
+            match opcode {
+                Opcode::Getstatic => (0, slots(descriptor)),
+                Opcode::Putstatic => (slots(descriptor), 0),
+                Opcode::Getfield => (1, slots(descriptor)),
+                _ => (1 + slots(descriptor), 0),
+            }
+        }
+        Opcode::Invokevirtual | Opcode::Invokespecial | Opcode::Invokestatic | Opcode::Invokeinterface => {
+            let (_, _, descriptor) = get_member_ref(constant_pool, u16_at(pc + 1)?)?;
+            let (_, return_descriptor) = split_method_descriptor(descriptor)?;
+            let receiver = usize::from(opcode != Opcode::Invokestatic);
+            (receiver + parameter_slots(descriptor)?, slots(return_descriptor))
+        }
+        Opcode::Invokedynamic => {
+            let ConstantPoolInfo::InvokeDynamic(info) = constant_pool.get(u16_at(pc + 1)?)? else {
+                return None;
+            };
+            let ConstantPoolInfo::NameAndType(name_and_type) = constant_pool.get(info.name_and_type_index)? else {
+                return None;
+            };
+            let descriptor = get_utf8(constant_pool, name_and_type.descriptor_index)?;
+            let (_, return_descriptor) = split_method_descriptor(descriptor)?;
+            (parameter_slots(descriptor)?, slots(return_descriptor))
+        }
+        Opcode::Multianewarray => (*code.get(pc + 3)? as usize, 1),
+    })
+}
+
+/// Returns the first local variable slot the instruction at `pc` loads, stores or increments
+/// and the number of slots it takes, or `None` if it uses no local variable.
+pub(crate) fn local_slots(code: &[u8], pc: usize) -> Option<(usize, usize)> {
+    let wide = code.get(pc) == Some(&(Opcode::Wide as u8));
+    let index = || match wide {
+        true => Some(u16::from_be_bytes([*code.get(pc + 2)?, *code.get(pc + 3)?]) as usize),
+        false => code.get(pc + 1).map(|&index| index as usize),
+    };
+    // The forms with an implicit index come in runs of 4 for int, long, float, double and reference.
+    let implicit = |first: Opcode, opcode: Opcode| {
+        let offset = (opcode as u8 - first as u8) as usize;
+        (offset % 4, if matches!(offset / 4, 1 | 3) { 2 } else { 1 })
+    };
+    let opcode = opcode_at(code, pc)?;
+    Some(match opcode {
+        Opcode::Iload | Opcode::Fload | Opcode::Aload | Opcode::Istore | Opcode::Fstore | Opcode::Astore
+        | Opcode::Iinc | Opcode::Ret => (index()?, 1),
+        Opcode::Lload | Opcode::Dload | Opcode::Lstore | Opcode::Dstore => (index()?, 2),
+        _ if (Opcode::Iload0 as u8..=Opcode::Aload3 as u8).contains(&(opcode as u8)) => {
+            implicit(Opcode::Iload0, opcode)
+        }
+        _ if (Opcode::Istore0 as u8..=Opcode::Astore3 as u8).contains(&(opcode as u8)) => {
+            implicit(Opcode::Istore0, opcode)
+        }
+        _ => return None,
+    })
+}
+
+/// Returns the targets of the jumps of the instruction at `pc`, without duplicates, and
+/// whether it ends a block. Returns `None` if a target is negative.
+pub(crate) fn jump_targets(code: &[u8], pc: usize) -> Option<(Vec<usize>, bool)> {
+    let target = |offset: i64| usize::try_from(pc as i64 + offset).ok();
+    let i16_at = |at: usize| i16::from_be_bytes([code[at], code[at + 1]]) as i64;
+    let i32_at = |at: usize| i32::from_be_bytes([code[at], code[at + 1], code[at + 2], code[at + 3]]) as i64;
+    let opcode = opcode_at(code, pc)?;
+    let targets = match opcode {
+        Opcode::Ifeq | Opcode::Ifne | Opcode::Iflt | Opcode::Ifge | Opcode::Ifgt | Opcode::Ifle
+        | Opcode::IfIcmpeq | Opcode::IfIcmpne | Opcode::IfIcmplt | Opcode::IfIcmpge | Opcode::IfIcmpgt
+        | Opcode::IfIcmple | Opcode::IfAcmpeq | Opcode::IfAcmpne | Opcode::Ifnull | Opcode::Ifnonnull
+        | Opcode::Goto | Opcode::Jsr => vec![target(i16_at(pc + 1))?],
+        Opcode::GotoW | Opcode::JsrW => vec![target(i32_at(pc + 1))?],
+        Opcode::Tableswitch => {
+            let switch = TableSwitch::decode(code, pc)?;
+            let offsets = std::iter::once(switch.default).chain(switch.offsets);
+            offsets.map(|offset| target(offset as i64)).collect::<Option<_>>()?
+        }
+        Opcode::Lookupswitch => {
+            let switch = LookupSwitch::decode(code, pc)?;
+            let offsets = std::iter::once(switch.default).chain(switch.pairs.into_iter().map(|(_, offset)| offset));
+            offsets.map(|offset| target(offset as i64)).collect::<Option<_>>()?
+        }
+        _ => Vec::new(),
+    };
+    let mut unique = Vec::with_capacity(targets.len());
+    for target in targets {
+        if !unique.contains(&target) {
+            unique.push(target);
+        }
+    }
+    let ends_block = !unique.is_empty() || !falls_through(Some(opcode));
+    Some((unique, ends_block))
+}
+
+/// Returns the opcode of the instruction at `pc`, or the one it modifies for wide, e.g. ret
+/// for `wide ret`.
+pub(crate) fn opcode_at(code: &[u8], pc: usize) -> Option<Opcode> {
+    match Opcode::from_u8(*code.get(pc)?)? {
+        Opcode::Wide => Opcode::from_u8(*code.get(pc + 1)?),
+        opcode => Some(opcode),
+    }
+}
+
+/// Returns true if execution may continue with the next instruction after `opcode`.
+pub(crate) fn falls_through(opcode: Option<Opcode>) -> bool {
+    !matches!(
+        opcode,
+        Some(
+            Opcode::Goto
+                | Opcode::GotoW
+                | Opcode::Jsr
+                | Opcode::JsrW
+                | Opcode::Ret
+                | Opcode::Tableswitch
+                | Opcode::Lookupswitch
+                | Opcode::Ireturn
+                | Opcode::Lreturn
+                | Opcode::Freturn
+                | Opcode::Dreturn
+                | Opcode::Areturn
+                | Opcode::Return
+                | Opcode::Athrow
+        )
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `try { i = 1; } finally { i++; }` as javac compiled it before Java 6.
+    const TRY_FINALLY: [u8; 18] = [
+        0x04, // 0: iconst_1
+        0x3C, // 1: istore_1
+        0xA8, 0x00, 0x0A, // 2: jsr 12
+        0xB1, // 5: return
+        0x4D, // 6: astore_2
+        0xA8, 0x00, 0x05, // 7: jsr 12
+        0x2C, // 10: aload_2
+        0xBF, // 11: athrow
+        0x4E, // 12: astore_3
+        0x84, 0x01, 0x01, // 13: iinc 1, 1
+        0xA9, 0x03, // 16: ret 3
+    ];
+
+    #[test]
+    fn subroutines() {
+        let exception_table = [ExceptionTableEntry { start_pc: 0, end_pc: 5, handler_pc: 6, catch_type: 0 }];
+        let graph = ControlFlowGraph::new(&TRY_FINALLY, &exception_table).unwrap();
+
+        let starts: Vec<_> = graph.blocks().iter().map(|block| block.start).collect();
+        assert_eq!(starts, [0, 5, 6, 10, 12]);
+        let edge = |target, kind| Edge { target, kind };
+        assert_eq!(graph.blocks()[0].successors, [edge(12, EdgeKind::Call), edge(6, EdgeKind::Exception)]);
+        assert_eq!(graph.blocks()[4].successors, [edge(5, EdgeKind::Return), edge(10, EdgeKind::Return)]);
+        assert_eq!(
+            graph.subroutines(),
+            [Subroutine { entry: 12, calls: vec![2, 7], blocks: vec![12], rets: vec![16] }]
+        );
+        assert_eq!(graph.reachable(), starts);
+        assert_eq!(graph.block_at(14).map(|block| block.start), Some(12));
+        let predecessors: Vec<_> = graph.predecessors(12).map(|block| block.start).collect();
+        assert_eq!(predecessors, [0, 6]);
+        let calls = graph.edges().filter(|(_, edge)| edge.kind == EdgeKind::Call);
+        assert_eq!(calls.map(|(from, _)| from).collect::<Vec<_>>(), [0, 6]);
+
+        let maxs = compute_maxs(&TRY_FINALLY, &exception_table, &[], "()V", false);
+        assert_eq!(maxs, Ok(Maxs { max_stack: 1, max_locals: 4 }));
+    }
+
+    #[test]
+    fn rejects_jumps_into_instructions() {
+        let code = [0xA7, 0x00, 0x02, 0xB1]; // goto 2
+        assert_eq!(ControlFlowGraph::new(&code, &[]).unwrap_err().pc, 0);
+    }
+}
@@ -0,0 +1,367 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    ops::Range,
+};
+
+use crate::{
+    dedup::{remap_constant, rewrite_attribute_references},
+    encode::{encode, encode_constant, encode_constant_pool},
+    error::EncodeError,
+    reader::ClassReader,
+    types::*,
+    utils::*,
+};
+
+/// A method detached from its class so that it can be edited on its own, e.g. on another
+/// thread, and merged back with [`check_in`].
+///
+/// The checkout owns a copy of the constant pool of the class. Constants added through
+/// [`MethodCheckout::constants_mut`] are appended after the entries of the class and form
+/// the pool delta of the checkout, which is renumbered when it is merged.
+#[derive(Debug, Clone)]
+pub struct MethodCheckout<'a> {
+    method_index: usize,
+    /// Number of constant pool entries of the class at checkout.
+    base_len: usize,
+    /// FNV-1a hash of those entries.
+    base_checksum: u64,
+    /// FNV-1a hash of the method_info at checkout.
+    method_checksum: u64,
+    constants: ConstantPoolBuilder<'a>,
+    pub access_flags: u16,
+    pub name_index: usize,
+    pub descriptor_index: usize,
+    /// Name indices and bodies of the attributes of the method, in class file order.
+    pub attributes: Vec<(u16, Vec<u8>)>,
+}
+
+impl<'a> MethodCheckout<'a> {
+    /// Returns the index of the method in the `methods` of the class.
+    pub fn method_index(&self) -> usize {
+        self.method_index
+    }
+
+    /// Returns the constant pool of the checkout: the entries of the class followed by the
+    /// pool delta.
+    pub fn constant_pool(&self) -> &[ConstantPoolInfo<'a>] {
+        self.constants.constants()
+    }
+
+    /// Returns the builder to add constants with, reusing the identical entries of the class.
+    pub fn constants_mut(&mut self) -> &mut ConstantPoolBuilder<'a> {
+        &mut self.constants
+    }
+
+    /// Returns the entries added since checkout.
+    pub fn delta(&self) -> &[ConstantPoolInfo<'a>] {
+        &self.constants.constants()[self.base_len..]
+    }
+
+    /// Returns the body of the first attribute named `name`.
+    pub fn attribute(&self, name: &str) -> Option<&[u8]> {
+        let position = self.attribute_position(name)?;
+        Some(&self.attributes[position].1)
+    }
+
+    /// Returns the body of the first attribute named `name` for editing.
+    pub fn attribute_mut(&mut self, name: &str) -> Option<&mut Vec<u8>> {
+        let position = self.attribute_position(name)?;
+        Some(&mut self.attributes[position].1)
+    }
+
+    /// Replaces the body of the first attribute named `name`, or appends the attribute if
+    /// the method has none.
+    pub fn set_attribute(&mut self, name: &str, body: Vec<u8>) {
+        match self.attribute_position(name) {
+            Some(position) => self.attributes[position].1 = body,
+            None => {
+                let name_index = self.constants.utf8(name.to_string()) as u16;
+                self.attributes.push((name_index, body));
+            }
+        }
+    }
+
+    fn attribute_position(&self, name: &str) -> Option<usize> {
+        let constant_pool = self.constants.constants();
+        self.attributes
+            .iter()
+            .position(|(name_index, _)| get_utf8(constant_pool, *name_index as usize) == Some(name))
+    }
+}
+
+/// Why [`check_in`] could not merge a set of checkouts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeConflict {
+    /// Two checkouts are of the same method.
+    CheckedOutTwice { method_index: usize },
+    /// The method is missing or differs from the one checked out.
+    MethodChanged { method_index: usize },
+    /// The constant pool entries present at checkout were changed.
+    ConstantPoolChanged { method_index: usize },
+    /// Two methods would have the same name and descriptor.
+    DuplicateMethod { name: String, descriptor: String },
+    /// The references of an attribute of the method cannot be located to renumber them,
+    /// e.g. in a non-standard attribute, or an ldc instruction refers to a constant merged
+    /// beyond index 255.
+    UnrelocatableAttribute { method_index: usize },
+    /// The merged constant pool would exceed 65535 entries.
+    TooManyConstants,
+    /// The class file or the merged constant pool cannot be encoded.
+    Encode(EncodeError),
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CheckedOutTwice { method_index } => write!(f, "methods[{}] is checked out twice", method_index),
+            Self::MethodChanged { method_index } => write!(f, "methods[{}] changed since checkout", method_index),
+            Self::ConstantPoolChanged { method_index } => {
+                write!(f, "constant pool changed since methods[{}] was checked out", method_index)
+            }
+            Self::DuplicateMethod { name, descriptor } => write!(f, "duplicate method {}{}", name, descriptor),
+            Self::UnrelocatableAttribute { method_index } => {
+                write!(f, "an attribute of methods[{}] cannot be renumbered", method_index)
+            }
+            Self::TooManyConstants => write!(f, "the merged constant pool exceeds 65535 entries"),
+            Self::Encode(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for MergeConflict {}
+
+impl From<EncodeError> for MergeConflict {
+    fn from(error: EncodeError) -> Self {
+        MergeConflict::Encode(error)
+    }
+}
+
+/// Checks out the methods at `method_indices` for editing.
+///
+/// Returns `None` if an index is out of range or the class file cannot be encoded.
+pub fn checkout<'a>(class_file: &JavaClassFile<'a>, method_indices: &[usize]) -> Option<Vec<MethodCheckout<'a>>> {
+    let bytes = encode(class_file).ok()?;
+    let layout = Layout::new(&bytes);
+    let constant_pool = &class_file.constant_pool;
+    let base_checksum = constants_checksum(constant_pool).ok()?;
+
+    method_indices
+        .iter()
+        .map(|&method_index| {
+            let method = class_file.methods.get(method_index)?;
+            let range = layout.methods[method_index].clone();
+            Some(MethodCheckout {
+                method_index,
+                base_len: constant_pool.len(),
+                base_checksum,
+                method_checksum: fnv1a(&bytes[range.clone()]),
+                constants: ConstantPoolBuilder::extending(constant_pool),
+                access_flags: method.access_flags,
+                name_index: method.name_index,
+                descriptor_index: method.descriptor_index,
+                attributes: read_attributes(&bytes[range.start + 3 * size_of::<u16>()..range.end]),
+            })
+        })
+        .collect()
+}
+
+/// Merges edited checkouts back into `class_file` and encodes the result.
+///
+/// The pool delta of each checkout is appended to the constant pool in turn, reusing
+/// identical entries, and the references of its method are renumbered accordingly. The
+/// checkouts must not overlap, and their methods and the constant pool entries present at
+/// checkout must be unchanged in `class_file`.
+pub fn check_in(class_file: &JavaClassFile, checkouts: &[MethodCheckout]) -> Result<Vec<u8>, MergeConflict> {
+    let bytes = encode(class_file)?;
+    let layout = Layout::new(&bytes);
+
+    let mut checked_out = vec![false; class_file.methods.len()];
+    for checkout in checkouts {
+        let method_index = checkout.method_index;
+        let Some(range) = layout.methods.get(method_index) else {
+            return Err(MergeConflict::MethodChanged { method_index });
+        };
+        if std::mem::replace(&mut checked_out[method_index], true) {
+            return Err(MergeConflict::CheckedOutTwice { method_index });
+        }
+        if fnv1a(&bytes[range.clone()]) != checkout.method_checksum {
+            return Err(MergeConflict::MethodChanged { method_index });
+        }
+        let base = class_file.constant_pool.get(..checkout.base_len);
+        if base.map(constants_checksum).transpose()? != Some(checkout.base_checksum) {
+            return Err(MergeConflict::ConstantPoolChanged { method_index });
+        }
+    }
+
+    let mut constant_pool = class_file.constant_pool.clone();
+    let mut existing: HashMap<Vec<u8>, usize> = HashMap::new();
+    for (index, constant) in constant_pool.iter().enumerate() {
+        if !matches!(constant, ConstantPoolInfo::Dummy()) {
+            existing.entry(constant_key(constant)?).or_insert(index);
+        }
+    }
+
+    let mut methods: Vec<Vec<u8>> = layout.methods.iter().map(|range| bytes[range.clone()].to_vec()).collect();
+    for checkout in checkouts {
+        let method_index = checkout.method_index;
+        let checkout_pool = checkout.constant_pool();
+
+        // Entries of the delta only refer to entries before them, so one pass renumbers them.
+        let mut indices: Vec<u16> = (0..checkout.base_len as u16).collect();
+        for constant in checkout.delta() {
+            if matches!(constant, ConstantPoolInfo::Dummy()) {
+                indices.push(indices.last().map_or(0, |index| index + 1));
+                continue;
+            }
+            let constant = remap_constant(constant, |i| indices.get(i).map_or(i, |new| *new as usize));
+            let key = constant_key(&constant)?;
+            let index = match existing.get(&key) {
+                Some(index) => *index,
+                None => {
+                    let index = constant_pool.len();
+                    existing.insert(key, index);
+                    let wide = matches!(constant, ConstantPoolInfo::Long(_) | ConstantPoolInfo::Double(_));
+                    constant_pool.push(constant);
+                    if wide {
+                        constant_pool.push(ConstantPoolInfo::Dummy());
+                    }
+                    index
+                }
+            };
+            if constant_pool.len() > u16::MAX as usize {
+                return Err(MergeConflict::TooManyConstants);
+            }
+            indices.push(index as u16);
+        }
+
+        let mut table = Vec::new();
+        write_u16(&mut table, checkout.attributes.len() as u16);
+        for (name_index, body) in &checkout.attributes {
+            write_u16(&mut table, *name_index);
+            write_u32(&mut table, body.len() as u32);
+            table.extend_from_slice(body);
+        }
+        // Renumbering is only needed when the delta did not land where the checkout put it.
+        if indices.iter().enumerate().any(|(old, new)| old != *new as usize) {
+            table = rewrite_attribute_references(&table, checkout_pool, indices.clone())
+                .ok_or(MergeConflict::UnrelocatableAttribute { method_index })?;
+        }
+        let map = |index: usize| indices.get(index).map_or(index, |new| *new as usize) as u16;
+
+        let method = &mut methods[method_index];
+        method.clear();
+        write_u16(method, checkout.access_flags);
+        write_u16(method, map(checkout.name_index));
+        write_u16(method, map(checkout.descriptor_index));
+        method.extend_from_slice(&table);
+    }
+
+    let mut signatures = HashSet::new();
+    for method in &methods {
+        let utf8 = |offset: usize| {
+            let index = u16::from_be_bytes([method[offset], method[offset + 1]]) as usize;
+            get_utf8(&constant_pool, index).unwrap_or_default()
+        };
+        let (name, descriptor) = (utf8(2), utf8(4));
+        if !signatures.insert((name, descriptor)) {
+            return Err(MergeConflict::DuplicateMethod {
+                name: name.to_string(),
+                descriptor: descriptor.to_string(),
+            });
+        }
+    }
+
+    let mut output = bytes[..2 * size_of::<u32>()].to_vec();
+    encode_constant_pool(&mut output, &constant_pool)?;
+    output.extend_from_slice(&bytes[layout.constant_pool_end..layout.methods_start]);
+    write_u16(&mut output, methods.len() as u16);
+    for method in methods {
+        output.extend_from_slice(&method);
+    }
+    output.extend_from_slice(&bytes[layout.methods_end..]);
+    Ok(output)
+}
+
+/// Offsets of the parts of an encoded class file.
+pub(crate) struct Layout {
+    pub(crate) constant_pool_end: usize,
+    /// Offset of methods_count.
+    pub(crate) methods_start: usize,
+    /// Range of each method_info.
+    pub(crate) methods: Vec<Range<usize>>,
+    pub(crate) methods_end: usize,
+    /// End of the attributes of the class, where the trailing bytes start.
+    pub(crate) attributes_end: usize,
+}
+
+impl Layout {
+    /// Walks a class file produced by [`encode`], which is well-formed.
+    pub(crate) fn new(bytes: &[u8]) -> Self {
+        Self::walk(bytes).expect("encoded class file is well-formed")
+    }
+
+    fn walk(bytes: &[u8]) -> Option<Self> {
+        let mut reader = ClassReader::new(bytes);
+        reader.read_bytes(2 * size_of::<u32>(), "magic").ok()?;
+        let mut context = DecodeContext::new(&Default::default());
+        crate::constant_pool::decode_constant_pool(&mut reader, &mut context).ok()?;
+        let constant_pool_end = reader.offset();
+        reader.read_bytes(3 * size_of::<u16>(), "this_class").ok()?;
+        let interfaces_count = reader.read_u16("interfaces").ok()? as usize;
+        reader.read_bytes(interfaces_count * size_of::<u16>(), "interfaces").ok()?;
+
+        let mut methods_start = 0;
+        let mut methods = Vec::new();
+        for section in ["fields", "methods"] {
+            methods_start = reader.offset();
+            let count = reader.read_u16("members_count").ok()?;
+            methods.clear();
+            for _ in 0..count {
+                let start = reader.offset();
+                reader.read_bytes(3 * size_of::<u16>(), section).ok()?;
+                skip_attributes(&mut reader)?;
+                methods.push(start..reader.offset());
+            }
+        }
+        let methods_end = reader.offset();
+        skip_attributes(&mut reader)?;
+        Some(Self { constant_pool_end, methods_start, methods, methods_end, attributes_end: reader.offset() })
+    }
+}
+
+fn skip_attributes(reader: &mut ClassReader) -> Option<()> {
+    let attributes_count = reader.read_u16("attributes_count").ok()?;
+    for _ in 0..attributes_count {
+        reader.read_u16("attribute_info").ok()?;
+        let length = reader.read_u32("attribute_info").ok()? as usize;
+        reader.read_bytes(length, "attribute_info").ok()?;
+    }
+    Some(())
+}
+
+/// Splits an encoded attribute table into name indices and bodies.
+pub(crate) fn read_attributes(bytes: &[u8]) -> Vec<(u16, Vec<u8>)> {
+    let mut reader = ClassReader::new(bytes);
+    let count = reader.read_u16("attributes_count").unwrap_or_default();
+    (0..count)
+        .map_while(|_| {
+            let name_index = reader.read_u16("attribute_info").ok()?;
+            let length = reader.read_u32("attribute_info").ok()? as usize;
+            Some((name_index, reader.read_bytes(length, "attribute_info").ok()?.to_vec()))
+        })
+        .collect()
+}
+
+/// Returns the encoding of a constant, which identical constants share.
+pub(crate) fn constant_key(constant: &ConstantPoolInfo) -> Result<Vec<u8>, EncodeError> {
+    let mut key = Vec::new();
+    encode_constant(&mut key, constant)?;
+    Ok(key)
+}
+
+fn constants_checksum(constant_pool: &[ConstantPoolInfo]) -> Result<u64, EncodeError> {
+    let mut bytes = Vec::new();
+    encode_constant_pool(&mut bytes, constant_pool)?;
+    Ok(fnv1a(&bytes))
+}
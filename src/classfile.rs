@@ -1,5 +1,16 @@
-use std::collections::HashMap;
-use crate::{types::{decode_attributes, AttributeInfo, ConstantPoolInfo}, utils::read_u16};
+use std::{borrow::Cow, fmt};
+use crate::{
+    error::{DecodeError, EncodeError},
+    reader::ClassReader,
+    scala::{SCALA_LONG_SIGNATURE_DESCRIPTOR, SCALA_SIGNATURE_DESCRIPTOR},
+    types::{
+        check_field_name, check_method_name, decode_attributes, get_class_name, get_member_ref, get_utf8,
+        is_method_descriptor, AnnotationEntry, AttributeInfo, Attributes, BootstrapMethodEntry, CodeAttribute,
+        ConstantPoolBuilder, ConstantPoolInfo, ConstantValueAttribute, ExceptionsAttribute, InnerClassInfo,
+        ModuleAttribute, NameError, NamedAttributes, RecordAttribute, ResolvedAnnotation, SyntheticAttribute,
+    },
+    utils::*,
+};
 
 pub const CLASS_FILE_MAGIC: u32 = 0xCAFEBABE;
 
@@ -9,6 +20,47 @@ pub const SUPPORTED_MINOR_VERSION: f32 = 45.0;
 /// The major version of the Java class file.
 pub const SUPPORTED_MAJOR_VERSION: f32 = 61.0;
 
+/// The minor version of a class file compiled with `--enable-preview`.
+pub const PREVIEW_MINOR_VERSION: u16 = 0xFFFF;
+
+/// The first major version which supports preview features (Java 12).
+pub const FIRST_PREVIEW_MAJOR_VERSION: u16 = 56;
+
+/// The version of a class file.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.1-200-B.2
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ClassFileVersion {
+    pub major: u16,
+    pub minor: u16,
+    /// Whether the class file depends on preview features of its Java SE release.
+    pub preview: bool,
+}
+
+impl ClassFileVersion {
+    pub fn new(major: u16, minor: u16) -> Self {
+        Self {
+            major,
+            minor,
+            preview: major >= FIRST_PREVIEW_MAJOR_VERSION && minor == PREVIEW_MINOR_VERSION,
+        }
+    }
+
+    pub fn is_preview(&self) -> bool {
+        self.preview
+    }
+}
+
+impl std::fmt::Display for ClassFileVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.preview {
+            write!(f, "{}.{} (preview)", self.major, self.minor)
+        } else {
+            write!(f, "{}.{}", self.major, self.minor)
+        }
+    }
+}
+
 /// Access flags for class.
 /// 
 /// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.1-200-E.1
@@ -99,7 +151,39 @@ pub struct FieldInfo<'a> {
     pub access_flags: u16,
     pub name_index: usize,
     pub descriptor_index: usize,
-    pub attributes: HashMap<u16, AttributeInfo<'a>>,
+    pub attributes: Attributes<'a>,
+}
+
+impl<'a> FieldInfo<'a> {
+    /// Returns true if the field has a Deprecated attribute or a `@Deprecated` annotation.
+    pub fn is_deprecated(&self, constant_pool: &[ConstantPoolInfo]) -> bool {
+        is_deprecated(&self.attributes, constant_pool)
+    }
+
+    /// Returns true if the field is compiler-generated, i.e. has ACC_SYNTHETIC or a
+    /// Synthetic attribute.
+    pub fn is_synthetic(&self, constant_pool: &[ConstantPoolInfo]) -> bool {
+        FieldAccessFlag::Synthetic.test(self.access_flags) || has_synthetic_attribute(&self.attributes, constant_pool)
+    }
+
+    /// Returns the generic signature of the field from its Signature attribute, e.g.
+    /// `Ljava/util/List<Ljava/lang/String;>;`.
+    pub fn signature<'c>(&self, constant_pool: &'c [ConstantPoolInfo]) -> Option<&'c str> {
+        signature(&self.attributes, constant_pool)
+    }
+
+    /// Returns the attributes of the field with their names resolved in `constant_pool`.
+    pub fn named_attributes<'s>(&'s self, constant_pool: &'s [ConstantPoolInfo]) -> NamedAttributes<'s, 'a> {
+        self.attributes.named(constant_pool)
+    }
+
+    /// Returns the ConstantValue attribute of the field, or `None` if it has none.
+    pub fn constant_value(&self) -> Option<&ConstantValueAttribute> {
+        self.attributes.values().find_map(|attribute| match attribute {
+            AttributeInfo::ConstantValue(attribute) => Some(attribute),
+            _ => None,
+        })
+    }
 }
 
 #[derive(Debug)]
@@ -107,85 +191,1262 @@ pub struct MethodInfo<'a> {
     pub access_flags: u16,
     pub name_index: usize,
     pub descriptor_index: usize,
-    pub attributes: HashMap<u16, AttributeInfo<'a>>,
+    pub attributes: Attributes<'a>,
+}
+
+impl<'a> MethodInfo<'a> {
+    /// Returns true if the method has a Deprecated attribute or a `@Deprecated` annotation.
+    pub fn is_deprecated(&self, constant_pool: &[ConstantPoolInfo]) -> bool {
+        is_deprecated(&self.attributes, constant_pool)
+    }
+
+    /// Returns true if the method is compiler-generated, e.g. a bridge method or a lambda
+    /// body, i.e. has ACC_SYNTHETIC or a Synthetic attribute.
+    pub fn is_synthetic(&self, constant_pool: &[ConstantPoolInfo]) -> bool {
+        MethodAccessFlag::Synthetic.test(self.access_flags) || has_synthetic_attribute(&self.attributes, constant_pool)
+    }
+
+    /// Returns the generic signature of the method from its Signature attribute, e.g.
+    /// `<T:Ljava/lang/Object;>(TT;)Ljava/util/List<TT;>;`.
+    pub fn signature<'c>(&self, constant_pool: &'c [ConstantPoolInfo]) -> Option<&'c str> {
+        signature(&self.attributes, constant_pool)
+    }
+
+    /// Returns the attributes of the method with their names resolved in `constant_pool`.
+    pub fn named_attributes<'s>(&'s self, constant_pool: &'s [ConstantPoolInfo]) -> NamedAttributes<'s, 'a> {
+        self.attributes.named(constant_pool)
+    }
+
+    /// Returns the names and bodies of the attributes of the method, in class file order, for
+    /// decoding attributes this crate does not know; see [`NamedAttributes::raw`].
+    pub fn raw_attributes<'s>(
+        &'s self,
+        constant_pool: &'s [ConstantPoolInfo],
+    ) -> impl Iterator<Item = (&'s str, Result<Cow<'a, [u8]>, EncodeError>)> + 's {
+        self.attributes.named(constant_pool).raw()
+    }
+
+    /// Returns the classes the method declares in its `throws` clause, from the Exceptions
+    /// attribute, e.g. `java/io/IOException`.
+    ///
+    /// Entries which do not resolve to a CONSTANT_Class are skipped.
+    pub fn throws<'c>(&self, constant_pool: &'c [ConstantPoolInfo]) -> Vec<&'c str> {
+        self.exceptions()
+            .into_iter()
+            .flat_map(|attribute| &attribute.exception_index_table)
+            .filter_map(|index| get_class_name(constant_pool, *index as usize))
+            .collect()
+    }
+
+    /// Returns the Code attribute of the method, or `None` if it has none or the attribute
+    /// was kept undecoded, e.g. with lazy attributes; [`CodeView`](crate::types::CodeView)
+    /// reads both.
+    pub fn code(&self) -> Option<&CodeAttribute<'a>> {
+        self.attributes.values().find_map(|attribute| match attribute {
+            AttributeInfo::Code(attribute) => Some(attribute),
+            _ => None,
+        })
+    }
+
+    /// Returns the Exceptions attribute of the method, or `None` if it has none.
+    pub fn exceptions(&self) -> Option<&ExceptionsAttribute> {
+        self.attributes.values().find_map(|attribute| match attribute {
+            AttributeInfo::Exceptions(attribute) => Some(attribute),
+            _ => None,
+        })
+    }
+
+    /// Returns the annotations of each formal parameter, runtime-visible ones first.
+    ///
+    /// Parameters are numbered as in the parameter annotation attributes, which may leave out
+    /// parameters of the descriptor; javac, for one, omits the synthetic outer instance
+    /// parameter of inner class constructors.
+    pub fn parameter_annotations(&self) -> Vec<Vec<&AnnotationEntry>> {
+        let mut parameters: Vec<Vec<&AnnotationEntry>> = Vec::new();
+        let visible = self.attributes.values().find_map(|attribute| match attribute {
+            AttributeInfo::RuntimeVisibleParameterAnnotations(attribute) => Some(&attribute.parameter_annotations),
+            _ => None,
+        });
+        let invisible = self.attributes.values().find_map(|attribute| match attribute {
+            AttributeInfo::RuntimeInvisibleParameterAnnotations(attribute) => Some(&attribute.parameter_annotations),
+            _ => None,
+        });
+        for parameter_annotations in visible.into_iter().chain(invisible) {
+            if parameters.len() < parameter_annotations.len() {
+                parameters.resize_with(parameter_annotations.len(), Vec::new);
+            }
+            for (parameter, annotations) in parameters.iter_mut().zip(parameter_annotations) {
+                parameter.extend(annotations);
+            }
+        }
+        parameters
+    }
+}
+
+/// A field or a method, for code handling both alike.
+pub trait ClassMember: Sized {
+    fn name_index(&self) -> usize;
+    fn descriptor_index(&self) -> usize;
+    /// Creates a member without attributes.
+    fn without_attributes(access_flags: u16, name_index: usize, descriptor_index: usize) -> Self;
+    /// Checks that `name` is legal for the kind of member.
+    fn check_name(name: &str) -> Result<(), NameError>;
+}
+
+impl ClassMember for FieldInfo<'_> {
+    fn name_index(&self) -> usize {
+        self.name_index
+    }
+
+    fn descriptor_index(&self) -> usize {
+        self.descriptor_index
+    }
+
+    fn without_attributes(access_flags: u16, name_index: usize, descriptor_index: usize) -> Self {
+        FieldInfo {
+            access_flags,
+            name_index,
+            descriptor_index,
+            attributes: Attributes::new(),
+        }
+    }
+
+    fn check_name(name: &str) -> Result<(), NameError> {
+        check_field_name(name)
+    }
+}
+
+impl ClassMember for MethodInfo<'_> {
+    fn name_index(&self) -> usize {
+        self.name_index
+    }
+
+    fn descriptor_index(&self) -> usize {
+        self.descriptor_index
+    }
+
+    fn without_attributes(access_flags: u16, name_index: usize, descriptor_index: usize) -> Self {
+        MethodInfo {
+            access_flags,
+            name_index,
+            descriptor_index,
+            attributes: Attributes::new(),
+        }
+    }
+
+    fn check_name(name: &str) -> Result<(), NameError> {
+        check_method_name(name)
+    }
+}
+
+/// Returns the position of the member named `name` with `descriptor`.
+fn member_position<T: ClassMember>(
+    members: &[T],
+    constant_pool: &[ConstantPoolInfo],
+    name: &str,
+    descriptor: &str,
+) -> Option<usize> {
+    members.iter().position(|member| {
+        get_utf8(constant_pool, member.name_index()) == Some(name)
+            && get_utf8(constant_pool, member.descriptor_index()) == Some(descriptor)
+    })
+}
+
+/// Tests for a Deprecated attribute, or a `java.lang.Deprecated` annotation which javac
+/// emits alongside it since Java 5 and alone for some other compilers.
+fn is_deprecated(attributes: &Attributes, constant_pool: &[ConstantPoolInfo]) -> bool {
+    attributes.values().any(|attribute| {
+        let annotations = match attribute {
+            AttributeInfo::Deprecated(_) => return true,
+            AttributeInfo::RuntimeVisibleAnnotations(attribute) => &attribute.annotations,
+            AttributeInfo::RuntimeInvisibleAnnotations(attribute) => &attribute.annotations,
+            _ => return false,
+        };
+        annotations
+            .iter()
+            .any(|annotation| get_utf8(constant_pool, annotation.type_index as usize) == Some("Ljava/lang/Deprecated;"))
+    })
+}
+
+/// Tests for a Synthetic attribute, which compilers before Java 5 wrote instead of
+/// ACC_SYNTHETIC. It is found by name, so that undecoded attributes count.
+fn has_synthetic_attribute(attributes: &Attributes, constant_pool: &[ConstantPoolInfo]) -> bool {
+    attributes.by_name("Synthetic", constant_pool).is_some()
+}
+
+/// Makes ACC_SYNTHETIC (`flag`) and the Synthetic attribute of one owner agree, and returns
+/// true if either changed. `name_index` is the index of `Synthetic` if attributes are added.
+fn reconcile_synthetic(
+    access_flags: &mut u16,
+    flag: u16,
+    attributes: &mut Attributes,
+    constant_pool: &[ConstantPoolInfo],
+    marking: SyntheticMarking,
+    name_index: u16,
+) -> bool {
+    let has_attribute = has_synthetic_attribute(attributes, constant_pool);
+    let has_flag = *access_flags & flag != 0;
+    if !has_attribute && !has_flag {
+        return false;
+    }
+
+    *access_flags |= flag;
+    match marking {
+        SyntheticMarking::Flag if has_attribute => {
+            attributes.retain(|index, _| get_utf8(constant_pool, index as usize) != Some("Synthetic"));
+            true
+        }
+        SyntheticMarking::Both if !has_attribute => {
+            attributes.push(name_index, AttributeInfo::Synthetic(SyntheticAttribute));
+            true
+        }
+        _ => !has_flag,
+    }
+}
+
+/// Resolves the Signature attribute among `attributes`.
+fn signature<'c>(attributes: &Attributes, constant_pool: &'c [ConstantPoolInfo]) -> Option<&'c str> {
+    attributes.values().find_map(|attribute| match attribute {
+        AttributeInfo::Signature(attribute) => attribute.signature(constant_pool),
+        _ => None,
+    })
+}
+
+/// Where a class is declared, as recorded by its own InnerClasses entry.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.6
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NestingKind {
+    /// Not nested, or compiled without an InnerClasses entry for itself.
+    TopLevel,
+    /// A member of another class.
+    Member,
+    /// A named class declared in a block.
+    Local,
+    /// An anonymous class.
+    Anonymous,
+}
+
+/// The EnclosingMethod attribute of a local or anonymous class with its indices resolved.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.7
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnclosingMethod<'c> {
+    /// The innermost class enclosing the declaration, e.g. `com/example/Outer`.
+    pub class_name: &'c str,
+    /// The name and descriptor of the enclosing method, or `None` if the class is declared
+    /// in an initializer.
+    pub method: Option<(&'c str, &'c str)>,
+}
+
+/// The bootstrap method of a CONSTANT_InvokeDynamic or CONSTANT_Dynamic entry, resolved
+/// through the BootstrapMethods attribute.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.23
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BootstrapCall<'c> {
+    /// Index of the entry in the BootstrapMethods attribute.
+    pub bootstrap_index: usize,
+    /// Name of the call site or dynamic constant, e.g. `run` for a lambda implementing Runnable.
+    pub name: &'c str,
+    /// Descriptor of the call site or dynamic constant.
+    pub descriptor: &'c str,
+    /// reference_kind of the CONSTANT_MethodHandle of the bootstrap method.
+    pub reference_kind: u8,
+    /// Owner, name and descriptor of the bootstrap method, e.g.
+    /// `java/lang/invoke/LambdaMetafactory`, `metafactory` and its descriptor.
+    pub method: (&'c str, &'c str, &'c str),
+    /// Constant pool indices of the static arguments.
+    pub arguments: &'c [usize],
+}
+
+/// A component of a record class, resolved from the Record attribute.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.30
+#[derive(Debug, Clone)]
+pub struct RecordComponent<'c> {
+    pub name: &'c str,
+    /// Field descriptor of the component, e.g. `Ljava/util/List;`.
+    pub descriptor: &'c str,
+    /// Generic signature of the component, e.g. `Ljava/util/List<Ljava/lang/String;>;`.
+    pub signature: Option<&'c str>,
+    /// Declaration annotations of the component, runtime-visible ones first.
+    pub annotations: Vec<&'c AnnotationEntry>,
+    /// All attributes of the component, in class file order.
+    pub attributes: &'c Attributes<'c>,
+}
+
+/// A component of a record class with its annotations resolved into typed values, see
+/// [`JavaClassFile::record_component_views`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordComponentView<'c> {
+    pub name: &'c str,
+    /// Field descriptor of the component, e.g. `Ljava/util/List;`.
+    pub descriptor: &'c str,
+    /// Generic signature of the component, e.g. `Ljava/util/List<Ljava/lang/String;>;`.
+    pub signature: Option<&'c str>,
+    /// Annotations from the RuntimeVisibleAnnotations attribute.
+    pub visible_annotations: Vec<ResolvedAnnotation<'c>>,
+    /// Annotations from the RuntimeInvisibleAnnotations attribute.
+    pub invisible_annotations: Vec<ResolvedAnnotation<'c>>,
+}
+
+impl<'c> RecordComponentView<'c> {
+    /// Resolves the annotations of `component` against `constant_pool`.
+    pub fn new(component: &RecordComponent<'c>, constant_pool: &'c [ConstantPoolInfo]) -> Self {
+        let resolve = |visible: bool| {
+            let annotations = component.attributes.values().filter_map(move |attribute| match attribute {
+                AttributeInfo::RuntimeVisibleAnnotations(attribute) if visible => Some(&attribute.annotations),
+                AttributeInfo::RuntimeInvisibleAnnotations(attribute) if !visible => Some(&attribute.annotations),
+                _ => None,
+            });
+            annotations.flatten().filter_map(|annotation| annotation.resolve(constant_pool)).collect()
+        };
+        Self {
+            name: component.name,
+            descriptor: component.descriptor,
+            signature: component.signature,
+            visible_annotations: resolve(true),
+            invisible_annotations: resolve(false),
+        }
+    }
+
+    /// Returns the visible or invisible annotation of the interface with `type_descriptor`.
+    pub fn annotation(&self, type_descriptor: &str) -> Option<&ResolvedAnnotation<'c>> {
+        self.visible_annotations
+            .iter()
+            .chain(&self.invisible_annotations)
+            .find(|annotation| annotation.type_descriptor == type_descriptor)
+    }
+}
+
+/// A field or method of a class, found by name and descriptor by
+/// [`JavaClassFile::field_entry`] or [`JavaClassFile::method_entry`], which may be missing.
+#[derive(Debug)]
+pub enum MemberEntry<'c, 'a, T> {
+    Occupied(&'c mut T),
+    Vacant(VacantMember<'c, 'a, T>),
+}
+
+/// A missing field or method, added with its name and descriptor by [`VacantMember::insert_with`].
+#[derive(Debug)]
+pub struct VacantMember<'c, 'a, T> {
+    constant_pool: &'c mut Vec<ConstantPoolInfo<'a>>,
+    members: &'c mut Vec<T>,
+    name: Cow<'a, str>,
+    descriptor: Cow<'a, str>,
+}
+
+impl<'c, 'a, T: ClassMember> VacantMember<'c, 'a, T> {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn descriptor(&self) -> &str {
+        &self.descriptor
+    }
+
+    /// Adds the name and descriptor to the constant pool unless they exist, then appends the
+    /// member `create` makes from their indices.
+    pub fn insert_with(self, create: impl FnOnce(usize, usize) -> T) -> &'c mut T {
+        let mut constants = ConstantPoolBuilder::extending(self.constant_pool);
+        let name_index = constants.utf8(self.name);
+        let descriptor_index = constants.utf8(self.descriptor);
+        *self.constant_pool = constants.build();
+        self.members.push(create(name_index, descriptor_index));
+        self.members.last_mut().expect("member just pushed")
+    }
+
+    /// Like [`insert_with`](Self::insert_with), but rejects a name which is illegal for the
+    /// kind of member, e.g. a method named `a.b`, before changing anything.
+    pub fn try_insert_with(self, create: impl FnOnce(usize, usize) -> T) -> Result<&'c mut T, NameError> {
+        T::check_name(&self.name)?;
+        Ok(self.insert_with(create))
+    }
+}
+
+impl<'c, 'a, T: ClassMember> MemberEntry<'c, 'a, T> {
+    /// Returns the member, adding one without attributes with `access_flags` if it is missing.
+    pub fn or_insert(self, access_flags: u16) -> &'c mut T {
+        self.or_insert_with(|name_index, descriptor_index| {
+            T::without_attributes(access_flags, name_index, descriptor_index)
+        })
+    }
+
+    /// Returns the member, adding the one `create` makes from the constant pool indices of
+    /// the name and descriptor if it is missing.
+    pub fn or_insert_with(self, create: impl FnOnce(usize, usize) -> T) -> &'c mut T {
+        match self {
+            MemberEntry::Occupied(member) => member,
+            MemberEntry::Vacant(entry) => entry.insert_with(create),
+        }
+    }
+
+    /// Like [`or_insert`](Self::or_insert), but rejects a name which is illegal for the kind of
+    /// member instead of adding it.
+    pub fn try_or_insert(self, access_flags: u16) -> Result<&'c mut T, NameError> {
+        match self {
+            MemberEntry::Occupied(member) => Ok(member),
+            MemberEntry::Vacant(entry) => entry.try_insert_with(|name_index, descriptor_index| {
+                T::without_attributes(access_flags, name_index, descriptor_index)
+            }),
+        }
+    }
+
+    /// Calls `modify` on the member if it exists.
+    pub fn and_modify(mut self, modify: impl FnOnce(&mut T)) -> Self {
+        if let MemberEntry::Occupied(member) = &mut self {
+            modify(member);
+        }
+        self
+    }
+}
+
+/// A field or method with its name and descriptor resolved, yielded by [`Members`] and
+/// [`MembersMut`]. `M` is a shared or mutable reference to a [`FieldInfo`] or [`MethodInfo`].
+#[derive(Debug)]
+pub struct MemberView<'c, M> {
+    /// The name, or `None` if name_index does not refer to a CONSTANT_Utf8.
+    pub name: Option<&'c str>,
+    /// The descriptor, or `None` if descriptor_index does not refer to a CONSTANT_Utf8.
+    pub descriptor: Option<&'c str>,
+    pub member: M,
+}
+
+impl<'c, M> MemberView<'c, M> {
+    fn new<T: ClassMember>(constant_pool: &'c [ConstantPoolInfo], member: M) -> Self
+    where
+        M: std::ops::Deref<Target = T>,
+    {
+        Self {
+            name: get_utf8(constant_pool, member.name_index()),
+            descriptor: get_utf8(constant_pool, member.descriptor_index()),
+            member,
+        }
+    }
+}
+
+/// The fields or methods of a class with the constant pool resolving their names, returned by
+/// [`JavaClassFile::field_views`] and [`JavaClassFile::method_views`].
+#[derive(Debug)]
+pub struct Members<'c, 'a, T> {
+    constant_pool: &'c [ConstantPoolInfo<'a>],
+    members: &'c [T],
+}
+
+impl<T> Clone for Members<'_, '_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Members<'_, '_, T> {}
+
+impl<'c, 'a, T: ClassMember> Members<'c, 'a, T> {
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Returns the members in class file order.
+    pub fn iter(&self) -> MemberIter<'c, 'a, T> {
+        MemberIter { constant_pool: self.constant_pool, members: self.members.iter() }
+    }
+}
+
+impl<'c, 'a, T: ClassMember> IntoIterator for Members<'c, 'a, T> {
+    type Item = MemberView<'c, &'c T>;
+    type IntoIter = MemberIter<'c, 'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Like [`Members`], but also yields the members for modification, returned by
+/// [`JavaClassFile::field_views_mut`] and [`JavaClassFile::method_views_mut`].
+#[derive(Debug)]
+pub struct MembersMut<'c, 'a, T> {
+    constant_pool: &'c [ConstantPoolInfo<'a>],
+    members: &'c mut [T],
+}
+
+impl<'c, 'a, T: ClassMember> MembersMut<'c, 'a, T> {
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Returns the members in class file order.
+    pub fn iter(&self) -> MemberIter<'_, 'a, T> {
+        MemberIter { constant_pool: self.constant_pool, members: self.members.iter() }
+    }
+
+    /// Returns the members in class file order for modification. Their names are resolved
+    /// before they are yielded, so changing name_index does not change the view.
+    pub fn iter_mut(&mut self) -> MemberIterMut<'_, 'a, T> {
+        MemberIterMut { constant_pool: self.constant_pool, members: self.members.iter_mut() }
+    }
+}
+
+impl<'c, 'a, T: ClassMember> IntoIterator for MembersMut<'c, 'a, T> {
+    type Item = MemberView<'c, &'c mut T>;
+    type IntoIter = MemberIterMut<'c, 'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        MemberIterMut { constant_pool: self.constant_pool, members: self.members.iter_mut() }
+    }
+}
+
+/// An iterator over [`MemberView`]s of shared members, see [`Members::iter`].
+#[derive(Debug, Clone)]
+pub struct MemberIter<'c, 'a, T> {
+    constant_pool: &'c [ConstantPoolInfo<'a>],
+    members: std::slice::Iter<'c, T>,
+}
+
+impl<'c, T: ClassMember> Iterator for MemberIter<'c, '_, T> {
+    type Item = MemberView<'c, &'c T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.members.next().map(|member| MemberView::new(self.constant_pool, member))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.members.size_hint()
+    }
+}
+
+impl<T: ClassMember> DoubleEndedIterator for MemberIter<'_, '_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.members.next_back().map(|member| MemberView::new(self.constant_pool, member))
+    }
+}
+
+impl<T: ClassMember> ExactSizeIterator for MemberIter<'_, '_, T> {}
+
+/// An iterator over [`MemberView`]s of mutable members, see [`MembersMut::iter_mut`].
+#[derive(Debug)]
+pub struct MemberIterMut<'c, 'a, T> {
+    constant_pool: &'c [ConstantPoolInfo<'a>],
+    members: std::slice::IterMut<'c, T>,
+}
+
+impl<'c, T: ClassMember> Iterator for MemberIterMut<'c, '_, T> {
+    type Item = MemberView<'c, &'c mut T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.members.next().map(|member| MemberView::new(self.constant_pool, member))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.members.size_hint()
+    }
+}
+
+impl<T: ClassMember> DoubleEndedIterator for MemberIterMut<'_, '_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.members.next_back().map(|member| MemberView::new(self.constant_pool, member))
+    }
+}
+
+impl<T: ClassMember> ExactSizeIterator for MemberIterMut<'_, '_, T> {}
+
+/// A method whose flags, name and descriptor [`MethodBuilder`] rejected, or which it could not
+/// add to a class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MethodError {
+    Name(NameError),
+    /// The descriptor is not a method descriptor.
+    Descriptor(String),
+    /// The access flags, the name and descriptor and whether the method has code disagree,
+    /// e.g. ACC_ABSTRACT with code.
+    Flags { name: String, descriptor: String, reason: &'static str },
+    /// The class already has a method with the name and descriptor.
+    Duplicate { name: String, descriptor: String },
+}
+
+impl fmt::Display for MethodError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MethodError::Name(error) => write!(f, "{}", error),
+            MethodError::Descriptor(descriptor) => write!(f, "illegal method descriptor \"{}\"", descriptor),
+            MethodError::Flags { name, descriptor, reason } => write!(f, "method {}{}: {}", name, descriptor, reason),
+            MethodError::Duplicate { name, descriptor } => write!(f, "method {}{} already exists", name, descriptor),
+        }
+    }
+}
+
+impl std::error::Error for MethodError {}
+
+/// A method checked as it is built, so that a mistake is reported where it is made rather
+/// than when the class is verified or loaded:
+///
+/// ```text
+/// MethodBuilder::new("<init>", "()V", MethodAccessFlag::Public as u16)?.code(code)?.add_to(&mut class_file)?;
+/// ```
+///
+/// Only the rules which hold whatever the class and its version are checked; see
+/// [`validate`](crate::validate::validate) for the others.
+#[derive(Debug)]
+pub struct MethodBuilder<'a> {
+    name: Cow<'a, str>,
+    descriptor: Cow<'a, str>,
+    access_flags: u16,
+    code: Option<CodeAttribute<'a>>,
+}
+
+impl<'a> MethodBuilder<'a> {
+    /// Starts a method, rejecting an illegal name or descriptor and flags which no method with
+    /// the name may have, e.g. ACC_ABSTRACT with ACC_FINAL, or a static `<init>`.
+    pub fn new(
+        name: impl Into<Cow<'a, str>>,
+        descriptor: impl Into<Cow<'a, str>>,
+        access_flags: u16,
+    ) -> Result<Self, MethodError> {
+        let builder = Self { name: name.into(), descriptor: descriptor.into(), access_flags, code: None };
+        check_method_name(&builder.name).map_err(MethodError::Name)?;
+        if !is_method_descriptor(&builder.descriptor) {
+            return Err(MethodError::Descriptor(builder.descriptor.into_owned()));
+        }
+        let flag = |flag: MethodAccessFlag| flag.test(access_flags);
+
+        let visibilities = [MethodAccessFlag::Public, MethodAccessFlag::Private, MethodAccessFlag::Protected];
+        if visibilities.into_iter().filter(|&visibility| flag(visibility)).count() > 1 {
+            return Err(builder.flags_error("at most one of ACC_PUBLIC, ACC_PRIVATE and ACC_PROTECTED may be set"));
+        }
+        let implementation_flags = [
+            MethodAccessFlag::Private,
+            MethodAccessFlag::Static,
+            MethodAccessFlag::Final,
+            MethodAccessFlag::Synchronized,
+            MethodAccessFlag::Native,
+        ];
+        if flag(MethodAccessFlag::Abstract) && implementation_flags.into_iter().any(flag) {
+            return Err(builder.flags_error(
+                "ACC_ABSTRACT excludes ACC_PRIVATE, ACC_STATIC, ACC_FINAL, ACC_SYNCHRONIZED and ACC_NATIVE",
+            ));
+        }
+        match &*builder.name {
+            "<init>" => {
+                let initializer_flags = [
+                    MethodAccessFlag::Static,
+                    MethodAccessFlag::Final,
+                    MethodAccessFlag::Synchronized,
+                    MethodAccessFlag::Bridge,
+                    MethodAccessFlag::Native,
+                    MethodAccessFlag::Abstract,
+                ];
+                if initializer_flags.into_iter().any(flag) {
+                    return Err(builder.flags_error(
+                        "<init> is not ACC_STATIC, ACC_FINAL, ACC_SYNCHRONIZED, ACC_BRIDGE, ACC_NATIVE or ACC_ABSTRACT",
+                    ));
+                }
+                if !builder.descriptor.ends_with(")V") {
+                    return Err(builder.flags_error("<init> must return void"));
+                }
+            }
+            "<clinit>" if builder.descriptor != "()V" => {
+                return Err(builder.flags_error("<clinit> must take no arguments and return void"));
+            }
+            _ => {}
+        }
+        Ok(builder)
+    }
+
+    /// Sets the Code attribute, which ACC_ABSTRACT and ACC_NATIVE methods may not have.
+    pub fn code(mut self, code: CodeAttribute<'a>) -> Result<Self, MethodError> {
+        if MethodAccessFlag::Abstract.test(self.access_flags) {
+            return Err(self.flags_error("an ACC_ABSTRACT method has no code"));
+        }
+        if MethodAccessFlag::Native.test(self.access_flags) {
+            return Err(self.flags_error("an ACC_NATIVE method has no code"));
+        }
+        self.code = Some(code);
+        Ok(self)
+    }
+
+    /// Adds the method to `class_file`, with its name, descriptor and the name of its Code
+    /// attribute added to the constant pool unless they exist.
+    ///
+    /// Fails without changing anything if the method has no code but is neither ACC_ABSTRACT
+    /// nor ACC_NATIVE, or if the class already has a method with the name and descriptor.
+    pub fn add_to<'c>(self, class_file: &'c mut JavaClassFile<'a>) -> Result<&'c mut MethodInfo<'a>, MethodError> {
+        let flag = |flag: MethodAccessFlag| flag.test(self.access_flags);
+        if self.code.is_none() && !flag(MethodAccessFlag::Abstract) && !flag(MethodAccessFlag::Native) {
+            return Err(self.flags_error("a method which is neither ACC_ABSTRACT nor ACC_NATIVE needs code"));
+        }
+        if class_file.method(&self.name, &self.descriptor).is_some() {
+            return Err(MethodError::Duplicate {
+                name: self.name.into_owned(),
+                descriptor: self.descriptor.into_owned(),
+            });
+        }
+        let code = self.code.map(|code| {
+            let mut constants = ConstantPoolBuilder::extending(&class_file.constant_pool);
+            let name_index = constants.utf8("Code") as u16;
+            class_file.constant_pool = constants.build();
+            (name_index, code)
+        });
+        let access_flags = self.access_flags;
+        let method = class_file.method_entry(self.name, self.descriptor).or_insert_with(|name_index, descriptor_index| {
+            MethodInfo::without_attributes(access_flags, name_index, descriptor_index)
+        });
+        if let Some((name_index, code)) = code {
+            method.attributes.push(name_index, AttributeInfo::Code(code));
+        }
+        Ok(method)
+    }
+
+    fn flags_error(&self, reason: &'static str) -> MethodError {
+        MethodError::Flags { name: self.name.to_string(), descriptor: self.descriptor.to_string(), reason }
+    }
+}
+
+/// How [`JavaClassFile::reconcile_synthetic`] marks compiler-generated classes and members.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SyntheticMarking {
+    /// ACC_SYNTHETIC alone, as compilers write since Java 5; Synthetic attributes are removed.
+    #[default]
+    Flag,
+    /// Both ACC_SYNTHETIC and a Synthetic attribute, for tools which read only one of them.
+    Both,
 }
 
 /// Represents a Java class file.
 /// 
 /// https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.1
+///
+/// With `--cfg allocator_api` on a nightly compiler, the constant pool, interfaces, fields and
+/// methods are in the allocator `A`, see `decode_in`.
 #[derive(Debug)]
-pub struct JavaClassFile<'a> {
+pub struct JavaClassFile<'a, #[cfg(allocator_api)] A: Allocator = Global> {
     pub magic: u32,
     pub minor_version: u16,
     pub major_version: u16,
+    #[cfg(allocator_api)]
+    pub constant_pool: Vec<ConstantPoolInfo<'a>, A>,
+    #[cfg(not(allocator_api))]
     pub constant_pool: Vec<ConstantPoolInfo<'a>>,
     pub access_flags: u16,
     pub this_class: usize,
     pub super_class: usize,
+    #[cfg(allocator_api)]
+    pub interfaces: Vec<usize, A>,
+    #[cfg(not(allocator_api))]
     pub interfaces: Vec<usize>,
+    #[cfg(allocator_api)]
+    pub fields: Vec<FieldInfo<'a>, A>,
+    #[cfg(not(allocator_api))]
     pub fields: Vec<FieldInfo<'a>>,
+    #[cfg(allocator_api)]
+    pub methods: Vec<MethodInfo<'a>, A>,
+    #[cfg(not(allocator_api))]
     pub methods: Vec<MethodInfo<'a>>,
-    pub attributes: HashMap<u16, AttributeInfo<'a>>,
+    pub attributes: Attributes<'a>,
+    /// Bytes following the class file structure, kept when decoding in lenient mode.
+    pub trailing_bytes: &'a [u8],
 }
 
 impl<'a> JavaClassFile<'a> {
-    /// Creates an empty JavaClassFile for primitive types.
+    pub fn version(&self) -> ClassFileVersion {
+        ClassFileVersion::new(self.major_version, self.minor_version)
+    }
+
+    /// Returns true if the class file was compiled with `--enable-preview`.
+    pub fn is_preview(&self) -> bool {
+        self.version().is_preview()
+    }
+
+    /// Returns true if the class has a Deprecated attribute or a `@Deprecated` annotation.
+    pub fn is_deprecated(&self) -> bool {
+        is_deprecated(&self.attributes, &self.constant_pool)
+    }
+
+    /// Returns true if the class is compiler-generated, e.g. a lambda proxy, i.e. has
+    /// ACC_SYNTHETIC or a Synthetic attribute.
+    pub fn is_synthetic(&self) -> bool {
+        ClassAccessFlag::Synthetic.test(self.access_flags)
+            || has_synthetic_attribute(&self.attributes, &self.constant_pool)
+    }
+
+    /// Makes ACC_SYNTHETIC and the Synthetic attribute of the class and of each of its
+    /// members agree, as `marking` asks, and returns the number of those changed.
+    ///
+    /// Compilers before Java 5 marked compiler-generated code with the attribute only, so
+    /// tools testing the flag alone miss it. [`SyntheticMarking::Both`] adds `Synthetic` to
+    /// the constant pool if it is missing.
+    pub fn reconcile_synthetic(&mut self, marking: SyntheticMarking) -> usize {
+        let (class_flag, field_flag, method_flag) = (
+            ClassAccessFlag::Synthetic as u16,
+            FieldAccessFlag::Synthetic as u16,
+            MethodAccessFlag::Synthetic as u16,
+        );
+        let unattributed = |access_flags: u16, flag: u16, attributes: &Attributes| {
+            access_flags & flag != 0 && !has_synthetic_attribute(attributes, &self.constant_pool)
+        };
+        let adds_attributes = marking == SyntheticMarking::Both
+            && (unattributed(self.access_flags, class_flag, &self.attributes)
+                || self.fields.iter().any(|field| unattributed(field.access_flags, field_flag, &field.attributes))
+                || self
+                    .methods
+                    .iter()
+                    .any(|method| unattributed(method.access_flags, method_flag, &method.attributes)));
+        // Otherwise no attribute is added, and the index is not used.
+        let mut name_index = 0;
+        if adds_attributes {
+            let mut constants = ConstantPoolBuilder::extending(&self.constant_pool);
+            name_index = constants.utf8("Synthetic") as u16;
+            self.constant_pool = constants.build();
+        }
+
+        let constant_pool = &self.constant_pool;
+        let reconcile = |access_flags: &mut u16, flag: u16, attributes: &mut Attributes| {
+            reconcile_synthetic(access_flags, flag, attributes, constant_pool, marking, name_index) as usize
+        };
+        let mut changed = reconcile(&mut self.access_flags, class_flag, &mut self.attributes);
+        for field in &mut self.fields {
+            changed += reconcile(&mut field.access_flags, field_flag, &mut field.attributes);
+        }
+        for method in &mut self.methods {
+            changed += reconcile(&mut method.access_flags, method_flag, &mut method.attributes);
+        }
+        changed
+    }
+
+    /// Returns true if the class looks compiled by scalac, i.e. has a ScalaSig or Scala
+    /// attribute or a ScalaSignature or ScalaLongSignature annotation.
+    ///
+    /// Java classes of Scala libraries have none of them, nor have some synthetic classes
+    /// scalac writes, e.g. those of lambdas, so this tells Scala artifacts apart rather than
+    /// classifying every class of one.
+    pub fn is_scala(&self) -> bool {
+        self.attributes.values().any(|attribute| match attribute {
+            AttributeInfo::ScalaSig(_) | AttributeInfo::Scala(_) => true,
+            AttributeInfo::RuntimeVisibleAnnotations(attribute) => attribute.annotations.iter().any(|annotation| {
+                matches!(
+                    get_utf8(&self.constant_pool, annotation.type_index as usize),
+                    Some(SCALA_SIGNATURE_DESCRIPTOR | SCALA_LONG_SIGNATURE_DESCRIPTOR)
+                )
+            }),
+            _ => false,
+        })
+    }
+
+    /// Returns the generic signature of the class from its Signature attribute, e.g.
+    /// `<E:Ljava/lang/Object;>Ljava/util/AbstractList<TE;>;Ljava/util/List<TE;>;`.
+    pub fn signature(&self) -> Option<&str> {
+        signature(&self.attributes, &self.constant_pool)
+    }
+
+    /// Returns the name of the source file the class was compiled from, e.g. `Foo.java`,
+    /// from its SourceFile attribute.
+    pub fn source_file(&self) -> Option<&str> {
+        self.attributes.values().find_map(|attribute| match attribute {
+            AttributeInfo::SourceFile(attribute) => get_utf8(&self.constant_pool, attribute.sourcefile_index as usize),
+            _ => None,
+        })
+    }
+
+    /// Returns the entries of the BootstrapMethods attribute, or an empty slice if there is none.
+    pub fn bootstrap_methods(&self) -> &[BootstrapMethodEntry] {
+        self.attributes
+            .values()
+            .find_map(|attribute| match attribute {
+                AttributeInfo::BootstrapMethods(attribute) => Some(attribute.bootstrap_methods.as_slice()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    /// Resolves the bootstrap method of the CONSTANT_InvokeDynamic or CONSTANT_Dynamic entry at
+    /// `index`, or returns `None` if the entry is of another kind or a reference is invalid.
+    pub fn bootstrap_call(&self, index: usize) -> Option<BootstrapCall<'_>> {
+        let (bootstrap_index, name_and_type_index) = match self.constant_pool.get(index)? {
+            ConstantPoolInfo::InvokeDynamic(info) => (info.bootstrap_method_attr_index, info.name_and_type_index),
+            ConstantPoolInfo::Dynamic(info) => (info.bootstrap_method_handle_attr_index, info.name_and_type_index),
+            _ => return None,
+        };
+        let ConstantPoolInfo::NameAndType(name_and_type) = self.constant_pool.get(name_and_type_index)? else {
+            return None;
+        };
+        let entry = self.bootstrap_methods().get(bootstrap_index)?;
+        let ConstantPoolInfo::MethodHandle(handle) = self.constant_pool.get(entry.bootstrap_method_ref)? else {
+            return None;
+        };
+        Some(BootstrapCall {
+            bootstrap_index,
+            name: get_utf8(&self.constant_pool, name_and_type.name_index)?,
+            descriptor: get_utf8(&self.constant_pool, name_and_type.descriptor_index)?,
+            reference_kind: handle.reference_kind,
+            method: get_member_ref(&self.constant_pool, handle.reference_index)?,
+            arguments: &entry.bootstrap_arguments,
+        })
+    }
+
+    /// Returns the entries of the InnerClasses attribute, or an empty slice if there is none.
+    pub fn inner_classes(&self) -> &[InnerClassInfo] {
+        self.attributes
+            .values()
+            .find_map(|attribute| match attribute {
+                AttributeInfo::InnerClasses(attribute) => Some(attribute.classes.as_slice()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns whether this class is a top-level, member, local or anonymous class.
+    ///
+    /// Classes are compared by name, since the InnerClasses entry may refer to a different
+    /// CONSTANT_Class than this_class.
+    pub fn nesting_kind(&self) -> NestingKind {
+        let this_class = get_class_name(&self.constant_pool, self.this_class);
+        let entry = self.inner_classes().iter().find(|entry| {
+            this_class.is_some() && get_class_name(&self.constant_pool, entry.inner_class_info_index as usize) == this_class
+        });
+        match entry {
+            None => NestingKind::TopLevel,
+            Some(entry) if entry.outer_class_info_index != 0 => NestingKind::Member,
+            Some(entry) if entry.inner_name_index == 0 => NestingKind::Anonymous,
+            Some(_) => NestingKind::Local,
+        }
+    }
+
+    /// Returns the resolved EnclosingMethod attribute, or `None` if there is none or its
+    /// indices are invalid.
+    pub fn enclosing_method(&self) -> Option<EnclosingMethod<'_>> {
+        let attribute = self.attributes.values().find_map(|attribute| match attribute {
+            AttributeInfo::EnclosingMethod(attribute) => Some(attribute),
+            _ => None,
+        })?;
+        let class_name = get_class_name(&self.constant_pool, attribute.class_index as usize)?;
+        let method = match attribute.method_index {
+            0 => None,
+            index => match self.constant_pool.get(index as usize)? {
+                ConstantPoolInfo::NameAndType(info) => Some((
+                    get_utf8(&self.constant_pool, info.name_index)?,
+                    get_utf8(&self.constant_pool, info.descriptor_index)?,
+                )),
+                _ => return None,
+            },
+        };
+        Some(EnclosingMethod { class_name, method })
+    }
+
+    /// Returns true if this class is declared inside another class or a block.
+    pub fn is_nested(&self) -> bool {
+        self.nesting_kind() != NestingKind::TopLevel
+    }
+
+    /// Returns the names of the classes declared as members of this class, e.g.
+    /// `java/util/Map$Entry` for `java/util/Map`, in InnerClasses order.
+    pub fn member_classes(&self) -> Vec<&str> {
+        let Some(this_class) = get_class_name(&self.constant_pool, self.this_class) else {
+            return Vec::new();
+        };
+        self.inner_classes()
+            .iter()
+            .filter(|entry| get_class_name(&self.constant_pool, entry.outer_class_info_index as usize) == Some(this_class))
+            .filter_map(|entry| get_class_name(&self.constant_pool, entry.inner_class_info_index as usize))
+            .collect()
+    }
+
+    /// Returns the name of the host of the nest this class belongs to, from its NestHost
+    /// attribute, or `None` if it has none and is thus the host of its own nest.
+    pub fn nest_host_name(&self) -> Option<&str> {
+        self.attributes.values().find_map(|attribute| match attribute {
+            AttributeInfo::NestHost(attribute) => get_class_name(&self.constant_pool, attribute.host_class_index as usize),
+            _ => None,
+        })
+    }
+
+    /// Returns the names of the classes this nest host declares as members of its nest, from
+    /// its NestMembers attribute, e.g. `java/util/HashMap$Node` for `java/util/HashMap`.
+    ///
+    /// Entries which do not resolve to a CONSTANT_Class are skipped.
+    pub fn nest_member_names(&self) -> Vec<&str> {
+        self.attributes
+            .values()
+            .find_map(|attribute| match attribute {
+                AttributeInfo::NestMembers(attribute) => Some(&attribute.classes),
+                _ => None,
+            })
+            .into_iter()
+            .flatten()
+            .filter_map(|&index| get_class_name(&self.constant_pool, index as usize))
+            .collect()
+    }
+
+    /// Returns the names of the classes and interfaces permitted to extend or implement this
+    /// sealed class, from its PermittedSubclasses attribute, or an empty list if it is not
+    /// sealed.
+    ///
+    /// Entries which do not resolve to a CONSTANT_Class are skipped.
+    pub fn permitted_subclass_names(&self) -> Vec<&str> {
+        self.attributes
+            .values()
+            .find_map(|attribute| match attribute {
+                AttributeInfo::PermittedSubclasses(attribute) => Some(&attribute.classes),
+                _ => None,
+            })
+            .into_iter()
+            .flatten()
+            .filter_map(|&index| get_class_name(&self.constant_pool, index as usize))
+            .collect()
+    }
+
+    /// Returns true if the class has a PermittedSubclasses attribute.
+    pub fn is_sealed(&self) -> bool {
+        self.attributes
+            .values()
+            .any(|attribute| matches!(attribute, AttributeInfo::PermittedSubclasses(_)))
+    }
+
+    /// Returns the attributes of the class with their names resolved, e.g. to find the
+    /// SourceFile attribute with `class_file.named_attributes().get("SourceFile")`.
+    pub fn named_attributes(&self) -> NamedAttributes<'_, 'a> {
+        self.attributes.named(&self.constant_pool)
+    }
+
+    /// Returns the Record attribute of the class, or `None` if it is not a record class.
+    pub fn record(&self) -> Option<&RecordAttribute<'a>> {
+        self.attributes.values().find_map(|attribute| match attribute {
+            AttributeInfo::Record(attribute) => Some(attribute),
+            _ => None,
+        })
+    }
+
+    /// Returns the Module attribute of the class, or `None` if it is not a `module-info` class.
+    pub fn module(&self) -> Option<&ModuleAttribute> {
+        self.attributes.values().find_map(|attribute| match attribute {
+            AttributeInfo::Module(attribute) => Some(attribute),
+            _ => None,
+        })
+    }
+
+    /// Returns the components of a record class from its Record attribute, in declaration
+    /// order, or `None` if it has none.
+    ///
+    /// Components whose name or descriptor does not resolve to a CONSTANT_Utf8 are skipped.
+    pub fn record_components(&self) -> Option<Vec<RecordComponent<'_>>> {
+        let attribute = self.record()?;
+        let components = attribute.components.iter().filter_map(|component| {
+            let visible = component.attributes.values().filter_map(|attribute| match attribute {
+                AttributeInfo::RuntimeVisibleAnnotations(attribute) => Some(&attribute.annotations),
+                _ => None,
+            });
+            let invisible = component.attributes.values().filter_map(|attribute| match attribute {
+                AttributeInfo::RuntimeInvisibleAnnotations(attribute) => Some(&attribute.annotations),
+                _ => None,
+            });
+            Some(RecordComponent {
+                name: get_utf8(&self.constant_pool, component.name_index as usize)?,
+                descriptor: get_utf8(&self.constant_pool, component.descriptor_index as usize)?,
+                signature: signature(&component.attributes, &self.constant_pool),
+                annotations: visible.chain(invisible).flatten().collect(),
+                attributes: &component.attributes,
+            })
+        });
+        Some(components.collect())
+    }
+
+    /// Returns the components of a record class with their annotations resolved, or `None`
+    /// if it has no Record attribute.
+    pub fn record_component_views(&self) -> Option<Vec<RecordComponentView<'_>>> {
+        let components = self.record_components()?;
+        Some(components.iter().map(|component| RecordComponentView::new(component, &self.constant_pool)).collect())
+    }
+
+    /// Returns the descriptor of the canonical constructor of a record class, whose parameters
+    /// are the component types in declaration order, e.g. `(ILjava/lang/String;)V`.
+    pub fn canonical_constructor_descriptor(&self) -> Option<String> {
+        let mut descriptor = String::from("(");
+        for component in self.record_components()? {
+            descriptor.push_str(component.descriptor);
+        }
+        descriptor.push_str(")V");
+        Some(descriptor)
+    }
+
+    /// Returns the canonical constructor of a record class, or `None` if the class is not a
+    /// record or declares no matching `<init>`.
+    pub fn canonical_constructor(&self) -> Option<&MethodInfo<'a>> {
+        self.method("<init>", &self.canonical_constructor_descriptor()?)
+    }
+
+    /// Decodes the attributes of the class, its fields and its methods left undecoded with
+    /// [`ParseOptions::lazy_attributes`](crate::ParseOptions::lazy_attributes).
+    pub fn decode_lazy_attributes(&mut self) -> Result<(), DecodeError> {
+        self.attributes.decode_lazy(&self.constant_pool)?;
+        for field in &mut self.fields {
+            field.attributes.decode_lazy(&self.constant_pool)?;
+        }
+        for method in &mut self.methods {
+            method.attributes.decode_lazy(&self.constant_pool)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the field named `name` with `descriptor`, e.g. `Ljava/lang/String;`.
+    pub fn field(&self, name: &str, descriptor: &str) -> Option<&FieldInfo<'a>> {
+        member_position(&self.fields, &self.constant_pool, name, descriptor).map(|i| &self.fields[i])
+    }
+
+    pub fn field_mut(&mut self, name: &str, descriptor: &str) -> Option<&mut FieldInfo<'a>> {
+        member_position(&self.fields, &self.constant_pool, name, descriptor).map(|i| &mut self.fields[i])
+    }
+
+    /// Returns the method named `name` with `descriptor`, e.g. `([Ljava/lang/String;)V`.
+    pub fn method(&self, name: &str, descriptor: &str) -> Option<&MethodInfo<'a>> {
+        member_position(&self.methods, &self.constant_pool, name, descriptor).map(|i| &self.methods[i])
+    }
+
+    pub fn method_mut(&mut self, name: &str, descriptor: &str) -> Option<&mut MethodInfo<'a>> {
+        member_position(&self.methods, &self.constant_pool, name, descriptor).map(|i| &mut self.methods[i])
+    }
+
+    /// Returns the field named `name` with `descriptor` for in-place manipulation, e.g.
+    /// `class_file.field_entry("count", "I").or_insert(FieldAccessFlag::Private as u16)`.
+    pub fn field_entry(
+        &mut self,
+        name: impl Into<Cow<'a, str>>,
+        descriptor: impl Into<Cow<'a, str>>,
+    ) -> MemberEntry<'_, 'a, FieldInfo<'a>> {
+        member_entry(&mut self.constant_pool, &mut self.fields, name.into(), descriptor.into())
+    }
+
+    /// Returns the method named `name` with `descriptor` for in-place manipulation, e.g.
+    /// `class_file.method_entry("run", "()V").and_modify(|method| method.access_flags |= flag)`.
+    pub fn method_entry(
+        &mut self,
+        name: impl Into<Cow<'a, str>>,
+        descriptor: impl Into<Cow<'a, str>>,
+    ) -> MemberEntry<'_, 'a, MethodInfo<'a>> {
+        member_entry(&mut self.constant_pool, &mut self.methods, name.into(), descriptor.into())
+    }
+
+    /// Returns the fields of the class, which yield their names and descriptors alongside them,
+    /// e.g. `for field in class_file.field_views() { println!("{:?}", field.name) }`.
+    pub fn field_views(&self) -> Members<'_, 'a, FieldInfo<'a>> {
+        Members { constant_pool: &self.constant_pool, members: &self.fields }
+    }
+
+    pub fn field_views_mut(&mut self) -> MembersMut<'_, 'a, FieldInfo<'a>> {
+        MembersMut { constant_pool: &self.constant_pool, members: &mut self.fields }
+    }
+
+    /// Returns the methods of the class, which yield their names and descriptors alongside them.
+    pub fn method_views(&self) -> Members<'_, 'a, MethodInfo<'a>> {
+        Members { constant_pool: &self.constant_pool, members: &self.methods }
+    }
+
+    pub fn method_views_mut(&mut self) -> MembersMut<'_, 'a, MethodInfo<'a>> {
+        MembersMut { constant_pool: &self.constant_pool, members: &mut self.methods }
+    }
+
+    /// Returns the bytes found after the last attribute of the class file.
+    pub fn trailing_bytes(&self) -> &'a [u8] {
+        self.trailing_bytes
+    }
+
+    /// Creates the class file of an empty public class named `Empty`, which extends
+    /// `java.lang.Object` and has no interfaces, fields, methods or attributes.
+    ///
+    /// The result encodes to a class HotSpot loads as is. Generators override the fields they
+    /// need, e.g. `JavaClassFile { constant_pool, this_class, super_class, ..JavaClassFile::empty() }`.
     pub fn empty() -> JavaClassFile<'a> {
+        let mut constant_pool = ConstantPoolBuilder::new();
+        let this_class = constant_pool.class("Empty");
+        let super_class = constant_pool.class("java/lang/Object");
         Self {
             magic: CLASS_FILE_MAGIC,
             minor_version: 0,
-            major_version: 0,
-            constant_pool: Vec::new(),
-            access_flags: 0,
-            this_class: 0,
-            super_class: 0,
+            major_version: 52,
+            constant_pool: constant_pool.build(),
+            access_flags: ClassAccessFlag::Public as u16 | ClassAccessFlag::Super as u16,
+            this_class,
+            super_class,
             interfaces: Vec::new(),
             fields: Vec::new(),
             methods: Vec::new(),
-            attributes: HashMap::new(),
+            attributes: Attributes::new(),
+            trailing_bytes: &[],
         }
     }
 }
 
+fn member_entry<'c, 'a, T: ClassMember>(
+    constant_pool: &'c mut Vec<ConstantPoolInfo<'a>>,
+    members: &'c mut Vec<T>,
+    name: Cow<'a, str>,
+    descriptor: Cow<'a, str>,
+) -> MemberEntry<'c, 'a, T> {
+    match member_position(members, constant_pool, &name, &descriptor) {
+        Some(i) => MemberEntry::Occupied(&mut members[i]),
+        None => MemberEntry::Vacant(VacantMember { constant_pool, members, name, descriptor }),
+    }
+}
+
 /// Decodes this_class or super_class
-pub(crate) fn decode_this_or_super_class(buffer: &[u8]) -> (usize, &[u8]) {
-    let (head, rest) = buffer.split_at(size_of::<u16>());
-    let class_index = read_u16(head) as usize;
-    (class_index, rest)
+pub(crate) fn decode_this_or_super_class(reader: &mut ClassReader) -> Result<usize, DecodeError> {
+    Ok(reader.read_u16("this_class or super_class")? as usize)
 }
 
-/// Decodes interfaces
-pub(crate) fn decode_interfaces(buffer: &[u8]) -> (Vec<usize>, &[u8]) {
-    let (head, rest) = buffer.split_at(size_of::<u16>());
-    let interfaces_count = read_u16(head) as usize;
-    let mut interfaces = Vec::with_capacity(interfaces_count);
+/// The smallest field_info or method_info: access_flags, name_index, descriptor_index and attributes_count.
+const MIN_MEMBER_SIZE: usize = 4 * size_of::<u16>();
+
+/// Decodes interfaces into `interfaces`, replacing its contents but reusing its capacity.
+pub(crate) fn decode_interfaces_into<#[cfg(allocator_api)] A: Allocator>(
+    reader: &mut ClassReader,
+    context: &mut DecodeContext,
+    interfaces: &mut table!(usize),
+) -> Result<(), DecodeError> {
+    let interfaces_count = reader.read_u16("interfaces_count")? as usize;
+    reader.check_count(interfaces_count, size_of::<u16>(), "interfaces")?;
+    context.budget.reserve::<usize>(interfaces_count, "interfaces")?;
+    interfaces.clear();
+    interfaces.reserve(interfaces_count);
 
-    let mut buffer = rest;
     for _ in 0..interfaces_count {
-        let (head, rest) = buffer.split_at(size_of::<u16>());
-        let interface_index = read_u16(head) as usize;
+        let interface_index = reader.read_u16("interfaces")? as usize;
         interfaces.push(interface_index);
-        buffer = rest;
     }
 
-    (interfaces, buffer)
+    Ok(())
 }
 
 /// Decodes fields
-pub(crate) fn decode_fields<'a>(buffer: &'a [u8], constant_pool: &[ConstantPoolInfo]) -> (Vec<FieldInfo<'a>>, &'a [u8]) {
-    let (head, rest) = buffer.split_at(size_of::<u16>());
-    let fields_count = read_u16(head) as usize;
-    let mut fields = Vec::with_capacity(fields_count);
+pub(crate) fn decode_fields<'a>(reader: &mut ClassReader<'a>, constant_pool: &[ConstantPoolInfo], context: &mut DecodeContext) -> Result<Vec<FieldInfo<'a>>, DecodeError> {
+    let mut fields = Vec::new();
+    decode_fields_into(reader, constant_pool, context, &mut fields)?;
+    Ok(fields)
+}
+
+/// Decodes fields into `fields`, replacing its contents but reusing its capacity.
+pub(crate) fn decode_fields_into<'a, #[cfg(allocator_api)] A: Allocator>(
+    reader: &mut ClassReader<'a>,
+    constant_pool: &[ConstantPoolInfo],
+    context: &mut DecodeContext,
+    fields: &mut table!(FieldInfo<'a>),
+) -> Result<(), DecodeError> {
+    let fields_count = reader.read_u16("fields_count")? as usize;
+    reader.check_count(fields_count, MIN_MEMBER_SIZE, "fields")?;
+    context.budget.reserve::<FieldInfo>(fields_count, "fields")?;
+    fields.clear();
+    fields.reserve(fields_count);
     
-    let mut buffer = rest;
-    for _ in 0..fields_count {
-        let (head, rest) = buffer.split_at(size_of::<u16>());
-        let access_flags = read_u16(head);
-        let (head, rest) = rest.split_at(size_of::<u16>());
-        let name_index = read_u16(head) as usize;
-        let (head, rest) = rest.split_at(size_of::<u16>());
-        let descriptor_index = read_u16(head) as usize;
-        let (attributes, rest) = decode_attributes(rest, constant_pool);
+    for i in 0..fields_count {
+        let access_flags = reader.read_u16("field_info")?;
+        let name_index = reader.read_u16("field_info")? as usize;
+        let descriptor_index = reader.read_u16("field_info")? as usize;
+        context.location = format!("fields[{}]", i);
+        let attributes = decode_attributes(reader, constant_pool, context)?;
 
         fields.push(FieldInfo {
             access_flags,
@@ -193,28 +1454,37 @@ pub(crate) fn decode_fields<'a>(buffer: &'a [u8], constant_pool: &[ConstantPoolI
             descriptor_index,
             attributes,
         });
-
-        buffer = rest;
     }
 
-    (fields, buffer)
+    Ok(())
 }
 
 /// Decodes methods
-pub(crate) fn decode_methods<'a>(buffer: &'a [u8], constant_pool: &[ConstantPoolInfo]) -> (Vec<MethodInfo<'a>>, &'a [u8]) {
-    let (head, rest) = buffer.split_at(size_of::<u16>());
-    let methods_count = read_u16(head) as usize;
-    let mut methods = Vec::with_capacity(methods_count);
-
-    let mut buffer = rest;
-    for _ in 0..methods_count {
-        let (head, rest) = buffer.split_at(size_of::<u16>());
-        let access_flags = read_u16(head);
-        let (head, rest) = rest.split_at(size_of::<u16>());
-        let name_index = read_u16(head) as usize;
-        let (head, rest) = rest.split_at(size_of::<u16>());
-        let descriptor_index = read_u16(head) as usize;
-        let (attributes, rest) = decode_attributes(rest, constant_pool);
+pub(crate) fn decode_methods<'a>(reader: &mut ClassReader<'a>, constant_pool: &[ConstantPoolInfo], context: &mut DecodeContext) -> Result<Vec<MethodInfo<'a>>, DecodeError> {
+    let mut methods = Vec::new();
+    decode_methods_into(reader, constant_pool, context, &mut methods)?;
+    Ok(methods)
+}
+
+/// Decodes methods into `methods`, replacing its contents but reusing its capacity.
+pub(crate) fn decode_methods_into<'a, #[cfg(allocator_api)] A: Allocator>(
+    reader: &mut ClassReader<'a>,
+    constant_pool: &[ConstantPoolInfo],
+    context: &mut DecodeContext,
+    methods: &mut table!(MethodInfo<'a>),
+) -> Result<(), DecodeError> {
+    let methods_count = reader.read_u16("methods_count")? as usize;
+    reader.check_count(methods_count, MIN_MEMBER_SIZE, "methods")?;
+    context.budget.reserve::<MethodInfo>(methods_count, "methods")?;
+    methods.clear();
+    methods.reserve(methods_count);
+
+    for i in 0..methods_count {
+        let access_flags = reader.read_u16("method_info")?;
+        let name_index = reader.read_u16("method_info")? as usize;
+        let descriptor_index = reader.read_u16("method_info")? as usize;
+        context.location = format!("methods[{}]", i);
+        let attributes = decode_attributes(reader, constant_pool, context)?;
 
         methods.push(MethodInfo {
             access_flags,
@@ -222,9 +1492,7 @@ pub(crate) fn decode_methods<'a>(buffer: &'a [u8], constant_pool: &[ConstantPool
             descriptor_index,
             attributes,
         });
-
-        buffer = rest;
     }
 
-    (methods, buffer)
+    Ok(())
 }
@@ -0,0 +1,229 @@
+use std::sync::Arc;
+
+use crate::{
+    attributes::decode_code, error::DecodeError, options::{DuplicateAttributePolicy, ParseOptions},
+    reader::ClassReader, types::*, utils::DecodeContext, validate::ValidationMode,
+};
+
+/// A read-only view over the Code attribute of a method.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.3
+#[derive(Debug, Clone)]
+pub struct CodeView<'a, 'c> {
+    constant_pool: &'c [ConstantPoolInfo<'a>],
+    pub max_stack: u16,
+    pub max_locals: u16,
+    pub code: &'a [u8],
+    /// The exception handlers, in class file order.
+    pub exception_table: Vec<ExceptionTableEntry>,
+    source: Source<'a, 'c>,
+}
+
+/// The Code attribute read by a [`CodeView`]: the one decoded with the class, or one the view
+/// decoded from a body kept undecoded.
+#[derive(Debug, Clone)]
+enum Source<'a, 'c> {
+    Borrowed(&'c CodeAttribute<'a>),
+    Decoded(Arc<CodeAttribute<'a>>),
+}
+
+/// A local variable merged from LocalVariableTable and LocalVariableTypeTable.
+///
+/// The variable is live for `start_pc..start_pc + length` in local variable slot `index`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalVariable<'c> {
+    pub start_pc: u16,
+    pub length: u16,
+    pub index: u16,
+    pub name: &'c str,
+    /// Erased field descriptor from LocalVariableTable, e.g. `Ljava/util/List;`.
+    pub descriptor: Option<&'c str>,
+    /// Generic signature from LocalVariableTypeTable, e.g. `Ljava/util/List<Ljava/lang/String;>;`.
+    pub signature: Option<&'c str>,
+}
+
+impl<'a, 'c> CodeView<'a, 'c> {
+    /// Creates a view over the Code attribute of `method`, or returns `None` if the method
+    /// has no code (abstract and native methods).
+    ///
+    /// A Code attribute kept undecoded is decoded leniently, so that a malformed attribute
+    /// within it only fails the methods reading that attribute.
+    pub fn new(
+        method: &'c MethodInfo<'a>,
+        constant_pool: &'c [ConstantPoolInfo<'a>],
+    ) -> Result<Option<Self>, DecodeError> {
+        let body = method.attributes.iter().find_map(|(index, attribute)| {
+            match (get_utf8(constant_pool, *index as usize), attribute) {
+                (Some("Code"), AttributeInfo::Code(code)) => Some(Err(code)),
+                (Some("Code"), AttributeInfo::Unknown(body))
+                | (Some("Code"), AttributeInfo::Lazy(LazyAttribute { data: body, .. })) => Some(Ok(*body)),
+                _ => None,
+            }
+        });
+        let source = match body {
+            Some(Ok(body)) => {
+                let mut context = DecodeContext::new(&ParseOptions::default());
+                context.mode = ValidationMode::Lenient;
+                context.duplicate_attributes = DuplicateAttributePolicy::KeepAll;
+                Source::Decoded(Arc::new(decode_code(&mut ClassReader::new(body), constant_pool, &mut context)?))
+            }
+            Some(Err(code)) => Source::Borrowed(code),
+            None => return Ok(None),
+        };
+
+        let code = source.attribute();
+        Ok(Some(Self {
+            constant_pool,
+            max_stack: code.max_stack,
+            max_locals: code.max_locals,
+            code: code.code,
+            exception_table: code.exception_table.clone(),
+            source,
+        }))
+    }
+
+    /// Returns true if the Code attribute has an attribute named `name`.
+    pub fn has_attribute(&self, name: &str) -> bool {
+        let mut attributes = self.source.attribute().attributes.iter();
+        attributes.any(|(index, _)| get_utf8(self.constant_pool, *index as usize) == Some(name))
+    }
+
+    /// Returns the runtime-visible annotations on types used in this code, e.g. in local
+    /// variable declarations, casts and catch clauses.
+    pub fn visible_type_annotations(&self) -> Result<Vec<TypeAnnotation>, DecodeError> {
+        self.type_annotations("RuntimeVisibleTypeAnnotations")
+    }
+
+    /// Returns the runtime-invisible annotations on types used in this code.
+    pub fn invisible_type_annotations(&self) -> Result<Vec<TypeAnnotation>, DecodeError> {
+        self.type_annotations("RuntimeInvisibleTypeAnnotations")
+    }
+
+    fn type_annotations(&self, name: &str) -> Result<Vec<TypeAnnotation>, DecodeError> {
+        self.tables(name, |attribute| match attribute {
+            AttributeInfo::RuntimeVisibleTypeAnnotations(attribute) => Some(&attribute.annotations),
+            AttributeInfo::RuntimeInvisibleTypeAnnotations(attribute) => Some(&attribute.annotations),
+            _ => None,
+        })
+    }
+
+    /// Returns the local variables of this code, merging the entries of LocalVariableTable
+    /// and LocalVariableTypeTable which describe the same variable, i.e. have the same
+    /// `start_pc` and `index`.
+    ///
+    /// Variables are listed in LocalVariableTable order, followed by the variables found only
+    /// in LocalVariableTypeTable.
+    pub fn locals(&self) -> Result<Vec<LocalVariable<'c>>, DecodeError> {
+        let mut locals: Vec<LocalVariable<'c>> = self
+            .local_variable_table()?
+            .iter()
+            .map(|entry| LocalVariable {
+                start_pc: entry.start_pc,
+                length: entry.length,
+                index: entry.index as u16,
+                name: get_utf8(self.constant_pool, entry.name_index).unwrap_or_default(),
+                descriptor: get_utf8(self.constant_pool, entry.descriptor_index),
+                signature: None,
+            })
+            .collect();
+
+        for entry in self.local_variable_type_table()? {
+            let signature = get_utf8(self.constant_pool, entry.signature_index);
+            let existing = locals.iter_mut().find(|local| {
+                local.start_pc == entry.start_pc && local.index as usize == entry.index && local.signature.is_none()
+            });
+            match existing {
+                Some(local) => local.signature = signature,
+                None => locals.push(LocalVariable {
+                    start_pc: entry.start_pc,
+                    length: entry.length,
+                    index: entry.index as u16,
+                    name: get_utf8(self.constant_pool, entry.name_index).unwrap_or_default(),
+                    descriptor: None,
+                    signature,
+                }),
+            }
+        }
+
+        Ok(locals)
+    }
+
+    /// Returns the entries of the LineNumberTable attributes of this code, in class file
+    /// order. Compilers may split the table over several attributes.
+    pub fn line_numbers(&self) -> Result<Vec<LineNumberTableEntry>, DecodeError> {
+        self.tables("LineNumberTable", |attribute| match attribute {
+            AttributeInfo::LineNumberTable(attribute) => Some(&attribute.line_number_table),
+            _ => None,
+        })
+    }
+
+    /// Returns the source line of the instruction at `pc`, i.e. the line of the entry with
+    /// the greatest `start_pc` not after `pc`, or `None` if there is no such entry.
+    pub fn line_number(&self, pc: u16) -> Result<Option<u16>, DecodeError> {
+        Ok(self
+            .line_numbers()?
+            .iter()
+            .filter(|entry| entry.start_pc <= pc)
+            .max_by_key(|entry| entry.start_pc)
+            .map(|entry| entry.line_number))
+    }
+
+    /// Returns the entries of the LocalVariableTable attributes of this code.
+    pub fn local_variable_table(&self) -> Result<Vec<LocalVariableTableEntry>, DecodeError> {
+        self.tables("LocalVariableTable", |attribute| match attribute {
+            AttributeInfo::LocalVariableTable(attribute) => Some(&attribute.local_variable_table),
+            _ => None,
+        })
+    }
+
+    /// Returns the entries of the LocalVariableTypeTable attributes of this code.
+    pub fn local_variable_type_table(&self) -> Result<Vec<LocalVariableTypeTableEntry>, DecodeError> {
+        self.tables("LocalVariableTypeTable", |attribute| match attribute {
+            AttributeInfo::LocalVariableTypeTable(attribute) => Some(&attribute.local_variable_type_table),
+            _ => None,
+        })
+    }
+
+    /// Returns the entries of the CharacterRangeTable attributes of this code, written by
+    /// javac with `-Xjcov`.
+    pub fn character_ranges(&self) -> Result<Vec<CharacterRangeTableEntry>, DecodeError> {
+        self.tables("CharacterRangeTable", |attribute| match attribute {
+            AttributeInfo::CharacterRangeTable(attribute) => Some(&attribute.character_range_table),
+            _ => None,
+        })
+    }
+
+    /// Concatenates the entries which `entries` finds in the attributes named `name`, decoding
+    /// those kept undecoded.
+    fn tables<T: Clone>(
+        &self,
+        name: &str,
+        entries: impl for<'t> Fn(&'t AttributeInfo<'a>) -> Option<&'t Vec<T>>,
+    ) -> Result<Vec<T>, DecodeError> {
+        let mut tables = Vec::new();
+        for (index, attribute) in self.source.attribute().attributes.iter() {
+            if get_utf8(self.constant_pool, *index as usize) != Some(name) {
+                continue;
+            }
+            let decoded;
+            let attribute = match attribute {
+                AttributeInfo::Unknown(data) | AttributeInfo::Lazy(LazyAttribute { data, .. }) => {
+                    decoded = LazyAttribute { name_index: *index, data }.decode(self.constant_pool)?;
+                    &decoded
+                }
+                attribute => attribute,
+            };
+            tables.extend(entries(attribute).into_iter().flatten().cloned());
+        }
+        Ok(tables)
+    }
+}
+
+impl<'a> Source<'a, '_> {
+    fn attribute(&self) -> &CodeAttribute<'a> {
+        match self {
+            Source::Borrowed(code) => code,
+            Source::Decoded(code) => code,
+        }
+    }
+}
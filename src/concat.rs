@@ -0,0 +1,173 @@
+//! String concatenation compiled to invokedynamic, as javac does since Java 9.
+//!
+//! ref. https://docs.oracle.com/en/java/javase/17/docs/api/java.base/java/lang/invoke/StringConcatFactory.html
+
+use crate::{
+    code::CodeView,
+    opcode::instruction_length,
+    operand::{resolve_operand, ResolvedOperand},
+    types::*,
+};
+
+const STRING_CONCAT_FACTORY: &str = "java/lang/invoke/StringConcatFactory";
+
+/// Marks an argument passed when the call site runs in a recipe.
+const ARGUMENT_TAG: char = '\u{1}';
+
+/// Marks the next constant among the static arguments in a recipe.
+const CONSTANT_TAG: char = '\u{2}';
+
+/// A static argument spliced into a concatenation by a `\u{2}` of the recipe.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConcatConstant<'c> {
+    String(&'c str),
+    Integer(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    /// Index of a constant of another kind, e.g. a CONSTANT_Class.
+    Other(usize),
+}
+
+/// A piece of a string concatenation, in the order it is concatenated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConcatPart<'c> {
+    /// Text of the recipe copied as is.
+    Literal(&'c str),
+    /// A value on the operand stack, numbered from 0 in the order they are pushed.
+    Argument { index: usize, descriptor: &'c str },
+    Constant(ConcatConstant<'c>),
+}
+
+/// An invokedynamic instruction bootstrapped by `StringConcatFactory`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConcatSite<'c> {
+    /// Index of the method in the `methods` of the class file.
+    pub method_index: usize,
+    /// Offset of the invokedynamic instruction in the code of the method.
+    pub pc: usize,
+    /// The recipe of `makeConcatWithConstants`, or `None` for `makeConcat`, which
+    /// concatenates its arguments only.
+    pub recipe: Option<&'c str>,
+    /// Field descriptors of the arguments, e.g. `Ljava/lang/String;` and `I`.
+    pub arguments: Vec<&'c str>,
+    pub parts: Vec<ConcatPart<'c>>,
+}
+
+impl ConcatSite<'_> {
+    /// Returns the concatenation with its literals and constants in place and each argument
+    /// written as `{index}`, e.g. `Hello, {0}! You have {1} messages`.
+    ///
+    /// Constants of other kinds are written as `{#index}` with their constant pool index.
+    /// Braces of the literals are not escaped.
+    pub fn template(&self) -> String {
+        let mut template = String::new();
+        for part in &self.parts {
+            match part {
+                ConcatPart::Literal(text) | ConcatPart::Constant(ConcatConstant::String(text)) => {
+                    template.push_str(text)
+                }
+                ConcatPart::Argument { index, .. } => template.push_str(&format!("{{{}}}", index)),
+                ConcatPart::Constant(ConcatConstant::Integer(value)) => template.push_str(&value.to_string()),
+                ConcatPart::Constant(ConcatConstant::Long(value)) => template.push_str(&value.to_string()),
+                ConcatPart::Constant(ConcatConstant::Float(value)) => template.push_str(&format!("{:?}", value)),
+                ConcatPart::Constant(ConcatConstant::Double(value)) => template.push_str(&format!("{:?}", value)),
+                ConcatPart::Constant(ConcatConstant::Other(index)) => template.push_str(&format!("{{#{}}}", index)),
+            }
+        }
+        template
+    }
+}
+
+/// Lists the string concatenation call sites of a class, in method and then code order.
+///
+/// Call sites whose recipe does not match their arguments and constants are skipped, as are
+/// methods whose code cannot be decoded.
+pub fn concat_sites<'c>(class_file: &'c JavaClassFile) -> Vec<ConcatSite<'c>> {
+    let mut sites = Vec::new();
+    for (method_index, method) in class_file.methods.iter().enumerate() {
+        let Ok(Some(code)) = CodeView::new(method, &class_file.constant_pool) else {
+            continue;
+        };
+        let mut pc = 0;
+        while let Some(length) = instruction_length(code.code, pc) {
+            if let Some(ResolvedOperand::InvokeDynamic(call)) = resolve_operand(class_file, code.code, pc) {
+                if let Some(site) = concat_site(class_file, call) {
+                    sites.push(ConcatSite { method_index, pc, ..site });
+                }
+            }
+            pc += length;
+        }
+    }
+    sites
+}
+
+/// Resolves the invokedynamic call site `call` if it is bootstrapped by `StringConcatFactory`,
+/// leaving `method_index` and `pc` zero.
+fn concat_site<'c>(class_file: &'c JavaClassFile, call: BootstrapCall<'c>) -> Option<ConcatSite<'c>> {
+    let constant_pool = &class_file.constant_pool;
+    let (arguments, _) = split_method_descriptor(call.descriptor)?;
+    let parts = match call.method {
+        (STRING_CONCAT_FACTORY, "makeConcat", _) => {
+            let parts = arguments.iter().enumerate();
+            parts.map(|(index, descriptor)| ConcatPart::Argument { index, descriptor }).collect()
+        }
+        (STRING_CONCAT_FACTORY, "makeConcatWithConstants", _) => {
+            let (recipe, constants) = call.arguments.split_first()?;
+            let ConstantPoolInfo::String(recipe) = constant_pool.get(*recipe)? else {
+                return None;
+            };
+            let recipe = get_utf8(constant_pool, recipe.string_index)?;
+            let constants = constants.iter().map(|index| concat_constant(constant_pool, *index));
+            let parts = parse_recipe(recipe, &arguments, constants.collect::<Option<Vec<_>>>()?)?;
+            return Some(ConcatSite { method_index: 0, pc: 0, recipe: Some(recipe), arguments, parts });
+        }
+        _ => return None,
+    };
+    Some(ConcatSite { method_index: 0, pc: 0, recipe: None, arguments, parts })
+}
+
+fn concat_constant<'c>(constant_pool: &'c [ConstantPoolInfo], index: usize) -> Option<ConcatConstant<'c>> {
+    Some(match constant_pool.get(index)? {
+        ConstantPoolInfo::String(info) => ConcatConstant::String(get_utf8(constant_pool, info.string_index)?),
+        ConstantPoolInfo::Integer(info) => ConcatConstant::Integer(info.data),
+        ConstantPoolInfo::Long(info) => ConcatConstant::Long(info.data),
+        ConstantPoolInfo::Float(info) => ConcatConstant::Float(info.data),
+        ConstantPoolInfo::Double(info) => ConcatConstant::Double(info.data),
+        ConstantPoolInfo::Dummy() => return None,
+        _ => ConcatConstant::Other(index),
+    })
+}
+
+/// Splits `recipe` into its parts, or returns `None` if it refers to more or fewer arguments
+/// or constants than there are.
+fn parse_recipe<'c>(
+    recipe: &'c str,
+    arguments: &[&'c str],
+    constants: Vec<ConcatConstant<'c>>,
+) -> Option<Vec<ConcatPart<'c>>> {
+    let mut parts = Vec::new();
+    let mut constants = constants.into_iter();
+    let mut next_argument = 0;
+    let mut literal_start = 0;
+    for (offset, c) in recipe.char_indices() {
+        if c != ARGUMENT_TAG && c != CONSTANT_TAG {
+            continue;
+        }
+        if literal_start < offset {
+            parts.push(ConcatPart::Literal(&recipe[literal_start..offset]));
+        }
+        literal_start = offset + c.len_utf8();
+        if c == ARGUMENT_TAG {
+            let descriptor = arguments.get(next_argument)?;
+            parts.push(ConcatPart::Argument { index: next_argument, descriptor });
+            next_argument += 1;
+        } else {
+            parts.push(ConcatPart::Constant(constants.next()?));
+        }
+    }
+    if literal_start < recipe.len() {
+        parts.push(ConcatPart::Literal(&recipe[literal_start..]));
+    }
+    (next_argument == arguments.len() && constants.next().is_none()).then_some(parts)
+}
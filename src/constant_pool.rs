@@ -1,4 +1,12 @@
-use crate::utils::*;
+use std::borrow::Cow;
+
+use crate::{
+    diagnostics::DiagnosticKind,
+    error::DecodeError,
+    names::{check_class_name, NameError},
+    reader::ClassReader,
+    utils::*,
+};
 
 /// Constant pool kinds as defined in the JVM specification.
 /// 
@@ -60,7 +68,14 @@ pub enum ConstantKind {
 
 impl From<u8> for ConstantKind {
     fn from(value: u8) -> Self {
-        match value {
+        ConstantKind::from_tag(value).expect("Unknown ConstantKind value")
+    }
+}
+
+impl ConstantKind {
+    /// Returns the kind of a constant pool tag, or `None` if the tag is not defined.
+    pub fn from_tag(value: u8) -> Option<Self> {
+        let kind = match value {
             1 => ConstantKind::Utf8,
             3 => ConstantKind::Integer,
             4 => ConstantKind::Float,
@@ -78,7 +93,18 @@ impl From<u8> for ConstantKind {
             18 => ConstantKind::InvokeDynamic,
             19 => ConstantKind::Module,
             20 => ConstantKind::Package,
-            _ => panic!("Unknown ConstantKind value"),
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    /// Returns the first class file major version in which this kind may appear.
+    pub fn since_major_version(&self) -> u16 {
+        match self {
+            ConstantKind::MethodHandle | ConstantKind::MethodType | ConstantKind::InvokeDynamic => 51,
+            ConstantKind::Module | ConstantKind::Package => 53,
+            ConstantKind::Dynamic => 55,
+            _ => 45,
         }
     }
 }
@@ -86,7 +112,7 @@ impl From<u8> for ConstantKind {
 /// Represents a constant pool entry in a Java class file.
 /// 
 /// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.4-210
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ConstantPoolInfo<'a> {
     Dummy(),
     /// CONSTANT_Class (tag: 7)
@@ -228,13 +254,41 @@ pub struct ConstantNameAndTypeInfo {
 
 /// CONSTANT_Utf8 (tag: 1)
 /// since: class file format 45.3 (Java 1.0.2)
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///
+/// `length` is the number of bytes in the class file. `data` borrows from the class file
+/// unless the bytes use the modified UTF-8 forms (encoded NUL, surrogate pairs).
+///
+/// `bytes` keeps the bytes a decoded constant was read from, which are written back as long as
+/// `data` is unchanged, so that bytes a string cannot hold, e.g. lone surrogates, survive a
+/// round trip. Constants built in memory have none. Two constants are equal if they encode to
+/// the same bytes.
+#[derive(Debug, Clone)]
 pub struct ConstantUtf8Info<'a> {
     pub tag: ConstantKind,
     pub length: usize,
-    pub data: &'a str,
+    pub data: Cow<'a, str>,
+    pub bytes: Option<Cow<'a, [u8]>>,
 }
 
+impl ConstantUtf8Info<'_> {
+    /// Returns the bytes the constant is encoded to: those it was decoded from if `data` still
+    /// reads from them, and the modified UTF-8 encoding of `data` otherwise.
+    pub fn encoded(&self) -> Cow<'_, [u8]> {
+        match &self.bytes {
+            Some(bytes) if read_modified_utf8(bytes) == self.data => Cow::Borrowed(bytes),
+            _ => to_modified_utf8(&self.data),
+        }
+    }
+}
+
+impl PartialEq for ConstantUtf8Info<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.tag == other.tag && self.data == other.data && self.encoded() == other.encoded()
+    }
+}
+
+impl Eq for ConstantUtf8Info<'_> {}
+
 /// CONSTANT_MethodHandle (tag: 15)
 /// since: class file format 51.0 (Java 7)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -287,383 +341,554 @@ pub struct ConstantPackageInfo {
 }
 
 /// Decodes ConstantClassInfo
-fn decode_class_info(buffer: &[u8]) -> (ConstantClassInfo, &[u8]) {
-    let (head, rest) = buffer.split_at(size_of::<u16>());
-    let name_index = read_u16(head) as usize;
-    (
-        ConstantClassInfo {
-            tag: ConstantKind::Class,
-            name_index,
-        },
-        rest,
-    )
+fn decode_class_info(reader: &mut ClassReader) -> Result<ConstantClassInfo, DecodeError> {
+    let name_index = reader.read_u16("CONSTANT_Class")? as usize;
+    Ok(ConstantClassInfo {
+        tag: ConstantKind::Class,
+        name_index,
+    })
 }
 
 /// Decodes ConstantFieldRefInfo
-fn decode_field_ref_info(buffer: &[u8]) -> (ConstantFieldRefInfo, &[u8]) {
-    let (head, rest) = buffer.split_at(size_of::<u16>());
-    let class_index = read_u16(head) as usize;
-    let (head, rest) = rest.split_at(size_of::<u16>());
-    let name_and_type_index = read_u16(head) as usize;
-    (
-        ConstantFieldRefInfo {
-            tag: ConstantKind::FieldRef,
-            class_index,
-            name_and_type_index,
-        },
-        rest,
-    )
+fn decode_field_ref_info(reader: &mut ClassReader) -> Result<ConstantFieldRefInfo, DecodeError> {
+    let class_index = reader.read_u16("CONSTANT_Fieldref")? as usize;
+    let name_and_type_index = reader.read_u16("CONSTANT_Fieldref")? as usize;
+    Ok(ConstantFieldRefInfo {
+        tag: ConstantKind::FieldRef,
+        class_index,
+        name_and_type_index,
+    })
 }
 
 /// Decodes ConstantMethodRefInfo
-fn decode_method_ref_info(buffer: &[u8]) -> (ConstantMethodRefInfo, &[u8]) {
-    let (head, rest) = buffer.split_at(size_of::<u16>());
-    let class_index = read_u16(head) as usize;
-    let (head, rest) = rest.split_at(size_of::<u16>());
-    let name_and_type_index = read_u16(head) as usize;
-    (
-        ConstantMethodRefInfo {
-            tag: ConstantKind::MethodRef,
-            class_index,
-            name_and_type_index,
-        },
-        rest
-    )
+fn decode_method_ref_info(reader: &mut ClassReader) -> Result<ConstantMethodRefInfo, DecodeError> {
+    let class_index = reader.read_u16("CONSTANT_Methodref")? as usize;
+    let name_and_type_index = reader.read_u16("CONSTANT_Methodref")? as usize;
+    Ok(ConstantMethodRefInfo {
+        tag: ConstantKind::MethodRef,
+        class_index,
+        name_and_type_index,
+    })
 }
 
 /// Decodes ConstantInterfaceMethodRefInfo
-fn decode_interface_method_ref_info(buffer: &[u8]) -> (ConstantInterfaceMethodRefInfo, &[u8]) {
-    let (head, rest) = buffer.split_at(size_of::<u16>());
-    let class_index = read_u16(head) as usize;
-    let (head, rest) = rest.split_at(size_of::<u16>());
-    let name_and_type_index = read_u16(head) as usize;
-    (
-        ConstantInterfaceMethodRefInfo {
-            tag: ConstantKind::InterfaceMethodRef,
-            class_index,
-            name_and_type_index,
-        },
-        rest
-    )
+fn decode_interface_method_ref_info(reader: &mut ClassReader) -> Result<ConstantInterfaceMethodRefInfo, DecodeError> {
+    let class_index = reader.read_u16("CONSTANT_InterfaceMethodref")? as usize;
+    let name_and_type_index = reader.read_u16("CONSTANT_InterfaceMethodref")? as usize;
+    Ok(ConstantInterfaceMethodRefInfo {
+        tag: ConstantKind::InterfaceMethodRef,
+        class_index,
+        name_and_type_index,
+    })
 }
 
 /// Decodes ConstantStringInfo
-fn decode_string_info(buffer: &[u8]) -> (ConstantStringInfo, &[u8]) {
-    let (head, rest) = buffer.split_at(size_of::<u16>());
-    let string_index = read_u16(head) as usize;
-    (
-        ConstantStringInfo {
-            tag: ConstantKind::String,
-            string_index,
-        },
-        rest,
-    )
+fn decode_string_info(reader: &mut ClassReader) -> Result<ConstantStringInfo, DecodeError> {
+    let string_index = reader.read_u16("CONSTANT_String")? as usize;
+    Ok(ConstantStringInfo {
+        tag: ConstantKind::String,
+        string_index,
+    })
 }
 
 /// Decodes ConstantIntegerInfo
-fn decode_integer_info(buffer: &[u8]) -> (ConstantIntegerInfo, &[u8]) {
-    let (head, rest) = buffer.split_at(size_of::<i32>());
-    let data = read_i32(head);
-    (
-        ConstantIntegerInfo {
-            tag: ConstantKind::Integer,
-            data,
-        },
-        rest,
-    )
+fn decode_integer_info(reader: &mut ClassReader) -> Result<ConstantIntegerInfo, DecodeError> {
+    let data = reader.read_i32("CONSTANT_Integer")?;
+    Ok(ConstantIntegerInfo {
+        tag: ConstantKind::Integer,
+        data,
+    })
 }
 
 /// Decodes ConstantFloatInfo
-fn decode_float_info(buffer: &[u8]) -> (ConstantFloatInfo, &[u8]) {
-    let (head, rest) = buffer.split_at(size_of::<f32>());
-    let data = read_f32(head);
-    (
-        ConstantFloatInfo {
-            tag: ConstantKind::Float,
-            data,
-        },
-        rest,
-    )
+fn decode_float_info(reader: &mut ClassReader) -> Result<ConstantFloatInfo, DecodeError> {
+    let data = reader.read_f32("CONSTANT_Float")?;
+    Ok(ConstantFloatInfo {
+        tag: ConstantKind::Float,
+        data,
+    })
 }
+
 /// Decodes ConstantLongInfo
-fn decode_long_info(buffer: &[u8]) -> (ConstantLongInfo, &[u8]) {
-    let (head, rest) = buffer.split_at(size_of::<i64>());
-    let data = read_i64(head);
-    (
-        ConstantLongInfo {
-            tag: ConstantKind::Long,
-            data,
-        },
-        rest,
-    )
+fn decode_long_info(reader: &mut ClassReader) -> Result<ConstantLongInfo, DecodeError> {
+    let data = reader.read_i64("CONSTANT_Long")?;
+    Ok(ConstantLongInfo {
+        tag: ConstantKind::Long,
+        data,
+    })
 }
 
 /// Decodes ConstantDoubleInfo
-fn decode_double_info(buffer: &[u8]) -> (ConstantDoubleInfo, &[u8]) {
-    let (head, rest) = buffer.split_at(size_of::<f64>());
-    let data = read_f64(head);
-    (
-        ConstantDoubleInfo {
-            tag: ConstantKind::Double,
-            data,
-        },
-        rest,
-    )
+fn decode_double_info(reader: &mut ClassReader) -> Result<ConstantDoubleInfo, DecodeError> {
+    let data = reader.read_f64("CONSTANT_Double")?;
+    Ok(ConstantDoubleInfo {
+        tag: ConstantKind::Double,
+        data,
+    })
 }
 
 /// Decodes ConstantNameAndTypeInfo
-fn decode_name_and_type_info(buffer: &[u8]) -> (ConstantNameAndTypeInfo, &[u8]) {
-    let (head, rest) = buffer.split_at(size_of::<u16>());
-    let name_index = read_u16(head) as usize;
-    let (head, rest) = rest.split_at(size_of::<u16>());
-    let descriptor_index = read_u16(head) as usize;
-    (
-        ConstantNameAndTypeInfo {
-            tag: ConstantKind::NameAndType,
-            name_index,
-            descriptor_index,
-        },
-        rest,
-    )
+fn decode_name_and_type_info(reader: &mut ClassReader) -> Result<ConstantNameAndTypeInfo, DecodeError> {
+    let name_index = reader.read_u16("CONSTANT_NameAndType")? as usize;
+    let descriptor_index = reader.read_u16("CONSTANT_NameAndType")? as usize;
+    Ok(ConstantNameAndTypeInfo {
+        tag: ConstantKind::NameAndType,
+        name_index,
+        descriptor_index,
+    })
 }
 
 /// Decodes ConstantUtf8Info
-fn decode_utf8_info<'a>(buffer: &'a [u8]) -> (ConstantUtf8Info<'a>, &'a [u8]) {
-    let (head, rest) = buffer.split_at(size_of::<u16>());
-    let length = read_u16(head) as usize;
-    let (head, rest) = rest.split_at(length);
-    let data = read_str(head);
-    (
-        ConstantUtf8Info {
-            tag: ConstantKind::Utf8,
-            length,
-            data,
-        },
-        rest,
-    )
+fn decode_utf8_info<'a>(reader: &mut ClassReader<'a>, context: &mut DecodeContext) -> Result<ConstantUtf8Info<'a>, DecodeError> {
+    let length = reader.read_u16("CONSTANT_Utf8")? as usize;
+    let bytes = reader.read_bytes(length, "CONSTANT_Utf8")?;
+    // Converting modified UTF-8 allocates at most 3 bytes per input byte.
+    if std::str::from_utf8(bytes).is_err() {
+        context.budget.reserve::<u8>(length.checked_mul(3).ok_or(DecodeError::SizeOverflow { while_parsing: "CONSTANT_Utf8" })?, "CONSTANT_Utf8")?;
+    }
+    let data = read_modified_utf8(bytes);
+    Ok(ConstantUtf8Info {
+        tag: ConstantKind::Utf8,
+        length,
+        data,
+        bytes: Some(Cow::Borrowed(bytes)),
+    })
 }
 
 /// Decodes ConstantMethodHandleInfo
-fn decode_method_handle_info(buffer: &[u8]) -> (ConstantMethodHandleInfo, &[u8]) {
-    let (head, rest) = buffer.split_at(1);
-    let reference_kind = head[0];
-    let (head, rest) = rest.split_at(size_of::<u16>());
-    let reference_index = read_u16(head) as usize;
-    (
-        ConstantMethodHandleInfo {
-            tag: ConstantKind::MethodHandle,
-            reference_kind,
-            reference_index,
-        },
-        rest,
-    )
+fn decode_method_handle_info(reader: &mut ClassReader) -> Result<ConstantMethodHandleInfo, DecodeError> {
+    let reference_kind = reader.read_u8("CONSTANT_MethodHandle")?;
+    let reference_index = reader.read_u16("CONSTANT_MethodHandle")? as usize;
+    Ok(ConstantMethodHandleInfo {
+        tag: ConstantKind::MethodHandle,
+        reference_kind,
+        reference_index,
+    })
 }
 
 /// Decodes ConstantMethodTypeInfo
-fn decode_method_type_info(buffer: &[u8]) -> (ConstantMethodTypeInfo, &[u8]) {
-    let (head, rest) = buffer.split_at(size_of::<u16>());
-    let descriptor_index = read_u16(head) as usize;
-    (
-        ConstantMethodTypeInfo {
-            tag: ConstantKind::MethodType,
-            descriptor_index,
-        },
-        rest,
-    )
+fn decode_method_type_info(reader: &mut ClassReader) -> Result<ConstantMethodTypeInfo, DecodeError> {
+    let descriptor_index = reader.read_u16("CONSTANT_MethodType")? as usize;
+    Ok(ConstantMethodTypeInfo {
+        tag: ConstantKind::MethodType,
+        descriptor_index,
+    })
 }
 
 /// Decodes ConstantDynamicInfo
-fn decode_dynamic_info(buffer: &[u8]) -> (ConstantDynamicInfo, &[u8]) {
-    let (head, rest) = buffer.split_at(size_of::<u16>());
-    let bootstrap_method_handle_attr_index = read_u16(head) as usize;
-    let (head, rest) = rest.split_at(size_of::<u16>());
-    let name_and_type_index = read_u16(head) as usize;
-    (
-        ConstantDynamicInfo {
-            tag: ConstantKind::Dynamic,
-            bootstrap_method_handle_attr_index,
-            name_and_type_index,
-        },
-        rest,
-    )
+fn decode_dynamic_info(reader: &mut ClassReader) -> Result<ConstantDynamicInfo, DecodeError> {
+    let bootstrap_method_handle_attr_index = reader.read_u16("CONSTANT_Dynamic")? as usize;
+    let name_and_type_index = reader.read_u16("CONSTANT_Dynamic")? as usize;
+    Ok(ConstantDynamicInfo {
+        tag: ConstantKind::Dynamic,
+        bootstrap_method_handle_attr_index,
+        name_and_type_index,
+    })
 }
 
 /// Decodes ConstantInvokeDynamicInfo
-fn decode_invoke_dynamic_info(buffer: &[u8]) -> (ConstantInvokeDynamicInfo, &[u8]) {
-    let (head, rest) = buffer.split_at(size_of::<u16>());
-    let bootstrap_method_attr_index = read_u16(head) as usize;
-    let (head, rest) = rest.split_at(size_of::<u16>());
-    let name_and_type_index = read_u16(head) as usize;
-    (
-        ConstantInvokeDynamicInfo {
-            tag: ConstantKind::InvokeDynamic,
-            bootstrap_method_attr_index,
-            name_and_type_index,
-        },
-        rest,
-    )
+fn decode_invoke_dynamic_info(reader: &mut ClassReader) -> Result<ConstantInvokeDynamicInfo, DecodeError> {
+    let bootstrap_method_attr_index = reader.read_u16("CONSTANT_InvokeDynamic")? as usize;
+    let name_and_type_index = reader.read_u16("CONSTANT_InvokeDynamic")? as usize;
+    Ok(ConstantInvokeDynamicInfo {
+        tag: ConstantKind::InvokeDynamic,
+        bootstrap_method_attr_index,
+        name_and_type_index,
+    })
 }
 
 /// Decodes ConstantModuleInfo
-fn decode_module_info(buffer: &[u8]) -> (ConstantModuleInfo, &[u8]) {
-    let (head, rest) = buffer.split_at(size_of::<u16>());
-    let name_index = read_u16(head) as usize;
-    (
-        ConstantModuleInfo {
-            tag: ConstantKind::Module,
-            name_index,
-        },
-        rest,
-    )
+fn decode_module_info(reader: &mut ClassReader) -> Result<ConstantModuleInfo, DecodeError> {
+    let name_index = reader.read_u16("CONSTANT_Module")? as usize;
+    Ok(ConstantModuleInfo {
+        tag: ConstantKind::Module,
+        name_index,
+    })
 }
 
 /// Decodes ConstantPackageInfo
-fn decode_package_info(buffer: &[u8]) -> (ConstantPackageInfo, &[u8]) {
-    let (head, rest) = buffer.split_at(size_of::<u16>());
-    let name_index = read_u16(head) as usize;
-    (
-        ConstantPackageInfo {
-            tag: ConstantKind::Package,
-            name_index,
-        },
-        rest,
-    )
+fn decode_package_info(reader: &mut ClassReader) -> Result<ConstantPackageInfo, DecodeError> {
+    let name_index = reader.read_u16("CONSTANT_Package")? as usize;
+    Ok(ConstantPackageInfo {
+        tag: ConstantKind::Package,
+        name_index,
+    })
 }
 
-/// Decodes a constant pool.
-pub(crate) fn decode_constant_pool(buffer: &[u8]) -> (Vec<ConstantPoolInfo>, &[u8]) {
-    let (head, rest) = buffer.split_at(size_of::<u16>());
-    let count = read_u16(head) as usize;
+/// The smallest constant pool entry: a tag and a u16 index.
+const MIN_CONSTANT_SIZE: usize = 3;
 
-    let mut constants = Vec::with_capacity(count);
+/// Decodes a constant pool.
+pub(crate) fn decode_constant_pool<'a>(reader: &mut ClassReader<'a>, context: &mut DecodeContext) -> Result<Vec<ConstantPoolInfo<'a>>, DecodeError> {
+    let mut constants = Vec::new();
+    decode_constant_pool_into(reader, context, &mut constants)?;
+    Ok(constants)
+}
+
+/// Decodes a constant pool into `constants`, replacing its contents but reusing its capacity.
+pub(crate) fn decode_constant_pool_into<'a, #[cfg(allocator_api)] A: Allocator>(
+    reader: &mut ClassReader<'a>,
+    context: &mut DecodeContext,
+    constants: &mut table!(ConstantPoolInfo<'a>),
+) -> Result<(), DecodeError> {
+    let count = reader.read_u16("constant_pool_count")? as usize;
+    reader.check_count(count.saturating_sub(1), MIN_CONSTANT_SIZE, "constant_pool")?;
+    context.budget.reserve::<ConstantPoolInfo>(count, "constant_pool")?;
+
+    constants.clear();
+    constants.reserve(count);
     constants.push(ConstantPoolInfo::Dummy());
 
     let mut i = 1;
-    let mut buffer = rest;
 
     while i < count {
-        let (head, rest) = buffer.split_at(1);
-        let tag_byte = head[0];
-        let tag = ConstantKind::from(tag_byte);
+        let tag_byte = reader.read_u8("constant_pool")?;
+        let tag = ConstantKind::from_tag(tag_byte).ok_or(DecodeError::UnknownConstantTag { index: i, tag: tag_byte })?;
 
         match tag {
             ConstantKind::Class => {
-                let (info, rest) = decode_class_info(rest);
+                let info = decode_class_info(reader)?;
                 constants.push(ConstantPoolInfo::Class(info));
-                buffer = rest;
             }
 
             ConstantKind::FieldRef => {
-                let (info, rest) = decode_field_ref_info(rest);
+                let info = decode_field_ref_info(reader)?;
                 constants.push(ConstantPoolInfo::FieldRef(info));
-                buffer = rest;
             }
 
             ConstantKind::MethodRef => {
-                let (info, rest) = decode_method_ref_info(rest);
+                let info = decode_method_ref_info(reader)?;
                 constants.push(ConstantPoolInfo::MethodRef(info));
-                buffer = rest;
             }
 
             ConstantKind::InterfaceMethodRef => {
-                let (info, rest) = decode_interface_method_ref_info(rest);
+                let info = decode_interface_method_ref_info(reader)?;
                 constants.push(ConstantPoolInfo::InterfaceMethodRef(info));
-                buffer = rest;
             }
 
             ConstantKind::String => {
-                let (info, rest) = decode_string_info(rest);
+                let info = decode_string_info(reader)?;
                 constants.push(ConstantPoolInfo::String(info));
-                buffer = rest;
             }
 
             ConstantKind::Integer => {
-                let (info, rest) = decode_integer_info(rest);
+                let info = decode_integer_info(reader)?;
                 constants.push(ConstantPoolInfo::Integer(info));
-                buffer = rest;
             }
 
             ConstantKind::Float => {
-                let (info, rest) = decode_float_info(rest);
+                let info = decode_float_info(reader)?;
                 constants.push(ConstantPoolInfo::Float(info));
-                buffer = rest;
             }
 
             ConstantKind::Long => {
-                let (info, rest) = decode_long_info(rest);
+                let info = decode_long_info(reader)?;
                 constants.push(ConstantPoolInfo::Long(info));
                 constants.push(ConstantPoolInfo::Dummy());
-                buffer = rest;
                 i += 1;
             }
 
             ConstantKind::Double => {
-                let (info, rest) = decode_double_info(rest);
+                let info = decode_double_info(reader)?;
                 constants.push(ConstantPoolInfo::Double(info));
                 constants.push(ConstantPoolInfo::Dummy());
-                buffer = rest;
                 i += 1;
             }
 
             ConstantKind::NameAndType => {
-                let (info, rest) = decode_name_and_type_info(rest);
+                let info = decode_name_and_type_info(reader)?;
                 constants.push(ConstantPoolInfo::NameAndType(info));
-                buffer = rest;
             }
 
             ConstantKind::Utf8 => {
-                let (info, rest) = decode_utf8_info(rest);
+                let info = decode_utf8_info(reader, context)?;
+                if let Some(offset) = info.bytes.as_deref().and_then(modified_utf8_error) {
+                    context.diagnostics.push(
+                        DiagnosticKind::MalformedUtf8,
+                        format!("constant_pool[{}]", i),
+                        format!("byte {} is not valid modified UTF-8", offset),
+                    );
+                }
                 constants.push(ConstantPoolInfo::Utf8(info));
-                buffer = rest;
             }
 
             ConstantKind::MethodHandle => {
-                let (info, rest) = decode_method_handle_info(rest);
+                let info = decode_method_handle_info(reader)?;
                 constants.push(ConstantPoolInfo::MethodHandle(info));
-                buffer = rest;
             }
 
             ConstantKind::MethodType => {
-                let (info, rest) = decode_method_type_info(rest);
+                let info = decode_method_type_info(reader)?;
                 constants.push(ConstantPoolInfo::MethodType(info));
-                buffer = rest;
             }
 
             ConstantKind::Dynamic => {
-                let (info, rest) = decode_dynamic_info(rest);
+                let info = decode_dynamic_info(reader)?;
                 constants.push(ConstantPoolInfo::Dynamic(info));
-                buffer = rest;
             }
 
             ConstantKind::InvokeDynamic => {
-                let (info, rest) = decode_invoke_dynamic_info(rest);
+                let info = decode_invoke_dynamic_info(reader)?;
                 constants.push(ConstantPoolInfo::InvokeDynamic(info));
-                buffer = rest;
             }
 
             ConstantKind::Module => {
-                let (info, rest) = decode_module_info(rest);
+                let info = decode_module_info(reader)?;
                 constants.push(ConstantPoolInfo::Module(info));
-                buffer = rest;
             }
 
             ConstantKind::Package => {
-                let (info, rest) = decode_package_info(rest);
+                let info = decode_package_info(reader)?;
                 constants.push(ConstantPoolInfo::Package(info));
-                buffer = rest;
             }
         }
 
         i += 1;
     }
 
-    (constants, buffer)
+    Ok(())
+}
+
+/// Builds a constant pool, reusing identical entries.
+#[derive(Debug, Clone)]
+pub struct ConstantPoolBuilder<'a> {
+    constants: Vec<ConstantPoolInfo<'a>>,
+}
+
+impl Default for ConstantPoolBuilder<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> ConstantPoolBuilder<'a> {
+    /// Creates a builder holding only the unusable entry at index 0.
+    pub fn new() -> Self {
+        Self {
+            constants: vec![ConstantPoolInfo::Dummy()],
+        }
+    }
+
+    /// Creates a builder holding a copy of `constant_pool`, which new entries are appended to.
+    pub fn extending(constant_pool: &[ConstantPoolInfo<'a>]) -> Self {
+        let mut constants = constant_pool.to_vec();
+        if constants.is_empty() {
+            constants.push(ConstantPoolInfo::Dummy());
+        }
+        Self { constants }
+    }
+
+    /// Returns the constant pool built so far.
+    pub fn constants(&self) -> &[ConstantPoolInfo<'a>] {
+        &self.constants
+    }
+
+    /// Adds an entry unless an identical one exists, and returns its index.
+    fn add(&mut self, info: ConstantPoolInfo<'a>) -> usize {
+        if let Some(index) = self.constants.iter().skip(1).position(|constant| *constant == info) {
+            return index + 1;
+        }
+        let wide = matches!(info, ConstantPoolInfo::Long(_) | ConstantPoolInfo::Double(_));
+        self.constants.push(info);
+        if wide {
+            self.constants.push(ConstantPoolInfo::Dummy());
+            return self.constants.len() - 2;
+        }
+        self.constants.len() - 1
+    }
+
+    /// Adds a CONSTANT_Utf8.
+    pub fn utf8(&mut self, data: impl Into<Cow<'a, str>>) -> usize {
+        let data = data.into();
+        let length = to_modified_utf8(&data).len();
+        self.add(ConstantPoolInfo::Utf8(ConstantUtf8Info {
+            tag: ConstantKind::Utf8,
+            length,
+            data,
+            bytes: None,
+        }))
+    }
+
+    /// Adds a CONSTANT_Class with a name in internal form (e.g. `java/lang/Object`).
+    pub fn class(&mut self, name: impl Into<Cow<'a, str>>) -> usize {
+        let name_index = self.utf8(name);
+        self.add(ConstantPoolInfo::Class(ConstantClassInfo {
+            tag: ConstantKind::Class,
+            name_index,
+        }))
+    }
+
+    /// Adds a CONSTANT_Class like [`class`](Self::class), but rejects a name which is neither
+    /// a binary class name in internal form nor an array descriptor, e.g. `java.lang.Object`.
+    pub fn checked_class(&mut self, name: impl Into<Cow<'a, str>>) -> Result<usize, NameError> {
+        let name = name.into();
+        check_class_name(&name)?;
+        Ok(self.class(name))
+    }
+
+    /// Adds a CONSTANT_String.
+    pub fn string(&mut self, data: impl Into<Cow<'a, str>>) -> usize {
+        let string_index = self.utf8(data);
+        self.add(ConstantPoolInfo::String(ConstantStringInfo {
+            tag: ConstantKind::String,
+            string_index,
+        }))
+    }
+
+    /// Adds a CONSTANT_Integer.
+    pub fn integer(&mut self, data: i32) -> usize {
+        self.add(ConstantPoolInfo::Integer(ConstantIntegerInfo {
+            tag: ConstantKind::Integer,
+            data,
+        }))
+    }
+
+    /// Adds a CONSTANT_Float.
+    pub fn float(&mut self, data: f32) -> usize {
+        self.add(ConstantPoolInfo::Float(ConstantFloatInfo {
+            tag: ConstantKind::Float,
+            data,
+        }))
+    }
+
+    /// Adds a CONSTANT_Long, which occupies two entries.
+    pub fn long(&mut self, data: i64) -> usize {
+        self.add(ConstantPoolInfo::Long(ConstantLongInfo {
+            tag: ConstantKind::Long,
+            data,
+        }))
+    }
+
+    /// Adds a CONSTANT_Double, which occupies two entries.
+    pub fn double(&mut self, data: f64) -> usize {
+        self.add(ConstantPoolInfo::Double(ConstantDoubleInfo {
+            tag: ConstantKind::Double,
+            data,
+        }))
+    }
+
+    /// Adds a CONSTANT_NameAndType.
+    pub fn name_and_type(&mut self, name: impl Into<Cow<'a, str>>, descriptor: impl Into<Cow<'a, str>>) -> usize {
+        let name_index = self.utf8(name);
+        let descriptor_index = self.utf8(descriptor);
+        self.add(ConstantPoolInfo::NameAndType(ConstantNameAndTypeInfo {
+            tag: ConstantKind::NameAndType,
+            name_index,
+            descriptor_index,
+        }))
+    }
+
+    /// Adds a CONSTANT_FieldRef.
+    pub fn field_ref(
+        &mut self,
+        class: impl Into<Cow<'a, str>>,
+        name: impl Into<Cow<'a, str>>,
+        descriptor: impl Into<Cow<'a, str>>,
+    ) -> usize {
+        let class_index = self.class(class);
+        let name_and_type_index = self.name_and_type(name, descriptor);
+        self.add(ConstantPoolInfo::FieldRef(ConstantFieldRefInfo {
+            tag: ConstantKind::FieldRef,
+            class_index,
+            name_and_type_index,
+        }))
+    }
+
+    /// Adds a CONSTANT_MethodRef.
+    pub fn method_ref(
+        &mut self,
+        class: impl Into<Cow<'a, str>>,
+        name: impl Into<Cow<'a, str>>,
+        descriptor: impl Into<Cow<'a, str>>,
+    ) -> usize {
+        let class_index = self.class(class);
+        let name_and_type_index = self.name_and_type(name, descriptor);
+        self.add(ConstantPoolInfo::MethodRef(ConstantMethodRefInfo {
+            tag: ConstantKind::MethodRef,
+            class_index,
+            name_and_type_index,
+        }))
+    }
+
+    /// Adds a CONSTANT_InterfaceMethodRef.
+    pub fn interface_method_ref(
+        &mut self,
+        class: impl Into<Cow<'a, str>>,
+        name: impl Into<Cow<'a, str>>,
+        descriptor: impl Into<Cow<'a, str>>,
+    ) -> usize {
+        let class_index = self.class(class);
+        let name_and_type_index = self.name_and_type(name, descriptor);
+        self.add(ConstantPoolInfo::InterfaceMethodRef(ConstantInterfaceMethodRefInfo {
+            tag: ConstantKind::InterfaceMethodRef,
+            class_index,
+            name_and_type_index,
+        }))
+    }
+
+    /// Returns the constant pool.
+    pub fn build(self) -> Vec<ConstantPoolInfo<'a>> {
+        self.constants
+    }
+}
+
+/// Returns the string of the CONSTANT_Utf8 at `index`, or `None` if the entry is missing or
+/// of another kind.
+pub fn get_utf8<'c>(constant_pool: &'c [ConstantPoolInfo], index: usize) -> Option<&'c str> {
+    match constant_pool.get(index) {
+        Some(ConstantPoolInfo::Utf8(utf8_info)) => Some(utf8_info.data.as_ref()),
+        _ => None,
+    }
+}
+
+/// Returns the name of the CONSTANT_Class at `index`, or `None` if the entry is missing or
+/// of another kind.
+pub fn get_class_name<'c>(constant_pool: &'c [ConstantPoolInfo], index: usize) -> Option<&'c str> {
+    match constant_pool.get(index) {
+        Some(ConstantPoolInfo::Class(class_info)) => get_utf8(constant_pool, class_info.name_index),
+        _ => None,
+    }
+}
+
+/// Returns the owner class name, name and descriptor of the CONSTANT_Fieldref at `index`,
+/// or `None` if the entry or one it refers to is missing or of another kind.
+pub fn get_field_ref<'c>(constant_pool: &'c [ConstantPoolInfo], index: usize) -> Option<(&'c str, &'c str, &'c str)> {
+    let Some(ConstantPoolInfo::FieldRef(field_ref)) = constant_pool.get(index) else {
+        return None;
+    };
+    let Some(ConstantPoolInfo::NameAndType(name_and_type)) = constant_pool.get(field_ref.name_and_type_index) else {
+        return None;
+    };
+    Some((
+        get_class_name(constant_pool, field_ref.class_index)?,
+        get_utf8(constant_pool, name_and_type.name_index)?,
+        get_utf8(constant_pool, name_and_type.descriptor_index)?,
+    ))
+}
+
+/// Returns the owner class, name and descriptor of the CONSTANT_Fieldref, CONSTANT_Methodref
+/// or CONSTANT_InterfaceMethodref at `index`, e.g. the target of a CONSTANT_MethodHandle.
+pub fn get_member_ref<'c>(constant_pool: &'c [ConstantPoolInfo], index: usize) -> Option<(&'c str, &'c str, &'c str)> {
+    let (class_index, name_and_type_index) = match constant_pool.get(index)? {
+        ConstantPoolInfo::FieldRef(info) => (info.class_index, info.name_and_type_index),
+        ConstantPoolInfo::MethodRef(info) => (info.class_index, info.name_and_type_index),
+        ConstantPoolInfo::InterfaceMethodRef(info) => (info.class_index, info.name_and_type_index),
+        _ => return None,
+    };
+    let Some(ConstantPoolInfo::NameAndType(name_and_type)) = constant_pool.get(name_and_type_index) else {
+        return None;
+    };
+    Some((
+        get_class_name(constant_pool, class_index)?,
+        get_utf8(constant_pool, name_and_type.name_index)?,
+        get_utf8(constant_pool, name_and_type.descriptor_index)?,
+    ))
 }
 
 macro_rules! utf8_info_as_str {
     ($constant_pool:expr, $index:expr) => {
         match &$constant_pool[$index] {
-            ConstantPoolInfo::Utf8(utf8_info) => utf8_info.data,
+            ConstantPoolInfo::Utf8(utf8_info) => utf8_info.data.as_ref(),
             _ => panic!("Not Utf8 ConstantPool Error"),
         }
     };
@@ -0,0 +1,293 @@
+//! Copying methods between classes.
+
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    checkout::{constant_key, read_attributes, Layout},
+    dedup::{referenced_attribute_constants, remap_constant, rewrite_attribute_references},
+    encode::{encode, encode_constant_pool},
+    error::EncodeError,
+    types::*,
+    utils::*,
+};
+
+/// Constants nested deeper than this, e.g. through the arguments of dynamic constants, are
+/// not copied.
+const MAX_DEPTH: usize = 256;
+
+/// Why a method cannot be copied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CopyError {
+    /// The source class has no method at this index.
+    NoSuchMethod { method_index: usize },
+    /// The destination class already has a method with the same name and descriptor.
+    DuplicateMethod { name: String, descriptor: String },
+    /// An attribute of the method cannot be renumbered, e.g. a non-standard one, or an ldc
+    /// instruction whose constant would be added beyond index 255.
+    UnrelocatableAttribute,
+    /// A constant the method refers to is missing, malformed or nested too deeply, or
+    /// refers to a missing bootstrap method.
+    MalformedConstant { index: usize },
+    /// The destination constant pool would exceed 65535 entries.
+    TooManyConstants,
+    /// The destination BootstrapMethods attribute would exceed 65535 entries.
+    TooManyBootstrapMethods,
+    /// The source, the destination or the copy cannot be encoded.
+    Encode(EncodeError),
+}
+
+impl fmt::Display for CopyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoSuchMethod { method_index } => write!(f, "methods[{}] does not exist", method_index),
+            Self::DuplicateMethod { name, descriptor } => {
+                write!(f, "the destination already has a method {}{}", name, descriptor)
+            }
+            Self::UnrelocatableAttribute => write!(f, "an attribute of the method cannot be renumbered"),
+            Self::MalformedConstant { index } => write!(f, "constant_pool[{}] cannot be copied", index),
+            Self::TooManyConstants => write!(f, "the destination constant pool exceeds 65535 entries"),
+            Self::TooManyBootstrapMethods => write!(f, "the destination BootstrapMethods exceeds 65535 entries"),
+            Self::Encode(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for CopyError {}
+
+impl From<EncodeError> for CopyError {
+    fn from(error: EncodeError) -> Self {
+        CopyError::Encode(error)
+    }
+}
+
+/// Copies the method at `method_index` of `source`, with all of its attributes, into
+/// `destination` and encodes the result.
+///
+/// Every constant the method refers to is added to the destination constant pool unless an
+/// identical entry exists, and the references are renumbered accordingly. Bootstrap methods
+/// of invokedynamic and dynamic constants are added to the BootstrapMethods attribute of the
+/// destination in the same way. Other members the method uses, e.g. the synthetic methods
+/// implementing its lambdas, are not copied.
+pub fn copy_method(
+    source: &JavaClassFile,
+    method_index: usize,
+    destination: &JavaClassFile,
+) -> Result<Vec<u8>, CopyError> {
+    let source_bytes = encode(source)?;
+    let source_layout = Layout::new(&source_bytes);
+    let range = source_layout.methods.get(method_index).ok_or(CopyError::NoSuchMethod { method_index })?;
+    let method = &source_bytes[range.clone()];
+    let table = &method[3 * size_of::<u16>()..];
+    let name_index = u16::from_be_bytes([method[2], method[3]]) as usize;
+    let descriptor_index = u16::from_be_bytes([method[4], method[5]]) as usize;
+
+    let utf8 = |index: usize| get_utf8(&source.constant_pool, index).unwrap_or_default();
+    let (name, descriptor) = (utf8(name_index), utf8(descriptor_index));
+    let constant_pool = &destination.constant_pool;
+    if destination.methods.iter().any(|method| {
+        get_utf8(constant_pool, method.name_index) == Some(name)
+            && get_utf8(constant_pool, method.descriptor_index) == Some(descriptor)
+    }) {
+        return Err(CopyError::DuplicateMethod { name: name.to_string(), descriptor: descriptor.to_string() });
+    }
+
+    let used = referenced_attribute_constants(table, &source.constant_pool).ok_or(CopyError::UnrelocatableAttribute)?;
+    let mut importer = Importer::new(source, destination);
+    let mut indices = vec![0u16; source.constant_pool.len()];
+    for (index, used) in used.into_iter().enumerate() {
+        if used {
+            indices[index] = importer.import(index, 0)?;
+        }
+    }
+    let name_index = importer.import(name_index, 0)?;
+    let descriptor_index = importer.import(descriptor_index, 0)?;
+    let table = rewrite_attribute_references(table, &source.constant_pool, indices)
+        .ok_or(CopyError::UnrelocatableAttribute)?;
+
+    // Adding BootstrapMethods may need its name in the pool, so the attributes come first.
+    let bytes = encode(destination)?;
+    let layout = Layout::new(&bytes);
+    let mut attributes = bytes[layout.methods_end..layout.attributes_end].to_vec();
+    if importer.bootstrap_methods.len() > destination.bootstrap_methods().len() {
+        let mut body = Vec::new();
+        write_u16(&mut body, importer.bootstrap_methods.len() as u16);
+        for (method_ref, arguments) in &importer.bootstrap_methods {
+            write_u16(&mut body, *method_ref);
+            write_u16(&mut body, arguments.len() as u16);
+            for argument in arguments {
+                write_u16(&mut body, *argument);
+            }
+        }
+        let name_index = importer.add(ConstantPoolInfo::Utf8(ConstantUtf8Info {
+            tag: ConstantKind::Utf8,
+            length: "BootstrapMethods".len(),
+            data: "BootstrapMethods".into(),
+            bytes: None,
+        }))?;
+
+        let mut table = read_attributes(&attributes);
+        let existing = table
+            .iter()
+            .position(|(index, _)| get_utf8(&importer.pool, *index as usize) == Some("BootstrapMethods"));
+        match existing {
+            Some(position) => table[position].1 = body,
+            None => table.push((name_index, body)),
+        }
+        attributes.clear();
+        write_u16(&mut attributes, table.len() as u16);
+        for (name_index, body) in table {
+            write_u16(&mut attributes, name_index);
+            write_u32(&mut attributes, body.len() as u32);
+            attributes.extend_from_slice(&body);
+        }
+    }
+
+    let mut output = bytes[..2 * size_of::<u32>()].to_vec();
+    encode_constant_pool(&mut output, &importer.pool)?;
+    output.extend_from_slice(&bytes[layout.constant_pool_end..layout.methods_start]);
+    write_u16(&mut output, layout.methods.len() as u16 + 1);
+    output.extend_from_slice(&bytes[layout.methods_start + size_of::<u16>()..layout.methods_end]);
+    output.extend_from_slice(&method[..size_of::<u16>()]);
+    write_u16(&mut output, name_index);
+    write_u16(&mut output, descriptor_index);
+    output.extend_from_slice(&table);
+    output.extend_from_slice(&attributes);
+    output.extend_from_slice(&bytes[layout.attributes_end..]);
+    Ok(output)
+}
+
+/// Adds constants of a source pool to a destination pool, reusing identical entries.
+struct Importer<'s, 'a> {
+    source: &'s [ConstantPoolInfo<'a>],
+    source_bootstrap_methods: &'s [BootstrapMethodEntry],
+    /// The destination pool with the constants added so far.
+    pool: Vec<ConstantPoolInfo<'a>>,
+    existing: HashMap<Vec<u8>, usize>,
+    /// Method handle and argument indices of the destination bootstrap methods.
+    bootstrap_methods: Vec<(u16, Vec<u16>)>,
+    /// Destination index of each source index copied so far.
+    indices: HashMap<usize, u16>,
+}
+
+impl<'s, 'a> Importer<'s, 'a> {
+    fn new(source: &'s JavaClassFile<'a>, destination: &JavaClassFile<'a>) -> Self {
+        let mut pool = destination.constant_pool.clone();
+        // Index 0 is never used, even in an empty pool.
+        if pool.is_empty() {
+            pool.push(ConstantPoolInfo::Dummy());
+        }
+        let mut existing = HashMap::new();
+        for (index, constant) in pool.iter().enumerate() {
+            // A constant which cannot be encoded matches none of those added.
+            if let (false, Ok(key)) = (matches!(constant, ConstantPoolInfo::Dummy()), constant_key(constant)) {
+                existing.entry(key).or_insert(index);
+            }
+        }
+        let bootstrap_methods = destination
+            .bootstrap_methods()
+            .iter()
+            .map(|entry| {
+                let arguments = entry.bootstrap_arguments.iter().map(|&argument| argument as u16).collect();
+                (entry.bootstrap_method_ref as u16, arguments)
+            })
+            .collect();
+        Importer {
+            source: &source.constant_pool,
+            source_bootstrap_methods: source.bootstrap_methods(),
+            pool,
+            existing,
+            bootstrap_methods,
+            indices: HashMap::new(),
+        }
+    }
+
+    /// Returns the destination index of the source constant at `index`, adding it and the
+    /// constants it refers to as needed. Index 0 stays 0.
+    fn import(&mut self, index: usize, depth: usize) -> Result<u16, CopyError> {
+        if index == 0 {
+            return Ok(0);
+        }
+        if let Some(new) = self.indices.get(&index) {
+            return Ok(*new);
+        }
+        let malformed = CopyError::MalformedConstant { index };
+        let constant = match self.source.get(index) {
+            Some(ConstantPoolInfo::Dummy()) | None => return Err(malformed),
+            Some(constant) if depth < MAX_DEPTH => constant,
+            Some(_) => return Err(malformed),
+        };
+
+        let mut references = Vec::new();
+        remap_constant(constant, |reference| {
+            references.push(reference);
+            reference
+        });
+        let mut mapped = HashMap::new();
+        for reference in references {
+            mapped.insert(reference, self.import(reference, depth + 1)? as usize);
+        }
+        let mut constant = remap_constant(constant, |reference| mapped[&reference]);
+        match &mut constant {
+            ConstantPoolInfo::Dynamic(info) => {
+                info.bootstrap_method_handle_attr_index =
+                    self.import_bootstrap_method(info.bootstrap_method_handle_attr_index, index, depth)? as usize;
+            }
+            ConstantPoolInfo::InvokeDynamic(info) => {
+                info.bootstrap_method_attr_index =
+                    self.import_bootstrap_method(info.bootstrap_method_attr_index, index, depth)? as usize;
+            }
+            _ => {}
+        }
+
+        let new = self.add(constant)?;
+        self.indices.insert(index, new);
+        Ok(new)
+    }
+
+    /// Returns the destination index of a source bootstrap method used by the constant at
+    /// `index`, adding it as needed.
+    fn import_bootstrap_method(
+        &mut self,
+        bootstrap_index: usize,
+        index: usize,
+        depth: usize,
+    ) -> Result<u16, CopyError> {
+        let entry = self.source_bootstrap_methods.get(bootstrap_index);
+        let entry = entry.ok_or(CopyError::MalformedConstant { index })?;
+        let method_ref = self.import(entry.bootstrap_method_ref, depth + 1)?;
+        let arguments = entry
+            .bootstrap_arguments
+            .iter()
+            .map(|&argument| self.import(argument, depth + 1))
+            .collect::<Result<Vec<u16>, CopyError>>()?;
+        let entry = (method_ref, arguments);
+        if let Some(position) = self.bootstrap_methods.iter().position(|existing| *existing == entry) {
+            return Ok(position as u16);
+        }
+        if self.bootstrap_methods.len() == u16::MAX as usize {
+            return Err(CopyError::TooManyBootstrapMethods);
+        }
+        self.bootstrap_methods.push(entry);
+        Ok(self.bootstrap_methods.len() as u16 - 1)
+    }
+
+    /// Adds a constant whose references are destination indices, unless an identical one
+    /// exists, and returns its index.
+    fn add(&mut self, constant: ConstantPoolInfo<'a>) -> Result<u16, CopyError> {
+        let key = constant_key(&constant)?;
+        if let Some(index) = self.existing.get(&key) {
+            return Ok(*index as u16);
+        }
+        let index = self.pool.len();
+        let wide = matches!(constant, ConstantPoolInfo::Long(_) | ConstantPoolInfo::Double(_));
+        self.pool.push(constant);
+        if wide {
+            self.pool.push(ConstantPoolInfo::Dummy());
+        }
+        if self.pool.len() > u16::MAX as usize {
+            return Err(CopyError::TooManyConstants);
+        }
+        self.existing.insert(key, index);
+        Ok(index as u16)
+    }
+}
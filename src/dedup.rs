@@ -0,0 +1,603 @@
+use std::collections::HashMap;
+
+use crate::{
+    encode::{encode_constant, encode_constant_pool},
+    markers::MARKERS_ATTRIBUTE,
+    reader::ClassReader,
+    types::*,
+    utils::*,
+};
+
+/// A constant pool entry identical to an earlier one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DuplicateConstant {
+    pub index: usize,
+    /// Index of the first identical entry.
+    pub original: usize,
+}
+
+impl<'a> JavaClassFile<'a> {
+    /// Returns the constant pool entries which are identical to an earlier entry.
+    ///
+    /// Entries are compared after resolving references to duplicates, so two
+    /// CONSTANT_Class entries naming two identical CONSTANT_Utf8 entries are duplicates too.
+    pub fn duplicate_constants(&self) -> Vec<DuplicateConstant> {
+        find_duplicate_constants(&self.constant_pool)
+    }
+}
+
+pub(crate) fn find_duplicate_constants(constant_pool: &[ConstantPoolInfo]) -> Vec<DuplicateConstant> {
+    canonical_indices(constant_pool)
+        .into_iter()
+        .enumerate()
+        .filter(|(index, original)| index != original)
+        .map(|(index, original)| DuplicateConstant { index, original })
+        .collect()
+}
+
+/// Returns, for each constant pool index, the index of the first identical entry.
+fn canonical_indices(constant_pool: &[ConstantPoolInfo]) -> Vec<usize> {
+    let mut canonical: Vec<usize> = (0..constant_pool.len()).collect();
+    // Merging entries can make the entries referring to them identical, so repeat until
+    // nothing changes.
+    loop {
+        let mut first: HashMap<Vec<u8>, usize> = HashMap::new();
+        let mut next = canonical.clone();
+        for (index, constant) in constant_pool.iter().enumerate() {
+            if matches!(constant, ConstantPoolInfo::Dummy()) {
+                continue;
+            }
+            let mut key = Vec::new();
+            let remapped = remap_constant(constant, |i| canonical.get(i).copied().unwrap_or(i));
+            // A constant which cannot be encoded is left unmerged.
+            if encode_constant(&mut key, &remapped).is_ok() {
+                next[index] = *first.entry(key).or_insert(index);
+            }
+        }
+        if next == canonical {
+            return canonical;
+        }
+        canonical = next;
+    }
+}
+
+/// Returns `constant` with its references to other entries passed through `map`.
+pub(crate) fn remap_constant<'a>(constant: &ConstantPoolInfo<'a>, mut map: impl FnMut(usize) -> usize) -> ConstantPoolInfo<'a> {
+    let mut constant = constant.clone();
+    match &mut constant {
+        ConstantPoolInfo::Class(info) => info.name_index = map(info.name_index),
+        ConstantPoolInfo::FieldRef(info) => {
+            info.class_index = map(info.class_index);
+            info.name_and_type_index = map(info.name_and_type_index);
+        }
+        ConstantPoolInfo::MethodRef(info) => {
+            info.class_index = map(info.class_index);
+            info.name_and_type_index = map(info.name_and_type_index);
+        }
+        ConstantPoolInfo::InterfaceMethodRef(info) => {
+            info.class_index = map(info.class_index);
+            info.name_and_type_index = map(info.name_and_type_index);
+        }
+        ConstantPoolInfo::String(info) => info.string_index = map(info.string_index),
+        ConstantPoolInfo::NameAndType(info) => {
+            info.name_index = map(info.name_index);
+            info.descriptor_index = map(info.descriptor_index);
+        }
+        ConstantPoolInfo::MethodHandle(info) => info.reference_index = map(info.reference_index),
+        ConstantPoolInfo::MethodType(info) => info.descriptor_index = map(info.descriptor_index),
+        ConstantPoolInfo::Dynamic(info) => info.name_and_type_index = map(info.name_and_type_index),
+        ConstantPoolInfo::InvokeDynamic(info) => info.name_and_type_index = map(info.name_and_type_index),
+        ConstantPoolInfo::Module(info) => info.name_index = map(info.name_index),
+        ConstantPoolInfo::Package(info) => info.name_index = map(info.name_index),
+        ConstantPoolInfo::Dummy()
+        | ConstantPoolInfo::Integer(_)
+        | ConstantPoolInfo::Float(_)
+        | ConstantPoolInfo::Long(_)
+        | ConstantPoolInfo::Double(_)
+        | ConstantPoolInfo::Utf8(_) => {}
+    }
+    constant
+}
+
+/// Rewrites an encoded class file with duplicate constants removed and every reference
+/// renumbered.
+///
+/// Returns `None` if there is nothing to merge, or if the class file has an attribute
+/// whose references cannot be located, e.g. a non-standard attribute.
+pub(crate) fn dedup_constants(bytes: &[u8]) -> Option<Vec<u8>> {
+    let (_, constant_pool, _) = split_constant_pool(bytes)?;
+
+    let canonical = canonical_indices(&constant_pool);
+    if canonical.iter().enumerate().all(|(index, original)| index == *original) {
+        return None;
+    }
+
+    // Kept entries move down over the removed ones; removed entries take the new index
+    // of their original, which is always lower.
+    let mut indices = vec![0u16; constant_pool.len()];
+    let mut kept = Vec::with_capacity(constant_pool.len());
+    for (index, constant) in constant_pool.iter().enumerate() {
+        if canonical[index] == index {
+            indices[index] = kept.len() as u16;
+            kept.push(constant);
+        } else {
+            indices[index] = indices[canonical[index]];
+        }
+    }
+    let new_pool: Vec<ConstantPoolInfo> = kept
+        .into_iter()
+        .map(|constant| remap_constant(constant, |i| indices.get(i).map_or(i, |new| *new as usize)))
+        .collect();
+
+    rewrite_references(bytes, indices, &new_pool)
+}
+
+/// Returns which constant pool entries an encoded class file refers to outside the
+/// constant pool, or `None` if some references cannot be located.
+///
+/// References between constant pool entries are not followed.
+pub(crate) fn referenced_constants(bytes: &[u8]) -> Option<Vec<bool>> {
+    let (_, constant_pool, reader) = split_constant_pool(bytes)?;
+
+    let mut rewriter = Rewriter {
+        reader,
+        output: Vec::new(),
+        constant_pool: &constant_pool,
+        indices: (0..constant_pool.len() as u16).collect(),
+        used: vec![false; constant_pool.len()],
+    };
+    rewriter.class()?;
+    Some(rewriter.used)
+}
+
+/// Returns which constant pool entries an encoded attribute table refers to, or `None` if
+/// some references cannot be located. Attribute names are resolved in `constant_pool`.
+///
+/// References between constant pool entries are not followed.
+pub(crate) fn referenced_attribute_constants(bytes: &[u8], constant_pool: &[ConstantPoolInfo]) -> Option<Vec<bool>> {
+    let mut rewriter = Rewriter {
+        reader: ClassReader::new(bytes),
+        output: Vec::new(),
+        constant_pool,
+        indices: (0..constant_pool.len() as u16).collect(),
+        used: vec![false; constant_pool.len()],
+    };
+    rewriter.attributes()?;
+    rewriter.reader.remaining().is_empty().then_some(rewriter.used)
+}
+
+/// Rewrites an encoded class file with `new_pool` as its constant pool, renumbering every
+/// reference from old index to `indices[old]`.
+pub(crate) fn rewrite_references(bytes: &[u8], indices: Vec<u16>, new_pool: &[ConstantPoolInfo]) -> Option<Vec<u8>> {
+    let (header, constant_pool, reader) = split_constant_pool(bytes)?;
+
+    let mut rewriter = Rewriter {
+        reader,
+        output: header.to_vec(),
+        constant_pool: &constant_pool,
+        used: vec![false; constant_pool.len()],
+        indices,
+    };
+    encode_constant_pool(&mut rewriter.output, new_pool).ok()?;
+    rewriter.class()?;
+    Some(rewriter.output)
+}
+
+/// Rewrites an encoded attribute table, i.e. attributes_count and the attributes following
+/// it, renumbering every reference from old index to `indices[old]`.
+///
+/// Attribute names are resolved in `constant_pool`, the pool the old indices refer to.
+pub(crate) fn rewrite_attribute_references(
+    bytes: &[u8],
+    constant_pool: &[ConstantPoolInfo],
+    indices: Vec<u16>,
+) -> Option<Vec<u8>> {
+    let mut rewriter = Rewriter {
+        reader: ClassReader::new(bytes),
+        output: Vec::with_capacity(bytes.len()),
+        constant_pool,
+        used: vec![false; constant_pool.len()],
+        indices,
+    };
+    rewriter.attributes()?;
+    rewriter.reader.remaining().is_empty().then_some(rewriter.output)
+}
+
+/// Splits an encoded class file into magic and version, the decoded constant pool, and a
+/// reader positioned after the constant pool.
+fn split_constant_pool(bytes: &[u8]) -> Option<(&[u8], Vec<ConstantPoolInfo<'_>>, ClassReader<'_>)> {
+    let mut reader = ClassReader::new(bytes);
+    let header = reader.read_bytes(2 * size_of::<u32>(), "magic").ok()?;
+    let mut context = DecodeContext::new(&Default::default());
+    let constant_pool = crate::constant_pool::decode_constant_pool(&mut reader, &mut context).ok()?;
+    Some((header, constant_pool, reader))
+}
+
+/// Element values nested deeper than this are not rewritten.
+const MAX_DEPTH: usize = 256;
+
+/// Copies a class file following its constant pool while renumbering constant pool
+/// references.
+struct Rewriter<'a, 'c> {
+    reader: ClassReader<'a>,
+    output: Vec<u8>,
+    /// The constant pool before deduplication, to look up attribute names.
+    constant_pool: &'c [ConstantPoolInfo<'a>],
+    /// New index of each old index.
+    indices: Vec<u16>,
+    /// Whether each old index has been referenced.
+    used: Vec<bool>,
+}
+
+impl Rewriter<'_, '_> {
+    /// Copies everything following the constant pool.
+    fn class(&mut self) -> Option<()> {
+        self.copy(size_of::<u16>())?;
+        self.index()?;
+        self.index()?;
+        self.indices()?;
+        for _ in 0..2 {
+            let count = self.u16()?;
+            for _ in 0..count {
+                self.copy(size_of::<u16>())?;
+                self.index()?;
+                self.index()?;
+                self.attributes()?;
+            }
+        }
+        self.attributes()?;
+        let trailing_bytes = self.reader.remaining().len();
+        self.copy(trailing_bytes)
+    }
+
+    fn copy(&mut self, length: usize) -> Option<()> {
+        let bytes = self.reader.read_bytes(length, "attribute_info").ok()?;
+        self.output.extend_from_slice(bytes);
+        Some(())
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        let value = self.reader.read_u8("attribute_info").ok()?;
+        write_u8(&mut self.output, value);
+        Some(value)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        let value = self.reader.read_u16("attribute_info").ok()?;
+        write_u16(&mut self.output, value);
+        Some(value)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let value = self.reader.read_u32("attribute_info").ok()?;
+        write_u32(&mut self.output, value);
+        Some(value)
+    }
+
+    fn map(&mut self, index: u16) -> u16 {
+        if let Some(used) = self.used.get_mut(index as usize) {
+            *used = true;
+        }
+        self.indices.get(index as usize).copied().unwrap_or(index)
+    }
+
+    /// Copies a u16 constant pool index.
+    fn index(&mut self) -> Option<()> {
+        let index = self.reader.read_u16("attribute_info").ok()?;
+        let index = self.map(index);
+        write_u16(&mut self.output, index);
+        Some(())
+    }
+
+    /// Copies a u16 count and that many constant pool indices.
+    fn indices(&mut self) -> Option<()> {
+        let count = self.u16()?;
+        for _ in 0..count {
+            self.index()?;
+        }
+        Some(())
+    }
+
+    fn attributes(&mut self) -> Option<()> {
+        let count = self.u16()?;
+        for _ in 0..count {
+            let name_index = self.reader.read_u16("attribute_info").ok()?;
+            let new_name_index = self.map(name_index);
+            write_u16(&mut self.output, new_name_index);
+            let length = self.u32()? as usize;
+            let end = self.reader.offset() + length;
+            let name = get_utf8(self.constant_pool, name_index as usize)?;
+            self.attribute(name, length)?;
+            if self.reader.offset() != end {
+                return None;
+            }
+        }
+        Some(())
+    }
+
+    /// Copies the body of the attribute named `name`.
+    ///
+    /// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7
+    fn attribute(&mut self, name: &str, length: usize) -> Option<()> {
+        match name {
+            "ConstantValue" | "Signature" | "SourceFile" | "NestHost" | "ModuleMainClass" | "CompilationID"
+            | "SourceID" => self.index(),
+            "Synthetic" | "Deprecated" | "SourceDebugExtension" | "LineNumberTable" | "CharacterRangeTable"
+            | "ScalaSig" | "Scala" | MARKERS_ATTRIBUTE => self.copy(length),
+            "Exceptions" | "NestMembers" | "PermittedSubclasses" | "ModulePackages" => self.indices(),
+            "Code" => {
+                self.copy(2 * size_of::<u16>())?;
+                let code_length = self.u32()? as usize;
+                self.code(code_length)?;
+                let exception_table_length = self.u16()?;
+                for _ in 0..exception_table_length {
+                    self.copy(3 * size_of::<u16>())?;
+                    self.index()?;
+                }
+                self.attributes()
+            }
+            "StackMapTable" => {
+                let number_of_entries = self.u16()?;
+                for _ in 0..number_of_entries {
+                    self.stack_map_frame()?;
+                }
+                Some(())
+            }
+            "InnerClasses" => {
+                let number_of_classes = self.u16()?;
+                for _ in 0..number_of_classes {
+                    self.index()?;
+                    self.index()?;
+                    self.index()?;
+                    self.copy(size_of::<u16>())?;
+                }
+                Some(())
+            }
+            "EnclosingMethod" => {
+                self.index()?;
+                self.index()
+            }
+            "LocalVariableTable" | "LocalVariableTypeTable" => {
+                let length = self.u16()?;
+                for _ in 0..length {
+                    self.copy(2 * size_of::<u16>())?;
+                    self.index()?;
+                    self.index()?;
+                    self.copy(size_of::<u16>())?;
+                }
+                Some(())
+            }
+            "RuntimeVisibleAnnotations" | "RuntimeInvisibleAnnotations" => self.annotations(),
+            "RuntimeVisibleParameterAnnotations" | "RuntimeInvisibleParameterAnnotations" => {
+                let num_parameters = self.u8()?;
+                for _ in 0..num_parameters {
+                    self.annotations()?;
+                }
+                Some(())
+            }
+            "RuntimeVisibleTypeAnnotations" | "RuntimeInvisibleTypeAnnotations" => {
+                let num_annotations = self.u16()?;
+                for _ in 0..num_annotations {
+                    self.type_annotation()?;
+                }
+                Some(())
+            }
+            "AnnotationDefault" => self.element_value(0),
+            "BootstrapMethods" => {
+                let num_bootstrap_methods = self.u16()?;
+                for _ in 0..num_bootstrap_methods {
+                    self.index()?;
+                    self.indices()?;
+                }
+                Some(())
+            }
+            "MethodParameters" => {
+                let parameters_count = self.u8()?;
+                for _ in 0..parameters_count {
+                    self.index()?;
+                    self.copy(size_of::<u16>())?;
+                }
+                Some(())
+            }
+            "Module" => {
+                self.index()?;
+                self.copy(size_of::<u16>())?;
+                self.index()?;
+                // requires
+                let count = self.u16()?;
+                for _ in 0..count {
+                    self.index()?;
+                    self.copy(size_of::<u16>())?;
+                    self.index()?;
+                }
+                // exports, then opens
+                for _ in 0..2 {
+                    let count = self.u16()?;
+                    for _ in 0..count {
+                        self.index()?;
+                        self.copy(size_of::<u16>())?;
+                        self.indices()?;
+                    }
+                }
+                // uses
+                self.indices()?;
+                // provides
+                let count = self.u16()?;
+                for _ in 0..count {
+                    self.index()?;
+                    self.indices()?;
+                }
+                Some(())
+            }
+            "Record" => {
+                let components_count = self.u16()?;
+                for _ in 0..components_count {
+                    self.index()?;
+                    self.index()?;
+                    self.attributes()?;
+                }
+                Some(())
+            }
+            _ => None,
+        }
+    }
+
+    /// Copies bytecode, renumbering the operands of instructions which refer to the
+    /// constant pool.
+    fn code(&mut self, length: usize) -> Option<()> {
+        let start = self.reader.offset();
+        let end = start.checked_add(length)?;
+        while self.reader.offset() < end {
+            let pc = self.reader.offset() - start;
+            let opcode = Opcode::from_u8(self.u8()?)?;
+            match opcode {
+                // Deduplication never raises an index, but merged or copied constants may land
+                // beyond what one byte holds.
+                Opcode::Ldc => {
+                    let index = self.reader.read_u8("Code").ok()?;
+                    let index = u8::try_from(self.map(index as u16)).ok()?;
+                    write_u8(&mut self.output, index);
+                }
+                Opcode::LdcW
+                | Opcode::Ldc2W
+                | Opcode::Getstatic
+                | Opcode::Putstatic
+                | Opcode::Getfield
+                | Opcode::Putfield
+                | Opcode::Invokevirtual
+                | Opcode::Invokespecial
+                | Opcode::Invokestatic
+                | Opcode::New
+                | Opcode::Anewarray
+                | Opcode::Checkcast
+                | Opcode::Instanceof => self.index()?,
+                Opcode::Invokeinterface | Opcode::Invokedynamic => {
+                    self.index()?;
+                    self.copy(2)?;
+                }
+                Opcode::Multianewarray => {
+                    self.index()?;
+                    self.copy(1)?;
+                }
+                Opcode::Wide => {
+                    let modified = self.u8()?;
+                    self.copy(if modified == Opcode::Iinc as u8 { 4 } else { 2 })?;
+                }
+                Opcode::Tableswitch => {
+                    self.copy(switch_padding(pc))?;
+                    self.copy(4)?;
+                    let low = self.u32()? as i32 as i64;
+                    let high = self.u32()? as i32 as i64;
+                    self.copy(usize::try_from((high - low + 1) * 4).ok()?)?;
+                }
+                Opcode::Lookupswitch => {
+                    self.copy(switch_padding(pc))?;
+                    self.copy(4)?;
+                    let npairs = self.u32()? as i32;
+                    self.copy(usize::try_from(npairs).ok()?.checked_mul(8)?)?;
+                }
+                opcode => self.copy(operand_length(opcode))?,
+            }
+        }
+        (self.reader.offset() == end).then_some(())
+    }
+
+    fn stack_map_frame(&mut self) -> Option<()> {
+        let frame_type = self.u8()?;
+        match frame_type {
+            0..=63 => Some(()),
+            64..=127 => self.verification_type_info(),
+            247 => {
+                self.copy(size_of::<u16>())?;
+                self.verification_type_info()
+            }
+            248..=251 => self.copy(size_of::<u16>()),
+            252..=254 => {
+                self.copy(size_of::<u16>())?;
+                for _ in 251..frame_type {
+                    self.verification_type_info()?;
+                }
+                Some(())
+            }
+            255 => {
+                self.copy(size_of::<u16>())?;
+                for _ in 0..2 {
+                    let count = self.u16()?;
+                    for _ in 0..count {
+                        self.verification_type_info()?;
+                    }
+                }
+                Some(())
+            }
+            _ => None,
+        }
+    }
+
+    fn verification_type_info(&mut self) -> Option<()> {
+        match self.u8()? {
+            // Object_variable_info
+            7 => self.index(),
+            // Uninitialized_variable_info
+            8 => self.copy(size_of::<u16>()),
+            _ => Some(()),
+        }
+    }
+
+    fn annotations(&mut self) -> Option<()> {
+        let num_annotations = self.u16()?;
+        for _ in 0..num_annotations {
+            self.annotation(0)?;
+        }
+        Some(())
+    }
+
+    fn annotation(&mut self, depth: usize) -> Option<()> {
+        self.index()?;
+        let num_element_value_pairs = self.u16()?;
+        for _ in 0..num_element_value_pairs {
+            self.index()?;
+            self.element_value(depth + 1)?;
+        }
+        Some(())
+    }
+
+    fn element_value(&mut self, depth: usize) -> Option<()> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        match self.u8()? {
+            b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' | b's' | b'c' => self.index(),
+            b'e' => {
+                self.index()?;
+                self.index()
+            }
+            b'@' => self.annotation(depth),
+            b'[' => {
+                let num_values = self.u16()?;
+                for _ in 0..num_values {
+                    self.element_value(depth + 1)?;
+                }
+                Some(())
+            }
+            _ => None,
+        }
+    }
+
+    fn type_annotation(&mut self) -> Option<()> {
+        match self.u8()? {
+            0x00 | 0x01 | 0x16 => self.copy(1)?,
+            0x10 | 0x17 | 0x42..=0x46 => self.copy(2)?,
+            0x11 | 0x12 => self.copy(2)?,
+            0x13..=0x15 => {}
+            0x40 | 0x41 => {
+                let table_length = self.u16()? as usize;
+                self.copy(table_length * 3 * size_of::<u16>())?;
+            }
+            0x47..=0x4B => self.copy(3)?,
+            _ => return None,
+        }
+        let path_length = self.u8()? as usize;
+        self.copy(path_length * 2)?;
+        self.annotation(0)
+    }
+}
@@ -0,0 +1,212 @@
+use std::fmt;
+
+/// Parses one FieldType at the start of `descriptor` and returns the rest.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.3.2
+fn parse_field_type(descriptor: &str) -> Option<&str> {
+    let dimensions = descriptor.bytes().take_while(|&b| b == b'[').count();
+    if dimensions > 255 {
+        return None;
+    }
+
+    let rest = &descriptor[dimensions..];
+    match rest.as_bytes().first()? {
+        b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' => Some(&rest[1..]),
+        b'L' => {
+            let end = rest.find(';')?;
+            let class_name = &rest[1..end];
+            if class_name.is_empty() || class_name.split('/').any(|part| part.is_empty() || part.contains(['.', '[', ';'])) {
+                return None;
+            }
+            Some(&rest[end + 1..])
+        }
+        _ => None,
+    }
+}
+
+/// Tests if `descriptor` is a well-formed field descriptor.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.3.2
+pub fn is_field_descriptor(descriptor: &str) -> bool {
+    parse_field_type(descriptor) == Some("")
+}
+
+/// Tests if `descriptor` is a well-formed method descriptor.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.3.3
+pub fn is_method_descriptor(descriptor: &str) -> bool {
+    let Some(mut rest) = descriptor.strip_prefix('(') else {
+        return false;
+    };
+
+    while !rest.starts_with(')') {
+        match parse_field_type(rest) {
+            Some(next) => rest = next,
+            None => return false,
+        }
+    }
+
+    let return_descriptor = &rest[1..];
+    return_descriptor == "V" || is_field_descriptor(return_descriptor)
+}
+
+/// Returns the number of local variable slots taken by the parameters of a method
+/// descriptor, counting two for long and double, or `None` if it is malformed.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.3.3
+pub fn parameter_slots(descriptor: &str) -> Option<usize> {
+    let mut rest = descriptor.strip_prefix('(')?;
+    let mut slots = 0;
+    while !rest.starts_with(')') {
+        slots += if rest.starts_with(['J', 'D']) { 2 } else { 1 };
+        rest = parse_field_type(rest)?;
+    }
+    Some(slots)
+}
+
+/// Splits a method descriptor into its parameter descriptors and return descriptor,
+/// e.g. `(I[Ljava/lang/String;)V` into `["I", "[Ljava/lang/String;"]` and `V`.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.3.3
+pub fn split_method_descriptor(descriptor: &str) -> Option<(Vec<&str>, &str)> {
+    if !is_method_descriptor(descriptor) {
+        return None;
+    }
+
+    let mut rest = &descriptor[1..];
+    let mut parameters = Vec::new();
+    while !rest.starts_with(')') {
+        let next = parse_field_type(rest)?;
+        parameters.push(&rest[..rest.len() - next.len()]);
+        rest = next;
+    }
+    Some((parameters, &rest[1..]))
+}
+
+/// Renders a field descriptor as a Java type, e.g. `[Ljava/lang/String;` as
+/// `java.lang.String[]`, writing straight to the formatter without allocating.
+///
+/// Only the structure of the descriptor is checked; a descriptor of another structure is
+/// written as it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayDescriptor<'d>(pub &'d str);
+
+impl fmt::Display for DisplayDescriptor<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match split_field_type(self.0) {
+            Some((field_type, "")) => write_field_type(f, field_type),
+            _ => f.write_str(self.0),
+        }
+    }
+}
+
+/// Renders a method descriptor as a Java return type and parameter list, e.g.
+/// `(I[Ljava/lang/String;)V` as `void (int, java.lang.String[])`, or with the name of the
+/// method between them, writing straight to the formatter without allocating.
+///
+/// Only the structure of the descriptor is checked; a descriptor of another structure is
+/// written as it is, after the name if any.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayMethodDescriptor<'d> {
+    pub name: Option<&'d str>,
+    pub descriptor: &'d str,
+}
+
+impl<'d> DisplayMethodDescriptor<'d> {
+    pub fn new(descriptor: &'d str) -> Self {
+        Self { name: None, descriptor }
+    }
+
+    /// Renders the method named `name`, e.g. `void main(java.lang.String[])`.
+    pub fn named(name: &'d str, descriptor: &'d str) -> Self {
+        Self { name: Some(name), descriptor }
+    }
+
+    /// Returns the parameter descriptors and the return descriptor, or `None` if the
+    /// descriptor is not structured like a method descriptor.
+    fn split(&self) -> Option<(&'d str, &'d str)> {
+        let parameters = self.descriptor.strip_prefix('(')?;
+        let mut rest = parameters;
+        while !rest.starts_with(')') {
+            rest = split_field_type(rest)?.1;
+        }
+        let return_descriptor = &rest[1..];
+        match split_field_type(return_descriptor) {
+            Some((_, "")) => {}
+            _ if return_descriptor == "V" => {}
+            _ => return None,
+        }
+        Some((&parameters[..parameters.len() - rest.len()], return_descriptor))
+    }
+}
+
+impl fmt::Display for DisplayMethodDescriptor<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some((mut parameters, return_descriptor)) = self.split() else {
+            if let Some(name) = self.name {
+                write!(f, "{} ", name)?;
+            }
+            return f.write_str(self.descriptor);
+        };
+
+        match return_descriptor {
+            "V" => f.write_str("void ")?,
+            _ => {
+                write_field_type(f, return_descriptor)?;
+                f.write_str(" ")?;
+            }
+        }
+        if let Some(name) = self.name {
+            f.write_str(name)?;
+        }
+        f.write_str("(")?;
+        let mut first = true;
+        while let Some((parameter, rest)) = split_field_type(parameters) {
+            if !first {
+                f.write_str(", ")?;
+            }
+            write_field_type(f, parameter)?;
+            (parameters, first) = (rest, false);
+        }
+        f.write_str(")")
+    }
+}
+
+/// Splits the field type at the start of `descriptor` from the rest, checking only the
+/// structure rendering needs.
+fn split_field_type(descriptor: &str) -> Option<(&str, &str)> {
+    let dimensions = descriptor.bytes().take_while(|&b| b == b'[').count();
+    let length = match descriptor.as_bytes().get(dimensions)? {
+        b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' => dimensions + 1,
+        b'L' => dimensions + descriptor[dimensions..].find(';')? + 1,
+        _ => return None,
+    };
+    Some(descriptor.split_at(length))
+}
+
+/// Writes a field type split by [`split_field_type`] as a Java type.
+fn write_field_type(f: &mut fmt::Formatter<'_>, descriptor: &str) -> fmt::Result {
+    let element = descriptor.trim_start_matches('[');
+    match element.as_bytes()[0] {
+        b'B' => f.write_str("byte")?,
+        b'C' => f.write_str("char")?,
+        b'D' => f.write_str("double")?,
+        b'F' => f.write_str("float")?,
+        b'I' => f.write_str("int")?,
+        b'J' => f.write_str("long")?,
+        b'S' => f.write_str("short")?,
+        b'Z' => f.write_str("boolean")?,
+        _ => {
+            let mut segments = element[1..element.len() - 1].split('/');
+            f.write_str(segments.next().unwrap_or_default())?;
+            for segment in segments {
+                f.write_str(".")?;
+                f.write_str(segment)?;
+            }
+        }
+    }
+    for _ in 0..descriptor.len() - element.len() {
+        f.write_str("[]")?;
+    }
+    Ok(())
+}
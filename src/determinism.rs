@@ -0,0 +1,216 @@
+//! Detection of class file contents that differ between otherwise identical builds.
+
+use std::fmt;
+
+use crate::types::*;
+
+/// Milliseconds since the epoch of 2000-01-01 and 2100-01-01, the range of plausible
+/// build timestamps.
+const MIN_TIMESTAMP_MILLIS: u64 = 946_684_800_000;
+const MAX_TIMESTAMP_MILLIS: u64 = 4_102_444_800_000;
+
+/// A name segment of this many hex digits or more looks like a hash or an address.
+const MIN_RANDOM_HEX_LENGTH: usize = 8;
+
+/// The kind of a [`Finding`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FindingKind {
+    /// A non-standard attribute holds what looks like a build date or time.
+    Timestamp,
+    /// The SourceFile attribute holds an absolute path of the build machine rather than a
+    /// file name.
+    AbsoluteSourcePath,
+    /// The class or a synthetic member has a name with a hash-like or address-like part,
+    /// e.g. `access$9f86d081` or `Foo$$Lambda/0x0000000800c03000`.
+    RandomName,
+}
+
+impl FindingKind {
+    /// Returns true if [`normalize`] fixes findings of this kind.
+    pub fn is_fixable(&self) -> bool {
+        matches!(self, FindingKind::Timestamp | FindingKind::AbsoluteSourcePath)
+    }
+}
+
+/// Something in a class file that breaks reproducible builds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub kind: FindingKind,
+    /// Where it was found, e.g. `attributes` or `methods[3]`.
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+/// Lists the contents of a class file that likely differ between builds of the same source.
+///
+/// The checks are heuristics: non-standard attributes are searched for date strings and
+/// 64-bit epoch timestamps, the SourceFile attribute for absolute paths, and the names of
+/// the class and of its synthetic members for long runs of hex digits.
+pub fn audit(class_file: &JavaClassFile) -> Vec<Finding> {
+    let constant_pool = &class_file.constant_pool;
+    let mut findings = Vec::new();
+
+    if let Some(name) = get_class_name(constant_pool, class_file.this_class) {
+        if looks_random(name) {
+            findings.push(Finding {
+                kind: FindingKind::RandomName,
+                location: "this_class".to_string(),
+                message: format!("class name {} looks generated at random", name),
+            });
+        }
+    }
+
+    if let Some(path) = class_file.source_file() {
+        if is_absolute_path(path) {
+            findings.push(Finding {
+                kind: FindingKind::AbsoluteSourcePath,
+                location: "attributes".to_string(),
+                message: format!("SourceFile is the absolute path {}", path),
+            });
+        }
+    }
+
+    audit_timestamps(&mut findings, "attributes", &class_file.attributes);
+    for (i, field) in class_file.fields.iter().enumerate() {
+        let location = format!("fields[{}]", i);
+        if field.is_synthetic(constant_pool) {
+            audit_member_name(&mut findings, &location, get_utf8(constant_pool, field.name_index));
+        }
+        audit_timestamps(&mut findings, &location, &field.attributes);
+    }
+    for (i, method) in class_file.methods.iter().enumerate() {
+        let location = format!("methods[{}]", i);
+        if method.is_synthetic(constant_pool) {
+            audit_member_name(&mut findings, &location, get_utf8(constant_pool, method.name_index));
+        }
+        audit_timestamps(&mut findings, &location, &method.attributes);
+    }
+
+    findings
+}
+
+/// Fixes what [`audit`] finds where possible and returns the findings fixed.
+///
+/// Non-standard attributes holding timestamps are removed, and an absolute SourceFile
+/// path is replaced by its file name, added to the constant pool as needed. Random-looking
+/// names are left alone, as renaming would have to reach every class referring to them.
+pub fn normalize(class_file: &mut JavaClassFile) -> Vec<Finding> {
+    let fixable: Vec<Finding> = audit(class_file).into_iter().filter(|finding| finding.kind.is_fixable()).collect();
+    if fixable.is_empty() {
+        return fixable;
+    }
+
+    remove_timestamps(&mut class_file.attributes);
+    for field in &mut class_file.fields {
+        remove_timestamps(&mut field.attributes);
+    }
+    for method in &mut class_file.methods {
+        remove_timestamps(&mut method.attributes);
+    }
+
+    let file_name = class_file.source_file().filter(|path| is_absolute_path(path)).map(|path| {
+        let start = path.rfind(['/', '\\']).map_or(0, |separator| separator + 1);
+        path[start..].to_string()
+    });
+    if let Some(file_name) = file_name {
+        let mut constants = ConstantPoolBuilder::extending(&class_file.constant_pool);
+        let sourcefile_index = constants.utf8(file_name) as u16;
+        class_file.constant_pool = constants.build();
+        for attribute in class_file.attributes.values_mut() {
+            if let AttributeInfo::SourceFile(attribute) = attribute {
+                attribute.sourcefile_index = sourcefile_index;
+            }
+        }
+    }
+
+    fixable
+}
+
+fn audit_member_name(findings: &mut Vec<Finding>, location: &str, name: Option<&str>) {
+    if let Some(name) = name.filter(|name| looks_random(name)) {
+        findings.push(Finding {
+            kind: FindingKind::RandomName,
+            location: location.to_string(),
+            message: format!("synthetic member name {} looks generated at random", name),
+        });
+    }
+}
+
+fn audit_timestamps(findings: &mut Vec<Finding>, location: &str, attributes: &Attributes) {
+    let mut names: Vec<&str> = attributes.values().filter_map(timestamp_attribute).collect();
+    names.sort_unstable();
+    for name in names {
+        findings.push(Finding {
+            kind: FindingKind::Timestamp,
+            location: location.to_string(),
+            message: format!("attribute \"{}\" looks like it holds a timestamp", name),
+        });
+    }
+}
+
+fn remove_timestamps(attributes: &mut Attributes) {
+    attributes.retain(|_, attribute| timestamp_attribute(attribute).is_none());
+}
+
+/// Returns the name of a non-standard attribute whose body looks like it holds a timestamp.
+fn timestamp_attribute<'c>(attribute: &'c AttributeInfo) -> Option<&'c str> {
+    let (AttributeInfo::Raw { name, data } | AttributeInfo::User { name, data, .. }) = attribute else {
+        return None;
+    };
+    (has_date_text(data) || has_epoch_timestamp(data)).then_some(name.as_str())
+}
+
+/// Returns true if the bytes contain a date written like `2024-05-31` or a time written like
+/// `23:59:07`.
+fn has_date_text(data: &[u8]) -> bool {
+    let digits = |window: &[u8], positions: &[usize]| positions.iter().all(|&i| window[i].is_ascii_digit());
+    data.windows(10).any(|window| {
+        digits(window, &[0, 1, 2, 3, 5, 6, 8, 9])
+            && window[4] == b'-'
+            && window[7] == b'-'
+            && matches!(&window[..2], b"19" | b"20")
+    }) || data.windows(8).any(|window| {
+        digits(window, &[0, 1, 3, 4, 6, 7]) && window[2] == b':' && window[5] == b':' && window[0] <= b'2'
+    })
+}
+
+/// Returns true if the bytes contain a big-endian 64-bit count of milliseconds or seconds
+/// since the epoch that falls in this century.
+fn has_epoch_timestamp(data: &[u8]) -> bool {
+    data.windows(8).any(|window| {
+        let value = u64::from_be_bytes(window.try_into().expect("window of 8 bytes"));
+        (MIN_TIMESTAMP_MILLIS..MAX_TIMESTAMP_MILLIS).contains(&value)
+            || (MIN_TIMESTAMP_MILLIS / 1000..MAX_TIMESTAMP_MILLIS / 1000).contains(&value)
+    })
+}
+
+fn is_absolute_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    path.starts_with(['/', '\\']) || (bytes.len() > 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':')
+}
+
+/// Returns true if a part of the name is a long run of hex digits mixing digits and letters,
+/// like a hash, a UUID or a `0x` address.
+fn looks_random(name: &str) -> bool {
+    // javac names serializable lambdas like `lambda$main$9f86d081$1` after a hash of the
+    // lambda itself, which is stable.
+    if name.starts_with("lambda$") {
+        return false;
+    }
+    name.split(['$', '_', '/', '-', '.']).any(|part| {
+        let (part, is_address) = match part.strip_prefix("0x") {
+            Some(digits) => (digits, true),
+            None => (part, false),
+        };
+        part.len() >= MIN_RANDOM_HEX_LENGTH
+            && part.bytes().all(|byte| byte.is_ascii_hexdigit())
+            && part.bytes().any(|byte| byte.is_ascii_digit())
+            && (is_address || part.bytes().any(|byte| byte.is_ascii_alphabetic()))
+    })
+}
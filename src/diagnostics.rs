@@ -0,0 +1,88 @@
+use std::fmt;
+
+/// Kinds of non-fatal problems found while decoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiagnosticKind {
+    /// An attribute whose name is not defined by the JVM specification.
+    UnknownAttribute,
+    /// An access flag which is obsolete or undefined for the class file version.
+    DeprecatedFlag,
+    /// A version number combination which is unusual or not supported.
+    UnusualVersion,
+    /// An attribute which appears more than once on the same owner; only one is kept.
+    DuplicateAttribute,
+    /// Bytes following the class file structure.
+    TrailingBytes,
+    /// A constant pool entry identical to an earlier one.
+    DuplicateConstant,
+    /// An attribute whose body could not be decoded; it is kept as raw bytes.
+    MalformedAttribute,
+    /// A CONSTANT_Utf8 which is not valid modified UTF-8. Its string replaces what it cannot
+    /// hold with U+FFFD, and its bytes are kept to be written back.
+    MalformedUtf8,
+}
+
+/// A non-fatal problem found while decoding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    /// Where the problem was found, e.g. `methods[3]`.
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+/// Diagnostics collected while decoding a class file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Diagnostics {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, kind: DiagnosticKind, location: impl Into<String>, message: impl Into<String>) {
+        self.diagnostics.push(Diagnostic {
+            kind,
+            location: location.into(),
+            message: message.into(),
+        });
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        self.diagnostics.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.diagnostics.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+impl IntoIterator for Diagnostics {
+    type Item = Diagnostic;
+    type IntoIter = std::vec::IntoIter<Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.diagnostics.into_iter()
+    }
+}
+
+impl<'d> IntoIterator for &'d Diagnostics {
+    type Item = &'d Diagnostic;
+    type IntoIter = std::slice::Iter<'d, Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.diagnostics.iter()
+    }
+}
@@ -0,0 +1,290 @@
+use std::fmt;
+
+use crate::{
+    disasm::{decode_instruction, Operand},
+    opcode::instruction_length,
+    types::*,
+};
+
+/// An instruction rendered for comparison, with constant pool references resolved so
+/// that instructions compare equal across builds with differently ordered constant pools.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub pc: usize,
+    /// The mnemonic and operands, e.g. `getstatic java/lang/System.out:Ljava/io/PrintStream;`.
+    /// Branch targets are relative offsets, e.g. `ifeq +12`.
+    pub text: String,
+}
+
+/// Whether an [`Edit`] inserts, deletes or replaces instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditKind {
+    Insert,
+    Delete,
+    Replace,
+}
+
+/// A run of instructions which differ between two versions of a method body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub kind: EditKind,
+    /// Offset in the old code of the first deleted instruction, or of the instruction the
+    /// new ones are inserted before; the code length at the end.
+    pub old_pc: usize,
+    /// Offset in the new code of the first inserted instruction, or of the instruction
+    /// following the deleted ones; the code length at the end.
+    pub new_pc: usize,
+    /// Instructions removed from the old code.
+    pub old: Vec<Instruction>,
+    /// Instructions added in the new code.
+    pub new: Vec<Instruction>,
+}
+
+impl fmt::Display for Edit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "@@ old {} new {} @@", self.old_pc, self.new_pc)?;
+        for instruction in &self.old {
+            writeln!(f, "-{:5}: {}", instruction.pc, instruction.text)?;
+        }
+        for instruction in &self.new {
+            writeln!(f, "+{:5}: {}", instruction.pc, instruction.text)?;
+        }
+        Ok(())
+    }
+}
+
+/// How a method differs between two versions of a class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MethodChange {
+    Added,
+    Removed,
+    /// The code differs; the edits are empty when only one version has code.
+    Changed(Vec<Edit>),
+}
+
+/// A method of [`diff_methods`], identified by name and descriptor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodDiff {
+    pub name: String,
+    pub descriptor: String,
+    pub change: MethodChange,
+}
+
+/// Splits code into instructions. A malformed or truncated instruction ends the list.
+pub fn instructions(code: &[u8], constant_pool: &[ConstantPoolInfo]) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    let mut pc = 0;
+    while let Some(length) = instruction_length(code, pc) {
+        instructions.push(Instruction {
+            pc,
+            text: render(code, pc, constant_pool),
+        });
+        pc += length;
+    }
+    instructions
+}
+
+/// Computes a minimal instruction-level edit script turning `old` code into `new` code.
+///
+/// Instructions are compared by their rendered text, so renumbered constant pool entries
+/// do not show up as changes. Adjacent deletions and insertions are reported as one
+/// replacement.
+pub fn diff_code(
+    old: &[u8],
+    old_constant_pool: &[ConstantPoolInfo],
+    new: &[u8],
+    new_constant_pool: &[ConstantPoolInfo],
+) -> Vec<Edit> {
+    let end = |code: &[u8], instructions: &[Instruction]| {
+        instructions.last().map_or(0, |last| last.pc + instruction_length(code, last.pc).unwrap_or(0))
+    };
+    let (old_code, new_code) = (old, new);
+    let old = instructions(old_code, old_constant_pool);
+    let new = instructions(new_code, new_constant_pool);
+    let (old_end, new_end) = (end(old_code, &old), end(new_code, &new));
+
+    let operations = edit_operations(&old, &new);
+    let mut edits = Vec::new();
+    let (mut old_index, mut new_index) = (0, 0);
+    let mut operations = operations.into_iter().peekable();
+    while let Some(operation) = operations.next() {
+        if operation == Operation::Equal {
+            old_index += 1;
+            new_index += 1;
+            continue;
+        }
+
+        let (old_start, new_start) = (old_index, new_index);
+        let mut operation = Some(operation);
+        while let Some(current) = operation {
+            match current {
+                Operation::Delete => old_index += 1,
+                Operation::Insert => new_index += 1,
+                Operation::Equal => unreachable!(),
+            }
+            operation = operations.next_if(|next| *next != Operation::Equal);
+        }
+        let kind = match (old_index > old_start, new_index > new_start) {
+            (true, true) => EditKind::Replace,
+            (true, false) => EditKind::Delete,
+            _ => EditKind::Insert,
+        };
+        edits.push(Edit {
+            kind,
+            old_pc: old.get(old_start).map_or(old_end, |instruction| instruction.pc),
+            new_pc: new.get(new_start).map_or(new_end, |instruction| instruction.pc),
+            old: old[old_start..old_index].to_vec(),
+            new: new[new_start..new_index].to_vec(),
+        });
+    }
+    edits
+}
+
+/// Compares the methods of two versions of a class by name and descriptor, returning the
+/// added, removed and changed ones in old then new method order.
+pub fn diff_methods(old: &JavaClassFile, new: &JavaClassFile) -> Vec<MethodDiff> {
+    let key = |class_file: &JavaClassFile, method: &MethodInfo| {
+        (
+            get_utf8(&class_file.constant_pool, method.name_index).unwrap_or_default().to_string(),
+            get_utf8(&class_file.constant_pool, method.descriptor_index).unwrap_or_default().to_string(),
+        )
+    };
+
+    let mut diffs = Vec::new();
+    for old_method in &old.methods {
+        let (name, descriptor) = key(old, old_method);
+        let new_method = new.methods.iter().find(|method| key(new, method) == (name.clone(), descriptor.clone()));
+        let change = match new_method {
+            None => MethodChange::Removed,
+            Some(new_method) => match (code(old, old_method), code(new, new_method)) {
+                (Some(old_code), Some(new_code)) => {
+                    let edits = diff_code(old_code, &old.constant_pool, new_code, &new.constant_pool);
+                    if edits.is_empty() {
+                        continue;
+                    }
+                    MethodChange::Changed(edits)
+                }
+                (None, None) => continue,
+                _ => MethodChange::Changed(Vec::new()),
+            },
+        };
+        diffs.push(MethodDiff { name, descriptor, change });
+    }
+    for new_method in &new.methods {
+        let (name, descriptor) = key(new, new_method);
+        if !old.methods.iter().any(|method| key(old, method) == (name.clone(), descriptor.clone())) {
+            diffs.push(MethodDiff {
+                name,
+                descriptor,
+                change: MethodChange::Added,
+            });
+        }
+    }
+    diffs
+}
+
+fn code<'a>(class_file: &JavaClassFile<'a>, method: &MethodInfo<'a>) -> Option<&'a [u8]> {
+    CodeView::new(method, &class_file.constant_pool).ok().flatten().map(|view| view.code)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Operation {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Computes a shortest edit script with Myers' algorithm.
+///
+/// ref. E. W. Myers, "An O(ND) Difference Algorithm and Its Variations", 1986.
+pub(crate) fn edit_operations(old: &[Instruction], new: &[Instruction]) -> Vec<Operation> {
+    let equal = |x: usize, y: usize| old[x].text == new[y].text;
+
+    // The common prefix and suffix do not need the search.
+    let prefix = (0..old.len().min(new.len())).take_while(|&i| equal(i, i)).count();
+    let suffix = (0..old.len().min(new.len()) - prefix)
+        .take_while(|&i| equal(old.len() - 1 - i, new.len() - 1 - i))
+        .count();
+    let (n, m) = ((old.len() - prefix - suffix) as isize, (new.len() - prefix - suffix) as isize);
+    let equal = |x: isize, y: isize| equal(prefix + x as usize, prefix + y as usize);
+
+    // v[k + offset] is the furthest x reached on diagonal k; trace keeps v before each step.
+    let offset = n + m + 1;
+    let mut v = vec![0isize; (2 * offset + 1) as usize];
+    let mut trace = Vec::new();
+    'search: for d in 0..=n + m {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let index = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+                v[index + 1]
+            } else {
+                v[index - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && equal(x, y) {
+                x += 1;
+                y += 1;
+            }
+            v[index] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut operations = vec![Operation::Equal; suffix];
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let previous_k = if k == -d || (k != d && v[(k - 1 + offset) as usize] < v[(k + 1 + offset) as usize]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = v[(previous_k + offset) as usize];
+        let previous_y = previous_x - previous_k;
+        while x > previous_x && y > previous_y {
+            operations.push(Operation::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            operations.push(if x == previous_x { Operation::Insert } else { Operation::Delete });
+        }
+        x = previous_x;
+        y = previous_y;
+    }
+    operations.extend(std::iter::repeat_n(Operation::Equal, prefix));
+    operations.reverse();
+    operations
+}
+
+/// Renders the instruction at `pc`, which `instruction_length` has checked to be complete, from
+/// the operands [`disasm`](crate::disasm) decodes, with branch targets relative to `pc`.
+fn render(code: &[u8], pc: usize, constant_pool: &[ConstantPoolInfo]) -> String {
+    let instruction = decode_instruction(code, pc, constant_pool);
+    let offset = |target: usize| format!("{:+}", target as i64 - pc as i64);
+    let mut text = String::new();
+    if instruction.wide {
+        text.push_str("wide ");
+    }
+    text.push_str(instruction.opcode.mnemonic());
+    for operand in &instruction.operands {
+        text.push(' ');
+        match operand {
+            Operand::Int(value) => text.push_str(&value.to_string()),
+            Operand::LocalVariable(index) => text.push_str(&index.to_string()),
+            Operand::Constant { text: resolved, .. } => text.push_str(resolved),
+            Operand::Target(target) => text.push_str(&offset(*target)),
+            Operand::ArrayType(element) => text.push_str(element),
+            Operand::Switch { cases, default } => {
+                let cases: Vec<String> =
+                    cases.iter().map(|(key, target)| format!("{}: {}", key, offset(*target))).collect();
+                text.push_str(&format!("{{{}}} default {}", cases.join(", "), offset(*default)));
+            }
+        }
+    }
+    text
+}
@@ -0,0 +1,663 @@
+//! A disassembler producing either text or typed lines for rendering elsewhere.
+
+use std::fmt;
+
+use crate::{
+    code::CodeView,
+    opcode::instruction_length,
+    types::*,
+};
+
+/// How [`render`] formats disassembled lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Style {
+    /// Spaces per nesting level; members are at level 1, their code at level 2.
+    pub indent: usize,
+    /// Prefix instructions with their offset in the code, e.g. `12: `.
+    pub show_pc: bool,
+    /// Show constant pool indices of operands, e.g. `#7`.
+    pub show_pool_indices: bool,
+    /// Show what constant pool operands refer to, e.g. `java/lang/Object.<init>:()V`. When
+    /// both this and `show_pool_indices` are set, the names follow the instruction as a
+    /// `//` comment; when neither is, indices are shown.
+    pub resolve_names: bool,
+    /// Write offsets, immediates and indices in hexadecimal, e.g. `0x1F`.
+    pub hex: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            indent: 4,
+            show_pc: true,
+            show_pool_indices: false,
+            resolve_names: true,
+            hex: false,
+        }
+    }
+}
+
+/// An operand of a disassembled instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operand {
+    /// An immediate value, e.g. of bipush, sipush or the increment of iinc.
+    Int(i32),
+    /// A local variable index.
+    LocalVariable(u16),
+    /// A constant pool index and what it refers to, e.g. `java/lang/String` for a class.
+    Constant { index: u16, text: String },
+    /// An absolute branch target.
+    Target(usize),
+    /// The element type of newarray, e.g. `int`.
+    ArrayType(&'static str),
+    /// The keys and absolute targets of tableswitch or lookupswitch.
+    Switch { cases: Vec<(i32, usize)>, default: usize },
+}
+
+/// A disassembled instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub pc: usize,
+    /// The opcode, or for wide the opcode it modifies.
+    pub opcode: Opcode,
+    /// True if prefixed by wide.
+    pub wide: bool,
+    pub operands: Vec<Operand>,
+}
+
+impl Instruction {
+    /// Returns the instruction displayed as javap does with what its constant pool operand
+    /// refers to in a comment, e.g.
+    /// `invokevirtual #12 // Method java/io/PrintStream.println:(Ljava/lang/String;)V`.
+    ///
+    /// Unlike javap, members of the class being disassembled keep their owner.
+    pub fn javap<'i, 'c>(&'i self, constant_pool: &'i [ConstantPoolInfo<'c>]) -> Javap<'i, 'c> {
+        Javap { instruction: self, constant_pool }
+    }
+
+    fn write_javap(&self, f: &mut fmt::Formatter<'_>, constant_pool: Option<&[ConstantPoolInfo]>) -> fmt::Result {
+        f.write_str(self.opcode.mnemonic())?;
+        if self.wide {
+            f.write_str("_w")?;
+        }
+        let mut comment = None;
+        for (position, operand) in self.operands.iter().enumerate() {
+            // javap separates the count of invokeinterface and multianewarray by two spaces.
+            f.write_str(match position.checked_sub(1).map(|previous| &self.operands[previous]) {
+                None => " ",
+                Some(Operand::Constant { .. }) => ",  ",
+                Some(_) => ", ",
+            })?;
+            match operand {
+                Operand::Int(value) => write!(f, "{}", value)?,
+                Operand::LocalVariable(index) => write!(f, "{}", index)?,
+                Operand::Constant { index, .. } => {
+                    write!(f, "#{}", index)?;
+                    comment = constant_pool.and_then(|constant_pool| javap_constant(constant_pool, *index as usize));
+                }
+                Operand::Target(target) => write!(f, "{}", target)?,
+                Operand::ArrayType(element) => f.write_str(element)?,
+                Operand::Switch { cases, default } => {
+                    match (self.opcode, cases.first(), cases.last()) {
+                        (Opcode::Tableswitch, Some((low, _)), Some((high, _))) => {
+                            write!(f, "{{ // {} to {}", low, high)?
+                        }
+                        _ => write!(f, "{{ // {}", cases.len())?,
+                    }
+                    for (key, target) in cases {
+                        write!(f, "\n  {}: {}", key, target)?;
+                    }
+                    write!(f, "\n  default: {}\n}}", default)?;
+                }
+            }
+        }
+        // The two reserved bytes, which are always zero.
+        if self.opcode == Opcode::Invokedynamic {
+            f.write_str(",  0")?;
+        }
+        if let Some(comment) = comment {
+            write!(f, " // {}", comment)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the instruction as javap does, with constant pool operands as indices alone, e.g.
+/// `invokevirtual #12`, branch targets as offsets in the code and switches on several lines.
+/// See [`Instruction::javap`] to resolve the operands.
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_javap(f, None)
+    }
+}
+
+/// An instruction displayed as javap does with its constant pool operand resolved, returned by
+/// [`Instruction::javap`].
+#[derive(Debug, Clone, Copy)]
+pub struct Javap<'i, 'c> {
+    instruction: &'i Instruction,
+    constant_pool: &'i [ConstantPoolInfo<'c>],
+}
+
+impl fmt::Display for Javap<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.instruction.write_javap(f, Some(self.constant_pool))
+    }
+}
+
+/// A line of disassembly. Lines nest by [`Line::depth`]: a class, its members and their code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Line<'c> {
+    Class {
+        access_flags: u16,
+        name: &'c str,
+        super_class: Option<&'c str>,
+        interfaces: Vec<&'c str>,
+    },
+    Field {
+        /// Index of the field in the `fields` of the class file.
+        index: usize,
+        access_flags: u16,
+        name: &'c str,
+        descriptor: &'c str,
+    },
+    Method {
+        /// Index of the method in the `methods` of the class file.
+        index: usize,
+        access_flags: u16,
+        name: &'c str,
+        descriptor: &'c str,
+    },
+    Code { max_stack: u16, max_locals: u16 },
+    Instruction(Instruction),
+    /// The code from `pc` on cannot be decoded, or the Code attribute itself is malformed.
+    Malformed { pc: usize },
+}
+
+impl Line<'_> {
+    /// Returns the nesting level of the line: 0 for the class, 1 for members and 2 for code.
+    pub fn depth(&self) -> usize {
+        match self {
+            Line::Class { .. } => 0,
+            Line::Field { .. } | Line::Method { .. } => 1,
+            Line::Code { .. } | Line::Instruction(_) | Line::Malformed { .. } => 2,
+        }
+    }
+
+    /// Renders the line without indentation.
+    pub fn render(&self, style: &Style) -> String {
+        match self {
+            Line::Class { access_flags, name, super_class, interfaces } => {
+                let keyword = if ClassAccessFlag::Module.test(*access_flags) {
+                    "module"
+                } else if ClassAccessFlag::Annotation.test(*access_flags) {
+                    "@interface"
+                } else if ClassAccessFlag::Interface.test(*access_flags) {
+                    "interface"
+                } else {
+                    "class"
+                };
+                let mut text = modifiers(*access_flags, CLASS_MODIFIERS);
+                text.push_str(keyword);
+                text.push(' ');
+                text.push_str(name);
+                if let Some(super_class) = super_class {
+                    text.push_str(" extends ");
+                    text.push_str(super_class);
+                }
+                if !interfaces.is_empty() {
+                    text.push_str(" implements ");
+                    text.push_str(&interfaces.join(", "));
+                }
+                text
+            }
+            Line::Field { access_flags, name, descriptor, .. } => {
+                format!("{}{} {}", modifiers(*access_flags, FIELD_MODIFIERS), name, descriptor)
+            }
+            Line::Method { access_flags, name, descriptor, .. } => {
+                format!("{}{}{}", modifiers(*access_flags, METHOD_MODIFIERS), name, descriptor)
+            }
+            Line::Code { max_stack, max_locals } => {
+                format!("code stack {} locals {}", number(style, *max_stack as i64), number(style, *max_locals as i64))
+            }
+            Line::Instruction(instruction) => render_instruction(instruction, style),
+            Line::Malformed { pc } if style.show_pc => format!("{}: malformed code", number(style, *pc as i64)),
+            Line::Malformed { .. } => "malformed code".to_string(),
+        }
+    }
+}
+
+/// Keywords of the access flags shown before class, field and method names.
+const CLASS_MODIFIERS: &[(u16, &str)] = &[
+    (ClassAccessFlag::Public as u16, "public"),
+    (ClassAccessFlag::Final as u16, "final"),
+    (ClassAccessFlag::Synthetic as u16, "synthetic"),
+    (ClassAccessFlag::Enum as u16, "enum"),
+];
+const FIELD_MODIFIERS: &[(u16, &str)] = &[
+    (FieldAccessFlag::Public as u16, "public"),
+    (FieldAccessFlag::Private as u16, "private"),
+    (FieldAccessFlag::Protected as u16, "protected"),
+    (FieldAccessFlag::Static as u16, "static"),
+    (FieldAccessFlag::Final as u16, "final"),
+    (FieldAccessFlag::Volatile as u16, "volatile"),
+    (FieldAccessFlag::Transient as u16, "transient"),
+    (FieldAccessFlag::Synthetic as u16, "synthetic"),
+    (FieldAccessFlag::Enum as u16, "enum"),
+];
+const METHOD_MODIFIERS: &[(u16, &str)] = &[
+    (MethodAccessFlag::Public as u16, "public"),
+    (MethodAccessFlag::Private as u16, "private"),
+    (MethodAccessFlag::Protected as u16, "protected"),
+    (MethodAccessFlag::Static as u16, "static"),
+    (MethodAccessFlag::Final as u16, "final"),
+    (MethodAccessFlag::Synchronized as u16, "synchronized"),
+    (MethodAccessFlag::Bridge as u16, "bridge"),
+    (MethodAccessFlag::Varargs as u16, "varargs"),
+    (MethodAccessFlag::Native as u16, "native"),
+    (MethodAccessFlag::Abstract as u16, "abstract"),
+    (MethodAccessFlag::Strict as u16, "strictfp"),
+    (MethodAccessFlag::Synthetic as u16, "synthetic"),
+];
+
+/// Returns the keywords of the set flags, each followed by a space.
+fn modifiers(access_flags: u16, keywords: &[(u16, &str)]) -> String {
+    let mut text = String::new();
+    for (flag, keyword) in keywords {
+        if access_flags & flag != 0 {
+            text.push_str(keyword);
+            text.push(' ');
+        }
+    }
+    text
+}
+
+fn number(style: &Style, value: i64) -> String {
+    match (style.hex, value < 0) {
+        (false, _) => value.to_string(),
+        (true, false) => format!("0x{:X}", value),
+        (true, true) => format!("-0x{:X}", value.unsigned_abs()),
+    }
+}
+
+fn render_instruction(instruction: &Instruction, style: &Style) -> String {
+    let mut text = String::new();
+    if style.show_pc {
+        text.push_str(&number(style, instruction.pc as i64));
+        text.push_str(": ");
+    }
+    if instruction.wide {
+        text.push_str("wide ");
+    }
+    text.push_str(instruction.opcode.mnemonic());
+
+    let mut comments = Vec::new();
+    for operand in &instruction.operands {
+        text.push(' ');
+        match operand {
+            Operand::Int(value) => text.push_str(&number(style, *value as i64)),
+            Operand::LocalVariable(index) => text.push_str(&number(style, *index as i64)),
+            Operand::Constant { index, text: resolved } => {
+                if style.resolve_names && !style.show_pool_indices {
+                    text.push_str(resolved);
+                } else {
+                    text.push('#');
+                    text.push_str(&number(style, *index as i64));
+                    if style.resolve_names {
+                        comments.push(resolved.as_str());
+                    }
+                }
+            }
+            Operand::Target(target) => text.push_str(&number(style, *target as i64)),
+            Operand::ArrayType(element) => text.push_str(element),
+            Operand::Switch { cases, default } => {
+                text.push('{');
+                for (key, target) in cases {
+                    text.push_str(&format!(" {}: {},", number(style, *key as i64), number(style, *target as i64)));
+                }
+                text.push_str(&format!(" default: {} }}", number(style, *default as i64)));
+            }
+        }
+    }
+    if !comments.is_empty() {
+        text.push_str(" // ");
+        text.push_str(&comments.join(", "));
+    }
+    text
+}
+
+/// Returns the constant at `index` as javap comments it, e.g. `String hello`, `long 1l` or
+/// `Method java/lang/Object."<init>":()V`.
+fn javap_constant(constant_pool: &[ConstantPoolInfo], index: usize) -> Option<String> {
+    let member = |index: usize| {
+        let (owner, name, descriptor) = get_member_ref(constant_pool, index)?;
+        Some(format!("{}.{}:{}", javap_name(owner), javap_name(name), descriptor))
+    };
+    let name_and_type = |index: usize| match constant_pool.get(index)? {
+        ConstantPoolInfo::NameAndType(info) => Some(format!(
+            "{}:{}",
+            javap_name(get_utf8(constant_pool, info.name_index)?),
+            get_utf8(constant_pool, info.descriptor_index)?
+        )),
+        _ => None,
+    };
+    Some(match constant_pool.get(index)? {
+        ConstantPoolInfo::Integer(info) => format!("int {}", info.data),
+        ConstantPoolInfo::Float(info) => format!("float {}f", java_floating(info.data, info.data as f64)),
+        ConstantPoolInfo::Long(info) => format!("long {}l", info.data),
+        ConstantPoolInfo::Double(info) => format!("double {}d", java_floating(info.data, info.data)),
+        ConstantPoolInfo::String(info) => {
+            format!("String {}", java_escape(get_utf8(constant_pool, info.string_index)?))
+        }
+        ConstantPoolInfo::Class(_) => format!("class {}", javap_name(get_class_name(constant_pool, index)?)),
+        ConstantPoolInfo::FieldRef(_) => format!("Field {}", member(index)?),
+        ConstantPoolInfo::MethodRef(_) => format!("Method {}", member(index)?),
+        ConstantPoolInfo::InterfaceMethodRef(_) => format!("InterfaceMethod {}", member(index)?),
+        ConstantPoolInfo::MethodType(info) => format!("MethodType {}", get_utf8(constant_pool, info.descriptor_index)?),
+        ConstantPoolInfo::MethodHandle(info) => {
+            let kind = REFERENCE_KINDS.get(info.reference_kind as usize)?;
+            format!("MethodHandle {} {}", kind, member(info.reference_index)?)
+        }
+        ConstantPoolInfo::InvokeDynamic(info) => {
+            format!("InvokeDynamic #{}:{}", info.bootstrap_method_attr_index, name_and_type(info.name_and_type_index)?)
+        }
+        ConstantPoolInfo::Dynamic(info) => {
+            format!("Dynamic #{}:{}", info.bootstrap_method_handle_attr_index, name_and_type(info.name_and_type_index)?)
+        }
+        _ => return None,
+    })
+}
+
+/// Names of the reference kinds of CONSTANT_MethodHandle, by kind.
+const REFERENCE_KINDS: [&str; 10] = [
+    "",
+    "REF_getField",
+    "REF_getStatic",
+    "REF_putField",
+    "REF_putStatic",
+    "REF_invokeVirtual",
+    "REF_invokeStatic",
+    "REF_invokeSpecial",
+    "REF_newInvokeSpecial",
+    "REF_invokeInterface",
+];
+
+/// Quotes array class names and special method names as javap does, e.g. `"[I"` and `"<init>"`.
+fn javap_name(name: &str) -> String {
+    match name.starts_with(['[', '<']) {
+        true => format!("\"{}\"", name),
+        false => name.to_string(),
+    }
+}
+
+/// Formats a float or double as Java's `toString` does, e.g. `0.001`, `1.0E7` or `NaN`.
+fn java_floating<T: fmt::Debug + fmt::LowerExp>(value: T, as_f64: f64) -> String {
+    if as_f64.is_nan() {
+        return "NaN".to_string();
+    }
+    if as_f64.is_infinite() {
+        return if as_f64 < 0.0 { "-Infinity" } else { "Infinity" }.to_string();
+    }
+    if as_f64 == 0.0 || (1e-3..1e7).contains(&as_f64.abs()) {
+        return format!("{:?}", value);
+    }
+    let text = format!("{:e}", value);
+    let (mantissa, exponent) = text.split_once('e').expect("exponent of {:e}");
+    match mantissa.contains('.') {
+        true => format!("{}E{}", mantissa, exponent),
+        false => format!("{}.0E{}", mantissa, exponent),
+    }
+}
+
+/// Escapes a string as javap shows string constants, e.g. a line feed as `\n`.
+fn java_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\u{8}' => escaped.push_str("\\b"),
+            '\u{c}' => escaped.push_str("\\f"),
+            '"' => escaped.push_str("\\\""),
+            '\'' => escaped.push_str("\\'"),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Disassembles a class into its header, fields and methods, the latter followed by their code.
+///
+/// Names that cannot be resolved are empty; code that cannot be decoded ends with
+/// [`Line::Malformed`].
+pub fn disassemble<'c>(class_file: &'c JavaClassFile) -> Vec<Line<'c>> {
+    let constant_pool = &class_file.constant_pool;
+    let mut lines = vec![Line::Class {
+        access_flags: class_file.access_flags,
+        name: get_class_name(constant_pool, class_file.this_class).unwrap_or_default(),
+        super_class: get_class_name(constant_pool, class_file.super_class),
+        interfaces: class_file
+            .interfaces
+            .iter()
+            .map(|&interface| get_class_name(constant_pool, interface).unwrap_or_default())
+            .collect(),
+    }];
+
+    for (index, field) in class_file.fields.iter().enumerate() {
+        lines.push(Line::Field {
+            index,
+            access_flags: field.access_flags,
+            name: get_utf8(constant_pool, field.name_index).unwrap_or_default(),
+            descriptor: get_utf8(constant_pool, field.descriptor_index).unwrap_or_default(),
+        });
+    }
+
+    for (index, method) in class_file.methods.iter().enumerate() {
+        lines.push(Line::Method {
+            index,
+            access_flags: method.access_flags,
+            name: get_utf8(constant_pool, method.name_index).unwrap_or_default(),
+            descriptor: get_utf8(constant_pool, method.descriptor_index).unwrap_or_default(),
+        });
+        match CodeView::new(method, constant_pool) {
+            Ok(Some(code)) => {
+                lines.push(Line::Code { max_stack: code.max_stack, max_locals: code.max_locals });
+                let instructions = disassemble_code(code.code, constant_pool);
+                let end = instructions.last().map_or(0, |last| {
+                    last.pc + instruction_length(code.code, last.pc).expect("decoded by disassemble_code")
+                });
+                lines.extend(instructions.into_iter().map(Line::Instruction));
+                if end < code.code.len() {
+                    lines.push(Line::Malformed { pc: end });
+                }
+            }
+            Ok(None) => {}
+            Err(_) => lines.push(Line::Malformed { pc: 0 }),
+        }
+    }
+
+    lines
+}
+
+/// Disassembles code into instructions. A malformed or truncated instruction ends the list.
+pub fn disassemble_code(code: &[u8], constant_pool: &[ConstantPoolInfo]) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    let mut pc = 0;
+    while let Some(length) = instruction_length(code, pc) {
+        instructions.push(decode_instruction(code, pc, constant_pool));
+        pc += length;
+    }
+    instructions
+}
+
+/// Decodes the instruction at `pc`, which `instruction_length` has checked to be complete.
+pub(crate) fn decode_instruction(code: &[u8], pc: usize, constant_pool: &[ConstantPoolInfo]) -> Instruction {
+    let opcode = Opcode::from_u8(code[pc]).expect("checked by instruction_length");
+    let u8_at = |at: usize| code[at];
+    let u16_at = |at: usize| u16::from_be_bytes([code[at], code[at + 1]]);
+    let i32_at = |at: usize| i32::from_be_bytes([code[at], code[at + 1], code[at + 2], code[at + 3]]);
+    let target = |offset: i32| (pc as i64 + offset as i64) as usize;
+    let constant = |index: u16, text: String| Operand::Constant { index, text };
+    let class = |index: u16| {
+        let name = get_class_name(constant_pool, index as usize).unwrap_or_default();
+        Operand::Constant { index, text: name.to_string() }
+    };
+    let instruction = |opcode: Opcode, operands: Vec<Operand>| Instruction { pc, opcode, wide: false, operands };
+
+    match opcode {
+        Opcode::Bipush => instruction(opcode, vec![Operand::Int(u8_at(pc + 1) as i8 as i32)]),
+        Opcode::Sipush => instruction(opcode, vec![Operand::Int(u16_at(pc + 1) as i16 as i32)]),
+        Opcode::Ldc => {
+            let index = u8_at(pc + 1) as u16;
+            instruction(opcode, vec![constant(index, render_constant(constant_pool, index as usize))])
+        }
+        Opcode::LdcW | Opcode::Ldc2W => {
+            let index = u16_at(pc + 1);
+            instruction(opcode, vec![constant(index, render_constant(constant_pool, index as usize))])
+        }
+        Opcode::Iload | Opcode::Lload | Opcode::Fload | Opcode::Dload | Opcode::Aload | Opcode::Istore
+        | Opcode::Lstore | Opcode::Fstore | Opcode::Dstore | Opcode::Astore | Opcode::Ret => {
+            instruction(opcode, vec![Operand::LocalVariable(u8_at(pc + 1) as u16)])
+        }
+        Opcode::Iinc => instruction(
+            opcode,
+            vec![Operand::LocalVariable(u8_at(pc + 1) as u16), Operand::Int(u8_at(pc + 2) as i8 as i32)],
+        ),
+        Opcode::Wide => {
+            let modified = Opcode::from_u8(u8_at(pc + 1)).expect("checked by instruction_length");
+            let mut operands = vec![Operand::LocalVariable(u16_at(pc + 2))];
+            if modified == Opcode::Iinc {
+                operands.push(Operand::Int(u16_at(pc + 4) as i16 as i32));
+            }
+            Instruction { pc, opcode: modified, wide: true, operands }
+        }
+        Opcode::Getstatic | Opcode::Putstatic | Opcode::Getfield | Opcode::Putfield | Opcode::Invokevirtual
+        | Opcode::Invokespecial | Opcode::Invokestatic => {
+            let index = u16_at(pc + 1);
+            instruction(opcode, vec![constant(index, render_member(constant_pool, index as usize))])
+        }
+        Opcode::Invokeinterface => {
+            let index = u16_at(pc + 1);
+            let operands = vec![
+                constant(index, render_member(constant_pool, index as usize)),
+                Operand::Int(u8_at(pc + 3) as i32),
+            ];
+            instruction(opcode, operands)
+        }
+        Opcode::Invokedynamic => {
+            let index = u16_at(pc + 1);
+            let text = match constant_pool.get(index as usize) {
+                Some(ConstantPoolInfo::InvokeDynamic(info)) => format!(
+                    "bootstrap {} {}",
+                    info.bootstrap_method_attr_index,
+                    render_name_and_type(constant_pool, info.name_and_type_index)
+                ),
+                _ => format!("#{}", index),
+            };
+            instruction(opcode, vec![constant(index, text)])
+        }
+        Opcode::New | Opcode::Anewarray | Opcode::Checkcast | Opcode::Instanceof => {
+            instruction(opcode, vec![class(u16_at(pc + 1))])
+        }
+        Opcode::Multianewarray => {
+            instruction(opcode, vec![class(u16_at(pc + 1)), Operand::Int(u8_at(pc + 3) as i32)])
+        }
+        Opcode::Newarray => {
+            let element = match u8_at(pc + 1) {
+                4 => "boolean",
+                5 => "char",
+                6 => "float",
+                7 => "double",
+                8 => "byte",
+                9 => "short",
+                10 => "int",
+                11 => "long",
+                _ => "?",
+            };
+            instruction(opcode, vec![Operand::ArrayType(element)])
+        }
+        Opcode::GotoW | Opcode::JsrW => instruction(opcode, vec![Operand::Target(target(i32_at(pc + 1)))]),
+        Opcode::Tableswitch => {
+            let switch = TableSwitch::decode(code, pc).expect("checked by instruction_length");
+            let cases = switch.cases().map(|(key, offset)| (key, target(offset))).collect();
+            instruction(opcode, vec![Operand::Switch { cases, default: target(switch.default) }])
+        }
+        Opcode::Lookupswitch => {
+            let switch = LookupSwitch::decode(code, pc).expect("checked by instruction_length");
+            let cases = switch.pairs.iter().map(|(key, offset)| (*key, target(*offset))).collect();
+            instruction(opcode, vec![Operand::Switch { cases, default: target(switch.default) }])
+        }
+        opcode if opcode.category() == OpcodeCategory::Branch => {
+            instruction(opcode, vec![Operand::Target(target(u16_at(pc + 1) as i16 as i32))])
+        }
+        _ => instruction(opcode, Vec::new()),
+    }
+}
+
+/// Renders a constant loaded by ldc, ldc_w or ldc2_w.
+fn render_constant(constant_pool: &[ConstantPoolInfo], index: usize) -> String {
+    match constant_pool.get(index) {
+        Some(ConstantPoolInfo::Integer(info)) => format!("int {}", info.data),
+        Some(ConstantPoolInfo::Float(info)) => format!("float {:?}", info.data),
+        Some(ConstantPoolInfo::Long(info)) => format!("long {}", info.data),
+        Some(ConstantPoolInfo::Double(info)) => format!("double {:?}", info.data),
+        Some(ConstantPoolInfo::String(info)) => {
+            format!("{:?}", get_utf8(constant_pool, info.string_index).unwrap_or_default())
+        }
+        Some(ConstantPoolInfo::Class(_)) => {
+            format!("class {}", get_class_name(constant_pool, index).unwrap_or_default())
+        }
+        Some(ConstantPoolInfo::MethodType(info)) => {
+            format!("methodtype {}", get_utf8(constant_pool, info.descriptor_index).unwrap_or_default())
+        }
+        Some(ConstantPoolInfo::MethodHandle(info)) => {
+            format!("methodhandle {} {}", info.reference_kind, render_member(constant_pool, info.reference_index))
+        }
+        Some(ConstantPoolInfo::Dynamic(info)) => format!(
+            "dynamic #{} {}",
+            info.bootstrap_method_handle_attr_index,
+            render_name_and_type(constant_pool, info.name_and_type_index)
+        ),
+        _ => format!("#{}", index),
+    }
+}
+
+fn render_name_and_type(constant_pool: &[ConstantPoolInfo], index: usize) -> String {
+    match constant_pool.get(index) {
+        Some(ConstantPoolInfo::NameAndType(info)) => format!(
+            "{}:{}",
+            get_utf8(constant_pool, info.name_index).unwrap_or_default(),
+            get_utf8(constant_pool, info.descriptor_index).unwrap_or_default()
+        ),
+        _ => format!("#{}", index),
+    }
+}
+
+/// Renders a field, method or interface method reference as `owner.name:descriptor`.
+fn render_member(constant_pool: &[ConstantPoolInfo], index: usize) -> String {
+    let (class_index, name_and_type_index) = match constant_pool.get(index) {
+        Some(ConstantPoolInfo::FieldRef(info)) => (info.class_index, info.name_and_type_index),
+        Some(ConstantPoolInfo::MethodRef(info)) => (info.class_index, info.name_and_type_index),
+        Some(ConstantPoolInfo::InterfaceMethodRef(info)) => (info.class_index, info.name_and_type_index),
+        _ => return format!("#{}", index),
+    };
+    format!(
+        "{}.{}",
+        get_class_name(constant_pool, class_index).unwrap_or_default(),
+        render_name_and_type(constant_pool, name_and_type_index)
+    )
+}
+
+/// Renders lines as text, one per line, indented by their depth.
+pub fn render(lines: &[Line], style: &Style) -> String {
+    let mut text = String::new();
+    for line in lines {
+        text.push_str(&" ".repeat(style.indent * line.depth()));
+        text.push_str(&line.render(style));
+        text.push('\n');
+    }
+    text
+}
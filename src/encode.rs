@@ -0,0 +1,671 @@
+use crate::{
+    cfg::{compute_maxs, Maxs},
+    code::CodeView,
+    dedup::dedup_constants,
+    error::EncodeError,
+    types::*,
+    utils::*,
+};
+
+/// Options for encoding a Java class file.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EncodeOptions {
+    /// Order in which the attributes of each owner are written.
+    pub attribute_order: AttributeOrder,
+    /// Whether to merge identical constant pool entries and renumber the references to
+    /// them. Class files with non-standard attributes, whose references cannot be
+    /// located, are written without merging.
+    pub dedup_constants: bool,
+    /// Whether to write the max_stack and max_locals of the code of methods as computed by
+    /// [`compute_maxs`] instead of those of the Code attributes, e.g. after rewriting code.
+    /// Code whose maxs cannot be computed keeps its own.
+    pub compute_maxs: bool,
+}
+
+/// Encodes a Java class file into bytes.
+///
+/// Attributes are written in the order they were decoded. Fails if a count, index or
+/// length does not fit its field, e.g. a CONSTANT_Utf8 longer than 65535 bytes.
+pub fn encode(class_file: &JavaClassFile) -> Result<Vec<u8>, EncodeError> {
+    encode_with_options(class_file, &EncodeOptions::default())
+}
+
+/// Encodes a Java class file into bytes with options.
+pub fn encode_with_options(class_file: &JavaClassFile, options: &EncodeOptions) -> Result<Vec<u8>, EncodeError> {
+    let constant_pool = &class_file.constant_pool;
+    let mut buffer = Vec::new();
+
+    write_u32(&mut buffer, class_file.magic);
+    write_u16(&mut buffer, class_file.minor_version);
+    write_u16(&mut buffer, class_file.major_version);
+
+    encode_constant_pool(&mut buffer, constant_pool)?;
+
+    write_u16(&mut buffer, class_file.access_flags);
+    write_u16(&mut buffer, narrow(class_file.this_class, "this_class")?);
+    write_u16(&mut buffer, narrow(class_file.super_class, "super_class")?);
+
+    write_u16(&mut buffer, narrow(class_file.interfaces.len(), "interfaces_count")?);
+    for interface in &class_file.interfaces {
+        write_u16(&mut buffer, narrow(*interface, "interfaces")?);
+    }
+
+    write_u16(&mut buffer, narrow(class_file.fields.len(), "fields_count")?);
+    for field in &class_file.fields {
+        write_u16(&mut buffer, field.access_flags);
+        write_u16(&mut buffer, narrow(field.name_index, "name_index")?);
+        write_u16(&mut buffer, narrow(field.descriptor_index, "descriptor_index")?);
+        encode_attributes(&mut buffer, &field.attributes, constant_pool, options)?;
+    }
+
+    write_u16(&mut buffer, narrow(class_file.methods.len(), "methods_count")?);
+    for method in &class_file.methods {
+        write_u16(&mut buffer, method.access_flags);
+        write_u16(&mut buffer, narrow(method.name_index, "name_index")?);
+        write_u16(&mut buffer, narrow(method.descriptor_index, "descriptor_index")?);
+        let attributes_start = buffer.len();
+        encode_attributes(&mut buffer, &method.attributes, constant_pool, options)?;
+        if options.compute_maxs {
+            if let Some(maxs) = method_maxs(method, constant_pool) {
+                patch_code_maxs(&mut buffer[attributes_start..], constant_pool, maxs);
+            }
+        }
+    }
+
+    encode_attributes(&mut buffer, &class_file.attributes, constant_pool, options)?;
+    buffer.extend_from_slice(class_file.trailing_bytes);
+
+    if options.dedup_constants {
+        if let Some(deduplicated) = dedup_constants(&buffer) {
+            return Ok(deduplicated);
+        }
+    }
+    Ok(buffer)
+}
+
+/// Narrows a count, index or length to the width of its field, failing instead of truncating it.
+fn narrow<T: TryFrom<usize>>(value: usize, while_encoding: &'static str) -> Result<T, EncodeError> {
+    T::try_from(value).map_err(|_| EncodeError::Overflow {
+        while_encoding,
+        value,
+        max: usize::MAX >> (usize::BITS - 8 * size_of::<T>() as u32),
+    })
+}
+
+fn method_maxs(method: &MethodInfo, constant_pool: &[ConstantPoolInfo]) -> Option<Maxs> {
+    let code = CodeView::new(method, constant_pool).ok()??;
+    let descriptor = get_utf8(constant_pool, method.descriptor_index)?;
+    let is_static = MethodAccessFlag::Static.test(method.access_flags);
+    compute_maxs(code.code, &code.exception_table, constant_pool, descriptor, is_static).ok()
+}
+
+/// Overwrites max_stack and max_locals of the Code attribute among the encoded `attributes`.
+fn patch_code_maxs(attributes: &mut [u8], constant_pool: &[ConstantPoolInfo], maxs: Maxs) {
+    let count = u16::from_be_bytes([attributes[0], attributes[1]]);
+    let mut offset = 2;
+    for _ in 0..count {
+        let name_index = u16::from_be_bytes([attributes[offset], attributes[offset + 1]]) as usize;
+        let length = u32::from_be_bytes(attributes[offset + 2..offset + 6].try_into().unwrap()) as usize;
+        if get_utf8(constant_pool, name_index) == Some("Code") {
+            attributes[offset + 6..offset + 8].copy_from_slice(&maxs.max_stack.to_be_bytes());
+            attributes[offset + 8..offset + 10].copy_from_slice(&maxs.max_locals.to_be_bytes());
+        }
+        offset += 6 + length;
+    }
+}
+
+/// Encodes a constant pool.
+pub(crate) fn encode_constant_pool(
+    buffer: &mut Vec<u8>,
+    constant_pool: &[ConstantPoolInfo],
+) -> Result<(), EncodeError> {
+    // Index 0 is never used, so an empty pool is written like one holding only that entry.
+    write_u16(buffer, narrow(constant_pool.len().max(1), "constant_pool_count")?);
+
+    for constant in constant_pool {
+        encode_constant(buffer, constant)?;
+    }
+    Ok(())
+}
+
+/// Encodes one constant pool entry.
+pub(crate) fn encode_constant(buffer: &mut Vec<u8>, constant: &ConstantPoolInfo) -> Result<(), EncodeError> {
+    match constant {
+        // The unusable entries (index 0 and the ones following Long/Double) are not written.
+        ConstantPoolInfo::Dummy() => {}
+
+        ConstantPoolInfo::Class(info) => {
+            write_u8(buffer, ConstantKind::Class as u8);
+            write_u16(buffer, narrow(info.name_index, "name_index")?);
+        }
+
+        ConstantPoolInfo::FieldRef(info) => {
+            write_u8(buffer, ConstantKind::FieldRef as u8);
+            write_u16(buffer, narrow(info.class_index, "class_index")?);
+            write_u16(buffer, narrow(info.name_and_type_index, "name_and_type_index")?);
+        }
+
+        ConstantPoolInfo::MethodRef(info) => {
+            write_u8(buffer, ConstantKind::MethodRef as u8);
+            write_u16(buffer, narrow(info.class_index, "class_index")?);
+            write_u16(buffer, narrow(info.name_and_type_index, "name_and_type_index")?);
+        }
+
+        ConstantPoolInfo::InterfaceMethodRef(info) => {
+            write_u8(buffer, ConstantKind::InterfaceMethodRef as u8);
+            write_u16(buffer, narrow(info.class_index, "class_index")?);
+            write_u16(buffer, narrow(info.name_and_type_index, "name_and_type_index")?);
+        }
+
+        ConstantPoolInfo::String(info) => {
+            write_u8(buffer, ConstantKind::String as u8);
+            write_u16(buffer, narrow(info.string_index, "string_index")?);
+        }
+
+        ConstantPoolInfo::Integer(info) => {
+            write_u8(buffer, ConstantKind::Integer as u8);
+            buffer.extend_from_slice(&info.data.to_be_bytes());
+        }
+
+        ConstantPoolInfo::Float(info) => {
+            write_u8(buffer, ConstantKind::Float as u8);
+            buffer.extend_from_slice(&info.data.to_bits().to_be_bytes());
+        }
+
+        ConstantPoolInfo::Long(info) => {
+            write_u8(buffer, ConstantKind::Long as u8);
+            buffer.extend_from_slice(&info.data.to_be_bytes());
+        }
+
+        ConstantPoolInfo::Double(info) => {
+            write_u8(buffer, ConstantKind::Double as u8);
+            buffer.extend_from_slice(&info.data.to_bits().to_be_bytes());
+        }
+
+        ConstantPoolInfo::NameAndType(info) => {
+            write_u8(buffer, ConstantKind::NameAndType as u8);
+            write_u16(buffer, narrow(info.name_index, "name_index")?);
+            write_u16(buffer, narrow(info.descriptor_index, "descriptor_index")?);
+        }
+
+        ConstantPoolInfo::Utf8(info) => {
+            write_u8(buffer, ConstantKind::Utf8 as u8);
+            let data = info.encoded();
+            write_u16(buffer, narrow(data.len(), "CONSTANT_Utf8 length")?);
+            buffer.extend_from_slice(&data);
+        }
+
+        ConstantPoolInfo::MethodHandle(info) => {
+            write_u8(buffer, ConstantKind::MethodHandle as u8);
+            write_u8(buffer, info.reference_kind);
+            write_u16(buffer, narrow(info.reference_index, "reference_index")?);
+        }
+
+        ConstantPoolInfo::MethodType(info) => {
+            write_u8(buffer, ConstantKind::MethodType as u8);
+            write_u16(buffer, narrow(info.descriptor_index, "descriptor_index")?);
+        }
+
+        ConstantPoolInfo::Dynamic(info) => {
+            write_u8(buffer, ConstantKind::Dynamic as u8);
+            write_u16(buffer, narrow(info.bootstrap_method_handle_attr_index, "bootstrap_method_attr_index")?);
+            write_u16(buffer, narrow(info.name_and_type_index, "name_and_type_index")?);
+        }
+
+        ConstantPoolInfo::InvokeDynamic(info) => {
+            write_u8(buffer, ConstantKind::InvokeDynamic as u8);
+            write_u16(buffer, narrow(info.bootstrap_method_attr_index, "bootstrap_method_attr_index")?);
+            write_u16(buffer, narrow(info.name_and_type_index, "name_and_type_index")?);
+        }
+
+        ConstantPoolInfo::Module(info) => {
+            write_u8(buffer, ConstantKind::Module as u8);
+            write_u16(buffer, narrow(info.name_index, "name_index")?);
+        }
+
+        ConstantPoolInfo::Package(info) => {
+            write_u8(buffer, ConstantKind::Package as u8);
+            write_u16(buffer, narrow(info.name_index, "name_index")?);
+        }
+    }
+    Ok(())
+}
+
+/// Encodes the attributes of an owner in the requested order.
+fn encode_attributes(
+    buffer: &mut Vec<u8>,
+    attributes: &Attributes,
+    constant_pool: &[ConstantPoolInfo],
+    options: &EncodeOptions,
+) -> Result<(), EncodeError> {
+    let ordered = ordered_attributes(attributes, options.attribute_order, constant_pool);
+
+    write_u16(buffer, narrow(ordered.len(), "attributes_count")?);
+    for (index, attribute) in ordered {
+        write_u16(buffer, *index);
+
+        // attribute_length is patched once the body has been written.
+        let length_position = buffer.len();
+        write_u32(buffer, 0);
+        encode_attribute_body(buffer, attribute, constant_pool, options)?;
+
+        let attribute_length: u32 = narrow(buffer.len() - length_position - size_of::<u32>(), "attribute_length")?;
+        buffer[length_position..length_position + size_of::<u32>()].copy_from_slice(&attribute_length.to_be_bytes());
+    }
+    Ok(())
+}
+
+/// Encodes the body of an attribute, i.e. everything following attribute_length.
+pub(crate) fn encode_attribute_body(
+    buffer: &mut Vec<u8>,
+    attribute: &AttributeInfo,
+    constant_pool: &[ConstantPoolInfo],
+    options: &EncodeOptions,
+) -> Result<(), EncodeError> {
+    match attribute {
+        AttributeInfo::ConstantValue(attribute) => {
+            write_u16(buffer, attribute.constant_value_index);
+        }
+
+        AttributeInfo::Code(attribute) => {
+            write_u16(buffer, attribute.max_stack);
+            write_u16(buffer, attribute.max_locals);
+            write_u32(buffer, narrow(attribute.code.len(), "code_length")?);
+            buffer.extend_from_slice(attribute.code);
+            write_u16(buffer, narrow(attribute.exception_table.len(), "exception_table_length")?);
+            for entry in &attribute.exception_table {
+                write_u16(buffer, entry.start_pc);
+                write_u16(buffer, entry.end_pc);
+                write_u16(buffer, entry.handler_pc);
+                write_u16(buffer, entry.catch_type);
+            }
+            encode_attributes(buffer, &attribute.attributes, constant_pool, options)?;
+        }
+
+        AttributeInfo::StackMapTable(attribute) => {
+            write_u16(buffer, narrow(attribute.entries.len(), "number_of_entries")?);
+            for frame in &attribute.entries {
+                encode_stack_map_frame(buffer, frame)?;
+            }
+        }
+
+        AttributeInfo::Exceptions(attribute) => {
+            encode_indices(buffer, &attribute.exception_index_table)?;
+        }
+
+        AttributeInfo::InnerClasses(attribute) => {
+            write_u16(buffer, narrow(attribute.classes.len(), "number_of_classes")?);
+            for class in &attribute.classes {
+                write_u16(buffer, class.inner_class_info_index);
+                write_u16(buffer, class.outer_class_info_index);
+                write_u16(buffer, class.inner_name_index);
+                write_u16(buffer, class.inner_class_access_flags);
+            }
+        }
+
+        AttributeInfo::EnclosingMethod(attribute) => {
+            write_u16(buffer, attribute.class_index);
+            write_u16(buffer, attribute.method_index);
+        }
+
+        AttributeInfo::Synthetic(_) => {}
+
+        AttributeInfo::Deprecated(_) => {}
+
+        AttributeInfo::Signature(attribute) => {
+            write_u16(buffer, attribute.signature_index);
+        }
+
+        AttributeInfo::SourceFile(attribute) => {
+            write_u16(buffer, attribute.sourcefile_index);
+        }
+
+        AttributeInfo::LineNumberTable(attribute) => {
+            write_u16(buffer, narrow(attribute.line_number_table.len(), "line_number_table_length")?);
+            for entry in &attribute.line_number_table {
+                write_u16(buffer, entry.start_pc);
+                write_u16(buffer, entry.line_number);
+            }
+        }
+
+        AttributeInfo::LocalVariableTable(attribute) => {
+            write_u16(buffer, narrow(attribute.local_variable_table.len(), "local_variable_table_length")?);
+            for entry in &attribute.local_variable_table {
+                write_u16(buffer, entry.start_pc);
+                write_u16(buffer, entry.length);
+                write_u16(buffer, narrow(entry.name_index, "name_index")?);
+                write_u16(buffer, narrow(entry.descriptor_index, "descriptor_index")?);
+                write_u16(buffer, narrow(entry.index, "index")?);
+            }
+        }
+
+        AttributeInfo::LocalVariableTypeTable(attribute) => {
+            write_u16(buffer, narrow(attribute.local_variable_type_table.len(), "local_variable_type_table_length")?);
+            for entry in &attribute.local_variable_type_table {
+                write_u16(buffer, entry.start_pc);
+                write_u16(buffer, entry.length);
+                write_u16(buffer, narrow(entry.name_index, "name_index")?);
+                write_u16(buffer, narrow(entry.signature_index, "signature_index")?);
+                write_u16(buffer, narrow(entry.index, "index")?);
+            }
+        }
+
+        AttributeInfo::RuntimeVisibleAnnotations(attribute) => {
+            encode_annotations(buffer, &attribute.annotations)?;
+        }
+
+        AttributeInfo::RuntimeInvisibleAnnotations(attribute) => {
+            encode_annotations(buffer, &attribute.annotations)?;
+        }
+
+        AttributeInfo::RuntimeVisibleParameterAnnotations(attribute) => {
+            encode_parameter_annotations(buffer, &attribute.parameter_annotations)?;
+        }
+
+        AttributeInfo::RuntimeInvisibleParameterAnnotations(attribute) => {
+            encode_parameter_annotations(buffer, &attribute.parameter_annotations)?;
+        }
+
+        AttributeInfo::RuntimeVisibleTypeAnnotations(attribute) => {
+            encode_type_annotations(buffer, &attribute.annotations)?;
+        }
+
+        AttributeInfo::RuntimeInvisibleTypeAnnotations(attribute) => {
+            encode_type_annotations(buffer, &attribute.annotations)?;
+        }
+
+        AttributeInfo::BootstrapMethods(attribute) => {
+            write_u16(buffer, narrow(attribute.bootstrap_methods.len(), "num_bootstrap_methods")?);
+            for method in &attribute.bootstrap_methods {
+                write_u16(buffer, narrow(method.bootstrap_method_ref, "bootstrap_method_ref")?);
+                write_u16(buffer, narrow(method.bootstrap_arguments.len(), "num_bootstrap_arguments")?);
+                for argument in &method.bootstrap_arguments {
+                    write_u16(buffer, narrow(*argument, "bootstrap_arguments")?);
+                }
+            }
+        }
+
+        AttributeInfo::Module(attribute) => {
+            write_u16(buffer, attribute.module_name_index);
+            write_u16(buffer, attribute.module_flags);
+            write_u16(buffer, attribute.module_version_index);
+            write_u16(buffer, narrow(attribute.requires.len(), "requires_count")?);
+            for requires in &attribute.requires {
+                write_u16(buffer, requires.requires_index);
+                write_u16(buffer, requires.requires_flags);
+                write_u16(buffer, requires.requires_version_index);
+            }
+            write_u16(buffer, narrow(attribute.exports.len(), "exports_count")?);
+            for exports in &attribute.exports {
+                write_u16(buffer, exports.exports_index);
+                write_u16(buffer, exports.exports_flags);
+                encode_indices(buffer, &exports.exports_to_index)?;
+            }
+            write_u16(buffer, narrow(attribute.opens.len(), "opens_count")?);
+            for opens in &attribute.opens {
+                write_u16(buffer, opens.opens_index);
+                write_u16(buffer, opens.opens_flags);
+                encode_indices(buffer, &opens.opens_to_index)?;
+            }
+            encode_indices(buffer, &attribute.uses_index)?;
+            write_u16(buffer, narrow(attribute.provides.len(), "provides_count")?);
+            for provides in &attribute.provides {
+                write_u16(buffer, provides.provides_index);
+                encode_indices(buffer, &provides.provides_with_index)?;
+            }
+        }
+
+        AttributeInfo::ModulePackages(attribute) => {
+            encode_indices(buffer, &attribute.package_index)?;
+        }
+
+        AttributeInfo::ModuleMainClass(attribute) => {
+            write_u16(buffer, attribute.main_class_index);
+        }
+
+        AttributeInfo::NestHost(attribute) => {
+            write_u16(buffer, attribute.host_class_index);
+        }
+
+        AttributeInfo::NestMembers(attribute) => {
+            write_u16(buffer, narrow(attribute.classes.len(), "number_of_classes")?);
+            for class in &attribute.classes {
+                write_u16(buffer, *class);
+            }
+        }
+
+        AttributeInfo::Record(attribute) => {
+            write_u16(buffer, narrow(attribute.components.len(), "components_count")?);
+            for component in &attribute.components {
+                write_u16(buffer, component.name_index);
+                write_u16(buffer, component.descriptor_index);
+                encode_attributes(buffer, &component.attributes, constant_pool, options)?;
+            }
+        }
+
+        AttributeInfo::PermittedSubclasses(attribute) => {
+            write_u16(buffer, narrow(attribute.classes.len(), "number_of_classes")?);
+            for class in &attribute.classes {
+                write_u16(buffer, *class);
+            }
+        }
+
+        AttributeInfo::CharacterRangeTable(attribute) => {
+            write_u16(buffer, narrow(attribute.character_range_table.len(), "character_range_table_length")?);
+            for entry in &attribute.character_range_table {
+                write_u16(buffer, entry.start_pc);
+                write_u16(buffer, entry.end_pc);
+                write_u32(buffer, entry.character_range_start);
+                write_u32(buffer, entry.character_range_end);
+                write_u16(buffer, entry.flags);
+            }
+        }
+
+        AttributeInfo::CompilationID(attribute) => {
+            write_u16(buffer, attribute.compilation_id_index);
+        }
+
+        AttributeInfo::SourceID(attribute) => {
+            write_u16(buffer, attribute.source_id_index);
+        }
+
+        AttributeInfo::ScalaSig(attribute) => {
+            buffer.extend_from_slice(attribute.pickle);
+        }
+
+        AttributeInfo::Scala(_) => {}
+
+        AttributeInfo::Unknown(body)
+        | AttributeInfo::Raw { data: body, .. }
+        | AttributeInfo::User { data: body, .. }
+        | AttributeInfo::Lazy(LazyAttribute { data: body, .. }) => {
+            buffer.extend_from_slice(body);
+        }
+    }
+    Ok(())
+}
+
+/// Encodes a table of u16 constant pool indices preceded by its u16 count.
+fn encode_indices(buffer: &mut Vec<u8>, indices: &[u16]) -> Result<(), EncodeError> {
+    write_u16(buffer, narrow(indices.len(), "index table length")?);
+    for index in indices {
+        write_u16(buffer, *index);
+    }
+    Ok(())
+}
+
+/// Encodes num_annotations and the annotations following it.
+fn encode_annotations(buffer: &mut Vec<u8>, annotations: &[AnnotationEntry]) -> Result<(), EncodeError> {
+    write_u16(buffer, narrow(annotations.len(), "num_annotations")?);
+    for annotation in annotations {
+        encode_annotation(buffer, annotation)?;
+    }
+    Ok(())
+}
+
+/// Encodes num_parameters and the annotations of each parameter.
+fn encode_parameter_annotations(
+    buffer: &mut Vec<u8>,
+    parameter_annotations: &[Vec<AnnotationEntry>],
+) -> Result<(), EncodeError> {
+    write_u8(buffer, narrow(parameter_annotations.len(), "num_parameters")?);
+    for annotations in parameter_annotations {
+        encode_annotations(buffer, annotations)?;
+    }
+    Ok(())
+}
+
+/// Encodes num_annotations and the type annotations following it.
+fn encode_type_annotations(buffer: &mut Vec<u8>, annotations: &[TypeAnnotation]) -> Result<(), EncodeError> {
+    write_u16(buffer, narrow(annotations.len(), "num_annotations")?);
+    for annotation in annotations {
+        write_u8(buffer, annotation.target_type);
+        match &annotation.target_info {
+            TargetInfo::TypeParameter { type_parameter_index } => write_u8(buffer, *type_parameter_index),
+            TargetInfo::Supertype { supertype_index } => write_u16(buffer, *supertype_index),
+            TargetInfo::TypeParameterBound { type_parameter_index, bound_index } => {
+                write_u8(buffer, *type_parameter_index);
+                write_u8(buffer, *bound_index);
+            }
+            TargetInfo::Empty => {}
+            TargetInfo::FormalParameter { formal_parameter_index } => write_u8(buffer, *formal_parameter_index),
+            TargetInfo::Throws { throws_type_index } => write_u16(buffer, *throws_type_index),
+            TargetInfo::LocalVar { table, .. } => {
+                write_u16(buffer, narrow(table.len(), "table_length")?);
+                for entry in table {
+                    write_u16(buffer, entry.start_pc);
+                    write_u16(buffer, entry.length);
+                    write_u16(buffer, entry.index);
+                }
+            }
+            TargetInfo::Catch { exception_table_index } => write_u16(buffer, *exception_table_index),
+            TargetInfo::Offset { offset } => write_u16(buffer, *offset),
+            TargetInfo::TypeArgument { offset, type_argument_index } => {
+                write_u16(buffer, *offset);
+                write_u8(buffer, *type_argument_index);
+            }
+        }
+        write_u8(buffer, narrow(annotation.target_path.path.len(), "path_length")?);
+        for entry in &annotation.target_path.path {
+            write_u8(buffer, entry.type_path_kind);
+            write_u8(buffer, entry.type_argument_index);
+        }
+        encode_element_value_pairs(buffer, annotation.type_index, &annotation.element_value_pairs)?;
+    }
+    Ok(())
+}
+
+/// Encodes an annotation.
+fn encode_annotation(buffer: &mut Vec<u8>, annotation: &AnnotationEntry) -> Result<(), EncodeError> {
+    encode_element_value_pairs(buffer, annotation.type_index, &annotation.element_value_pairs)
+}
+
+/// Encodes the type_index and element value pairs shared by annotations and type annotations.
+fn encode_element_value_pairs(
+    buffer: &mut Vec<u8>,
+    type_index: u16,
+    element_value_pairs: &[AnnotationElementValuePair],
+) -> Result<(), EncodeError> {
+    write_u16(buffer, type_index);
+    write_u16(buffer, narrow(element_value_pairs.len(), "num_element_value_pairs")?);
+    for pair in element_value_pairs {
+        write_u16(buffer, pair.element_name_index);
+        encode_element_value(buffer, &pair.value)?;
+    }
+    Ok(())
+}
+
+/// Encodes an element_value.
+fn encode_element_value(buffer: &mut Vec<u8>, element_value: &AnnotationElementValue) -> Result<(), EncodeError> {
+    write_u8(buffer, element_value.tag);
+    match &element_value.value {
+        AnnotationElementValueEntryValue::ConstValueIndex(index) | AnnotationElementValueEntryValue::ClassInfoIndex(index) => {
+            write_u16(buffer, *index);
+        }
+        AnnotationElementValueEntryValue::EnumConstValue { type_name_index, const_name_index } => {
+            write_u16(buffer, *type_name_index);
+            write_u16(buffer, *const_name_index);
+        }
+        AnnotationElementValueEntryValue::AnnotationValue(annotation) => encode_annotation(buffer, annotation)?,
+        AnnotationElementValueEntryValue::ArrayValue { values, .. } => {
+            write_u16(buffer, narrow(values.len(), "num_values")?);
+            for value in values {
+                encode_element_value(buffer, value)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Encodes a stack_map_frame.
+fn encode_stack_map_frame(buffer: &mut Vec<u8>, frame: &StackMapFrame) -> Result<(), EncodeError> {
+    match frame {
+        StackMapFrame::SameFrame(frame) => {
+            write_u8(buffer, frame.frame_type);
+        }
+
+        StackMapFrame::SameLocals1StackItemFrame(frame) => {
+            write_u8(buffer, frame.frame_type);
+            encode_verification_type_info(buffer, &frame.stack);
+        }
+
+        StackMapFrame::SameLocals1StackItemFrameExtended(frame) => {
+            write_u8(buffer, frame.frame_type);
+            write_u16(buffer, frame.offset_delta);
+            encode_verification_type_info(buffer, &frame.stack);
+        }
+
+        StackMapFrame::ChopFrame(frame) => {
+            write_u8(buffer, frame.frame_type);
+            write_u16(buffer, frame.offset_delta);
+        }
+
+        StackMapFrame::SameFrameExtended(frame) => {
+            write_u8(buffer, frame.frame_type);
+            write_u16(buffer, frame.offset_delta);
+        }
+
+        StackMapFrame::AppendFrame(frame) => {
+            write_u8(buffer, frame.frame_type);
+            write_u16(buffer, frame.offset_delta);
+            for local in &frame.locals {
+                encode_verification_type_info(buffer, local);
+            }
+        }
+
+        StackMapFrame::FullFrame(frame) => {
+            write_u8(buffer, frame.frame_type);
+            write_u16(buffer, frame.offset_delta);
+            write_u16(buffer, narrow(frame.locals.len(), "number_of_locals")?);
+            for local in &frame.locals {
+                encode_verification_type_info(buffer, local);
+            }
+            write_u16(buffer, narrow(frame.stack.len(), "number_of_stack_items")?);
+            for item in &frame.stack {
+                encode_verification_type_info(buffer, item);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Encodes a verification_type_info.
+fn encode_verification_type_info(buffer: &mut Vec<u8>, info: &VerificationTypeInfo) {
+    match info {
+        VerificationTypeInfo::Top => write_u8(buffer, 0),
+        VerificationTypeInfo::Integer => write_u8(buffer, 1),
+        VerificationTypeInfo::Float => write_u8(buffer, 2),
+        VerificationTypeInfo::Double => write_u8(buffer, 3),
+        VerificationTypeInfo::Long => write_u8(buffer, 4),
+        VerificationTypeInfo::Null => write_u8(buffer, 5),
+        VerificationTypeInfo::UninitializedThis => write_u8(buffer, 6),
+        VerificationTypeInfo::Object { cpool_index } => {
+            write_u8(buffer, 7);
+            write_u16(buffer, *cpool_index);
+        }
+        VerificationTypeInfo::Uninitialized { offset } => {
+            write_u8(buffer, 8);
+            write_u16(buffer, *offset);
+        }
+    }
+}
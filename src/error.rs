@@ -0,0 +1,145 @@
+use std::fmt;
+
+/// Errors returned when a class file cannot be decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The class file ends in the middle of a structure.
+    UnexpectedEof {
+        while_parsing: &'static str,
+        needed: usize,
+        available: usize,
+    },
+    /// A count or length read from the class file describes more bytes than are left.
+    LengthOutOfBounds {
+        while_parsing: &'static str,
+        needed: usize,
+        available: usize,
+    },
+    /// Computing the size of a structure overflowed.
+    SizeOverflow { while_parsing: &'static str },
+    /// Bytes follow the class file structure.
+    TrailingBytes { count: usize },
+    /// An attribute which may appear at most once is repeated on the same owner.
+    DuplicateAttribute { location: String, name: String },
+    /// A constant pool entry is identical to an earlier one.
+    DuplicateConstant { index: usize, original: usize },
+    /// A constant pool entry has a tag not defined by the JVM specification.
+    UnknownConstantTag { index: usize, tag: u8 },
+    /// An element_value has a tag not defined by the JVM specification.
+    UnknownElementValueTag { tag: u8 },
+    /// A type annotation has a target_type not defined by the JVM specification.
+    UnknownTargetType { target_type: u8 },
+    /// A stack_map_frame has a frame_type reserved by the JVM specification.
+    UnknownFrameType { frame_type: u8 },
+    /// A verification_type_info has a tag not defined by the JVM specification.
+    UnknownVerificationType { tag: u8 },
+    /// Annotations, element values or attributes are nested deeper than the decoder supports.
+    NestingTooDeep { while_parsing: &'static str, max: usize },
+    /// The body of an attribute is longer than its decoded structure.
+    AttributeLengthMismatch { name: String, length: usize, consumed: usize },
+    /// A decoder registered with [`ParseOptions::register_attribute`](crate::ParseOptions::register_attribute)
+    /// rejected the body of an attribute.
+    UserAttribute { name: String, message: String },
+    /// Decoding would allocate more than [`ParseOptions::max_allocation`](crate::ParseOptions::max_allocation).
+    AllocationLimitExceeded {
+        while_parsing: &'static str,
+        requested: usize,
+        remaining: usize,
+    },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEof { while_parsing, needed, available } => write!(
+                f,
+                "unexpected end of class file while parsing {}: needed {} bytes but only {} are left",
+                while_parsing, needed, available
+            ),
+            DecodeError::LengthOutOfBounds { while_parsing, needed, available } => write!(
+                f,
+                "{} needs at least {} bytes but only {} are available",
+                while_parsing, needed, available
+            ),
+            DecodeError::SizeOverflow { while_parsing } => write!(f, "size of {} overflows", while_parsing),
+            DecodeError::TrailingBytes { count } => write!(f, "{} trailing bytes after the class file", count),
+            DecodeError::DuplicateAttribute { location, name } => {
+                write!(f, "{}: duplicate {} attribute", location, name)
+            }
+            DecodeError::DuplicateConstant { index, original } => {
+                write!(f, "constant_pool[{}] duplicates constant_pool[{}]", index, original)
+            }
+            DecodeError::UnknownConstantTag { index, tag } => {
+                write!(f, "constant_pool[{}] has unknown tag {}", index, tag)
+            }
+            DecodeError::UnknownElementValueTag { tag } => write!(f, "unknown element_value tag 0x{:02x}", tag),
+            DecodeError::UnknownTargetType { target_type } => {
+                write!(f, "unknown type annotation target_type 0x{:02x}", target_type)
+            }
+            DecodeError::UnknownFrameType { frame_type } => write!(f, "reserved stack_map_frame type {}", frame_type),
+            DecodeError::UnknownVerificationType { tag } => write!(f, "unknown verification_type_info tag {}", tag),
+            DecodeError::NestingTooDeep { while_parsing, max } => {
+                write!(f, "{} is nested more than {} levels deep", while_parsing, max)
+            }
+            DecodeError::AttributeLengthMismatch { name, length, consumed } => write!(
+                f,
+                "{} attribute is {} bytes long but its contents take {} bytes",
+                name, length, consumed
+            ),
+            DecodeError::UserAttribute { name, message } => write!(f, "{} attribute: {}", name, message),
+            DecodeError::AllocationLimitExceeded { while_parsing, requested, remaining } => write!(
+                f,
+                "{} requests {} bytes but only {} bytes of the allocation budget remain",
+                while_parsing, requested, remaining
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Errors returned when an attribute is constructed with inconsistent values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttributeError {
+    /// A table has more entries than its count field can hold.
+    TooManyEntries { while_building: &'static str, count: usize, max: usize },
+    /// A value is outside the range allowed by the JVM specification.
+    OutOfRange { while_building: &'static str, value: usize },
+}
+
+impl fmt::Display for AttributeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttributeError::TooManyEntries { while_building, count, max } => write!(
+                f,
+                "{} has {} entries but at most {} are allowed",
+                while_building, count, max
+            ),
+            AttributeError::OutOfRange { while_building, value } => {
+                write!(f, "{} is out of range for {}", value, while_building)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AttributeError {}
+
+/// Errors returned when a class file cannot be encoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodeError {
+    /// A count, index or length is larger than its field in the class file can hold, e.g. a
+    /// constant pool of more than 65535 entries.
+    Overflow { while_encoding: &'static str, value: usize, max: usize },
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodeError::Overflow { while_encoding, value, max } => {
+                write!(f, "{} is {} but at most {} can be written", while_encoding, value, max)
+            }
+        }
+    }
+}
+
+impl std::error::Error for EncodeError {}
@@ -0,0 +1,553 @@
+use std::collections::BTreeMap;
+
+use crate::{analysis::ConstantFieldValue, opcode::instruction_length, types::*};
+
+/// The type of a value whose content is not known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    /// int, and boolean, byte, char and short, which the JVM computes as int.
+    Int,
+    Long,
+    Float,
+    Double,
+    Reference,
+}
+
+/// A value computed by [`evaluate`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    String(String),
+    Null,
+    /// A new array, whose elements are not tracked.
+    Array { length: i32 },
+    Unknown(ValueKind),
+}
+
+impl Value {
+    /// Returns true for long and double values, which take two operand stack slots.
+    fn is_wide(&self) -> bool {
+        matches!(
+            self,
+            Value::Long(_) | Value::Double(_) | Value::Unknown(ValueKind::Long) | Value::Unknown(ValueKind::Double)
+        )
+    }
+}
+
+impl From<ConstantFieldValue> for Value {
+    fn from(value: ConstantFieldValue) -> Self {
+        match value {
+            ConstantFieldValue::Int(value) => Value::Int(value),
+            ConstantFieldValue::Long(value) => Value::Long(value),
+            ConstantFieldValue::Float(value) => Value::Float(value),
+            ConstantFieldValue::Double(value) => Value::Double(value),
+            ConstantFieldValue::String(value) => Value::String(value),
+        }
+    }
+}
+
+/// A putstatic executed by [`evaluate`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaticStore<'c> {
+    pub owner: &'c str,
+    pub name: &'c str,
+    pub descriptor: &'c str,
+    pub value: Value,
+}
+
+/// The state in which [`evaluate`] stopped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation<'c> {
+    /// The operand stack, bottom first.
+    pub stack: Vec<Value>,
+    /// The local variables which were stored to, by index.
+    pub locals: BTreeMap<u16, Value>,
+    /// The putstatic instructions executed, in order.
+    pub static_stores: Vec<StaticStore<'c>>,
+    /// The value of an ireturn, lreturn, freturn, dreturn or areturn which ended the code.
+    pub returned: Option<Value>,
+    /// Offset of the first instruction which could not be evaluated, or `None` if a return
+    /// instruction or the end of the code was reached.
+    pub stopped_at: Option<usize>,
+}
+
+/// Evaluates straight-line bytecode from its first instruction, e.g. a `<clinit>` which
+/// computes array sizes or flag masks.
+///
+/// Constants, local variables, arithmetic, conversions, comparisons, stack manipulation,
+/// new arrays and static field accesses are evaluated; values read from array elements
+/// and values derived from unknown ones are [`Value::Unknown`]. Evaluation stops at the
+/// first branch, invocation, object creation, instance field access or other instruction
+/// with effects outside the method, and at an instruction which would throw.
+///
+/// `statics` supplies the values of static fields read with getstatic, given the owner,
+/// name and descriptor, e.g. from a [`ConstantTable`](crate::analysis::ConstantTable).
+/// Static fields stored to earlier in the same code take precedence.
+pub fn evaluate<'c>(
+    code: &[u8],
+    constant_pool: &'c [ConstantPoolInfo],
+    mut statics: impl FnMut(&str, &str, &str) -> Option<Value>,
+) -> Evaluation<'c> {
+    let mut evaluator = Evaluator {
+        code,
+        constant_pool,
+        stack: Vec::new(),
+        locals: BTreeMap::new(),
+        static_stores: Vec::new(),
+    };
+    let mut pc = 0;
+    let (returned, stopped_at) = loop {
+        if pc == code.len() {
+            break (None, None);
+        }
+        let Some(length) = instruction_length(code, pc) else {
+            break (None, Some(pc));
+        };
+        match evaluator.step(pc, &mut statics) {
+            Some(Step::Next) => pc += length,
+            Some(Step::Return(value)) => break (value, None),
+            None => break (None, Some(pc)),
+        }
+    };
+    Evaluation {
+        stack: evaluator.stack,
+        locals: evaluator.locals,
+        static_stores: evaluator.static_stores,
+        returned,
+        stopped_at,
+    }
+}
+
+enum Step {
+    Next,
+    Return(Option<Value>),
+}
+
+struct Evaluator<'b, 'c> {
+    code: &'b [u8],
+    constant_pool: &'c [ConstantPoolInfo<'c>],
+    stack: Vec<Value>,
+    locals: BTreeMap<u16, Value>,
+    static_stores: Vec<StaticStore<'c>>,
+}
+
+/// Returns the kind of a value with the field descriptor `descriptor`.
+fn descriptor_kind(descriptor: &str) -> ValueKind {
+    match descriptor.as_bytes().first() {
+        Some(b'J') => ValueKind::Long,
+        Some(b'F') => ValueKind::Float,
+        Some(b'D') => ValueKind::Double,
+        Some(b'L' | b'[') => ValueKind::Reference,
+        _ => ValueKind::Int,
+    }
+}
+
+impl<'c> Evaluator<'_, 'c> {
+    fn u8_operand(&self, pc: usize) -> u8 {
+        self.code[pc + 1]
+    }
+
+    fn u16_operand(&self, pc: usize) -> u16 {
+        u16::from_be_bytes([self.code[pc + 1], self.code[pc + 2]])
+    }
+
+    fn push(&mut self, value: Value) -> Option<Step> {
+        self.stack.push(value);
+        Some(Step::Next)
+    }
+
+    fn pop(&mut self) -> Option<Value> {
+        self.stack.pop()
+    }
+
+    fn load(&mut self, index: u16, kind: ValueKind) -> Option<Step> {
+        let value = self.locals.get(&index).cloned().unwrap_or(Value::Unknown(kind));
+        self.push(value)
+    }
+
+    fn store(&mut self, index: u16) -> Option<Step> {
+        let value = self.pop()?;
+        self.locals.insert(index, value);
+        Some(Step::Next)
+    }
+
+    fn iinc(&mut self, index: u16, increment: i32) -> Option<Step> {
+        let value = match self.locals.get(&index) {
+            Some(Value::Int(value)) => Value::Int(value.wrapping_add(increment)),
+            _ => Value::Unknown(ValueKind::Int),
+        };
+        self.locals.insert(index, value);
+        Some(Step::Next)
+    }
+
+    fn ldc(&mut self, index: usize) -> Option<Step> {
+        let value = match self.constant_pool.get(index)? {
+            ConstantPoolInfo::Integer(info) => Value::Int(info.data),
+            ConstantPoolInfo::Float(info) => Value::Float(info.data),
+            ConstantPoolInfo::Long(info) => Value::Long(info.data),
+            ConstantPoolInfo::Double(info) => Value::Double(info.data),
+            ConstantPoolInfo::String(info) => Value::String(get_utf8(self.constant_pool, info.string_index)?.to_string()),
+            ConstantPoolInfo::Class(_) | ConstantPoolInfo::MethodType(_) | ConstantPoolInfo::MethodHandle(_) => {
+                Value::Unknown(ValueKind::Reference)
+            }
+            _ => return None,
+        };
+        self.push(value)
+    }
+
+    /// Pops two ints and pushes `operation` of them; `operation` returns `None` where
+    /// the JVM would throw.
+    fn int_binary(&mut self, operation: impl Fn(i32, i32) -> Option<i32>) -> Option<Step> {
+        let (right, left) = (self.pop()?, self.pop()?);
+        match (left, right) {
+            (Value::Int(left), Value::Int(right)) => self.push(Value::Int(operation(left, right)?)),
+            _ => self.push(Value::Unknown(ValueKind::Int)),
+        }
+    }
+
+    fn long_binary(&mut self, operation: impl Fn(i64, i64) -> Option<i64>) -> Option<Step> {
+        let (right, left) = (self.pop()?, self.pop()?);
+        match (left, right) {
+            (Value::Long(left), Value::Long(right)) => self.push(Value::Long(operation(left, right)?)),
+            _ => self.push(Value::Unknown(ValueKind::Long)),
+        }
+    }
+
+    /// Pops an int shift distance and a long, and pushes the shifted long.
+    fn long_shift(&mut self, operation: impl Fn(i64, u32) -> i64) -> Option<Step> {
+        let (distance, value) = (self.pop()?, self.pop()?);
+        match (value, distance) {
+            (Value::Long(value), Value::Int(distance)) => self.push(Value::Long(operation(value, distance as u32 & 0x3F))),
+            _ => self.push(Value::Unknown(ValueKind::Long)),
+        }
+    }
+
+    fn float_binary(&mut self, operation: impl Fn(f32, f32) -> f32) -> Option<Step> {
+        let (right, left) = (self.pop()?, self.pop()?);
+        match (left, right) {
+            (Value::Float(left), Value::Float(right)) => self.push(Value::Float(operation(left, right))),
+            _ => self.push(Value::Unknown(ValueKind::Float)),
+        }
+    }
+
+    fn double_binary(&mut self, operation: impl Fn(f64, f64) -> f64) -> Option<Step> {
+        let (right, left) = (self.pop()?, self.pop()?);
+        match (left, right) {
+            (Value::Double(left), Value::Double(right)) => self.push(Value::Double(operation(left, right))),
+            _ => self.push(Value::Unknown(ValueKind::Double)),
+        }
+    }
+
+    /// Pops one value and pushes `operation` of it, or an unknown value of `kind`.
+    fn unary(&mut self, kind: ValueKind, operation: impl Fn(&Value) -> Option<Value>) -> Option<Step> {
+        let value = self.pop()?;
+        self.push(operation(&value).unwrap_or(Value::Unknown(kind)))
+    }
+
+    /// Pops two floats or doubles and pushes -1, 0 or 1; `nan` is pushed if either is NaN.
+    fn compare(&mut self, nan: i32) -> Option<Step> {
+        let (right, left) = (self.pop()?, self.pop()?);
+        let ordering = match (left, right) {
+            (Value::Float(left), Value::Float(right)) => left.partial_cmp(&right),
+            (Value::Double(left), Value::Double(right)) => left.partial_cmp(&right),
+            _ => return self.push(Value::Unknown(ValueKind::Int)),
+        };
+        self.push(Value::Int(ordering.map_or(nan, |ordering| ordering as i32)))
+    }
+
+    fn new_array(&mut self) -> Option<Step> {
+        match self.pop()? {
+            Value::Int(length) if length >= 0 => self.push(Value::Array { length }),
+            Value::Int(_) => None,
+            _ => self.push(Value::Unknown(ValueKind::Reference)),
+        }
+    }
+
+    fn step(&mut self, pc: usize, statics: &mut impl FnMut(&str, &str, &str) -> Option<Value>) -> Option<Step> {
+        let opcode = Opcode::from_u8(self.code[pc])?;
+        match opcode {
+            Opcode::Nop => Some(Step::Next),
+            Opcode::AconstNull => self.push(Value::Null),
+            Opcode::IconstM1 | Opcode::Iconst0 | Opcode::Iconst1 | Opcode::Iconst2 | Opcode::Iconst3
+            | Opcode::Iconst4 | Opcode::Iconst5 => self.push(Value::Int(opcode as i32 - Opcode::Iconst0 as i32)),
+            Opcode::Lconst0 | Opcode::Lconst1 => self.push(Value::Long(opcode as i64 - Opcode::Lconst0 as i64)),
+            Opcode::Fconst0 | Opcode::Fconst1 | Opcode::Fconst2 => {
+                self.push(Value::Float((opcode as u8 - Opcode::Fconst0 as u8) as f32))
+            }
+            Opcode::Dconst0 | Opcode::Dconst1 => self.push(Value::Double((opcode as u8 - Opcode::Dconst0 as u8) as f64)),
+            Opcode::Bipush => self.push(Value::Int(self.u8_operand(pc) as i8 as i32)),
+            Opcode::Sipush => self.push(Value::Int(self.u16_operand(pc) as i16 as i32)),
+            Opcode::Ldc => self.ldc(self.u8_operand(pc) as usize),
+            Opcode::LdcW | Opcode::Ldc2W => self.ldc(self.u16_operand(pc) as usize),
+
+            Opcode::Iload => self.load(self.u8_operand(pc) as u16, ValueKind::Int),
+            Opcode::Lload => self.load(self.u8_operand(pc) as u16, ValueKind::Long),
+            Opcode::Fload => self.load(self.u8_operand(pc) as u16, ValueKind::Float),
+            Opcode::Dload => self.load(self.u8_operand(pc) as u16, ValueKind::Double),
+            Opcode::Aload => self.load(self.u8_operand(pc) as u16, ValueKind::Reference),
+            Opcode::Iload0 | Opcode::Iload1 | Opcode::Iload2 | Opcode::Iload3 => {
+                self.load((opcode as u8 - Opcode::Iload0 as u8) as u16, ValueKind::Int)
+            }
+            Opcode::Lload0 | Opcode::Lload1 | Opcode::Lload2 | Opcode::Lload3 => {
+                self.load((opcode as u8 - Opcode::Lload0 as u8) as u16, ValueKind::Long)
+            }
+            Opcode::Fload0 | Opcode::Fload1 | Opcode::Fload2 | Opcode::Fload3 => {
+                self.load((opcode as u8 - Opcode::Fload0 as u8) as u16, ValueKind::Float)
+            }
+            Opcode::Dload0 | Opcode::Dload1 | Opcode::Dload2 | Opcode::Dload3 => {
+                self.load((opcode as u8 - Opcode::Dload0 as u8) as u16, ValueKind::Double)
+            }
+            Opcode::Aload0 | Opcode::Aload1 | Opcode::Aload2 | Opcode::Aload3 => {
+                self.load((opcode as u8 - Opcode::Aload0 as u8) as u16, ValueKind::Reference)
+            }
+            Opcode::Istore | Opcode::Lstore | Opcode::Fstore | Opcode::Dstore | Opcode::Astore => {
+                self.store(self.u8_operand(pc) as u16)
+            }
+            Opcode::Istore0 | Opcode::Istore1 | Opcode::Istore2 | Opcode::Istore3 => {
+                self.store((opcode as u8 - Opcode::Istore0 as u8) as u16)
+            }
+            Opcode::Lstore0 | Opcode::Lstore1 | Opcode::Lstore2 | Opcode::Lstore3 => {
+                self.store((opcode as u8 - Opcode::Lstore0 as u8) as u16)
+            }
+            Opcode::Fstore0 | Opcode::Fstore1 | Opcode::Fstore2 | Opcode::Fstore3 => {
+                self.store((opcode as u8 - Opcode::Fstore0 as u8) as u16)
+            }
+            Opcode::Dstore0 | Opcode::Dstore1 | Opcode::Dstore2 | Opcode::Dstore3 => {
+                self.store((opcode as u8 - Opcode::Dstore0 as u8) as u16)
+            }
+            Opcode::Astore0 | Opcode::Astore1 | Opcode::Astore2 | Opcode::Astore3 => {
+                self.store((opcode as u8 - Opcode::Astore0 as u8) as u16)
+            }
+            Opcode::Iinc => self.iinc(self.u8_operand(pc) as u16, self.code[pc + 2] as i8 as i32),
+            Opcode::Wide => {
+                let modified = Opcode::from_u8(self.u8_operand(pc))?;
+                let index = u16::from_be_bytes([self.code[pc + 2], self.code[pc + 3]]);
+                match modified {
+                    Opcode::Iinc => self.iinc(index, i16::from_be_bytes([self.code[pc + 4], self.code[pc + 5]]) as i32),
+                    Opcode::Iload => self.load(index, ValueKind::Int),
+                    Opcode::Lload => self.load(index, ValueKind::Long),
+                    Opcode::Fload => self.load(index, ValueKind::Float),
+                    Opcode::Dload => self.load(index, ValueKind::Double),
+                    Opcode::Aload => self.load(index, ValueKind::Reference),
+                    Opcode::Istore | Opcode::Lstore | Opcode::Fstore | Opcode::Dstore | Opcode::Astore => self.store(index),
+                    _ => None,
+                }
+            }
+
+            Opcode::Iaload | Opcode::Baload | Opcode::Caload | Opcode::Saload => {
+                self.pop()?;
+                self.pop()?;
+                self.push(Value::Unknown(ValueKind::Int))
+            }
+            Opcode::Laload | Opcode::Faload | Opcode::Daload | Opcode::Aaload => {
+                self.pop()?;
+                self.pop()?;
+                let kind = match opcode {
+                    Opcode::Laload => ValueKind::Long,
+                    Opcode::Faload => ValueKind::Float,
+                    Opcode::Daload => ValueKind::Double,
+                    _ => ValueKind::Reference,
+                };
+                self.push(Value::Unknown(kind))
+            }
+            Opcode::Iastore | Opcode::Lastore | Opcode::Fastore | Opcode::Dastore | Opcode::Aastore
+            | Opcode::Bastore | Opcode::Castore | Opcode::Sastore => {
+                let (_, index, array) = (self.pop()?, self.pop()?, self.pop()?);
+                match (array, index) {
+                    (Value::Array { length }, Value::Int(index)) if !(0..length).contains(&index) => None,
+                    (Value::Null, _) => None,
+                    _ => Some(Step::Next),
+                }
+            }
+            Opcode::Newarray | Opcode::Anewarray => self.new_array(),
+            Opcode::Arraylength => match self.pop()? {
+                Value::Array { length } => self.push(Value::Int(length)),
+                Value::Null => None,
+                _ => self.push(Value::Unknown(ValueKind::Int)),
+            },
+
+            Opcode::Pop => self.pop().map(|_| Step::Next),
+            Opcode::Pop2 => {
+                if !self.pop()?.is_wide() {
+                    self.pop()?;
+                }
+                Some(Step::Next)
+            }
+            Opcode::Dup => {
+                let value = self.stack.last()?.clone();
+                self.push(value)
+            }
+            Opcode::DupX1 => {
+                let (first, second) = (self.pop()?, self.pop()?);
+                self.stack.extend([first.clone(), second, first]);
+                Some(Step::Next)
+            }
+            Opcode::DupX2 => {
+                let (first, second) = (self.pop()?, self.pop()?);
+                if second.is_wide() {
+                    self.stack.extend([first.clone(), second, first]);
+                } else {
+                    let third = self.pop()?;
+                    self.stack.extend([first.clone(), third, second, first]);
+                }
+                Some(Step::Next)
+            }
+            Opcode::Dup2 => {
+                let first = self.pop()?;
+                if first.is_wide() {
+                    self.stack.extend([first.clone(), first]);
+                } else {
+                    let second = self.pop()?;
+                    self.stack.extend([second.clone(), first.clone(), second, first]);
+                }
+                Some(Step::Next)
+            }
+            Opcode::Dup2X1 => {
+                let (first, second) = (self.pop()?, self.pop()?);
+                if first.is_wide() {
+                    self.stack.extend([first.clone(), second, first]);
+                } else {
+                    let third = self.pop()?;
+                    self.stack.extend([second.clone(), first.clone(), third, second, first]);
+                }
+                Some(Step::Next)
+            }
+            Opcode::Swap => {
+                let (first, second) = (self.pop()?, self.pop()?);
+                self.stack.extend([first, second]);
+                Some(Step::Next)
+            }
+
+            Opcode::Iadd => self.int_binary(|left, right| Some(left.wrapping_add(right))),
+            Opcode::Isub => self.int_binary(|left, right| Some(left.wrapping_sub(right))),
+            Opcode::Imul => self.int_binary(|left, right| Some(left.wrapping_mul(right))),
+            Opcode::Idiv => self.int_binary(|left, right| (right != 0).then(|| left.wrapping_div(right))),
+            Opcode::Irem => self.int_binary(|left, right| (right != 0).then(|| left.wrapping_rem(right))),
+            Opcode::Ishl => self.int_binary(|left, right| Some(left.wrapping_shl(right as u32))),
+            Opcode::Ishr => self.int_binary(|left, right| Some(left.wrapping_shr(right as u32))),
+            Opcode::Iushr => self.int_binary(|left, right| Some((left as u32).wrapping_shr(right as u32) as i32)),
+            Opcode::Iand => self.int_binary(|left, right| Some(left & right)),
+            Opcode::Ior => self.int_binary(|left, right| Some(left | right)),
+            Opcode::Ixor => self.int_binary(|left, right| Some(left ^ right)),
+            Opcode::Ladd => self.long_binary(|left, right| Some(left.wrapping_add(right))),
+            Opcode::Lsub => self.long_binary(|left, right| Some(left.wrapping_sub(right))),
+            Opcode::Lmul => self.long_binary(|left, right| Some(left.wrapping_mul(right))),
+            Opcode::Ldiv => self.long_binary(|left, right| (right != 0).then(|| left.wrapping_div(right))),
+            Opcode::Lrem => self.long_binary(|left, right| (right != 0).then(|| left.wrapping_rem(right))),
+            Opcode::Land => self.long_binary(|left, right| Some(left & right)),
+            Opcode::Lor => self.long_binary(|left, right| Some(left | right)),
+            Opcode::Lxor => self.long_binary(|left, right| Some(left ^ right)),
+            Opcode::Lshl => self.long_shift(|value, distance| value << distance),
+            Opcode::Lshr => self.long_shift(|value, distance| value >> distance),
+            Opcode::Lushr => self.long_shift(|value, distance| ((value as u64) >> distance) as i64),
+            Opcode::Fadd => self.float_binary(|left, right| left + right),
+            Opcode::Fsub => self.float_binary(|left, right| left - right),
+            Opcode::Fmul => self.float_binary(|left, right| left * right),
+            Opcode::Fdiv => self.float_binary(|left, right| left / right),
+            Opcode::Frem => self.float_binary(|left, right| left % right),
+            Opcode::Dadd => self.double_binary(|left, right| left + right),
+            Opcode::Dsub => self.double_binary(|left, right| left - right),
+            Opcode::Dmul => self.double_binary(|left, right| left * right),
+            Opcode::Ddiv => self.double_binary(|left, right| left / right),
+            Opcode::Drem => self.double_binary(|left, right| left % right),
+            Opcode::Ineg => self.unary(ValueKind::Int, |value| match value {
+                Value::Int(value) => Some(Value::Int(value.wrapping_neg())),
+                _ => None,
+            }),
+            Opcode::Lneg => self.unary(ValueKind::Long, |value| match value {
+                Value::Long(value) => Some(Value::Long(value.wrapping_neg())),
+                _ => None,
+            }),
+            Opcode::Fneg => self.unary(ValueKind::Float, |value| match value {
+                Value::Float(value) => Some(Value::Float(-value)),
+                _ => None,
+            }),
+            Opcode::Dneg => self.unary(ValueKind::Double, |value| match value {
+                Value::Double(value) => Some(Value::Double(-value)),
+                _ => None,
+            }),
+
+            // Rust's float to integer casts saturate and map NaN to zero, as the JVM does.
+            Opcode::I2l | Opcode::F2l | Opcode::D2l => self.unary(ValueKind::Long, |value| match *value {
+                Value::Int(value) => Some(Value::Long(value as i64)),
+                Value::Float(value) => Some(Value::Long(value as i64)),
+                Value::Double(value) => Some(Value::Long(value as i64)),
+                _ => None,
+            }),
+            Opcode::I2f | Opcode::L2f | Opcode::D2f => self.unary(ValueKind::Float, |value| match *value {
+                Value::Int(value) => Some(Value::Float(value as f32)),
+                Value::Long(value) => Some(Value::Float(value as f32)),
+                Value::Double(value) => Some(Value::Float(value as f32)),
+                _ => None,
+            }),
+            Opcode::I2d | Opcode::L2d | Opcode::F2d => self.unary(ValueKind::Double, |value| match *value {
+                Value::Int(value) => Some(Value::Double(value as f64)),
+                Value::Long(value) => Some(Value::Double(value as f64)),
+                Value::Float(value) => Some(Value::Double(value as f64)),
+                _ => None,
+            }),
+            Opcode::L2i | Opcode::F2i | Opcode::D2i => self.unary(ValueKind::Int, |value| match *value {
+                Value::Long(value) => Some(Value::Int(value as i32)),
+                Value::Float(value) => Some(Value::Int(value as i32)),
+                Value::Double(value) => Some(Value::Int(value as i32)),
+                _ => None,
+            }),
+            Opcode::I2b => self.unary(ValueKind::Int, |value| match *value {
+                Value::Int(value) => Some(Value::Int(value as i8 as i32)),
+                _ => None,
+            }),
+            Opcode::I2c => self.unary(ValueKind::Int, |value| match *value {
+                Value::Int(value) => Some(Value::Int(value as u16 as i32)),
+                _ => None,
+            }),
+            Opcode::I2s => self.unary(ValueKind::Int, |value| match *value {
+                Value::Int(value) => Some(Value::Int(value as i16 as i32)),
+                _ => None,
+            }),
+
+            Opcode::Lcmp => {
+                let (right, left) = (self.pop()?, self.pop()?);
+                match (left, right) {
+                    (Value::Long(left), Value::Long(right)) => self.push(Value::Int(left.cmp(&right) as i32)),
+                    _ => self.push(Value::Unknown(ValueKind::Int)),
+                }
+            }
+            Opcode::Fcmpl | Opcode::Dcmpl => self.compare(-1),
+            Opcode::Fcmpg | Opcode::Dcmpg => self.compare(1),
+
+            Opcode::Getstatic => {
+                let (owner, name, descriptor) = get_field_ref(self.constant_pool, self.u16_operand(pc) as usize)?;
+                let stored = self
+                    .static_stores
+                    .iter()
+                    .rev()
+                    .find(|store| store.owner == owner && store.name == name && store.descriptor == descriptor)
+                    .map(|store| store.value.clone());
+                let value = stored
+                    .or_else(|| statics(owner, name, descriptor))
+                    .unwrap_or(Value::Unknown(descriptor_kind(descriptor)));
+                self.push(value)
+            }
+            Opcode::Putstatic => {
+                let (owner, name, descriptor) = get_field_ref(self.constant_pool, self.u16_operand(pc) as usize)?;
+                let value = self.pop()?;
+                self.static_stores.push(StaticStore {
+                    owner,
+                    name,
+                    descriptor,
+                    value,
+                });
+                Some(Step::Next)
+            }
+
+            Opcode::Ireturn | Opcode::Lreturn | Opcode::Freturn | Opcode::Dreturn | Opcode::Areturn => {
+                Some(Step::Return(Some(self.pop()?)))
+            }
+            Opcode::Return => Some(Step::Return(None)),
+            _ => None,
+        }
+    }
+}
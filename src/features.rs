@@ -0,0 +1,150 @@
+//! Which Java language and VM features a class uses, e.g. to inventory their adoption
+//! across the classes of an application.
+
+use crate::types::*;
+
+/// Owner of the bootstrap methods javac uses for lambdas and method references.
+const LAMBDA_METAFACTORY: &str = "java/lang/invoke/LambdaMetafactory";
+
+/// Owner of the bootstrap methods javac uses for string concatenation since Java 9.
+const STRING_CONCAT_FACTORY: &str = "java/lang/invoke/StringConcatFactory";
+
+const VAR_HANDLE: &str = "java/lang/invoke/VarHandle";
+
+/// A feature a class may use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Feature {
+    /// Compiled with `--enable-preview`.
+    Preview,
+    /// A record class.
+    Record,
+    /// A sealed class or interface.
+    Sealed,
+    /// A nest host with members or a nest member, i.e. a NestHost or NestMembers attribute.
+    Nestmates,
+    /// Any invokedynamic call site.
+    InvokeDynamic,
+    /// Lambdas or method references, bootstrapped by `LambdaMetafactory`.
+    Lambda,
+    /// String concatenation bootstrapped by `StringConcatFactory`.
+    StringConcat,
+    /// Dynamically-computed constants, i.e. CONSTANT_Dynamic.
+    ConstantDynamic,
+    /// Calls to the methods of `java.lang.invoke.VarHandle`.
+    VarHandle,
+}
+
+impl Feature {
+    /// All features, in declaration order.
+    pub const ALL: [Feature; 9] = [
+        Feature::Preview,
+        Feature::Record,
+        Feature::Sealed,
+        Feature::Nestmates,
+        Feature::InvokeDynamic,
+        Feature::Lambda,
+        Feature::StringConcat,
+        Feature::ConstantDynamic,
+        Feature::VarHandle,
+    ];
+
+    /// Returns a stable name of the feature, e.g. `string-concat`, for reports and metrics.
+    pub fn name(self) -> &'static str {
+        match self {
+            Feature::Preview => "preview",
+            Feature::Record => "record",
+            Feature::Sealed => "sealed",
+            Feature::Nestmates => "nestmates",
+            Feature::InvokeDynamic => "invokedynamic",
+            Feature::Lambda => "lambda",
+            Feature::StringConcat => "string-concat",
+            Feature::ConstantDynamic => "condy",
+            Feature::VarHandle => "var-handle",
+        }
+    }
+}
+
+/// The features one class uses.
+///
+/// Call sites and constants are counted by constant pool entry, so two invokedynamic
+/// instructions sharing a CONSTANT_InvokeDynamic count once.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeatureReport {
+    pub preview: bool,
+    pub record: bool,
+    pub sealed: bool,
+    pub nestmates: bool,
+    /// Number of CONSTANT_InvokeDynamic entries.
+    pub invokedynamic: usize,
+    /// Number of CONSTANT_InvokeDynamic entries bootstrapped by `LambdaMetafactory`.
+    pub lambdas: usize,
+    /// Number of CONSTANT_InvokeDynamic entries bootstrapped by `StringConcatFactory`.
+    pub string_concats: usize,
+    /// Number of CONSTANT_Dynamic entries.
+    pub dynamic_constants: usize,
+    /// Number of CONSTANT_Methodref entries to a method of `VarHandle`, e.g. `compareAndSet`.
+    pub var_handle_calls: usize,
+}
+
+impl FeatureReport {
+    /// Returns true if the class uses `feature`.
+    pub fn uses(&self, feature: Feature) -> bool {
+        match feature {
+            Feature::Preview => self.preview,
+            Feature::Record => self.record,
+            Feature::Sealed => self.sealed,
+            Feature::Nestmates => self.nestmates,
+            Feature::InvokeDynamic => self.invokedynamic > 0,
+            Feature::Lambda => self.lambdas > 0,
+            Feature::StringConcat => self.string_concats > 0,
+            Feature::ConstantDynamic => self.dynamic_constants > 0,
+            Feature::VarHandle => self.var_handle_calls > 0,
+        }
+    }
+
+    /// Returns the features the class uses, in [`Feature::ALL`] order.
+    pub fn features(&self) -> impl Iterator<Item = Feature> + '_ {
+        Feature::ALL.into_iter().filter(|&feature| self.uses(feature))
+    }
+}
+
+/// Reports the features a class uses.
+///
+/// Only the constant pool and the attributes of the class are read, not its code.
+/// Invokedynamic call sites whose bootstrap method cannot be resolved count as neither
+/// lambdas nor string concatenations.
+pub fn detect(class_file: &JavaClassFile) -> FeatureReport {
+    let constant_pool = &class_file.constant_pool;
+    let mut report = FeatureReport {
+        preview: class_file.is_preview(),
+        record: class_file.record_components().is_some(),
+        sealed: class_file.is_sealed(),
+        nestmates: class_file
+            .attributes
+            .values()
+            .any(|attribute| matches!(attribute, AttributeInfo::NestHost(_) | AttributeInfo::NestMembers(_))),
+        ..FeatureReport::default()
+    };
+
+    for (index, constant) in constant_pool.iter().enumerate() {
+        match constant {
+            ConstantPoolInfo::InvokeDynamic(_) => {
+                report.invokedynamic += 1;
+                match class_file.bootstrap_call(index).map(|call| call.method.0) {
+                    Some(LAMBDA_METAFACTORY) => report.lambdas += 1,
+                    Some(STRING_CONCAT_FACTORY) => report.string_concats += 1,
+                    _ => {}
+                }
+            }
+            ConstantPoolInfo::Dynamic(_) => report.dynamic_constants += 1,
+            ConstantPoolInfo::MethodRef(_)
+                if get_member_ref(constant_pool, index).is_some_and(|(owner, _, _)| owner == VAR_HANDLE) =>
+            {
+                report.var_handle_calls += 1
+            }
+            _ => {}
+        }
+    }
+
+    report
+}
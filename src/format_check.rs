@@ -0,0 +1,306 @@
+use std::fmt;
+
+use crate::{types::*, validate::RuleId};
+
+/// A violation of the class file format found by [`JavaClassFile::verify_format`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatViolation {
+    /// The rule the violation breaks.
+    pub rule: RuleId,
+    /// Where the violation was found, e.g. `constant_pool[12]` or `methods[3]`.
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for FormatViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} [{}]", self.location, self.message, self.rule)
+    }
+}
+
+/// Collects violations while walking a class file.
+struct FormatChecker<'c, 'a> {
+    constant_pool: &'c [ConstantPoolInfo<'a>],
+    violations: Vec<FormatViolation>,
+}
+
+impl<'c, 'a> FormatChecker<'c, 'a> {
+    fn report(&mut self, rule: RuleId, location: impl Into<String>, message: impl Into<String>) {
+        self.violations.push(FormatViolation {
+            rule,
+            location: location.into(),
+            message: message.into(),
+        });
+    }
+
+    /// Returns the entry at `index` if it is a usable constant pool index.
+    fn entry(&self, index: usize) -> Option<&'c ConstantPoolInfo<'a>> {
+        match self.constant_pool.get(index) {
+            Some(ConstantPoolInfo::Dummy()) | None => None,
+            entry => entry,
+        }
+    }
+
+    /// Checks that `index` points at a CONSTANT_Utf8 and returns its string.
+    fn expect_utf8(&mut self, rule: RuleId, location: &str, field: &str, index: usize) -> Option<&'c str> {
+        match self.entry(index) {
+            Some(ConstantPoolInfo::Utf8(info)) => Some(info.data.as_ref()),
+            _ => {
+                self.report(rule, location, format!("{} #{} is not a CONSTANT_Utf8", field, index));
+                None
+            }
+        }
+    }
+
+    /// Checks that `index` points at a CONSTANT_Class.
+    fn expect_class(&mut self, rule: RuleId, location: &str, field: &str, index: usize) {
+        if !matches!(self.entry(index), Some(ConstantPoolInfo::Class(_))) {
+            self.report(rule, location, format!("{} #{} is not a CONSTANT_Class", field, index));
+        }
+    }
+
+    /// Checks that `index` points at a CONSTANT_NameAndType and returns its descriptor.
+    fn expect_name_and_type(&mut self, location: &str, index: usize) -> Option<&'c str> {
+        match self.entry(index) {
+            Some(ConstantPoolInfo::NameAndType(info)) => match self.entry(info.descriptor_index) {
+                Some(ConstantPoolInfo::Utf8(descriptor)) => Some(descriptor.data.as_ref()),
+                _ => None,
+            },
+            _ => {
+                self.report(RuleId::ConstantReferences, location, format!("name_and_type_index #{} is not a CONSTANT_NameAndType", index));
+                None
+            }
+        }
+    }
+
+    fn expect_field_descriptor(&mut self, location: &str, descriptor: &str) {
+        if !is_field_descriptor(descriptor) {
+            self.report(RuleId::Descriptors, location, format!("\"{}\" is not a valid field descriptor", descriptor));
+        }
+    }
+
+    fn expect_method_descriptor(&mut self, location: &str, descriptor: &str) {
+        if !is_method_descriptor(descriptor) {
+            self.report(RuleId::Descriptors, location, format!("\"{}\" is not a valid method descriptor", descriptor));
+        }
+    }
+
+    fn expect_name(&mut self, location: &str, checked: Result<(), NameError>) {
+        if let Err(error) = checked {
+            self.report(RuleId::Names, location, error.to_string());
+        }
+    }
+
+    fn check_constant_pool(&mut self) {
+        let mut index = 1;
+        while index < self.constant_pool.len() {
+            let location = format!("constant_pool[{}]", index);
+            match &self.constant_pool[index] {
+                ConstantPoolInfo::Dummy() => {
+                    self.report(RuleId::ConstantSlots, &location, "unusable entry outside of a Long/Double slot");
+                }
+
+                ConstantPoolInfo::Class(info) => {
+                    let name_index = info.name_index;
+                    if let Some(name) =
+                        self.expect_utf8(RuleId::ConstantReferences, &location, "name_index", name_index)
+                    {
+                        self.expect_name(&location, check_class_name(name));
+                    }
+                }
+
+                ConstantPoolInfo::FieldRef(info) => {
+                    self.expect_class(RuleId::ConstantReferences, &location, "class_index", info.class_index);
+                    if let Some(descriptor) = self.expect_name_and_type(&location, info.name_and_type_index) {
+                        self.expect_field_descriptor(&location, descriptor);
+                    }
+                }
+
+                ConstantPoolInfo::MethodRef(info) => {
+                    self.expect_class(RuleId::ConstantReferences, &location, "class_index", info.class_index);
+                    if let Some(descriptor) = self.expect_name_and_type(&location, info.name_and_type_index) {
+                        self.expect_method_descriptor(&location, descriptor);
+                    }
+                }
+
+                ConstantPoolInfo::InterfaceMethodRef(info) => {
+                    self.expect_class(RuleId::ConstantReferences, &location, "class_index", info.class_index);
+                    if let Some(descriptor) = self.expect_name_and_type(&location, info.name_and_type_index) {
+                        self.expect_method_descriptor(&location, descriptor);
+                    }
+                }
+
+                ConstantPoolInfo::String(info) => {
+                    self.expect_utf8(RuleId::ConstantReferences, &location, "string_index", info.string_index);
+                }
+
+                ConstantPoolInfo::Integer(_) | ConstantPoolInfo::Float(_) => {}
+
+                ConstantPoolInfo::Long(_) | ConstantPoolInfo::Double(_) => {
+                    // The entry following a Long or Double is unusable.
+                    if !matches!(self.constant_pool.get(index + 1), Some(ConstantPoolInfo::Dummy())) {
+                        self.report(RuleId::ConstantSlots, &location, "8-byte constant does not occupy two entries");
+                    }
+                    index += 1;
+                }
+
+                ConstantPoolInfo::NameAndType(info) => {
+                    let name = self.expect_utf8(RuleId::ConstantReferences, &location, "name_index", info.name_index);
+                    let descriptor_index = info.descriptor_index;
+                    if let Some(descriptor) =
+                        self.expect_utf8(RuleId::ConstantReferences, &location, "descriptor_index", descriptor_index)
+                    {
+                        if !is_field_descriptor(descriptor) && !is_method_descriptor(descriptor) {
+                            self.report(RuleId::Descriptors, &location, format!("\"{}\" is not a valid descriptor", descriptor));
+                        }
+                        if let Some(name) = name {
+                            let checked = if descriptor.starts_with('(') {
+                                check_method_name(name)
+                            } else {
+                                check_field_name(name)
+                            };
+                            self.expect_name(&location, checked);
+                        }
+                    }
+                }
+
+                ConstantPoolInfo::Utf8(_) => {}
+
+                ConstantPoolInfo::MethodHandle(info) => {
+                    let reference = self.entry(info.reference_index);
+                    let valid = match info.reference_kind {
+                        1..=4 => matches!(reference, Some(ConstantPoolInfo::FieldRef(_))),
+                        5 | 8 => matches!(reference, Some(ConstantPoolInfo::MethodRef(_))),
+                        6 | 7 => matches!(
+                            reference,
+                            Some(ConstantPoolInfo::MethodRef(_) | ConstantPoolInfo::InterfaceMethodRef(_))
+                        ),
+                        9 => matches!(reference, Some(ConstantPoolInfo::InterfaceMethodRef(_))),
+                        kind => {
+                            self.report(RuleId::MethodHandleKind, &location, format!("reference_kind {} is out of range", kind));
+                            true
+                        }
+                    };
+                    if !valid {
+                        self.report(
+                            RuleId::MethodHandleKind,
+                            &location,
+                            format!(
+                                "reference_index #{} does not match reference_kind {}",
+                                info.reference_index, info.reference_kind
+                            ),
+                        );
+                    }
+                }
+
+                ConstantPoolInfo::MethodType(info) => {
+                    let descriptor_index = info.descriptor_index;
+                    if let Some(descriptor) =
+                        self.expect_utf8(RuleId::ConstantReferences, &location, "descriptor_index", descriptor_index)
+                    {
+                        self.expect_method_descriptor(&location, descriptor);
+                    }
+                }
+
+                ConstantPoolInfo::Dynamic(info) => {
+                    if let Some(descriptor) = self.expect_name_and_type(&location, info.name_and_type_index) {
+                        self.expect_field_descriptor(&location, descriptor);
+                    }
+                }
+
+                ConstantPoolInfo::InvokeDynamic(info) => {
+                    if let Some(descriptor) = self.expect_name_and_type(&location, info.name_and_type_index) {
+                        self.expect_method_descriptor(&location, descriptor);
+                    }
+                }
+
+                ConstantPoolInfo::Module(info) => {
+                    let name_index = info.name_index;
+                    if let Some(name) =
+                        self.expect_utf8(RuleId::ConstantReferences, &location, "name_index", name_index)
+                    {
+                        self.expect_name(&location, check_module_name(name));
+                    }
+                }
+
+                ConstantPoolInfo::Package(info) => {
+                    let name_index = info.name_index;
+                    if let Some(name) =
+                        self.expect_utf8(RuleId::ConstantReferences, &location, "name_index", name_index)
+                    {
+                        self.expect_name(&location, check_package_name(name));
+                    }
+                }
+            }
+
+            index += 1;
+        }
+    }
+
+    fn check_attribute_names(&mut self, location: &str, attributes: &Attributes) {
+        for name_index in attributes.name_indices() {
+            self.expect_utf8(RuleId::AttributeNames, location, "attribute_name_index", name_index as usize);
+        }
+    }
+}
+
+impl JavaClassFile<'_> {
+    /// Checks the class file against the format checks of the JVM specification
+    /// and returns all violations found.
+    ///
+    /// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.8
+    pub fn verify_format(&self) -> Vec<FormatViolation> {
+        let mut checker = FormatChecker {
+            constant_pool: &self.constant_pool,
+            violations: Vec::new(),
+        };
+
+        if self.magic != CLASS_FILE_MAGIC {
+            checker.report(RuleId::Magic, "magic", format!("0x{:08X} is not 0xCAFEBABE", self.magic));
+        }
+
+        checker.check_constant_pool();
+
+        checker.expect_class(RuleId::ClassReferences, "this_class", "this_class", self.this_class);
+        if self.super_class != 0 {
+            checker.expect_class(RuleId::ClassReferences, "super_class", "super_class", self.super_class);
+        }
+        for (i, interface) in self.interfaces.iter().enumerate() {
+            checker.expect_class(RuleId::ClassReferences, &format!("interfaces[{}]", i), "interface", *interface);
+        }
+
+        for (i, field) in self.fields.iter().enumerate() {
+            let location = format!("fields[{}]", i);
+            let name_index = field.name_index;
+            if let Some(name) = checker.expect_utf8(RuleId::FieldReferences, &location, "name_index", name_index) {
+                checker.expect_name(&location, check_field_name(name));
+            }
+            let descriptor_index = field.descriptor_index;
+            if let Some(descriptor) =
+                checker.expect_utf8(RuleId::FieldReferences, &location, "descriptor_index", descriptor_index)
+            {
+                checker.expect_field_descriptor(&location, descriptor);
+            }
+            checker.check_attribute_names(&location, &field.attributes);
+        }
+
+        for (i, method) in self.methods.iter().enumerate() {
+            let location = format!("methods[{}]", i);
+            let name_index = method.name_index;
+            if let Some(name) = checker.expect_utf8(RuleId::MethodReferences, &location, "name_index", name_index) {
+                checker.expect_name(&location, check_method_name(name));
+            }
+            let descriptor_index = method.descriptor_index;
+            if let Some(descriptor) =
+                checker.expect_utf8(RuleId::MethodReferences, &location, "descriptor_index", descriptor_index)
+            {
+                checker.expect_method_descriptor(&location, descriptor);
+            }
+            checker.check_attribute_names(&location, &method.attributes);
+        }
+
+        checker.check_attribute_names("attributes", &self.attributes);
+
+        checker.violations
+    }
+}
@@ -0,0 +1,1114 @@
+//! Computation of the StackMapTable frames of code which was generated or rewritten, as
+//! `ClassWriter.COMPUTE_FRAMES` of ASM does, so that class files of version 50.0 and later
+//! pass the type checking verifier without frames maintained by hand.
+//!
+//! The types of the local variables and operand stack are inferred along the edges of the
+//! [`ControlFlowGraph`] of the code until they no longer change. Where paths meet, types which
+//! differ are merged: two classes into their nearest common superclass, found through a
+//! [`ClassHierarchy`], and anything else into Top, which the stack must not hold.
+//!
+//! ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.10.1
+
+use std::{borrow::Cow, fmt};
+
+use crate::{
+    cfg::{falls_through, jump_targets, local_slots, opcode_at, CfgError, ControlFlowGraph, EdgeKind},
+    code::CodeView,
+    error::AttributeError,
+    index::{TypeEntry, TypeHierarchyIndex},
+    opcode::{instruction_length, Opcode},
+    operand::{resolve_operand, Constant, ResolvedOperand},
+    types::*,
+};
+
+const OBJECT: &str = "java/lang/Object";
+
+/// Finds common superclasses of classes, and whether one is assignable to another, which the
+/// code being framed or verified may refer to without them being at hand.
+pub trait ClassHierarchy {
+    /// Returns the internal name of the nearest common superclass of the classes `a` and `b`,
+    /// e.g. `java/lang/Number` for `java/lang/Integer` and `java/lang/Long`. The verifier treats
+    /// interfaces as `java/lang/Object`, which is also the answer if either is one.
+    fn common_superclass(&self, a: &str, b: &str) -> String;
+
+    /// Returns true if an instance of the class `from` may be used as one of the class `to`:
+    /// if `to` is a superclass of `from`, or an interface, which the verifier lets any class
+    /// stand for. Hierarchies which cannot tell interfaces apart take none to be one.
+    fn is_assignable(&self, from: &str, to: &str) -> bool {
+        self.common_superclass(from, to) == to
+    }
+}
+
+impl<F: Fn(&str, &str) -> String> ClassHierarchy for F {
+    fn common_superclass(&self, a: &str, b: &str) -> String {
+        self(a, b)
+    }
+}
+
+/// Classes missing from the index are taken to extend `java/lang/Object` directly when merging
+/// types, but when verifying, any class is assignable to them, and they and classes extending
+/// them are assignable to any class, since they may be an interface or a subclass.
+impl ClassHierarchy for TypeHierarchyIndex {
+    fn common_superclass(&self, a: &str, b: &str) -> String {
+        let is_interface = |name: &str| self.get(name).is_some_and(TypeEntry::is_interface);
+        if is_interface(a) || is_interface(b) {
+            return OBJECT.to_string();
+        }
+        let superclasses_of_a = superclasses(self, a);
+        superclasses(self, b)
+            .into_iter()
+            .find(|name| superclasses_of_a.contains(name))
+            .unwrap_or(OBJECT)
+            .to_string()
+    }
+
+    fn is_assignable(&self, from: &str, to: &str) -> bool {
+        match (self.get(from), self.get(to)) {
+            (Some(_), Some(entry)) if !entry.is_interface() => {
+                let superclasses = superclasses(self, from);
+                let unknown = superclasses.last().is_some_and(|&name| name != OBJECT && self.get(name).is_none());
+                unknown || superclasses.contains(&to)
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Returns `name` followed by its superclasses, nearest first.
+fn superclasses<'i>(index: &'i TypeHierarchyIndex, name: &'i str) -> Vec<&'i str> {
+    let mut chain = vec![name];
+    while let Some(super_class) = index.get(chain[chain.len() - 1]).and_then(|entry| entry.super_class.as_deref()) {
+        if chain.contains(&super_class) {
+            break;
+        }
+        chain.push(super_class);
+    }
+    chain
+}
+
+/// The type of a local variable or operand stack slot, as the type checking verifier infers it.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.10.1.2
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum VerificationType {
+    /// A value which cannot be used, e.g. of a local variable not stored yet, or the second
+    /// slot of a long or double.
+    Top,
+    /// An int, short, char, byte or boolean.
+    Integer,
+    Float,
+    Long,
+    Double,
+    Null,
+    /// `this` in a constructor before it calls another constructor.
+    UninitializedThis,
+    /// An object created by the `new` at this offset, whose constructor has not been called.
+    Uninitialized(u16),
+    /// An instance of the class with this internal name, or of the array type with this
+    /// descriptor, e.g. `[I`.
+    Object(String),
+    /// The offset a jsr or jsr_w returns to, which only the inference verifier of class files
+    /// before version 50.0 knows.
+    ReturnAddress,
+}
+
+impl VerificationType {
+    /// Returns the type of a value of the field descriptor, or `None` if it is not one.
+    pub fn from_descriptor(descriptor: &str) -> Option<Self> {
+        Some(match descriptor.as_bytes().first()? {
+            b'B' | b'C' | b'I' | b'S' | b'Z' if descriptor.len() == 1 => Self::Integer,
+            b'F' if descriptor.len() == 1 => Self::Float,
+            b'J' if descriptor.len() == 1 => Self::Long,
+            b'D' if descriptor.len() == 1 => Self::Double,
+            b'L' => Self::Object(descriptor[1..].strip_suffix(';')?.to_string()),
+            b'[' => Self::Object(descriptor.to_string()),
+            _ => return None,
+        })
+    }
+
+    /// Returns true for long and double, which take two slots.
+    pub fn is_wide(&self) -> bool {
+        matches!(self, Self::Long | Self::Double)
+    }
+
+    /// Converts the type to its entry in a StackMapTable, adding the CONSTANT_Class of an
+    /// object type to `constants`.
+    pub fn to_info(&self, constants: &mut ConstantPoolBuilder) -> VerificationTypeInfo {
+        match self {
+            Self::Top | Self::ReturnAddress => VerificationTypeInfo::Top,
+            Self::Integer => VerificationTypeInfo::Integer,
+            Self::Float => VerificationTypeInfo::Float,
+            Self::Long => VerificationTypeInfo::Long,
+            Self::Double => VerificationTypeInfo::Double,
+            Self::Null => VerificationTypeInfo::Null,
+            Self::UninitializedThis => VerificationTypeInfo::UninitializedThis,
+            Self::Uninitialized(offset) => VerificationTypeInfo::Uninitialized { offset: *offset },
+            Self::Object(name) => VerificationTypeInfo::Object { cpool_index: constants.class(name.clone()) as u16 },
+        }
+    }
+}
+
+/// Writes the type as the verifier of HotSpot names it in its messages, e.g. `integer` or
+/// `'java/lang/String'`.
+impl fmt::Display for VerificationType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Top => f.write_str("top"),
+            Self::Integer => f.write_str("integer"),
+            Self::Float => f.write_str("float"),
+            Self::Long => f.write_str("long"),
+            Self::Double => f.write_str("double"),
+            Self::Null => f.write_str("null"),
+            Self::UninitializedThis => f.write_str("uninitializedThis"),
+            Self::Uninitialized(offset) => write!(f, "uninitialized({})", offset),
+            Self::Object(name) => write!(f, "'{}'", name),
+            Self::ReturnAddress => f.write_str("returnAddress"),
+        }
+    }
+}
+
+/// The types of the local variables and operand stack before an instruction, one per slot:
+/// a long or double is followed by Top in both. Local variables past the end are Top.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Frame {
+    pub locals: Vec<VerificationType>,
+    pub stack: Vec<VerificationType>,
+}
+
+impl Frame {
+    /// Returns the frame on entry to a method, which the verifier derives from its descriptor,
+    /// or `None` if the descriptor is malformed.
+    pub fn initial(this_class: &str, method_name: &str, descriptor: &str, access_flags: u16) -> Option<Self> {
+        let (parameters, _) = split_method_descriptor(descriptor)?;
+        let mut frame = Frame::default();
+        if !MethodAccessFlag::Static.test(access_flags) {
+            frame.locals.push(match method_name == "<init>" && this_class != OBJECT {
+                true => VerificationType::UninitializedThis,
+                false => VerificationType::Object(this_class.to_string()),
+            });
+        }
+        for parameter in parameters {
+            let parameter = VerificationType::from_descriptor(parameter)?;
+            frame.store(frame.locals.len(), parameter);
+        }
+        Some(frame)
+    }
+
+    /// Returns the local variables as a StackMapTable lists them: one entry for a long or
+    /// double, and without trailing Top.
+    pub fn stack_map_locals(&self) -> Vec<&VerificationType> {
+        let mut locals = stack_map_types(&self.locals);
+        while locals.last() == Some(&&VerificationType::Top) {
+            locals.pop();
+        }
+        locals
+    }
+
+    /// Returns the operand stack as a StackMapTable lists it: one entry for a long or double.
+    pub fn stack_map_stack(&self) -> Vec<&VerificationType> {
+        stack_map_types(&self.stack)
+    }
+
+    pub(crate) fn push(&mut self, value: VerificationType) {
+        let wide = value.is_wide();
+        self.stack.push(value);
+        if wide {
+            self.stack.push(VerificationType::Top);
+        }
+    }
+
+    /// Pops `slots` slots, whatever they hold.
+    pub(crate) fn pop(&mut self, slots: usize) -> Result<(), &'static str> {
+        let depth = self.stack.len().checked_sub(slots).ok_or("pops more values than the stack holds")?;
+        self.stack.truncate(depth);
+        Ok(())
+    }
+
+    /// Pops the value of one slot.
+    pub(crate) fn pop_value(&mut self) -> Result<VerificationType, &'static str> {
+        self.stack.pop().ok_or("pops more values than the stack holds")
+    }
+
+    pub(crate) fn load(&self, index: usize) -> Result<VerificationType, &'static str> {
+        match self.locals.get(index) {
+            Some(VerificationType::Top) | None => Err("loads a local variable which holds no value"),
+            Some(value) => Ok(value.clone()),
+        }
+    }
+
+    pub(crate) fn store(&mut self, index: usize, value: VerificationType) {
+        let end = index + if value.is_wide() { 2 } else { 1 };
+        if self.locals.len() < end {
+            self.locals.resize(end, VerificationType::Top);
+        }
+        // Overwriting the second slot of a long or double leaves the first unusable.
+        if index > 0 && self.locals[index - 1].is_wide() {
+            self.locals[index - 1] = VerificationType::Top;
+        }
+        if value.is_wide() {
+            self.locals[index + 1] = VerificationType::Top;
+        }
+        self.locals[index] = value;
+    }
+
+    /// Replaces every occurrence of `from`, once a constructor initialized the object.
+    pub(crate) fn replace(&mut self, from: &VerificationType, to: VerificationType) {
+        for value in self.locals.iter_mut().chain(&mut self.stack) {
+            if value == from {
+                *value = to.clone();
+            }
+        }
+    }
+}
+
+fn stack_map_types(types: &[VerificationType]) -> Vec<&VerificationType> {
+    let mut listed = Vec::with_capacity(types.len());
+    let mut position = 0;
+    while position < types.len() {
+        listed.push(&types[position]);
+        position += if types[position].is_wide() { 2 } else { 1 };
+    }
+    listed
+}
+
+/// Code whose frames cannot be computed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameError {
+    /// Name and descriptor of the method, e.g. `run()V`.
+    pub method: String,
+    /// Offset of the instruction at fault.
+    pub pc: usize,
+    pub reason: Cow<'static, str>,
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: pc {}: {}", self.method, self.pc, self.reason)
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+/// Infers the frames `method` of `class_file` needs in its StackMapTable, with their offsets in
+/// code order: at the targets of jumps and exception handlers, and at instructions following
+/// one which does not fall through, e.g. goto or athrow. Returns nothing if the method has no
+/// code.
+///
+/// Fails if the code has no [`ControlFlowGraph`], calls a subroutine, pops more than its stack
+/// holds, loads a local variable holding no value, reaches an instruction with stacks which
+/// cannot be merged, refers to a constant or member which cannot be resolved, or needs a frame
+/// at unreachable code, whose types cannot be inferred.
+pub fn infer_frames(
+    class_file: &JavaClassFile,
+    method: &MethodInfo,
+    hierarchy: &impl ClassHierarchy,
+) -> Result<Vec<(usize, Frame)>, FrameError> {
+    let constant_pool = &class_file.constant_pool;
+    let name = get_utf8(constant_pool, method.name_index).unwrap_or_default();
+    let descriptor = get_utf8(constant_pool, method.descriptor_index).unwrap_or_default();
+    let fault = |pc: usize, reason: Fault| FrameError { method: format!("{}{}", name, descriptor), pc, reason };
+    let error = |pc: usize, reason: &'static str| fault(pc, reason.into());
+
+    let this_class = get_class_name(constant_pool, class_file.this_class).ok_or_else(|| error(0, "has no this_class"))?;
+    let code = match CodeView::new(method, constant_pool) {
+        Ok(Some(code)) => code,
+        Ok(None) => return Ok(Vec::new()),
+        Err(_) => return Err(error(0, "has a malformed Code attribute")),
+    };
+    let initial = Frame::initial(this_class, name, descriptor, method.access_flags)
+        .ok_or_else(|| error(0, "has a malformed method descriptor"))?;
+    let graph = ControlFlowGraph::from_code(&code).map_err(|CfgError { pc, reason }| error(pc, reason))?;
+    let inference = Interpreter { class_file, code: code.code, this_class, hierarchy, checks: None };
+
+    let blocks = graph.blocks();
+    let block_of = |pc: usize| blocks.binary_search_by_key(&pc, |block| block.start).ok();
+    let mut frames: Vec<Option<Frame>> = vec![None; blocks.len()];
+    let mut pending = Vec::new();
+    if !blocks.is_empty() {
+        frames[0] = Some(initial);
+        pending.push(0);
+    }
+    while let Some(position) = pending.pop() {
+        let block = &blocks[position];
+        let mut frame = frames[position].clone().unwrap_or_default();
+        let mut pc = block.start;
+        while pc < block.end {
+            for entry in &code.exception_table {
+                if !(entry.start_pc as usize..entry.end_pc as usize).contains(&pc) {
+                    continue;
+                }
+                let handler = entry.handler_pc as usize;
+                let catch_type = match entry.catch_type {
+                    0 => "java/lang/Throwable",
+                    index => get_class_name(constant_pool, index as usize)
+                        .ok_or_else(|| error(handler, "catches no class"))?,
+                };
+                let thrown = Frame {
+                    locals: frame.locals.clone(),
+                    stack: vec![VerificationType::Object(catch_type.to_string())],
+                };
+                let target = block_of(handler).ok_or_else(|| error(handler, "is not the start of a block"))?;
+                if inference.merge(&mut frames[target], thrown).map_err(|reason| error(handler, reason))? {
+                    pending.push(target);
+                }
+            }
+            inference.execute(&mut frame, pc).map_err(|reason| fault(pc, reason))?;
+            pc += instruction_length(code.code, pc).unwrap_or(block.end - pc);
+        }
+        for edge in &block.successors {
+            match edge.kind {
+                EdgeKind::FallThrough | EdgeKind::Branch => {}
+                EdgeKind::Exception => continue,
+                EdgeKind::Call | EdgeKind::Return => return Err(error(block.last, "calls a subroutine")),
+            }
+            let Some(target) = block_of(edge.target) else {
+                continue;
+            };
+            if inference.merge(&mut frames[target], frame.clone()).map_err(|reason| error(edge.target, reason))? {
+                pending.push(target);
+            }
+        }
+    }
+
+    let mut needed: Vec<usize> = code.exception_table.iter().map(|entry| entry.handler_pc as usize).collect();
+    let mut pc = 0;
+    while let Some(length) = instruction_length(code.code, pc) {
+        let (targets, _) = jump_targets(code.code, pc).ok_or_else(|| error(pc, "jumps before the code"))?;
+        needed.extend(targets);
+        if !falls_through(opcode_at(code.code, pc)) && pc + length < code.code.len() {
+            needed.push(pc + length);
+        }
+        pc += length;
+    }
+    needed.sort_unstable();
+    needed.dedup();
+    needed
+        .into_iter()
+        .map(|pc| {
+            let frame = block_of(pc).and_then(|position| frames[position].clone());
+            Ok((pc, frame.ok_or_else(|| error(pc, "is unreachable, so its frame cannot be inferred"))?))
+        })
+        .collect()
+}
+
+/// Encodes `frames`, as [`infer_frames`] returns them, into the StackMapTable of code entered
+/// with `initial`, each in the most compact kind of frame: same, same_locals_1_stack_item,
+/// chop or append where they apply, and full otherwise. The CONSTANT_Class entries of object
+/// types are added to `constants`.
+pub fn stack_map_table(
+    initial: &Frame,
+    frames: &[(usize, Frame)],
+    constants: &mut ConstantPoolBuilder,
+) -> Result<StackMapTableAttribute, AttributeError> {
+    let mut entries = Vec::with_capacity(frames.len());
+    let mut previous_locals = initial.stack_map_locals();
+    let mut previous_pc = None;
+    for (pc, frame) in frames {
+        let delta = match previous_pc {
+            Some(previous_pc) => pc.checked_sub(previous_pc + 1),
+            None => Some(*pc),
+        };
+        let delta = delta
+            .and_then(|delta| u16::try_from(delta).ok())
+            .ok_or(AttributeError::OutOfRange { while_building: "StackMapTable", value: *pc })?;
+        let locals = frame.stack_map_locals();
+        let stack = frame.stack_map_stack();
+        let mut infos = |types: &[&VerificationType]| types.iter().map(|value| value.to_info(constants)).collect();
+
+        let entry = if locals == previous_locals && stack.is_empty() {
+            match delta {
+                0..=63 => StackMapFrame::SameFrame(SameFrame::new(delta as u8)?),
+                _ => StackMapFrame::SameFrameExtended(SameFrameExtended::new(delta)),
+            }
+        } else if locals == previous_locals && stack.len() == 1 {
+            let stack = stack[0].to_info(constants);
+            match delta {
+                0..=63 => StackMapFrame::SameLocals1StackItemFrame(SameLocals1StackItemFrame::new(delta as u8, stack)?),
+                _ => StackMapFrame::SameLocals1StackItemFrameExtended(SameLocals1StackItemFrameExtended::new(
+                    delta, stack,
+                )),
+            }
+        } else if stack.is_empty() && locals.len() > previous_locals.len() && locals.starts_with(&previous_locals) {
+            match locals.len() - previous_locals.len() {
+                1..=3 => StackMapFrame::AppendFrame(AppendFrame::new(delta, infos(&locals[previous_locals.len()..]))?),
+                _ => StackMapFrame::FullFrame(FullFrame::new(delta, infos(&locals), Vec::new())?),
+            }
+        } else if stack.is_empty() && locals.len() < previous_locals.len() && previous_locals.starts_with(&locals) {
+            match previous_locals.len() - locals.len() {
+                chopped @ 1..=3 => StackMapFrame::ChopFrame(ChopFrame::new(chopped as u8, delta)?),
+                _ => StackMapFrame::FullFrame(FullFrame::new(delta, infos(&locals), Vec::new())?),
+            }
+        } else {
+            StackMapFrame::FullFrame(FullFrame::new(delta, infos(&locals), infos(&stack))?)
+        };
+        entries.push(entry);
+        previous_locals = locals;
+        previous_pc = Some(*pc);
+    }
+    StackMapTableAttribute::new(entries)
+}
+
+/// Replaces the StackMapTable of every method of `class_file` with frames computed by
+/// [`infer_frames`], and removes it from methods which need none. The constants the frames
+/// refer to are added to the constant pool.
+///
+/// Class files before version 50.0 are left unchanged, since they are verified without
+/// frames. Nothing is changed if the frames of a method cannot be computed.
+pub fn compute_frames(class_file: &mut JavaClassFile, hierarchy: &impl ClassHierarchy) -> Result<(), FrameError> {
+    if class_file.major_version < 50 {
+        return Ok(());
+    }
+    let constant_pool = &class_file.constant_pool;
+    let this_class = get_class_name(constant_pool, class_file.this_class).unwrap_or_default();
+    let mut constants = ConstantPoolBuilder::extending(constant_pool);
+    let mut tables = Vec::with_capacity(class_file.methods.len());
+    for method in &class_file.methods {
+        let frames = infer_frames(class_file, method, hierarchy)?;
+        if frames.is_empty() {
+            tables.push(None);
+            continue;
+        }
+        let name = get_utf8(constant_pool, method.name_index).unwrap_or_default();
+        let descriptor = get_utf8(constant_pool, method.descriptor_index).unwrap_or_default();
+        let initial = Frame::initial(this_class, name, descriptor, method.access_flags).unwrap_or_default();
+        let table = stack_map_table(&initial, &frames, &mut constants).map_err(|_| FrameError {
+            method: format!("{}{}", name, descriptor),
+            pc: 0,
+            reason: "needs more frames than a StackMapTable holds".into(),
+        })?;
+        tables.push(Some(table));
+    }
+    let name_index = tables.iter().any(Option::is_some).then(|| constants.utf8("StackMapTable") as u16);
+    class_file.constant_pool = constants.build();
+
+    let constant_pool = &class_file.constant_pool;
+    for (method, table) in class_file.methods.iter_mut().zip(tables) {
+        let code = method.attributes.values_mut().find_map(|attribute| match attribute {
+            AttributeInfo::Code(code) => Some(code),
+            _ => None,
+        });
+        let Some(code) = code else {
+            continue;
+        };
+        code.attributes.retain(|index, attribute| {
+            !matches!(attribute, AttributeInfo::StackMapTable(_))
+                && get_utf8(constant_pool, index as usize) != Some("StackMapTable")
+        });
+        if let (Some(name_index), Some(table)) = (name_index, table) {
+            code.attributes.push(name_index, AttributeInfo::StackMapTable(table));
+        }
+    }
+    Ok(())
+}
+
+/// Why an instruction cannot be executed on a frame.
+pub(crate) type Fault = Cow<'static, str>;
+
+/// Executes instructions on frames of types, to infer the frames of code and, with
+/// [`Checks`], to verify it as the type checking verifier does.
+pub(crate) struct Interpreter<'c, 'h, H> {
+    pub(crate) class_file: &'c JavaClassFile<'c>,
+    pub(crate) code: &'c [u8],
+    pub(crate) this_class: &'c str,
+    pub(crate) hierarchy: &'h H,
+    /// `None` to only infer the types instructions produce, trusting those they consume.
+    pub(crate) checks: Option<Checks>,
+}
+
+/// What the verifier checks beyond the types of operands.
+pub(crate) struct Checks {
+    /// The type the method returns, or `None` for void.
+    pub(crate) return_type: Option<VerificationType>,
+    /// True for `<init>`, which must initialize `this` before returning.
+    pub(crate) constructor: bool,
+}
+
+impl<'c, H: ClassHierarchy> Interpreter<'c, '_, H> {
+    /// Merges `incoming` into the frame of a block, and returns true if that frame changed.
+    pub(crate) fn merge(&self, frame: &mut Option<Frame>, incoming: Frame) -> Result<bool, &'static str> {
+        let Some(frame) = frame else {
+            *frame = Some(incoming);
+            return Ok(true);
+        };
+        if frame.stack.len() != incoming.stack.len() {
+            return Err("is reached with different stack depths");
+        }
+        let mut changed = false;
+        for (value, incoming) in frame.stack.iter_mut().zip(&incoming.stack) {
+            let merged = self.merge_types(value, incoming);
+            if merged == VerificationType::Top && *value != VerificationType::Top {
+                return Err("is reached with stacks holding incompatible types");
+            }
+            changed |= merged != *value;
+            *value = merged;
+        }
+        for (position, value) in frame.locals.iter_mut().enumerate() {
+            let merged = self.merge_types(value, incoming.locals.get(position).unwrap_or(&VerificationType::Top));
+            changed |= merged != *value;
+            *value = merged;
+        }
+        Ok(changed)
+    }
+
+    fn merge_types(&self, a: &VerificationType, b: &VerificationType) -> VerificationType {
+        match (a, b) {
+            _ if a == b => a.clone(),
+            (VerificationType::Null, VerificationType::Object(_)) => b.clone(),
+            (VerificationType::Object(_), VerificationType::Null) => a.clone(),
+            (VerificationType::Object(a), VerificationType::Object(b)) => {
+                VerificationType::Object(self.common_supertype(a, b))
+            }
+            _ => VerificationType::Top,
+        }
+    }
+
+    /// Returns the nearest common supertype of two classes or array types, where arrays of
+    /// references are covariant in their elements.
+    fn common_supertype(&self, a: &str, b: &str) -> String {
+        match (a.strip_prefix('['), b.strip_prefix('[')) {
+            (None, None) => self.hierarchy.common_superclass(a, b),
+            (Some(a), Some(b)) => match (element_class(a), element_class(b)) {
+                (Some(a), Some(b)) => match self.common_supertype(a, b) {
+                    element if element.starts_with('[') => format!("[{}", element),
+                    element => format!("[L{};", element),
+                },
+                _ => OBJECT.to_string(),
+            },
+            _ => OBJECT.to_string(),
+        }
+    }
+
+    /// Returns true if a value of type `from` may be used where `to` is expected.
+    ///
+    /// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.10.1.2
+    pub(crate) fn is_assignable(&self, from: &VerificationType, to: &VerificationType) -> bool {
+        match (from, to) {
+            _ if from == to => true,
+            (_, VerificationType::Top) => true,
+            (VerificationType::Null, VerificationType::Object(_)) => true,
+            (VerificationType::Object(from), VerificationType::Object(to)) => self.is_subclass(from, to),
+            _ => false,
+        }
+    }
+
+    fn is_subclass(&self, from: &str, to: &str) -> bool {
+        if from == to || to == OBJECT {
+            return true;
+        }
+        match (from.strip_prefix('['), to.strip_prefix('[')) {
+            (Some(from), Some(to)) => match (element_class(from), element_class(to)) {
+                (Some(from), Some(to)) => self.is_subclass(from, to),
+                _ => from == to,
+            },
+            (Some(_), None) => matches!(to, "java/lang/Cloneable" | "java/io/Serializable"),
+            (None, Some(_)) => false,
+            (None, None) => self.hierarchy.is_assignable(from, to),
+        }
+    }
+
+    fn operand(&self, pc: usize) -> Result<ResolvedOperand<'c>, Fault> {
+        resolve_operand(self.class_file, self.code, pc).ok_or(Cow::Borrowed("has an operand which cannot be resolved"))
+    }
+
+    /// Checks that a value of type `found` may be used as `expected`, unless only inferring.
+    fn expect(&self, found: &VerificationType, expected: &VerificationType) -> Result<(), Fault> {
+        match self.checks.is_none() || self.is_assignable(found, expected) {
+            true => Ok(()),
+            false => Err(format!("expects {}, but finds {}", expected, found).into()),
+        }
+    }
+
+    /// Pops a value of type `expected`, taking two slots for a long or double.
+    fn pop(&self, frame: &mut Frame, expected: &VerificationType) -> Result<VerificationType, Fault> {
+        let slots = if expected.is_wide() { 2 } else { 1 };
+        let depth = frame.stack.len().checked_sub(slots).ok_or("pops more values than the stack holds")?;
+        let value = frame.stack[depth].clone();
+        self.expect(&value, expected)?;
+        frame.stack.truncate(depth);
+        Ok(value)
+    }
+
+    /// Pops a reference, which unlike `java/lang/Object` may be uninitialized.
+    fn pop_reference(&self, frame: &mut Frame) -> Result<VerificationType, Fault> {
+        let value = frame.stack.pop().ok_or("pops more values than the stack holds")?;
+        match value {
+            VerificationType::Null
+            | VerificationType::Object(_)
+            | VerificationType::Uninitialized(_)
+            | VerificationType::UninitializedThis => Ok(value),
+            _ if self.checks.is_none() => Ok(value),
+            _ => Err(format!("expects a reference, but finds {}", value).into()),
+        }
+    }
+
+    /// Pops an array whose descriptor `element` accepts, or null.
+    fn pop_array(&self, frame: &mut Frame, element: impl Fn(&str) -> bool) -> Result<VerificationType, Fault> {
+        let value = frame.stack.pop().ok_or("pops more values than the stack holds")?;
+        match &value {
+            VerificationType::Null => Ok(value),
+            VerificationType::Object(array) if array.strip_prefix('[').is_some_and(&element) => Ok(value),
+            _ if self.checks.is_none() => Ok(value),
+            _ => Err(format!("expects an array of another type, but finds {}", value).into()),
+        }
+    }
+
+    fn load(&self, frame: &Frame, index: usize, expected: &VerificationType) -> Result<VerificationType, Fault> {
+        match frame.locals.get(index) {
+            Some(VerificationType::Top) | None => Err("loads a local variable which holds no value".into()),
+            Some(value) => {
+                self.expect(value, expected)?;
+                Ok(value.clone())
+            }
+        }
+    }
+
+    /// Applies the instruction at `pc` to `frame`.
+    pub(crate) fn execute(&self, frame: &mut Frame, pc: usize) -> Result<(), Fault> {
+        use VerificationType::{Double, Float, Integer, Long, Null, Object, Uninitialized, UninitializedThis};
+
+        let code = self.code;
+        let object = |name: &str| Object(name.to_string());
+        let returned = |descriptor: &str| match descriptor {
+            "V" => Ok(None),
+            descriptor => VerificationType::from_descriptor(descriptor).map(Some).ok_or("has a malformed descriptor"),
+        };
+        let references = |element: &str| element.starts_with(['L', '[']);
+        let opcode = opcode_at(code, pc).ok_or("cannot be decoded")?;
+        let byte = opcode as u8;
+        let in_range = |first: Opcode, last: Opcode| (first as u8..=last as u8).contains(&byte);
+        // Loads and stores come in runs of int, long, float, double and reference, first with
+        // an explicit index, then 4 of each with an implicit one.
+        let kind = |explicit: Opcode, implicit: Opcode| match byte < implicit as u8 {
+            true => byte - explicit as u8,
+            false => (byte - implicit as u8) / 4,
+        };
+        let typed = |kind: u8| [Integer, Long, Float, Double][kind as usize].clone();
+
+        match opcode {
+            Opcode::Nop | Opcode::Goto | Opcode::GotoW => {}
+            Opcode::Jsr | Opcode::JsrW | Opcode::Ret => return Err("calls or returns from a subroutine".into()),
+            Opcode::AconstNull => frame.push(Null),
+            Opcode::IconstM1 | Opcode::Iconst0 | Opcode::Iconst1 | Opcode::Iconst2 | Opcode::Iconst3
+            | Opcode::Iconst4 | Opcode::Iconst5 | Opcode::Bipush | Opcode::Sipush => frame.push(Integer),
+            Opcode::Lconst0 | Opcode::Lconst1 => frame.push(Long),
+            Opcode::Fconst0 | Opcode::Fconst1 | Opcode::Fconst2 => frame.push(Float),
+            Opcode::Dconst0 | Opcode::Dconst1 => frame.push(Double),
+            Opcode::Ldc | Opcode::LdcW | Opcode::Ldc2W => {
+                let ResolvedOperand::Constant(constant) = self.operand(pc)? else {
+                    return Err("has an operand which cannot be resolved".into());
+                };
+                frame.push(match constant {
+                    Constant::Integer(_) => Integer,
+                    Constant::Float(_) => Float,
+                    Constant::Long(_) => Long,
+                    Constant::Double(_) => Double,
+                    Constant::String(_) => object("java/lang/String"),
+                    Constant::Class(_) => object("java/lang/Class"),
+                    Constant::MethodType(_) => object("java/lang/invoke/MethodType"),
+                    Constant::MethodHandle { .. } => object("java/lang/invoke/MethodHandle"),
+                    Constant::Dynamic(call) => returned(call.descriptor)?.ok_or("has a malformed descriptor")?,
+                });
+            }
+            _ if in_range(Opcode::Iload, Opcode::Aload3) => {
+                let (index, _) = local_slots(code, pc).ok_or("cannot be decoded")?;
+                match kind(Opcode::Iload, Opcode::Iload0) {
+                    4 => match frame.load(index)? {
+                        value @ (Null | Object(_) | Uninitialized(_) | UninitializedThis) => frame.push(value),
+                        value if self.checks.is_none() => frame.push(value),
+                        value => return Err(format!("expects a reference, but finds {}", value).into()),
+                    },
+                    kind => {
+                        let value = typed(kind);
+                        self.load(frame, index, &value)?;
+                        frame.push(value);
+                    }
+                }
+            }
+            _ if in_range(Opcode::Istore, Opcode::Astore3) => {
+                let (index, _) = local_slots(code, pc).ok_or("cannot be decoded")?;
+                let value = match kind(Opcode::Istore, Opcode::Istore0) {
+                    4 => match frame.stack.last() {
+                        Some(VerificationType::ReturnAddress) => frame.pop_value()?,
+                        _ => self.pop_reference(frame)?,
+                    },
+                    kind => self.pop(frame, &typed(kind))?,
+                };
+                frame.store(index, value);
+            }
+            Opcode::Iinc => {
+                let (index, _) = local_slots(code, pc).ok_or("cannot be decoded")?;
+                self.load(frame, index, &Integer)?;
+            }
+            Opcode::Iaload | Opcode::Laload | Opcode::Faload | Opcode::Daload | Opcode::Baload | Opcode::Caload
+            | Opcode::Saload => {
+                self.pop(frame, &Integer)?;
+                let (element, value) = match opcode {
+                    Opcode::Iaload => ("I", Integer),
+                    Opcode::Laload => ("J", Long),
+                    Opcode::Faload => ("F", Float),
+                    Opcode::Daload => ("D", Double),
+                    Opcode::Caload => ("C", Integer),
+                    Opcode::Saload => ("S", Integer),
+                    _ => ("B", Integer),
+                };
+                // baload also loads from boolean arrays.
+                self.pop_array(frame, |found| found == element || (element == "B" && found == "Z"))?;
+                frame.push(value);
+            }
+            Opcode::Aaload => {
+                self.pop(frame, &Integer)?;
+                let element = match self.pop_array(frame, references)? {
+                    Object(array) if array.starts_with('[') => {
+                        VerificationType::from_descriptor(&array[1..]).ok_or("has a malformed descriptor")?
+                    }
+                    // An unchecked value which is not an array loads an unknown reference.
+                    _ => Null,
+                };
+                frame.push(element);
+            }
+            Opcode::Iastore | Opcode::Lastore | Opcode::Fastore | Opcode::Dastore | Opcode::Bastore
+            | Opcode::Castore | Opcode::Sastore => {
+                let (element, value) = match opcode {
+                    Opcode::Iastore => ("I", Integer),
+                    Opcode::Lastore => ("J", Long),
+                    Opcode::Fastore => ("F", Float),
+                    Opcode::Dastore => ("D", Double),
+                    Opcode::Castore => ("C", Integer),
+                    Opcode::Sastore => ("S", Integer),
+                    _ => ("B", Integer),
+                };
+                self.pop(frame, &value)?;
+                self.pop(frame, &Integer)?;
+                self.pop_array(frame, |found| found == element || (element == "B" && found == "Z"))?;
+            }
+            Opcode::Aastore => {
+                // Whether the value fits the array is left to the JVM at run time.
+                self.pop(frame, &object(OBJECT))?;
+                self.pop(frame, &Integer)?;
+                self.pop_array(frame, references)?;
+            }
+            Opcode::Pop | Opcode::Pop2 => {
+                let slots = if opcode == Opcode::Pop { 1 } else { 2 };
+                let depth = frame.stack.len().checked_sub(slots).ok_or("pops more values than the stack holds")?;
+                check_category(&frame.stack, depth)?;
+                frame.pop(slots)?;
+            }
+            Opcode::Dup | Opcode::DupX1 | Opcode::DupX2 | Opcode::Dup2 | Opcode::Dup2X1 | Opcode::Dup2X2 => {
+                let offset = byte - Opcode::Dup as u8;
+                let (copied, skipped) = ((offset / 3 + 1) as usize, (offset % 3) as usize);
+                let depth = frame.stack.len();
+                let start = depth.checked_sub(copied + skipped).ok_or("pops more values than the stack holds")?;
+                check_category(&frame.stack, depth - copied)?;
+                check_category(&frame.stack, start)?;
+                let copy = frame.stack[depth - copied..].to_vec();
+                frame.stack.splice(start..start, copy);
+            }
+            Opcode::Swap => {
+                let depth = frame.stack.len().checked_sub(2).ok_or("pops more values than the stack holds")?;
+                check_category(&frame.stack, depth)?;
+                check_category(&frame.stack, depth + 1)?;
+                frame.stack.swap(depth, depth + 1);
+            }
+            _ if in_range(Opcode::Iadd, Opcode::Drem) => {
+                let value = typed((byte - Opcode::Iadd as u8) % 4);
+                self.pop(frame, &value)?;
+                self.pop(frame, &value)?;
+                frame.push(value);
+            }
+            Opcode::Ineg | Opcode::Lneg | Opcode::Fneg | Opcode::Dneg => {
+                let value = typed(byte - Opcode::Ineg as u8);
+                self.pop(frame, &value)?;
+                frame.push(value);
+            }
+            // Shifts and bitwise operations alternate between int and long.
+            _ if in_range(Opcode::Ishl, Opcode::Lxor) => {
+                let value = typed((byte - Opcode::Ishl as u8) % 2);
+                let shifts = in_range(Opcode::Ishl, Opcode::Lushr);
+                self.pop(frame, if shifts { &Integer } else { &value })?;
+                self.pop(frame, &value)?;
+                frame.push(value);
+            }
+            _ if in_range(Opcode::I2l, Opcode::I2s) => {
+                let (from, to) = match opcode {
+                    Opcode::I2l => (Integer, Long),
+                    Opcode::I2f => (Integer, Float),
+                    Opcode::I2d => (Integer, Double),
+                    Opcode::L2i => (Long, Integer),
+                    Opcode::L2f => (Long, Float),
+                    Opcode::L2d => (Long, Double),
+                    Opcode::F2i => (Float, Integer),
+                    Opcode::F2l => (Float, Long),
+                    Opcode::F2d => (Float, Double),
+                    Opcode::D2i => (Double, Integer),
+                    Opcode::D2l => (Double, Long),
+                    Opcode::D2f => (Double, Float),
+                    _ => (Integer, Integer), // i2b, i2c and i2s
+                };
+                self.pop(frame, &from)?;
+                frame.push(to);
+            }
+            Opcode::Lcmp | Opcode::Fcmpl | Opcode::Fcmpg | Opcode::Dcmpl | Opcode::Dcmpg => {
+                let value = match opcode {
+                    Opcode::Lcmp => Long,
+                    Opcode::Fcmpl | Opcode::Fcmpg => Float,
+                    _ => Double,
+                };
+                self.pop(frame, &value)?;
+                self.pop(frame, &value)?;
+                frame.push(Integer);
+            }
+            Opcode::Ifeq | Opcode::Ifne | Opcode::Iflt | Opcode::Ifge | Opcode::Ifgt | Opcode::Ifle
+            | Opcode::Tableswitch | Opcode::Lookupswitch => {
+                self.pop(frame, &Integer)?;
+            }
+            Opcode::IfIcmpeq | Opcode::IfIcmpne | Opcode::IfIcmplt | Opcode::IfIcmpge | Opcode::IfIcmpgt
+            | Opcode::IfIcmple => {
+                self.pop(frame, &Integer)?;
+                self.pop(frame, &Integer)?;
+            }
+            Opcode::IfAcmpeq | Opcode::IfAcmpne => {
+                self.pop_reference(frame)?;
+                self.pop_reference(frame)?;
+            }
+            Opcode::Ifnull | Opcode::Ifnonnull | Opcode::Monitorenter | Opcode::Monitorexit => {
+                self.pop_reference(frame)?;
+            }
+            Opcode::Ireturn | Opcode::Lreturn | Opcode::Freturn | Opcode::Dreturn | Opcode::Areturn => {
+                let value = match opcode {
+                    Opcode::Areturn => object(OBJECT),
+                    _ => typed(byte - Opcode::Ireturn as u8),
+                };
+                match &self.checks {
+                    Some(Checks { return_type: Some(return_type), .. }) => {
+                        let kind = |value: &VerificationType| matches!(value, Null | Object(_));
+                        if kind(return_type) != kind(&value) || (!kind(&value) && *return_type != value) {
+                            return Err(format!("returns {} from a method returning {}", value, return_type).into());
+                        }
+                        self.pop(frame, return_type)?;
+                    }
+                    Some(Checks { return_type: None, .. }) => return Err("returns a value from a void method".into()),
+                    None => {
+                        self.pop(frame, &value)?;
+                    }
+                }
+            }
+            Opcode::Return => match &self.checks {
+                Some(Checks { return_type: Some(return_type), .. }) => {
+                    return Err(format!("returns void from a method returning {}", return_type).into());
+                }
+                Some(Checks { constructor: true, .. }) if frame.locals.contains(&UninitializedThis) => {
+                    return Err("returns from a constructor before calling another one".into());
+                }
+                _ => {}
+            },
+            Opcode::Athrow => {
+                self.pop(frame, &object("java/lang/Throwable"))?;
+            }
+            Opcode::Getstatic | Opcode::Putstatic | Opcode::Getfield | Opcode::Putfield => {
+                let ResolvedOperand::Member(member) = self.operand(pc)? else {
+                    return Err("has an operand which cannot be resolved".into());
+                };
+                let value = returned(member.descriptor)?.ok_or("has a malformed descriptor")?;
+                match opcode {
+                    Opcode::Getstatic => frame.push(value),
+                    Opcode::Putstatic => {
+                        self.pop(frame, &value)?;
+                    }
+                    Opcode::Getfield => {
+                        self.pop(frame, &object(member.owner))?;
+                        frame.push(value);
+                    }
+                    _ => {
+                        self.pop(frame, &value)?;
+                        // A constructor may set the fields of its class before calling another one.
+                        match frame.stack.last() {
+                            Some(UninitializedThis) if member.owner == self.this_class => frame.pop(1)?,
+                            _ => {
+                                self.pop(frame, &object(member.owner))?;
+                            }
+                        }
+                    }
+                }
+            }
+            Opcode::Invokevirtual | Opcode::Invokespecial | Opcode::Invokestatic | Opcode::Invokeinterface => {
+                let ResolvedOperand::Member(member) = self.operand(pc)? else {
+                    return Err("has an operand which cannot be resolved".into());
+                };
+                let (parameters, return_descriptor) =
+                    split_method_descriptor(member.descriptor).ok_or("has a malformed descriptor")?;
+                for parameter in parameters.iter().rev() {
+                    let parameter = VerificationType::from_descriptor(parameter).ok_or("has a malformed descriptor")?;
+                    self.pop(frame, &parameter)?;
+                }
+                if opcode == Opcode::Invokespecial && member.name == "<init>" {
+                    let receiver = self.pop_reference(frame)?;
+                    let initialized = match receiver {
+                        UninitializedThis => {
+                            let constant_pool = &self.class_file.constant_pool;
+                            let super_class = get_class_name(constant_pool, self.class_file.super_class);
+                            let own = member.owner == self.this_class || Some(member.owner) == super_class;
+                            if self.checks.is_some() && !own {
+                                return Err("initializes this with a constructor of another class".into());
+                            }
+                            self.this_class
+                        }
+                        Uninitialized(offset) => match (code.get(offset as usize), self.operand(offset as usize)) {
+                            (Some(&byte), Ok(ResolvedOperand::Class(name))) if byte == Opcode::New as u8 => {
+                                if self.checks.is_some() && member.owner != name {
+                                    return Err("initializes an object with a constructor of another class".into());
+                                }
+                                name
+                            }
+                            _ => return Err("initializes an object not created by new".into()),
+                        },
+                        _ => return Err("calls a constructor of an initialized object".into()),
+                    };
+                    frame.replace(&receiver, object(initialized));
+                } else if opcode != Opcode::Invokestatic {
+                    self.pop(frame, &object(member.owner))?;
+                }
+                if let Some(value) = returned(return_descriptor)? {
+                    frame.push(value);
+                }
+            }
+            Opcode::Invokedynamic => {
+                let ResolvedOperand::InvokeDynamic(call) = self.operand(pc)? else {
+                    return Err("has an operand which cannot be resolved".into());
+                };
+                let (parameters, return_descriptor) =
+                    split_method_descriptor(call.descriptor).ok_or("has a malformed descriptor")?;
+                for parameter in parameters.iter().rev() {
+                    let parameter = VerificationType::from_descriptor(parameter).ok_or("has a malformed descriptor")?;
+                    self.pop(frame, &parameter)?;
+                }
+                if let Some(value) = returned(return_descriptor)? {
+                    frame.push(value);
+                }
+            }
+            Opcode::New => frame.push(Uninitialized(pc as u16)),
+            Opcode::Newarray => {
+                self.pop(frame, &Integer)?;
+                let array = match code.get(pc + 1).ok_or("cannot be decoded")? {
+                    4 => "[Z",
+                    5 => "[C",
+                    6 => "[F",
+                    7 => "[D",
+                    8 => "[B",
+                    9 => "[S",
+                    10 => "[I",
+                    11 => "[J",
+                    _ => return Err("creates an array of an unknown type".into()),
+                };
+                frame.push(object(array));
+            }
+            Opcode::Anewarray | Opcode::Multianewarray => {
+                let ResolvedOperand::Class(name) = self.operand(pc)? else {
+                    return Err("has an operand which cannot be resolved".into());
+                };
+                let dimensions = match opcode {
+                    Opcode::Multianewarray => *code.get(pc + 3).ok_or("cannot be decoded")? as usize,
+                    _ => 1,
+                };
+                if self.checks.is_some() && opcode == Opcode::Multianewarray {
+                    let depth = name.bytes().take_while(|&byte| byte == b'[').count();
+                    if dimensions == 0 || depth < dimensions {
+                        return Err("creates more dimensions than its array type has".into());
+                    }
+                }
+                for _ in 0..dimensions {
+                    self.pop(frame, &Integer)?;
+                }
+                frame.push(match opcode {
+                    Opcode::Anewarray if name.starts_with('[') => Object(format!("[{}", name)),
+                    Opcode::Anewarray => Object(format!("[L{};", name)),
+                    _ => object(name),
+                });
+            }
+            Opcode::Checkcast | Opcode::Instanceof => {
+                let ResolvedOperand::Class(name) = self.operand(pc)? else {
+                    return Err("has an operand which cannot be resolved".into());
+                };
+                self.pop(frame, &object(OBJECT))?;
+                frame.push(if opcode == Opcode::Checkcast { object(name) } else { Integer });
+            }
+            Opcode::Arraylength => {
+                self.pop_array(frame, |_| true)?;
+                frame.push(Integer);
+            }
+            // Loads, stores, arithmetic and conversions are matched by the ranges above.
+            _ => return Err("cannot be decoded".into()),
+        }
+        Ok(())
+    }
+}
+
+/// Fails if the slots of `stack` from `boundary` on start with the second half of a long or
+/// double, which must not be split.
+fn check_category(stack: &[VerificationType], boundary: usize) -> Result<(), Fault> {
+    match boundary.checked_sub(1).map(|first| &stack[first]) {
+        Some(first) if first.is_wide() && boundary < stack.len() => Err("splits a long or double on the stack".into()),
+        _ => Ok(()),
+    }
+}
+
+/// Returns the class of the elements of an array, e.g. `java/lang/String` for the element
+/// descriptor `Ljava/lang/String;`, or `None` if they are not references.
+fn element_class(element: &str) -> Option<&str> {
+    match element.starts_with('[') {
+        true => Some(element),
+        false => element.strip_prefix('L')?.strip_suffix(';'),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_compact_frames() {
+        use VerificationType::{Integer, Long, Object, Top, UninitializedThis};
+
+        let initial = Frame::initial("Example", "<init>", "(JI)V", 0).unwrap();
+        assert_eq!(initial.locals, [UninitializedThis, Long, Top, Integer]);
+        let mut overwritten = initial.clone();
+        overwritten.store(2, Integer);
+        assert_eq!(overwritten.locals, [UninitializedThis, Top, Integer, Integer]);
+
+        let frame = |locals: &[VerificationType], stack: &[VerificationType]| Frame {
+            locals: locals.to_vec(),
+            stack: stack.to_vec(),
+        };
+        let frames = [
+            (3, initial.clone()),
+            (10, frame(&initial.locals, &[Long, Top])),
+            (100, frame(&[UninitializedThis, Long, Top, Integer, Integer, Top], &[])),
+            (101, frame(&[UninitializedThis], &[])),
+            (102, frame(&[UninitializedThis], &[Object("java/lang/Throwable".to_string())])),
+            (103, frame(&[Integer], &[Integer])),
+        ];
+        let mut constants = ConstantPoolBuilder::new();
+        let table = stack_map_table(&initial, &frames, &mut constants).unwrap();
+        let kinds = table.entries.iter().map(|entry| match entry {
+            StackMapFrame::SameFrame(frame) => ("same", frame.frame_type as u16),
+            StackMapFrame::SameLocals1StackItemFrame(frame) => ("same_locals_1_stack_item", frame.frame_type as u16),
+            StackMapFrame::AppendFrame(frame) => ("append", frame.offset_delta),
+            StackMapFrame::ChopFrame(frame) => ("chop", frame.offset_delta),
+            StackMapFrame::FullFrame(frame) => ("full", frame.offset_delta),
+            _ => ("other", 0),
+        });
+        assert_eq!(
+            kinds.collect::<Vec<_>>(),
+            [
+                ("same", 3),
+                ("same_locals_1_stack_item", 64 + 6),
+                ("append", 89),
+                ("chop", 0),
+                ("same_locals_1_stack_item", 64),
+                ("full", 0),
+            ]
+        );
+        let StackMapFrame::SameLocals1StackItemFrame(caught) = &table.entries[4] else { unreachable!() };
+        let VerificationTypeInfo::Object { cpool_index } = caught.stack else { unreachable!() };
+        assert_eq!(get_class_name(constants.constants(), cpool_index as usize), Some("java/lang/Throwable"));
+    }
+}
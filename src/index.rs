@@ -0,0 +1,600 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    io::{self, Read, Write},
+    path::Path,
+    time::UNIX_EPOCH,
+};
+
+use crate::{types::*, utils::fnv1a};
+
+/// Magic of the persisted index format.
+const INDEX_MAGIC: &[u8; 4] = b"JCTH";
+const INDEX_VERSION: u16 = 1;
+
+/// Magic of the persisted [`ClassIndex`] format.
+const CLASS_INDEX_MAGIC: &[u8; 4] = b"JCCI";
+const CLASS_INDEX_VERSION: u16 = 1;
+
+fn write_str(writer: &mut impl Write, value: &str) -> io::Result<()> {
+    writer.write_all(&(value.len() as u32).to_be_bytes())?;
+    writer.write_all(value.as_bytes())
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut buffer = [0u8; N];
+    reader.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
+
+fn read_str(reader: &mut impl Read) -> io::Result<String> {
+    let length = u32::from_be_bytes(read_array(reader)?) as u64;
+    let mut buffer = Vec::new();
+    reader.take(length).read_to_end(&mut buffer)?;
+    if buffer.len() as u64 != length {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    String::from_utf8(buffer).map_err(|_| invalid("name is not UTF-8"))
+}
+
+/// Supertypes of one class in a [`TypeHierarchyIndex`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeEntry {
+    pub access_flags: u16,
+    /// `None` for `java/lang/Object` and module descriptors.
+    pub super_class: Option<String>,
+    pub interfaces: Vec<String>,
+}
+
+impl TypeEntry {
+    pub fn is_interface(&self) -> bool {
+        ClassAccessFlag::Interface.test(self.access_flags)
+    }
+}
+
+/// Index of the "extends" and "implements" relations of a set of classes.
+///
+/// Class names are in internal form, e.g. `java/util/ArrayList`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TypeHierarchyIndex {
+    types: HashMap<String, TypeEntry>,
+    /// Reverse of [`TypeEntry::super_class`] and [`TypeEntry::interfaces`].
+    subtypes: HashMap<String, BTreeSet<String>>,
+}
+
+impl TypeHierarchyIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds the index of all classes in a jar.
+    #[cfg(feature = "jar")]
+    pub fn build(jar: &crate::jar::Jar) -> Result<Self, crate::jar::JarError> {
+        use crate::jar::JarError;
+
+        let mut index = Self::new();
+        for entry in jar.class_entries() {
+            let bytes = jar.read(entry)?;
+            let class_file = crate::decode(&bytes).map_err(|error| JarError::Decode {
+                name: entry.name.clone(),
+                error,
+            })?;
+            index.add_class(&class_file);
+        }
+        Ok(index)
+    }
+
+    /// Adds a class to the index. Returns `false` if this_class cannot be resolved.
+    pub fn add_class(&mut self, class_file: &JavaClassFile) -> bool {
+        let constant_pool = &class_file.constant_pool;
+        let Some(name) = get_class_name(constant_pool, class_file.this_class) else {
+            return false;
+        };
+
+        let entry = TypeEntry {
+            access_flags: class_file.access_flags,
+            super_class: get_class_name(constant_pool, class_file.super_class).map(str::to_string),
+            interfaces: class_file
+                .interfaces
+                .iter()
+                .filter_map(|&index| get_class_name(constant_pool, index))
+                .map(str::to_string)
+                .collect(),
+        };
+        self.insert(name.to_string(), entry);
+        true
+    }
+
+    fn insert(&mut self, name: String, entry: TypeEntry) {
+        for supertype in entry.super_class.iter().chain(&entry.interfaces) {
+            self.subtypes.entry(supertype.clone()).or_default().insert(name.clone());
+        }
+        self.types.insert(name, entry);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&TypeEntry> {
+        self.types.get(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+
+    fn direct_subtypes<'s>(&'s self, name: &str) -> impl Iterator<Item = &'s str> {
+        self.subtypes.get(name).into_iter().flatten().map(String::as_str)
+    }
+
+    /// Returns the classes that directly or indirectly extend `name`, sorted by name.
+    pub fn subclasses(&self, name: &str) -> Vec<&str> {
+        let mut found = BTreeSet::new();
+        let mut pending = vec![name];
+        while let Some(current) = pending.pop() {
+            for subtype in self.direct_subtypes(current) {
+                let extends = self.types[subtype].super_class.as_deref() == Some(current);
+                if extends && found.insert(subtype) {
+                    pending.push(subtype);
+                }
+            }
+        }
+        found.into_iter().collect()
+    }
+
+    /// Returns the non-interface classes that implement `name`, either directly, through a
+    /// subinterface or through a superclass, sorted by name.
+    pub fn implementors(&self, name: &str) -> Vec<&str> {
+        let mut visited = BTreeSet::new();
+        let mut pending = vec![name];
+        while let Some(current) = pending.pop() {
+            for subtype in self.direct_subtypes(current) {
+                if visited.insert(subtype) {
+                    pending.push(subtype);
+                }
+            }
+        }
+        visited
+            .into_iter()
+            .filter(|subtype| !self.types[*subtype].is_interface())
+            .collect()
+    }
+
+    /// Writes the index in a compact binary format readable by [`TypeHierarchyIndex::read`].
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(INDEX_MAGIC)?;
+        writer.write_all(&INDEX_VERSION.to_be_bytes())?;
+        writer.write_all(&(self.types.len() as u32).to_be_bytes())?;
+
+        // Sorted so that equal indexes produce identical files.
+        let mut names: Vec<&String> = self.types.keys().collect();
+        names.sort();
+        for name in names {
+            let entry = &self.types[name];
+            write_str(&mut writer, name)?;
+            writer.write_all(&entry.access_flags.to_be_bytes())?;
+            write_str(&mut writer, entry.super_class.as_deref().unwrap_or(""))?;
+            writer.write_all(&(entry.interfaces.len() as u16).to_be_bytes())?;
+            for interface in &entry.interfaces {
+                write_str(&mut writer, interface)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads an index written by [`TypeHierarchyIndex::write`].
+    pub fn read(mut reader: impl Read) -> io::Result<Self> {
+        if &read_array::<4>(&mut reader)? != INDEX_MAGIC {
+            return Err(invalid("not a type hierarchy index"));
+        }
+        if u16::from_be_bytes(read_array(&mut reader)?) != INDEX_VERSION {
+            return Err(invalid("unsupported type hierarchy index version"));
+        }
+
+        let mut index = Self::new();
+        let count = u32::from_be_bytes(read_array(&mut reader)?);
+        for _ in 0..count {
+            let name = read_str(&mut reader)?;
+            let access_flags = u16::from_be_bytes(read_array(&mut reader)?);
+            let super_class = Some(read_str(&mut reader)?).filter(|name| !name.is_empty());
+            let interface_count = u16::from_be_bytes(read_array(&mut reader)?);
+            let interfaces = (0..interface_count)
+                .map(|_| read_str(&mut reader))
+                .collect::<io::Result<_>>()?;
+
+            index.insert(
+                name,
+                TypeEntry {
+                    access_flags,
+                    super_class,
+                    interfaces,
+                },
+            );
+        }
+        Ok(index)
+    }
+}
+
+/// A field or method in a [`ClassSummary`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemberSummary {
+    pub access_flags: u16,
+    pub name: String,
+    pub descriptor: String,
+}
+
+/// What a [`ClassIndex`] records about one class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassSummary {
+    pub name: String,
+    pub minor_version: u16,
+    pub major_version: u16,
+    pub access_flags: u16,
+    /// `None` for `java/lang/Object` and module descriptors.
+    pub super_class: Option<String>,
+    pub interfaces: Vec<String>,
+    pub fields: Vec<MemberSummary>,
+    pub methods: Vec<MemberSummary>,
+    /// Classes named by the constant pool and by descriptors, excluding the class itself,
+    /// sorted by name.
+    pub referenced_classes: Vec<String>,
+}
+
+impl ClassSummary {
+    /// Summarizes a class. Returns `None` if this_class cannot be resolved.
+    pub fn new(class_file: &JavaClassFile) -> Option<Self> {
+        let constant_pool = &class_file.constant_pool;
+        let name = get_class_name(constant_pool, class_file.this_class)?;
+        let members = |members: &[(u16, usize, usize)]| -> Vec<MemberSummary> {
+            members
+                .iter()
+                .map(|&(access_flags, name_index, descriptor_index)| MemberSummary {
+                    access_flags,
+                    name: get_utf8(constant_pool, name_index).unwrap_or_default().to_string(),
+                    descriptor: get_utf8(constant_pool, descriptor_index).unwrap_or_default().to_string(),
+                })
+                .collect()
+        };
+        let fields: Vec<_> = class_file.fields.iter().map(|field| (field.access_flags, field.name_index, field.descriptor_index)).collect();
+        let methods: Vec<_> = class_file.methods.iter().map(|method| (method.access_flags, method.name_index, method.descriptor_index)).collect();
+
+        let mut referenced = BTreeSet::new();
+        for constant in constant_pool {
+            match constant {
+                ConstantPoolInfo::Class(info) => {
+                    let class_name = get_utf8(constant_pool, info.name_index).unwrap_or_default();
+                    if class_name.starts_with('[') {
+                        referenced.extend(descriptor_classes(class_name));
+                    } else if !class_name.is_empty() {
+                        referenced.insert(class_name);
+                    }
+                }
+                ConstantPoolInfo::NameAndType(info) => {
+                    referenced.extend(descriptor_classes(get_utf8(constant_pool, info.descriptor_index).unwrap_or_default()));
+                }
+                ConstantPoolInfo::MethodType(info) => {
+                    referenced.extend(descriptor_classes(get_utf8(constant_pool, info.descriptor_index).unwrap_or_default()));
+                }
+                _ => {}
+            }
+        }
+        for &(_, _, descriptor_index) in fields.iter().chain(&methods) {
+            referenced.extend(descriptor_classes(get_utf8(constant_pool, descriptor_index).unwrap_or_default()));
+        }
+        referenced.remove(name);
+
+        Some(Self {
+            name: name.to_string(),
+            minor_version: class_file.minor_version,
+            major_version: class_file.major_version,
+            access_flags: class_file.access_flags,
+            super_class: get_class_name(constant_pool, class_file.super_class).map(str::to_string),
+            interfaces: class_file
+                .interfaces
+                .iter()
+                .filter_map(|&index| get_class_name(constant_pool, index))
+                .map(str::to_string)
+                .collect(),
+            fields: members(&fields),
+            methods: members(&methods),
+            referenced_classes: referenced.into_iter().map(str::to_string).collect(),
+        })
+    }
+}
+
+/// Returns the class names of the object types in a descriptor.
+fn descriptor_classes(descriptor: &str) -> Vec<&str> {
+    let mut classes = Vec::new();
+    let mut rest = descriptor;
+    while let Some(start) = rest.find('L') {
+        let Some(end) = rest[start..].find(';') else {
+            break;
+        };
+        classes.push(&rest[start + 1..start + end]);
+        rest = &rest[start + end + 1..];
+    }
+    classes
+}
+
+/// Identifies the content of a source file, to tell whether its classes must be indexed again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceStamp {
+    pub size: u64,
+    /// Modification time in nanoseconds since the Unix epoch.
+    pub modified: u64,
+    /// FNV-1a hash of the content.
+    pub hash: u64,
+}
+
+/// The classes of one jar or class file in a [`ClassIndex`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedSource {
+    pub stamp: SourceStamp,
+    pub classes: Vec<ClassSummary>,
+}
+
+/// Summaries of the classes of a classpath, persisted with [`ClassIndex::write`] so that
+/// later runs only parse the jars and class files which changed.
+///
+/// A source whose size and modification time are unchanged is assumed unchanged; otherwise
+/// its content hash decides. Sources are keyed by path.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClassIndex {
+    sources: BTreeMap<String, IndexedSource>,
+    /// Path of the source of each class. The first source by path wins for duplicate names.
+    class_sources: HashMap<String, String>,
+}
+
+impl ClassIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the summary of a class by its internal name.
+    pub fn get(&self, name: &str) -> Option<&ClassSummary> {
+        let source = &self.sources[self.class_sources.get(name)?];
+        source.classes.iter().find(|class| class.name == name)
+    }
+
+    /// Iterates over the classes of all sources, ordered by source path.
+    pub fn classes(&self) -> impl Iterator<Item = &ClassSummary> {
+        self.sources.values().flat_map(|source| &source.classes)
+    }
+
+    pub fn sources(&self) -> impl Iterator<Item = (&str, &IndexedSource)> {
+        self.sources.iter().map(|(path, source)| (path.as_str(), source))
+    }
+
+    pub fn len(&self) -> usize {
+        self.class_sources.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.class_sources.is_empty()
+    }
+
+    /// Replaces the classes of the source at `path`.
+    pub fn insert(&mut self, path: String, source: IndexedSource) {
+        for class in &source.classes {
+            match self.class_sources.get_mut(&class.name) {
+                Some(existing) if *existing <= path => {}
+                Some(existing) => *existing = path.clone(),
+                None => {
+                    self.class_sources.insert(class.name.clone(), path.clone());
+                }
+            }
+        }
+        if self.sources.insert(path, source).is_some() {
+            // Names only the old classes had still point at this source.
+            self.rebuild_class_sources();
+        }
+    }
+
+    /// Removes the source at `path`, returning its classes.
+    pub fn remove(&mut self, path: &str) -> Option<IndexedSource> {
+        let source = self.sources.remove(path)?;
+        self.rebuild_class_sources();
+        Some(source)
+    }
+
+    /// Removes the sources whose files no longer exist. Returns the number removed.
+    pub fn remove_missing(&mut self) -> usize {
+        let count = self.sources.len();
+        self.sources.retain(|path, _| Path::new(path).exists());
+        self.rebuild_class_sources();
+        count - self.sources.len()
+    }
+
+    fn rebuild_class_sources(&mut self) {
+        self.class_sources.clear();
+        for (path, source) in &self.sources {
+            for class in &source.classes {
+                self.class_sources.entry(class.name.clone()).or_insert_with(|| path.clone());
+            }
+        }
+    }
+
+    /// Indexes a class file unless it is unchanged. Returns `true` if it was parsed.
+    pub fn update_class_file(&mut self, path: impl AsRef<Path>) -> io::Result<bool> {
+        self.update_source(path.as_ref(), |bytes| {
+            let class_file = crate::decode(bytes).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))?;
+            Ok(ClassSummary::new(&class_file).into_iter().collect())
+        })
+    }
+
+    /// Indexes the classes of a jar unless it is unchanged. Returns `true` if it was parsed.
+    #[cfg(feature = "jar")]
+    pub fn update_jar(&mut self, path: impl AsRef<Path>) -> Result<bool, crate::jar::JarError> {
+        use crate::jar::{Jar, JarError};
+
+        self.update_source(path.as_ref(), |bytes| {
+            let jar = Jar::from_bytes(bytes.to_vec())?;
+            let mut classes = Vec::new();
+            for entry in jar.class_entries() {
+                let bytes = jar.read(entry)?;
+                let class_file = crate::decode(&bytes).map_err(|error| JarError::Decode {
+                    name: entry.name.clone(),
+                    error,
+                })?;
+                classes.extend(ClassSummary::new(&class_file));
+            }
+            Ok(classes)
+        })
+    }
+
+    /// Indexes every class file under a directory, recursively. Returns the number of class
+    /// files parsed.
+    pub fn update_directory(&mut self, path: impl AsRef<Path>) -> io::Result<usize> {
+        let mut parsed = 0;
+        let mut pending = vec![path.as_ref().to_path_buf()];
+        while let Some(directory) = pending.pop() {
+            for entry in std::fs::read_dir(directory)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    pending.push(path);
+                } else if path.extension().is_some_and(|extension| extension == "class") && self.update_class_file(&path)? {
+                    parsed += 1;
+                }
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn update_source<E: From<io::Error>>(
+        &mut self,
+        path: &Path,
+        parse: impl FnOnce(&[u8]) -> Result<Vec<ClassSummary>, E>,
+    ) -> Result<bool, E> {
+        let key = path.to_string_lossy().into_owned();
+        let metadata = std::fs::metadata(path)?;
+        let size = metadata.len();
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos() as u64);
+        if let Some(source) = self.sources.get(&key) {
+            if source.stamp.size == size && source.stamp.modified == modified {
+                return Ok(false);
+            }
+        }
+
+        let bytes = std::fs::read(path)?;
+        let stamp = SourceStamp {
+            size,
+            modified,
+            hash: fnv1a(&bytes),
+        };
+        if let Some(source) = self.sources.get_mut(&key) {
+            if source.stamp.hash == stamp.hash && source.stamp.size == stamp.size {
+                source.stamp = stamp;
+                return Ok(false);
+            }
+        }
+
+        let classes = parse(&bytes)?;
+        self.insert(key, IndexedSource { stamp, classes });
+        Ok(true)
+    }
+
+    /// Writes the index in a compact binary format readable by [`ClassIndex::read`].
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        fn write_members(writer: &mut impl Write, members: &[MemberSummary]) -> io::Result<()> {
+            writer.write_all(&(members.len() as u32).to_be_bytes())?;
+            for member in members {
+                writer.write_all(&member.access_flags.to_be_bytes())?;
+                write_str(writer, &member.name)?;
+                write_str(writer, &member.descriptor)?;
+            }
+            Ok(())
+        }
+
+        fn write_strs(writer: &mut impl Write, values: &[String]) -> io::Result<()> {
+            writer.write_all(&(values.len() as u32).to_be_bytes())?;
+            values.iter().try_for_each(|value| write_str(writer, value))
+        }
+
+        writer.write_all(CLASS_INDEX_MAGIC)?;
+        writer.write_all(&CLASS_INDEX_VERSION.to_be_bytes())?;
+        writer.write_all(&(self.sources.len() as u32).to_be_bytes())?;
+        for (path, source) in &self.sources {
+            write_str(&mut writer, path)?;
+            writer.write_all(&source.stamp.size.to_be_bytes())?;
+            writer.write_all(&source.stamp.modified.to_be_bytes())?;
+            writer.write_all(&source.stamp.hash.to_be_bytes())?;
+            writer.write_all(&(source.classes.len() as u32).to_be_bytes())?;
+            for class in &source.classes {
+                write_str(&mut writer, &class.name)?;
+                writer.write_all(&class.minor_version.to_be_bytes())?;
+                writer.write_all(&class.major_version.to_be_bytes())?;
+                writer.write_all(&class.access_flags.to_be_bytes())?;
+                write_str(&mut writer, class.super_class.as_deref().unwrap_or(""))?;
+                write_strs(&mut writer, &class.interfaces)?;
+                write_members(&mut writer, &class.fields)?;
+                write_members(&mut writer, &class.methods)?;
+                write_strs(&mut writer, &class.referenced_classes)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads an index written by [`ClassIndex::write`].
+    pub fn read(mut reader: impl Read) -> io::Result<Self> {
+        fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+            Ok(u32::from_be_bytes(read_array(reader)?))
+        }
+
+        fn read_members(reader: &mut impl Read) -> io::Result<Vec<MemberSummary>> {
+            (0..read_u32(reader)?)
+                .map(|_| {
+                    Ok(MemberSummary {
+                        access_flags: u16::from_be_bytes(read_array(reader)?),
+                        name: read_str(reader)?,
+                        descriptor: read_str(reader)?,
+                    })
+                })
+                .collect()
+        }
+
+        fn read_strs(reader: &mut impl Read) -> io::Result<Vec<String>> {
+            (0..read_u32(reader)?).map(|_| read_str(reader)).collect()
+        }
+
+        if &read_array::<4>(&mut reader)? != CLASS_INDEX_MAGIC {
+            return Err(invalid("not a class index"));
+        }
+        if u16::from_be_bytes(read_array(&mut reader)?) != CLASS_INDEX_VERSION {
+            return Err(invalid("unsupported class index version"));
+        }
+
+        let mut index = Self::new();
+        for _ in 0..read_u32(&mut reader)? {
+            let path = read_str(&mut reader)?;
+            let stamp = SourceStamp {
+                size: u64::from_be_bytes(read_array(&mut reader)?),
+                modified: u64::from_be_bytes(read_array(&mut reader)?),
+                hash: u64::from_be_bytes(read_array(&mut reader)?),
+            };
+            let mut classes = Vec::new();
+            for _ in 0..read_u32(&mut reader)? {
+                classes.push(ClassSummary {
+                    name: read_str(&mut reader)?,
+                    minor_version: u16::from_be_bytes(read_array(&mut reader)?),
+                    major_version: u16::from_be_bytes(read_array(&mut reader)?),
+                    access_flags: u16::from_be_bytes(read_array(&mut reader)?),
+                    super_class: Some(read_str(&mut reader)?).filter(|name| !name.is_empty()),
+                    interfaces: read_strs(&mut reader)?,
+                    fields: read_members(&mut reader)?,
+                    methods: read_members(&mut reader)?,
+                    referenced_classes: read_strs(&mut reader)?,
+                });
+            }
+            index.sources.insert(path, IndexedSource { stamp, classes });
+        }
+        index.rebuild_class_sources();
+        Ok(index)
+    }
+}
@@ -0,0 +1,270 @@
+use std::{fmt, ops::Range};
+
+use crate::{
+    decode_with_options, decode_with_report, error::DecodeError, options::ParseOptions, reader::ClassReader, sections,
+    types::*, utils::fnv1a,
+};
+
+/// Checksum of one top-level section of a class file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionChecksum {
+    /// Name of the section as in [`DecodeReport::sections`](crate::DecodeReport::sections).
+    pub name: &'static str,
+    pub range: Range<usize>,
+    /// 64-bit FNV-1a hash of the bytes of the section.
+    pub checksum: u64,
+}
+
+/// Returns the checksum of each top-level section of a class file, in file order.
+///
+/// Comparing the checksums of a broken copy with those of a good one tells which sections
+/// differ without shipping the whole file.
+pub fn section_checksums(bytes: &[u8]) -> Result<Vec<SectionChecksum>, DecodeError> {
+    let (_, report) = decode_with_report(bytes, &ParseOptions::default())?;
+    Ok(report
+        .sections
+        .into_iter()
+        .map(|(name, range)| SectionChecksum {
+            name,
+            checksum: fnv1a(&bytes[range.clone()]),
+            range,
+        })
+        .collect())
+}
+
+/// The region of a class file found to be corrupted by [`locate_corruption`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Corruption {
+    /// Top-level section containing the region, e.g. `constant_pool` or `methods`.
+    pub section: &'static str,
+    /// The innermost structure which fails to decode, e.g. `methods[3].attributes[1]`.
+    pub location: String,
+    /// Absolute byte range of that structure. It extends to the end of the file when the
+    /// corruption hides where the structure ends.
+    pub range: Range<usize>,
+    /// The error decoding the structure on its own.
+    pub error: DecodeError,
+}
+
+impl fmt::Display for Corruption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (bytes {}..{}): {}", self.location, self.range.start, self.range.end, self.error)
+    }
+}
+
+/// Locates the most likely corrupted region of a class file which fails to decode, or
+/// returns `None` if it decodes.
+///
+/// Each section is decoded on its own, in file order, until one fails. Within the failing
+/// section, the entry count is bisected to find the first entry which does not decode, and
+/// the attributes of a failing field or method are then decoded one by one.
+pub fn locate_corruption(bytes: &[u8]) -> Option<Corruption> {
+    let options = ParseOptions::default();
+    let error = decode_with_options(bytes, &options).err()?;
+    let whole_file = |section: &'static str, error: DecodeError| Corruption {
+        section,
+        location: section.to_string(),
+        range: 0..bytes.len(),
+        error,
+    };
+
+    let mut reader = ClassReader::new(bytes);
+    if let Err(error) = reader.read_bytes(8, "magic") {
+        return Some(whole_file("magic", error));
+    }
+
+    let (constant_pool, length) = match sections::decode_constant_pool(&bytes[8..], &options) {
+        Ok(constant_pool) => constant_pool,
+        Err(_) => return Some(locate_constant(bytes, 8, &options)),
+    };
+    let mut offset = 8 + length;
+
+    // access_flags, this_class, super_class and interfaces.
+    let mut reader = ClassReader::new(&bytes[offset..]);
+    let header = reader.read_bytes(6, "this_class").and_then(|_| {
+        let count = reader.read_u16("interfaces_count")? as usize;
+        reader.read_bytes(count * 2, "interfaces")
+    });
+    if let Err(error) = header {
+        return Some(Corruption {
+            section: "interfaces",
+            location: "interfaces".to_string(),
+            range: offset..bytes.len(),
+            error,
+        });
+    }
+    offset += reader.offset();
+
+    for (section, decode) in [("fields", decode_fields as DecodeMembers), ("methods", decode_methods)] {
+        match decode(&bytes[offset..], &constant_pool, &options) {
+            Ok(length) => offset += length,
+            Err(_) => return Some(locate_member(bytes, offset, section, decode, &constant_pool, &options)),
+        }
+    }
+
+    if let Err(error) = sections::decode_attributes(&bytes[offset..], &constant_pool, &options) {
+        if bytes.len() < offset + 2 {
+            return Some(Corruption {
+                section: "attributes",
+                location: "attributes".to_string(),
+                range: offset..bytes.len(),
+                error,
+            });
+        }
+        let (index, start, error) = bisect(bytes, offset, |buffer| {
+            sections::decode_attributes(buffer, &constant_pool, &options).map(|(_, length)| length)
+        });
+        let end = attribute_end(bytes, start);
+        return Some(Corruption {
+            section: "attributes",
+            location: format!("attributes[{}]", index),
+            range: start..end.unwrap_or(bytes.len()),
+            error,
+        });
+    }
+
+    match error {
+        DecodeError::TrailingBytes { count } => Some(Corruption {
+            section: "trailing bytes",
+            location: "trailing bytes".to_string(),
+            range: bytes.len() - count..bytes.len(),
+            error,
+        }),
+        error => Some(whole_file("class file", error)),
+    }
+}
+
+type DecodeMembers = fn(&[u8], &[ConstantPoolInfo], &ParseOptions) -> Result<usize, DecodeError>;
+
+fn decode_fields(bytes: &[u8], constant_pool: &[ConstantPoolInfo], options: &ParseOptions) -> Result<usize, DecodeError> {
+    sections::decode_fields(bytes, constant_pool, options).map(|(_, length)| length)
+}
+
+fn decode_methods(bytes: &[u8], constant_pool: &[ConstantPoolInfo], options: &ParseOptions) -> Result<usize, DecodeError> {
+    sections::decode_methods(bytes, constant_pool, options).map(|(_, length)| length)
+}
+
+/// Finds the first entry of a table starting with a u16 count at `start` which does not
+/// decode, by decoding the table with smaller counts.
+///
+/// `decode` returns the number of bytes read. Returns the number of entries which decode,
+/// i.e. the index of the failing one, its offset and the error decoding it.
+fn bisect(bytes: &[u8], start: usize, decode: impl Fn(&[u8]) -> Result<usize, DecodeError>) -> (usize, usize, DecodeError) {
+    let count = u16::from_be_bytes([bytes[start], bytes[start + 1]]) as usize;
+    let mut buffer = bytes[start..].to_vec();
+    let mut decode_count = |count: usize| {
+        buffer[..2].copy_from_slice(&(count as u16).to_be_bytes());
+        decode(&buffer)
+    };
+
+    // Invariant: the first `low` entries decode and the first `high` do not.
+    let (mut low, mut high) = (0, count);
+    while high - low > 1 {
+        let middle = low + (high - low) / 2;
+        match decode_count(middle) {
+            Ok(_) => low = middle,
+            Err(_) => high = middle,
+        }
+    }
+    let length = decode_count(low).unwrap_or(2);
+    let error = decode_count(high).expect_err("the whole table fails to decode");
+    (low, start + length, error)
+}
+
+/// Locates the failing entry of the constant pool at `start`.
+fn locate_constant(bytes: &[u8], start: usize, options: &ParseOptions) -> Corruption {
+    let section = "constant_pool";
+    if bytes.len() < start + 2 {
+        let error = sections::decode_constant_pool(&bytes[start..], options).expect_err("the count is missing");
+        return Corruption { section, location: section.to_string(), range: start..bytes.len(), error };
+    }
+
+    let (count, entry_start, error) =
+        bisect(bytes, start, |buffer| sections::decode_constant_pool(buffer, options).map(|(_, length)| length));
+
+    // Long and Double entries take two indices, so the index is read off the decoded entries.
+    let mut buffer = bytes[start..entry_start].to_vec();
+    buffer[..2].copy_from_slice(&(count as u16).to_be_bytes());
+    let index = sections::decode_constant_pool(&buffer, options).map_or(1, |(constant_pool, _)| constant_pool.len());
+    Corruption {
+        section,
+        location: format!("constant_pool[{}]", index),
+        range: entry_start..constant_end(bytes, entry_start).unwrap_or(bytes.len()),
+        error,
+    }
+}
+
+/// Locates the failing field or method, and the failing attribute within it if it can be
+/// delimited.
+fn locate_member(
+    bytes: &[u8],
+    start: usize,
+    section: &'static str,
+    decode: DecodeMembers,
+    constant_pool: &[ConstantPoolInfo],
+    options: &ParseOptions,
+) -> Corruption {
+    if bytes.len() < start + 2 {
+        let error = decode(&bytes[start..], constant_pool, options).expect_err("the count is missing");
+        return Corruption { section, location: section.to_string(), range: start..bytes.len(), error };
+    }
+
+    let (index, member_start, error) = bisect(bytes, start, |buffer| decode(buffer, constant_pool, options));
+    let location = format!("{}[{}]", section, index);
+    let Some(attributes) = member_attributes(bytes, member_start) else {
+        return Corruption { section, location, range: member_start..bytes.len(), error };
+    };
+
+    for (i, range) in attributes.iter().enumerate() {
+        let mut buffer = vec![0, 1];
+        buffer.extend_from_slice(&bytes[range.clone()]);
+        if let Err(error) = sections::decode_attributes(&buffer, constant_pool, options) {
+            return Corruption {
+                section,
+                location: format!("{}.attributes[{}]", location, i),
+                range: range.clone(),
+                error,
+            };
+        }
+    }
+
+    // Every attribute decodes alone, e.g. when one is duplicated.
+    let end = attributes.last().map_or(member_start + 8, |range| range.end);
+    Corruption { section, location, range: member_start..end, error }
+}
+
+/// Returns the ranges of the attributes of the field_info or method_info at `start`, or
+/// `None` if they run past the end of the file.
+fn member_attributes(bytes: &[u8], start: usize) -> Option<Vec<Range<usize>>> {
+    let count = u16::from_be_bytes(bytes.get(start + 6..start + 8)?.try_into().ok()?);
+    let mut ranges = Vec::new();
+    let mut offset = start + 8;
+    for _ in 0..count {
+        let end = attribute_end(bytes, offset)?;
+        ranges.push(offset..end);
+        offset = end;
+    }
+    Some(ranges)
+}
+
+/// Returns the end of the attribute at `start` from its attribute_length, or `None` if it
+/// runs past the end of the file.
+fn attribute_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let length = u32::from_be_bytes(bytes.get(start + 2..start + 6)?.try_into().ok()?) as usize;
+    let end = start.checked_add(6 + length)?;
+    (end <= bytes.len()).then_some(end)
+}
+
+/// Returns the end of the constant pool entry at `start` from its tag, or `None` if the tag
+/// is unknown or the entry runs past the end of the file.
+fn constant_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let size = match bytes.get(start)? {
+        1 => 3 + u16::from_be_bytes(bytes.get(start + 1..start + 3)?.try_into().ok()?) as usize,
+        7 | 8 | 16 | 19 | 20 => 3,
+        15 => 4,
+        3 | 4 | 9 | 10 | 11 | 12 | 17 | 18 => 5,
+        5 | 6 => 9,
+        _ => return None,
+    };
+    (start + size <= bytes.len()).then_some(start + size)
+}
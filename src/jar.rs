@@ -0,0 +1,286 @@
+use std::{fmt, io, path::Path};
+
+use crate::{error::{DecodeError, EncodeError}, inflate::{crc32, inflate}, options::ParseOptions, types::JavaClassFile};
+
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4B50;
+const CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0201_4B50;
+const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x0403_4B50;
+const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x0807_4B50;
+
+const END_OF_CENTRAL_DIRECTORY_SIZE: usize = 22;
+const CENTRAL_DIRECTORY_HEADER_SIZE: usize = 46;
+const LOCAL_FILE_HEADER_SIZE: usize = 30;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+
+/// General purpose flag telling that the CRC-32 and sizes follow the data in a data descriptor.
+const FLAG_DATA_DESCRIPTOR: u16 = 1 << 3;
+
+/// Errors returned when reading a jar.
+#[derive(Debug)]
+pub enum JarError {
+    Io(io::Error),
+    /// The zip structure itself is broken.
+    InvalidArchive(&'static str),
+    UnsupportedCompression { name: String, method: u16 },
+    Inflate { name: String, message: &'static str },
+    ChecksumMismatch { name: String },
+    /// A class entry could not be decoded.
+    Decode { name: String, error: DecodeError },
+    /// A class entry could not be encoded again, e.g. after renaming.
+    Encode { name: String, error: EncodeError },
+}
+
+impl fmt::Display for JarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JarError::Io(error) => write!(f, "{}", error),
+            JarError::InvalidArchive(message) => write!(f, "invalid archive: {}", message),
+            JarError::UnsupportedCompression { name, method } => {
+                write!(f, "{}: unsupported compression method {}", name, method)
+            }
+            JarError::Inflate { name, message } => write!(f, "{}: {}", name, message),
+            JarError::ChecksumMismatch { name } => write!(f, "{}: CRC-32 mismatch", name),
+            JarError::Decode { name, error } => write!(f, "{}: {}", name, error),
+            JarError::Encode { name, error } => write!(f, "{}: {}", name, error),
+        }
+    }
+}
+
+impl std::error::Error for JarError {}
+
+impl From<io::Error> for JarError {
+    fn from(error: io::Error) -> Self {
+        JarError::Io(error)
+    }
+}
+
+/// An entry of a jar's central directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JarEntry {
+    pub name: String,
+    pub compression_method: u16,
+    pub crc32: u32,
+    pub compressed_size: usize,
+    pub uncompressed_size: usize,
+    pub local_header_offset: usize,
+}
+
+impl JarEntry {
+    pub fn is_class(&self) -> bool {
+        self.name.ends_with(".class")
+    }
+}
+
+/// A jar (zip) archive held in memory.
+///
+/// ref. https://pkware.cachefly.net/webdocs/casestudies/APPNOTE.TXT
+#[derive(Debug, Clone)]
+pub struct Jar {
+    bytes: Vec<u8>,
+    entries: Vec<JarEntry>,
+}
+
+fn read_u16_le(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read_u32_le(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+impl Jar {
+    /// Reads a jar from a file.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, JarError> {
+        Self::from_bytes(std::fs::read(path)?)
+    }
+
+    /// Reads a jar from its bytes, parsing the central directory.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, JarError> {
+        if bytes.len() < END_OF_CENTRAL_DIRECTORY_SIZE {
+            return Err(JarError::InvalidArchive("too short for a zip archive"));
+        }
+
+        // The end of central directory record is followed by a comment of at most 65535 bytes.
+        let search_start = bytes.len().saturating_sub(END_OF_CENTRAL_DIRECTORY_SIZE + u16::MAX as usize);
+        let end = (search_start..=bytes.len() - END_OF_CENTRAL_DIRECTORY_SIZE)
+            .rev()
+            .find(|&offset| read_u32_le(&bytes, offset) == END_OF_CENTRAL_DIRECTORY_SIGNATURE)
+            .ok_or(JarError::InvalidArchive("end of central directory not found"))?;
+
+        let entry_count = read_u16_le(&bytes, end + 10) as usize;
+        let mut offset = read_u32_le(&bytes, end + 16) as usize;
+
+        let mut entries = Vec::with_capacity(entry_count);
+        for _ in 0..entry_count {
+            if offset + CENTRAL_DIRECTORY_HEADER_SIZE > bytes.len()
+                || read_u32_le(&bytes, offset) != CENTRAL_DIRECTORY_SIGNATURE
+            {
+                return Err(JarError::InvalidArchive("broken central directory"));
+            }
+
+            let name_length = read_u16_le(&bytes, offset + 28) as usize;
+            let extra_length = read_u16_le(&bytes, offset + 30) as usize;
+            let comment_length = read_u16_le(&bytes, offset + 32) as usize;
+            let name_start = offset + CENTRAL_DIRECTORY_HEADER_SIZE;
+            let name = bytes
+                .get(name_start..name_start + name_length)
+                .ok_or(JarError::InvalidArchive("broken central directory"))?;
+
+            entries.push(JarEntry {
+                name: String::from_utf8_lossy(name).into_owned(),
+                compression_method: read_u16_le(&bytes, offset + 10),
+                crc32: read_u32_le(&bytes, offset + 16),
+                compressed_size: read_u32_le(&bytes, offset + 20) as usize,
+                uncompressed_size: read_u32_le(&bytes, offset + 24) as usize,
+                local_header_offset: read_u32_le(&bytes, offset + 42) as usize,
+            });
+
+            offset = name_start + name_length + extra_length + comment_length;
+        }
+
+        Ok(Self { bytes, entries })
+    }
+
+    /// Reads a damaged jar by scanning for local file headers instead of trusting the
+    /// central directory, e.g. when the archive is truncated.
+    ///
+    /// Every header found becomes an entry, in archive order; entries whose data was cut off
+    /// fail to [`read`](Self::read). Entries written with a data descriptor are kept only if
+    /// the descriptor is found.
+    pub fn recover(bytes: Vec<u8>) -> Self {
+        let mut entries = Vec::new();
+        let mut offset = 0;
+        while offset + LOCAL_FILE_HEADER_SIZE <= bytes.len() {
+            if read_u32_le(&bytes, offset) != LOCAL_FILE_HEADER_SIGNATURE {
+                offset += 1;
+                continue;
+            }
+            match recover_entry(&bytes, offset) {
+                Some((entry, end)) if end <= bytes.len() => {
+                    entries.push(entry);
+                    offset = end;
+                }
+                Some((entry, _)) => {
+                    entries.push(entry);
+                    offset += LOCAL_FILE_HEADER_SIZE;
+                }
+                None => offset += LOCAL_FILE_HEADER_SIZE,
+            }
+        }
+        Self { bytes, entries }
+    }
+
+    /// Reads and decodes every class entry, calling `visit` with the name and class of each
+    /// that survives, and returns the errors of the others.
+    ///
+    /// Meant for jars read by [`recover`](Self::recover), along with lenient options.
+    pub fn recover_classes(
+        &self,
+        options: &ParseOptions,
+        mut visit: impl FnMut(&str, &JavaClassFile),
+    ) -> Vec<JarError> {
+        let mut errors = Vec::new();
+        for entry in self.class_entries() {
+            let bytes = match self.read(entry) {
+                Ok(bytes) => bytes,
+                Err(error) => {
+                    errors.push(error);
+                    continue;
+                }
+            };
+            match crate::decode_with_options(&bytes, options) {
+                Ok(class_file) => visit(&entry.name, &class_file),
+                Err(error) => errors.push(JarError::Decode { name: entry.name.clone(), error }),
+            }
+        }
+        errors
+    }
+
+    pub fn entries(&self) -> &[JarEntry] {
+        &self.entries
+    }
+
+    pub fn entry(&self, name: &str) -> Option<&JarEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    /// Iterates over the entries ending with `.class`.
+    pub fn class_entries(&self) -> impl Iterator<Item = &JarEntry> {
+        self.entries.iter().filter(|entry| entry.is_class())
+    }
+
+    /// Reads and decompresses an entry, verifying its CRC-32.
+    pub fn read(&self, entry: &JarEntry) -> Result<Vec<u8>, JarError> {
+        let header = entry.local_header_offset;
+        if header + LOCAL_FILE_HEADER_SIZE > self.bytes.len()
+            || read_u32_le(&self.bytes, header) != LOCAL_FILE_HEADER_SIGNATURE
+        {
+            return Err(JarError::InvalidArchive("broken local file header"));
+        }
+
+        let name_length = read_u16_le(&self.bytes, header + 26) as usize;
+        let extra_length = read_u16_le(&self.bytes, header + 28) as usize;
+        let start = header + LOCAL_FILE_HEADER_SIZE + name_length + extra_length;
+        let data = self
+            .bytes
+            .get(start..start + entry.compressed_size)
+            .ok_or(JarError::InvalidArchive("entry data out of bounds"))?;
+
+        let content = match entry.compression_method {
+            METHOD_STORED => data.to_vec(),
+            METHOD_DEFLATED => inflate(data, entry.uncompressed_size).map_err(|message| JarError::Inflate {
+                name: entry.name.clone(),
+                message,
+            })?,
+            method => {
+                return Err(JarError::UnsupportedCompression {
+                    name: entry.name.clone(),
+                    method,
+                })
+            }
+        };
+
+        if crc32(&content) != entry.crc32 {
+            return Err(JarError::ChecksumMismatch { name: entry.name.clone() });
+        }
+
+        Ok(content)
+    }
+}
+
+/// Reads the local file header at `offset`, returning the entry and the offset following its
+/// data, which may lie beyond the end of a truncated archive. Returns `None` if the header
+/// is cut off or the data descriptor of the entry cannot be found.
+fn recover_entry(bytes: &[u8], offset: usize) -> Option<(JarEntry, usize)> {
+    let name_length = read_u16_le(bytes, offset + 26) as usize;
+    let extra_length = read_u16_le(bytes, offset + 28) as usize;
+    let name_start = offset + LOCAL_FILE_HEADER_SIZE;
+    let name = bytes.get(name_start..name_start + name_length)?;
+    let start = name_start + name_length + extra_length;
+
+    let mut entry = JarEntry {
+        name: String::from_utf8_lossy(name).into_owned(),
+        compression_method: read_u16_le(bytes, offset + 8),
+        crc32: read_u32_le(bytes, offset + 14),
+        compressed_size: read_u32_le(bytes, offset + 18) as usize,
+        uncompressed_size: read_u32_le(bytes, offset + 22) as usize,
+        local_header_offset: offset,
+    };
+    if read_u16_le(bytes, offset + 6) & FLAG_DATA_DESCRIPTOR == 0 {
+        let end = start + entry.compressed_size;
+        return Some((entry, end));
+    }
+
+    // The descriptor is the first signed one whose compressed size matches the distance
+    // from the start of the data. jar tools always write the optional signature.
+    let descriptor = (start..bytes.len().saturating_sub(15)).find(|&position| {
+        read_u32_le(bytes, position) == DATA_DESCRIPTOR_SIGNATURE
+            && read_u32_le(bytes, position + 8) as usize == position - start
+    })?;
+    entry.crc32 = read_u32_le(bytes, descriptor + 4);
+    entry.compressed_size = read_u32_le(bytes, descriptor + 8) as usize;
+    entry.uncompressed_size = read_u32_le(bytes, descriptor + 12) as usize;
+    Some((entry, descriptor + 16))
+}
@@ -0,0 +1,128 @@
+//! The `kotlin.Metadata` annotation kotlinc puts on every class it compiles.
+//!
+//! ref. https://kotlinlang.org/api/core/kotlin-stdlib/kotlin/-metadata/
+
+use crate::types::*;
+
+/// Field descriptor of the `kotlin.Metadata` annotation interface.
+pub const METADATA_DESCRIPTOR: &str = "Lkotlin/Metadata;";
+
+/// What a class compiled by kotlinc holds, from the `k` element of its metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KotlinClassKind {
+    /// A class, interface or object.
+    Class,
+    /// The facade of top-level declarations of a file, e.g. `FooKt`.
+    File,
+    /// A class of no Kotlin declaration, e.g. for a lambda or `when` mappings.
+    SyntheticClass,
+    /// The facade of a `@JvmMultifileClass` file group.
+    MultiFileClassFacade,
+    /// A part of a `@JvmMultifileClass` file group.
+    MultiFileClassPart,
+}
+
+impl KotlinClassKind {
+    pub fn from_value(value: i32) -> Option<Self> {
+        match value {
+            1 => Some(KotlinClassKind::Class),
+            2 => Some(KotlinClassKind::File),
+            3 => Some(KotlinClassKind::SyntheticClass),
+            4 => Some(KotlinClassKind::MultiFileClassFacade),
+            5 => Some(KotlinClassKind::MultiFileClassPart),
+            _ => None,
+        }
+    }
+}
+
+/// The elements of a `kotlin.Metadata` annotation, with the defaults of the annotation
+/// interface for those left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KotlinMetadata<'c> {
+    /// `k`, the kind of the class; see [`KotlinMetadata::class_kind`].
+    pub kind: i32,
+    /// `mv`, the version of the metadata format, e.g. `[1, 9, 0]`.
+    pub metadata_version: Vec<i32>,
+    /// `bv`, the bytecode interface version, deprecated and no longer written.
+    pub bytecode_version: Vec<i32>,
+    /// `d1`, the protobuf-encoded declarations, split into strings.
+    pub data1: Vec<&'c str>,
+    /// `d2`, the strings `d1` refers to.
+    pub data2: Vec<&'c str>,
+    /// `xs`, e.g. the facade class name of a multi-file class part.
+    pub extra_string: &'c str,
+    /// `pn`, the fully qualified name of the Kotlin package when it differs from the JVM
+    /// package.
+    pub package_name: &'c str,
+    /// `xi`, a set of flags.
+    pub extra_int: i32,
+}
+
+impl KotlinMetadata<'_> {
+    /// Returns the kind of the class, or `None` if `k` has a value kotlinc does not write.
+    pub fn class_kind(&self) -> Option<KotlinClassKind> {
+        KotlinClassKind::from_value(self.kind)
+    }
+}
+
+/// Returns the `kotlin.Metadata` annotation of a class, or `None` if it was not compiled
+/// by kotlinc.
+///
+/// Elements of an unexpected type are left at their defaults.
+pub fn kotlin_metadata<'c>(class_file: &'c JavaClassFile) -> Option<KotlinMetadata<'c>> {
+    let constant_pool = &class_file.constant_pool;
+    let annotation = class_file.attributes.values().find_map(|attribute| match attribute {
+        AttributeInfo::RuntimeVisibleAnnotations(attribute) => attribute
+            .annotations
+            .iter()
+            .find(|annotation| get_utf8(constant_pool, annotation.type_index as usize) == Some(METADATA_DESCRIPTOR)),
+        _ => None,
+    })?;
+
+    let mut metadata = KotlinMetadata { kind: 1, ..KotlinMetadata::default() };
+    for pair in &annotation.element_value_pairs {
+        let value = &pair.value;
+        match get_utf8(constant_pool, pair.element_name_index as usize) {
+            Some("k") => metadata.kind = int(constant_pool, value).unwrap_or(metadata.kind),
+            Some("mv") => metadata.metadata_version = array(value, |value| int(constant_pool, value)),
+            Some("bv") => metadata.bytecode_version = array(value, |value| int(constant_pool, value)),
+            Some("d1") => metadata.data1 = array(value, |value| string(constant_pool, value)),
+            Some("d2") => metadata.data2 = array(value, |value| string(constant_pool, value)),
+            Some("xs") => metadata.extra_string = string(constant_pool, value).unwrap_or_default(),
+            Some("pn") => metadata.package_name = string(constant_pool, value).unwrap_or_default(),
+            Some("xi") => metadata.extra_int = int(constant_pool, value).unwrap_or_default(),
+            _ => {}
+        }
+    }
+    Some(metadata)
+}
+
+/// Returns true if the class was compiled by kotlinc, i.e. has a `kotlin.Metadata` annotation.
+pub fn is_kotlin(class_file: &JavaClassFile) -> bool {
+    kotlin_metadata(class_file).is_some()
+}
+
+fn int(constant_pool: &[ConstantPoolInfo], value: &AnnotationElementValue) -> Option<i32> {
+    match (value.tag, &value.value) {
+        (b'I', AnnotationElementValueEntryValue::ConstValueIndex(index)) => match constant_pool.get(*index as usize) {
+            Some(ConstantPoolInfo::Integer(info)) => Some(info.data),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn string<'c>(constant_pool: &'c [ConstantPoolInfo], value: &AnnotationElementValue) -> Option<&'c str> {
+    match (value.tag, &value.value) {
+        (b's', AnnotationElementValueEntryValue::ConstValueIndex(index)) => get_utf8(constant_pool, *index as usize),
+        _ => None,
+    }
+}
+
+/// Returns the elements of an array value converted by `element`, skipping those it rejects.
+fn array<T>(value: &AnnotationElementValue, element: impl Fn(&AnnotationElementValue) -> Option<T>) -> Vec<T> {
+    match &value.value {
+        AnnotationElementValueEntryValue::ArrayValue { values, .. } => values.iter().filter_map(element).collect(),
+        _ => Vec::new(),
+    }
+}
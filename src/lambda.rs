@@ -0,0 +1,162 @@
+//! Lambda and method reference usage of a class.
+
+use crate::{
+    code::CodeView,
+    opcode::instruction_length,
+    operand::{resolve_operand, ResolvedOperand},
+    types::*,
+};
+
+/// Owner and name of the bootstrap methods javac uses for lambdas and method references.
+const METAFACTORIES: [(&str, &str); 2] = [
+    ("java/lang/invoke/LambdaMetafactory", "metafactory"),
+    ("java/lang/invoke/LambdaMetafactory", "altMetafactory"),
+];
+
+/// Whether a call site creates a lambda or a method reference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LambdaKind {
+    /// The implementation is a synthetic method of the class itself, e.g. `lambda$main$0`.
+    Lambda,
+    /// The implementation is a method written by hand, e.g. `String::length`.
+    MethodReference,
+}
+
+/// An invokedynamic instruction bootstrapped by `LambdaMetafactory`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LambdaSite<'c> {
+    /// Index of the method in the `methods` of the class file.
+    pub method_index: usize,
+    /// Offset of the invokedynamic instruction in the code of the method.
+    pub pc: usize,
+    pub kind: LambdaKind,
+    /// The functional interface implemented, e.g. `java/util/function/Function`.
+    pub interface: &'c str,
+    /// Name of the implemented interface method, e.g. `apply`.
+    pub interface_method: &'c str,
+    /// Erased descriptor of the implemented interface method, e.g.
+    /// `(Ljava/lang/Object;)Ljava/lang/Object;`.
+    pub interface_descriptor: &'c str,
+    /// Field descriptors of the values captured when the call site runs, e.g. the enclosing
+    /// instance and effectively final locals.
+    pub captured: Vec<&'c str>,
+    /// Owner, name and descriptor of the implementation method.
+    pub target: (&'c str, &'c str, &'c str),
+    /// reference_kind of the method handle of the implementation method, e.g. 6 for
+    /// REF_invokeStatic.
+    pub target_kind: u8,
+    /// True if bootstrapped by `altMetafactory`, used for serializable lambdas and lambdas
+    /// with marker interfaces or bridges.
+    pub alternate: bool,
+}
+
+/// The lambdas, method references and anonymous classes of one class.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LambdaReport<'c> {
+    /// Lambda and method reference call sites, in method and then code order.
+    pub sites: Vec<LambdaSite<'c>>,
+    /// Anonymous classes recorded in InnerClasses, e.g. `com/example/Foo$1`, including the
+    /// class itself if it is one.
+    pub anonymous_classes: Vec<&'c str>,
+    /// True if the class is a lambda proxy class spun by `LambdaMetafactory`, as dumped with
+    /// `-Djdk.internal.lambda.dumpProxyClasses`, e.g. `com/example/Foo$$Lambda$14`.
+    pub is_lambda_proxy: bool,
+}
+
+impl LambdaReport<'_> {
+    /// Returns the number of call sites creating lambdas.
+    pub fn lambda_count(&self) -> usize {
+        self.sites.iter().filter(|site| site.kind == LambdaKind::Lambda).count()
+    }
+
+    /// Returns the number of call sites creating method references.
+    pub fn method_reference_count(&self) -> usize {
+        self.sites.iter().filter(|site| site.kind == LambdaKind::MethodReference).count()
+    }
+}
+
+/// Lists the lambda and method reference call sites and the anonymous classes of a class.
+///
+/// Call sites are found by their bootstrap method, so those of compilers other than javac
+/// are found too; methods whose code cannot be decoded are skipped.
+pub fn lambda_report<'c>(class_file: &'c JavaClassFile) -> LambdaReport<'c> {
+    let constant_pool = &class_file.constant_pool;
+    let this_class = get_class_name(constant_pool, class_file.this_class);
+    let mut report = LambdaReport {
+        is_lambda_proxy: class_file.is_synthetic()
+            && this_class.is_some_and(|name| name.contains("$$Lambda")),
+        ..LambdaReport::default()
+    };
+
+    for entry in class_file.inner_classes() {
+        if entry.inner_name_index == 0 {
+            if let Some(name) = get_class_name(constant_pool, entry.inner_class_info_index as usize) {
+                report.anonymous_classes.push(name);
+            }
+        }
+    }
+
+    for (method_index, method) in class_file.methods.iter().enumerate() {
+        let Ok(Some(code)) = CodeView::new(method, constant_pool) else {
+            continue;
+        };
+        let mut pc = 0;
+        while let Some(length) = instruction_length(code.code, pc) {
+            if let Some(ResolvedOperand::InvokeDynamic(call)) = resolve_operand(class_file, code.code, pc) {
+                if let Some(site) = lambda_site(class_file, this_class, call) {
+                    report.sites.push(LambdaSite { method_index, pc, ..site });
+                }
+            }
+            pc += length;
+        }
+    }
+
+    report
+}
+
+/// Resolves the invokedynamic call site `call` if it is bootstrapped by `LambdaMetafactory`,
+/// leaving `method_index` and `pc` zero.
+fn lambda_site<'c>(
+    class_file: &'c JavaClassFile,
+    this_class: Option<&str>,
+    call: BootstrapCall<'c>,
+) -> Option<LambdaSite<'c>> {
+    let constant_pool = &class_file.constant_pool;
+    let (owner, name, _) = call.method;
+    let alternate = METAFACTORIES.iter().position(|metafactory| *metafactory == (owner, name))? == 1;
+
+    // The static arguments start with samMethodType, implMethod and instantiatedMethodType.
+    let ConstantPoolInfo::MethodType(interface_type) = constant_pool.get(*call.arguments.first()?)? else {
+        return None;
+    };
+    let ConstantPoolInfo::MethodHandle(handle) = constant_pool.get(*call.arguments.get(1)?)? else {
+        return None;
+    };
+    let target = get_member_ref(constant_pool, handle.reference_index)?;
+
+    let (captured, interface) = split_method_descriptor(call.descriptor)?;
+    let interface = interface.strip_prefix('L')?.strip_suffix(';')?;
+
+    let is_synthetic_method = |(owner, name, descriptor): (&str, &str, &str)| {
+        Some(owner) == this_class
+            && class_file.methods.iter().any(|method| {
+                method.is_synthetic(constant_pool)
+                    && get_utf8(constant_pool, method.name_index) == Some(name)
+                    && get_utf8(constant_pool, method.descriptor_index) == Some(descriptor)
+            })
+    };
+    let kind = if is_synthetic_method(target) { LambdaKind::Lambda } else { LambdaKind::MethodReference };
+
+    Some(LambdaSite {
+        method_index: 0,
+        pc: 0,
+        kind,
+        interface,
+        interface_method: call.name,
+        interface_descriptor: get_utf8(constant_pool, interface_type.descriptor_index)?,
+        captured,
+        target,
+        target_kind: handle.reference_kind,
+        alternate,
+    })
+}
@@ -1,57 +1,344 @@
-use crate::{types::*, utils::*};
+#![cfg_attr(allocator_api, feature(allocator_api))]
 
+use crate::{reader::ClassReader, types::*, utils::*, validate::ValidationMode};
+
+/// The type of a table of a class file: a `Vec` in the allocator `A` in scope with
+/// `--cfg allocator_api`, and in the global allocator otherwise.
+#[cfg(allocator_api)]
+macro_rules! table {
+    ($element:ty) => {
+        Vec<$element, A>
+    };
+}
+
+#[cfg(not(allocator_api))]
+macro_rules! table {
+    ($element:ty) => {
+        Vec<$element>
+    };
+}
+
+/// The type of a class file whose tables are in the allocator `A` in scope with
+/// `--cfg allocator_api`, and in the global allocator otherwise.
+#[cfg(allocator_api)]
+macro_rules! class_file {
+    ($lifetime:lifetime) => {
+        JavaClassFile<$lifetime, A>
+    };
+}
+
+#[cfg(not(allocator_api))]
+macro_rules! class_file {
+    ($lifetime:lifetime) => {
+        JavaClassFile<$lifetime>
+    };
+}
+
+mod agent;
+pub mod analysis;
 mod attributes;
+pub mod cfg;
+pub mod checkout;
 mod classfile;
+mod code;
+pub mod concat;
 mod constant_pool;
+pub mod copy;
+mod dedup;
+mod descriptor;
+pub mod determinism;
+mod diagnostics;
+pub mod diff;
+pub mod disasm;
+mod encode;
+mod error;
+pub mod eval;
+pub mod features;
+pub mod frames;
+mod format_check;
+mod opcode;
+#[cfg(feature = "jar")]
+mod inflate;
+pub mod index;
+pub mod integrity;
+#[cfg(feature = "jar")]
+pub mod jar;
+pub mod kotlin;
+pub mod lambda;
+pub mod limits;
+pub mod link;
+pub mod markers;
+pub mod metrics;
+pub mod nest;
+pub mod operand;
+mod names;
+mod options;
+pub mod placeholder;
+pub mod policy;
+mod quirks;
+mod reader;
+pub mod redact;
+mod regex;
+pub mod remap;
+mod report;
+pub mod scala;
+pub mod sections;
+pub mod signature;
+pub mod slice;
+pub mod stacktrace;
+pub mod strings;
+mod template;
 
 pub(crate) mod utils;
+pub mod validate;
+pub mod verify;
+
+pub use agent::{generate_agent_class, AgentSpec};
+pub use diagnostics::{Diagnostic, DiagnosticKind, Diagnostics};
+pub use encode::{encode, encode_with_options, EncodeOptions};
+pub use error::{AttributeError, DecodeError, EncodeError};
+pub use options::{DuplicateAttributePolicy, DuplicateConstantPolicy, ParseOptions};
+pub use report::DecodeReport;
+pub use template::{generate_delegate, generate_proxy};
 
 pub mod types {
     pub use crate::attributes::*;
     pub use crate::classfile::*;
+    pub use crate::code::*;
     pub use crate::constant_pool::*;
+    pub use crate::dedup::DuplicateConstant;
+    pub use crate::descriptor::*;
+    pub use crate::format_check::*;
+    pub use crate::names::*;
+    pub use crate::opcode::*;
+    pub use crate::quirks::*;
 }
 
 /// Decode a Java class file from bytes.
-pub fn decode(bytes: &[u8]) -> JavaClassFile {
-    let (head, rest) = bytes.split_at(size_of::<u32>());
-    let magic = read_u32(head);
+pub fn decode(bytes: &[u8]) -> Result<JavaClassFile<'_>, DecodeError> {
+    decode_with_options(bytes, &ParseOptions::default())
+}
+
+/// Decode a Java class file from bytes with options.
+pub fn decode_with_options<'a>(bytes: &'a [u8], options: &ParseOptions) -> Result<JavaClassFile<'a>, DecodeError> {
+    decode_with_diagnostics(bytes, options).map(|(class_file, _)| class_file)
+}
+
+/// Decode a Java class file from bytes with options, also returning the non-fatal
+/// problems found while decoding.
+pub fn decode_with_diagnostics<'a>(
+    bytes: &'a [u8],
+    options: &ParseOptions,
+) -> Result<(JavaClassFile<'a>, Diagnostics), DecodeError> {
+    let mut class_file = unfilled();
+    decode_inner(bytes, options, &mut class_file).map(|(diagnostics, _)| (class_file, diagnostics))
+}
+
+/// Decode a Java class file from bytes with options, also returning where each section
+/// of the class file was found.
+pub fn decode_with_report<'a>(bytes: &'a [u8], options: &ParseOptions) -> Result<(JavaClassFile<'a>, DecodeReport), DecodeError> {
+    let mut class_file = unfilled();
+    decode_inner(bytes, options, &mut class_file).map(|(_, report)| (class_file, report))
+}
+
+/// Decode a Java class file from bytes into `class_file`, reusing the memory of its constant
+/// pool, interfaces, fields and methods, and return the non-fatal problems found.
+///
+/// Meant for memory-constrained targets decoding class after class, e.g. from an image in
+/// flash, where allocating the largest tables once keeps the heap from fragmenting.
+/// [`ParseOptions::max_allocation`] still applies to each decode as a whole. On error
+/// `class_file` is left partially decoded, fit only to be decoded into again.
+///
+/// With `--cfg allocator_api`, the tables reused keep the allocator they are in, see
+/// `decode_in`.
+pub fn decode_into<'a, #[cfg(allocator_api)] A: Allocator>(
+    bytes: &'a [u8],
+    options: &ParseOptions,
+    class_file: &mut class_file!('a),
+) -> Result<Diagnostics, DecodeError> {
+    decode_inner(bytes, options, class_file).map(|(diagnostics, _)| diagnostics)
+}
+
+/// Decode a Java class file from bytes with options, allocating its constant pool, interfaces,
+/// fields and methods in `allocator`, and return the non-fatal problems found.
+///
+/// Meant for embedders routing the allocations of decoding into a dedicated pool, e.g. an arena
+/// per compilation. Only available with `--cfg allocator_api` on a nightly compiler. The
+/// attributes and the tables inside fields and methods are still in the global allocator, and
+/// only the fields of the class file are usable: its methods and the functions of this crate
+/// take class files in the global allocator.
+#[cfg(allocator_api)]
+pub fn decode_in<'a, A: Allocator + Clone>(
+    bytes: &'a [u8],
+    options: &ParseOptions,
+    allocator: A,
+) -> Result<(JavaClassFile<'a, A>, Diagnostics), DecodeError> {
+    let mut class_file = JavaClassFile {
+        magic: 0,
+        minor_version: 0,
+        major_version: 0,
+        constant_pool: Vec::new_in(allocator.clone()),
+        access_flags: 0,
+        this_class: 0,
+        super_class: 0,
+        interfaces: Vec::new_in(allocator.clone()),
+        fields: Vec::new_in(allocator.clone()),
+        methods: Vec::new_in(allocator),
+        attributes: Attributes::new(),
+        trailing_bytes: &[],
+    };
+    decode_inner(bytes, options, &mut class_file).map(|(diagnostics, _)| (class_file, diagnostics))
+}
+
+/// A class file for [`decode_inner`] to fill in.
+fn unfilled<'a>() -> JavaClassFile<'a> {
+    JavaClassFile {
+        magic: 0,
+        minor_version: 0,
+        major_version: 0,
+        constant_pool: Vec::new(),
+        access_flags: 0,
+        this_class: 0,
+        super_class: 0,
+        interfaces: Vec::new(),
+        fields: Vec::new(),
+        methods: Vec::new(),
+        attributes: Attributes::new(),
+        trailing_bytes: &[],
+    }
+}
 
-    let (head, rest) = rest.split_at(size_of::<u16>());
-    let minor_version = read_u16(head);
+fn decode_inner<'a, #[cfg(allocator_api)] A: Allocator>(
+    bytes: &'a [u8],
+    options: &ParseOptions,
+    class_file: &mut class_file!('a),
+) -> Result<(Diagnostics, DecodeReport), DecodeError> {
+    let mut context = DecodeContext::new(options);
+    let mut report = DecodeReport::default();
 
-    let (head, rest) = rest.split_at(size_of::<u16>());
-    let major_version = read_u16(head);
+    let mut reader = ClassReader::new(bytes);
+
+    class_file.magic = report.record("magic", &mut reader, |reader| reader.read_u32("magic"))?;
+    class_file.minor_version = report.record("minor_version", &mut reader, |reader| reader.read_u16("minor_version"))?;
+    class_file.major_version = report.record("major_version", &mut reader, |reader| reader.read_u16("major_version"))?;
     
-    let (constant_pool, rest) = decode_constant_pool(rest);
+    let constant_pool = &mut class_file.constant_pool;
+    report.record("constant_pool", &mut reader, |reader| {
+        decode_constant_pool_into(reader, &mut context, constant_pool)
+    })?;
+    let constant_pool = &class_file.constant_pool;
+    if options.duplicate_constants != DuplicateConstantPolicy::Allow {
+        for duplicate in dedup::find_duplicate_constants(constant_pool) {
+            if options.duplicate_constants == DuplicateConstantPolicy::Error {
+                return Err(DecodeError::DuplicateConstant { index: duplicate.index, original: duplicate.original });
+            }
+            context.diagnostics.push(
+                DiagnosticKind::DuplicateConstant,
+                format!("constant_pool[{}]", duplicate.index),
+                format!("duplicates constant_pool[{}]", duplicate.original),
+            );
+        }
+    }
     
-    let (head, rest) = rest.split_at(size_of::<u16>());
-    let access_flags = read_u16(head);
+    class_file.access_flags = report.record("access_flags", &mut reader, |reader| reader.read_u16("access_flags"))?;
 
-    let (this_class, rest) = decode_this_or_super_class(rest);
-    let (super_class, rest) = decode_this_or_super_class(rest);
+    class_file.this_class = report.record("this_class", &mut reader, decode_this_or_super_class)?;
+    class_file.super_class = report.record("super_class", &mut reader, decode_this_or_super_class)?;
 
-    let (interfaces, rest) = decode_interfaces(rest);
-    let (fields, rest) = decode_fields(rest, &constant_pool);
-    let (methods, rest) = decode_methods(rest, &constant_pool);
-    let (attributes, _) = decode_attributes(rest, &constant_pool);
+    let (interfaces, fields, methods) = (&mut class_file.interfaces, &mut class_file.fields, &mut class_file.methods);
+    report.record("interfaces", &mut reader, |reader| decode_interfaces_into(reader, &mut context, interfaces))?;
+    report.record("fields", &mut reader, |reader| decode_fields_into(reader, constant_pool, &mut context, fields))?;
+    report.record("methods", &mut reader, |reader| decode_methods_into(reader, constant_pool, &mut context, methods))?;
+    context.location = "attributes".to_string();
+    class_file.attributes =
+        report.record("attributes", &mut reader, |reader| decode_attributes(reader, constant_pool, &mut context))?;
 
-    JavaClassFile {
-        magic,
-        minor_version,
-        major_version,
-        constant_pool,
-        access_flags,
-        this_class,
-        super_class,
-        interfaces,
-        fields,
-        methods,
-        attributes,
-    }
-}
-
-fn hoge() {
-    let bytes = [0u8; 4];
-    let java_class_file: JavaClassFile = decode(&bytes);
-}
\ No newline at end of file
+    report.consumed = reader.offset();
+    let trailing_bytes = reader.remaining();
+    report.remaining = trailing_bytes.len();
+    if !trailing_bytes.is_empty() {
+        if options.mode == ValidationMode::Strict {
+            return Err(DecodeError::TrailingBytes { count: trailing_bytes.len() });
+        }
+        context.diagnostics.push(
+            DiagnosticKind::TrailingBytes,
+            "class file",
+            format!("{} trailing bytes after the class file", trailing_bytes.len()),
+        );
+    }
+    class_file.trailing_bytes = trailing_bytes;
+
+    check_versions_and_flags(class_file, &mut context.diagnostics);
+
+    Ok((context.diagnostics, report))
+}
+
+/// Access flag bits defined for classes, fields and methods.
+const DEFINED_CLASS_FLAGS: u16 = 0xF631;
+const DEFINED_FIELD_FLAGS: u16 = 0x50DF;
+const DEFINED_METHOD_FLAGS: u16 = 0x1DFF;
+
+/// Reports unusual version numbers and obsolete or undefined access flags.
+fn check_versions_and_flags<#[cfg(allocator_api)] A: Allocator>(
+    class_file: &class_file!('_),
+    diagnostics: &mut Diagnostics,
+) {
+    let (major_version, minor_version) = (class_file.major_version, class_file.minor_version);
+    if (major_version as f32) < SUPPORTED_MINOR_VERSION || (major_version as f32) > SUPPORTED_MAJOR_VERSION {
+        diagnostics.push(
+            DiagnosticKind::UnusualVersion,
+            "major_version",
+            format!("class file version {}.{} is outside the supported range", major_version, minor_version),
+        );
+    }
+    // Since Java 12 the minor version is either 0 or 0xFFFF (preview features).
+    if major_version >= FIRST_PREVIEW_MAJOR_VERSION && minor_version != 0 && minor_version != PREVIEW_MINOR_VERSION {
+        diagnostics.push(
+            DiagnosticKind::UnusualVersion,
+            "minor_version",
+            format!("minor version {} is neither 0 nor 65535 for major version {}", minor_version, major_version),
+        );
+    }
+    if major_version < FIRST_PREVIEW_MAJOR_VERSION && minor_version == PREVIEW_MINOR_VERSION {
+        diagnostics.push(
+            DiagnosticKind::UnusualVersion,
+            "minor_version",
+            format!("preview minor version used with major version {}", major_version),
+        );
+    }
+
+    if class_file.access_flags & !DEFINED_CLASS_FLAGS != 0 {
+        diagnostics.push(
+            DiagnosticKind::DeprecatedFlag,
+            "access_flags",
+            format!("undefined flag bits 0x{:04X}", class_file.access_flags & !DEFINED_CLASS_FLAGS),
+        );
+    }
+
+    for (i, field) in class_file.fields.iter().enumerate() {
+        if field.access_flags & !DEFINED_FIELD_FLAGS != 0 {
+            diagnostics.push(
+                DiagnosticKind::DeprecatedFlag,
+                format!("fields[{}]", i),
+                format!("undefined flag bits 0x{:04X}", field.access_flags & !DEFINED_FIELD_FLAGS),
+            );
+        }
+    }
+
+    for (i, method) in class_file.methods.iter().enumerate() {
+        if method.access_flags & !DEFINED_METHOD_FLAGS != 0 {
+            diagnostics.push(
+                DiagnosticKind::DeprecatedFlag,
+                format!("methods[{}]", i),
+                format!("undefined flag bits 0x{:04X}", method.access_flags & !DEFINED_METHOD_FLAGS),
+            );
+        }
+        if major_version >= 61 && MethodAccessFlag::Strict.test(method.access_flags) {
+            diagnostics.push(
+                DiagnosticKind::DeprecatedFlag,
+                format!("methods[{}]", i),
+                "ACC_STRICT is obsolete since class file version 61.0",
+            );
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use std::fmt;
+
+use crate::types::*;
+
+/// Hard limits of the Java Virtual Machine on the contents of a class file.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.11
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LimitKind {
+    /// Entries of the constant pool, counting long and double twice.
+    ConstantPoolEntries,
+    Fields,
+    Methods,
+    Interfaces,
+    /// Bytes of the code array of a method.
+    CodeLength,
+    /// Local variable slots taken by the parameters of a method, including `this`.
+    ParameterSlots,
+    /// Dimensions of an array type in a descriptor or class name.
+    ArrayDimensions,
+    /// Bytes of a CONSTANT_Utf8 entry.
+    Utf8Length,
+}
+
+impl LimitKind {
+    /// The largest value allowed by the JVM.
+    pub fn max(&self) -> usize {
+        match self {
+            LimitKind::ConstantPoolEntries => 65534,
+            LimitKind::Fields | LimitKind::Methods | LimitKind::Interfaces => 65535,
+            LimitKind::CodeLength | LimitKind::Utf8Length => 65535,
+            LimitKind::ParameterSlots | LimitKind::ArrayDimensions => 255,
+        }
+    }
+}
+
+/// The highest value found in a class file for one [`LimitKind`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitUsage {
+    pub kind: LimitKind,
+    /// Where the value was found, e.g. `methods[3]`.
+    pub location: String,
+    pub value: usize,
+    pub max: usize,
+}
+
+impl LimitUsage {
+    fn new(kind: LimitKind, location: impl Into<String>, value: usize) -> Self {
+        Self {
+            kind,
+            location: location.into(),
+            value,
+            max: kind.max(),
+        }
+    }
+
+    /// Returns how close the value is to the limit, in percent.
+    pub fn percentage(&self) -> f64 {
+        self.value as f64 * 100.0 / self.max as f64
+    }
+
+    pub fn is_exceeded(&self) -> bool {
+        self.value > self.max
+    }
+
+    /// Keeps the larger of the two usages.
+    fn update(&mut self, location: impl Into<String>, value: usize) {
+        if value > self.value {
+            self.location = location.into();
+            self.value = value;
+        }
+    }
+}
+
+impl fmt::Display for LimitUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?}: {} of {} ({:.1}%) at {}",
+            self.kind,
+            self.value,
+            self.max,
+            self.percentage(),
+            self.location
+        )
+    }
+}
+
+/// Returns the most dimensions of the array types in a descriptor or class name.
+fn array_dimensions(descriptor: &str) -> usize {
+    descriptor.split(|c| c != '[').map(str::len).max().unwrap_or(0)
+}
+
+/// Reports how close `class_file` is to each of the hard JVM limits, one [`LimitUsage`]
+/// per [`LimitKind`] holding the highest value found.
+pub fn check(class_file: &JavaClassFile) -> Vec<LimitUsage> {
+    let constant_pool = &class_file.constant_pool;
+
+    let mut utf8_length = LimitUsage::new(LimitKind::Utf8Length, "constant_pool", 0);
+    let mut dimensions = LimitUsage::new(LimitKind::ArrayDimensions, "constant_pool", 0);
+    for (i, constant) in constant_pool.iter().enumerate() {
+        let location = || format!("constant_pool[{}]", i);
+        match constant {
+            ConstantPoolInfo::Utf8(utf8_info) => utf8_length.update(location(), utf8_info.length),
+            ConstantPoolInfo::Class(class_info) => {
+                let name = get_utf8(constant_pool, class_info.name_index).unwrap_or_default();
+                dimensions.update(location(), array_dimensions(name));
+            }
+            ConstantPoolInfo::NameAndType(name_and_type_info) => {
+                let descriptor = get_utf8(constant_pool, name_and_type_info.descriptor_index).unwrap_or_default();
+                dimensions.update(location(), array_dimensions(descriptor));
+            }
+            _ => {}
+        }
+    }
+
+    for (i, field) in class_file.fields.iter().enumerate() {
+        let descriptor = get_utf8(constant_pool, field.descriptor_index).unwrap_or_default();
+        dimensions.update(format!("fields[{}]", i), array_dimensions(descriptor));
+    }
+
+    let mut code_length = LimitUsage::new(LimitKind::CodeLength, "methods", 0);
+    let mut parameters = LimitUsage::new(LimitKind::ParameterSlots, "methods", 0);
+    for (i, method) in class_file.methods.iter().enumerate() {
+        let location = format!("methods[{}]", i);
+        let descriptor = get_utf8(constant_pool, method.descriptor_index).unwrap_or_default();
+        let receiver = if MethodAccessFlag::Static.test(method.access_flags) { 0 } else { 1 };
+        parameters.update(&location, parameter_slots(descriptor).unwrap_or(0) + receiver);
+        dimensions.update(&location, array_dimensions(descriptor));
+        if let Ok(Some(code)) = CodeView::new(method, constant_pool) {
+            code_length.update(&location, code.code.len());
+        }
+    }
+
+    vec![
+        LimitUsage::new(LimitKind::ConstantPoolEntries, "constant_pool", constant_pool.len().saturating_sub(1)),
+        LimitUsage::new(LimitKind::Fields, "fields", class_file.fields.len()),
+        LimitUsage::new(LimitKind::Methods, "methods", class_file.methods.len()),
+        LimitUsage::new(LimitKind::Interfaces, "interfaces", class_file.interfaces.len()),
+        code_length,
+        parameters,
+        dimensions,
+        utf8_length,
+    ]
+}
@@ -0,0 +1,363 @@
+//! Predicts the linkage errors a JVM would raise when loading a class and resolving its
+//! references against a classpath.
+//!
+//! ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.4.3
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use crate::{
+    code::CodeView,
+    index::{ClassIndex, ClassSummary, MemberSummary},
+    opcode::{instruction_length, Opcode},
+    types::*,
+};
+
+/// Classes whose native varargs methods are signature polymorphic, matching any descriptor.
+const SIGNATURE_POLYMORPHIC_CLASSES: [&str; 2] = ["java/lang/invoke/MethodHandle", "java/lang/invoke/VarHandle"];
+
+/// A linkage error a JVM would raise, with the reference which fails to resolve.
+///
+/// Class names are in internal form, e.g. `java/util/ArrayList`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    /// The class, or a superclass or superinterface needed to load it, is not on the
+    /// classpath.
+    NoClassDefFound { class: String },
+    NoSuchField { owner: String, name: String, descriptor: String },
+    NoSuchMethod { owner: String, name: String, descriptor: String },
+    /// A class is used as an interface or the other way round, or a member is static where
+    /// an instance one is expected or the other way round.
+    IncompatibleClassChange { reference: String, reason: &'static str },
+}
+
+impl LinkError {
+    /// Returns the binary name of the error class the JVM would throw, e.g.
+    /// `java.lang.NoSuchMethodError`.
+    pub fn exception(&self) -> &'static str {
+        match self {
+            LinkError::NoClassDefFound { .. } => "java.lang.NoClassDefFoundError",
+            LinkError::NoSuchField { .. } => "java.lang.NoSuchFieldError",
+            LinkError::NoSuchMethod { .. } => "java.lang.NoSuchMethodError",
+            LinkError::IncompatibleClassChange { .. } => "java.lang.IncompatibleClassChangeError",
+        }
+    }
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.exception())?;
+        match self {
+            LinkError::NoClassDefFound { class } => write!(f, "{}", class),
+            LinkError::NoSuchField { owner, name, descriptor }
+            | LinkError::NoSuchMethod { owner, name, descriptor } => write!(f, "{}.{}:{}", owner, name, descriptor),
+            LinkError::IncompatibleClassChange { reference, reason } => write!(f, "{}: {}", reference, reason),
+        }
+    }
+}
+
+/// Predicts the errors linking `class_file` against `classpath` would raise: loading its
+/// supertypes, resolving the classes, fields and methods its constant pool refers to, and
+/// the static or instance kind the instructions of its methods expect of them.
+///
+/// Every class involved must be in `classpath`, those of the JDK included, e.g. by
+/// indexing the classes of the target runtime; the class itself need not be. Access
+/// checks are not made. Errors are listed once each, supertypes first and then in constant
+/// pool order.
+pub fn simulate_link(class_file: &JavaClassFile, classpath: &ClassIndex) -> Vec<LinkError> {
+    let Some(this) = ClassSummary::new(class_file) else {
+        return Vec::new();
+    };
+    let mut linker = Linker { this: &this, classpath, loadable: HashMap::new(), errors: Vec::new() };
+    let constant_pool = &class_file.constant_pool;
+
+    linker.load(&this.name);
+    for (index, constant) in constant_pool.iter().enumerate() {
+        match constant {
+            ConstantPoolInfo::Class(_) => {
+                linker.load(get_class_name(constant_pool, index).unwrap_or_default());
+            }
+            ConstantPoolInfo::FieldRef(_) => {
+                linker.resolve_field(constant_pool, index);
+            }
+            ConstantPoolInfo::MethodRef(_) | ConstantPoolInfo::InterfaceMethodRef(_) => {
+                linker.resolve_method(constant_pool, index);
+            }
+            _ => {}
+        }
+    }
+
+    for method in &class_file.methods {
+        let Ok(Some(code)) = CodeView::new(method, constant_pool) else {
+            continue;
+        };
+        let mut pc = 0;
+        while let Some(length) = instruction_length(code.code, pc) {
+            if let Some(opcode) = Opcode::from_u8(code.code[pc]) {
+                linker.check_instruction(constant_pool, opcode, code.code, pc);
+            }
+            pc += length;
+        }
+    }
+
+    linker.errors
+}
+
+struct Linker<'i> {
+    this: &'i ClassSummary,
+    classpath: &'i ClassIndex,
+    /// Whether each class met so far loads, i.e. it and its supertypes are on the classpath.
+    loadable: HashMap<String, bool>,
+    errors: Vec<LinkError>,
+}
+
+impl<'i> Linker<'i> {
+    fn get(&self, name: &str) -> Option<&'i ClassSummary> {
+        if name == self.this.name {
+            Some(self.this)
+        } else {
+            self.classpath.get(name)
+        }
+    }
+
+    fn report(&mut self, error: LinkError) {
+        if !self.errors.contains(&error) {
+            self.errors.push(error);
+        }
+    }
+
+    /// Returns true if the class named `name` and all its supertypes load, reporting those
+    /// which do not. An array class loads if its element class, if any, does.
+    fn load(&mut self, name: &str) -> bool {
+        if let Some(element) = name.strip_prefix('[') {
+            let element = element.trim_start_matches('[');
+            return match element.strip_prefix('L').and_then(|element| element.strip_suffix(';')) {
+                Some(element) => self.load(element),
+                None => true,
+            };
+        }
+        if let Some(loadable) = self.loadable.get(name) {
+            return *loadable;
+        }
+        let Some(class) = self.get(name) else {
+            self.loadable.insert(name.to_string(), false);
+            self.report(LinkError::NoClassDefFound { class: name.to_string() });
+            return false;
+        };
+        // Assumed loadable while its supertypes are loaded, which ends circular hierarchies.
+        self.loadable.insert(name.to_string(), true);
+
+        let mut loadable = true;
+        if let Some(super_class) = &class.super_class {
+            loadable &= self.load(super_class);
+            if self.get(super_class).is_some_and(is_interface) {
+                self.report(LinkError::IncompatibleClassChange {
+                    reference: name.to_string(),
+                    reason: "has an interface as its superclass",
+                });
+            }
+        }
+        for interface in &class.interfaces {
+            loadable &= self.load(interface);
+            if self.get(interface).is_some_and(|interface| !is_interface(interface)) {
+                self.report(LinkError::IncompatibleClassChange {
+                    reference: name.to_string(),
+                    reason: "implements a class which is not an interface",
+                });
+            }
+        }
+        self.loadable.insert(name.to_string(), loadable);
+        loadable
+    }
+
+    /// Resolves the CONSTANT_Fieldref at `index`, returning the field found.
+    fn resolve_field(&mut self, constant_pool: &[ConstantPoolInfo], index: usize) -> Option<&'i MemberSummary> {
+        let (owner, name, descriptor) = get_field_ref(constant_pool, index)?;
+        if !self.load(owner) {
+            return None;
+        }
+        let field = self.find_field(owner, name, descriptor, &mut HashSet::new());
+        if field.is_none() {
+            self.report(LinkError::NoSuchField {
+                owner: owner.to_string(),
+                name: name.to_string(),
+                descriptor: descriptor.to_string(),
+            });
+        }
+        field
+    }
+
+    /// Looks a field up in `class`, then its superinterfaces and then its superclass.
+    fn find_field<'s>(
+        &self,
+        class: &'s str,
+        name: &str,
+        descriptor: &str,
+        visited: &mut HashSet<&'s str>,
+    ) -> Option<&'i MemberSummary>
+    where
+        'i: 's,
+    {
+        if !visited.insert(class) {
+            return None;
+        }
+        let summary = self.get(class)?;
+        if let Some(field) = find_member(&summary.fields, name, descriptor) {
+            return Some(field);
+        }
+        summary
+            .interfaces
+            .iter()
+            .chain(&summary.super_class)
+            .find_map(|supertype| self.find_field(supertype, name, descriptor, visited))
+    }
+
+    /// Resolves the CONSTANT_Methodref or CONSTANT_InterfaceMethodref at `index`, returning
+    /// the method found.
+    fn resolve_method(&mut self, constant_pool: &[ConstantPoolInfo], index: usize) -> Option<&'i MemberSummary> {
+        let interface_ref = matches!(constant_pool.get(index), Some(ConstantPoolInfo::InterfaceMethodRef(_)));
+        let (owner, name, descriptor) = get_member_ref(constant_pool, index)?;
+        if !self.load(owner) {
+            return None;
+        }
+        // Arrays have the methods of Object, e.g. `[B.clone:()Ljava/lang/Object;`.
+        let owner = if owner.starts_with('[') { "java/lang/Object" } else { owner };
+        let class = self.get(owner)?;
+        let reference = || format!("{}.{}:{}", owner, name, descriptor);
+        if is_interface(class) != interface_ref {
+            let reason = if interface_ref {
+                "interface method reference to a class"
+            } else {
+                "method reference to an interface"
+            };
+            self.report(LinkError::IncompatibleClassChange { reference: reference(), reason });
+            return None;
+        }
+
+        let found = if interface_ref {
+            find_member(&class.methods, name, descriptor)
+                .or_else(|| {
+                    let object = self.get("java/lang/Object")?;
+                    find_member(&object.methods, name, descriptor).filter(|method| {
+                        MethodAccessFlag::Public.test(method.access_flags)
+                            && !MethodAccessFlag::Static.test(method.access_flags)
+                    })
+                })
+                .or_else(|| self.find_interface_method(owner, name, descriptor))
+        } else {
+            self.find_class_method(owner, name, descriptor)
+                .or_else(|| self.find_interface_method(owner, name, descriptor))
+        };
+        if found.is_none() {
+            self.report(LinkError::NoSuchMethod {
+                owner: owner.to_string(),
+                name: name.to_string(),
+                descriptor: descriptor.to_string(),
+            });
+        }
+        found
+    }
+
+    /// Looks a method up in `class` and then its superclasses, matching the signature
+    /// polymorphic methods of `MethodHandle` and `VarHandle` by name alone.
+    fn find_class_method(&self, class: &str, name: &str, descriptor: &str) -> Option<&'i MemberSummary> {
+        let mut visited = HashSet::new();
+        let mut current = Some(class);
+        while let Some(class) = current.filter(|class| visited.insert(*class)) {
+            let summary = self.get(class)?;
+            if let Some(method) = find_member(&summary.methods, name, descriptor) {
+                return Some(method);
+            }
+            if SIGNATURE_POLYMORPHIC_CLASSES.contains(&class) {
+                let polymorphic = summary.methods.iter().find(|method| {
+                    method.name == name
+                        && MethodAccessFlag::Native.test(method.access_flags)
+                        && MethodAccessFlag::Varargs.test(method.access_flags)
+                });
+                if polymorphic.is_some() {
+                    return polymorphic;
+                }
+            }
+            current = summary.super_class.as_deref();
+        }
+        None
+    }
+
+    /// Looks a non-private, non-static method up in the superinterfaces of `class`, direct
+    /// or not, including those of its superclasses.
+    fn find_interface_method(&self, start: &str, name: &str, descriptor: &str) -> Option<&'i MemberSummary> {
+        let mut visited = HashSet::new();
+        let mut pending = vec![start];
+        while let Some(class) = pending.pop() {
+            if !visited.insert(class) {
+                continue;
+            }
+            let Some(summary) = self.get(class) else {
+                continue;
+            };
+            if class != start && is_interface(summary) {
+                let method = find_member(&summary.methods, name, descriptor).filter(|method| {
+                    !MethodAccessFlag::Private.test(method.access_flags)
+                        && !MethodAccessFlag::Static.test(method.access_flags)
+                });
+                if method.is_some() {
+                    return method;
+                }
+            }
+            pending.extend(summary.interfaces.iter().map(String::as_str));
+            pending.extend(summary.super_class.as_deref());
+        }
+        None
+    }
+
+    /// Checks that the field or method an instruction refers to is static or not as the
+    /// instruction expects.
+    fn check_instruction(&mut self, constant_pool: &[ConstantPoolInfo], opcode: Opcode, code: &[u8], pc: usize) {
+        let (expects_static, is_field) = match opcode {
+            Opcode::Getstatic | Opcode::Putstatic => (true, true),
+            Opcode::Getfield | Opcode::Putfield => (false, true),
+            Opcode::Invokestatic => (true, false),
+            Opcode::Invokevirtual | Opcode::Invokespecial | Opcode::Invokeinterface => (false, false),
+            _ => return,
+        };
+        let index = u16::from_be_bytes([code[pc + 1], code[pc + 2]]) as usize;
+        // Resolution errors were already reported while walking the constant pool.
+        let member = if is_field {
+            self.resolve_field(constant_pool, index)
+        } else {
+            self.resolve_method(constant_pool, index)
+        };
+        let Some(member) = member else {
+            return;
+        };
+
+        let is_static = if is_field {
+            FieldAccessFlag::Static.test(member.access_flags)
+        } else {
+            MethodAccessFlag::Static.test(member.access_flags)
+        };
+        if is_static != expects_static {
+            let (owner, name, descriptor) = get_member_ref(constant_pool, index).unwrap_or_default();
+            let reason = match (is_field, expects_static) {
+                (true, true) => "expected a static field",
+                (true, false) => "expected a non-static field",
+                (false, true) => "expected a static method",
+                (false, false) => "expected a non-static method",
+            };
+            self.report(LinkError::IncompatibleClassChange {
+                reference: format!("{}.{}:{}", owner, name, descriptor),
+                reason,
+            });
+        }
+    }
+}
+
+fn is_interface(class: &ClassSummary) -> bool {
+    ClassAccessFlag::Interface.test(class.access_flags)
+}
+
+fn find_member<'i>(members: &'i [MemberSummary], name: &str, descriptor: &str) -> Option<&'i MemberSummary> {
+    members.iter().find(|member| member.name == name && member.descriptor == descriptor)
+}
@@ -0,0 +1,252 @@
+//! Labels, comments and flags attached to the instructions of a method while it is edited,
+//! e.g. by one pass of a transformation pipeline for a later one to find.
+//!
+//! Markers are kept by instruction offset. [`InstructionMarkers::carry_over`] moves them
+//! onto the edited code, and they can be stored in a custom attribute of a
+//! [`MethodCheckout`], which is checked in along with the code.
+
+use std::{collections::BTreeMap, fmt};
+
+use crate::{
+    checkout::MethodCheckout,
+    diff::{edit_operations, instructions, Operation},
+    types::*,
+    utils::*,
+};
+
+/// Name of the method attribute markers are stored in.
+pub const MARKERS_ATTRIBUTE: &str = "java_classfile.Markers";
+
+/// Something attached to an instruction.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Marker {
+    /// A name to find the instruction by, e.g. `loop_head`.
+    Label(String),
+    /// Free text, e.g. why a pass inserted the instruction.
+    Comment(String),
+    /// A tag for passes to test, e.g. `instrumented`.
+    Flag(String),
+}
+
+impl Marker {
+    fn kind(&self) -> u8 {
+        match self {
+            Marker::Label(_) => 0,
+            Marker::Comment(_) => 1,
+            Marker::Flag(_) => 2,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        match self {
+            Marker::Label(text) | Marker::Comment(text) | Marker::Flag(text) => text,
+        }
+    }
+}
+
+/// Why markers cannot be stored in or read from an attribute.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MarkerError {
+    /// More than 65535 markers, or a marker on an offset beyond 65535.
+    TooMany { count: usize },
+    /// A marker text longer than 65535 bytes.
+    TextTooLong { pc: usize, length: usize },
+    /// The attribute ends within the marker at this offset.
+    Truncated { offset: usize },
+    /// The marker at this offset has an unknown kind.
+    UnknownKind { offset: usize, kind: u8 },
+    /// The text of the marker at this offset is not UTF-8.
+    InvalidText { offset: usize },
+}
+
+impl fmt::Display for MarkerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooMany { count } => write!(f, "{} markers do not fit in an attribute", count),
+            Self::TextTooLong { pc, length } => write!(f, "marker text of {} bytes at pc {} is too long", length, pc),
+            Self::Truncated { offset } => write!(f, "markers truncated at offset {}", offset),
+            Self::UnknownKind { offset, kind } => write!(f, "unknown marker kind {} at offset {}", kind, offset),
+            Self::InvalidText { offset } => write!(f, "marker text at offset {} is not UTF-8", offset),
+        }
+    }
+}
+
+impl std::error::Error for MarkerError {}
+
+/// The markers of the instructions of one method, by instruction offset.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InstructionMarkers {
+    markers: BTreeMap<usize, Vec<Marker>>,
+}
+
+impl InstructionMarkers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attaches `marker` to the instruction at `pc`, after those already attached.
+    pub fn add(&mut self, pc: usize, marker: Marker) {
+        self.markers.entry(pc).or_default().push(marker);
+    }
+
+    /// Returns the markers of the instruction at `pc`.
+    pub fn at(&self, pc: usize) -> &[Marker] {
+        self.markers.get(&pc).map_or(&[], Vec::as_slice)
+    }
+
+    /// Detaches and returns the markers of the instruction at `pc`.
+    pub fn remove(&mut self, pc: usize) -> Vec<Marker> {
+        self.markers.remove(&pc).unwrap_or_default()
+    }
+
+    /// Returns the markers with their offsets, in offset order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Marker)> {
+        self.markers.iter().flat_map(|(pc, markers)| markers.iter().map(move |marker| (*pc, marker)))
+    }
+
+    /// Returns the number of markers.
+    pub fn len(&self) -> usize {
+        self.markers.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.markers.is_empty()
+    }
+
+    /// Returns the offset of the instruction labelled `name`.
+    pub fn label(&self, name: &str) -> Option<usize> {
+        self.iter()
+            .find(|(_, marker)| matches!(marker, Marker::Label(label) if label == name))
+            .map(|(pc, _)| pc)
+    }
+
+    /// Moves the markers of `old` code onto `new` code, matching instructions as
+    /// [`diff_code`](crate::diff::diff_code) does.
+    ///
+    /// Markers of unchanged instructions follow them. Markers of removed or replaced
+    /// instructions move to the first instruction inserted in their place, or else to the
+    /// instruction following them. Markers not on the start of an instruction are dropped.
+    pub fn carry_over(
+        &self,
+        old: &[u8],
+        old_constant_pool: &[ConstantPoolInfo],
+        new: &[u8],
+        new_constant_pool: &[ConstantPoolInfo],
+    ) -> InstructionMarkers {
+        let (old_code, new_code) = (old, new);
+        let old = instructions(old_code, old_constant_pool);
+        let new = instructions(new_code, new_constant_pool);
+
+        let mut carried = InstructionMarkers::new();
+        let (mut old_index, mut new_index) = (0, 0);
+        // Index in `new` where the current run of changes starts.
+        let mut run_start = None;
+        for operation in edit_operations(&old, &new) {
+            let target = match operation {
+                Operation::Equal => {
+                    run_start = None;
+                    Some(new[new_index].pc)
+                }
+                Operation::Delete => {
+                    let start = *run_start.get_or_insert(new_index);
+                    Some(new.get(start).map_or(new_code.len(), |instruction| instruction.pc))
+                }
+                Operation::Insert => {
+                    run_start.get_or_insert(new_index);
+                    None
+                }
+            };
+            if let Some(target) = target {
+                let markers = self.at(old[old_index].pc);
+                if !markers.is_empty() {
+                    carried.markers.entry(target).or_default().extend_from_slice(markers);
+                }
+                old_index += 1;
+            }
+            if operation != Operation::Delete {
+                new_index += 1;
+            }
+        }
+        carried
+    }
+
+    /// Encodes the markers as the body of a [`MARKERS_ATTRIBUTE`] attribute.
+    ///
+    /// The body is a u2 count followed by, for each marker, its u2 offset, a u1 kind (0 for
+    /// a label, 1 for a comment and 2 for a flag), and its text as a u2 length and UTF-8.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, MarkerError> {
+        let count = self.len();
+        if count > u16::MAX as usize || self.markers.keys().any(|pc| *pc > u16::MAX as usize) {
+            return Err(MarkerError::TooMany { count });
+        }
+        let mut bytes = Vec::new();
+        write_u16(&mut bytes, count as u16);
+        for (pc, marker) in self.iter() {
+            let text = marker.text();
+            if text.len() > u16::MAX as usize {
+                return Err(MarkerError::TextTooLong { pc, length: text.len() });
+            }
+            write_u16(&mut bytes, pc as u16);
+            bytes.push(marker.kind());
+            write_u16(&mut bytes, text.len() as u16);
+            bytes.extend_from_slice(text.as_bytes());
+        }
+        Ok(bytes)
+    }
+
+    /// Decodes the body of a [`MARKERS_ATTRIBUTE`] attribute.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MarkerError> {
+        let mut offset = 0;
+        let read = |length: usize, offset: &mut usize| {
+            let data = bytes.get(*offset..*offset + length).ok_or(MarkerError::Truncated { offset: *offset })?;
+            *offset += length;
+            Ok::<_, MarkerError>(data)
+        };
+        let u16 = |data: &[u8]| u16::from_be_bytes([data[0], data[1]]) as usize;
+
+        let count = u16(read(2, &mut offset)?);
+        let mut markers = InstructionMarkers::new();
+        for _ in 0..count {
+            let start = offset;
+            let pc = u16(read(2, &mut offset)?);
+            let kind = read(1, &mut offset)?[0];
+            let length = u16(read(2, &mut offset)?);
+            let text = std::str::from_utf8(read(length, &mut offset)?)
+                .map_err(|_| MarkerError::InvalidText { offset: start })?
+                .to_string();
+            let marker = match kind {
+                0 => Marker::Label(text),
+                1 => Marker::Comment(text),
+                2 => Marker::Flag(text),
+                kind => return Err(MarkerError::UnknownKind { offset: start, kind }),
+            };
+            markers.add(pc, marker);
+        }
+        Ok(markers)
+    }
+
+    /// Reads the markers stored in a method, or returns empty markers if it has none.
+    pub fn from_method(method: &MethodInfo, constant_pool: &[ConstantPoolInfo]) -> Result<Self, MarkerError> {
+        match method.attributes.by_name(MARKERS_ATTRIBUTE, constant_pool) {
+            Some(
+                AttributeInfo::Raw { data, .. }
+                | AttributeInfo::User { data, .. }
+                | AttributeInfo::Unknown(data)
+                | AttributeInfo::Lazy(LazyAttribute { data, .. }),
+            ) => Self::from_bytes(data),
+            _ => Ok(Self::new()),
+        }
+    }
+
+    /// Reads the markers stored in a checkout, or returns empty markers if it has none.
+    pub fn from_checkout(checkout: &MethodCheckout) -> Result<Self, MarkerError> {
+        checkout.attribute(MARKERS_ATTRIBUTE).map_or(Ok(Self::new()), Self::from_bytes)
+    }
+
+    /// Stores the markers in a checkout, replacing those stored before, so that they are
+    /// checked in with the method.
+    pub fn store(&self, checkout: &mut MethodCheckout) -> Result<(), MarkerError> {
+        checkout.set_attribute(MARKERS_ATTRIBUTE, self.to_bytes()?);
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+//! Static metrics of the code of classes, e.g. to characterize a workload from its jar.
+
+use std::collections::HashMap;
+
+use crate::{
+    code::CodeView,
+    opcode::{instruction_length, Opcode, OpcodeCategory},
+    types::*,
+};
+
+/// The code an [`OpcodeHistogram`] is built from.
+#[derive(Debug, Clone, Copy)]
+pub enum Scope<'s, 'a> {
+    /// One method of a class.
+    Method(&'s JavaClassFile<'a>, &'s MethodInfo<'a>),
+    /// All methods of a class.
+    Class(&'s JavaClassFile<'a>),
+    /// All methods of the given classes.
+    Classes(&'s [JavaClassFile<'a>]),
+    /// All methods of all classes of a jar.
+    #[cfg(feature = "jar")]
+    Jar(&'s crate::jar::Jar),
+}
+
+/// How many times each opcode appears in some code.
+///
+/// `wide` counts as an instruction of its own, not as the instruction it modifies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpcodeHistogram {
+    counts: [u64; 256],
+    /// Number of methods whose code was counted. Abstract and native methods have none.
+    pub methods: usize,
+    /// Number of methods whose code could not be decoded, and of classes of a jar which
+    /// could not be read or decoded. None of their code is counted.
+    pub skipped: usize,
+}
+
+impl Default for OpcodeHistogram {
+    fn default() -> Self {
+        Self {
+            counts: [0; 256],
+            methods: 0,
+            skipped: 0,
+        }
+    }
+}
+
+impl OpcodeHistogram {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns how many times `opcode` appears.
+    pub fn count(&self, opcode: Opcode) -> u64 {
+        self.counts[opcode as usize]
+    }
+
+    /// Returns the number of instructions.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Returns the fraction of the instructions which are `opcode`, or 0 if there are none.
+    pub fn frequency(&self, opcode: Opcode) -> f64 {
+        match self.total() {
+            0 => 0.0,
+            total => self.count(opcode) as f64 / total as f64,
+        }
+    }
+
+    /// Iterates over the opcodes which appear and their counts, in opcode order.
+    pub fn iter(&self) -> impl Iterator<Item = (Opcode, u64)> + '_ {
+        self.counts.iter().enumerate().filter(|(_, &count)| count > 0).filter_map(|(value, &count)| {
+            Some((Opcode::from_u8(value as u8)?, count))
+        })
+    }
+
+    /// Returns the opcodes which appear, most frequent first. Ties are in opcode order.
+    pub fn most_frequent(&self) -> Vec<(Opcode, u64)> {
+        let mut opcodes: Vec<_> = self.iter().collect();
+        opcodes.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+        opcodes
+    }
+
+    /// Returns the number of instructions of each category which appears.
+    pub fn by_category(&self) -> HashMap<OpcodeCategory, u64> {
+        let mut categories = HashMap::new();
+        for (opcode, count) in self.iter() {
+            *categories.entry(opcode.category()).or_insert(0) += count;
+        }
+        categories
+    }
+
+    /// Adds the counts of `other` to this histogram.
+    pub fn merge(&mut self, other: &OpcodeHistogram) {
+        for (count, other) in self.counts.iter_mut().zip(other.counts.iter()) {
+            *count += other;
+        }
+        self.methods += other.methods;
+        self.skipped += other.skipped;
+    }
+
+    fn add_method(&mut self, class_file: &JavaClassFile, method: &MethodInfo) {
+        let Ok(code) = CodeView::new(method, &class_file.constant_pool) else {
+            self.skipped += 1;
+            return;
+        };
+        let Some(code) = code else {
+            return;
+        };
+        // Counted only once the whole code decodes, so that a method is either counted or skipped.
+        let mut opcodes = Vec::new();
+        let mut pc = 0;
+        while let Some(length) = instruction_length(code.code, pc) {
+            opcodes.push(code.code[pc]);
+            pc += length;
+        }
+        if pc != code.code.len() {
+            self.skipped += 1;
+            return;
+        }
+        for opcode in opcodes {
+            self.counts[opcode as usize] += 1;
+        }
+        self.methods += 1;
+    }
+
+    fn add_class(&mut self, class_file: &JavaClassFile) {
+        for method in &class_file.methods {
+            self.add_method(class_file, method);
+        }
+    }
+}
+
+/// Counts the opcodes of the code in `scope`.
+pub fn opcode_histogram(scope: Scope) -> OpcodeHistogram {
+    let mut histogram = OpcodeHistogram::new();
+    match scope {
+        Scope::Method(class_file, method) => histogram.add_method(class_file, method),
+        Scope::Class(class_file) => histogram.add_class(class_file),
+        Scope::Classes(class_files) => class_files.iter().for_each(|class_file| histogram.add_class(class_file)),
+        #[cfg(feature = "jar")]
+        Scope::Jar(jar) => {
+            for entry in jar.class_entries() {
+                match jar.read(entry).ok().as_deref().map(crate::decode) {
+                    Some(Ok(class_file)) => histogram.add_class(&class_file),
+                    _ => histogram.skipped += 1,
+                }
+            }
+        }
+    }
+    histogram
+}
+
+/// Serialized as a map with `total`, `methods`, `skipped` and `opcodes`, the counts of the
+/// opcodes which appear by mnemonic, e.g. `{"total": 3, "methods": 1, "skipped": 0,
+/// "opcodes": {"aload_0": 1, "invokespecial": 1, "return": 1}}`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for OpcodeHistogram {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        struct Opcodes<'h>(&'h OpcodeHistogram);
+
+        impl serde::Serialize for Opcodes<'_> {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_map(self.0.iter().map(|(opcode, count)| (opcode.mnemonic(), count)))
+            }
+        }
+
+        let mut state = serializer.serialize_struct("OpcodeHistogram", 4)?;
+        state.serialize_field("total", &self.total())?;
+        state.serialize_field("methods", &self.methods)?;
+        state.serialize_field("skipped", &self.skipped)?;
+        state.serialize_field("opcodes", &Opcodes(self))?;
+        state.end()
+    }
+}
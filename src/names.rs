@@ -0,0 +1,200 @@
+use std::fmt;
+
+use crate::descriptor::is_field_descriptor;
+
+/// What a name checked by the functions of this module names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameKind {
+    Class,
+    Field,
+    Method,
+    Module,
+    Package,
+}
+
+impl fmt::Display for NameKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            NameKind::Class => "class",
+            NameKind::Field => "field",
+            NameKind::Method => "method",
+            NameKind::Module => "module",
+            NameKind::Package => "package",
+        })
+    }
+}
+
+/// A name which breaks the rules of the JVM specification for its kind.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.2
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameError {
+    pub kind: NameKind,
+    pub name: String,
+    /// Why the name is illegal, e.g. `contains '.'`.
+    pub reason: String,
+}
+
+impl fmt::Display for NameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "illegal {} name \"{}\": {}", self.kind, self.name, self.reason)
+    }
+}
+
+impl std::error::Error for NameError {}
+
+fn name_error(kind: NameKind, name: &str, reason: impl Into<String>) -> NameError {
+    NameError {
+        kind,
+        name: name.to_string(),
+        reason: reason.into(),
+    }
+}
+
+/// Returns why `name` is not an unqualified name, or `None` if it is one.
+fn unqualified_name_problem(name: &str) -> Option<String> {
+    if name.is_empty() {
+        return Some("is empty".to_string());
+    }
+    name.chars().find(|c| matches!(c, '.' | ';' | '[' | '/')).map(|c| format!("contains '{}'", c))
+}
+
+/// Checks that `name` is a binary class name in internal form, e.g. `java/lang/Thread$State`.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.2.1
+pub fn check_binary_name(name: &str) -> Result<(), NameError> {
+    check_internal_form(NameKind::Class, name)
+}
+
+/// Checks that `name` is the name of a CONSTANT_Class: a binary class name in internal form,
+/// or an array type descriptor such as `[Ljava/lang/String;`.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.4.1
+pub fn check_class_name(name: &str) -> Result<(), NameError> {
+    if !name.starts_with('[') {
+        return check_binary_name(name);
+    }
+    if name.bytes().take_while(|&b| b == b'[').count() > 255 {
+        return Err(name_error(NameKind::Class, name, "has more than 255 array dimensions"));
+    }
+    if !is_field_descriptor(name) {
+        return Err(name_error(NameKind::Class, name, "is not a valid array descriptor"));
+    }
+    Ok(())
+}
+
+/// Checks that `name` is an unqualified name, as field names are.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.2.2
+pub fn check_field_name(name: &str) -> Result<(), NameError> {
+    match unqualified_name_problem(name) {
+        Some(reason) => Err(name_error(NameKind::Field, name, reason)),
+        None => Ok(()),
+    }
+}
+
+/// Checks that `name` is an unqualified name without `<` or `>`, unless it is `<init>` or
+/// `<clinit>`.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.2.2
+pub fn check_method_name(name: &str) -> Result<(), NameError> {
+    if let Some(reason) = unqualified_name_problem(name) {
+        return Err(name_error(NameKind::Method, name, reason));
+    }
+    if name != "<init>" && name != "<clinit>" && name.contains(['<', '>']) {
+        return Err(name_error(NameKind::Method, name, "contains '<' or '>' but is not <init> or <clinit>"));
+    }
+    Ok(())
+}
+
+/// Checks that `name` is a module name: no control characters, and `\` only escaping `\`,
+/// `:` or `@`, which may not appear otherwise.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.2.3
+pub fn check_module_name(name: &str) -> Result<(), NameError> {
+    if name.is_empty() {
+        return Err(name_error(NameKind::Module, name, "is empty"));
+    }
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\u{0}'..='\u{1f}' => {
+                return Err(name_error(NameKind::Module, name, format!("contains the control character {:?}", c)));
+            }
+            '\\' => match chars.next() {
+                Some('\\' | ':' | '@') => {}
+                _ => return Err(name_error(NameKind::Module, name, "contains '\\' not followed by '\\', ':' or '@'")),
+            },
+            ':' | '@' => return Err(name_error(NameKind::Module, name, format!("contains an unescaped '{}'", c))),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Checks that `name` is a package name in internal form, e.g. `java/lang`.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.2.3
+pub fn check_package_name(name: &str) -> Result<(), NameError> {
+    check_internal_form(NameKind::Package, name)
+}
+
+/// Checks that `name` is a sequence of unqualified names separated by `/`.
+fn check_internal_form(kind: NameKind, name: &str) -> Result<(), NameError> {
+    if name.is_empty() {
+        return Err(name_error(kind, name, "is empty"));
+    }
+    for identifier in name.split('/') {
+        if identifier.is_empty() {
+            return Err(name_error(kind, name, "has an empty identifier"));
+        }
+        if let Some(reason) = unqualified_name_problem(identifier) {
+            return Err(name_error(kind, name, reason));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn class_names() {
+        assert!(check_class_name("java/lang/Thread$State").is_ok());
+        assert!(check_class_name("[[Ljava/lang/String;").is_ok());
+        assert!(check_class_name("[I").is_ok());
+        assert!(check_class_name("java.lang.Object").is_err());
+        assert!(check_class_name("java//Object").is_err());
+        assert!(check_class_name("java/lang/").is_err());
+        assert!(check_class_name("[Ljava/lang/String").is_err());
+        assert!(check_class_name(&format!("{}I", "[".repeat(256))).is_err());
+    }
+
+    #[test]
+    fn member_names() {
+        assert!(check_field_name("<weird>").is_ok());
+        assert!(check_field_name("a;b").is_err());
+        assert!(check_method_name("<init>").is_ok());
+        assert!(check_method_name("<clinit>").is_ok());
+        assert!(check_method_name("lambda$run$0").is_ok());
+        assert!(check_method_name("run()").is_ok());
+        assert!(check_method_name("").is_err());
+        assert_eq!(
+            check_method_name("<lambda>").unwrap_err().to_string(),
+            "illegal method name \"<lambda>\": contains '<' or '>' but is not <init> or <clinit>",
+        );
+        assert_eq!(check_method_name("a.b").unwrap_err().reason, "contains '.'");
+    }
+
+    #[test]
+    fn module_and_package_names() {
+        assert!(check_module_name("java.base").is_ok());
+        assert!(check_module_name("a\\:b\\@c\\\\d").is_ok());
+        assert!(check_module_name("a:b").is_err());
+        assert!(check_module_name("a\\b").is_err());
+        assert!(check_module_name("a\u{1}").is_err());
+        assert!(check_package_name("java/lang").is_ok());
+        assert!(check_package_name("java.lang").is_err());
+    }
+}
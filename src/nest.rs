@@ -0,0 +1,355 @@
+//! One model of a class and the classes nested in it, combining their InnerClasses,
+//! EnclosingMethod, NestHost and NestMembers attributes, for tools reasoning about
+//! nest-based access control.
+//!
+//! ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.4.4
+
+use crate::{
+    code::CodeView,
+    opcode::{instruction_length, Opcode},
+    operand::{resolve_operand, ResolvedOperand},
+    types::*,
+};
+
+/// Prefix of the names of the static methods javac generates before Java 11 so that nested
+/// classes can reach the private members of each other, e.g. `access$000`.
+const ACCESSOR_PREFIX: &str = "access$";
+
+/// A class of a [`NestedClassModel`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NestedClass<'c> {
+    /// The name in internal form, e.g. `com/example/Outer$Inner`.
+    pub name: &'c str,
+    /// The simple name from InnerClasses, or `None` for the root and anonymous classes.
+    pub simple_name: Option<&'c str>,
+    pub kind: NestingKind,
+    /// The access flags as declared in the source, from InnerClasses, including private,
+    /// protected and static which the access flags of a class file cannot hold. The access
+    /// flags of the class file for the root.
+    pub access_flags: u16,
+    /// The class this class is declared in, or `None` for the root.
+    pub outer: Option<&'c str>,
+    /// The name and descriptor of the method a local or anonymous class is declared in, or
+    /// `None` if it is declared in an initializer or its class file was not given.
+    pub enclosing_method: Option<(&'c str, &'c str)>,
+    /// The classes declared directly in this class, in the order they were found.
+    pub nested: Vec<&'c str>,
+    /// The host named by the NestHost attribute of this class, or `None` if it has none or its
+    /// class file was not given.
+    pub nest_host: Option<&'c str>,
+    /// True if the NestMembers attribute of the root lists this class.
+    pub in_nest_members: bool,
+    /// True if the class file of this class was given, and not only mentioned by the
+    /// InnerClasses attribute of another.
+    pub has_class_file: bool,
+}
+
+/// How a synthetic accessor reaches its target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AccessKind {
+    /// Reads a field.
+    Get,
+    /// Writes a field.
+    Set,
+    /// Reads and writes a field, as for `count++`.
+    Update,
+    /// Invokes a method.
+    Invoke,
+}
+
+/// A static `access$NNN` method and the private member of its class it reaches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntheticAccessor<'c> {
+    /// The class declaring both the accessor and its target.
+    pub owner: &'c str,
+    pub name: &'c str,
+    pub descriptor: &'c str,
+    /// The name and descriptor of the member the accessor reaches.
+    pub target: (&'c str, &'c str),
+    pub kind: AccessKind,
+    /// The classes of the model whose code invokes the accessor, in model order.
+    pub callers: Vec<&'c str>,
+}
+
+/// A class and the classes nested in it, found by following their InnerClasses and
+/// EnclosingMethod attributes from the root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NestedClassModel<'c> {
+    /// The classes of the tree, the root first and then in the order they were found.
+    classes: Vec<NestedClass<'c>>,
+    accessors: Vec<SyntheticAccessor<'c>>,
+}
+
+/// An InnerClasses entry resolved against the constant pool of the class it was found in.
+struct InnerEntry<'c> {
+    inner: &'c str,
+    outer: Option<&'c str>,
+    simple_name: Option<&'c str>,
+    access_flags: u16,
+}
+
+impl<'c> NestedClassModel<'c> {
+    /// Builds the model of `outer` and the classes nested in it.
+    ///
+    /// `inner` may hold any classes; those not nested in `outer`, directly or not, are
+    /// ignored. Nested classes whose class file is not given are still part of the model
+    /// when the InnerClasses attribute of another class places them, but local and anonymous
+    /// ones can only be placed by their own EnclosingMethod attribute. Returns `None` if the
+    /// name of `outer` cannot be resolved.
+    pub fn new(outer: &'c JavaClassFile, inner: &'c [JavaClassFile]) -> Option<Self> {
+        let root = get_class_name(&outer.constant_pool, outer.this_class)?;
+        let class_files: Vec<(&str, &JavaClassFile)> = std::iter::once(outer)
+            .chain(inner)
+            .filter_map(|class_file| {
+                Some((get_class_name(&class_file.constant_pool, class_file.this_class)?, class_file))
+            })
+            .collect();
+        let class_file = |name: &str| {
+            class_files.iter().find(|(class, _)| *class == name).map(|(_, class_file)| *class_file)
+        };
+
+        let mut entries: Vec<InnerEntry> = Vec::new();
+        for (_, class_file) in &class_files {
+            for entry in inner_entries(class_file) {
+                if !entries.iter().any(|known| known.inner == entry.inner) {
+                    entries.push(entry);
+                }
+            }
+        }
+        let outer_of = |entry: &InnerEntry<'c>| {
+            entry.outer.or_else(|| Some(class_file(entry.inner)?.enclosing_method()?.class_name))
+        };
+        let in_tree = |entry: &InnerEntry<'c>| {
+            let mut current = outer_of(entry);
+            // Bounded, since a broken InnerClasses attribute may make a cycle.
+            for _ in 0..=entries.len() {
+                match current {
+                    Some(name) if name == root => return true,
+                    Some(name) => current = entries.iter().find(|entry| entry.inner == name).and_then(outer_of),
+                    None => return false,
+                }
+            }
+            false
+        };
+
+        let nest_members = outer.nest_member_names();
+        let mut classes = vec![NestedClass {
+            name: root,
+            simple_name: None,
+            kind: NestingKind::TopLevel,
+            access_flags: outer.access_flags,
+            outer: None,
+            enclosing_method: None,
+            nested: Vec::new(),
+            nest_host: outer.nest_host_name(),
+            in_nest_members: false,
+            has_class_file: true,
+        }];
+        for entry in entries.iter().filter(|entry| entry.inner != root && in_tree(entry)) {
+            let own_class_file = class_file(entry.inner);
+            classes.push(NestedClass {
+                name: entry.inner,
+                simple_name: entry.simple_name,
+                kind: match (entry.outer, entry.simple_name) {
+                    (Some(_), _) => NestingKind::Member,
+                    (None, None) => NestingKind::Anonymous,
+                    (None, Some(_)) => NestingKind::Local,
+                },
+                access_flags: entry.access_flags,
+                outer: outer_of(entry),
+                enclosing_method: own_class_file.and_then(|class_file| class_file.enclosing_method()?.method),
+                nested: Vec::new(),
+                nest_host: own_class_file.and_then(JavaClassFile::nest_host_name),
+                in_nest_members: nest_members.contains(&entry.inner),
+                has_class_file: own_class_file.is_some(),
+            });
+        }
+        for index in 1..classes.len() {
+            let (name, outer) = (classes[index].name, classes[index].outer);
+            if let Some(parent) = classes.iter_mut().find(|class| Some(class.name) == outer) {
+                parent.nested.push(name);
+            }
+        }
+
+        let mut accessors = Vec::new();
+        for class in &classes {
+            if let Some(class_file) = class_file(class.name) {
+                accessors.extend(synthetic_accessors(class.name, class_file));
+            }
+        }
+        for class in &classes {
+            let Some(class_file) = class_file(class.name) else {
+                continue;
+            };
+            for (owner, name, descriptor) in invoked_statics(class_file) {
+                let accessor = accessors.iter_mut().find(|accessor| {
+                    (accessor.owner, accessor.name, accessor.descriptor) == (owner, name, descriptor)
+                });
+                if let Some(accessor) = accessor.filter(|accessor| !accessor.callers.contains(&class.name)) {
+                    accessor.callers.push(class.name);
+                }
+            }
+        }
+
+        Some(Self { classes, accessors })
+    }
+
+    /// Returns the root of the tree, the class the model was built for.
+    pub fn root(&self) -> &NestedClass<'c> {
+        &self.classes[0]
+    }
+
+    /// Returns the classes of the tree, the root first.
+    pub fn classes(&self) -> &[NestedClass<'c>] {
+        &self.classes
+    }
+
+    pub fn get(&self, name: &str) -> Option<&NestedClass<'c>> {
+        self.classes.iter().find(|class| class.name == name)
+    }
+
+    /// Returns the classes declared directly in the class `name`.
+    pub fn nested(&self, name: &str) -> impl Iterator<Item = &NestedClass<'c>> {
+        let nested = self.get(name).map(|class| class.nested.as_slice()).unwrap_or_default();
+        nested.iter().filter_map(|name| self.get(name))
+    }
+
+    /// Returns the classes `name` is declared in, innermost first, ending with the root.
+    pub fn outers(&self, name: &str) -> Vec<&NestedClass<'c>> {
+        let mut outers = Vec::new();
+        let mut current = self.get(name).and_then(|class| class.outer);
+        while let Some(class) = current.and_then(|name| self.get(name)) {
+            outers.push(class);
+            current = class.outer;
+        }
+        outers
+    }
+
+    /// Returns the access flags of the class `name` as declared in the source.
+    pub fn access_flags(&self, name: &str) -> Option<u16> {
+        self.get(name).map(|class| class.access_flags)
+    }
+
+    /// Returns the nested classes the root and their own NestHost attribute disagree on:
+    /// those listed by the NestMembers attribute of the root but naming another host, and
+    /// those naming the root but not listed. Classes without a class file are not checked.
+    pub fn nest_mismatches(&self) -> Vec<&NestedClass<'c>> {
+        let root = self.root().name;
+        self.classes[1..]
+            .iter()
+            .filter(|class| class.has_class_file && class.in_nest_members != (class.nest_host == Some(root)))
+            .collect()
+    }
+
+    /// Returns the synthetic accessors of the classes of the tree, in model order.
+    pub fn synthetic_accessors(&self) -> &[SyntheticAccessor<'c>] {
+        &self.accessors
+    }
+
+    /// Returns the accessors reaching the member `name` and `descriptor` of the class `owner`.
+    pub fn accessors_of<'m>(
+        &'m self,
+        owner: &'m str,
+        name: &'m str,
+        descriptor: &'m str,
+    ) -> impl Iterator<Item = &'m SyntheticAccessor<'c>> {
+        self.accessors
+            .iter()
+            .filter(move |accessor| accessor.owner == owner && accessor.target == (name, descriptor))
+    }
+}
+
+fn inner_entries<'c>(class_file: &'c JavaClassFile) -> impl Iterator<Item = InnerEntry<'c>> {
+    let constant_pool = &class_file.constant_pool;
+    class_file.inner_classes().iter().filter_map(move |entry| {
+        Some(InnerEntry {
+            inner: get_class_name(constant_pool, entry.inner_class_info_index as usize)?,
+            outer: get_class_name(constant_pool, entry.outer_class_info_index as usize),
+            simple_name: get_utf8(constant_pool, entry.inner_name_index as usize),
+            access_flags: entry.inner_class_access_flags,
+        })
+    })
+}
+
+/// Calls `visit` with the opcode and member reference of each field and method instruction
+/// of `method`.
+fn visit_member_instructions<'c>(
+    class_file: &'c JavaClassFile,
+    method: &MethodInfo,
+    mut visit: impl FnMut(Opcode, (&'c str, &'c str, &'c str)),
+) {
+    let Ok(Some(code)) = CodeView::new(method, &class_file.constant_pool) else {
+        return;
+    };
+    let mut pc = 0;
+    while let Some(length) = instruction_length(code.code, pc) {
+        if let (Some(opcode), Some(ResolvedOperand::Member(member))) =
+            (Opcode::from_u8(code.code[pc]), resolve_operand(class_file, code.code, pc))
+        {
+            visit(opcode, (member.owner, member.name, member.descriptor));
+        }
+        pc += length;
+    }
+}
+
+/// Finds the static synthetic `access$` methods of `class_file` and the members of its own
+/// they reach. Callers are left empty.
+fn synthetic_accessors<'c>(owner: &'c str, class_file: &'c JavaClassFile) -> Vec<SyntheticAccessor<'c>> {
+    let constant_pool = &class_file.constant_pool;
+    let mut accessors = Vec::new();
+    for method in &class_file.methods {
+        let Some(name) = get_utf8(constant_pool, method.name_index) else {
+            continue;
+        };
+        if !name.starts_with(ACCESSOR_PREFIX)
+            || !method.is_synthetic(constant_pool)
+            || !MethodAccessFlag::Static.test(method.access_flags)
+        {
+            continue;
+        }
+        let Some(descriptor) = get_utf8(constant_pool, method.descriptor_index) else {
+            continue;
+        };
+        let mut reached: Option<((&str, &str), AccessKind)> = None;
+        visit_member_instructions(class_file, method, |opcode, (class, name, descriptor)| {
+            if class != owner {
+                return;
+            }
+            let kind = match opcode {
+                Opcode::Getstatic | Opcode::Getfield => AccessKind::Get,
+                Opcode::Putstatic | Opcode::Putfield => AccessKind::Set,
+                _ => AccessKind::Invoke,
+            };
+            // The first member reached is the target; reading and writing it is an update.
+            reached = match reached {
+                None => Some(((name, descriptor), kind)),
+                Some((target, previous))
+                    if target == (name, descriptor)
+                        && previous != kind
+                        && previous != AccessKind::Invoke
+                        && kind != AccessKind::Invoke =>
+                {
+                    Some((target, AccessKind::Update))
+                }
+                reached => reached,
+            };
+        });
+        if let Some((target, kind)) = reached {
+            accessors.push(SyntheticAccessor { owner, name, descriptor, target, kind, callers: Vec::new() });
+        }
+    }
+    accessors
+}
+
+/// Returns the methods invoked by invokestatic in the code of `class_file`.
+fn invoked_statics<'c>(class_file: &'c JavaClassFile) -> Vec<(&'c str, &'c str, &'c str)> {
+    let mut invoked = Vec::new();
+    for method in &class_file.methods {
+        visit_member_instructions(class_file, method, |opcode, member| {
+            if opcode == Opcode::Invokestatic {
+                invoked.push(member);
+            }
+        });
+    }
+    invoked
+}
@@ -0,0 +1,839 @@
+/// Opcodes of the Java Virtual Machine instruction set.
+///
+/// The reserved opcodes (breakpoint, impdep1 and impdep2) are not included since they
+/// must not appear in a class file.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-6.html#jvms-6.5
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Opcode {
+    Nop = 0x00,
+    AconstNull = 0x01,
+    IconstM1 = 0x02,
+    Iconst0 = 0x03,
+    Iconst1 = 0x04,
+    Iconst2 = 0x05,
+    Iconst3 = 0x06,
+    Iconst4 = 0x07,
+    Iconst5 = 0x08,
+    Lconst0 = 0x09,
+    Lconst1 = 0x0A,
+    Fconst0 = 0x0B,
+    Fconst1 = 0x0C,
+    Fconst2 = 0x0D,
+    Dconst0 = 0x0E,
+    Dconst1 = 0x0F,
+    Bipush = 0x10,
+    Sipush = 0x11,
+    Ldc = 0x12,
+    LdcW = 0x13,
+    Ldc2W = 0x14,
+    Iload = 0x15,
+    Lload = 0x16,
+    Fload = 0x17,
+    Dload = 0x18,
+    Aload = 0x19,
+    Iload0 = 0x1A,
+    Iload1 = 0x1B,
+    Iload2 = 0x1C,
+    Iload3 = 0x1D,
+    Lload0 = 0x1E,
+    Lload1 = 0x1F,
+    Lload2 = 0x20,
+    Lload3 = 0x21,
+    Fload0 = 0x22,
+    Fload1 = 0x23,
+    Fload2 = 0x24,
+    Fload3 = 0x25,
+    Dload0 = 0x26,
+    Dload1 = 0x27,
+    Dload2 = 0x28,
+    Dload3 = 0x29,
+    Aload0 = 0x2A,
+    Aload1 = 0x2B,
+    Aload2 = 0x2C,
+    Aload3 = 0x2D,
+    Iaload = 0x2E,
+    Laload = 0x2F,
+    Faload = 0x30,
+    Daload = 0x31,
+    Aaload = 0x32,
+    Baload = 0x33,
+    Caload = 0x34,
+    Saload = 0x35,
+    Istore = 0x36,
+    Lstore = 0x37,
+    Fstore = 0x38,
+    Dstore = 0x39,
+    Astore = 0x3A,
+    Istore0 = 0x3B,
+    Istore1 = 0x3C,
+    Istore2 = 0x3D,
+    Istore3 = 0x3E,
+    Lstore0 = 0x3F,
+    Lstore1 = 0x40,
+    Lstore2 = 0x41,
+    Lstore3 = 0x42,
+    Fstore0 = 0x43,
+    Fstore1 = 0x44,
+    Fstore2 = 0x45,
+    Fstore3 = 0x46,
+    Dstore0 = 0x47,
+    Dstore1 = 0x48,
+    Dstore2 = 0x49,
+    Dstore3 = 0x4A,
+    Astore0 = 0x4B,
+    Astore1 = 0x4C,
+    Astore2 = 0x4D,
+    Astore3 = 0x4E,
+    Iastore = 0x4F,
+    Lastore = 0x50,
+    Fastore = 0x51,
+    Dastore = 0x52,
+    Aastore = 0x53,
+    Bastore = 0x54,
+    Castore = 0x55,
+    Sastore = 0x56,
+    Pop = 0x57,
+    Pop2 = 0x58,
+    Dup = 0x59,
+    DupX1 = 0x5A,
+    DupX2 = 0x5B,
+    Dup2 = 0x5C,
+    Dup2X1 = 0x5D,
+    Dup2X2 = 0x5E,
+    Swap = 0x5F,
+    Iadd = 0x60,
+    Ladd = 0x61,
+    Fadd = 0x62,
+    Dadd = 0x63,
+    Isub = 0x64,
+    Lsub = 0x65,
+    Fsub = 0x66,
+    Dsub = 0x67,
+    Imul = 0x68,
+    Lmul = 0x69,
+    Fmul = 0x6A,
+    Dmul = 0x6B,
+    Idiv = 0x6C,
+    Ldiv = 0x6D,
+    Fdiv = 0x6E,
+    Ddiv = 0x6F,
+    Irem = 0x70,
+    Lrem = 0x71,
+    Frem = 0x72,
+    Drem = 0x73,
+    Ineg = 0x74,
+    Lneg = 0x75,
+    Fneg = 0x76,
+    Dneg = 0x77,
+    Ishl = 0x78,
+    Lshl = 0x79,
+    Ishr = 0x7A,
+    Lshr = 0x7B,
+    Iushr = 0x7C,
+    Lushr = 0x7D,
+    Iand = 0x7E,
+    Land = 0x7F,
+    Ior = 0x80,
+    Lor = 0x81,
+    Ixor = 0x82,
+    Lxor = 0x83,
+    Iinc = 0x84,
+    I2l = 0x85,
+    I2f = 0x86,
+    I2d = 0x87,
+    L2i = 0x88,
+    L2f = 0x89,
+    L2d = 0x8A,
+    F2i = 0x8B,
+    F2l = 0x8C,
+    F2d = 0x8D,
+    D2i = 0x8E,
+    D2l = 0x8F,
+    D2f = 0x90,
+    I2b = 0x91,
+    I2c = 0x92,
+    I2s = 0x93,
+    Lcmp = 0x94,
+    Fcmpl = 0x95,
+    Fcmpg = 0x96,
+    Dcmpl = 0x97,
+    Dcmpg = 0x98,
+    Ifeq = 0x99,
+    Ifne = 0x9A,
+    Iflt = 0x9B,
+    Ifge = 0x9C,
+    Ifgt = 0x9D,
+    Ifle = 0x9E,
+    IfIcmpeq = 0x9F,
+    IfIcmpne = 0xA0,
+    IfIcmplt = 0xA1,
+    IfIcmpge = 0xA2,
+    IfIcmpgt = 0xA3,
+    IfIcmple = 0xA4,
+    IfAcmpeq = 0xA5,
+    IfAcmpne = 0xA6,
+    Goto = 0xA7,
+    Jsr = 0xA8,
+    Ret = 0xA9,
+    Tableswitch = 0xAA,
+    Lookupswitch = 0xAB,
+    Ireturn = 0xAC,
+    Lreturn = 0xAD,
+    Freturn = 0xAE,
+    Dreturn = 0xAF,
+    Areturn = 0xB0,
+    Return = 0xB1,
+    Getstatic = 0xB2,
+    Putstatic = 0xB3,
+    Getfield = 0xB4,
+    Putfield = 0xB5,
+    Invokevirtual = 0xB6,
+    Invokespecial = 0xB7,
+    Invokestatic = 0xB8,
+    Invokeinterface = 0xB9,
+    Invokedynamic = 0xBA,
+    New = 0xBB,
+    Newarray = 0xBC,
+    Anewarray = 0xBD,
+    Arraylength = 0xBE,
+    Athrow = 0xBF,
+    Checkcast = 0xC0,
+    Instanceof = 0xC1,
+    Monitorenter = 0xC2,
+    Monitorexit = 0xC3,
+    Wide = 0xC4,
+    Multianewarray = 0xC5,
+    Ifnull = 0xC6,
+    Ifnonnull = 0xC7,
+    GotoW = 0xC8,
+    JsrW = 0xC9,
+}
+
+/// Coarse classification of opcodes returned by [`Opcode::category`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OpcodeCategory {
+    /// Pushes a local variable, array element or constant onto the operand stack.
+    Load,
+    /// Stores the top of the operand stack into a local variable or array element.
+    Store,
+    /// Arithmetic, bitwise and shift operations, including iinc.
+    Arithmetic,
+    /// Primitive widening and narrowing conversions.
+    Conversion,
+    /// Comparisons of long, float and double values pushing an int result.
+    Compare,
+    /// Conditional and unconditional jumps, switches and subroutines.
+    Branch,
+    /// Method invocations.
+    Invoke,
+    /// Reads and writes of static and instance fields.
+    FieldAccess,
+    /// Creation of objects and arrays.
+    ObjectCreation,
+    /// Operand stack manipulation.
+    Stack,
+    /// Returns from the current method.
+    Return,
+    /// Everything else, e.g. nop, athrow, checkcast and monitors.
+    Other,
+}
+
+impl Opcode {
+    /// Returns the opcode for a byte, or `None` for unassigned and reserved values.
+    pub fn from_u8(value: u8) -> Option<Self> {
+        let opcode = match value {
+            0x00 => Opcode::Nop,
+            0x01 => Opcode::AconstNull,
+            0x02 => Opcode::IconstM1,
+            0x03 => Opcode::Iconst0,
+            0x04 => Opcode::Iconst1,
+            0x05 => Opcode::Iconst2,
+            0x06 => Opcode::Iconst3,
+            0x07 => Opcode::Iconst4,
+            0x08 => Opcode::Iconst5,
+            0x09 => Opcode::Lconst0,
+            0x0A => Opcode::Lconst1,
+            0x0B => Opcode::Fconst0,
+            0x0C => Opcode::Fconst1,
+            0x0D => Opcode::Fconst2,
+            0x0E => Opcode::Dconst0,
+            0x0F => Opcode::Dconst1,
+            0x10 => Opcode::Bipush,
+            0x11 => Opcode::Sipush,
+            0x12 => Opcode::Ldc,
+            0x13 => Opcode::LdcW,
+            0x14 => Opcode::Ldc2W,
+            0x15 => Opcode::Iload,
+            0x16 => Opcode::Lload,
+            0x17 => Opcode::Fload,
+            0x18 => Opcode::Dload,
+            0x19 => Opcode::Aload,
+            0x1A => Opcode::Iload0,
+            0x1B => Opcode::Iload1,
+            0x1C => Opcode::Iload2,
+            0x1D => Opcode::Iload3,
+            0x1E => Opcode::Lload0,
+            0x1F => Opcode::Lload1,
+            0x20 => Opcode::Lload2,
+            0x21 => Opcode::Lload3,
+            0x22 => Opcode::Fload0,
+            0x23 => Opcode::Fload1,
+            0x24 => Opcode::Fload2,
+            0x25 => Opcode::Fload3,
+            0x26 => Opcode::Dload0,
+            0x27 => Opcode::Dload1,
+            0x28 => Opcode::Dload2,
+            0x29 => Opcode::Dload3,
+            0x2A => Opcode::Aload0,
+            0x2B => Opcode::Aload1,
+            0x2C => Opcode::Aload2,
+            0x2D => Opcode::Aload3,
+            0x2E => Opcode::Iaload,
+            0x2F => Opcode::Laload,
+            0x30 => Opcode::Faload,
+            0x31 => Opcode::Daload,
+            0x32 => Opcode::Aaload,
+            0x33 => Opcode::Baload,
+            0x34 => Opcode::Caload,
+            0x35 => Opcode::Saload,
+            0x36 => Opcode::Istore,
+            0x37 => Opcode::Lstore,
+            0x38 => Opcode::Fstore,
+            0x39 => Opcode::Dstore,
+            0x3A => Opcode::Astore,
+            0x3B => Opcode::Istore0,
+            0x3C => Opcode::Istore1,
+            0x3D => Opcode::Istore2,
+            0x3E => Opcode::Istore3,
+            0x3F => Opcode::Lstore0,
+            0x40 => Opcode::Lstore1,
+            0x41 => Opcode::Lstore2,
+            0x42 => Opcode::Lstore3,
+            0x43 => Opcode::Fstore0,
+            0x44 => Opcode::Fstore1,
+            0x45 => Opcode::Fstore2,
+            0x46 => Opcode::Fstore3,
+            0x47 => Opcode::Dstore0,
+            0x48 => Opcode::Dstore1,
+            0x49 => Opcode::Dstore2,
+            0x4A => Opcode::Dstore3,
+            0x4B => Opcode::Astore0,
+            0x4C => Opcode::Astore1,
+            0x4D => Opcode::Astore2,
+            0x4E => Opcode::Astore3,
+            0x4F => Opcode::Iastore,
+            0x50 => Opcode::Lastore,
+            0x51 => Opcode::Fastore,
+            0x52 => Opcode::Dastore,
+            0x53 => Opcode::Aastore,
+            0x54 => Opcode::Bastore,
+            0x55 => Opcode::Castore,
+            0x56 => Opcode::Sastore,
+            0x57 => Opcode::Pop,
+            0x58 => Opcode::Pop2,
+            0x59 => Opcode::Dup,
+            0x5A => Opcode::DupX1,
+            0x5B => Opcode::DupX2,
+            0x5C => Opcode::Dup2,
+            0x5D => Opcode::Dup2X1,
+            0x5E => Opcode::Dup2X2,
+            0x5F => Opcode::Swap,
+            0x60 => Opcode::Iadd,
+            0x61 => Opcode::Ladd,
+            0x62 => Opcode::Fadd,
+            0x63 => Opcode::Dadd,
+            0x64 => Opcode::Isub,
+            0x65 => Opcode::Lsub,
+            0x66 => Opcode::Fsub,
+            0x67 => Opcode::Dsub,
+            0x68 => Opcode::Imul,
+            0x69 => Opcode::Lmul,
+            0x6A => Opcode::Fmul,
+            0x6B => Opcode::Dmul,
+            0x6C => Opcode::Idiv,
+            0x6D => Opcode::Ldiv,
+            0x6E => Opcode::Fdiv,
+            0x6F => Opcode::Ddiv,
+            0x70 => Opcode::Irem,
+            0x71 => Opcode::Lrem,
+            0x72 => Opcode::Frem,
+            0x73 => Opcode::Drem,
+            0x74 => Opcode::Ineg,
+            0x75 => Opcode::Lneg,
+            0x76 => Opcode::Fneg,
+            0x77 => Opcode::Dneg,
+            0x78 => Opcode::Ishl,
+            0x79 => Opcode::Lshl,
+            0x7A => Opcode::Ishr,
+            0x7B => Opcode::Lshr,
+            0x7C => Opcode::Iushr,
+            0x7D => Opcode::Lushr,
+            0x7E => Opcode::Iand,
+            0x7F => Opcode::Land,
+            0x80 => Opcode::Ior,
+            0x81 => Opcode::Lor,
+            0x82 => Opcode::Ixor,
+            0x83 => Opcode::Lxor,
+            0x84 => Opcode::Iinc,
+            0x85 => Opcode::I2l,
+            0x86 => Opcode::I2f,
+            0x87 => Opcode::I2d,
+            0x88 => Opcode::L2i,
+            0x89 => Opcode::L2f,
+            0x8A => Opcode::L2d,
+            0x8B => Opcode::F2i,
+            0x8C => Opcode::F2l,
+            0x8D => Opcode::F2d,
+            0x8E => Opcode::D2i,
+            0x8F => Opcode::D2l,
+            0x90 => Opcode::D2f,
+            0x91 => Opcode::I2b,
+            0x92 => Opcode::I2c,
+            0x93 => Opcode::I2s,
+            0x94 => Opcode::Lcmp,
+            0x95 => Opcode::Fcmpl,
+            0x96 => Opcode::Fcmpg,
+            0x97 => Opcode::Dcmpl,
+            0x98 => Opcode::Dcmpg,
+            0x99 => Opcode::Ifeq,
+            0x9A => Opcode::Ifne,
+            0x9B => Opcode::Iflt,
+            0x9C => Opcode::Ifge,
+            0x9D => Opcode::Ifgt,
+            0x9E => Opcode::Ifle,
+            0x9F => Opcode::IfIcmpeq,
+            0xA0 => Opcode::IfIcmpne,
+            0xA1 => Opcode::IfIcmplt,
+            0xA2 => Opcode::IfIcmpge,
+            0xA3 => Opcode::IfIcmpgt,
+            0xA4 => Opcode::IfIcmple,
+            0xA5 => Opcode::IfAcmpeq,
+            0xA6 => Opcode::IfAcmpne,
+            0xA7 => Opcode::Goto,
+            0xA8 => Opcode::Jsr,
+            0xA9 => Opcode::Ret,
+            0xAA => Opcode::Tableswitch,
+            0xAB => Opcode::Lookupswitch,
+            0xAC => Opcode::Ireturn,
+            0xAD => Opcode::Lreturn,
+            0xAE => Opcode::Freturn,
+            0xAF => Opcode::Dreturn,
+            0xB0 => Opcode::Areturn,
+            0xB1 => Opcode::Return,
+            0xB2 => Opcode::Getstatic,
+            0xB3 => Opcode::Putstatic,
+            0xB4 => Opcode::Getfield,
+            0xB5 => Opcode::Putfield,
+            0xB6 => Opcode::Invokevirtual,
+            0xB7 => Opcode::Invokespecial,
+            0xB8 => Opcode::Invokestatic,
+            0xB9 => Opcode::Invokeinterface,
+            0xBA => Opcode::Invokedynamic,
+            0xBB => Opcode::New,
+            0xBC => Opcode::Newarray,
+            0xBD => Opcode::Anewarray,
+            0xBE => Opcode::Arraylength,
+            0xBF => Opcode::Athrow,
+            0xC0 => Opcode::Checkcast,
+            0xC1 => Opcode::Instanceof,
+            0xC2 => Opcode::Monitorenter,
+            0xC3 => Opcode::Monitorexit,
+            0xC4 => Opcode::Wide,
+            0xC5 => Opcode::Multianewarray,
+            0xC6 => Opcode::Ifnull,
+            0xC7 => Opcode::Ifnonnull,
+            0xC8 => Opcode::GotoW,
+            0xC9 => Opcode::JsrW,
+            _ => return None,
+        };
+        Some(opcode)
+    }
+
+    /// Returns the mnemonic used by the JVM specification, e.g. `invokevirtual`.
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Opcode::Nop => "nop",
+            Opcode::AconstNull => "aconst_null",
+            Opcode::IconstM1 => "iconst_m1",
+            Opcode::Iconst0 => "iconst_0",
+            Opcode::Iconst1 => "iconst_1",
+            Opcode::Iconst2 => "iconst_2",
+            Opcode::Iconst3 => "iconst_3",
+            Opcode::Iconst4 => "iconst_4",
+            Opcode::Iconst5 => "iconst_5",
+            Opcode::Lconst0 => "lconst_0",
+            Opcode::Lconst1 => "lconst_1",
+            Opcode::Fconst0 => "fconst_0",
+            Opcode::Fconst1 => "fconst_1",
+            Opcode::Fconst2 => "fconst_2",
+            Opcode::Dconst0 => "dconst_0",
+            Opcode::Dconst1 => "dconst_1",
+            Opcode::Bipush => "bipush",
+            Opcode::Sipush => "sipush",
+            Opcode::Ldc => "ldc",
+            Opcode::LdcW => "ldc_w",
+            Opcode::Ldc2W => "ldc2_w",
+            Opcode::Iload => "iload",
+            Opcode::Lload => "lload",
+            Opcode::Fload => "fload",
+            Opcode::Dload => "dload",
+            Opcode::Aload => "aload",
+            Opcode::Iload0 => "iload_0",
+            Opcode::Iload1 => "iload_1",
+            Opcode::Iload2 => "iload_2",
+            Opcode::Iload3 => "iload_3",
+            Opcode::Lload0 => "lload_0",
+            Opcode::Lload1 => "lload_1",
+            Opcode::Lload2 => "lload_2",
+            Opcode::Lload3 => "lload_3",
+            Opcode::Fload0 => "fload_0",
+            Opcode::Fload1 => "fload_1",
+            Opcode::Fload2 => "fload_2",
+            Opcode::Fload3 => "fload_3",
+            Opcode::Dload0 => "dload_0",
+            Opcode::Dload1 => "dload_1",
+            Opcode::Dload2 => "dload_2",
+            Opcode::Dload3 => "dload_3",
+            Opcode::Aload0 => "aload_0",
+            Opcode::Aload1 => "aload_1",
+            Opcode::Aload2 => "aload_2",
+            Opcode::Aload3 => "aload_3",
+            Opcode::Iaload => "iaload",
+            Opcode::Laload => "laload",
+            Opcode::Faload => "faload",
+            Opcode::Daload => "daload",
+            Opcode::Aaload => "aaload",
+            Opcode::Baload => "baload",
+            Opcode::Caload => "caload",
+            Opcode::Saload => "saload",
+            Opcode::Istore => "istore",
+            Opcode::Lstore => "lstore",
+            Opcode::Fstore => "fstore",
+            Opcode::Dstore => "dstore",
+            Opcode::Astore => "astore",
+            Opcode::Istore0 => "istore_0",
+            Opcode::Istore1 => "istore_1",
+            Opcode::Istore2 => "istore_2",
+            Opcode::Istore3 => "istore_3",
+            Opcode::Lstore0 => "lstore_0",
+            Opcode::Lstore1 => "lstore_1",
+            Opcode::Lstore2 => "lstore_2",
+            Opcode::Lstore3 => "lstore_3",
+            Opcode::Fstore0 => "fstore_0",
+            Opcode::Fstore1 => "fstore_1",
+            Opcode::Fstore2 => "fstore_2",
+            Opcode::Fstore3 => "fstore_3",
+            Opcode::Dstore0 => "dstore_0",
+            Opcode::Dstore1 => "dstore_1",
+            Opcode::Dstore2 => "dstore_2",
+            Opcode::Dstore3 => "dstore_3",
+            Opcode::Astore0 => "astore_0",
+            Opcode::Astore1 => "astore_1",
+            Opcode::Astore2 => "astore_2",
+            Opcode::Astore3 => "astore_3",
+            Opcode::Iastore => "iastore",
+            Opcode::Lastore => "lastore",
+            Opcode::Fastore => "fastore",
+            Opcode::Dastore => "dastore",
+            Opcode::Aastore => "aastore",
+            Opcode::Bastore => "bastore",
+            Opcode::Castore => "castore",
+            Opcode::Sastore => "sastore",
+            Opcode::Pop => "pop",
+            Opcode::Pop2 => "pop2",
+            Opcode::Dup => "dup",
+            Opcode::DupX1 => "dup_x1",
+            Opcode::DupX2 => "dup_x2",
+            Opcode::Dup2 => "dup2",
+            Opcode::Dup2X1 => "dup2_x1",
+            Opcode::Dup2X2 => "dup2_x2",
+            Opcode::Swap => "swap",
+            Opcode::Iadd => "iadd",
+            Opcode::Ladd => "ladd",
+            Opcode::Fadd => "fadd",
+            Opcode::Dadd => "dadd",
+            Opcode::Isub => "isub",
+            Opcode::Lsub => "lsub",
+            Opcode::Fsub => "fsub",
+            Opcode::Dsub => "dsub",
+            Opcode::Imul => "imul",
+            Opcode::Lmul => "lmul",
+            Opcode::Fmul => "fmul",
+            Opcode::Dmul => "dmul",
+            Opcode::Idiv => "idiv",
+            Opcode::Ldiv => "ldiv",
+            Opcode::Fdiv => "fdiv",
+            Opcode::Ddiv => "ddiv",
+            Opcode::Irem => "irem",
+            Opcode::Lrem => "lrem",
+            Opcode::Frem => "frem",
+            Opcode::Drem => "drem",
+            Opcode::Ineg => "ineg",
+            Opcode::Lneg => "lneg",
+            Opcode::Fneg => "fneg",
+            Opcode::Dneg => "dneg",
+            Opcode::Ishl => "ishl",
+            Opcode::Lshl => "lshl",
+            Opcode::Ishr => "ishr",
+            Opcode::Lshr => "lshr",
+            Opcode::Iushr => "iushr",
+            Opcode::Lushr => "lushr",
+            Opcode::Iand => "iand",
+            Opcode::Land => "land",
+            Opcode::Ior => "ior",
+            Opcode::Lor => "lor",
+            Opcode::Ixor => "ixor",
+            Opcode::Lxor => "lxor",
+            Opcode::Iinc => "iinc",
+            Opcode::I2l => "i2l",
+            Opcode::I2f => "i2f",
+            Opcode::I2d => "i2d",
+            Opcode::L2i => "l2i",
+            Opcode::L2f => "l2f",
+            Opcode::L2d => "l2d",
+            Opcode::F2i => "f2i",
+            Opcode::F2l => "f2l",
+            Opcode::F2d => "f2d",
+            Opcode::D2i => "d2i",
+            Opcode::D2l => "d2l",
+            Opcode::D2f => "d2f",
+            Opcode::I2b => "i2b",
+            Opcode::I2c => "i2c",
+            Opcode::I2s => "i2s",
+            Opcode::Lcmp => "lcmp",
+            Opcode::Fcmpl => "fcmpl",
+            Opcode::Fcmpg => "fcmpg",
+            Opcode::Dcmpl => "dcmpl",
+            Opcode::Dcmpg => "dcmpg",
+            Opcode::Ifeq => "ifeq",
+            Opcode::Ifne => "ifne",
+            Opcode::Iflt => "iflt",
+            Opcode::Ifge => "ifge",
+            Opcode::Ifgt => "ifgt",
+            Opcode::Ifle => "ifle",
+            Opcode::IfIcmpeq => "if_icmpeq",
+            Opcode::IfIcmpne => "if_icmpne",
+            Opcode::IfIcmplt => "if_icmplt",
+            Opcode::IfIcmpge => "if_icmpge",
+            Opcode::IfIcmpgt => "if_icmpgt",
+            Opcode::IfIcmple => "if_icmple",
+            Opcode::IfAcmpeq => "if_acmpeq",
+            Opcode::IfAcmpne => "if_acmpne",
+            Opcode::Goto => "goto",
+            Opcode::Jsr => "jsr",
+            Opcode::Ret => "ret",
+            Opcode::Tableswitch => "tableswitch",
+            Opcode::Lookupswitch => "lookupswitch",
+            Opcode::Ireturn => "ireturn",
+            Opcode::Lreturn => "lreturn",
+            Opcode::Freturn => "freturn",
+            Opcode::Dreturn => "dreturn",
+            Opcode::Areturn => "areturn",
+            Opcode::Return => "return",
+            Opcode::Getstatic => "getstatic",
+            Opcode::Putstatic => "putstatic",
+            Opcode::Getfield => "getfield",
+            Opcode::Putfield => "putfield",
+            Opcode::Invokevirtual => "invokevirtual",
+            Opcode::Invokespecial => "invokespecial",
+            Opcode::Invokestatic => "invokestatic",
+            Opcode::Invokeinterface => "invokeinterface",
+            Opcode::Invokedynamic => "invokedynamic",
+            Opcode::New => "new",
+            Opcode::Newarray => "newarray",
+            Opcode::Anewarray => "anewarray",
+            Opcode::Arraylength => "arraylength",
+            Opcode::Athrow => "athrow",
+            Opcode::Checkcast => "checkcast",
+            Opcode::Instanceof => "instanceof",
+            Opcode::Monitorenter => "monitorenter",
+            Opcode::Monitorexit => "monitorexit",
+            Opcode::Wide => "wide",
+            Opcode::Multianewarray => "multianewarray",
+            Opcode::Ifnull => "ifnull",
+            Opcode::Ifnonnull => "ifnonnull",
+            Opcode::GotoW => "goto_w",
+            Opcode::JsrW => "jsr_w",
+        }
+    }
+
+    /// Returns the category of this opcode.
+    pub fn category(&self) -> OpcodeCategory {
+        match self {
+            Opcode::AconstNull | Opcode::IconstM1 | Opcode::Iconst0 | Opcode::Iconst1 | Opcode::Iconst2
+            | Opcode::Iconst3 | Opcode::Iconst4 | Opcode::Iconst5 | Opcode::Lconst0 | Opcode::Lconst1
+            | Opcode::Fconst0 | Opcode::Fconst1 | Opcode::Fconst2 | Opcode::Dconst0 | Opcode::Dconst1
+            | Opcode::Bipush | Opcode::Sipush | Opcode::Ldc | Opcode::LdcW | Opcode::Ldc2W | Opcode::Iload
+            | Opcode::Lload | Opcode::Fload | Opcode::Dload | Opcode::Aload | Opcode::Iload0 | Opcode::Iload1
+            | Opcode::Iload2 | Opcode::Iload3 | Opcode::Lload0 | Opcode::Lload1 | Opcode::Lload2
+            | Opcode::Lload3 | Opcode::Fload0 | Opcode::Fload1 | Opcode::Fload2 | Opcode::Fload3
+            | Opcode::Dload0 | Opcode::Dload1 | Opcode::Dload2 | Opcode::Dload3 | Opcode::Aload0
+            | Opcode::Aload1 | Opcode::Aload2 | Opcode::Aload3 | Opcode::Iaload | Opcode::Laload
+            | Opcode::Faload | Opcode::Daload | Opcode::Aaload | Opcode::Baload | Opcode::Caload
+            | Opcode::Saload => OpcodeCategory::Load,
+            Opcode::Istore | Opcode::Lstore | Opcode::Fstore | Opcode::Dstore | Opcode::Astore | Opcode::Istore0
+            | Opcode::Istore1 | Opcode::Istore2 | Opcode::Istore3 | Opcode::Lstore0 | Opcode::Lstore1
+            | Opcode::Lstore2 | Opcode::Lstore3 | Opcode::Fstore0 | Opcode::Fstore1 | Opcode::Fstore2
+            | Opcode::Fstore3 | Opcode::Dstore0 | Opcode::Dstore1 | Opcode::Dstore2 | Opcode::Dstore3
+            | Opcode::Astore0 | Opcode::Astore1 | Opcode::Astore2 | Opcode::Astore3 | Opcode::Iastore
+            | Opcode::Lastore | Opcode::Fastore | Opcode::Dastore | Opcode::Aastore | Opcode::Bastore
+            | Opcode::Castore | Opcode::Sastore => OpcodeCategory::Store,
+            Opcode::Iadd | Opcode::Ladd | Opcode::Fadd | Opcode::Dadd | Opcode::Isub | Opcode::Lsub
+            | Opcode::Fsub | Opcode::Dsub | Opcode::Imul | Opcode::Lmul | Opcode::Fmul | Opcode::Dmul
+            | Opcode::Idiv | Opcode::Ldiv | Opcode::Fdiv | Opcode::Ddiv | Opcode::Irem | Opcode::Lrem
+            | Opcode::Frem | Opcode::Drem | Opcode::Ineg | Opcode::Lneg | Opcode::Fneg | Opcode::Dneg
+            | Opcode::Ishl | Opcode::Lshl | Opcode::Ishr | Opcode::Lshr | Opcode::Iushr | Opcode::Lushr
+            | Opcode::Iand | Opcode::Land | Opcode::Ior | Opcode::Lor | Opcode::Ixor | Opcode::Lxor
+            | Opcode::Iinc => OpcodeCategory::Arithmetic,
+            Opcode::I2l | Opcode::I2f | Opcode::I2d | Opcode::L2i | Opcode::L2f | Opcode::L2d | Opcode::F2i
+            | Opcode::F2l | Opcode::F2d | Opcode::D2i | Opcode::D2l | Opcode::D2f | Opcode::I2b | Opcode::I2c
+            | Opcode::I2s => OpcodeCategory::Conversion,
+            Opcode::Lcmp | Opcode::Fcmpl | Opcode::Fcmpg | Opcode::Dcmpl | Opcode::Dcmpg => OpcodeCategory::Compare,
+            Opcode::Ifeq | Opcode::Ifne | Opcode::Iflt | Opcode::Ifge | Opcode::Ifgt | Opcode::Ifle
+            | Opcode::IfIcmpeq | Opcode::IfIcmpne | Opcode::IfIcmplt | Opcode::IfIcmpge | Opcode::IfIcmpgt
+            | Opcode::IfIcmple | Opcode::IfAcmpeq | Opcode::IfAcmpne | Opcode::Goto | Opcode::Jsr | Opcode::Ret
+            | Opcode::Tableswitch | Opcode::Lookupswitch | Opcode::Ifnull | Opcode::Ifnonnull | Opcode::GotoW
+            | Opcode::JsrW => OpcodeCategory::Branch,
+            Opcode::Invokevirtual | Opcode::Invokespecial | Opcode::Invokestatic | Opcode::Invokeinterface
+            | Opcode::Invokedynamic => OpcodeCategory::Invoke,
+            Opcode::Getstatic | Opcode::Putstatic | Opcode::Getfield | Opcode::Putfield => OpcodeCategory::FieldAccess,
+            Opcode::New | Opcode::Newarray | Opcode::Anewarray | Opcode::Multianewarray => OpcodeCategory::ObjectCreation,
+            Opcode::Pop | Opcode::Pop2 | Opcode::Dup | Opcode::DupX1 | Opcode::DupX2 | Opcode::Dup2
+            | Opcode::Dup2X1 | Opcode::Dup2X2 | Opcode::Swap => OpcodeCategory::Stack,
+            Opcode::Ireturn | Opcode::Lreturn | Opcode::Freturn | Opcode::Dreturn | Opcode::Areturn
+            | Opcode::Return => OpcodeCategory::Return,
+            _ => OpcodeCategory::Other,
+        }
+    }
+}
+
+/// Returns the number of operand bytes of an instruction with a fixed length.
+pub(crate) fn operand_length(opcode: Opcode) -> usize {
+    match opcode {
+        Opcode::Bipush | Opcode::Newarray => 1,
+        Opcode::Iload | Opcode::Lload | Opcode::Fload | Opcode::Dload | Opcode::Aload => 1,
+        Opcode::Istore | Opcode::Lstore | Opcode::Fstore | Opcode::Dstore | Opcode::Astore | Opcode::Ret => 1,
+        Opcode::Sipush | Opcode::Iinc => 2,
+        Opcode::GotoW | Opcode::JsrW => 4,
+        opcode if opcode.category() == OpcodeCategory::Branch => 2,
+        _ => 0,
+    }
+}
+
+/// Returns the length of the instruction at `pc`, or `None` at the end of the code or if
+/// the instruction is malformed or truncated.
+pub(crate) fn instruction_length(code: &[u8], pc: usize) -> Option<usize> {
+    let opcode = Opcode::from_u8(*code.get(pc)?)?;
+    let read_i32 = |at: usize| Some(i32::from_be_bytes(code.get(at..at + 4)?.try_into().ok()?) as i64);
+    let length = match opcode {
+        Opcode::Ldc => 2,
+        Opcode::LdcW
+        | Opcode::Ldc2W
+        | Opcode::Getstatic
+        | Opcode::Putstatic
+        | Opcode::Getfield
+        | Opcode::Putfield
+        | Opcode::Invokevirtual
+        | Opcode::Invokespecial
+        | Opcode::Invokestatic
+        | Opcode::New
+        | Opcode::Anewarray
+        | Opcode::Checkcast
+        | Opcode::Instanceof => 3,
+        Opcode::Multianewarray => 4,
+        Opcode::Invokeinterface | Opcode::Invokedynamic => 5,
+        Opcode::Wide => match Opcode::from_u8(*code.get(pc + 1)?)? {
+            Opcode::Iinc => 6,
+            _ => 4,
+        },
+        Opcode::Tableswitch => {
+            let operands = pc + 1 + switch_padding(pc);
+            let entries = read_i32(operands + 8)? - read_i32(operands + 4)? + 1;
+            operands - pc + 12 + usize::try_from(entries.checked_mul(4)?).ok()?
+        }
+        Opcode::Lookupswitch => {
+            let operands = pc + 1 + switch_padding(pc);
+            operands - pc + 8 + usize::try_from(read_i32(operands + 4)?.checked_mul(8)?).ok()?
+        }
+        opcode => 1 + operand_length(opcode),
+    };
+    (pc + length <= code.len()).then_some(length)
+}
+
+/// Returns the number of padding bytes, 0 to 3, after the opcode of the tableswitch or
+/// lookupswitch at `pc`, which align its operands to a multiple of 4 from the start of the code.
+pub(crate) fn switch_padding(pc: usize) -> usize {
+    (4 - (pc + 1) % 4) % 4
+}
+
+/// Reads the big-endian i32 at `at`.
+fn i32_at(code: &[u8], at: usize) -> Option<i32> {
+    Some(i32::from_be_bytes(code.get(at..at.checked_add(4)?)?.try_into().ok()?))
+}
+
+/// The operands of a tableswitch instruction. Offsets are relative to the instruction.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-6.html#jvms-6.5.tableswitch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableSwitch {
+    pub default: i32,
+    pub low: i32,
+    pub high: i32,
+    /// Offsets of the keys `low` to `high`, in order.
+    pub offsets: Vec<i32>,
+}
+
+impl TableSwitch {
+    /// Decodes the tableswitch at `pc` of `code`, or returns `None` if there is none or it is
+    /// truncated or has `low` greater than `high`.
+    pub fn decode(code: &[u8], pc: usize) -> Option<Self> {
+        if *code.get(pc)? != Opcode::Tableswitch as u8 {
+            return None;
+        }
+        let operands = pc + 1 + switch_padding(pc);
+        let (low, high) = (i32_at(code, operands + 4)?, i32_at(code, operands + 8)?);
+        let entries = usize::try_from(high as i64 - low as i64 + 1).ok()?;
+        // Checked before allocating so that a huge range in a truncated table is rejected.
+        code.get(operands + 12..operands + 12 + entries.checked_mul(4)?)?;
+        Some(Self {
+            default: i32_at(code, operands)?,
+            low,
+            high,
+            offsets: (0..entries).map(|i| i32_at(code, operands + 12 + 4 * i)).collect::<Option<_>>()?,
+        })
+    }
+
+    /// Iterates over the keys and their offsets.
+    pub fn cases(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        (self.low..=self.high).zip(self.offsets.iter().copied())
+    }
+}
+
+/// The operands of a lookupswitch instruction. Offsets are relative to the instruction.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-6.html#jvms-6.5.lookupswitch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LookupSwitch {
+    pub default: i32,
+    /// Keys and their offsets, in class file order, which is by increasing key.
+    pub pairs: Vec<(i32, i32)>,
+}
+
+impl LookupSwitch {
+    /// Decodes the lookupswitch at `pc` of `code`, or returns `None` if there is none or it is
+    /// truncated or has a negative number of pairs.
+    pub fn decode(code: &[u8], pc: usize) -> Option<Self> {
+        if *code.get(pc)? != Opcode::Lookupswitch as u8 {
+            return None;
+        }
+        let operands = pc + 1 + switch_padding(pc);
+        let npairs = usize::try_from(i32_at(code, operands + 4)?).ok()?;
+        code.get(operands + 8..operands + 8 + npairs.checked_mul(8)?)?;
+        let pair = |i: usize| Some((i32_at(code, operands + 8 + 8 * i)?, i32_at(code, operands + 12 + 8 * i)?));
+        Some(Self {
+            default: i32_at(code, operands)?,
+            pairs: (0..npairs).map(pair).collect::<Option<_>>()?,
+        })
+    }
+}
@@ -0,0 +1,153 @@
+//! Constant pool operands of instructions, resolved to the values they refer to.
+//!
+//! ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-6.html
+
+use crate::{
+    opcode::{instruction_length, Opcode},
+    types::*,
+};
+
+/// Whether a CONSTANT_Fieldref, CONSTANT_Methodref or CONSTANT_InterfaceMethodref is referenced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemberKind {
+    Field,
+    Method,
+    InterfaceMethod,
+}
+
+/// A field or method referenced by an instruction or a CONSTANT_MethodHandle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MemberRef<'c> {
+    pub kind: MemberKind,
+    /// Internal name of the class the member is looked up in, e.g. `java/lang/Object`.
+    pub owner: &'c str,
+    pub name: &'c str,
+    pub descriptor: &'c str,
+}
+
+/// A loadable constant, pushed by `ldc`, `ldc_w` or `ldc2_w`.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.4-310
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Constant<'c> {
+    Integer(i32),
+    Float(f32),
+    Long(i64),
+    Double(f64),
+    String(&'c str),
+    /// Name of the class, or descriptor of the array type, e.g. `[I`.
+    Class(&'c str),
+    /// Method descriptor, e.g. `(I)V`.
+    MethodType(&'c str),
+    MethodHandle { reference_kind: u8, member: MemberRef<'c> },
+    /// A CONSTANT_Dynamic, computed by its bootstrap method.
+    Dynamic(BootstrapCall<'c>),
+}
+
+/// The value the constant pool operand of an instruction refers to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResolvedOperand<'c> {
+    /// The constant of `ldc`, `ldc_w` or `ldc2_w`.
+    Constant(Constant<'c>),
+    /// The field of `getstatic`, `putstatic`, `getfield` or `putfield`, or the method of
+    /// `invokevirtual`, `invokespecial`, `invokestatic` or `invokeinterface`.
+    Member(MemberRef<'c>),
+    /// The class of `new`, `checkcast` or `instanceof`, the element class of `anewarray`, or
+    /// the array type of `multianewarray`.
+    Class(&'c str),
+    /// The call site of `invokedynamic`.
+    InvokeDynamic(BootstrapCall<'c>),
+}
+
+/// Resolves the loadable constant at `index`, or returns `None` if the entry is missing, is not
+/// loadable or a reference is invalid.
+pub fn resolve_constant<'c>(class_file: &'c JavaClassFile, index: usize) -> Option<Constant<'c>> {
+    let constant_pool = &class_file.constant_pool;
+    Some(match constant_pool.get(index)? {
+        ConstantPoolInfo::Integer(info) => Constant::Integer(info.data),
+        ConstantPoolInfo::Float(info) => Constant::Float(info.data),
+        ConstantPoolInfo::Long(info) => Constant::Long(info.data),
+        ConstantPoolInfo::Double(info) => Constant::Double(info.data),
+        ConstantPoolInfo::String(info) => Constant::String(get_utf8(constant_pool, info.string_index)?),
+        ConstantPoolInfo::Class(_) => Constant::Class(get_class_name(constant_pool, index)?),
+        ConstantPoolInfo::MethodType(info) => Constant::MethodType(get_utf8(constant_pool, info.descriptor_index)?),
+        ConstantPoolInfo::MethodHandle(info) => Constant::MethodHandle {
+            reference_kind: info.reference_kind,
+            member: resolve_member(constant_pool, info.reference_index)?,
+        },
+        ConstantPoolInfo::Dynamic(_) => Constant::Dynamic(class_file.bootstrap_call(index)?),
+        _ => return None,
+    })
+}
+
+/// Resolves the CONSTANT_Fieldref, CONSTANT_Methodref or CONSTANT_InterfaceMethodref at
+/// `index`, or returns `None` if the entry is of another kind or a reference is invalid.
+pub fn resolve_member<'c>(constant_pool: &'c [ConstantPoolInfo], index: usize) -> Option<MemberRef<'c>> {
+    let kind = match constant_pool.get(index)? {
+        ConstantPoolInfo::FieldRef(_) => MemberKind::Field,
+        ConstantPoolInfo::MethodRef(_) => MemberKind::Method,
+        ConstantPoolInfo::InterfaceMethodRef(_) => MemberKind::InterfaceMethod,
+        _ => return None,
+    };
+    let (owner, name, descriptor) = get_member_ref(constant_pool, index)?;
+    Some(MemberRef { kind, owner, name, descriptor })
+}
+
+/// Resolves the constant pool operand of the instruction at `pc` of `code`, a method of
+/// `class_file`.
+///
+/// Returns `None` if the instruction has no constant pool operand, is truncated, or its operand
+/// is not an entry of the kind the instruction expects. The kind of member is not checked
+/// against the opcode, e.g. `invokestatic` of a CONSTANT_InterfaceMethodref resolves.
+pub fn resolve_operand<'c>(class_file: &'c JavaClassFile, code: &[u8], pc: usize) -> Option<ResolvedOperand<'c>> {
+    let constant_pool = &class_file.constant_pool;
+    let opcode = Opcode::from_u8(*code.get(pc)?)?;
+    let index = match opcode {
+        Opcode::Ldc => *code.get(pc + 1)? as usize,
+        _ => u16::from_be_bytes([*code.get(pc + 1)?, *code.get(pc + 2)?]) as usize,
+    };
+    Some(match opcode {
+        Opcode::Ldc | Opcode::LdcW | Opcode::Ldc2W => {
+            let constant = resolve_constant(class_file, index)?;
+            let wide = match constant {
+                Constant::Long(_) | Constant::Double(_) => true,
+                Constant::Dynamic(call) => matches!(call.descriptor, "J" | "D"),
+                _ => false,
+            };
+            if wide != (opcode == Opcode::Ldc2W) {
+                return None;
+            }
+            ResolvedOperand::Constant(constant)
+        }
+        Opcode::Getstatic
+        | Opcode::Putstatic
+        | Opcode::Getfield
+        | Opcode::Putfield
+        | Opcode::Invokevirtual
+        | Opcode::Invokespecial
+        | Opcode::Invokestatic
+        | Opcode::Invokeinterface => ResolvedOperand::Member(resolve_member(constant_pool, index)?),
+        Opcode::New | Opcode::Anewarray | Opcode::Checkcast | Opcode::Instanceof | Opcode::Multianewarray => {
+            ResolvedOperand::Class(get_class_name(constant_pool, index)?)
+        }
+        Opcode::Invokedynamic => match constant_pool.get(index)? {
+            ConstantPoolInfo::InvokeDynamic(_) => ResolvedOperand::InvokeDynamic(class_file.bootstrap_call(index)?),
+            _ => return None,
+        },
+        _ => return None,
+    })
+}
+
+/// Lists the instructions of `code` whose constant pool operand resolves, with their offsets,
+/// in code order.
+pub fn resolve_operands<'c>(class_file: &'c JavaClassFile, code: &[u8]) -> Vec<(usize, ResolvedOperand<'c>)> {
+    let mut operands = Vec::new();
+    let mut pc = 0;
+    while let Some(length) = instruction_length(code, pc) {
+        if let Some(operand) = resolve_operand(class_file, code, pc) {
+            operands.push((pc, operand));
+        }
+        pc += length;
+    }
+    operands
+}
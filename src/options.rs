@@ -0,0 +1,126 @@
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use crate::{
+    types::{ConstantPoolInfo, UserAttribute},
+    validate::ValidationMode,
+};
+
+/// What to do when an attribute which may appear at most once is repeated, e.g. a
+/// method with two Code attributes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateAttributePolicy {
+    /// Fail with [`DecodeError::DuplicateAttribute`](crate::DecodeError::DuplicateAttribute), as the JVM does.
+    #[default]
+    Error,
+    /// Keep the first occurrence and report a diagnostic.
+    KeepFirst,
+    /// Keep the last occurrence and report a diagnostic.
+    KeepLast,
+    /// Keep every occurrence, in order, and report a diagnostic, so that the class file
+    /// round-trips. Attributes which may be repeated, e.g. LineNumberTable, and those not
+    /// defined by the JVMS are always handled this way.
+    KeepAll,
+}
+
+/// What to do when the constant pool has entries identical to an earlier entry, as some
+/// code generators emit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateConstantPolicy {
+    /// Accept them without checking.
+    #[default]
+    Allow,
+    /// Accept them and report a diagnostic for each.
+    Report,
+    /// Fail with [`DecodeError::DuplicateConstant`](crate::DecodeError::DuplicateConstant).
+    Error,
+}
+
+/// A decoder registered with [`ParseOptions::register_attribute`].
+type AttributeDecoder = Arc<dyn Fn(&[u8], &[ConstantPoolInfo]) -> Result<Box<dyn UserAttribute>, String> + Send + Sync>;
+
+/// Decoders of non-standard attributes by name, shared by copies of the options.
+#[derive(Clone, Default)]
+pub(crate) struct AttributeDecoders(Arc<HashMap<String, AttributeDecoder>>);
+
+impl AttributeDecoders {
+    pub(crate) fn get(&self, name: &str) -> Option<&AttributeDecoder> {
+        self.0.get(name)
+    }
+}
+
+impl fmt::Debug for AttributeDecoders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<&String> = self.0.keys().collect();
+        names.sort();
+        f.debug_set().entries(names).finish()
+    }
+}
+
+/// Options for decoding a Java class file.
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// Maximum number of bytes the decoded structures may allocate in total.
+    ///
+    /// Counts in the class file are checked against this budget before anything is
+    /// allocated, so adversarial inputs fail with an error instead of exhausting memory.
+    /// The budget is a hard cap on the tables, strings and attribute names decoding creates,
+    /// which makes it usable as the heap limit of memory-constrained targets; exceeding it
+    /// fails with [`DecodeError::AllocationLimitExceeded`](crate::DecodeError::AllocationLimitExceeded),
+    /// even in lenient mode. Only small bookkeeping, e.g. the location of diagnostics, is not
+    /// counted.
+    pub max_allocation: usize,
+    pub duplicate_attributes: DuplicateAttributePolicy,
+    pub duplicate_constants: DuplicateConstantPolicy,
+    /// Whether bytes after the class file structure are an error (strict) or kept in
+    /// [`JavaClassFile::trailing_bytes`](crate::types::JavaClassFile::trailing_bytes) (lenient).
+    ///
+    /// In lenient mode an attribute whose body fails to decode is also kept as
+    /// [`AttributeInfo::Unknown`](crate::types::AttributeInfo::Unknown), or
+    /// [`AttributeInfo::Raw`](crate::types::AttributeInfo::Raw) if not defined by the JVMS, with a
+    /// [`DiagnosticKind::MalformedAttribute`](crate::DiagnosticKind::MalformedAttribute)
+    /// diagnostic, and decoding continues with the next attribute.
+    pub mode: ValidationMode,
+    /// Whether to keep every attribute as [`AttributeInfo::Lazy`](crate::types::AttributeInfo::Lazy),
+    /// its name index and raw body, instead of decoding it. Decoding is then mostly a scan of
+    /// the constant pool and the member tables; attributes are decoded on demand with
+    /// [`LazyAttribute::decode`](crate::types::LazyAttribute::decode) or
+    /// [`JavaClassFile::decode_lazy_attributes`](crate::types::JavaClassFile::decode_lazy_attributes).
+    ///
+    /// Malformed attributes then go unnoticed until they are decoded.
+    pub lazy_attributes: bool,
+    pub(crate) attribute_decoders: AttributeDecoders,
+}
+
+impl ParseOptions {
+    /// Registers a decoder for the non-standard attributes named `name`, e.g. ones written
+    /// by a build tool or an obfuscator, replacing any decoder registered before.
+    ///
+    /// The decoder receives the attribute body and the constant pool. What it returns is
+    /// kept as [`AttributeInfo::User`](crate::types::AttributeInfo::User); an error fails
+    /// the decode with [`DecodeError::UserAttribute`](crate::DecodeError::UserAttribute),
+    /// or in lenient mode keeps the attribute raw. Attributes defined by the JVMS are always
+    /// decoded by this crate, so decoders registered for their names are not used.
+    pub fn register_attribute<T, F>(&mut self, name: impl Into<String>, decoder: F)
+    where
+        T: UserAttribute,
+        F: Fn(&[u8], &[ConstantPoolInfo]) -> Result<T, String> + Send + Sync + 'static,
+    {
+        let decoder: AttributeDecoder = Arc::new(move |body: &[u8], constant_pool: &[ConstantPoolInfo]| {
+            decoder(body, constant_pool).map(|value| Box::new(value) as Box<dyn UserAttribute>)
+        });
+        Arc::make_mut(&mut self.attribute_decoders.0).insert(name.into(), decoder);
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            max_allocation: 64 * 1024 * 1024,
+            duplicate_attributes: DuplicateAttributePolicy::default(),
+            duplicate_constants: DuplicateConstantPolicy::default(),
+            mode: ValidationMode::default(),
+            lazy_attributes: false,
+            attribute_decoders: AttributeDecoders::default(),
+        }
+    }
+}
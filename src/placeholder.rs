@@ -0,0 +1,248 @@
+//! Class file templates whose placeholder constants are substituted when stamped.
+//!
+//! A placeholder is written `${name}` inside a CONSTANT_Utf8, e.g. the string
+//! `"version ${build.version}"`, or stands for a whole CONSTANT_Integer. Placeholders are
+//! declared when creating the template, as class files may hold `${...}` text of their own,
+//! e.g. property references. The
+//! template is encoded once; stamping only rewrites the placeholder constants, whose
+//! indices do not change, so nothing else in the class needs renumbering.
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeSet, HashMap},
+    fmt,
+};
+
+use crate::{
+    encode::{encode, encode_constant},
+    error::EncodeError,
+    types::*,
+    utils::*,
+};
+
+/// A value substituted for a placeholder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlaceholderValue<'v> {
+    /// Text, only accepted by placeholders in CONSTANT_Utf8.
+    Text(Cow<'v, str>),
+    /// An integer, written in decimal by placeholders in CONSTANT_Utf8.
+    Integer(i32),
+}
+
+impl<'v> From<&'v str> for PlaceholderValue<'v> {
+    fn from(text: &'v str) -> Self {
+        PlaceholderValue::Text(Cow::Borrowed(text))
+    }
+}
+
+impl From<String> for PlaceholderValue<'_> {
+    fn from(text: String) -> Self {
+        PlaceholderValue::Text(Cow::Owned(text))
+    }
+}
+
+impl From<i32> for PlaceholderValue<'_> {
+    fn from(value: i32) -> Self {
+        PlaceholderValue::Integer(value)
+    }
+}
+
+/// Why a template cannot be created or stamped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlaceholderError {
+    /// A declared integer placeholder is not a CONSTANT_Integer.
+    NotAnInteger { index: usize },
+    /// No value was given for a placeholder.
+    Missing { name: String },
+    /// A text value was given for an integer placeholder.
+    TextForInteger { name: String },
+    /// The substituted CONSTANT_Utf8 is longer than 65535 bytes.
+    TooLong { index: usize },
+    /// The class file of the template cannot be encoded.
+    Encode(EncodeError),
+}
+
+impl fmt::Display for PlaceholderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotAnInteger { index } => write!(f, "constant_pool[{}] is not a CONSTANT_Integer", index),
+            Self::Missing { name } => write!(f, "no value for placeholder {}", name),
+            Self::TextForInteger { name } => write!(f, "placeholder {} needs an integer value", name),
+            Self::TooLong { index } => write!(f, "constant_pool[{}] is longer than 65535 bytes once stamped", index),
+            Self::Encode(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for PlaceholderError {}
+
+impl From<EncodeError> for PlaceholderError {
+    fn from(error: EncodeError) -> Self {
+        PlaceholderError::Encode(error)
+    }
+}
+
+/// A part of a CONSTANT_Utf8 holding placeholders.
+#[derive(Debug, Clone)]
+enum Piece {
+    Text(String),
+    Placeholder(String),
+}
+
+/// A constant substituted when stamping.
+#[derive(Debug, Clone)]
+enum Slot {
+    Utf8 { index: usize, pieces: Vec<Piece> },
+    Integer { name: String },
+}
+
+/// An encoded class whose placeholder constants are substituted by [`ClassTemplate::stamp`].
+#[derive(Debug, Clone)]
+pub struct ClassTemplate {
+    /// The encoded class split around the slots; one more segment than slots.
+    segments: Vec<Vec<u8>>,
+    slots: Vec<Slot>,
+}
+
+impl ClassTemplate {
+    /// Creates a template from a class. `names` declares the placeholders written `${name}`
+    /// in CONSTANT_Utf8 entries; other `${...}` text is kept. `integers` declares the
+    /// CONSTANT_Integer entries replaced by placeholders, as `(index, name)` pairs.
+    pub fn new(
+        class_file: &JavaClassFile,
+        names: &[&str],
+        integers: &[(usize, &str)],
+    ) -> Result<Self, PlaceholderError> {
+        let mut integer_names = HashMap::new();
+        for &(index, name) in integers {
+            if !matches!(class_file.constant_pool.get(index), Some(ConstantPoolInfo::Integer(_))) {
+                return Err(PlaceholderError::NotAnInteger { index });
+            }
+            integer_names.insert(index, name);
+        }
+
+        let bytes = encode(class_file)?;
+        let mut segments = Vec::new();
+        let mut slots = Vec::new();
+        let mut segment_start = 0;
+        // The constant pool follows magic, the versions and constant_pool_count.
+        let mut offset = 10;
+        let mut buffer = Vec::new();
+        for (index, constant) in class_file.constant_pool.iter().enumerate() {
+            buffer.clear();
+            encode_constant(&mut buffer, constant)?;
+            let slot = match constant {
+                ConstantPoolInfo::Integer(_) => {
+                    integer_names.get(&index).map(|name| Slot::Integer { name: name.to_string() })
+                }
+                ConstantPoolInfo::Utf8(info) => {
+                    let pieces = pieces(&info.data, names);
+                    if pieces.iter().any(|piece| matches!(piece, Piece::Placeholder(_))) {
+                        Some(Slot::Utf8 { index, pieces })
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            };
+            if let Some(slot) = slot {
+                segments.push(bytes[segment_start..offset].to_vec());
+                slots.push(slot);
+                segment_start = offset + buffer.len();
+            }
+            offset += buffer.len();
+        }
+        segments.push(bytes[segment_start..].to_vec());
+
+        Ok(ClassTemplate { segments, slots })
+    }
+
+    /// Returns the names of the placeholders, sorted and without duplicates.
+    pub fn placeholders(&self) -> Vec<&str> {
+        let mut names = BTreeSet::new();
+        for slot in &self.slots {
+            match slot {
+                Slot::Utf8 { pieces, .. } => {
+                    for piece in pieces {
+                        if let Piece::Placeholder(name) = piece {
+                            names.insert(name.as_str());
+                        }
+                    }
+                }
+                Slot::Integer { name } => {
+                    names.insert(name.as_str());
+                }
+            }
+        }
+        names.into_iter().collect()
+    }
+
+    /// Encodes a variant of the class with the placeholders replaced by `values`.
+    pub fn stamp(&self, values: &HashMap<&str, PlaceholderValue>) -> Result<Vec<u8>, PlaceholderError> {
+        let value = |name: &str| values.get(name).ok_or_else(|| PlaceholderError::Missing { name: name.to_string() });
+
+        let mut buffer = Vec::with_capacity(self.segments.iter().map(Vec::len).sum::<usize>() + 16 * self.slots.len());
+        for (segment, slot) in self.segments.iter().zip(&self.slots) {
+            buffer.extend_from_slice(segment);
+            match slot {
+                Slot::Utf8 { index, pieces } => {
+                    let mut text = String::new();
+                    for piece in pieces {
+                        match piece {
+                            Piece::Text(part) => text.push_str(part),
+                            Piece::Placeholder(name) => match value(name)? {
+                                PlaceholderValue::Text(part) => text.push_str(part),
+                                PlaceholderValue::Integer(number) => text.push_str(&number.to_string()),
+                            },
+                        }
+                    }
+                    let data = to_modified_utf8(&text);
+                    if data.len() > u16::MAX as usize {
+                        return Err(PlaceholderError::TooLong { index: *index });
+                    }
+                    write_u8(&mut buffer, ConstantKind::Utf8 as u8);
+                    write_u16(&mut buffer, data.len() as u16);
+                    buffer.extend_from_slice(&data);
+                }
+                Slot::Integer { name } => match value(name)? {
+                    PlaceholderValue::Integer(number) => {
+                        write_u8(&mut buffer, ConstantKind::Integer as u8);
+                        write_u32(&mut buffer, *number as u32);
+                    }
+                    PlaceholderValue::Text(_) => return Err(PlaceholderError::TextForInteger { name: name.clone() }),
+                },
+            }
+        }
+        buffer.extend_from_slice(self.segments.last().expect("one more segment than slots"));
+        Ok(buffer)
+    }
+}
+
+/// Splits text into literal parts and the `${name}` placeholders of `names`.
+fn pieces(text: &str, names: &[&str]) -> Vec<Piece> {
+    let mut pieces = Vec::new();
+    let mut literal = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        let after = &rest[start + 2..];
+        match after.find('}') {
+            Some(end) if names.contains(&&after[..end]) => {
+                literal.push_str(&rest[..start]);
+                if !literal.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut literal)));
+                }
+                pieces.push(Piece::Placeholder(after[..end].to_string()));
+                rest = &after[end + 1..];
+            }
+            _ => {
+                literal.push_str(&rest[..start + 2]);
+                rest = after;
+            }
+        }
+    }
+    literal.push_str(rest);
+    if !literal.is_empty() {
+        pieces.push(Piece::Text(literal));
+    }
+    pieces
+}
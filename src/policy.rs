@@ -0,0 +1,223 @@
+use std::fmt;
+
+use crate::types::*;
+
+/// Whether a [`Rule`] forbids or requires references matching its pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleKind {
+    Forbid,
+    Require,
+}
+
+/// A rule over the classes and members a class file references.
+///
+/// Patterns name classes in binary (`java.net.Socket`) or internal (`java/net/Socket`)
+/// form. `*` matches within one package segment, e.g. `java.net.*` or `*Impl`, and a
+/// `**` segment matches any number of segments, e.g. `sun.**`. A `#` followed by a
+/// member name pattern restricts the rule to field and method references, e.g.
+/// `java.lang.System#exit`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub kind: RuleKind,
+    pub pattern: String,
+    /// Shown in violations, e.g. "no sun.misc.Unsafe".
+    pub description: String,
+}
+
+impl Rule {
+    pub fn forbid(pattern: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            kind: RuleKind::Forbid,
+            pattern: pattern.into(),
+            description: description.into(),
+        }
+    }
+
+    pub fn require(pattern: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            kind: RuleKind::Require,
+            pattern: pattern.into(),
+            description: description.into(),
+        }
+    }
+
+    /// Tests if a reference to class `owner`, or to its member `member`, matches the pattern.
+    fn matches(&self, owner: &str, member: Option<&str>) -> bool {
+        let pattern = self.pattern.replace('.', "/");
+        let (class_pattern, member_pattern) = match pattern.split_once('#') {
+            Some((class_pattern, member_pattern)) => (class_pattern, Some(member_pattern)),
+            None => (pattern.as_str(), None),
+        };
+        let member_matches = match (member_pattern, member) {
+            (None, _) => true,
+            (Some(member_pattern), Some(member)) => matches_segment(member_pattern.as_bytes(), member.as_bytes()),
+            (Some(_), None) => false,
+        };
+        let class_segments: Vec<&str> = class_pattern.split('/').collect();
+        let owner_segments: Vec<&str> = owner.split('/').collect();
+        member_matches && matches_segments(&class_segments, &owner_segments)
+    }
+}
+
+/// Matches package segments, where a `**` segment matches any number of segments.
+fn matches_segments(pattern: &[&str], name: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((&"**", rest)) => (0..=name.len()).any(|skip| matches_segments(rest, &name[skip..])),
+        Some((segment, rest)) => match name.split_first() {
+            Some((first, name_rest)) => {
+                matches_segment(segment.as_bytes(), first.as_bytes()) && matches_segments(rest, name_rest)
+            }
+            None => false,
+        },
+    }
+}
+
+/// Matches one segment, where `*` matches any run of characters.
+fn matches_segment(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|skip| matches_segment(rest, &name[skip..])),
+        Some((c, rest)) => name.first() == Some(c) && matches_segment(rest, &name[1..]),
+    }
+}
+
+/// A reference found in a class file which breaks a [`Rule`], or a required reference
+/// which is missing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyViolation {
+    pub rule: Rule,
+    /// Where the reference was found, e.g. `constant_pool[12]` or `methods[3]`;
+    /// `class` for a missing required reference.
+    pub location: String,
+    /// The reference, e.g. `sun/misc/Unsafe` or `java/lang/System#exit`.
+    pub reference: String,
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.rule.kind {
+            RuleKind::Forbid => write!(f, "{}: {} violates \"{}\"", self.location, self.reference, self.rule.description),
+            RuleKind::Require => write!(f, "{}: no reference to {} for \"{}\"", self.location, self.reference, self.rule.description),
+        }
+    }
+}
+
+/// A class or member referenced from a class file.
+struct Reference<'c> {
+    location: String,
+    owner: &'c str,
+    member: Option<&'c str>,
+}
+
+/// Returns the class names of the object types in a descriptor or array class name.
+fn descriptor_classes(descriptor: &str) -> Vec<&str> {
+    let mut classes = Vec::new();
+    let mut rest = descriptor;
+    while let Some(start) = rest.find('L') {
+        let Some(end) = rest[start..].find(';') else {
+            break;
+        };
+        classes.push(&rest[start + 1..start + end]);
+        rest = &rest[start + end + 1..];
+    }
+    classes
+}
+
+/// Adds the classes of the object types in `descriptor`.
+fn add_descriptor<'c>(references: &mut Vec<Reference<'c>>, location: String, descriptor: &'c str) {
+    for owner in descriptor_classes(descriptor) {
+        references.push(Reference { location: location.clone(), owner, member: None });
+    }
+}
+
+/// Collects the classes and members referenced from the constant pool and the
+/// descriptors of fields and methods.
+fn collect_references<'c>(class_file: &'c JavaClassFile) -> Vec<Reference<'c>> {
+    let constant_pool = &class_file.constant_pool;
+    let mut references = Vec::new();
+
+    for (i, constant) in constant_pool.iter().enumerate() {
+        let location = format!("constant_pool[{}]", i);
+        let (class_index, name_and_type_index) = match constant {
+            ConstantPoolInfo::Class(info) => {
+                let name = get_utf8(constant_pool, info.name_index).unwrap_or_default();
+                if name.starts_with('[') {
+                    add_descriptor(&mut references, location, name);
+                } else {
+                    references.push(Reference { location, owner: name, member: None });
+                }
+                continue;
+            }
+            ConstantPoolInfo::NameAndType(info) => {
+                let descriptor = get_utf8(constant_pool, info.descriptor_index).unwrap_or_default();
+                add_descriptor(&mut references, location, descriptor);
+                continue;
+            }
+            ConstantPoolInfo::MethodType(info) => {
+                let descriptor = get_utf8(constant_pool, info.descriptor_index).unwrap_or_default();
+                add_descriptor(&mut references, location, descriptor);
+                continue;
+            }
+            ConstantPoolInfo::FieldRef(info) => (info.class_index, info.name_and_type_index),
+            ConstantPoolInfo::MethodRef(info) => (info.class_index, info.name_and_type_index),
+            ConstantPoolInfo::InterfaceMethodRef(info) => (info.class_index, info.name_and_type_index),
+            _ => continue,
+        };
+        let owner = get_class_name(constant_pool, class_index).unwrap_or_default();
+        let member = match constant_pool.get(name_and_type_index) {
+            Some(ConstantPoolInfo::NameAndType(info)) => get_utf8(constant_pool, info.name_index),
+            _ => None,
+        };
+        references.push(Reference { location, owner, member });
+    }
+
+    for (i, field) in class_file.fields.iter().enumerate() {
+        let descriptor = get_utf8(constant_pool, field.descriptor_index).unwrap_or_default();
+        add_descriptor(&mut references, format!("fields[{}]", i), descriptor);
+    }
+    for (i, method) in class_file.methods.iter().enumerate() {
+        let descriptor = get_utf8(constant_pool, method.descriptor_index).unwrap_or_default();
+        add_descriptor(&mut references, format!("methods[{}]", i), descriptor);
+    }
+
+    references
+}
+
+/// Checks the references of `class_file` against `rules`.
+///
+/// Forbid rules report every matching reference; require rules report a violation when
+/// no reference matches.
+pub fn check(class_file: &JavaClassFile, rules: &[Rule]) -> Vec<PolicyViolation> {
+    let references = collect_references(class_file);
+    let mut violations = Vec::new();
+
+    for rule in rules {
+        let mut matching = references.iter().filter(|reference| rule.matches(reference.owner, reference.member));
+        match rule.kind {
+            RuleKind::Forbid => {
+                for reference in matching {
+                    violations.push(PolicyViolation {
+                        rule: rule.clone(),
+                        location: reference.location.clone(),
+                        reference: match reference.member {
+                            Some(member) => format!("{}#{}", reference.owner, member),
+                            None => reference.owner.to_string(),
+                        },
+                    });
+                }
+            }
+            RuleKind::Require => {
+                if matching.next().is_none() {
+                    violations.push(PolicyViolation {
+                        rule: rule.clone(),
+                        location: "class".to_string(),
+                        reference: rule.pattern.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    violations
+}
@@ -0,0 +1,100 @@
+use std::fmt;
+
+use crate::types::*;
+
+/// Legacy behaviors of class files produced by old compilers, reported by
+/// [`JavaClassFile::quirks`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QuirkKind {
+    /// A major version 45 class file with a minor version below 3, produced by JDK 1.0 betas.
+    PreReleaseVersion,
+    /// Methods with code but without StackMapTable, verified by type inference (before 50.0).
+    NoStackMapTable,
+    /// A CLDC preverified StackMap attribute instead of StackMapTable.
+    CldcStackMap,
+    /// A Synthetic attribute marking a compiler-generated member instead of ACC_SYNTHETIC (before 49.0).
+    SyntheticAttribute,
+    /// A class without ACC_SUPER, whose invokespecial uses the pre-1.0.2 semantics.
+    MissingSuperFlag,
+    /// An interface without ACC_ABSTRACT (before 50.0).
+    InterfaceWithoutAbstract,
+    /// A <clinit> method which is not ACC_STATIC (before 51.0).
+    NonStaticClassInitializer,
+}
+
+/// A legacy behavior observed in a class file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Quirk {
+    pub kind: QuirkKind,
+    /// Where the quirk was observed, e.g. `methods[3]`.
+    pub location: String,
+}
+
+impl fmt::Display for Quirk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {:?}", self.location, self.kind)
+    }
+}
+
+/// Returns true if `attributes` include one named `name`.
+fn has_attribute(attributes: &Attributes, constant_pool: &[ConstantPoolInfo], name: &str) -> bool {
+    attributes.by_name(name, constant_pool).is_some()
+}
+
+impl JavaClassFile<'_> {
+    /// Lists the legacy behaviors of old compilers observed in this class file.
+    ///
+    /// Decoding accepts all of them; the report is meant for archival and migration tools.
+    pub fn quirks(&self) -> Vec<Quirk> {
+        let mut quirks = Vec::new();
+        let mut report = |kind, location: String| quirks.push(Quirk { kind, location });
+        let constant_pool = &self.constant_pool;
+
+        if self.major_version == 45 && self.minor_version < 3 {
+            report(QuirkKind::PreReleaseVersion, "minor_version".to_string());
+        }
+
+        if ClassAccessFlag::Interface.test(self.access_flags) {
+            if !ClassAccessFlag::Abstract.test(self.access_flags) {
+                report(QuirkKind::InterfaceWithoutAbstract, "access_flags".to_string());
+            }
+        } else if !ClassAccessFlag::Super.test(self.access_flags) && !ClassAccessFlag::Module.test(self.access_flags) {
+            report(QuirkKind::MissingSuperFlag, "access_flags".to_string());
+        }
+
+        if has_attribute(&self.attributes, constant_pool, "Synthetic") {
+            report(QuirkKind::SyntheticAttribute, "attributes".to_string());
+        }
+        for (i, field) in self.fields.iter().enumerate() {
+            if has_attribute(&field.attributes, constant_pool, "Synthetic") {
+                report(QuirkKind::SyntheticAttribute, format!("fields[{}]", i));
+            }
+        }
+
+        let mut without_stack_map = None;
+        for (i, method) in self.methods.iter().enumerate() {
+            let location = format!("methods[{}]", i);
+            if has_attribute(&method.attributes, constant_pool, "Synthetic") {
+                report(QuirkKind::SyntheticAttribute, location.clone());
+            }
+            if get_utf8(constant_pool, method.name_index) == Some("<clinit>")
+                && !MethodAccessFlag::Static.test(method.access_flags)
+            {
+                report(QuirkKind::NonStaticClassInitializer, location.clone());
+            }
+            if let Ok(Some(code)) = CodeView::new(method, constant_pool) {
+                if code.has_attribute("StackMap") {
+                    report(QuirkKind::CldcStackMap, location.clone());
+                } else if self.major_version < 50 && !code.has_attribute("StackMapTable") {
+                    without_stack_map.get_or_insert(location);
+                }
+            }
+        }
+        // Reported once, as it applies to every method of such a class file.
+        if let Some(location) = without_stack_map {
+            report(QuirkKind::NoStackMapTable, location);
+        }
+
+        quirks
+    }
+}
@@ -0,0 +1,202 @@
+use crate::error::DecodeError;
+
+/// A cursor over a class file that fails with `UnexpectedEof` instead of panicking
+/// when the buffer ends in the middle of a structure.
+#[derive(Debug, Clone)]
+pub struct ClassReader<'a> {
+    buffer: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ClassReader<'a> {
+    pub fn new(buffer: &'a [u8]) -> Self {
+        Self { buffer, position: 0 }
+    }
+
+    /// Offset of the next byte from the start of the buffer.
+    pub fn offset(&self) -> usize {
+        self.position
+    }
+
+    /// Bytes that have not been read yet.
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.buffer[self.position..]
+    }
+
+    /// Reads the next `length` bytes.
+    pub fn read_bytes(&mut self, length: usize, while_parsing: &'static str) -> Result<&'a [u8], DecodeError> {
+        let available = self.buffer.len() - self.position;
+        if length > available {
+            return Err(DecodeError::UnexpectedEof {
+                while_parsing,
+                needed: length,
+                available,
+            });
+        }
+        let bytes = &self.buffer[self.position..self.position + length];
+        self.position += length;
+        Ok(bytes)
+    }
+
+    /// Reads the next `N` bytes as an array.
+    fn read_array<const N: usize>(&mut self, while_parsing: &'static str) -> Result<[u8; N], DecodeError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.read_bytes(N, while_parsing)?);
+        Ok(array)
+    }
+
+    pub fn read_u8(&mut self, while_parsing: &'static str) -> Result<u8, DecodeError> {
+        Ok(self.read_array::<1>(while_parsing)?[0])
+    }
+
+    pub fn read_u16(&mut self, while_parsing: &'static str) -> Result<u16, DecodeError> {
+        Ok(u16::from_be_bytes(self.read_array(while_parsing)?))
+    }
+
+    pub fn read_u32(&mut self, while_parsing: &'static str) -> Result<u32, DecodeError> {
+        Ok(u32::from_be_bytes(self.read_array(while_parsing)?))
+    }
+
+    pub fn read_i32(&mut self, while_parsing: &'static str) -> Result<i32, DecodeError> {
+        Ok(i32::from_be_bytes(self.read_array(while_parsing)?))
+    }
+
+    pub fn read_i64(&mut self, while_parsing: &'static str) -> Result<i64, DecodeError> {
+        Ok(i64::from_be_bytes(self.read_array(while_parsing)?))
+    }
+
+    /// Reads a big-endian IEEE 754 single, keeping the exact bit pattern of NaNs.
+    pub fn read_f32(&mut self, while_parsing: &'static str) -> Result<f32, DecodeError> {
+        Ok(f32::from_bits(self.read_u32(while_parsing)?))
+    }
+
+    /// Reads a big-endian IEEE 754 double, keeping the exact bit pattern of NaNs.
+    pub fn read_f64(&mut self, while_parsing: &'static str) -> Result<f64, DecodeError> {
+        Ok(f64::from_bits(u64::from_be_bytes(self.read_array(while_parsing)?)))
+    }
+
+    /// Checks that `count` entries of at least `min_entry_size` bytes can still be read.
+    pub fn check_count(&self, count: usize, min_entry_size: usize, while_parsing: &'static str) -> Result<(), DecodeError> {
+        crate::utils::check_count(self.remaining(), count, min_entry_size, while_parsing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ClassReader;
+    use crate::{
+        constant_pool::decode_constant_pool, diagnostics::DiagnosticKind, encode::encode_constant,
+        types::ConstantPoolInfo, utils::DecodeContext,
+    };
+
+    // Bit patterns are compared with to_bits, so the expectations hold on hosts of
+    // either endianness.
+
+    #[test]
+    fn reads_f32_big_endian() {
+        let cases: [([u8; 4], u32); 5] = [
+            ([0x3F, 0x80, 0x00, 0x00], 0x3F80_0000),
+            ([0xC0, 0x49, 0x0F, 0xDB], 0xC049_0FDB),
+            ([0x00, 0x00, 0x00, 0x01], 0x0000_0001),
+            ([0x7F, 0x80, 0x00, 0x00], 0x7F80_0000),
+            ([0x80, 0x00, 0x00, 0x00], 0x8000_0000),
+        ];
+        for (bytes, bits) in cases {
+            let value = ClassReader::new(&bytes).read_f32("test").unwrap();
+            assert_eq!(value.to_bits(), bits);
+        }
+        assert_eq!(ClassReader::new(&[0x3F, 0x80, 0x00, 0x00]).read_f32("test").unwrap(), 1.0);
+        assert_eq!(ClassReader::new(&[0xC0, 0x49, 0x0F, 0xDB]).read_f32("test").unwrap(), -std::f32::consts::PI);
+    }
+
+    #[test]
+    fn reads_f64_big_endian() {
+        let cases: [([u8; 8], u64); 4] = [
+            ([0x3F, 0xF0, 0, 0, 0, 0, 0, 0], 0x3FF0_0000_0000_0000),
+            ([0x40, 0x09, 0x21, 0xFB, 0x54, 0x44, 0x2D, 0x18], 0x4009_21FB_5444_2D18),
+            ([0, 0, 0, 0, 0, 0, 0, 0x01], 0x0000_0000_0000_0001),
+            ([0xFF, 0xF0, 0, 0, 0, 0, 0, 0], 0xFFF0_0000_0000_0000),
+        ];
+        for (bytes, bits) in cases {
+            let value = ClassReader::new(&bytes).read_f64("test").unwrap();
+            assert_eq!(value.to_bits(), bits);
+        }
+        assert_eq!(
+            ClassReader::new(&[0x40, 0x09, 0x21, 0xFB, 0x54, 0x44, 0x2D, 0x18]).read_f64("test").unwrap(),
+            std::f64::consts::PI
+        );
+    }
+
+    #[test]
+    fn keeps_nan_payloads() {
+        let value = ClassReader::new(&[0x7F, 0xC0, 0x12, 0x34]).read_f32("test").unwrap();
+        assert!(value.is_nan());
+        assert_eq!(value.to_bits(), 0x7FC0_1234);
+
+        // A signalling NaN must not be quieted.
+        let value = ClassReader::new(&[0x7F, 0x80, 0x00, 0x01]).read_f32("test").unwrap();
+        assert!(value.is_nan());
+        assert_eq!(value.to_bits(), 0x7F80_0001);
+
+        let value = ClassReader::new(&[0xFF, 0xF0, 0, 0, 0xDE, 0xAD, 0xBE, 0xEF]).read_f64("test").unwrap();
+        assert!(value.is_nan());
+        assert_eq!(value.to_bits(), 0xFFF0_0000_DEAD_BEEF);
+    }
+
+    #[test]
+    fn round_trips_float_constants() {
+        // constant_pool_count 4: a CONSTANT_Float and a CONSTANT_Double (two slots), both NaNs.
+        let bytes = [
+            0x00, 0x04, //
+            0x04, 0x7F, 0x80, 0x00, 0x01, //
+            0x06, 0x7F, 0xF0, 0x00, 0x00, 0x00, 0x00, 0xAB, 0xCD,
+        ];
+        let constant_pool = decode_constant_pool(&mut ClassReader::new(&bytes), &mut DecodeContext::new(&Default::default())).unwrap();
+        match (&constant_pool[1], &constant_pool[2]) {
+            (ConstantPoolInfo::Float(float), ConstantPoolInfo::Double(double)) => {
+                assert_eq!(float.data.to_bits(), 0x7F80_0001);
+                assert_eq!(double.data.to_bits(), 0x7FF0_0000_0000_ABCD);
+            }
+            other => panic!("unexpected constants {:?}", other),
+        }
+
+        let mut encoded = bytes[..2].to_vec();
+        encode_constant(&mut encoded, &constant_pool[1]).unwrap();
+        encode_constant(&mut encoded, &constant_pool[2]).unwrap();
+        assert_eq!(encoded, bytes);
+    }
+
+    #[test]
+    fn round_trips_utf8_constants() {
+        // constant_pool_count 5: a lone surrogate, a surrogate pair, an embedded NUL and a raw 0x00.
+        let bytes = [
+            0x00, 0x05, //
+            0x01, 0x00, 0x04, b'a', 0xED, 0xA0, 0x80, //
+            0x01, 0x00, 0x06, 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80, //
+            0x01, 0x00, 0x04, b'a', 0xC0, 0x80, b'b', //
+            0x01, 0x00, 0x03, b'a', 0x00, b'b',
+        ];
+        let mut context = DecodeContext::new(&Default::default());
+        let constant_pool = decode_constant_pool(&mut ClassReader::new(&bytes), &mut context).unwrap();
+        let strings: Vec<_> = (1..5)
+            .map(|i| match &constant_pool[i] {
+                ConstantPoolInfo::Utf8(info) => info.data.to_string(),
+                other => panic!("unexpected constant {:?}", other),
+            })
+            .collect();
+        assert_eq!(strings, ["a\u{FFFD}", "\u{1F600}", "a\0b", "a\0b"]);
+
+        let mut encoded = bytes[..2].to_vec();
+        for constant in &constant_pool[1..] {
+            encode_constant(&mut encoded, constant).unwrap();
+        }
+        assert_eq!(encoded, bytes);
+
+        let locations: Vec<_> =
+            context.diagnostics.iter().map(|diagnostic| (diagnostic.kind, diagnostic.location.as_str())).collect();
+        assert_eq!(locations, [
+            (DiagnosticKind::MalformedUtf8, "constant_pool[1]"),
+            (DiagnosticKind::MalformedUtf8, "constant_pool[4]"),
+        ]);
+    }
+}
@@ -0,0 +1,338 @@
+//! Redaction of sensitive constants, e.g. credentials and internal host names, so that
+//! class files can be shared in bug reports.
+//!
+//! Redacted strings are replaced whole by a placeholder. Only string values are touched,
+//! never names or descriptors, so the class still loads and links as before.
+
+use std::{borrow::Cow, fmt};
+
+pub use crate::regex::{Regex, RegexError};
+use crate::{
+    dedup::{referenced_constants, remap_constant},
+    encode::encode,
+    types::*,
+    utils::to_modified_utf8,
+};
+
+/// What a string constant is compared with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StringPattern {
+    /// The whole string.
+    Exact(String),
+    /// A regular expression found anywhere in the string; see [`Regex`] for the syntax.
+    Regex(Regex),
+}
+
+impl StringPattern {
+    pub fn matches(&self, text: &str) -> bool {
+        match self {
+            StringPattern::Exact(exact) => text == exact,
+            StringPattern::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+/// An error found while parsing a redaction config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedactionConfigError {
+    /// 1-based line number.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for RedactionConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for RedactionConfigError {}
+
+/// What [`redact`] replaces, and with what.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedactionConfig {
+    /// Text written over redacted strings.
+    pub placeholder: String,
+    /// Strings to redact wherever they are used as values, i.e. CONSTANT_String entries and
+    /// string elements of annotations.
+    pub strings: Vec<StringPattern>,
+    /// Annotation elements whose string values are redacted whatever they are, as the
+    /// internal name of the annotation interface and the element name, e.g.
+    /// `("com/example/Credentials", "password")`.
+    pub annotation_elements: Vec<(String, String)>,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            placeholder: "<redacted>".to_string(),
+            strings: Vec::new(),
+            annotation_elements: Vec::new(),
+        }
+    }
+}
+
+impl RedactionConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_exact(&mut self, text: impl Into<String>) {
+        self.strings.push(StringPattern::Exact(text.into()));
+    }
+
+    pub fn add_regex(&mut self, pattern: &str) -> Result<(), RegexError> {
+        self.strings.push(StringPattern::Regex(Regex::new(pattern)?));
+        Ok(())
+    }
+
+    /// Adds an annotation element, with the annotation interface named in binary
+    /// (`com.example.Credentials`) or internal (`com/example/Credentials`) form.
+    pub fn add_annotation_element(&mut self, annotation: &str, element: impl Into<String>) {
+        self.annotation_elements.push((annotation.replace('.', "/"), element.into()));
+    }
+
+    /// Parses a config of one directive per line; empty lines and lines starting with `#`
+    /// are skipped.
+    ///
+    /// ```text
+    /// placeholder REDACTED
+    /// exact jdbc:postgresql://db.internal/orders
+    /// regex AKIA[0-9A-Z]{16}
+    /// annotation com.example.Credentials#password
+    /// ```
+    pub fn parse(text: &str) -> Result<Self, RedactionConfigError> {
+        let mut config = Self::new();
+        for (i, line) in text.lines().enumerate() {
+            let error = |message: String| RedactionConfigError { line: i + 1, message };
+            let line = line.trim_start();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (directive, argument) = line.split_once(' ').unwrap_or((line, ""));
+            match directive {
+                "placeholder" => config.placeholder = argument.to_string(),
+                "exact" => config.add_exact(argument),
+                "regex" => config.add_regex(argument).map_err(|e| error(format!("invalid regex: {}", e)))?,
+                "annotation" => {
+                    let (annotation, element) = argument
+                        .split_once('#')
+                        .filter(|(annotation, element)| !annotation.is_empty() && !element.is_empty())
+                        .ok_or_else(|| error("expected an annotation and an element, e.g. a.B#value".to_string()))?;
+                    config.add_annotation_element(annotation, element.trim_end());
+                }
+                _ => return Err(error(format!("unknown directive {:?}", directive))),
+            }
+        }
+        Ok(config)
+    }
+
+    fn matches(&self, text: &str) -> bool {
+        self.strings.iter().any(|pattern| pattern.matches(text))
+    }
+
+    fn redacts_element(&self, annotation_type: &str, element: &str) -> bool {
+        let Some(annotation) = annotation_type.strip_prefix('L').and_then(|name| name.strip_suffix(';')) else {
+            return false;
+        };
+        self.annotation_elements.iter().any(|(name, element_name)| name == annotation && element_name == element)
+    }
+}
+
+/// What [`redact`] did.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RedactionReport {
+    /// Where strings were redacted, e.g. `constant_pool[12]` for a CONSTANT_String or
+    /// `methods[3]` for an annotation element.
+    pub redacted: Vec<String>,
+    /// Indices of CONSTANT_Utf8 entries still holding redacted text, as the class refers to
+    /// them otherwise too, e.g. as the name of a member, or from attributes whose
+    /// references cannot be located, e.g. non-standard or undecoded ones.
+    pub retained: Vec<usize>,
+}
+
+/// Replaces the strings `config` selects with its placeholder, and returns where.
+///
+/// String constants and annotation elements are pointed at a CONSTANT_Utf8 holding the
+/// placeholder, and the entries which held the redacted text are overwritten unless
+/// something else refers to them. Annotations in undecoded attributes, e.g. in
+/// AnnotationDefault, are not redacted.
+pub fn redact(class_file: &mut JavaClassFile, config: &RedactionConfig) -> RedactionReport {
+    let mut report = RedactionReport::default();
+    let constant_pool = &mut class_file.constant_pool;
+    let placeholder_index = constant_pool
+        .iter()
+        .position(|constant| matches!(constant, ConstantPoolInfo::Utf8(info) if info.data == config.placeholder))
+        .unwrap_or(constant_pool.len());
+    let mut redactor = Redactor { config, constant_pool, placeholder_index, originals: Vec::new() };
+
+    for index in 0..redactor.constant_pool.len() {
+        let ConstantPoolInfo::String(info) = &redactor.constant_pool[index] else {
+            continue;
+        };
+        let string_index = info.string_index;
+        if redactor.redacts(string_index, false) {
+            if let ConstantPoolInfo::String(info) = &mut redactor.constant_pool[index] {
+                info.string_index = placeholder_index;
+            }
+            report.redacted.push(format!("constant_pool[{}]", index));
+        }
+    }
+
+    if redactor.attributes(&mut class_file.attributes) {
+        report.redacted.push("attributes".to_string());
+    }
+    for (i, field) in class_file.fields.iter_mut().enumerate() {
+        if redactor.attributes(&mut field.attributes) {
+            report.redacted.push(format!("fields[{}]", i));
+        }
+    }
+    for (i, method) in class_file.methods.iter_mut().enumerate() {
+        if redactor.attributes(&mut method.attributes) {
+            report.redacted.push(format!("methods[{}]", i));
+        }
+    }
+
+    if report.redacted.is_empty() {
+        return report;
+    }
+    let mut originals = redactor.originals;
+    if placeholder_index == class_file.constant_pool.len() {
+        let mut constants = ConstantPoolBuilder::extending(&class_file.constant_pool);
+        constants.utf8(config.placeholder.clone());
+        class_file.constant_pool = constants.build();
+    }
+
+    // Overwrite the redacted text unless something else still refers to it.
+    originals.sort_unstable();
+    originals.dedup();
+    let mut referenced = encode(class_file)
+        .ok()
+        .and_then(|bytes| referenced_constants(&bytes))
+        .unwrap_or_else(|| vec![true; class_file.constant_pool.len()]);
+    for constant in &class_file.constant_pool {
+        remap_constant(constant, |index| {
+            if let Some(referenced) = referenced.get_mut(index) {
+                *referenced = true;
+            }
+            index
+        });
+    }
+    for index in originals {
+        if referenced[index] {
+            report.retained.push(index);
+        } else if let ConstantPoolInfo::Utf8(info) = &mut class_file.constant_pool[index] {
+            info.data = Cow::Owned(config.placeholder.clone());
+            info.length = to_modified_utf8(&config.placeholder).len();
+            info.bytes = None;
+        }
+    }
+    report
+}
+
+struct Redactor<'r, 'p, 'a> {
+    config: &'r RedactionConfig,
+    constant_pool: &'p mut Vec<ConstantPoolInfo<'a>>,
+    /// Index of the CONSTANT_Utf8 holding the placeholder, which is appended after
+    /// redaction if there is none yet.
+    placeholder_index: usize,
+    /// Indices of the CONSTANT_Utf8 entries which held redacted text.
+    originals: Vec<usize>,
+}
+
+impl Redactor<'_, '_, '_> {
+    /// Tests if the string at `index` is redacted, either as `forced` by an annotation
+    /// element or as selected by a pattern, and records it if so.
+    fn redacts(&mut self, index: usize, forced: bool) -> bool {
+        let Some(text) = get_utf8(self.constant_pool, index) else {
+            return false;
+        };
+        if index == self.placeholder_index || !(forced || self.config.matches(text)) {
+            return false;
+        }
+        self.originals.push(index);
+        true
+    }
+
+    /// Redacts the annotations among `attributes`, including those of the attributes of a
+    /// Code or Record attribute, and returns true if any string was redacted.
+    fn attributes(&mut self, attributes: &mut Attributes) -> bool {
+        let mut redacted = false;
+        for attribute in attributes.values_mut() {
+            redacted |= match attribute {
+                AttributeInfo::RuntimeVisibleAnnotations(attribute) => self.annotations(&mut attribute.annotations),
+                AttributeInfo::RuntimeInvisibleAnnotations(attribute) => self.annotations(&mut attribute.annotations),
+                AttributeInfo::RuntimeVisibleParameterAnnotations(attribute) => {
+                    attribute.parameter_annotations.iter_mut().fold(false, |redacted, annotations| {
+                        self.annotations(annotations) | redacted
+                    })
+                }
+                AttributeInfo::RuntimeInvisibleParameterAnnotations(attribute) => {
+                    attribute.parameter_annotations.iter_mut().fold(false, |redacted, annotations| {
+                        self.annotations(annotations) | redacted
+                    })
+                }
+                AttributeInfo::RuntimeVisibleTypeAnnotations(attribute) => {
+                    self.type_annotations(&mut attribute.annotations)
+                }
+                AttributeInfo::RuntimeInvisibleTypeAnnotations(attribute) => {
+                    self.type_annotations(&mut attribute.annotations)
+                }
+                AttributeInfo::Code(attribute) => self.attributes(&mut attribute.attributes),
+                AttributeInfo::Record(attribute) => attribute
+                    .components
+                    .iter_mut()
+                    .fold(false, |redacted, component| self.attributes(&mut component.attributes) | redacted),
+                _ => false,
+            };
+        }
+        redacted
+    }
+
+    fn annotations(&mut self, annotations: &mut [AnnotationEntry]) -> bool {
+        let mut redacted = false;
+        for annotation in annotations {
+            redacted |= self.element_value_pairs(annotation.type_index, &mut annotation.element_value_pairs);
+        }
+        redacted
+    }
+
+    fn type_annotations(&mut self, annotations: &mut [TypeAnnotation]) -> bool {
+        let mut redacted = false;
+        for annotation in annotations {
+            redacted |= self.element_value_pairs(annotation.type_index, &mut annotation.element_value_pairs);
+        }
+        redacted
+    }
+
+    fn element_value_pairs(&mut self, type_index: u16, pairs: &mut [AnnotationElementValuePair]) -> bool {
+        let mut redacted = false;
+        for pair in pairs {
+            let annotation_type = get_utf8(self.constant_pool, type_index as usize).unwrap_or_default();
+            let element = get_utf8(self.constant_pool, pair.element_name_index as usize).unwrap_or_default();
+            let forced = self.config.redacts_element(annotation_type, element);
+            redacted |= self.element_value(&mut pair.value, forced);
+        }
+        redacted
+    }
+
+    /// Redacts a string element value, or the strings in an array element value.
+    fn element_value(&mut self, element_value: &mut AnnotationElementValue, forced: bool) -> bool {
+        match &mut element_value.value {
+            AnnotationElementValueEntryValue::ConstValueIndex(index) if element_value.tag == b's' => {
+                let redacted = self.redacts(*index as usize, forced);
+                if redacted {
+                    *index = self.placeholder_index as u16;
+                }
+                redacted
+            }
+            AnnotationElementValueEntryValue::AnnotationValue(annotation) => {
+                self.element_value_pairs(annotation.type_index, &mut annotation.element_value_pairs)
+            }
+            AnnotationElementValueEntryValue::ArrayValue { values, .. } => {
+                values.iter_mut().fold(false, |redacted, value| self.element_value(value, forced) | redacted)
+            }
+            _ => false,
+        }
+    }
+}
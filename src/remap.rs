@@ -0,0 +1,1225 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+};
+
+use crate::{types::*, utils::to_modified_utf8};
+
+/// An error found while parsing a mapping file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MappingError {
+    /// 1-based line number.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for MappingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for MappingError {}
+
+fn mapping_error(line: usize, message: impl Into<String>) -> MappingError {
+    MappingError {
+        line: line + 1,
+        message: message.into(),
+    }
+}
+
+/// Renamings of classes, fields and methods.
+///
+/// Class names are in internal form (`java/lang/Object`). Members are keyed by the name of
+/// their owner and, for methods, their descriptor before renaming.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Mappings {
+    classes: HashMap<String, String>,
+    fields: HashMap<(String, String), String>,
+    methods: HashMap<(String, String, String), String>,
+}
+
+impl Mappings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_class(&mut self, name: impl Into<String>, new_name: impl Into<String>) {
+        self.classes.insert(name.into(), new_name.into());
+    }
+
+    pub fn add_field(&mut self, owner: impl Into<String>, name: impl Into<String>, new_name: impl Into<String>) {
+        self.fields.insert((owner.into(), name.into()), new_name.into());
+    }
+
+    pub fn add_method(
+        &mut self,
+        owner: impl Into<String>,
+        name: impl Into<String>,
+        descriptor: impl Into<String>,
+        new_name: impl Into<String>,
+    ) {
+        self.methods
+            .insert((owner.into(), name.into(), descriptor.into()), new_name.into());
+    }
+
+    pub fn map_class(&self, name: &str) -> Option<&str> {
+        self.classes.get(name).map(String::as_str)
+    }
+
+    pub fn map_field(&self, owner: &str, name: &str) -> Option<&str> {
+        self.fields
+            .get(&(owner.to_string(), name.to_string()))
+            .map(String::as_str)
+    }
+
+    pub fn map_method(&self, owner: &str, name: &str, descriptor: &str) -> Option<&str> {
+        self.methods
+            .get(&(owner.to_string(), name.to_string(), descriptor.to_string()))
+            .map(String::as_str)
+    }
+
+    /// Renames the classes of a field or method descriptor, or of an array class name.
+    pub fn map_descriptor(&self, descriptor: &str) -> String {
+        let mut mapped = String::with_capacity(descriptor.len());
+        let mut rest = descriptor;
+        while let Some(start) = rest.find('L') {
+            let Some(end) = rest[start..].find(';') else {
+                break;
+            };
+            let name = &rest[start + 1..start + end];
+            mapped.push_str(&rest[..=start]);
+            mapped.push_str(self.map_class(name).unwrap_or(name));
+            mapped.push(';');
+            rest = &rest[start + end + 1..];
+        }
+        mapped.push_str(rest);
+        mapped
+    }
+
+    /// Renames the name of a CONSTANT_Class, which is a descriptor for array classes.
+    fn map_class_entry(&self, name: &str) -> String {
+        if name.starts_with('[') {
+            self.map_descriptor(name)
+        } else {
+            self.map_class(name).unwrap_or(name).to_string()
+        }
+    }
+
+    /// Renames the classes of a generic signature, or returns `None` if it is malformed.
+    ///
+    /// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.9.1
+    pub fn map_signature(&self, signature: &str) -> Option<String> {
+        let mut parser = SignatureMapper {
+            mappings: self,
+            input: signature.as_bytes(),
+            position: 0,
+            output: String::with_capacity(signature.len()),
+        };
+        parser.signature()?;
+        Some(parser.output)
+    }
+
+    /// Returns the mappings in the opposite direction, e.g. to deobfuscate with a mapping
+    /// file written by an obfuscator.
+    pub fn reverse(&self) -> Mappings {
+        let mut reversed = Mappings::new();
+        for (name, new_name) in &self.classes {
+            reversed.add_class(new_name, name);
+        }
+        for ((owner, name), new_name) in &self.fields {
+            reversed.add_field(self.map_class_entry(owner), new_name, name);
+        }
+        for ((owner, name, descriptor), new_name) in &self.methods {
+            reversed.add_method(self.map_class_entry(owner), new_name, self.map_descriptor(descriptor), name);
+        }
+        reversed
+    }
+
+    /// Parses a ProGuard or R8 `mapping.txt`, mapping original names to obfuscated names.
+    ///
+    /// Members of inlined methods, which are qualified with another class, are skipped.
+    pub fn parse_proguard(text: &str) -> Result<Mappings, MappingError> {
+        let mut mappings = Mappings::new();
+        let mut owner: Option<String> = None;
+
+        for (i, line) in text.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let (left, new_name) = trimmed
+                .split_once(" -> ")
+                .ok_or_else(|| mapping_error(i, "expected \" -> \""))?;
+
+            if !line.starts_with(char::is_whitespace) {
+                let new_name = new_name
+                    .strip_suffix(':')
+                    .ok_or_else(|| mapping_error(i, "class line must end with ':'"))?;
+                let name = left.replace('.', "/");
+                mappings.add_class(&name, new_name.replace('.', "/"));
+                owner = Some(name);
+                continue;
+            }
+
+            let owner = owner
+                .as_deref()
+                .ok_or_else(|| mapping_error(i, "member before the first class"))?;
+            // Line number ranges: "12:15:void run():40:43 -> a".
+            let left = left.trim_start_matches(|c: char| c.is_ascii_digit() || c == ':');
+            let (type_name, member) = left
+                .split_once(' ')
+                .ok_or_else(|| mapping_error(i, "expected a type and a name"))?;
+
+            match member.split_once('(') {
+                Some((name, arguments)) => {
+                    let arguments = arguments
+                        .split_once(')')
+                        .ok_or_else(|| mapping_error(i, "expected ')'"))?
+                        .0;
+                    if name.contains('.') {
+                        continue;
+                    }
+                    let mut descriptor = String::from("(");
+                    for argument in arguments.split(',').filter(|argument| !argument.is_empty()) {
+                        descriptor.push_str(&java_type_descriptor(argument.trim()));
+                    }
+                    descriptor.push(')');
+                    descriptor.push_str(&java_type_descriptor(type_name));
+                    mappings.add_method(owner, name, descriptor, new_name);
+                }
+                None if member.contains('.') => continue,
+                None => mappings.add_field(owner, member, new_name),
+            }
+        }
+
+        Ok(mappings)
+    }
+
+    /// Parses an SRG file (`CL:`, `FD:` and `MD:` lines).
+    pub fn parse_srg(text: &str) -> Result<Mappings, MappingError> {
+        fn split_member(i: usize, qualified: &str) -> Result<(&str, &str), MappingError> {
+            qualified
+                .rsplit_once('/')
+                .ok_or_else(|| mapping_error(i, format!("\"{}\" has no owner", qualified)))
+        }
+
+        let mut mappings = Mappings::new();
+        for (i, line) in text.lines().enumerate() {
+            let mut parts = line.split_whitespace();
+            let Some(kind) = parts.next() else {
+                continue;
+            };
+            let parts: Vec<&str> = parts.collect();
+            match (kind, parts.as_slice()) {
+                ("PK:", _) => {}
+                ("CL:", [name, new_name]) => mappings.add_class(*name, *new_name),
+                ("FD:", [field, new_field, ..]) => {
+                    let (owner, name) = split_member(i, field)?;
+                    mappings.add_field(owner, name, split_member(i, new_field)?.1);
+                }
+                ("MD:", [method, descriptor, new_method, _]) => {
+                    let (owner, name) = split_member(i, method)?;
+                    mappings.add_method(owner, name, *descriptor, split_member(i, new_method)?.1);
+                }
+                _ => return Err(mapping_error(i, format!("unrecognized line \"{}\"", line))),
+            }
+        }
+        Ok(mappings)
+    }
+
+    /// Parses a Tiny v2 file, mapping the names of namespace `from` to those of `to`.
+    ///
+    /// Names are unescaped if the file has the `escaped-names` property.
+    ///
+    /// ref. https://fabricmc.net/wiki/documentation:tiny2
+    pub fn parse_tiny_v2(text: &str, from: &str, to: &str) -> Result<Mappings, MappingError> {
+        let mut lines = text.lines().enumerate().peekable();
+        let (_, header) = lines.next().ok_or_else(|| mapping_error(0, "missing header"))?;
+        let header: Vec<&str> = header.split('\t').collect();
+        if header.len() < 5 || header[0] != "tiny" || header[1] != "2" {
+            return Err(mapping_error(0, "not a Tiny v2 file"));
+        }
+        let namespaces = &header[3..];
+        let namespace = |name: &str| {
+            namespaces
+                .iter()
+                .position(|namespace| *namespace == name)
+                .ok_or_else(|| mapping_error(0, format!("no namespace \"{}\"", name)))
+        };
+        let (from, to) = (namespace(from)?, namespace(to)?);
+
+        // Properties are indented once and precede the first class.
+        let mut escaped_names = false;
+        while let Some((_, property)) = lines.next_if(|(_, line)| {
+            let member = line.starts_with("\tf\t") || line.starts_with("\tm\t");
+            line.starts_with('\t') && !line.starts_with("\t\t") && !member
+        }) {
+            escaped_names |= property.split('\t').nth(1) == Some("escaped-names");
+        }
+        let unescape = |name: &str| if escaped_names { unescape_tiny_name(name) } else { name.to_string() };
+
+        // Names in namespaces without a mapping fall back to the first namespace.
+        let pick = |columns: &[&str], index: usize| -> String {
+            match columns.get(index) {
+                Some(name) if !name.is_empty() => unescape(name),
+                _ => unescape(columns[0]),
+            }
+        };
+
+        // Descriptors are written in the first namespace, so members are resolved once all
+        // classes are known.
+        let mut first_to_from = Mappings::new();
+        let mut classes = Vec::new();
+        let mut members = Vec::new();
+        for (i, line) in lines {
+            let columns: Vec<&str> = line.split('\t').collect();
+            match columns.as_slice() {
+                ["c", names @ ..] if names.len() == namespaces.len() => {
+                    classes.push((pick(names, from), pick(names, to)));
+                    first_to_from.add_class(unescape(names[0]), pick(names, from));
+                }
+                ["", kind @ ("f" | "m"), descriptor, names @ ..] if names.len() == namespaces.len() => {
+                    let owner = classes
+                        .last()
+                        .map(|(owner, _)| owner.clone())
+                        .ok_or_else(|| mapping_error(i, "member before the first class"))?;
+                    members.push((*kind, owner, unescape(descriptor), pick(names, from), pick(names, to)));
+                }
+                ["", "c", ..] | ["", "", ..] | [""] => {}
+                _ => return Err(mapping_error(i, format!("unrecognized line \"{}\"", line))),
+            }
+        }
+
+        let mut mappings = Mappings::new();
+        for (name, new_name) in classes {
+            mappings.add_class(name, new_name);
+        }
+        for (kind, owner, descriptor, name, new_name) in members {
+            if kind == "f" {
+                mappings.add_field(owner, name, new_name);
+            } else {
+                mappings.add_method(owner, name, first_to_from.map_descriptor(&descriptor), new_name);
+            }
+        }
+        Ok(mappings)
+    }
+}
+
+/// Undoes the escapes of `\\`, `\n`, `\r`, `\t` and `\0` in a name of a Tiny v2 file. Other
+/// backslashes are kept.
+fn unescape_tiny_name(name: &str) -> String {
+    let mut unescaped = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => unescaped.push('\\'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('t') => unescaped.push('\t'),
+            Some('0') => unescaped.push('\0'),
+            Some(other) => {
+                unescaped.push('\\');
+                unescaped.push(other);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// Converts a Java type as written in ProGuard mappings, e.g. `java.lang.String[]`, to a
+/// field descriptor.
+fn java_type_descriptor(java_type: &str) -> String {
+    let element = java_type.trim_end_matches("[]");
+    let mut descriptor = "[".repeat((java_type.len() - element.len()) / 2);
+    match element {
+        "boolean" => descriptor.push('Z'),
+        "byte" => descriptor.push('B'),
+        "char" => descriptor.push('C'),
+        "short" => descriptor.push('S'),
+        "int" => descriptor.push('I'),
+        "long" => descriptor.push('J'),
+        "float" => descriptor.push('F'),
+        "double" => descriptor.push('D'),
+        "void" => descriptor.push('V'),
+        class_name => {
+            descriptor.push('L');
+            descriptor.push_str(&class_name.replace('.', "/"));
+            descriptor.push(';');
+        }
+    }
+    descriptor
+}
+
+/// Copies a generic signature while renaming its classes.
+struct SignatureMapper<'m, 's> {
+    mappings: &'m Mappings,
+    input: &'s [u8],
+    position: usize,
+    output: String,
+}
+
+impl SignatureMapper<'_, '_> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.position).copied()
+    }
+
+    /// Copies one expected byte.
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        (self.peek()? == byte).then(|| {
+            self.output.push(byte as char);
+            self.position += 1;
+        })
+    }
+
+    /// Reads an identifier up to one of the signature delimiters.
+    fn identifier(&mut self) -> Option<&str> {
+        let start = self.position;
+        while !matches!(self.peek()?, b'.' | b';' | b'[' | b'/' | b'<' | b'>' | b':') {
+            self.position += 1;
+        }
+        let identifier = std::str::from_utf8(&self.input[start..self.position]).ok()?;
+        (!identifier.is_empty()).then_some(identifier)
+    }
+
+    /// ClassSignature, MethodSignature or FieldSignature.
+    fn signature(&mut self) -> Option<()> {
+        if self.peek()? == b'<' {
+            self.type_parameters()?;
+        }
+        if self.peek()? == b'(' {
+            self.expect(b'(')?;
+            while self.peek()? != b')' {
+                self.java_type()?;
+            }
+            self.expect(b')')?;
+            if self.peek()? == b'V' {
+                self.expect(b'V')?;
+            } else {
+                self.java_type()?;
+            }
+            while self.peek() == Some(b'^') {
+                self.expect(b'^')?;
+                self.reference_type()?;
+            }
+        } else {
+            while self.peek().is_some() {
+                self.reference_type()?;
+            }
+        }
+        (self.position == self.input.len()).then_some(())
+    }
+
+    fn type_parameters(&mut self) -> Option<()> {
+        self.expect(b'<')?;
+        while self.peek()? != b'>' {
+            let name = self.identifier()?.to_string();
+            self.output.push_str(&name);
+            self.expect(b':')?;
+            if !matches!(self.peek()?, b':' | b'>') {
+                self.reference_type()?;
+            }
+            while self.peek()? == b':' {
+                self.expect(b':')?;
+                self.reference_type()?;
+            }
+        }
+        self.expect(b'>')
+    }
+
+    fn java_type(&mut self) -> Option<()> {
+        match self.peek()? {
+            b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' => self.expect(self.peek()?),
+            _ => self.reference_type(),
+        }
+    }
+
+    fn reference_type(&mut self) -> Option<()> {
+        match self.peek()? {
+            b'L' => self.class_type(),
+            b'T' => {
+                self.expect(b'T')?;
+                let name = self.identifier()?.to_string();
+                self.output.push_str(&name);
+                self.expect(b';')
+            }
+            b'[' => {
+                self.expect(b'[')?;
+                self.java_type()
+            }
+            _ => None,
+        }
+    }
+
+    fn class_type(&mut self) -> Option<()> {
+        self.expect(b'L')?;
+        let start = self.position;
+        while !matches!(self.peek()?, b'.' | b';' | b'<') {
+            self.position += 1;
+        }
+        let mut name = std::str::from_utf8(&self.input[start..self.position]).ok()?.to_string();
+        let mut mapped = self.mappings.map_class(&name).unwrap_or(&name).to_string();
+        self.output.push_str(&mapped);
+        self.type_arguments()?;
+
+        while self.peek()? == b'.' {
+            self.expect(b'.')?;
+            let inner = self.identifier()?.to_string();
+            name = format!("{}${}", name, inner);
+            let mapped_inner = match self.mappings.map_class(&name) {
+                Some(new_name) => new_name.strip_prefix(&format!("{}$", mapped)).unwrap_or(&inner).to_string(),
+                None => inner,
+            };
+            self.output.push_str(&mapped_inner);
+            mapped = format!("{}${}", mapped, mapped_inner);
+            self.type_arguments()?;
+        }
+        self.expect(b';')
+    }
+
+    fn type_arguments(&mut self) -> Option<()> {
+        if self.peek()? != b'<' {
+            return Some(());
+        }
+        self.expect(b'<')?;
+        while self.peek()? != b'>' {
+            match self.peek()? {
+                b'*' => self.expect(b'*')?,
+                b'+' | b'-' => {
+                    self.expect(self.peek()?)?;
+                    self.reference_type()?;
+                }
+                _ => self.reference_type()?,
+            }
+        }
+        self.expect(b'>')
+    }
+}
+
+/// The supertypes and declared members of the classes remapped together, so that a
+/// reference to an inherited member is renamed like the member it resolves to, e.g.
+/// `B.run()` when only `run()` of its superclass `A` has a mapping.
+///
+/// Class names are in internal form, before renaming.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemberHierarchy {
+    classes: HashMap<String, DeclaredMembers>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct DeclaredMembers {
+    super_class: Option<String>,
+    interfaces: Vec<String>,
+    fields: HashSet<String>,
+    /// Names followed by descriptors, e.g. `run()V`.
+    methods: HashSet<String>,
+}
+
+impl MemberHierarchy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the supertypes and members of a class. Returns `false` if this_class cannot be
+    /// resolved.
+    pub fn add_class(&mut self, class_file: &JavaClassFile) -> bool {
+        let constant_pool = &class_file.constant_pool;
+        let Some(name) = get_class_name(constant_pool, class_file.this_class) else {
+            return false;
+        };
+        let utf8 = |index: usize| get_utf8(constant_pool, index).unwrap_or_default();
+        let members = DeclaredMembers {
+            super_class: get_class_name(constant_pool, class_file.super_class).map(str::to_string),
+            interfaces: class_file
+                .interfaces
+                .iter()
+                .filter_map(|&index| get_class_name(constant_pool, index))
+                .map(str::to_string)
+                .collect(),
+            fields: class_file.fields.iter().map(|field| utf8(field.name_index).to_string()).collect(),
+            methods: class_file
+                .methods
+                .iter()
+                .map(|method| format!("{}{}", utf8(method.name_index), utf8(method.descriptor_index)))
+                .collect(),
+        };
+        self.classes.insert(name.to_string(), members);
+        true
+    }
+
+    /// Returns the new name of the field `name` referenced through `owner`, looked up in
+    /// `owner`, then its superinterfaces and then its superclass, as field resolution does.
+    ///
+    /// The lookup stops at the first class which has a mapping for the field or declares it,
+    /// and at classes outside the hierarchy.
+    ///
+    /// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.4.3.2
+    pub fn map_field<'m>(&self, mappings: &'m Mappings, owner: &str, name: &str) -> Option<&'m str> {
+        self.resolve_field(mappings, owner, name, &mut HashSet::new())?
+    }
+
+    /// Returns `Some` with the new name, if any, once the class declaring the field is found.
+    fn resolve_field<'s, 'm>(
+        &'s self,
+        mappings: &'m Mappings,
+        class: &'s str,
+        name: &str,
+        visited: &mut HashSet<&'s str>,
+    ) -> Option<Option<&'m str>> {
+        if !visited.insert(class) {
+            return None;
+        }
+        if let Some(new_name) = mappings.map_field(class, name) {
+            return Some(Some(new_name));
+        }
+        let members = self.classes.get(class)?;
+        if members.fields.contains(name) {
+            return Some(None);
+        }
+        for interface in &members.interfaces {
+            if let Some(resolved) = self.resolve_field(mappings, interface, name, visited) {
+                return Some(resolved);
+            }
+        }
+        self.resolve_field(mappings, members.super_class.as_deref()?, name, visited)
+    }
+
+    /// Returns the new name of the method `name` referenced through `owner`, looked up in
+    /// `owner` and its superclasses, then in their superinterfaces, as method resolution does.
+    ///
+    /// The lookup stops at the first class which has a mapping for the method or declares it,
+    /// and at classes outside the hierarchy.
+    ///
+    /// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.4.3.3
+    pub fn map_method<'m>(&self, mappings: &'m Mappings, owner: &str, name: &str, descriptor: &str) -> Option<&'m str> {
+        let key = format!("{}{}", name, descriptor);
+        let mut visited = HashSet::new();
+        let mut interfaces = VecDeque::new();
+        let mut class = Some(owner);
+        while let Some(current) = class.filter(|current| visited.insert(*current)) {
+            if let Some(new_name) = mappings.map_method(current, name, descriptor) {
+                return Some(new_name);
+            }
+            let Some(members) = self.classes.get(current) else {
+                break;
+            };
+            if members.methods.contains(&key) {
+                return None;
+            }
+            interfaces.extend(members.interfaces.iter().map(String::as_str));
+            class = members.super_class.as_deref();
+        }
+        while let Some(interface) = interfaces.pop_front() {
+            if !visited.insert(interface) {
+                continue;
+            }
+            if let Some(new_name) = mappings.map_method(interface, name, descriptor) {
+                return Some(new_name);
+            }
+            match self.classes.get(interface) {
+                Some(members) if !members.methods.contains(&key) => {
+                    interfaces.extend(members.interfaces.iter().map(String::as_str));
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+/// Adds entries to a constant pool, reusing existing CONSTANT_Utf8 and
+/// CONSTANT_NameAndType entries.
+struct PoolEditor<'p, 'a> {
+    constant_pool: &'p mut Vec<ConstantPoolInfo<'a>>,
+    utf8: HashMap<String, usize>,
+    name_and_types: HashMap<(usize, usize), usize>,
+}
+
+impl<'p, 'a> PoolEditor<'p, 'a> {
+    fn new(constant_pool: &'p mut Vec<ConstantPoolInfo<'a>>) -> Self {
+        let mut utf8 = HashMap::new();
+        let mut name_and_types = HashMap::new();
+        for (i, constant) in constant_pool.iter().enumerate() {
+            match constant {
+                ConstantPoolInfo::Utf8(info) => {
+                    utf8.entry(info.data.to_string()).or_insert(i);
+                }
+                ConstantPoolInfo::NameAndType(info) => {
+                    name_and_types.entry((info.name_index, info.descriptor_index)).or_insert(i);
+                }
+                _ => {}
+            }
+        }
+        Self {
+            constant_pool,
+            utf8,
+            name_and_types,
+        }
+    }
+
+    fn utf8(&mut self, data: &str) -> usize {
+        if let Some(index) = self.utf8.get(data) {
+            return *index;
+        }
+        let index = self.constant_pool.len();
+        self.constant_pool.push(ConstantPoolInfo::Utf8(ConstantUtf8Info {
+            tag: ConstantKind::Utf8,
+            length: to_modified_utf8(data).len(),
+            data: Cow::Owned(data.to_string()),
+            bytes: None,
+        }));
+        self.utf8.insert(data.to_string(), index);
+        index
+    }
+
+    /// Returns the index of the renamed descriptor held by the CONSTANT_Utf8 entry at `index`.
+    fn descriptor(&mut self, index: u16, mappings: &Mappings) -> u16 {
+        let descriptor = get_utf8(self.constant_pool, index as usize).unwrap_or_default();
+        let mapped = mappings.map_descriptor(descriptor);
+        if mapped == descriptor {
+            return index;
+        }
+        self.utf8(&mapped) as u16
+    }
+
+    /// Returns the index of the renamed generic signature held by the CONSTANT_Utf8 entry at
+    /// `index`, which is kept if the signature is malformed.
+    fn signature(&mut self, index: usize, mappings: &Mappings) -> usize {
+        let signature = get_utf8(self.constant_pool, index).unwrap_or_default();
+        match mappings.map_signature(signature) {
+            Some(mapped) if mapped != signature => self.utf8(&mapped),
+            _ => index,
+        }
+    }
+
+    fn name_and_type(&mut self, name: &str, descriptor: &str) -> usize {
+        let key = (self.utf8(name), self.utf8(descriptor));
+        if let Some(index) = self.name_and_types.get(&key) {
+            return *index;
+        }
+        let index = self.constant_pool.len();
+        self.constant_pool.push(ConstantPoolInfo::NameAndType(ConstantNameAndTypeInfo {
+            tag: ConstantKind::NameAndType,
+            name_index: key.0,
+            descriptor_index: key.1,
+        }));
+        self.name_and_types.insert(key, index);
+        index
+    }
+}
+
+/// A change to the class file, computed before the constant pool is edited.
+enum Edit {
+    ClassName { index: usize, name: String },
+    NameAndType { index: usize, name: String, descriptor: String },
+    MethodType { index: usize, descriptor: String },
+    Field { index: usize, name: String, descriptor: String },
+    Method { index: usize, name: String, descriptor: String },
+    Signature { owner: SignatureOwner, attribute: u16, signature: String },
+    EnclosingMethod { name: String, descriptor: String },
+}
+
+#[derive(Clone, Copy)]
+enum SignatureOwner {
+    Class,
+    Field(usize),
+    Method(usize),
+}
+
+/// Returns the Signature attribute of an owner as its name index and signature.
+fn find_signature<'c>(attributes: &Attributes, constant_pool: &'c [ConstantPoolInfo]) -> Option<(u16, &'c str)> {
+    attributes.iter().find_map(|(index, attribute)| {
+        if get_utf8(constant_pool, *index as usize) != Some("Signature") {
+            return None;
+        }
+        let signature_index = match attribute {
+            AttributeInfo::Signature(signature) => signature.signature_index,
+            AttributeInfo::Unknown(body) | AttributeInfo::Lazy(LazyAttribute { data: body, .. }) if body.len() == 2 => {
+                u16::from_be_bytes([body[0], body[1]])
+            }
+            _ => return None,
+        };
+        Some((*index, get_utf8(constant_pool, signature_index as usize)?))
+    })
+}
+
+/// Renames the classes, fields and methods of `class_file` according to `mappings`.
+///
+/// Class constants, member references, descriptors, the declared fields and methods,
+/// Signature attributes, local variable tables, record components, the method named by
+/// EnclosingMethod and the types and enum constants named by annotations are renamed.
+/// Member references are looked up with the class named in the reference only, so members
+/// inherited from a renamed superclass need mappings for the referencing class too, unless
+/// the classes are renamed together with [`remap_classes`]. Unused entries are left in the
+/// constant pool.
+pub fn remap(class_file: &mut JavaClassFile, mappings: &Mappings) {
+    remap_with_hierarchy(class_file, mappings, &MemberHierarchy::new());
+}
+
+/// Renames a set of classes, e.g. those of an application, according to `mappings`.
+///
+/// Unlike [`remap`], references to members inherited from another class of the set are
+/// renamed like the members they resolve to.
+pub fn remap_classes(class_files: &mut [JavaClassFile], mappings: &Mappings) {
+    let mut hierarchy = MemberHierarchy::new();
+    for class_file in class_files.iter() {
+        hierarchy.add_class(class_file);
+    }
+    for class_file in class_files {
+        remap_with_hierarchy(class_file, mappings, &hierarchy);
+    }
+}
+
+/// Renames the classes of a jar together, see [`remap_classes`], and returns the new entry
+/// names and bytes of its class entries, e.g. `a/B.class` renamed to `com/example/Main.class`.
+/// Other entries are left out.
+#[cfg(feature = "jar")]
+pub fn remap_jar(jar: &crate::jar::Jar, mappings: &Mappings) -> Result<Vec<(String, Vec<u8>)>, crate::jar::JarError> {
+    use crate::jar::JarError;
+
+    let entries = jar
+        .class_entries()
+        .map(|entry| Ok((entry.name.as_str(), jar.read(entry)?)))
+        .collect::<Result<Vec<_>, JarError>>()?;
+    let mut class_files = entries
+        .iter()
+        .map(|(name, bytes)| crate::decode(bytes).map_err(|error| JarError::Decode { name: name.to_string(), error }))
+        .collect::<Result<Vec<_>, _>>()?;
+    let class_names: Vec<Option<String>> = class_files
+        .iter()
+        .map(|class_file| get_class_name(&class_file.constant_pool, class_file.this_class).map(str::to_string))
+        .collect();
+    remap_classes(&mut class_files, mappings);
+
+    let mut remapped = Vec::with_capacity(entries.len());
+    for (((name, _), class_file), class_name) in entries.iter().zip(&class_files).zip(class_names) {
+        let bytes = crate::encode(class_file).map_err(|error| JarError::Encode { name: name.to_string(), error })?;
+        // Entries under a prefix, e.g. META-INF/versions/9/, keep it.
+        let new_name = match (class_name, get_class_name(&class_file.constant_pool, class_file.this_class)) {
+            (Some(class_name), Some(new_class_name)) => match name.strip_suffix(&format!("{}.class", class_name)) {
+                Some(prefix) => format!("{}{}.class", prefix, new_class_name),
+                None => name.to_string(),
+            },
+            _ => name.to_string(),
+        };
+        remapped.push((new_name, bytes));
+    }
+    Ok(remapped)
+}
+
+/// Renames the classes, fields and methods of `class_file` like [`remap`], resolving member
+/// references through the classes of `hierarchy`.
+pub fn remap_with_hierarchy(class_file: &mut JavaClassFile, mappings: &Mappings, hierarchy: &MemberHierarchy) {
+    let constant_pool = &class_file.constant_pool;
+    let utf8 = |index: usize| get_utf8(constant_pool, index).unwrap_or_default();
+    let mut edits = Vec::new();
+
+    for (index, constant) in constant_pool.iter().enumerate() {
+        match constant {
+            ConstantPoolInfo::Class(info) => {
+                let name = utf8(info.name_index);
+                let new_name = mappings.map_class_entry(name);
+                if new_name != name {
+                    edits.push(Edit::ClassName { index, name: new_name });
+                }
+            }
+            ConstantPoolInfo::FieldRef(ConstantFieldRefInfo { class_index, name_and_type_index, .. })
+            | ConstantPoolInfo::MethodRef(ConstantMethodRefInfo { class_index, name_and_type_index, .. })
+            | ConstantPoolInfo::InterfaceMethodRef(ConstantInterfaceMethodRefInfo { class_index, name_and_type_index, .. }) => {
+                let Some(ConstantPoolInfo::NameAndType(name_and_type)) = constant_pool.get(*name_and_type_index) else {
+                    continue;
+                };
+                let owner = get_class_name(constant_pool, *class_index).unwrap_or_default();
+                let (name, descriptor) = (utf8(name_and_type.name_index), utf8(name_and_type.descriptor_index));
+                let new_name = if matches!(constant, ConstantPoolInfo::FieldRef(_)) {
+                    hierarchy.map_field(mappings, owner, name)
+                } else {
+                    hierarchy.map_method(mappings, owner, name, descriptor)
+                };
+                let new_descriptor = mappings.map_descriptor(descriptor);
+                if new_name.is_some() || new_descriptor != descriptor {
+                    edits.push(Edit::NameAndType {
+                        index,
+                        name: new_name.unwrap_or(name).to_string(),
+                        descriptor: new_descriptor,
+                    });
+                }
+            }
+            ConstantPoolInfo::Dynamic(ConstantDynamicInfo { name_and_type_index, .. })
+            | ConstantPoolInfo::InvokeDynamic(ConstantInvokeDynamicInfo { name_and_type_index, .. }) => {
+                let Some(ConstantPoolInfo::NameAndType(name_and_type)) = constant_pool.get(*name_and_type_index) else {
+                    continue;
+                };
+                let descriptor = utf8(name_and_type.descriptor_index);
+                let new_descriptor = mappings.map_descriptor(descriptor);
+                if new_descriptor != descriptor {
+                    edits.push(Edit::NameAndType {
+                        index,
+                        name: utf8(name_and_type.name_index).to_string(),
+                        descriptor: new_descriptor,
+                    });
+                }
+            }
+            ConstantPoolInfo::MethodType(info) => {
+                let descriptor = utf8(info.descriptor_index);
+                let new_descriptor = mappings.map_descriptor(descriptor);
+                if new_descriptor != descriptor {
+                    edits.push(Edit::MethodType { index, descriptor: new_descriptor });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let this_class = get_class_name(constant_pool, class_file.this_class).unwrap_or_default();
+    for (index, field) in class_file.fields.iter().enumerate() {
+        let (name, descriptor) = (utf8(field.name_index), utf8(field.descriptor_index));
+        edits.push(Edit::Field {
+            index,
+            name: mappings.map_field(this_class, name).unwrap_or(name).to_string(),
+            descriptor: mappings.map_descriptor(descriptor),
+        });
+        if let Some((attribute, signature)) = find_signature(&field.attributes, constant_pool) {
+            let signature = mappings.map_signature(signature).unwrap_or_else(|| signature.to_string());
+            edits.push(Edit::Signature { owner: SignatureOwner::Field(index), attribute, signature });
+        }
+    }
+    for (index, method) in class_file.methods.iter().enumerate() {
+        let (name, descriptor) = (utf8(method.name_index), utf8(method.descriptor_index));
+        edits.push(Edit::Method {
+            index,
+            name: mappings.map_method(this_class, name, descriptor).unwrap_or(name).to_string(),
+            descriptor: mappings.map_descriptor(descriptor),
+        });
+        if let Some((attribute, signature)) = find_signature(&method.attributes, constant_pool) {
+            let signature = mappings.map_signature(signature).unwrap_or_else(|| signature.to_string());
+            edits.push(Edit::Signature { owner: SignatureOwner::Method(index), attribute, signature });
+        }
+    }
+    if let Some((attribute, signature)) = find_signature(&class_file.attributes, constant_pool) {
+        let signature = mappings.map_signature(signature).unwrap_or_else(|| signature.to_string());
+        edits.push(Edit::Signature { owner: SignatureOwner::Class, attribute, signature });
+    }
+    // The method is named by a CONSTANT_NameAndType of its own, which no reference renames.
+    let enclosing_method = class_file.attributes.values().find_map(|attribute| match attribute {
+        AttributeInfo::EnclosingMethod(attribute) => Some(attribute),
+        _ => None,
+    });
+    if let Some(attribute) = enclosing_method {
+        if let Some(ConstantPoolInfo::NameAndType(name_and_type)) = constant_pool.get(attribute.method_index as usize) {
+            let owner = get_class_name(constant_pool, attribute.class_index as usize).unwrap_or_default();
+            let (name, descriptor) = (utf8(name_and_type.name_index), utf8(name_and_type.descriptor_index));
+            let new_name = hierarchy.map_method(mappings, owner, name, descriptor);
+            let new_descriptor = mappings.map_descriptor(descriptor);
+            if new_name.is_some() || new_descriptor != descriptor {
+                let name = new_name.unwrap_or(name).to_string();
+                edits.push(Edit::EnclosingMethod { name, descriptor: new_descriptor });
+            }
+        }
+    }
+    let this_class = this_class.to_string();
+
+    let mut editor = PoolEditor::new(&mut class_file.constant_pool);
+    for edit in edits {
+        match edit {
+            Edit::ClassName { index, name } => {
+                let name_index = editor.utf8(&name);
+                if let ConstantPoolInfo::Class(info) = &mut editor.constant_pool[index] {
+                    info.name_index = name_index;
+                }
+            }
+            Edit::NameAndType { index, name, descriptor } => {
+                let name_and_type_index = editor.name_and_type(&name, &descriptor);
+                match &mut editor.constant_pool[index] {
+                    ConstantPoolInfo::FieldRef(info) => info.name_and_type_index = name_and_type_index,
+                    ConstantPoolInfo::MethodRef(info) => info.name_and_type_index = name_and_type_index,
+                    ConstantPoolInfo::InterfaceMethodRef(info) => info.name_and_type_index = name_and_type_index,
+                    ConstantPoolInfo::Dynamic(info) => info.name_and_type_index = name_and_type_index,
+                    ConstantPoolInfo::InvokeDynamic(info) => info.name_and_type_index = name_and_type_index,
+                    _ => {}
+                }
+            }
+            Edit::MethodType { index, descriptor } => {
+                let descriptor_index = editor.utf8(&descriptor);
+                if let ConstantPoolInfo::MethodType(info) = &mut editor.constant_pool[index] {
+                    info.descriptor_index = descriptor_index;
+                }
+            }
+            Edit::Field { index, name, descriptor } => {
+                let field = &mut class_file.fields[index];
+                field.name_index = editor.utf8(&name);
+                field.descriptor_index = editor.utf8(&descriptor);
+            }
+            Edit::Method { index, name, descriptor } => {
+                let method = &mut class_file.methods[index];
+                method.name_index = editor.utf8(&name);
+                method.descriptor_index = editor.utf8(&descriptor);
+            }
+            Edit::Signature { owner, attribute, signature } => {
+                let signature_index = editor.utf8(&signature) as u16;
+                let attributes = match owner {
+                    SignatureOwner::Class => &mut class_file.attributes,
+                    SignatureOwner::Field(index) => &mut class_file.fields[index].attributes,
+                    SignatureOwner::Method(index) => &mut class_file.methods[index].attributes,
+                };
+                attributes.insert(attribute, AttributeInfo::Signature(SignatureAttribute { signature_index }));
+            }
+            Edit::EnclosingMethod { name, descriptor } => {
+                let method_index = editor.name_and_type(&name, &descriptor) as u16;
+                for attribute in class_file.attributes.values_mut() {
+                    if let AttributeInfo::EnclosingMethod(attribute) = attribute {
+                        attribute.method_index = method_index;
+                    }
+                }
+            }
+        }
+    }
+
+    let owners = std::iter::once(&mut class_file.attributes)
+        .chain(class_file.fields.iter_mut().map(|field| &mut field.attributes))
+        .chain(class_file.methods.iter_mut().map(|method| &mut method.attributes));
+    for attributes in owners {
+        remap_attributes(attributes, &this_class, mappings, &mut editor);
+    }
+}
+
+/// Renames the annotations, local variables and record components among `attributes`, and
+/// within the attributes nested in them.
+fn remap_attributes(attributes: &mut Attributes, this_class: &str, mappings: &Mappings, editor: &mut PoolEditor) {
+    for attribute in attributes.values_mut() {
+        match attribute {
+            AttributeInfo::RuntimeVisibleAnnotations(attribute) => {
+                remap_annotations(&mut attribute.annotations, mappings, editor);
+            }
+            AttributeInfo::RuntimeInvisibleAnnotations(attribute) => {
+                remap_annotations(&mut attribute.annotations, mappings, editor);
+            }
+            AttributeInfo::RuntimeVisibleTypeAnnotations(attribute) => {
+                remap_type_annotations(&mut attribute.annotations, mappings, editor);
+            }
+            AttributeInfo::RuntimeInvisibleTypeAnnotations(attribute) => {
+                remap_type_annotations(&mut attribute.annotations, mappings, editor);
+            }
+            AttributeInfo::RuntimeVisibleParameterAnnotations(attribute) => {
+                for annotations in &mut attribute.parameter_annotations {
+                    remap_annotations(annotations, mappings, editor);
+                }
+            }
+            AttributeInfo::RuntimeInvisibleParameterAnnotations(attribute) => {
+                for annotations in &mut attribute.parameter_annotations {
+                    remap_annotations(annotations, mappings, editor);
+                }
+            }
+            AttributeInfo::Code(code) => remap_attributes(&mut code.attributes, this_class, mappings, editor),
+            AttributeInfo::LocalVariableTable(attribute) => {
+                for entry in &mut attribute.local_variable_table {
+                    entry.descriptor_index = editor.descriptor(entry.descriptor_index as u16, mappings) as usize;
+                }
+            }
+            AttributeInfo::LocalVariableTypeTable(attribute) => {
+                for entry in &mut attribute.local_variable_type_table {
+                    entry.signature_index = editor.signature(entry.signature_index, mappings);
+                }
+            }
+            AttributeInfo::Record(attribute) => {
+                for component in &mut attribute.components {
+                    // A component is named like the private field holding it.
+                    let name = get_utf8(editor.constant_pool, component.name_index as usize).unwrap_or_default();
+                    if let Some(new_name) = mappings.map_field(this_class, name) {
+                        component.name_index = editor.utf8(new_name) as u16;
+                    }
+                    component.descriptor_index = editor.descriptor(component.descriptor_index, mappings);
+                    let signature = find_signature(&component.attributes, editor.constant_pool);
+                    if let Some((signature_attribute, signature)) = signature {
+                        let signature = mappings.map_signature(signature).unwrap_or_else(|| signature.to_string());
+                        let signature_index = editor.utf8(&signature) as u16;
+                        let signature = AttributeInfo::Signature(SignatureAttribute { signature_index });
+                        component.attributes.insert(signature_attribute, signature);
+                    }
+                    remap_attributes(&mut component.attributes, this_class, mappings, editor);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Renames the annotation interfaces, classes and enum constants named by annotations.
+fn remap_annotations(annotations: &mut [AnnotationEntry], mappings: &Mappings, editor: &mut PoolEditor) {
+    for annotation in annotations {
+        annotation.type_index = editor.descriptor(annotation.type_index, mappings);
+        for pair in &mut annotation.element_value_pairs {
+            remap_element_value(&mut pair.value, mappings, editor);
+        }
+    }
+}
+
+fn remap_type_annotations(annotations: &mut [TypeAnnotation], mappings: &Mappings, editor: &mut PoolEditor) {
+    for annotation in annotations {
+        annotation.type_index = editor.descriptor(annotation.type_index, mappings);
+        for pair in &mut annotation.element_value_pairs {
+            remap_element_value(&mut pair.value, mappings, editor);
+        }
+    }
+}
+
+fn remap_element_value(element_value: &mut AnnotationElementValue, mappings: &Mappings, editor: &mut PoolEditor) {
+    match &mut element_value.value {
+        AnnotationElementValueEntryValue::ConstValueIndex(_) => {}
+        AnnotationElementValueEntryValue::EnumConstValue { type_name_index, const_name_index } => {
+            let type_name = get_utf8(editor.constant_pool, *type_name_index as usize).unwrap_or_default();
+            let owner = type_name.strip_prefix('L').and_then(|name| name.strip_suffix(';')).unwrap_or_default();
+            let name = get_utf8(editor.constant_pool, *const_name_index as usize).unwrap_or_default();
+            if let Some(new_name) = mappings.map_field(owner, name) {
+                *const_name_index = editor.utf8(new_name) as u16;
+            }
+            *type_name_index = editor.descriptor(*type_name_index, mappings);
+        }
+        AnnotationElementValueEntryValue::ClassInfoIndex(class_info_index) => {
+            *class_info_index = editor.descriptor(*class_info_index, mappings);
+        }
+        AnnotationElementValueEntryValue::AnnotationValue(annotation) => {
+            remap_annotations(std::slice::from_mut(annotation), mappings, editor);
+        }
+        AnnotationElementValueEntryValue::ArrayValue { values, .. } => {
+            for value in values {
+                remap_element_value(value, mappings, editor);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn members(super_class: &str, interfaces: &[&str], fields: &[&str], methods: &[&str]) -> DeclaredMembers {
+        DeclaredMembers {
+            super_class: Some(super_class.to_string()),
+            interfaces: interfaces.iter().map(|name| name.to_string()).collect(),
+            fields: fields.iter().map(|name| name.to_string()).collect(),
+            methods: methods.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn resolves_inherited_members() {
+        let mut mappings = Mappings::new();
+        mappings.add_field("a/Base", "count", "c");
+        mappings.add_field("a/Limits", "MAX", "m");
+        mappings.add_method("a/Base", "run", "()V", "r");
+        mappings.add_method("a/Named", "name", "()Ljava/lang/String;", "n");
+
+        let mut hierarchy = MemberHierarchy::new();
+        let classes = [
+            ("a/Base", members("java/lang/Object", &["a/Limits"], &["count"], &["run()V"])),
+            ("a/Limits", members("java/lang/Object", &[], &["MAX"], &[])),
+            ("a/Named", members("java/lang/Object", &[], &[], &["name()Ljava/lang/String;"])),
+            ("a/Child", members("a/Base", &["a/Named"], &[], &[])),
+            ("a/Shadow", members("a/Base", &[], &["count"], &["run()V"])),
+        ];
+        for (name, members) in classes {
+            hierarchy.classes.insert(name.to_string(), members);
+        }
+
+        assert_eq!(hierarchy.map_field(&mappings, "a/Child", "count"), Some("c"));
+        assert_eq!(hierarchy.map_field(&mappings, "a/Child", "MAX"), Some("m"));
+        assert_eq!(hierarchy.map_method(&mappings, "a/Child", "run", "()V"), Some("r"));
+        assert_eq!(hierarchy.map_method(&mappings, "a/Child", "name", "()Ljava/lang/String;"), Some("n"));
+        assert_eq!(hierarchy.map_method(&mappings, "a/Child", "run", "(I)V"), None);
+
+        // Members declared again without a mapping of their own are not those of the superclass.
+        assert_eq!(hierarchy.map_field(&mappings, "a/Shadow", "count"), None);
+        assert_eq!(hierarchy.map_method(&mappings, "a/Shadow", "run", "()V"), None);
+
+        // Without the hierarchy, only the class named in the reference is looked up.
+        assert_eq!(MemberHierarchy::new().map_field(&mappings, "a/Child", "count"), None);
+        assert_eq!(MemberHierarchy::new().map_field(&mappings, "a/Base", "count"), Some("c"));
+    }
+
+    #[test]
+    fn parses_proguard() {
+        let text = "\
+# compiler: R8
+com.example.Main -> a.a:
+    int count -> a
+    12:15:void run(java.lang.String[],int):40:43 -> b
+    java.util.List items() -> c
+    19:19:void com.example.Other.inlined():60:60 -> b
+";
+        let mappings = Mappings::parse_proguard(text).expect("mappings parse");
+        assert_eq!(mappings.map_class("com/example/Main"), Some("a/a"));
+        assert_eq!(mappings.map_field("com/example/Main", "count"), Some("a"));
+        assert_eq!(mappings.map_method("com/example/Main", "run", "([Ljava/lang/String;I)V"), Some("b"));
+        assert_eq!(mappings.map_method("com/example/Main", "items", "()Ljava/util/List;"), Some("c"));
+        assert_eq!(mappings.map_method("com/example/Main", "inlined", "()V"), None);
+
+        let error = Mappings::parse_proguard("    int count -> a\n").unwrap_err();
+        assert_eq!(error.to_string(), "line 1: member before the first class");
+    }
+
+    #[test]
+    fn parses_srg() {
+        let text = "\
+PK: net/minecraft net/minecraft
+CL: a net/minecraft/Main
+FD: a/b net/minecraft/Main/count
+MD: a/c (La;)V net/minecraft/Main/run (Lnet/minecraft/Main;)V
+";
+        let mappings = Mappings::parse_srg(text).expect("mappings parse");
+        assert_eq!(mappings.map_class("a"), Some("net/minecraft/Main"));
+        assert_eq!(mappings.map_field("a", "b"), Some("count"));
+        assert_eq!(mappings.map_method("a", "c", "(La;)V"), Some("run"));
+
+        let error = Mappings::parse_srg("FD: b count\n").unwrap_err();
+        assert_eq!(error.to_string(), "line 1: \"b\" has no owner");
+    }
+
+    #[test]
+    fn parses_tiny_v2() {
+        let text = "\
+tiny\t2\t0\tofficial\tintermediary\tnamed
+\tescaped-names
+\tmissing-lvt-indices
+c\ta\tclass_1\tcom/example/Main
+\tc\tThe entry point.
+\tf\tI\tb\tfield_1\tcount
+\tm\t(La;)V\tc\tmethod_1\trun
+\t\tp\t1\t\t\tother
+\tm\t()V\td\tmethod_2\t
+c\tb\tclass_2\tcom/example/Tab\\tName
+";
+        let mappings = Mappings::parse_tiny_v2(text, "official", "named").expect("mappings parse");
+        assert_eq!(mappings.map_class("a"), Some("com/example/Main"));
+        assert_eq!(mappings.map_class("b"), Some("com/example/Tab\tName"));
+        assert_eq!(mappings.map_field("a", "b"), Some("count"));
+        assert_eq!(mappings.map_method("a", "c", "(La;)V"), Some("run"));
+        // Names missing in a namespace fall back to the first one.
+        assert_eq!(mappings.map_method("a", "d", "()V"), Some("d"));
+
+        // Member descriptors are written in the first namespace.
+        let mappings = Mappings::parse_tiny_v2(text, "named", "intermediary").expect("mappings parse");
+        assert_eq!(mappings.map_class("com/example/Main"), Some("class_1"));
+        assert_eq!(mappings.map_method("com/example/Main", "run", "(Lcom/example/Main;)V"), Some("method_1"));
+
+        // Without the property, backslashes are part of the names.
+        let unescaped = text.replace("\tescaped-names\n", "");
+        let mappings = Mappings::parse_tiny_v2(&unescaped, "official", "named").expect("mappings parse");
+        assert_eq!(mappings.map_class("b"), Some("com/example/Tab\\tName"));
+
+        let error = Mappings::parse_tiny_v2(text, "official", "unknown").unwrap_err();
+        assert_eq!(error.line, 1);
+    }
+
+    #[test]
+    fn unescapes_tiny_names() {
+        assert_eq!(unescape_tiny_name("a\\\\b\\n\\r\\t\\0"), "a\\b\n\r\t\0");
+        assert_eq!(unescape_tiny_name("a\\qb\\"), "a\\qb\\");
+    }
+}
@@ -0,0 +1,38 @@
+use std::ops::Range;
+
+use crate::{error::DecodeError, reader::ClassReader};
+
+/// How the bytes of a class file were interpreted by the decoder.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DecodeReport {
+    /// Number of bytes decoded as the class file structure; trailing bytes start here.
+    pub consumed: usize,
+    /// Number of bytes following the class file structure.
+    pub remaining: usize,
+    /// Top-level sections in file order, e.g. `("constant_pool", 10..1234)`, with absolute
+    /// byte offsets.
+    pub sections: Vec<(&'static str, Range<usize>)>,
+}
+
+impl DecodeReport {
+    /// Returns the byte range of the section named `name`.
+    pub fn section(&self, name: &str) -> Option<Range<usize>> {
+        self.sections
+            .iter()
+            .find(|(section_name, _)| *section_name == name)
+            .map(|(_, range)| range.clone())
+    }
+
+    /// Runs `decode` and records the bytes it read as the section `name`.
+    pub(crate) fn record<'a, T>(
+        &mut self,
+        name: &'static str,
+        reader: &mut ClassReader<'a>,
+        decode: impl FnOnce(&mut ClassReader<'a>) -> Result<T, DecodeError>,
+    ) -> Result<T, DecodeError> {
+        let start = reader.offset();
+        let value = decode(reader)?;
+        self.sections.push((name, start..reader.offset()));
+        Ok(value)
+    }
+}
@@ -0,0 +1,191 @@
+//! Scala signatures, the Scala types of a class pickled by scalac.
+//!
+//! Before Scala 2.8 the pickle is the body of the ScalaSig attribute. Later versions leave
+//! an empty pickle there and store the real one, encoded as text, in the `bytes` element of
+//! a `scala.reflect.ScalaSignature` annotation, or a `scala.reflect.ScalaLongSignature` one
+//! splitting it into several strings when it exceeds a CONSTANT_Utf8.
+//!
+//! ref. https://github.com/scala/scala/blob/2.13.x/src/reflect/scala/reflect/internal/pickling/PickleFormat.scala
+
+use std::{fmt, ops::Range};
+
+use crate::types::*;
+
+/// Field descriptor of the `scala.reflect.ScalaSignature` annotation interface.
+pub const SCALA_SIGNATURE_DESCRIPTOR: &str = "Lscala/reflect/ScalaSignature;";
+
+/// Field descriptor of the `scala.reflect.ScalaLongSignature` annotation interface.
+pub const SCALA_LONG_SIGNATURE_DESCRIPTOR: &str = "Lscala/reflect/ScalaLongSignature;";
+
+/// Why a pickle cannot be split into entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PickleError {
+    /// The pickle ends within the entry or number at this offset.
+    Truncated { offset: usize },
+    /// A number at this offset does not fit in 32 bits.
+    Overflow { offset: usize },
+}
+
+impl fmt::Display for PickleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated { offset } => write!(f, "pickle truncated at offset {}", offset),
+            Self::Overflow { offset } => write!(f, "number at offset {} of the pickle overflows", offset),
+        }
+    }
+}
+
+impl std::error::Error for PickleError {}
+
+/// An entry of a pickle, e.g. a name, a symbol or a type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PickleEntry {
+    /// The kind of the entry, e.g. 1 for a term name or 6 for a class symbol.
+    pub tag: u8,
+    /// Where the data of the entry lies in [`ScalaPickle::bytes`].
+    pub range: Range<usize>,
+}
+
+/// A pickle split into its entries, whose contents are left undecoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScalaPickle {
+    /// 5 for every scalac since 2.8.
+    pub major_version: u32,
+    pub minor_version: u32,
+    bytes: Vec<u8>,
+    entries: Vec<PickleEntry>,
+}
+
+impl ScalaPickle {
+    /// Splits a pickle into its entries.
+    pub fn parse(bytes: Vec<u8>) -> Result<Self, PickleError> {
+        let mut offset = 0;
+        let major_version = read_nat(&bytes, &mut offset)?;
+        let minor_version = read_nat(&bytes, &mut offset)?;
+        let count = read_nat(&bytes, &mut offset)? as usize;
+        // An entry takes at least its tag and length.
+        if count > bytes.len().saturating_sub(offset) / 2 {
+            return Err(PickleError::Truncated { offset });
+        }
+
+        let mut entries = Vec::with_capacity(count);
+        for _ in 0..count {
+            let tag = *bytes.get(offset).ok_or(PickleError::Truncated { offset })?;
+            offset += 1;
+            let length = read_nat(&bytes, &mut offset)? as usize;
+            if length > bytes.len() - offset {
+                return Err(PickleError::Truncated { offset });
+            }
+            entries.push(PickleEntry { tag, range: offset..offset + length });
+            offset += length;
+        }
+
+        Ok(ScalaPickle { major_version, minor_version, bytes, entries })
+    }
+
+    /// Returns the whole pickle, starting with its versions.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn entries(&self) -> &[PickleEntry] {
+        &self.entries
+    }
+
+    /// Returns the data of an entry, following its tag and length.
+    pub fn entry_data(&self, entry: &PickleEntry) -> &[u8] {
+        &self.bytes[entry.range.clone()]
+    }
+}
+
+/// Returns the pickle of a class compiled by scalac, from its ScalaSignature or
+/// ScalaLongSignature annotation, or else its ScalaSig attribute. Returns `None` if the class
+/// has none of them.
+///
+/// Classes with only a Scala attribute have their signature pickled into another class.
+pub fn scala_signature(class_file: &JavaClassFile) -> Option<Result<ScalaPickle, PickleError>> {
+    if let Some(text) = annotation_bytes(class_file) {
+        return Some(ScalaPickle::parse(decode_signature_bytes(&text)));
+    }
+    class_file.attributes.values().find_map(|attribute| match attribute {
+        AttributeInfo::ScalaSig(attribute) => Some(ScalaPickle::parse(attribute.pickle.to_vec())),
+        _ => None,
+    })
+}
+
+/// Decodes the text of a ScalaSignature annotation back into the pickle.
+///
+/// scalac packs the pickle 7 bits per character, adding 1 to each and writing 0x7f as 0, so
+/// that the text needs one byte per character in a CONSTANT_Utf8 but U+0000 which takes two.
+pub fn decode_signature_bytes(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len() * 7 / 8 + 1);
+    let mut bits = 0u32;
+    let mut bit_count = 0;
+    for character in text.chars() {
+        let value = match character as u32 {
+            0 => 0x7f,
+            value => value.wrapping_sub(1) & 0x7f,
+        };
+        bits |= value << bit_count;
+        bit_count += 7;
+        if bit_count >= 8 {
+            bytes.push(bits as u8);
+            bits >>= 8;
+            bit_count -= 8;
+        }
+    }
+    if bit_count > 0 {
+        bytes.push(bits as u8);
+    }
+    bytes
+}
+
+/// Returns the concatenated `bytes` element of the ScalaSignature or ScalaLongSignature
+/// annotation, if the class has one.
+fn annotation_bytes(class_file: &JavaClassFile) -> Option<String> {
+    let constant_pool = &class_file.constant_pool;
+    let annotation = class_file.attributes.values().find_map(|attribute| match attribute {
+        AttributeInfo::RuntimeVisibleAnnotations(attribute) => attribute.annotations.iter().find(|annotation| {
+            matches!(
+                get_utf8(constant_pool, annotation.type_index as usize),
+                Some(SCALA_SIGNATURE_DESCRIPTOR | SCALA_LONG_SIGNATURE_DESCRIPTOR)
+            )
+        }),
+        _ => None,
+    })?;
+    let value = annotation
+        .element_value_pairs
+        .iter()
+        .find(|pair| get_utf8(constant_pool, pair.element_name_index as usize) == Some("bytes"))?;
+
+    let string = |value: &AnnotationElementValue| match value.value {
+        AnnotationElementValueEntryValue::ConstValueIndex(index) if value.tag == b's' => {
+            get_utf8(constant_pool, index as usize)
+        }
+        _ => None,
+    };
+    match &value.value.value {
+        AnnotationElementValueEntryValue::ArrayValue { values, .. } => {
+            values.iter().map(string).collect::<Option<Vec<&str>>>().map(|parts| parts.concat())
+        }
+        _ => string(&value.value).map(str::to_string),
+    }
+}
+
+/// Reads a natural number written 7 bits per byte, most significant first, with the high bit
+/// set on all bytes but the last.
+fn read_nat(bytes: &[u8], offset: &mut usize) -> Result<u32, PickleError> {
+    let start = *offset;
+    let mut value = 0u32;
+    loop {
+        let byte = *bytes.get(*offset).ok_or(PickleError::Truncated { offset: start })?;
+        *offset += 1;
+        if value > u32::MAX >> 7 {
+            return Err(PickleError::Overflow { offset: start });
+        }
+        value = value << 7 | (byte & 0x7f) as u32;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{
+    attributes, classfile, constant_pool, error::DecodeError, options::ParseOptions, reader::ClassReader, types::*,
+    utils::DecodeContext,
+};
+
+/// Decodes one section of a class file from the start of `bytes`, returning it together
+/// with the number of bytes read.
+fn decode_section<'a, T>(
+    bytes: &'a [u8],
+    options: &ParseOptions,
+    location: &str,
+    decode: impl FnOnce(&mut ClassReader<'a>, &mut DecodeContext) -> Result<T, DecodeError>,
+) -> Result<(T, usize), DecodeError> {
+    let mut context = DecodeContext::new(options);
+    context.location = location.to_string();
+    let mut reader = ClassReader::new(bytes);
+    let section = decode(&mut reader, &mut context)?;
+    Ok((section, bytes.len() - reader.remaining().len()))
+}
+
+/// Decodes constant_pool_count and the constant pool following it.
+///
+/// Entries are indexed as in the class file. Returns the constant pool and the number of
+/// bytes read.
+pub fn decode_constant_pool<'a>(
+    bytes: &'a [u8],
+    options: &ParseOptions,
+) -> Result<(Vec<ConstantPoolInfo<'a>>, usize), DecodeError> {
+    decode_section(bytes, options, "constant_pool", constant_pool::decode_constant_pool)
+}
+
+/// Decodes fields_count and the fields following it, resolving attribute names in
+/// `constant_pool`. Returns the fields and the number of bytes read.
+pub fn decode_fields<'a>(
+    bytes: &'a [u8],
+    constant_pool: &[ConstantPoolInfo],
+    options: &ParseOptions,
+) -> Result<(Vec<FieldInfo<'a>>, usize), DecodeError> {
+    decode_section(bytes, options, "fields", |reader, context| classfile::decode_fields(reader, constant_pool, context))
+}
+
+/// Decodes methods_count and the methods following it, resolving attribute names in
+/// `constant_pool`. Returns the methods and the number of bytes read.
+pub fn decode_methods<'a>(
+    bytes: &'a [u8],
+    constant_pool: &[ConstantPoolInfo],
+    options: &ParseOptions,
+) -> Result<(Vec<MethodInfo<'a>>, usize), DecodeError> {
+    decode_section(bytes, options, "methods", |reader, context| classfile::decode_methods(reader, constant_pool, context))
+}
+
+/// Decodes attributes_count and the attributes following it, resolving their names in
+/// `constant_pool`. Returns the attributes and the number of bytes read.
+pub fn decode_attributes<'a>(
+    bytes: &'a [u8],
+    constant_pool: &[ConstantPoolInfo],
+    options: &ParseOptions,
+) -> Result<(Attributes<'a>, usize), DecodeError> {
+    decode_section(bytes, options, "attributes", |reader, context| {
+        attributes::decode_attributes(reader, constant_pool, context)
+    })
+}
+
+/// Owned bytes of a class file fragment, e.g. one read out of a custom container, with
+/// the section decoders of this module as methods.
+///
+/// Decoded sections borrow from the buffer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SectionBuffer {
+    bytes: Vec<u8>,
+}
+
+impl SectionBuffer {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self { bytes }
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// See [`decode_constant_pool`].
+    pub fn decode_constant_pool(&self, options: &ParseOptions) -> Result<(Vec<ConstantPoolInfo<'_>>, usize), DecodeError> {
+        decode_constant_pool(&self.bytes, options)
+    }
+
+    /// See [`decode_fields`].
+    pub fn decode_fields(
+        &self,
+        constant_pool: &[ConstantPoolInfo],
+        options: &ParseOptions,
+    ) -> Result<(Vec<FieldInfo<'_>>, usize), DecodeError> {
+        decode_fields(&self.bytes, constant_pool, options)
+    }
+
+    /// See [`decode_methods`].
+    pub fn decode_methods(
+        &self,
+        constant_pool: &[ConstantPoolInfo],
+        options: &ParseOptions,
+    ) -> Result<(Vec<MethodInfo<'_>>, usize), DecodeError> {
+        decode_methods(&self.bytes, constant_pool, options)
+    }
+
+    /// See [`decode_attributes`].
+    pub fn decode_attributes(
+        &self,
+        constant_pool: &[ConstantPoolInfo],
+        options: &ParseOptions,
+    ) -> Result<(Attributes<'_>, usize), DecodeError> {
+        decode_attributes(&self.bytes, constant_pool, options)
+    }
+}
+
+impl From<Vec<u8>> for SectionBuffer {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::new(bytes)
+    }
+}
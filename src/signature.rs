@@ -0,0 +1,461 @@
+//! Parsing of generic signatures and matching of methods against signature queries.
+//!
+//! ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.9.1
+
+use std::collections::HashMap;
+
+use crate::types::*;
+
+/// A JavaTypeSignature, e.g. `I`, `TT;` or `Ljava/util/List<+Ljava/lang/Number;>;`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeSignature<'a> {
+    /// A primitive type by its descriptor character, e.g. `I`.
+    Base(char),
+    Class(ClassType<'a>),
+    TypeVariable(&'a str),
+    Array(Box<TypeSignature<'a>>),
+}
+
+/// A ClassTypeSignature, e.g. `Ljava/util/Map<TK;TV;>.Entry<TK;TV;>;`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassType<'a> {
+    /// Internal name of the outermost class, e.g. `java/util/Map`.
+    pub name: &'a str,
+    pub arguments: Vec<TypeArgument<'a>>,
+    /// Simple names and type arguments of the inner classes, outermost first.
+    pub inner: Vec<(&'a str, Vec<TypeArgument<'a>>)>,
+}
+
+impl<'a> ClassType<'a> {
+    /// Returns the binary name of the class, e.g. `java/util/Map$Entry`.
+    pub fn binary_name(&self) -> String {
+        let mut name = self.name.to_string();
+        for (inner, _) in &self.inner {
+            name.push('$');
+            name.push_str(inner);
+        }
+        name
+    }
+
+    /// Returns the type arguments of the outermost class and then of each inner class.
+    fn segments(&self) -> impl Iterator<Item = &[TypeArgument<'a>]> {
+        std::iter::once(self.arguments.as_slice()).chain(self.inner.iter().map(|(_, arguments)| arguments.as_slice()))
+    }
+}
+
+/// A TypeArgument of a class type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeArgument<'a> {
+    /// `*`, i.e. `?` in Java.
+    Any,
+    Exact(TypeSignature<'a>),
+    /// `+`, i.e. `? extends` in Java.
+    Extends(TypeSignature<'a>),
+    /// `-`, i.e. `? super` in Java.
+    Super(TypeSignature<'a>),
+}
+
+/// A TypeParameter, e.g. `T:Ljava/lang/Number;:Ljava/lang/Comparable<TT;>;`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeParameter<'a> {
+    pub name: &'a str,
+    pub class_bound: Option<TypeSignature<'a>>,
+    pub interface_bounds: Vec<TypeSignature<'a>>,
+}
+
+impl<'a> TypeParameter<'a> {
+    /// Returns the class bound and then the interface bounds.
+    pub fn bounds(&self) -> impl Iterator<Item = &TypeSignature<'a>> {
+        self.class_bound.iter().chain(&self.interface_bounds)
+    }
+}
+
+/// The Result of a method signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReturnType<'a> {
+    Void,
+    Type(TypeSignature<'a>),
+}
+
+/// A MethodSignature, e.g. `<T:Ljava/lang/Object;>(TT;)Ljava/util/List<TT;>;`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodSignature<'a> {
+    pub type_parameters: Vec<TypeParameter<'a>>,
+    pub parameters: Vec<TypeSignature<'a>>,
+    pub result: ReturnType<'a>,
+    pub throws: Vec<TypeSignature<'a>>,
+}
+
+/// A ClassSignature, e.g. `<E:Ljava/lang/Object;>Ljava/util/AbstractList<TE;>;Ljava/util/List<TE;>;`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassSignature<'a> {
+    pub type_parameters: Vec<TypeParameter<'a>>,
+    pub superclass: ClassType<'a>,
+    pub interfaces: Vec<ClassType<'a>>,
+}
+
+/// Parses a JavaTypeSignature, e.g. a field signature or a field descriptor.
+pub fn parse_type(signature: &str) -> Option<TypeSignature<'_>> {
+    let mut parser = Parser { input: signature, position: 0, any_result: false };
+    let java_type = parser.java_type()?;
+    parser.finish(java_type)
+}
+
+/// Parses a MethodSignature. Method descriptors are accepted too.
+pub fn parse_method_signature(signature: &str) -> Option<MethodSignature<'_>> {
+    let mut parser = Parser { input: signature, position: 0, any_result: false };
+    let type_parameters = parser.type_parameters()?;
+    let parameters = parser.parameters()?;
+    let result = parser.result()??;
+    let mut throws = Vec::new();
+    while parser.eat(b'^') {
+        throws.push(parser.reference_type()?);
+    }
+    parser.finish(MethodSignature { type_parameters, parameters, result, throws })
+}
+
+/// Parses a ClassSignature.
+pub fn parse_class_signature(signature: &str) -> Option<ClassSignature<'_>> {
+    let mut parser = Parser { input: signature, position: 0, any_result: false };
+    let type_parameters = parser.type_parameters()?;
+    let superclass = parser.class_type()?;
+    let mut interfaces = Vec::new();
+    while parser.peek().is_some() {
+        interfaces.push(parser.class_type()?);
+    }
+    parser.finish(ClassSignature { type_parameters, superclass, interfaces })
+}
+
+/// Returns the parsed generic signature of a method, falling back to its descriptor when it
+/// has no Signature attribute.
+pub fn method_signature<'c>(method: &MethodInfo, constant_pool: &'c [ConstantPoolInfo]) -> Option<MethodSignature<'c>> {
+    let signature = method
+        .signature(constant_pool)
+        .or_else(|| get_utf8(constant_pool, method.descriptor_index))?;
+    parse_method_signature(signature)
+}
+
+/// Reads signatures by recursive descent.
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+    /// Accepts `*` as the result, for [`MethodQuery::parse`].
+    any_result: bool,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.position).copied()
+    }
+
+    /// Consumes `byte` if it is next.
+    fn eat(&mut self, byte: u8) -> bool {
+        let next = self.peek() == Some(byte);
+        if next {
+            self.position += 1;
+        }
+        next
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        self.eat(byte).then_some(())
+    }
+
+    fn finish<T>(&self, value: T) -> Option<T> {
+        (self.position == self.input.len()).then_some(value)
+    }
+
+    /// Reads an identifier up to one of the signature delimiters.
+    fn identifier(&mut self) -> Option<&'a str> {
+        let start = self.position;
+        while !matches!(self.peek()?, b'.' | b';' | b'[' | b'/' | b'<' | b'>' | b':') {
+            self.position += 1;
+        }
+        let identifier = &self.input[start..self.position];
+        (!identifier.is_empty()).then_some(identifier)
+    }
+
+    fn type_parameters(&mut self) -> Option<Vec<TypeParameter<'a>>> {
+        let mut type_parameters = Vec::new();
+        if !self.eat(b'<') {
+            return Some(type_parameters);
+        }
+        while !self.eat(b'>') {
+            let name = self.identifier()?;
+            self.expect(b':')?;
+            let class_bound = match self.peek()? {
+                b':' | b'>' => None,
+                _ => Some(self.reference_type()?),
+            };
+            let mut interface_bounds = Vec::new();
+            while self.eat(b':') {
+                interface_bounds.push(self.reference_type()?);
+            }
+            type_parameters.push(TypeParameter { name, class_bound, interface_bounds });
+        }
+        (!type_parameters.is_empty()).then_some(type_parameters)
+    }
+
+    fn parameters(&mut self) -> Option<Vec<TypeSignature<'a>>> {
+        self.expect(b'(')?;
+        let mut parameters = Vec::new();
+        while !self.eat(b')') {
+            parameters.push(self.java_type()?);
+        }
+        Some(parameters)
+    }
+
+    /// Reads the Result of a method signature, or `Some(None)` for `*` if accepted.
+    fn result(&mut self) -> Option<Option<ReturnType<'a>>> {
+        if self.any_result && self.eat(b'*') {
+            return Some(None);
+        }
+        if self.eat(b'V') {
+            return Some(Some(ReturnType::Void));
+        }
+        Some(Some(ReturnType::Type(self.java_type()?)))
+    }
+
+    fn java_type(&mut self) -> Option<TypeSignature<'a>> {
+        match self.peek()? {
+            byte @ (b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z') => {
+                self.position += 1;
+                Some(TypeSignature::Base(byte as char))
+            }
+            _ => self.reference_type(),
+        }
+    }
+
+    fn reference_type(&mut self) -> Option<TypeSignature<'a>> {
+        match self.peek()? {
+            b'L' => Some(TypeSignature::Class(self.class_type()?)),
+            b'T' => {
+                self.position += 1;
+                let name = self.identifier()?;
+                self.expect(b';')?;
+                Some(TypeSignature::TypeVariable(name))
+            }
+            b'[' => {
+                self.position += 1;
+                Some(TypeSignature::Array(Box::new(self.java_type()?)))
+            }
+            _ => None,
+        }
+    }
+
+    fn class_type(&mut self) -> Option<ClassType<'a>> {
+        self.expect(b'L')?;
+        let start = self.position;
+        while !matches!(self.peek()?, b'.' | b';' | b'<') {
+            self.position += 1;
+        }
+        let name = &self.input[start..self.position];
+        if name.is_empty() || name.split('/').any(str::is_empty) {
+            return None;
+        }
+        let arguments = self.type_arguments()?;
+
+        let mut inner = Vec::new();
+        while self.eat(b'.') {
+            let name = self.identifier()?;
+            inner.push((name, self.type_arguments()?));
+        }
+        self.expect(b';')?;
+        Some(ClassType { name, arguments, inner })
+    }
+
+    fn type_arguments(&mut self) -> Option<Vec<TypeArgument<'a>>> {
+        let mut arguments = Vec::new();
+        if !self.eat(b'<') {
+            return Some(arguments);
+        }
+        while !self.eat(b'>') {
+            let argument = match self.peek()? {
+                b'*' => {
+                    self.position += 1;
+                    TypeArgument::Any
+                }
+                b'+' => {
+                    self.position += 1;
+                    TypeArgument::Extends(self.reference_type()?)
+                }
+                b'-' => {
+                    self.position += 1;
+                    TypeArgument::Super(self.reference_type()?)
+                }
+                _ => TypeArgument::Exact(self.reference_type()?),
+            };
+            arguments.push(argument);
+        }
+        (!arguments.is_empty()).then_some(arguments)
+    }
+}
+
+/// A pattern over method signatures, matched by [`matches`].
+///
+/// Type variables of the query are placeholders: each matches any candidate type within the
+/// bounds declared in `type_parameters`, and every occurrence must match the same type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodQuery<'q> {
+    pub type_parameters: Vec<TypeParameter<'q>>,
+    /// Patterns of the parameters, or `None` to match any parameters.
+    pub parameters: Option<Vec<TypeSignature<'q>>>,
+    /// Pattern of the result, or `None` to match any result.
+    pub result: Option<ReturnType<'q>>,
+}
+
+impl<'q> MethodQuery<'q> {
+    /// Parses a query written as a method signature, where the parameter list may be left
+    /// out to match any parameters and the result may be `*` to match any result.
+    ///
+    /// For example `Ljava/util/List<+Ljava/lang/Number;>;` finds methods returning a list of
+    /// anything extending `Number`, and `<T:Ljava/lang/Object;>(TT;)TT;` methods returning
+    /// their only argument's type.
+    pub fn parse(query: &'q str) -> Option<Self> {
+        let mut parser = Parser { input: query, position: 0, any_result: true };
+        let type_parameters = parser.type_parameters()?;
+        let parameters = match parser.peek()? {
+            b'(' => Some(parser.parameters()?),
+            _ => None,
+        };
+        let result = parser.result()?;
+        parser.finish(MethodQuery { type_parameters, parameters, result })
+    }
+
+    /// Returns a query matching methods which return `result`, with any parameters.
+    pub fn returns(result: TypeSignature<'q>) -> Self {
+        MethodQuery {
+            type_parameters: Vec::new(),
+            parameters: None,
+            result: Some(ReturnType::Type(result)),
+        }
+    }
+}
+
+/// Tests if the method signature `candidate` matches `query`.
+///
+/// Types match structurally, as the class hierarchy is not known: a class type matches the
+/// same class, and a query without type arguments matches any arguments of that class. A
+/// type variable of the candidate matches a class type its bounds match. `? extends T` in
+/// the query matches `T`, `? extends T` or a type variable bounded by `T`, and
+/// `? extends java/lang/Object` matches any reference type. Thrown exceptions are ignored.
+pub fn matches(candidate: &MethodSignature, query: &MethodQuery) -> bool {
+    let mut matcher = Matcher {
+        candidate: &candidate.type_parameters,
+        query: &query.type_parameters,
+        bindings: HashMap::new(),
+    };
+
+    if let Some(parameters) = &query.parameters {
+        if parameters.len() != candidate.parameters.len()
+            || !parameters.iter().zip(&candidate.parameters).all(|(query, candidate)| matcher.matches(query, candidate))
+        {
+            return false;
+        }
+    }
+
+    match (&query.result, &candidate.result) {
+        (None, _) | (Some(ReturnType::Void), ReturnType::Void) => true,
+        (Some(ReturnType::Type(query)), ReturnType::Type(candidate)) => matcher.matches(query, candidate),
+        _ => false,
+    }
+}
+
+/// Matches candidate types against query types, binding the query type variables.
+struct Matcher<'m, 'a, 'q> {
+    candidate: &'m [TypeParameter<'a>],
+    query: &'m [TypeParameter<'q>],
+    bindings: HashMap<&'q str, TypeSignature<'a>>,
+}
+
+impl<'a, 'q> Matcher<'_, 'a, 'q> {
+    fn matches(&mut self, query: &TypeSignature<'q>, candidate: &TypeSignature<'a>) -> bool {
+        match (query, candidate) {
+            (TypeSignature::TypeVariable(name), _) => self.bind(name, candidate),
+            (TypeSignature::Base(query), TypeSignature::Base(candidate)) => query == candidate,
+            (TypeSignature::Array(query), TypeSignature::Array(candidate)) => self.matches(query, candidate),
+            (TypeSignature::Class(query), TypeSignature::Class(candidate)) => self.matches_class(query, candidate),
+            (TypeSignature::Class(_), TypeSignature::TypeVariable(name)) => {
+                self.candidate_bounds(name).iter().any(|bound| self.matches(query, bound))
+            }
+            _ => false,
+        }
+    }
+
+    fn matches_class(&mut self, query: &ClassType<'q>, candidate: &ClassType<'a>) -> bool {
+        if query.binary_name() != candidate.binary_name() {
+            return false;
+        }
+        query.segments().zip(candidate.segments()).all(|(query, candidate)| {
+            query.is_empty()
+                || query.len() == candidate.len()
+                    && query.iter().zip(candidate).all(|(query, candidate)| self.matches_argument(query, candidate))
+        })
+    }
+
+    fn matches_argument(&mut self, query: &TypeArgument<'q>, candidate: &TypeArgument<'a>) -> bool {
+        match (query, candidate) {
+            (TypeArgument::Any, _) => true,
+            (TypeArgument::Exact(query), TypeArgument::Exact(candidate)) => self.matches(query, candidate),
+            (TypeArgument::Extends(query), TypeArgument::Exact(candidate) | TypeArgument::Extends(candidate)) => {
+                is_object(query) || self.matches(query, candidate)
+            }
+            (TypeArgument::Extends(query), TypeArgument::Any | TypeArgument::Super(_)) => is_object(query),
+            (TypeArgument::Super(query), TypeArgument::Exact(candidate) | TypeArgument::Super(candidate)) => {
+                self.matches(query, candidate)
+            }
+            _ => false,
+        }
+    }
+
+    /// Binds the query type variable `name` to `candidate`, or checks it against its binding.
+    fn bind(&mut self, name: &'q str, candidate: &TypeSignature<'a>) -> bool {
+        if let Some(bound) = self.bindings.get(name) {
+            return bound == candidate;
+        }
+        if matches!(candidate, TypeSignature::Base(_)) {
+            return false;
+        }
+        let bounds: Vec<_> = match self.query.iter().find(|parameter| parameter.name == name) {
+            Some(parameter) => parameter.bounds().cloned().collect(),
+            None => Vec::new(),
+        };
+        self.bindings.insert(name, candidate.clone());
+        bounds.iter().all(|bound| is_object(bound) || self.matches(bound, candidate))
+    }
+
+    /// Returns the bounds of the candidate type variable `name`, following bounds which are
+    /// type variables themselves, or `java/lang/Object` if it is not declared by the method.
+    fn candidate_bounds(&self, name: &str) -> Vec<TypeSignature<'a>> {
+        let mut bounds = Vec::new();
+        let mut pending = vec![name];
+        let mut visited = Vec::new();
+        while let Some(name) = pending.pop() {
+            if visited.contains(&name) {
+                continue;
+            }
+            visited.push(name);
+            let Some(parameter) = self.candidate.iter().find(|parameter| parameter.name == name) else {
+                continue;
+            };
+            for bound in parameter.bounds() {
+                match bound {
+                    TypeSignature::TypeVariable(name) => pending.push(name),
+                    bound => bounds.push(bound.clone()),
+                }
+            }
+        }
+        if bounds.is_empty() {
+            bounds.push(TypeSignature::Class(ClassType {
+                name: "java/lang/Object",
+                arguments: Vec::new(),
+                inner: Vec::new(),
+            }));
+        }
+        bounds
+    }
+}
+
+fn is_object(signature: &TypeSignature) -> bool {
+    matches!(signature, TypeSignature::Class(class) if class.name == "java/lang/Object" && class.inner.is_empty())
+}
@@ -0,0 +1,132 @@
+use crate::{
+    dedup::{referenced_constants, remap_constant, rewrite_references},
+    encode::encode,
+    reader::ClassReader,
+    types::*,
+    utils::*,
+};
+
+/// Whether a [`Member`] is a field or a method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemberKind {
+    Field,
+    Method,
+}
+
+/// A field or method offered to the predicate of [`slice`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Member<'c> {
+    pub kind: MemberKind,
+    /// Index in `fields` or `methods`.
+    pub index: usize,
+    pub access_flags: u16,
+    pub name: &'c str,
+    pub descriptor: &'c str,
+}
+
+/// Encodes a copy of `class_file` which keeps only the fields and methods accepted by
+/// `predicate`, together with the constant pool entries they and the class still need.
+///
+/// Class attributes are kept as they are, so e.g. BootstrapMethods and InnerClasses keep
+/// the entries they refer to. Code of the kept methods is not checked for references to
+/// removed members, so a slice may fail to link even though it is well-formed.
+///
+/// Returns `None` if a kept attribute's references cannot be located, e.g. a non-standard
+/// attribute, or the class file cannot be encoded.
+pub fn slice(class_file: &JavaClassFile, mut predicate: impl FnMut(&Member) -> bool) -> Option<Vec<u8>> {
+    let constant_pool = &class_file.constant_pool;
+    let mut visit = |kind: MemberKind, index: usize, access_flags: u16, name_index: usize, descriptor_index: usize| {
+        predicate(&Member {
+            kind,
+            index,
+            access_flags,
+            name: get_utf8(constant_pool, name_index).unwrap_or_default(),
+            descriptor: get_utf8(constant_pool, descriptor_index).unwrap_or_default(),
+        })
+    };
+    let keep_fields: Vec<bool> = class_file
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| visit(MemberKind::Field, i, field.access_flags, field.name_index, field.descriptor_index))
+        .collect();
+    let keep_methods: Vec<bool> = class_file
+        .methods
+        .iter()
+        .enumerate()
+        .map(|(i, method)| visit(MemberKind::Method, i, method.access_flags, method.name_index, method.descriptor_index))
+        .collect();
+
+    let filtered = remove_members(&encode(class_file).ok()?, &keep_fields, &keep_methods)?;
+
+    // Keep the referenced entries and everything they refer to, and index 0.
+    let mut used = referenced_constants(&filtered)?;
+    used[0] = true;
+    let mut pending: Vec<usize> = (0..used.len()).filter(|index| used[*index]).collect();
+    while let Some(index) = pending.pop() {
+        remap_constant(&constant_pool[index], |reference| {
+            if let Some(false) = used.get(reference) {
+                used[reference] = true;
+                pending.push(reference);
+            }
+            reference
+        });
+    }
+    // The unusable entry following a long or double goes with it.
+    for index in 1..used.len() {
+        if used[index - 1] && matches!(constant_pool[index - 1], ConstantPoolInfo::Long(_) | ConstantPoolInfo::Double(_)) {
+            used[index] = true;
+        }
+    }
+
+    let mut indices = vec![0u16; constant_pool.len()];
+    let mut kept = Vec::new();
+    for (index, constant) in constant_pool.iter().enumerate() {
+        if used[index] {
+            indices[index] = kept.len() as u16;
+            kept.push(constant);
+        }
+    }
+    let new_pool: Vec<ConstantPoolInfo> = kept
+        .into_iter()
+        .map(|constant| remap_constant(constant, |i| indices.get(i).map_or(i, |new| *new as usize)))
+        .collect();
+
+    rewrite_references(&filtered, indices, &new_pool)
+}
+
+/// Copies an encoded class file without the fields and methods whose flag is false.
+fn remove_members(bytes: &[u8], keep_fields: &[bool], keep_methods: &[bool]) -> Option<Vec<u8>> {
+    let mut reader = ClassReader::new(bytes);
+    reader.read_bytes(2 * size_of::<u32>(), "magic").ok()?;
+    let mut context = DecodeContext::new(&Default::default());
+    crate::constant_pool::decode_constant_pool(&mut reader, &mut context).ok()?;
+    reader.read_bytes(3 * size_of::<u16>(), "this_class").ok()?;
+    let interfaces_count = reader.read_u16("interfaces").ok()? as usize;
+    reader.read_bytes(interfaces_count * size_of::<u16>(), "interfaces").ok()?;
+
+    let mut output = bytes[..reader.offset()].to_vec();
+    for keep in [keep_fields, keep_methods] {
+        let count = reader.read_u16("members_count").ok()? as usize;
+        let mut members = Vec::new();
+        let mut kept = 0u16;
+        for i in 0..count {
+            let start = reader.offset();
+            reader.read_bytes(3 * size_of::<u16>(), "member_info").ok()?;
+            let attributes_count = reader.read_u16("attributes_count").ok()?;
+            for _ in 0..attributes_count {
+                reader.read_u16("attribute_info").ok()?;
+                let length = reader.read_u32("attribute_info").ok()? as usize;
+                reader.read_bytes(length, "attribute_info").ok()?;
+            }
+            if keep.get(i).copied().unwrap_or(true) {
+                members.extend_from_slice(&bytes[start..reader.offset()]);
+                kept += 1;
+            }
+        }
+        write_u16(&mut output, kept);
+        output.extend_from_slice(&members);
+    }
+    output.extend_from_slice(reader.remaining());
+    Some(output)
+}
@@ -0,0 +1,228 @@
+//! Frames of Java stack traces as `Throwable.printStackTrace` prints them, e.g. to symbolicate
+//! crash reports against the classes they were thrown from.
+//!
+//! ref. https://docs.oracle.com/en/java/javase/17/docs/api/java.base/java/lang/StackTraceElement.html#toString()
+
+use std::{borrow::Cow, fmt};
+
+use crate::{code::CodeView, types::*};
+
+/// A frame of a stack trace, e.g. `app//com.example.Main.run(Main.java:12)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackFrame<'s> {
+    /// Name of the class loader, e.g. `app`, printed unless it is a built-in one of the JDK.
+    pub class_loader: Option<&'s str>,
+    /// Name of the module, e.g. `java.base`.
+    pub module: Option<&'s str>,
+    /// Version of the module, e.g. `17.0.2`, printed for modules not of the JDK.
+    pub module_version: Option<&'s str>,
+    /// Binary name of the class with `.`, e.g. `com.example.Outer$Inner`.
+    pub class_name: Cow<'s, str>,
+    pub method_name: &'s str,
+    /// Name of the source file, or `None` for `Unknown Source`.
+    pub source_file: Option<&'s str>,
+    pub line_number: Option<u32>,
+    /// True for `Native Method`.
+    pub native: bool,
+}
+
+impl<'s> StackFrame<'s> {
+    /// Parses a frame, with or without the leading `at ` and indentation of a printed stack
+    /// trace, or returns `None` if `frame` is not one.
+    pub fn parse(frame: &'s str) -> Option<Self> {
+        let frame = frame.trim();
+        let frame = frame.strip_prefix("at ").unwrap_or(frame).trim_start();
+        let (element, location) = frame.strip_suffix(')')?.rsplit_once('(')?;
+
+        let (prefix, member) = match element.rsplit_once('/') {
+            Some((prefix, member)) => (Some(prefix), member),
+            None => (None, element),
+        };
+        let (class_loader, module) = match prefix.map(|prefix| prefix.split_once('/').unwrap_or(("", prefix))) {
+            Some((class_loader, module)) => (non_empty(class_loader), non_empty(module)),
+            None => (None, None),
+        };
+        let (module, module_version) = match module.and_then(|module| module.split_once('@')) {
+            Some((module, version)) => (Some(module), Some(version)),
+            None => (module, None),
+        };
+        let (class_name, method_name) = member.rsplit_once('.')?;
+        if class_name.is_empty() || method_name.is_empty() {
+            return None;
+        }
+
+        let mut frame = StackFrame {
+            class_loader,
+            module,
+            module_version,
+            class_name: Cow::Borrowed(class_name),
+            method_name,
+            source_file: None,
+            line_number: None,
+            native: false,
+        };
+        let (source, line_number) = match location.rsplit_once(':') {
+            Some((source, line_number)) => (source, Some(line_number.parse().ok()?)),
+            None => (location, None),
+        };
+        match source {
+            "Native Method" if line_number.is_none() => frame.native = true,
+            "Unknown Source" => {}
+            "" => return None,
+            source_file => frame.source_file = Some(source_file),
+        }
+        frame.line_number = line_number;
+        Some(frame)
+    }
+
+    /// Returns the name of the class in internal form, e.g. `com/example/Outer$Inner`, to look
+    /// it up in a jar or an index.
+    pub fn internal_class_name(&self) -> String {
+        self.class_name.replace('.', "/")
+    }
+}
+
+/// Formats the frame as `StackTraceElement.toString` does, without the leading `at `.
+impl fmt::Display for StackFrame<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(class_loader) = self.class_loader {
+            write!(f, "{}/", class_loader)?;
+        }
+        if let Some(module) = self.module {
+            f.write_str(module)?;
+            if let Some(version) = self.module_version {
+                write!(f, "@{}", version)?;
+            }
+        }
+        if self.class_loader.is_some() || self.module.is_some() {
+            f.write_str("/")?;
+        }
+        write!(f, "{}.{}(", self.class_name, self.method_name)?;
+        match (self.native, self.source_file, self.line_number) {
+            (true, _, _) => f.write_str("Native Method")?,
+            (false, Some(source_file), Some(line_number)) => write!(f, "{}:{}", source_file, line_number)?,
+            (false, Some(source_file), None) => f.write_str(source_file)?,
+            (false, None, Some(line_number)) => write!(f, "Unknown Source:{}", line_number)?,
+            (false, None, None) => f.write_str("Unknown Source")?,
+        }
+        f.write_str(")")
+    }
+}
+
+fn non_empty(name: &str) -> Option<&str> {
+    Some(name).filter(|name| !name.is_empty())
+}
+
+/// Returns the frame of `method` of `class_file` executing the instruction at `pc`, with the
+/// line from the LineNumberTable if there is one and `pc` is given. Class loader and module are
+/// left unset, since the class file does not record them.
+///
+/// Returns `None` if the name of the class or method cannot be resolved.
+pub fn frame<'c>(class_file: &'c JavaClassFile, method: &MethodInfo, pc: Option<u16>) -> Option<StackFrame<'c>> {
+    let constant_pool = &class_file.constant_pool;
+    let class_name = get_class_name(constant_pool, class_file.this_class)?;
+    let native = MethodAccessFlag::Native.test(method.access_flags);
+    let line_number = match (pc, CodeView::new(method, constant_pool)) {
+        (Some(pc), Ok(Some(code))) if !native => code.line_number(pc).ok().flatten().map(u32::from),
+        _ => None,
+    };
+    Some(StackFrame {
+        class_loader: None,
+        module: None,
+        module_version: None,
+        class_name: Cow::Owned(class_name.replace('/', ".")),
+        method_name: get_utf8(constant_pool, method.name_index)?,
+        source_file: class_file.source_file(),
+        line_number,
+        native,
+    })
+}
+
+/// A method of a class which may have executed a frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameMatch<'c> {
+    /// Index of the method in the `methods` of the class file.
+    pub method_index: usize,
+    pub descriptor: &'c str,
+    /// Offsets of the instructions starting the line of the frame, in LineNumberTable order.
+    /// Empty if the frame has no line or the method has no LineNumberTable.
+    pub pcs: Vec<u16>,
+}
+
+/// Looks up the methods of `class_file` which may have executed `frame`: those of its name,
+/// native if the frame is, and with code on its line if it has one and they have a
+/// LineNumberTable. Overloads without a LineNumberTable are kept, since they cannot be told
+/// apart.
+///
+/// Returns nothing if `class_file` is not the class of the frame.
+pub fn resolve_frame<'c>(class_file: &'c JavaClassFile, frame: &StackFrame) -> Vec<FrameMatch<'c>> {
+    let constant_pool = &class_file.constant_pool;
+    if get_class_name(constant_pool, class_file.this_class) != Some(frame.internal_class_name().as_str()) {
+        return Vec::new();
+    }
+    let mut matches = Vec::new();
+    for (method_index, method) in class_file.methods.iter().enumerate() {
+        if get_utf8(constant_pool, method.name_index) != Some(frame.method_name)
+            || MethodAccessFlag::Native.test(method.access_flags) != frame.native
+        {
+            continue;
+        }
+        let Some(descriptor) = get_utf8(constant_pool, method.descriptor_index) else {
+            continue;
+        };
+        let line_numbers = match CodeView::new(method, constant_pool) {
+            Ok(Some(code)) => code.line_numbers().unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        let pcs = match frame.line_number {
+            Some(line_number) if !line_numbers.is_empty() => {
+                let on_line = line_numbers.iter().filter(|entry| entry.line_number as u32 == line_number);
+                let pcs: Vec<_> = on_line.map(|entry| entry.start_pc).collect();
+                if pcs.is_empty() {
+                    continue;
+                }
+                pcs
+            }
+            _ => Vec::new(),
+        };
+        matches.push(FrameMatch { method_index, descriptor, pcs });
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for frame in [
+            "java.base/java.lang.Thread.run(Thread.java:833)",
+            "app//com.example.Main.main(Main.java:5)",
+            "com.foo.loader/foo@9.0/com.foo.Main.run(Main.java:101)",
+            "java.base/jdk.internal.misc.Unsafe.park(Native Method)",
+            "com.example.Outer$Inner.lambda$run$0(Unknown Source)",
+            "com.example.Main.<init>(Main.java)",
+        ] {
+            assert_eq!(StackFrame::parse(frame).unwrap().to_string(), frame);
+        }
+    }
+
+    #[test]
+    fn parses_printed_frames() {
+        let frame = StackFrame::parse("\tat app//com.example.Main$1.call(Main.java:42)").unwrap();
+        assert_eq!(frame.class_loader, Some("app"));
+        assert_eq!(frame.module, None);
+        assert_eq!(frame.internal_class_name(), "com/example/Main$1");
+        assert_eq!(frame.method_name, "call");
+        assert_eq!((frame.source_file, frame.line_number), (Some("Main.java"), Some(42)));
+
+        let frame = StackFrame::parse("at java.base@17.0.2/java.lang.Object.wait(Native Method)").unwrap();
+        assert_eq!((frame.module, frame.module_version), (Some("java.base"), Some("17.0.2")));
+        assert!(frame.native);
+
+        assert_eq!(StackFrame::parse("Caused by: java.lang.Error"), None);
+        assert_eq!(StackFrame::parse("... 3 more"), None);
+        assert_eq!(StackFrame::parse("at Main.run(Main.java:x)"), None);
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    collections::HashMap,
+    io::{self, Read, Write},
+};
+
+use crate::types::*;
+
+/// Magic of the exported string table format.
+const STRING_TABLE_MAGIC: &[u8; 4] = b"JCST";
+const STRING_TABLE_VERSION: u16 = 1;
+
+/// One CONSTANT_Utf8 entry of a [`StringTable`], locating its string in the data block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StringTableEntry {
+    pub constant_pool_index: u16,
+    pub offset: u32,
+    pub length: u32,
+}
+
+/// The CONSTANT_Utf8 entries of a class file, with every distinct string stored once in a
+/// single UTF-8 data block.
+///
+/// The binary layout written by [`StringTable::write`] is, in big-endian order:
+///
+/// ```text
+/// magic "JCST", u16 version
+/// u32 entry_count, entry_count × (u16 constant_pool_index, u32 offset, u32 length)
+/// u32 data_length, data_length bytes of UTF-8
+/// ```
+///
+/// Entries sharing a string share its offset, so tools may scan the data block directly.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StringTable {
+    entries: Vec<StringTableEntry>,
+    data: String,
+}
+
+impl StringTable {
+    /// Collects the CONSTANT_Utf8 entries of `class_file`.
+    pub fn new(class_file: &JavaClassFile) -> Self {
+        let mut table = Self::default();
+        let mut offsets: HashMap<&str, u32> = HashMap::new();
+        for (index, constant) in class_file.constant_pool.iter().enumerate() {
+            let ConstantPoolInfo::Utf8(utf8_info) = constant else {
+                continue;
+            };
+            let value = utf8_info.data.as_ref();
+            let offset = *offsets.entry(value).or_insert_with(|| {
+                let offset = table.data.len() as u32;
+                table.data.push_str(value);
+                offset
+            });
+            table.entries.push(StringTableEntry {
+                constant_pool_index: index as u16,
+                offset,
+                length: value.len() as u32,
+            });
+        }
+        table
+    }
+
+    /// Entries in constant pool order.
+    pub fn entries(&self) -> &[StringTableEntry] {
+        &self.entries
+    }
+
+    /// The distinct strings, concatenated.
+    pub fn data(&self) -> &str {
+        &self.data
+    }
+
+    /// Returns the string of an entry.
+    pub fn resolve(&self, entry: &StringTableEntry) -> Option<&str> {
+        let start = entry.offset as usize;
+        self.data.get(start..start.checked_add(entry.length as usize)?)
+    }
+
+    /// Returns the string of the CONSTANT_Utf8 entry at `constant_pool_index`.
+    pub fn get(&self, constant_pool_index: u16) -> Option<&str> {
+        let position = self
+            .entries
+            .binary_search_by_key(&constant_pool_index, |entry| entry.constant_pool_index)
+            .ok()?;
+        self.resolve(&self.entries[position])
+    }
+
+    /// Writes the table in the binary layout described on [`StringTable`].
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(STRING_TABLE_MAGIC)?;
+        writer.write_all(&STRING_TABLE_VERSION.to_be_bytes())?;
+        writer.write_all(&(self.entries.len() as u32).to_be_bytes())?;
+        for entry in &self.entries {
+            writer.write_all(&entry.constant_pool_index.to_be_bytes())?;
+            writer.write_all(&entry.offset.to_be_bytes())?;
+            writer.write_all(&entry.length.to_be_bytes())?;
+        }
+        writer.write_all(&(self.data.len() as u32).to_be_bytes())?;
+        writer.write_all(self.data.as_bytes())
+    }
+
+    /// Reads a table written by [`StringTable::write`].
+    pub fn read(mut reader: impl Read) -> io::Result<Self> {
+        fn invalid(message: &str) -> io::Error {
+            io::Error::new(io::ErrorKind::InvalidData, message)
+        }
+
+        fn read_array<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+            let mut buffer = [0u8; N];
+            reader.read_exact(&mut buffer)?;
+            Ok(buffer)
+        }
+
+        if &read_array::<4>(&mut reader)? != STRING_TABLE_MAGIC {
+            return Err(invalid("not a string table"));
+        }
+        if u16::from_be_bytes(read_array(&mut reader)?) != STRING_TABLE_VERSION {
+            return Err(invalid("unsupported string table version"));
+        }
+
+        let count = u32::from_be_bytes(read_array(&mut reader)?);
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            entries.push(StringTableEntry {
+                constant_pool_index: u16::from_be_bytes(read_array(&mut reader)?),
+                offset: u32::from_be_bytes(read_array(&mut reader)?),
+                length: u32::from_be_bytes(read_array(&mut reader)?),
+            });
+        }
+
+        let length = u32::from_be_bytes(read_array(&mut reader)?) as u64;
+        let mut data = Vec::new();
+        reader.take(length).read_to_end(&mut data)?;
+        if data.len() as u64 != length {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let data = String::from_utf8(data).map_err(|_| invalid("string data is not UTF-8"))?;
+
+        let table = Self { entries, data };
+        if table.entries.iter().any(|entry| table.resolve(entry).is_none()) {
+            return Err(invalid("string table entry out of bounds"));
+        }
+        Ok(table)
+    }
+}
@@ -0,0 +1,445 @@
+use crate::{agent::code_attribute, encode, types::*};
+
+const INVOCATION_HANDLER: &str = "java/lang/reflect/InvocationHandler";
+const INVOKE_DESCRIPTOR: &str = "(Ljava/lang/Object;Ljava/lang/reflect/Method;[Ljava/lang/Object;)Ljava/lang/Object;";
+
+/// Generates the bytes of a proxy class implementing `interface_class`, like the classes
+/// `java.lang.reflect.Proxy` defines at run time.
+///
+/// The class is named after the interface with a `$Proxy` suffix, stores the
+/// `java.lang.reflect.InvocationHandler` passed to its public constructor in the field
+/// `handler_field`, and implements every instance method of the interface, as well as
+/// `equals`, `hashCode` and `toString`, by calling `InvocationHandler.invoke` with the
+/// proxy, the `java.lang.reflect.Method` and the boxed arguments, then unboxing or
+/// casting the result. Methods inherited from superinterfaces are not implemented.
+///
+/// Unlike `Proxy`, checked exceptions thrown by the handler are not wrapped in
+/// `UndeclaredThrowableException`.
+///
+/// Returns `None` if `interface_class` is not an interface, has malformed names or
+/// descriptors, or the proxy is too large to encode.
+///
+/// ref. https://docs.oracle.com/javase/8/docs/api/java/lang/reflect/Proxy.html
+pub fn generate_proxy(interface_class: &JavaClassFile, handler_field: &str) -> Option<Vec<u8>> {
+    if interface_class.access_flags & ClassAccessFlag::Interface as u16 == 0 {
+        return None;
+    }
+
+    let interface_name = get_class_name(&interface_class.constant_pool, interface_class.this_class)?;
+    let mut targets = instance_methods(interface_class)?;
+    for (name, descriptor) in [
+        ("equals", "(Ljava/lang/Object;)Z"),
+        ("hashCode", "()I"),
+        ("toString", "()Ljava/lang/String;"),
+    ] {
+        if !targets.iter().any(|target| target.name == name && target.descriptor == descriptor) {
+            targets.push(Target {
+                owner: "java/lang/Object",
+                name,
+                descriptor,
+                access_flags: MethodAccessFlag::Public as u16,
+            });
+        }
+    }
+    for target in &mut targets {
+        if target.owner.is_empty() {
+            target.owner = interface_name;
+        }
+    }
+
+    let proxy_name = format!("{}$Proxy", interface_name);
+    let mut constant_pool = ConstantPoolBuilder::new();
+    let this_class = constant_pool.class(proxy_name.as_str());
+    let super_class = constant_pool.class("java/lang/Object");
+    let interface = constant_pool.class(interface_name);
+
+    let handler_descriptor = format!("L{};", INVOCATION_HANDLER);
+    let handler = constant_pool.field_ref(proxy_name.as_str(), handler_field, handler_descriptor.as_str());
+    let invoke = constant_pool.interface_method_ref(INVOCATION_HANDLER, "invoke", INVOKE_DESCRIPTOR);
+    let object_init = constant_pool.method_ref("java/lang/Object", "<init>", "()V");
+
+    let mut fields = vec![FieldInfo {
+        access_flags: FieldAccessFlag::Private as u16 | FieldAccessFlag::Final as u16,
+        name_index: constant_pool.utf8(handler_field),
+        descriptor_index: constant_pool.utf8(handler_descriptor.as_str()),
+        attributes: Attributes::new(),
+    }];
+
+    let mut init = Assembler::default();
+    init.op(Opcode::Aload0);
+    init.op_u16(Opcode::Invokespecial, object_init);
+    init.op(Opcode::Aload0);
+    init.op(Opcode::Aload1);
+    init.op_u16(Opcode::Putfield, handler);
+    init.op(Opcode::Return);
+    let mut bodies = vec![Body {
+        access_flags: MethodAccessFlag::Public as u16,
+        name_index: constant_pool.utf8("<init>"),
+        descriptor_index: constant_pool.utf8(format!("({})V", handler_descriptor)),
+        max_stack: 2,
+        max_locals: 2,
+        code: init.code,
+    }];
+
+    // <clinit> looks the methods up once, e.g. `m0 = Foo.class.getMethod("run", new Class[] { int.class })`.
+    let mut clinit = Assembler::default();
+    let get_method = constant_pool.method_ref(
+        "java/lang/Class",
+        "getMethod",
+        "(Ljava/lang/String;[Ljava/lang/Class;)Ljava/lang/reflect/Method;",
+    );
+    for (i, target) in targets.iter().enumerate() {
+        let (parameters, return_descriptor) = split_method_descriptor(target.descriptor)?;
+        let method_name = format!("m{}", i);
+        fields.push(FieldInfo {
+            access_flags: FieldAccessFlag::Private as u16 | FieldAccessFlag::Static as u16 | FieldAccessFlag::Final as u16,
+            name_index: constant_pool.utf8(method_name.clone()),
+            descriptor_index: constant_pool.utf8("Ljava/lang/reflect/Method;"),
+            attributes: Attributes::new(),
+        });
+        let method_field = constant_pool.field_ref(proxy_name.as_str(), method_name, "Ljava/lang/reflect/Method;");
+
+        let owner = constant_pool.class(target.owner);
+        clinit.op_u16(Opcode::LdcW, owner);
+        let name = constant_pool.string(target.name);
+        clinit.op_u16(Opcode::LdcW, name);
+        clinit.int(parameters.len());
+        clinit.op_u16(Opcode::Anewarray, constant_pool.class("java/lang/Class"));
+        for (j, parameter) in parameters.iter().enumerate() {
+            clinit.op(Opcode::Dup);
+            clinit.int(j);
+            match wrapper(parameter) {
+                Some((wrapper, _)) => {
+                    clinit.op_u16(Opcode::Getstatic, constant_pool.field_ref(wrapper, "TYPE", "Ljava/lang/Class;"))
+                }
+                None => clinit.op_u16(Opcode::LdcW, constant_pool.class(class_name(parameter))),
+            }
+            clinit.op(Opcode::Aastore);
+        }
+        clinit.op_u16(Opcode::Invokevirtual, get_method);
+        clinit.op_u16(Opcode::Putstatic, method_field);
+
+        // return (R) handler.invoke(this, m0, new Object[] { Integer.valueOf(arg0), ... });
+        let mut code = Assembler::default();
+        code.op(Opcode::Aload0);
+        code.op_u16(Opcode::Getfield, handler);
+        code.op(Opcode::Aload0);
+        code.op_u16(Opcode::Getstatic, method_field);
+        let mut slot = 1;
+        let mut max_stack = 4;
+        if parameters.is_empty() {
+            code.op(Opcode::AconstNull);
+        } else {
+            code.int(parameters.len());
+            code.op_u16(Opcode::Anewarray, constant_pool.class("java/lang/Object"));
+            for (j, parameter) in parameters.iter().enumerate() {
+                code.op(Opcode::Dup);
+                code.int(j);
+                code.load(parameter, slot);
+                if let Some((wrapper, _)) = wrapper(parameter) {
+                    let value_of = format!("({})L{};", parameter, wrapper);
+                    code.op_u16(Opcode::Invokestatic, constant_pool.method_ref(wrapper, "valueOf", value_of));
+                }
+                code.op(Opcode::Aastore);
+                slot += slots(parameter);
+                max_stack = max_stack.max(6 + slots(parameter));
+            }
+        }
+        code.op_u16(Opcode::Invokeinterface, invoke);
+        code.code.extend([4, 0]);
+        match return_descriptor {
+            "V" => code.op(Opcode::Pop),
+            "Ljava/lang/Object;" => {}
+            _ => match wrapper(return_descriptor) {
+                Some((wrapper, unbox)) => {
+                    code.op_u16(Opcode::Checkcast, constant_pool.class(wrapper));
+                    let unbox_descriptor = format!("(){}", return_descriptor);
+                    code.op_u16(Opcode::Invokevirtual, constant_pool.method_ref(wrapper, unbox, unbox_descriptor));
+                }
+                None => code.op_u16(Opcode::Checkcast, constant_pool.class(class_name(return_descriptor))),
+            },
+        }
+        code.ret(return_descriptor);
+
+        bodies.push(Body {
+            access_flags: MethodAccessFlag::Public as u16 | MethodAccessFlag::Final as u16 | target.varargs(),
+            name_index: constant_pool.utf8(target.name),
+            descriptor_index: constant_pool.utf8(target.descriptor),
+            max_stack: max_stack as u16,
+            max_locals: slot as u16,
+            code: code.code,
+        });
+    }
+    clinit.op(Opcode::Return);
+    bodies.push(Body {
+        access_flags: MethodAccessFlag::Static as u16,
+        name_index: constant_pool.utf8("<clinit>"),
+        descriptor_index: constant_pool.utf8("()V"),
+        max_stack: 6,
+        max_locals: 0,
+        code: clinit.code,
+    });
+
+    finish(constant_pool, this_class, super_class, vec![interface], fields, &bodies)
+}
+
+/// Generates the bytes of a class forwarding `methods` to an instance of `target_class`.
+///
+/// The class is named after the target with a `$Delegate` suffix, stores the target passed
+/// to its public constructor in the field `delegate`, and implements the target if it is
+/// an interface. An entry of `methods` selects either all instance methods with a name,
+/// e.g. `size`, or one with a name and descriptor, e.g. `get(I)Ljava/lang/Object;`.
+///
+/// Returns `None` if an entry matches no method, `target_class` has malformed names or
+/// descriptors, or the delegate is too large to encode.
+pub fn generate_delegate(target_class: &JavaClassFile, methods: &[&str]) -> Option<Vec<u8>> {
+    let target_name = get_class_name(&target_class.constant_pool, target_class.this_class)?;
+    let is_interface = target_class.access_flags & ClassAccessFlag::Interface as u16 != 0;
+    let candidates = instance_methods(target_class)?;
+    let mut targets: Vec<&Target> = Vec::new();
+    for selector in methods {
+        let matches: Vec<&Target> = candidates
+            .iter()
+            .filter(|target| match selector.find('(') {
+                Some(at) => target.name == &selector[..at] && target.descriptor == &selector[at..],
+                None => target.name == *selector,
+            })
+            .collect();
+        if matches.is_empty() {
+            return None;
+        }
+        for target in matches {
+            if !targets.iter().any(|selected| std::ptr::eq(*selected, target)) {
+                targets.push(target);
+            }
+        }
+    }
+
+    let delegate_name = format!("{}$Delegate", target_name);
+    let mut constant_pool = ConstantPoolBuilder::new();
+    let this_class = constant_pool.class(delegate_name.as_str());
+    let super_class = constant_pool.class("java/lang/Object");
+    let interfaces = if is_interface { vec![constant_pool.class(target_name)] } else { Vec::new() };
+
+    let delegate_descriptor = format!("L{};", target_name);
+    let delegate = constant_pool.field_ref(delegate_name.as_str(), "delegate", delegate_descriptor.as_str());
+    let object_init = constant_pool.method_ref("java/lang/Object", "<init>", "()V");
+
+    let fields = vec![FieldInfo {
+        access_flags: FieldAccessFlag::Private as u16 | FieldAccessFlag::Final as u16,
+        name_index: constant_pool.utf8("delegate"),
+        descriptor_index: constant_pool.utf8(delegate_descriptor.as_str()),
+        attributes: Attributes::new(),
+    }];
+
+    let mut init = Assembler::default();
+    init.op(Opcode::Aload0);
+    init.op_u16(Opcode::Invokespecial, object_init);
+    init.op(Opcode::Aload0);
+    init.op(Opcode::Aload1);
+    init.op_u16(Opcode::Putfield, delegate);
+    init.op(Opcode::Return);
+    let mut bodies = vec![Body {
+        access_flags: MethodAccessFlag::Public as u16,
+        name_index: constant_pool.utf8("<init>"),
+        descriptor_index: constant_pool.utf8(format!("({})V", delegate_descriptor)),
+        max_stack: 2,
+        max_locals: 2,
+        code: init.code,
+    }];
+
+    // return delegate.name(arg0, ...);
+    for target in targets {
+        let (parameters, return_descriptor) = split_method_descriptor(target.descriptor)?;
+        let mut code = Assembler::default();
+        code.op(Opcode::Aload0);
+        code.op_u16(Opcode::Getfield, delegate);
+        let mut slot = 1;
+        for parameter in &parameters {
+            code.load(parameter, slot);
+            slot += slots(parameter);
+        }
+        if is_interface {
+            let method = constant_pool.interface_method_ref(target_name, target.name, target.descriptor);
+            code.op_u16(Opcode::Invokeinterface, method);
+            code.code.extend([slot as u8, 0]);
+        } else {
+            let method = constant_pool.method_ref(target_name, target.name, target.descriptor);
+            code.op_u16(Opcode::Invokevirtual, method);
+        }
+        code.ret(return_descriptor);
+
+        bodies.push(Body {
+            access_flags: MethodAccessFlag::Public as u16 | target.varargs(),
+            name_index: constant_pool.utf8(target.name),
+            descriptor_index: constant_pool.utf8(target.descriptor),
+            max_stack: slot.max(slots(return_descriptor)) as u16,
+            max_locals: slot as u16,
+            code: code.code,
+        });
+    }
+
+    finish(constant_pool, this_class, super_class, interfaces, fields, &bodies)
+}
+
+/// A method a generated class implements. An empty owner stands for the input class.
+struct Target<'c> {
+    owner: &'c str,
+    name: &'c str,
+    descriptor: &'c str,
+    access_flags: u16,
+}
+
+impl Target<'_> {
+    fn varargs(&self) -> u16 {
+        self.access_flags & MethodAccessFlag::Varargs as u16
+    }
+}
+
+/// Returns the non-private instance methods of a class, excluding constructors.
+fn instance_methods<'c>(class_file: &'c JavaClassFile) -> Option<Vec<Target<'c>>> {
+    let excluded = MethodAccessFlag::Private as u16 | MethodAccessFlag::Static as u16;
+    let mut targets = Vec::new();
+    for method in &class_file.methods {
+        let name = get_utf8(&class_file.constant_pool, method.name_index)?;
+        if method.access_flags & excluded != 0 || name.starts_with('<') {
+            continue;
+        }
+        targets.push(Target {
+            owner: "",
+            name,
+            descriptor: get_utf8(&class_file.constant_pool, method.descriptor_index)?,
+            access_flags: method.access_flags,
+        });
+    }
+    Some(targets)
+}
+
+/// A method of a generated class with its code.
+struct Body {
+    access_flags: u16,
+    name_index: usize,
+    descriptor_index: usize,
+    max_stack: u16,
+    max_locals: u16,
+    code: Vec<u8>,
+}
+
+fn finish(
+    mut constant_pool: ConstantPoolBuilder,
+    this_class: usize,
+    super_class: usize,
+    interfaces: Vec<usize>,
+    fields: Vec<FieldInfo>,
+    bodies: &[Body],
+) -> Option<Vec<u8>> {
+    let code_name = constant_pool.utf8("Code") as u16;
+    let methods = bodies
+        .iter()
+        .map(|body| MethodInfo {
+            access_flags: body.access_flags,
+            name_index: body.name_index,
+            descriptor_index: body.descriptor_index,
+            attributes: Attributes::from(vec![(
+                code_name,
+                code_attribute(body.max_stack, body.max_locals, &body.code),
+            )]),
+        })
+        .collect();
+
+    let class_file = JavaClassFile {
+        // Java 8: no StackMapTable is needed as the methods have no branches.
+        major_version: 52,
+        constant_pool: constant_pool.build(),
+        access_flags: ClassAccessFlag::Public as u16 | ClassAccessFlag::Final as u16 | ClassAccessFlag::Super as u16,
+        this_class,
+        super_class,
+        interfaces,
+        fields,
+        methods,
+        ..JavaClassFile::empty()
+    };
+
+    encode(&class_file).ok()
+}
+
+#[derive(Default)]
+struct Assembler {
+    code: Vec<u8>,
+}
+
+impl Assembler {
+    fn op(&mut self, opcode: Opcode) {
+        self.code.push(opcode as u8);
+    }
+
+    fn op_u16(&mut self, opcode: Opcode, index: usize) {
+        self.op(opcode);
+        self.code.extend((index as u16).to_be_bytes());
+    }
+
+    /// Pushes a non-negative int constant.
+    fn int(&mut self, value: usize) {
+        match value {
+            0..=5 => self.code.push(Opcode::Iconst0 as u8 + value as u8),
+            6..=127 => self.code.extend([Opcode::Bipush as u8, value as u8]),
+            _ => {
+                self.code.push(Opcode::Sipush as u8);
+                self.code.extend((value as u16).to_be_bytes());
+            }
+        }
+    }
+
+    /// Loads the local variable in `slot`; parameter slots always fit in a byte.
+    fn load(&mut self, descriptor: &str, slot: usize) {
+        self.code.extend([Opcode::Iload as u8 + kind(descriptor), slot as u8]);
+    }
+
+    fn ret(&mut self, descriptor: &str) {
+        match descriptor {
+            "V" => self.op(Opcode::Return),
+            _ => self.code.push(Opcode::Ireturn as u8 + kind(descriptor)),
+        }
+    }
+}
+
+/// Returns the offset of the typed instruction from its int form, in the order int, long,
+/// float, double and reference shared by the load, store and return instructions.
+fn kind(descriptor: &str) -> u8 {
+    match descriptor.as_bytes()[0] {
+        b'J' => 1,
+        b'F' => 2,
+        b'D' => 3,
+        b'L' | b'[' => 4,
+        _ => 0,
+    }
+}
+
+fn slots(descriptor: &str) -> usize {
+    match descriptor {
+        "V" => 0,
+        "J" | "D" => 2,
+        _ => 1,
+    }
+}
+
+/// Returns the wrapper class and unboxing method of a primitive type.
+fn wrapper(descriptor: &str) -> Option<(&'static str, &'static str)> {
+    match descriptor {
+        "Z" => Some(("java/lang/Boolean", "booleanValue")),
+        "B" => Some(("java/lang/Byte", "byteValue")),
+        "C" => Some(("java/lang/Character", "charValue")),
+        "S" => Some(("java/lang/Short", "shortValue")),
+        "I" => Some(("java/lang/Integer", "intValue")),
+        "J" => Some(("java/lang/Long", "longValue")),
+        "F" => Some(("java/lang/Float", "floatValue")),
+        "D" => Some(("java/lang/Double", "doubleValue")),
+        _ => None,
+    }
+}
+
+/// Returns the name a CONSTANT_Class uses for a reference type: the internal name of a
+/// class or the descriptor of an array.
+fn class_name(descriptor: &str) -> &str {
+    descriptor.strip_prefix('L').and_then(|name| name.strip_suffix(';')).unwrap_or(descriptor)
+}
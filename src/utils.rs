@@ -1,34 +1,190 @@
-#[inline(always)]
-pub fn read_u16(buffer: &[u8]) -> u16 {
-    unsafe { *(buffer.as_ptr() as *const u16) }.to_le()
+use std::borrow::Cow;
+#[cfg(allocator_api)]
+pub use std::alloc::{Allocator, Global};
+
+use crate::{diagnostics::Diagnostics, error::DecodeError, options::{AttributeDecoders, DuplicateAttributePolicy, ParseOptions}, validate::ValidationMode};
+
+/// Reads a string in the modified UTF-8 encoding used by CONSTANT_Utf8.
+///
+/// Borrows the buffer when it is also valid standard UTF-8, otherwise converts the
+/// encoded NULs (0xC0 0x80) and surrogate pairs. Malformed sequences and lone surrogates,
+/// which a string cannot hold, are replaced with U+FFFD, so the string is only a view of
+/// such bytes: see [`modified_utf8_error`].
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.4.7
+pub fn read_modified_utf8(buffer: &[u8]) -> Cow<'_, str> {
+    if let Ok(data) = std::str::from_utf8(buffer) {
+        return Cow::Borrowed(data);
+    }
+
+    let mut units = Vec::with_capacity(buffer.len());
+    let mut i = 0;
+    while i < buffer.len() {
+        let (unit, length) = modified_utf8_unit(buffer, i).unwrap_or((char::REPLACEMENT_CHARACTER as u16, 1));
+        units.push(unit);
+        i += length;
+    }
+
+    Cow::Owned(String::from_utf16_lossy(&units))
 }
 
-#[inline(always)]
-pub fn read_u32(buffer: &[u8]) -> u32 {
-    unsafe { *(buffer.as_ptr() as *const u32) }.to_le()
+/// Returns the offset of the first byte of `buffer` which is not valid modified UTF-8, or
+/// `None` if all are: a NUL byte, a byte of a standard 4-byte sequence, a malformed sequence,
+/// or a surrogate not paired with another.
+pub fn modified_utf8_error(buffer: &[u8]) -> Option<usize> {
+    let is_high = |unit: u16| (0xD800..0xDC00).contains(&unit);
+    let is_low = |unit: u16| (0xDC00..0xE000).contains(&unit);
+    let mut i = 0;
+    while i < buffer.len() {
+        match modified_utf8_unit(buffer, i) {
+            Some((0, 1)) | None => return Some(i),
+            Some((unit, 3)) if is_high(unit) => match modified_utf8_unit(buffer, i + 3) {
+                Some((unit, 3)) if is_low(unit) => i += 6,
+                _ => return Some(i),
+            },
+            Some((unit, _)) if is_low(unit) => return Some(i),
+            Some((_, length)) => i += length,
+        }
+    }
+    None
 }
 
-#[inline(always)]
-pub fn read_i32(buffer: &[u8]) -> i32 {
-    unsafe { *(buffer.as_ptr() as *const i32) }.to_le()
+/// Reads the UTF-16 code unit encoded at `i` in one to three bytes, with its length.
+fn modified_utf8_unit(buffer: &[u8], i: usize) -> Option<(u16, usize)> {
+    let x = *buffer.get(i)? as u16;
+    let continuation = |offset: usize| match buffer.get(i + offset) {
+        Some(&byte) if byte & 0xC0 == 0x80 => Some(byte as u16 & 0x3F),
+        _ => None,
+    };
+    if x & 0x80 == 0 {
+        Some((x, 1))
+    } else if x & 0xE0 == 0xC0 {
+        Some((((x & 0x1F) << 6) | continuation(1)?, 2))
+    } else if x & 0xF0 == 0xE0 {
+        Some((((x & 0x0F) << 12) | (continuation(1)? << 6) | continuation(2)?, 3))
+    } else {
+        None
+    }
 }
 
-#[inline(always)]
-pub fn read_f32(buffer: &[u8]) -> f32 {
-    f32::from_bits(unsafe { *(buffer.as_ptr() as *const f32) }.to_bits().swap_bytes())
+/// Encodes a string in the modified UTF-8 encoding used by CONSTANT_Utf8.
+///
+/// Borrows the string when it contains neither NUL nor supplementary characters,
+/// in which case both encodings are identical.
+pub fn to_modified_utf8(data: &str) -> Cow<'_, [u8]> {
+    if !data.chars().any(|c| c == '\0' || c as u32 > 0xFFFF) {
+        return Cow::Borrowed(data.as_bytes());
+    }
+
+    let mut bytes = Vec::with_capacity(data.len() + 8);
+    for unit in data.encode_utf16() {
+        match unit {
+            0x0001..=0x007F => bytes.push(unit as u8),
+            0x0000 | 0x0080..=0x07FF => {
+                bytes.push(0xC0 | (unit >> 6) as u8);
+                bytes.push(0x80 | (unit & 0x3F) as u8);
+            }
+            _ => {
+                bytes.push(0xE0 | (unit >> 12) as u8);
+                bytes.push(0x80 | ((unit >> 6) & 0x3F) as u8);
+                bytes.push(0x80 | (unit & 0x3F) as u8);
+            }
+        }
+    }
+
+    Cow::Owned(bytes)
 }
 
 #[inline(always)]
-pub fn read_i64(buffer: &[u8]) -> i64 {
-    unsafe { *(buffer.as_ptr() as *const i64) }.to_le()
+pub fn write_u8(buffer: &mut Vec<u8>, value: u8) {
+    buffer.push(value);
 }
 
 #[inline(always)]
-pub fn read_f64(buffer: &[u8]) -> f64 {
-    f64::from_bits(unsafe { *(buffer.as_ptr() as *const f64) }.to_bits().swap_bytes())
+pub fn write_u16(buffer: &mut Vec<u8>, value: u16) {
+    buffer.extend_from_slice(&value.to_be_bytes());
 }
 
 #[inline(always)]
-pub fn read_str(buffer: &[u8]) -> &str {
-    unsafe { std::str::from_utf8_unchecked(buffer) }
-}
\ No newline at end of file
+pub fn write_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&value.to_be_bytes());
+}
+
+/// 64-bit FNV-1a hash.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF2_9CE4_8422_2325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01B3))
+}
+
+/// State shared by the decoders during a decode.
+#[derive(Debug)]
+pub struct DecodeContext {
+    pub budget: AllocationBudget,
+    pub diagnostics: Diagnostics,
+    pub duplicate_attributes: DuplicateAttributePolicy,
+    /// Owner of the attributes being decoded, used as the location of diagnostics.
+    pub location: String,
+    /// In lenient mode, attributes which fail to decode are kept raw instead.
+    pub mode: ValidationMode,
+    pub attribute_decoders: AttributeDecoders,
+    pub lazy_attributes: bool,
+    /// Number of Code and Record attributes enclosing the attributes being decoded.
+    pub attribute_depth: usize,
+}
+
+impl DecodeContext {
+    pub fn new(options: &ParseOptions) -> Self {
+        Self {
+            budget: AllocationBudget::new(options.max_allocation),
+            diagnostics: Diagnostics::new(),
+            duplicate_attributes: options.duplicate_attributes,
+            location: String::new(),
+            mode: options.mode,
+            attribute_decoders: options.attribute_decoders.clone(),
+            lazy_attributes: options.lazy_attributes,
+            attribute_depth: 0,
+        }
+    }
+}
+
+/// Tracks the allocation budget of a decode.
+#[derive(Debug)]
+pub struct AllocationBudget {
+    remaining: usize,
+}
+
+impl AllocationBudget {
+    pub fn new(max_allocation: usize) -> Self {
+        Self { remaining: max_allocation }
+    }
+
+    /// Reserves room for `count` values of `T`.
+    pub fn reserve<T>(&mut self, count: usize, while_parsing: &'static str) -> Result<(), DecodeError> {
+        let requested = count
+            .checked_mul(size_of::<T>())
+            .ok_or(DecodeError::SizeOverflow { while_parsing })?;
+        self.remaining = self
+            .remaining
+            .checked_sub(requested)
+            .ok_or(DecodeError::AllocationLimitExceeded {
+                while_parsing,
+                requested,
+                remaining: self.remaining,
+            })?;
+        Ok(())
+    }
+}
+
+/// Checks that `count` entries of at least `min_entry_size` bytes fit in `buffer`.
+pub fn check_count(buffer: &[u8], count: usize, min_entry_size: usize, while_parsing: &'static str) -> Result<(), DecodeError> {
+    let needed = count
+        .checked_mul(min_entry_size)
+        .ok_or(DecodeError::SizeOverflow { while_parsing })?;
+    if needed > buffer.len() {
+        return Err(DecodeError::LengthOutOfBounds {
+            while_parsing,
+            needed,
+            available: buffer.len(),
+        });
+    }
+    Ok(())
+}
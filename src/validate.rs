@@ -0,0 +1,552 @@
+use std::{collections::BTreeSet, fmt};
+
+use crate::types::*;
+
+/// How strictly [`validate_access_flags`] reports problems.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ValidationMode {
+    /// Every problem is an error.
+    #[default]
+    Strict,
+    /// Every problem is a warning.
+    Lenient,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Declares [`RuleId`] with the stable ID, JVMS section and description of each rule.
+macro_rules! rules {
+    ($($rule:ident => ($id:literal, $section:literal, $description:literal $(,)?),)*) => {
+        /// A check made by the validators of this crate.
+        ///
+        /// Each rule has a stable ID naming the edition and section of the JVM specification it
+        /// enforces, e.g. `jvms17-4.6-init-flags`, so downstream tools can document and select
+        /// exactly which checks they run.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub enum RuleId {
+            $($rule,)*
+        }
+
+        impl RuleId {
+            /// Every rule, in the order the validators check them.
+            pub const ALL: &'static [RuleId] = &[$(RuleId::$rule,)*];
+
+            /// Returns the stable ID of the rule, e.g. `jvms17-4.6-init-flags`.
+            pub fn id(self) -> &'static str {
+                match self {
+                    $(RuleId::$rule => $id,)*
+                }
+            }
+
+            /// Returns the section of the JVM specification, Java SE 17 edition, the rule
+            /// enforces, e.g. `4.6`.
+            pub fn section(self) -> &'static str {
+                match self {
+                    $(RuleId::$rule => $section,)*
+                }
+            }
+
+            /// Returns what the rule requires.
+            pub fn description(self) -> &'static str {
+                match self {
+                    $(RuleId::$rule => $description,)*
+                }
+            }
+        }
+    };
+}
+
+rules! {
+    ModuleVersion => ("jvms17-4.1-module-version", "4.1", "ACC_MODULE requires class file version 53.0 or above"),
+    ModuleFlags => ("jvms17-4.1-module-flags", "4.1", "no other flag is set with ACC_MODULE"),
+    ModuleMembers => ("jvms17-4.1-module-members", "4.1", "a module declares no fields or methods"),
+    InterfaceFlags => (
+        "jvms17-4.1-interface-flags",
+        "4.1",
+        "an interface is ACC_ABSTRACT and not ACC_FINAL, ACC_SUPER or ACC_ENUM",
+    ),
+    ClassFlags => (
+        "jvms17-4.1-class-flags",
+        "4.1",
+        "a class is not ACC_ANNOTATION, nor both ACC_FINAL and ACC_ABSTRACT",
+    ),
+    FieldVisibility => (
+        "jvms17-4.5-field-visibility",
+        "4.5",
+        "at most one of ACC_PUBLIC, ACC_PRIVATE and ACC_PROTECTED is set on a field",
+    ),
+    FieldFlags => ("jvms17-4.5-field-flags", "4.5", "a field is not both ACC_FINAL and ACC_VOLATILE"),
+    InterfaceFieldFlags => (
+        "jvms17-4.5-interface-field-flags",
+        "4.5",
+        "an interface field is ACC_PUBLIC, ACC_STATIC and ACC_FINAL only",
+    ),
+    ClinitFlags => ("jvms17-4.6-clinit-flags", "4.6", "<clinit> is ACC_STATIC since class file version 51.0"),
+    MethodVisibility => (
+        "jvms17-4.6-method-visibility",
+        "4.6",
+        "at most one of ACC_PUBLIC, ACC_PRIVATE and ACC_PROTECTED is set on a method",
+    ),
+    InterfaceMethodFlags => (
+        "jvms17-4.6-interface-method-flags",
+        "4.6",
+        "interface methods have the flags allowed for the class file version",
+    ),
+    AbstractMethodFlags => (
+        "jvms17-4.6-abstract-method-flags",
+        "4.6",
+        "an ACC_ABSTRACT method has no implementation flags",
+    ),
+    InitFlags => (
+        "jvms17-4.6-init-flags",
+        "4.6",
+        "<init> is not ACC_STATIC, ACC_FINAL, ACC_SYNCHRONIZED, ACC_BRIDGE, ACC_NATIVE or ACC_ABSTRACT",
+    ),
+    PreviewVersion => ("jvms17-4.1-preview-version", "4.1", "a preview class file targets the supported major version"),
+    ConstantVersion => (
+        "jvms17-4.4-constant-version",
+        "4.4",
+        "constant pool entries are defined in the class file version",
+    ),
+    AttributeVersion => (
+        "jvms17-4.7-attribute-version",
+        "4.7",
+        "predefined attributes are defined in the class file version",
+    ),
+    Magic => ("jvms17-4.1-magic", "4.1", "magic is 0xCAFEBABE"),
+    ConstantReferences => (
+        "jvms17-4.4-constant-references",
+        "4.4",
+        "constant pool entries refer to entries of the right kinds",
+    ),
+    ConstantSlots => (
+        "jvms17-4.4.5-constant-slots",
+        "4.4.5",
+        "Long and Double entries take two slots and no other entry is unusable",
+    ),
+    MethodHandleKind => (
+        "jvms17-4.4.8-method-handle-kind",
+        "4.4.8",
+        "reference_kind is 1 to 9 and matches the referenced entry",
+    ),
+    Descriptors => ("jvms17-4.3-descriptors", "4.3", "field and method descriptors are well-formed"),
+    ClassReferences => (
+        "jvms17-4.1-class-references",
+        "4.1",
+        "this_class, super_class and interfaces refer to CONSTANT_Class entries",
+    ),
+    FieldReferences => (
+        "jvms17-4.5-field-references",
+        "4.5",
+        "the name and descriptor of a field are CONSTANT_Utf8 entries",
+    ),
+    MethodReferences => (
+        "jvms17-4.6-method-references",
+        "4.6",
+        "the name and descriptor of a method are CONSTANT_Utf8 entries",
+    ),
+    AttributeNames => ("jvms17-4.7-attribute-names", "4.7", "attribute names are CONSTANT_Utf8 entries"),
+    Names => ("jvms17-4.2-names", "4.2", "class, member, module and package names are well-formed"),
+}
+
+impl RuleId {
+    /// Returns the rule with the stable ID `id`.
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|rule| rule.id() == id)
+    }
+}
+
+impl fmt::Display for RuleId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+/// Returns every rule the validators check.
+pub fn rules() -> &'static [RuleId] {
+    RuleId::ALL
+}
+
+/// A selection of rules, all enabled by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuleSet {
+    disabled: BTreeSet<RuleId>,
+}
+
+impl RuleSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn enable(&mut self, rule: RuleId) -> &mut Self {
+        self.disabled.remove(&rule);
+        self
+    }
+
+    pub fn disable(&mut self, rule: RuleId) -> &mut Self {
+        self.disabled.insert(rule);
+        self
+    }
+
+    pub fn is_enabled(&self, rule: RuleId) -> bool {
+        !self.disabled.contains(&rule)
+    }
+
+    /// Returns the enabled rules, in [`RuleId::ALL`] order.
+    pub fn enabled(&self) -> impl Iterator<Item = RuleId> + '_ {
+        RuleId::ALL.iter().copied().filter(|rule| self.is_enabled(*rule))
+    }
+
+    /// Runs [`validate_access_flags`], [`JavaClassFile::check_version_consistency`] and
+    /// [`JavaClassFile::verify_format`], keeping the issues of enabled rules.
+    ///
+    /// Format violations are reported with the severity of `mode`.
+    pub fn validate(&self, class_file: &JavaClassFile, mode: ValidationMode) -> Vec<ValidationIssue> {
+        let severity = Validator::new(mode).severity;
+        let mut issues = validate_access_flags(class_file, mode);
+        issues.extend(class_file.check_version_consistency(mode));
+        issues.extend(class_file.verify_format().into_iter().map(|violation| ValidationIssue {
+            rule: violation.rule,
+            severity,
+            location: violation.location,
+            message: violation.message,
+        }));
+        issues.retain(|issue| self.is_enabled(issue.rule));
+        issues
+    }
+}
+
+/// A problem found during validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationIssue {
+    /// The rule the problem breaks.
+    pub rule: RuleId,
+    pub severity: Severity,
+    /// Where the issue was found, e.g. `access_flags` or `methods[3]`.
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: {}: {} [{}]", severity, self.location, self.message, self.rule)
+    }
+}
+
+/// Returns the spec name of an access flag, e.g. `ACC_PUBLIC`.
+fn flag_name(flag: impl fmt::Debug) -> String {
+    format!("ACC_{:?}", flag).to_uppercase()
+}
+
+/// Collects issues while walking a class file.
+struct Validator {
+    severity: Severity,
+    issues: Vec<ValidationIssue>,
+}
+
+impl Validator {
+    fn new(mode: ValidationMode) -> Self {
+        Self {
+            severity: match mode {
+                ValidationMode::Strict => Severity::Error,
+                ValidationMode::Lenient => Severity::Warning,
+            },
+            issues: Vec::new(),
+        }
+    }
+
+    fn report(&mut self, rule: RuleId, location: impl Into<String>, message: impl Into<String>) {
+        self.issues.push(ValidationIssue {
+            rule,
+            severity: self.severity,
+            location: location.into(),
+            message: message.into(),
+        });
+    }
+
+    /// Reports every flag of `forbidden` which is set in `flags`.
+    fn forbid<F: AccessFlag + fmt::Debug + Copy>(
+        &mut self,
+        rule: RuleId,
+        location: &str,
+        flags: u16,
+        forbidden: &[F],
+        reason: &str,
+    ) {
+        for flag in forbidden {
+            if flag.test(flags) {
+                self.report(rule, location, format!("{} must not be set {}", flag_name(flag), reason));
+            }
+        }
+    }
+
+    /// Reports if more than one of ACC_PUBLIC, ACC_PRIVATE and ACC_PROTECTED is set.
+    fn check_visibility(&mut self, rule: RuleId, location: &str, flags: u16) {
+        let visibility = [FieldAccessFlag::Public, FieldAccessFlag::Private, FieldAccessFlag::Protected];
+        if visibility.iter().filter(|flag| flag.test(flags)).count() > 1 {
+            self.report(rule, location, "at most one of ACC_PUBLIC, ACC_PRIVATE and ACC_PROTECTED may be set");
+        }
+    }
+}
+
+/// Validates the access flags of the class, its fields and its methods against the
+/// rules of the JVM specification for the class file's major version.
+///
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.1
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.5
+/// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.6
+pub fn validate_access_flags(class_file: &JavaClassFile, mode: ValidationMode) -> Vec<ValidationIssue> {
+    let mut validator = Validator::new(mode);
+    let major_version = class_file.major_version;
+    let flags = class_file.access_flags;
+
+    if ClassAccessFlag::Module.test(flags) {
+        if major_version < 53 {
+            validator.report(
+                RuleId::ModuleVersion,
+                "access_flags",
+                "ACC_MODULE requires class file version 53.0 or above",
+            );
+        }
+        if flags != ClassAccessFlag::Module as u16 {
+            validator.report(RuleId::ModuleFlags, "access_flags", "no other flag may be set with ACC_MODULE");
+        }
+        if !class_file.fields.is_empty() || !class_file.methods.is_empty() {
+            validator.report(RuleId::ModuleMembers, "access_flags", "a module must not declare fields or methods");
+        }
+        return validator.issues;
+    }
+
+    let is_interface = ClassAccessFlag::Interface.test(flags);
+    if is_interface {
+        // Interfaces compiled before Java 6 may lack ACC_ABSTRACT; the JVM treats them as if it were set.
+        if !ClassAccessFlag::Abstract.test(flags) && major_version >= 50 {
+            validator.report(RuleId::InterfaceFlags, "access_flags", "ACC_ABSTRACT must be set with ACC_INTERFACE");
+        }
+        validator.forbid(
+            RuleId::InterfaceFlags,
+            "access_flags",
+            flags,
+            &[ClassAccessFlag::Final, ClassAccessFlag::Super, ClassAccessFlag::Enum],
+            "with ACC_INTERFACE",
+        );
+    } else {
+        if ClassAccessFlag::Annotation.test(flags) {
+            validator.report(
+                RuleId::ClassFlags,
+                "access_flags",
+                "ACC_ANNOTATION must not be set without ACC_INTERFACE",
+            );
+        }
+        if ClassAccessFlag::Final.test(flags) && ClassAccessFlag::Abstract.test(flags) {
+            validator.report(RuleId::ClassFlags, "access_flags", "ACC_FINAL and ACC_ABSTRACT must not both be set");
+        }
+    }
+
+    for (i, field) in class_file.fields.iter().enumerate() {
+        let location = format!("fields[{}]", i);
+        let flags = field.access_flags;
+
+        validator.check_visibility(RuleId::FieldVisibility, &location, flags);
+        if FieldAccessFlag::Final.test(flags) && FieldAccessFlag::Volatile.test(flags) {
+            validator.report(RuleId::FieldFlags, &location, "ACC_FINAL and ACC_VOLATILE must not both be set");
+        }
+        if is_interface {
+            let required = [FieldAccessFlag::Public, FieldAccessFlag::Static, FieldAccessFlag::Final];
+            if !required.iter().all(|flag| flag.test(flags)) {
+                validator.report(
+                    RuleId::InterfaceFieldFlags,
+                    &location,
+                    "interface fields must be ACC_PUBLIC, ACC_STATIC and ACC_FINAL",
+                );
+            }
+            validator.forbid(
+                RuleId::InterfaceFieldFlags,
+                &location,
+                flags,
+                &[FieldAccessFlag::Volatile, FieldAccessFlag::Transient, FieldAccessFlag::Enum],
+                "on an interface field",
+            );
+        }
+    }
+
+    for (i, method) in class_file.methods.iter().enumerate() {
+        let location = format!("methods[{}]", i);
+        let flags = method.access_flags;
+        let name = get_utf8(&class_file.constant_pool, method.name_index).unwrap_or_default();
+
+        if name == "<clinit>" {
+            // Other flags of a class initializer are ignored by the JVM.
+            if major_version >= 51 && !MethodAccessFlag::Static.test(flags) {
+                validator.report(
+                    RuleId::ClinitFlags,
+                    &location,
+                    "<clinit> must be ACC_STATIC since class file version 51.0",
+                );
+            }
+            continue;
+        }
+
+        validator.check_visibility(RuleId::MethodVisibility, &location, flags);
+
+        if is_interface {
+            if major_version >= 52 {
+                if MethodAccessFlag::Public.test(flags) == MethodAccessFlag::Private.test(flags) {
+                    validator.report(
+                        RuleId::InterfaceMethodFlags,
+                        &location,
+                        "interface methods must be exactly one of ACC_PUBLIC and ACC_PRIVATE",
+                    );
+                }
+                validator.forbid(
+                    RuleId::InterfaceMethodFlags,
+                    &location,
+                    flags,
+                    &[MethodAccessFlag::Protected, MethodAccessFlag::Final, MethodAccessFlag::Synchronized, MethodAccessFlag::Native],
+                    "on an interface method",
+                );
+            } else if !MethodAccessFlag::Public.test(flags) || !MethodAccessFlag::Abstract.test(flags) {
+                validator.report(
+                    RuleId::InterfaceMethodFlags,
+                    &location,
+                    "interface methods must be ACC_PUBLIC and ACC_ABSTRACT before class file version 52.0",
+                );
+            }
+        }
+
+        if MethodAccessFlag::Abstract.test(flags) {
+            validator.forbid(
+                RuleId::AbstractMethodFlags,
+                &location,
+                flags,
+                &[MethodAccessFlag::Private, MethodAccessFlag::Static, MethodAccessFlag::Final, MethodAccessFlag::Synchronized, MethodAccessFlag::Native],
+                "with ACC_ABSTRACT",
+            );
+            // ACC_STRICT is obsolete since class file version 61.0.
+            if (46..=60).contains(&major_version) && MethodAccessFlag::Strict.test(flags) {
+                validator.report(
+                    RuleId::AbstractMethodFlags,
+                    &location,
+                    "ACC_STRICT must not be set with ACC_ABSTRACT",
+                );
+            }
+        }
+
+        if name == "<init>" {
+            validator.forbid(
+                RuleId::InitFlags,
+                &location,
+                flags,
+                &[MethodAccessFlag::Static, MethodAccessFlag::Final, MethodAccessFlag::Synchronized, MethodAccessFlag::Bridge, MethodAccessFlag::Native, MethodAccessFlag::Abstract],
+                "on an instance initializer",
+            );
+        }
+    }
+
+    validator.issues
+}
+
+/// Returns the spec name of a constant kind, e.g. `CONSTANT_Dynamic`.
+fn constant_name(kind: ConstantKind) -> String {
+    format!("CONSTANT_{:?}", kind)
+}
+
+impl Validator {
+    /// Reports attributes which are not defined in class file version `major_version`.
+    fn check_attribute_versions(
+        &mut self,
+        location: &str,
+        attributes: &Attributes,
+        constant_pool: &[ConstantPoolInfo],
+        major_version: u16,
+    ) {
+        for index in attributes.name_indices() {
+            let name = get_utf8(constant_pool, index as usize).unwrap_or_default();
+            let Some(since) = attribute_required_version(name) else {
+                continue;
+            };
+            if major_version < since {
+                self.report(
+                    RuleId::AttributeVersion,
+                    location,
+                    format!("{} attribute requires class file version {}.0 or above", name, since),
+                );
+            }
+        }
+    }
+}
+
+impl JavaClassFile<'_> {
+    /// Checks that the constant pool entries and attributes of this class file are
+    /// defined in its declared `major_version`, e.g. CONSTANT_Dynamic requires 55.0 and
+    /// Record requires 60.0, as when downlevel bytecode keeps attributes of a later release.
+    ///
+    /// The attributes of decoded Code attributes are checked too, e.g. StackMapTable requires
+    /// 50.0.
+    ///
+    /// A preview class file is also checked to target the supported major version, since
+    /// the JVM only loads preview class files of its own release.
+    pub fn check_version_consistency(&self, mode: ValidationMode) -> Vec<ValidationIssue> {
+        let mut validator = Validator::new(mode);
+        let major_version = self.major_version;
+
+        if self.is_preview() && major_version as f32 != SUPPORTED_MAJOR_VERSION {
+            validator.report(
+                RuleId::PreviewVersion,
+                "minor_version",
+                format!(
+                    "preview class file version {} does not match the supported major version {}",
+                    self.version(),
+                    SUPPORTED_MAJOR_VERSION
+                ),
+            );
+        }
+
+        for (i, constant) in self.constant_pool.iter().enumerate() {
+            let kind = match constant {
+                ConstantPoolInfo::MethodHandle(info) => info.tag,
+                ConstantPoolInfo::MethodType(info) => info.tag,
+                ConstantPoolInfo::Dynamic(info) => info.tag,
+                ConstantPoolInfo::InvokeDynamic(info) => info.tag,
+                ConstantPoolInfo::Module(info) => info.tag,
+                ConstantPoolInfo::Package(info) => info.tag,
+                _ => continue,
+            };
+            if major_version < kind.since_major_version() {
+                validator.report(
+                    RuleId::ConstantVersion,
+                    format!("constant_pool[{}]", i),
+                    format!(
+                        "{} requires class file version {}.0 or above",
+                        constant_name(kind),
+                        kind.since_major_version()
+                    ),
+                );
+            }
+        }
+
+        for (i, field) in self.fields.iter().enumerate() {
+            let location = format!("fields[{}]", i);
+            validator.check_attribute_versions(&location, &field.attributes, &self.constant_pool, major_version);
+        }
+        for (i, method) in self.methods.iter().enumerate() {
+            let location = format!("methods[{}]", i);
+            validator.check_attribute_versions(&location, &method.attributes, &self.constant_pool, major_version);
+            if let Some(code) = method.code() {
+                let location = format!("{}.Code", location);
+                validator.check_attribute_versions(&location, &code.attributes, &self.constant_pool, major_version);
+            }
+        }
+        validator.check_attribute_versions("attributes", &self.attributes, &self.constant_pool, major_version);
+
+        validator.issues
+    }
+}
@@ -0,0 +1,492 @@
+//! Verification of the code of class files, as the JVM does when it links a class, so tools
+//! producing class files can find bytecode the JVM would reject before handing it over.
+//!
+//! Class files of version 50.0 and later are checked by the type checking verifier against the
+//! frames of their StackMapTable: every instruction must accept the types its frame holds, and
+//! every frame must accept the types reaching it. Earlier class files are checked by the type
+//! inference verifier, which infers the frames itself, following subroutines called by jsr.
+//! Version 50.0 falls back to inference if type checking fails, as the JVM does.
+//!
+//! Classes are compared through a [`ClassHierarchy`], which must know the classes the code
+//! refers to: an unknown class is taken to extend `java/lang/Object` directly.
+//!
+//! ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.10
+
+use std::{borrow::Cow, collections::BTreeSet, fmt};
+
+use crate::{
+    cfg::{falls_through, jump_targets, local_slots, opcode_at, BasicBlock, CfgError, ControlFlowGraph, Edge, EdgeKind},
+    code::CodeView,
+    frames::{Checks, ClassHierarchy, Fault, Frame, Interpreter, VerificationType},
+    opcode::{instruction_length, Opcode},
+    types::*,
+};
+
+/// Code which does not pass verification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyError {
+    /// Name and descriptor of the method, e.g. `run()V`.
+    pub method: String,
+    /// Offset of the instruction, frame or exception handler at fault.
+    pub pc: usize,
+    pub reason: Cow<'static, str>,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: pc {}: {}", self.method, self.pc, self.reason)
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Verifies the code of every method of `class_file`, and returns the first failure of each
+/// method which does not pass, in method order.
+pub fn verify(class_file: &JavaClassFile, hierarchy: &impl ClassHierarchy) -> Vec<VerifyError> {
+    class_file.methods.iter().filter_map(|method| verify_method(class_file, method, hierarchy).err()).collect()
+}
+
+/// Verifies the code of `method` of `class_file`, by type checking or type inference depending
+/// on the version of the class file. Methods without code pass.
+pub fn verify_method(
+    class_file: &JavaClassFile,
+    method: &MethodInfo,
+    hierarchy: &impl ClassHierarchy,
+) -> Result<(), VerifyError> {
+    let constant_pool = &class_file.constant_pool;
+    let name = get_utf8(constant_pool, method.name_index).unwrap_or_default();
+    let descriptor = get_utf8(constant_pool, method.descriptor_index).unwrap_or_default();
+    let error = |pc: usize, reason: Fault| VerifyError { method: format!("{}{}", name, descriptor), pc, reason };
+
+    let this_class =
+        get_class_name(constant_pool, class_file.this_class).ok_or_else(|| error(0, "has no this_class".into()))?;
+    let code = match CodeView::new(method, constant_pool) {
+        Ok(Some(code)) => code,
+        Ok(None) => return Ok(()),
+        Err(_) => return Err(error(0, "has a malformed Code attribute".into())),
+    };
+    let initial = Frame::initial(this_class, name, descriptor, method.access_flags)
+        .ok_or_else(|| error(0, "has a malformed method descriptor".into()))?;
+    let (_, return_descriptor) = split_method_descriptor(descriptor).unwrap_or_default();
+    let return_type = match return_descriptor {
+        "V" => None,
+        descriptor => VerificationType::from_descriptor(descriptor),
+    };
+    let graph = ControlFlowGraph::from_code(&code).map_err(|CfgError { pc, reason }| error(pc, reason.into()))?;
+    if initial.locals.len() > code.max_locals as usize {
+        return Err(error(0, "has more parameters than max_locals".into()));
+    }
+    let interpreter = Interpreter {
+        class_file,
+        code: code.code,
+        this_class,
+        hierarchy,
+        checks: Some(Checks { return_type, constructor: name == "<init>" }),
+    };
+    let verifier = Verifier { interpreter, code: &code, graph: &graph };
+
+    let result = match class_file.major_version {
+        ..=49 => verifier.infer(initial),
+        major_version => {
+            let table = method.code().and_then(|code| {
+                code.attributes.values().find_map(|attribute| match attribute {
+                    AttributeInfo::StackMapTable(table) => Some(table),
+                    _ => None,
+                })
+            });
+            let checked = verifier.check(&initial, table);
+            match checked {
+                Err(_) if major_version == 50 => verifier.infer(initial),
+                checked => checked,
+            }
+        }
+    };
+    result.map_err(|(pc, reason)| error(pc, reason))
+}
+
+/// A failure to verify, at an offset.
+type Failure = (usize, Fault);
+
+struct Verifier<'v, 'c, 'h, H> {
+    interpreter: Interpreter<'c, 'h, H>,
+    code: &'v CodeView<'c, 'v>,
+    graph: &'v ControlFlowGraph,
+}
+
+impl<H: ClassHierarchy> Verifier<'_, '_, '_, H> {
+    /// Type checks the code against the frames of `table`.
+    ///
+    /// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.10.1
+    fn check(&self, initial: &Frame, table: Option<&StackMapTableAttribute>) -> Result<(), Failure> {
+        let code = self.code.code;
+        let frames = match table {
+            Some(table) => self.decode_frames(initial, table)?,
+            None => Vec::new(),
+        };
+        let frame_at = |pc: usize| {
+            let position = frames.binary_search_by_key(&pc, |(pc, _)| *pc).ok()?;
+            Some(&frames[position].1)
+        };
+        let declared = |pc: usize| frame_at(pc).ok_or((pc, Cow::Borrowed("is a jump target without a frame")));
+        let mut instructions = Vec::new();
+        let mut pc = 0;
+        while let Some(length) = instruction_length(code, pc) {
+            instructions.push(pc);
+            pc += length;
+        }
+        if let Some((pc, _)) = frames.iter().find(|(pc, _)| instructions.binary_search(pc).is_err()) {
+            return Err((*pc, "has a frame, but no instruction starts there".into()));
+        }
+
+        let mut current = Some(initial.clone());
+        for &pc in &instructions {
+            let length = instruction_length(code, pc).unwrap_or_default();
+            if let Some(frame) = frame_at(pc) {
+                if let Some(current) = &current {
+                    self.check_assignable(current, frame).map_err(|reason| (pc, reason))?;
+                }
+                current = Some(frame.clone());
+            }
+            let frame = current
+                .take()
+                .ok_or((pc, Cow::Borrowed("follows an instruction which does not fall through, but has no frame")))?;
+            for (handler, thrown) in self.thrown(&frame, pc)? {
+                self.check_assignable(&thrown, declared(handler)?).map_err(|reason| (handler, reason))?;
+            }
+
+            let mut next = frame;
+            self.execute(&mut next, pc)?;
+            let (targets, _) = jump_targets(code, pc).ok_or((pc, Cow::Borrowed("cannot be decoded")))?;
+            for target in targets {
+                self.check_assignable(&next, declared(target)?).map_err(|reason| (pc, reason))?;
+            }
+            if falls_through(opcode_at(code, pc)) {
+                if pc + length >= code.len() {
+                    return Err((pc, "falls off the end of the code".into()));
+                }
+                current = Some(next);
+            }
+        }
+        Ok(())
+    }
+
+    /// Infers the frames of the code, and checks the instructions against them.
+    ///
+    /// ref. https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.10.2
+    fn infer(&self, initial: Frame) -> Result<(), Failure> {
+        let code = self.code.code;
+        let merge = |frames: &mut Vec<Option<Frame>>, pending: &mut Vec<usize>, target: usize, frame: Frame| {
+            if self.interpreter.merge(&mut frames[target], frame).map_err(|reason| (target, reason.into()))? {
+                pending.push(target);
+            }
+            Ok(())
+        };
+        let mut frames: Vec<Option<Frame>> = vec![None; code.len()];
+        let mut pending = Vec::new();
+        merge(&mut frames, &mut pending, 0, initial)?;
+        while let Some(pc) = pending.pop() {
+            let Some(frame) = frames[pc].clone() else {
+                continue;
+            };
+            for (handler, thrown) in self.thrown(&frame, pc)? {
+                merge(&mut frames, &mut pending, handler, thrown)?;
+            }
+            let length = instruction_length(code, pc).ok_or((pc, Cow::Borrowed("cannot be decoded")))?;
+            let (targets, _) = jump_targets(code, pc).ok_or((pc, Cow::Borrowed("cannot be decoded")))?;
+            let mut next = frame;
+            match opcode_at(code, pc) {
+                Some(Opcode::Jsr | Opcode::JsrW) => {
+                    next.push(VerificationType::ReturnAddress);
+                    self.check_limits(&next).map_err(|reason| (pc, reason))?;
+                    for &target in &targets {
+                        merge(&mut frames, &mut pending, target, next.clone())?;
+                    }
+                    // The returns of the subroutine depend on the frames of its calls.
+                    let subroutine = self.graph.subroutines().iter().find(|subroutine| subroutine.calls.contains(&pc));
+                    for &ret in subroutine.map(|subroutine| &subroutine.rets[..]).unwrap_or_default() {
+                        if frames[ret].is_some() && !pending.contains(&ret) {
+                            pending.push(ret);
+                        }
+                    }
+                    continue;
+                }
+                Some(Opcode::Ret) => {
+                    let (index, _) = local_slots(code, pc).ok_or((pc, Cow::Borrowed("cannot be decoded")))?;
+                    if next.locals.get(index) != Some(&VerificationType::ReturnAddress) {
+                        return Err((pc, "returns through a local variable which holds no return address".into()));
+                    }
+                    for (call, returned) in self.returns(&frames, &next, pc)? {
+                        merge(&mut frames, &mut pending, call, returned)?;
+                    }
+                    continue;
+                }
+                _ => self.execute(&mut next, pc)?,
+            }
+            for target in targets {
+                merge(&mut frames, &mut pending, target, next.clone())?;
+            }
+            if falls_through(opcode_at(code, pc)) {
+                if pc + length >= code.len() {
+                    return Err((pc, "falls off the end of the code".into()));
+                }
+                merge(&mut frames, &mut pending, pc + length, next)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the frames following each call of the subroutine the ret at `pc` returns from,
+    /// with their offsets: the local variables the subroutine modifies are taken from `frame`,
+    /// and the others from the frame of the call.
+    fn returns(&self, frames: &[Option<Frame>], frame: &Frame, pc: usize) -> Result<Vec<(usize, Frame)>, Failure> {
+        let subroutines = self.graph.subroutines();
+        let Some(subroutine) = subroutines.iter().position(|subroutine| subroutine.rets.contains(&pc)) else {
+            return Err((pc, "returns from no subroutine".into()));
+        };
+        let modified = self.modified_locals(subroutine, &mut BTreeSet::new());
+        let mut returns = Vec::new();
+        for &call in &subroutines[subroutine].calls {
+            let Some(caller) = &frames[call] else {
+                continue;
+            };
+            let slots = frame.locals.len().max(caller.locals.len());
+            let mut locals: Vec<_> = (0..slots)
+                .map(|slot| match modified.contains(&slot) {
+                    true => frame.locals.get(slot),
+                    false => caller.locals.get(slot),
+                })
+                .map(|value| value.cloned().unwrap_or(VerificationType::Top))
+                .collect();
+            // A long or double of the caller may have been split by the subroutine.
+            for slot in 0..slots.saturating_sub(1) {
+                if locals[slot].is_wide() && locals[slot + 1] != VerificationType::Top {
+                    locals[slot] = VerificationType::Top;
+                }
+            }
+            let length = instruction_length(self.code.code, call).unwrap_or_default();
+            returns.push((call + length, Frame { locals, stack: frame.stack.clone() }));
+        }
+        Ok(returns)
+    }
+
+    /// Returns the local variable slots stored to by the subroutine at position `subroutine`
+    /// or those it calls.
+    fn modified_locals(&self, subroutine: usize, visited: &mut BTreeSet<usize>) -> BTreeSet<usize> {
+        let mut modified = BTreeSet::new();
+        if !visited.insert(subroutine) {
+            return modified;
+        }
+        let subroutines = self.graph.subroutines();
+        let code = self.code.code;
+        for block in self.subroutine_blocks(subroutine) {
+            let mut pc = block.start;
+            while pc < block.end {
+                let opcode = opcode_at(code, pc);
+                let byte = opcode.map_or(0, |opcode| opcode as u8);
+                let stores = (Opcode::Istore as u8..=Opcode::Astore3 as u8).contains(&byte);
+                match local_slots(code, pc) {
+                    Some((index, slots)) if stores => modified.extend(index..index + slots),
+                    _ => {}
+                }
+                if let Some(Opcode::Jsr | Opcode::JsrW) = opcode {
+                    let callee = subroutines.iter().position(|subroutine| subroutine.calls.contains(&pc));
+                    if let Some(callee) = callee {
+                        modified.extend(self.modified_locals(callee, visited));
+                    }
+                }
+                pc += instruction_length(code, pc).unwrap_or(block.end - pc);
+            }
+        }
+        modified
+    }
+
+    /// Returns the blocks of the subroutine at position `subroutine`: those its entry reaches
+    /// without calls or exceptions, and the exception handlers protecting only them, unlike
+    /// the handlers of code the subroutine is called from.
+    fn subroutine_blocks(&self, subroutine: usize) -> Vec<&BasicBlock> {
+        let candidates = &self.graph.subroutines()[subroutine].blocks;
+        let mut owned = BTreeSet::new();
+        let mut pending = vec![self.graph.subroutines()[subroutine].entry];
+        loop {
+            while let Some(start) = pending.pop() {
+                let Some(block) = self.graph.block_at(start).filter(|_| owned.insert(start)) else {
+                    continue;
+                };
+                let flows = |edge: &&Edge| matches!(edge.kind, EdgeKind::FallThrough | EdgeKind::Branch);
+                let targets = block.successors.iter().filter(flows);
+                // A nested call returns to the instruction following it.
+                let returns = matches!(opcode_at(self.code.code, block.last), Some(Opcode::Jsr | Opcode::JsrW));
+                let targets = targets.map(|edge| edge.target).chain(returns.then_some(block.end));
+                pending.extend(targets.filter(|target| candidates.contains(target)));
+            }
+            for entry in &self.code.exception_table {
+                let protected = (entry.start_pc as usize)..(entry.end_pc as usize);
+                let handler = entry.handler_pc as usize;
+                let mut covered = self.graph.blocks().iter().filter(|block| protected.contains(&block.start));
+                if !owned.contains(&handler)
+                    && candidates.contains(&handler)
+                    && covered.all(|block| owned.contains(&block.start))
+                {
+                    pending.push(handler);
+                }
+            }
+            if pending.is_empty() {
+                break;
+            }
+        }
+        owned.into_iter().filter_map(|start| self.graph.block_at(start)).collect()
+    }
+
+    /// Returns the frames the exception handlers covering `pc` are entered with from `frame`,
+    /// with the offsets of the handlers.
+    fn thrown(&self, frame: &Frame, pc: usize) -> Result<Vec<(usize, Frame)>, Failure> {
+        let constant_pool = &self.interpreter.class_file.constant_pool;
+        let throwable = VerificationType::Object("java/lang/Throwable".to_string());
+        let mut thrown = Vec::new();
+        for entry in &self.code.exception_table {
+            if !(entry.start_pc as usize..entry.end_pc as usize).contains(&pc) {
+                continue;
+            }
+            let handler = entry.handler_pc as usize;
+            let catch_type = match entry.catch_type {
+                0 => throwable.clone(),
+                index => match get_class_name(constant_pool, index as usize) {
+                    Some(name) => VerificationType::Object(name.to_string()),
+                    None => return Err((handler, "catches no class".into())),
+                },
+            };
+            if !self.interpreter.is_assignable(&catch_type, &throwable) {
+                return Err((handler, format!("catches {}, which is not a Throwable", catch_type).into()));
+            }
+            thrown.push((handler, Frame { locals: frame.locals.clone(), stack: vec![catch_type] }));
+        }
+        Ok(thrown)
+    }
+
+    fn execute(&self, frame: &mut Frame, pc: usize) -> Result<(), Failure> {
+        self.interpreter.execute(frame, pc).map_err(|reason| (pc, reason))?;
+        self.check_limits(frame).map_err(|reason| (pc, reason))
+    }
+
+    /// Fails if `frame` holds more than max_stack or max_locals allow.
+    fn check_limits(&self, frame: &Frame) -> Result<(), Fault> {
+        if frame.stack.len() > self.code.max_stack as usize {
+            let (slots, max_stack) = (frame.stack.len(), self.code.max_stack);
+            return Err(format!("grows the stack to {} slots, beyond max_stack {}", slots, max_stack).into());
+        }
+        if frame.locals.len() > self.code.max_locals as usize {
+            let (index, max_locals) = (frame.locals.len() - 1, self.code.max_locals);
+            return Err(format!("uses local variable {}, beyond max_locals {}", index, max_locals).into());
+        }
+        Ok(())
+    }
+
+    /// Fails if a value of `from` cannot be used where `to` holds one of the same slot.
+    fn check_assignable(&self, from: &Frame, to: &Frame) -> Result<(), Fault> {
+        if from.stack.len() != to.stack.len() {
+            let (slots, declared) = (from.stack.len(), to.stack.len());
+            return Err(format!("has a stack of {} slots, but its frame declares {}", slots, declared).into());
+        }
+        let top = VerificationType::Top;
+        for slot in 0..from.locals.len().max(to.locals.len()) {
+            let (from, to) = (from.locals.get(slot).unwrap_or(&top), to.locals.get(slot).unwrap_or(&top));
+            if !self.interpreter.is_assignable(from, to) {
+                return Err(format!("holds {} in local variable {}, but its frame declares {}", from, slot, to).into());
+            }
+        }
+        for (slot, (from, to)) in from.stack.iter().zip(&to.stack).enumerate() {
+            if !self.interpreter.is_assignable(from, to) {
+                return Err(format!("holds {} in stack slot {}, but its frame declares {}", from, slot, to).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Decodes the frames of `table`, with their offsets, starting from `initial`.
+    fn decode_frames(&self, initial: &Frame, table: &StackMapTableAttribute) -> Result<Vec<(usize, Frame)>, Failure> {
+        let constant_pool = &self.interpreter.class_file.constant_pool;
+        let mut frames: Vec<(usize, Frame)> = Vec::with_capacity(table.entries.len());
+        let mut locals: Vec<VerificationType> = initial.stack_map_locals().into_iter().cloned().collect();
+        let mut pc = None;
+        for entry in &table.entries {
+            let delta = entry_delta(entry) as usize;
+            let at = pc.map_or(delta, |pc| pc + delta + 1);
+            let types = |infos: &[VerificationTypeInfo]| {
+                infos
+                    .iter()
+                    .map(|info| verification_type(constant_pool, info))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or((at, Cow::Borrowed("has a frame with a type which cannot be resolved")))
+            };
+            let stack = match entry {
+                StackMapFrame::SameFrame(_) | StackMapFrame::SameFrameExtended(_) => Vec::new(),
+                StackMapFrame::SameLocals1StackItemFrame(frame) => types(std::slice::from_ref(&frame.stack))?,
+                StackMapFrame::SameLocals1StackItemFrameExtended(frame) => types(std::slice::from_ref(&frame.stack))?,
+                StackMapFrame::ChopFrame(frame) => {
+                    let chopped = 251 - frame.frame_type as usize;
+                    let kept = locals.len().checked_sub(chopped);
+                    let reason = "has a frame chopping more local variables than there are";
+                    locals.truncate(kept.ok_or((at, Cow::Borrowed(reason)))?);
+                    Vec::new()
+                }
+                StackMapFrame::AppendFrame(frame) => {
+                    locals.extend(types(&frame.locals)?);
+                    Vec::new()
+                }
+                StackMapFrame::FullFrame(frame) => {
+                    locals = types(&frame.locals)?;
+                    types(&frame.stack)?
+                }
+            };
+            if at >= self.code.code.len() {
+                return Err((at, "has a frame past the end of the code".into()));
+            }
+            let frame = Frame { locals: slots(&locals), stack: slots(&stack) };
+            self.check_limits(&frame).map_err(|reason| (at, reason))?;
+            frames.push((at, frame));
+            pc = Some(at);
+        }
+        Ok(frames)
+    }
+}
+
+fn entry_delta(entry: &StackMapFrame) -> u16 {
+    match entry {
+        StackMapFrame::SameFrame(frame) => frame.frame_type as u16,
+        StackMapFrame::SameLocals1StackItemFrame(frame) => frame.frame_type as u16 - 64,
+        StackMapFrame::SameLocals1StackItemFrameExtended(frame) => frame.offset_delta,
+        StackMapFrame::ChopFrame(frame) => frame.offset_delta,
+        StackMapFrame::SameFrameExtended(frame) => frame.offset_delta,
+        StackMapFrame::AppendFrame(frame) => frame.offset_delta,
+        StackMapFrame::FullFrame(frame) => frame.offset_delta,
+    }
+}
+
+fn verification_type(constant_pool: &[ConstantPoolInfo], info: &VerificationTypeInfo) -> Option<VerificationType> {
+    Some(match info {
+        VerificationTypeInfo::Top => VerificationType::Top,
+        VerificationTypeInfo::Integer => VerificationType::Integer,
+        VerificationTypeInfo::Float => VerificationType::Float,
+        VerificationTypeInfo::Long => VerificationType::Long,
+        VerificationTypeInfo::Double => VerificationType::Double,
+        VerificationTypeInfo::Null => VerificationType::Null,
+        VerificationTypeInfo::UninitializedThis => VerificationType::UninitializedThis,
+        VerificationTypeInfo::Object { cpool_index } => {
+            VerificationType::Object(get_class_name(constant_pool, *cpool_index as usize)?.to_string())
+        }
+        VerificationTypeInfo::Uninitialized { offset } => VerificationType::Uninitialized(*offset),
+    })
+}
+
+/// Expands types as a StackMapTable lists them into one per slot, a long or double followed
+/// by Top.
+fn slots(types: &[VerificationType]) -> Vec<VerificationType> {
+    let mut slots = Vec::with_capacity(types.len());
+    for value in types {
+        slots.push(value.clone());
+        if value.is_wide() {
+            slots.push(VerificationType::Top);
+        }
+    }
+    slots
+}
@@ -0,0 +1,50 @@
+//! Decoding into a custom allocator, with `--cfg allocator_api` on a nightly compiler.
+#![cfg(allocator_api)]
+#![feature(allocator_api)]
+
+use std::{
+    alloc::{AllocError, Allocator, Global, Layout},
+    cell::Cell,
+    ptr::NonNull,
+};
+
+use java_classfile::{decode, decode_in, decode_into, ParseOptions};
+
+const MARKER: &[u8] = include_bytes!("fixtures/Marker.class");
+
+/// Counts the live allocations made through it.
+#[derive(Default)]
+struct Counting {
+    live: Cell<usize>,
+}
+
+unsafe impl Allocator for &Counting {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.live.set(self.live.get() + 1);
+        Global.allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.live.set(self.live.get() - 1);
+        unsafe { Global.deallocate(ptr, layout) }
+    }
+}
+
+#[test]
+fn tables_in_allocator() {
+    let counting = Counting::default();
+    let (class_file, diagnostics) = decode_in(MARKER, &ParseOptions::default(), &counting).unwrap();
+    let expected = decode(MARKER).unwrap();
+    assert!(diagnostics.is_empty());
+    assert_eq!(class_file.constant_pool.as_slice(), expected.constant_pool.as_slice());
+    assert_eq!(class_file.interfaces.as_slice(), expected.interfaces.as_slice());
+    assert_eq!(class_file.methods.len(), expected.methods.len());
+    // At least the constant pool and the interfaces.
+    assert!(counting.live.get() >= 2);
+
+    let mut class_file = class_file;
+    decode_into(MARKER, &ParseOptions::default(), &mut class_file).unwrap();
+    assert_eq!(class_file.constant_pool.as_slice(), expected.constant_pool.as_slice());
+    drop(class_file);
+    assert_eq!(counting.live.get(), 0);
+}
@@ -0,0 +1,2 @@
+package p;
+public interface Empty {}
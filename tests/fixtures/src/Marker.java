@@ -0,0 +1,4 @@
+package p;
+import java.lang.annotation.*;
+@Retention(RetentionPolicy.RUNTIME) @Target(ElementType.TYPE)
+public @interface Marker { String value() default "x"; int[] ids() default {}; }
@@ -0,0 +1,101 @@
+package corpus;
+
+import java.io.StringReader;
+import java.io.BufferedReader;
+import java.io.IOException;
+import java.lang.annotation.ElementType;
+import java.lang.annotation.Retention;
+import java.lang.annotation.RetentionPolicy;
+import java.lang.annotation.Target;
+import java.util.ArrayList;
+import java.util.List;
+import java.util.function.Function;
+import java.util.function.Supplier;
+
+/** Class files with a wide range of attributes for round-trip tests. */
+public final class Shapes {
+    @Retention(RetentionPolicy.RUNTIME)
+    @Target({ElementType.TYPE, ElementType.METHOD, ElementType.RECORD_COMPONENT, ElementType.TYPE_USE})
+    @interface Tag {
+        String value() default "";
+        Kind kind() default Kind.ROUND;
+        int[] sizes() default {};
+    }
+
+    enum Kind { ROUND, ANGULAR }
+
+    sealed interface Shape permits Circle, Square {
+        double area();
+    }
+
+    @Tag(value = "circle", sizes = {1, 2})
+    record Circle(@Tag("r") double radius) implements Shape {
+        public double area() {
+            return Math.PI * radius * radius;
+        }
+    }
+
+    record Square(double side, @Deprecated List<@Tag String> labels) implements Shape {
+        Square {
+            labels = List.copyOf(labels);
+        }
+
+        public double area() {
+            return side * side;
+        }
+    }
+
+    private final List<Shape> shapes = new ArrayList<>();
+
+    @Tag(kind = Kind.ANGULAR)
+    public <T extends Shape> void add(T shape) {
+        shapes.add(shape);
+    }
+
+    public double total() {
+        double total = 0;
+        for (Shape shape : shapes) {
+            if (shape instanceof Circle circle) {
+                total += circle.area();
+            } else if (shape instanceof Square square && square.side() > 0) {
+                total += square.area();
+            }
+        }
+        return total;
+    }
+
+    public static String describe(String name) {
+        switch (name) {
+            case "circle":
+                return "round";
+            case "square":
+                return "angular";
+            default:
+                return "unknown";
+        }
+    }
+
+    public static int countLines(String text) throws IOException {
+        int count = 0;
+        try (BufferedReader reader = new BufferedReader(new StringReader(text))) {
+            while (reader.readLine() != null) {
+                count++;
+            }
+        } catch (IOException | RuntimeException e) {
+            throw e;
+        }
+        return count;
+    }
+
+    public Supplier<String> summary(String prefix) {
+        Function<Shape, String> name = shape -> shape.getClass().getSimpleName();
+        Runnable log = new Runnable() {
+            @Override
+            public void run() {
+                System.out.println(prefix + shapes.size());
+            }
+        };
+        log.run();
+        return () -> prefix + shapes.stream().map(name).reduce("", String::concat) + total();
+    }
+}
@@ -0,0 +1,15 @@
+package corpus;
+
+/** Constants whose modified UTF-8 differs from standard UTF-8. */
+public class Strings {
+    /** A high surrogate without its low surrogate. */
+    public static final String LONE_SURROGATE = "\uD800";
+    /** U+1F600, written as a surrogate pair of three bytes each. */
+    public static final String SUPPLEMENTARY = "\uD83D\uDE00";
+    /** NUL, written in two bytes. */
+    public static final String NUL = "a\u0000b";
+
+    public static String concatenated() {
+        return LONE_SURROGATE + SUPPLEMENTARY + NUL;
+    }
+}
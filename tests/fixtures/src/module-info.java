@@ -0,0 +1 @@
+module m { exports p; }
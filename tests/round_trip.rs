@@ -0,0 +1,359 @@
+//! Class files compiled by javac encode back to the same bytes, and their attributes keep
+//! the bodies they were decoded from.
+//!
+//! The fixtures are compiled with `javac --release 17 -g` from `tests/fixtures/src/corpus`.
+
+use std::{fs, path::PathBuf};
+
+use java_classfile::{
+    decode, encode, encode_with_options, frames::compute_frames, index::TypeHierarchyIndex,
+    remap::{remap_classes, Mappings}, types::*, verify::verify, DecodeError, EncodeError, EncodeOptions,
+};
+
+fn corpus() -> Vec<(PathBuf, Vec<u8>)> {
+    let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/corpus");
+    let mut classes: Vec<_> = fs::read_dir(directory)
+        .expect("corpus exists")
+        .map(|entry| entry.expect("corpus is readable").path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "class"))
+        .map(|path| {
+            let bytes = fs::read(&path).expect("class is readable");
+            (path, bytes)
+        })
+        .collect();
+    classes.sort();
+    assert!(!classes.is_empty());
+    classes
+}
+
+/// Checks that every attribute among `attributes`, including those of Code attributes, has
+/// a source within `bytes`.
+fn assert_sources(attributes: &Attributes, bytes: &[u8]) -> usize {
+    let mut count = 0;
+    for (position, (_, attribute)) in attributes.iter().enumerate() {
+        let source = attributes.source(position).expect("decoded attribute has a source");
+        let range = attributes.source_range(position, bytes).expect("source is within the class file");
+        assert_eq!(&bytes[range], source);
+        count += 1;
+        if let AttributeInfo::Code(code) = attribute {
+            count += assert_sources(&code.attributes, bytes);
+        }
+    }
+    count
+}
+
+#[test]
+fn corpus_round_trips() {
+    for (path, bytes) in corpus() {
+        let class_file = decode(&bytes).unwrap_or_else(|error| panic!("{}: {}", path.display(), error));
+        assert!(encode(&class_file).expect("class encodes") == bytes, "{} does not round-trip", path.display());
+    }
+}
+
+#[test]
+fn modified_utf8_constants_round_trip() {
+    let (_, bytes) =
+        corpus().into_iter().find(|(path, _)| path.ends_with("Strings.class")).expect("corpus has Strings");
+    let class_file = decode(&bytes).expect("class decodes");
+    let strings: Vec<_> = class_file
+        .constant_pool
+        .iter()
+        .filter_map(|constant| match constant {
+            ConstantPoolInfo::Utf8(info) => Some(info.data.to_string()),
+            _ => None,
+        })
+        .collect();
+    // The lone surrogate has no char of its own, so only its bytes keep it.
+    for string in ["\u{FFFD}", "\u{1F600}", "a\0b", "\u{FFFD}\u{1F600}a\0b"] {
+        assert!(strings.iter().any(|decoded| decoded == string), "{:?} is missing", string);
+    }
+    assert!(encode(&class_file).expect("class encodes") == bytes);
+}
+
+#[test]
+fn computed_maxs_match_javac() {
+    let options = EncodeOptions { compute_maxs: true, ..EncodeOptions::default() };
+    for (path, bytes) in corpus() {
+        let class_file = decode(&bytes).expect("class decodes");
+        let encoded = encode_with_options(&class_file, &options).expect("class encodes");
+        assert!(encoded == bytes, "{} has other maxs", path.display());
+    }
+}
+
+/// Returns the number of StackMapTable entries of each method.
+fn frame_counts(class_file: &JavaClassFile) -> Vec<usize> {
+    let count = |code: &CodeAttribute| {
+        code.attributes.values().find_map(|attribute| match attribute {
+            AttributeInfo::StackMapTable(table) => Some(table.entries.len()),
+            _ => None,
+        })
+    };
+    class_file.methods.iter().map(|method| method.code().and_then(count).unwrap_or(0)).collect()
+}
+
+#[test]
+fn computed_frames_are_where_javac_put_them() {
+    let corpus = corpus();
+    let mut index = TypeHierarchyIndex::new();
+    for (_, bytes) in &corpus {
+        index.add_class(&decode(bytes).expect("class decodes"));
+    }
+    let mut unchanged = 0;
+    for (path, bytes) in &corpus {
+        let mut class_file = decode(bytes).expect("class decodes");
+        let counts = frame_counts(&class_file);
+        compute_frames(&mut class_file, &index).unwrap_or_else(|error| panic!("{}: {}", path.display(), error));
+        assert_eq!(frame_counts(&class_file), counts, "{} has frames elsewhere", path.display());
+        // javac types locals as declared rather than as inferred, so some frames differ.
+        unchanged += usize::from(encode(&class_file).expect("class encodes") == *bytes);
+    }
+    assert!(unchanged > 0);
+}
+
+#[test]
+fn corpus_verifies() {
+    let corpus = corpus();
+    let mut index = TypeHierarchyIndex::new();
+    for (_, bytes) in &corpus {
+        index.add_class(&decode(bytes).expect("class decodes"));
+    }
+    for (path, bytes) in &corpus {
+        let mut class_file = decode(bytes).expect("class decodes");
+        assert_eq!(verify(&class_file, &index), [], "{} is rejected", path.display());
+        compute_frames(&mut class_file, &index).unwrap_or_else(|error| panic!("{}: {}", path.display(), error));
+        assert_eq!(verify(&class_file, &index), [], "{} is rejected with computed frames", path.display());
+        class_file.major_version = 49;
+        assert_eq!(verify(&class_file, &index), [], "{} is rejected by inference", path.display());
+    }
+}
+
+#[test]
+fn verifier_reports_missing_frames() {
+    let (_, bytes) = corpus().into_iter().find(|(path, _)| path.ends_with("Shapes.class")).expect("corpus has Shapes");
+    let mut class_file = decode(&bytes).expect("class decodes");
+    for attribute in class_file.methods.iter_mut().flat_map(|method| method.attributes.values_mut()) {
+        if let AttributeInfo::Code(code) = attribute {
+            code.attributes.retain(|_, attribute| !matches!(attribute, AttributeInfo::StackMapTable(_)));
+        }
+    }
+    let index = TypeHierarchyIndex::new();
+    let errors = verify(&class_file, &index);
+    assert_eq!(errors.len(), 3);
+    assert_eq!(errors[0].to_string(), "total()D: pc 94: is a jump target without a frame");
+    // Class files before version 50.0 are verified without frames.
+    class_file.major_version = 49;
+    assert_eq!(verify(&class_file, &index), []);
+}
+
+#[test]
+fn attributes_keep_their_source() {
+    let mut count = 0;
+    for (_, bytes) in corpus() {
+        let class_file = decode(&bytes).expect("class decodes");
+        count += assert_sources(&class_file.attributes, &bytes);
+        for field in &class_file.fields {
+            count += assert_sources(&field.attributes, &bytes);
+        }
+        for method in &class_file.methods {
+            count += assert_sources(&method.attributes, &bytes);
+        }
+    }
+    assert!(count > 0);
+}
+
+#[test]
+fn unknown_attributes_round_trip() {
+    let (_, bytes) = corpus().into_iter().next().expect("corpus is not empty");
+    let mut class_file = decode(&bytes).expect("class decodes");
+    let mut constants = ConstantPoolBuilder::extending(&class_file.constant_pool);
+    let name_index = constants.utf8("org.example.Custom") as u16;
+    class_file.constant_pool = constants.build();
+    let data: &[u8] = &[0xca, 0xfe, 0, 1, 2, 3];
+    class_file.attributes.push(name_index, AttributeInfo::Raw { name: "org.example.Custom".to_string(), data });
+    let position = class_file.attributes.len() - 1;
+    assert_eq!(class_file.attributes.source(position), None);
+
+    let encoded = encode(&class_file).expect("class encodes");
+    let mut decoded = decode(&encoded).expect("class decodes");
+    assert!(matches!(decoded.attributes.get(name_index), Some(AttributeInfo::Raw { data: body, .. }) if *body == data));
+    assert_eq!(decoded.attributes.source(position), Some(data));
+    assert!(encode(&decoded).expect("class encodes") == encoded);
+
+    // Decoded outside the crate and attached back.
+    let (found, body) = decoded
+        .named_attributes()
+        .raw()
+        .enumerate()
+        .find_map(|(position, (name, body))| (name == "org.example.Custom").then_some((position, body)))
+        .expect("custom attribute is found by name");
+    let body = body.expect("attribute encodes");
+    assert_eq!((found, &*body), (position, data));
+    let value = u16::from_be_bytes([body[0], body[1]]);
+    decoded.attributes.set_user_value(position, Box::new(value)).expect("raw attribute takes a value");
+    let custom = decoded.named_attributes().get("org.example.Custom");
+    assert_eq!(custom.and_then(|attribute| attribute.user_value::<u16>()), Some(&0xcafe));
+    assert!(encode(&decoded).expect("class encodes") == encoded);
+}
+
+#[test]
+fn oversized_counts_fail_to_encode() {
+    let class_file = JavaClassFile { interfaces: vec![1; 65536], ..JavaClassFile::empty() };
+    let expected = EncodeError::Overflow { while_encoding: "interfaces_count", value: 65536, max: 65535 };
+    assert_eq!(encode(&class_file), Err(expected));
+
+    let mut class_file = JavaClassFile::empty();
+    let mut constant_pool = ConstantPoolBuilder::extending(&class_file.constant_pool);
+    constant_pool.utf8("a".repeat(70000));
+    class_file.constant_pool = constant_pool.build();
+    let expected = EncodeError::Overflow { while_encoding: "CONSTANT_Utf8 length", value: 70000, max: 65535 };
+    assert_eq!(encode(&class_file), Err(expected));
+}
+
+#[test]
+fn duplicate_attributes_are_found_by_name() {
+    // Two SourceFile attributes whose names are different CONSTANT_Utf8 entries.
+    let mut class_file = JavaClassFile::empty();
+    let mut constant_pool = ConstantPoolBuilder::extending(&class_file.constant_pool);
+    let name_index = constant_pool.utf8("SourceFile") as u16;
+    let sourcefile_index = constant_pool.utf8("Empty.java") as u16;
+    class_file.constant_pool = constant_pool.build();
+    let duplicate_name_index = class_file.constant_pool.len() as u16;
+    let duplicate_name = class_file.constant_pool[name_index as usize].clone();
+    class_file.constant_pool.push(duplicate_name);
+    let source_file = || AttributeInfo::SourceFile(SourceFileAttribute { sourcefile_index });
+    class_file.attributes = Attributes::from(vec![(name_index, source_file()), (duplicate_name_index, source_file())]);
+
+    let bytes = encode(&class_file).expect("class encodes");
+    let error = decode(&bytes).expect_err("duplicate SourceFile is rejected");
+    assert!(matches!(error, DecodeError::DuplicateAttribute { name, .. } if name == "SourceFile"));
+}
+
+#[test]
+fn deeply_nested_code_attributes_are_rejected() {
+    let mut class_file = JavaClassFile::empty();
+    let mut constant_pool = ConstantPoolBuilder::extending(&class_file.constant_pool);
+    let code_index = constant_pool.utf8("Code") as u16;
+    class_file.constant_pool = constant_pool.build();
+
+    // Code attributes each holding the next one, about 95 KB for 5000 levels, which would
+    // overflow the stack of a decoder recursing into all of them.
+    let mut body = Vec::new();
+    for level in 0..5000 {
+        let mut outer = vec![0, 0, 0, 0, 0, 0, 0, 1, 0xB1, 0, 0];
+        if level == 0 {
+            outer.extend([0, 0]);
+        } else {
+            outer.extend([0, 1]);
+            outer.extend(code_index.to_be_bytes());
+            outer.extend((body.len() as u32).to_be_bytes());
+            outer.extend(&body);
+        }
+        body = outer;
+    }
+    let method = class_file.method_entry("run", "()V").or_insert(MethodAccessFlag::Static as u16);
+    method.attributes.push(code_index, AttributeInfo::Unknown(&body));
+    let bytes = encode(&class_file).expect("class encodes");
+
+    let error = decode(&bytes).expect_err("nesting is rejected");
+    assert!(matches!(error, DecodeError::NestingTooDeep { while_parsing: "attributes", .. }));
+}
+
+#[test]
+fn records_within_record_components_are_kept_raw() {
+    let mut class_file = JavaClassFile::empty();
+    let mut constant_pool = ConstantPoolBuilder::extending(&class_file.constant_pool);
+    let record_index = constant_pool.utf8("Record") as u16;
+    let name_index = constant_pool.utf8("x") as u16;
+    let descriptor_index = constant_pool.utf8("I") as u16;
+    class_file.constant_pool = constant_pool.build();
+    let record = |attributes| {
+        let components = vec![RecordComponentInfo { name_index, descriptor_index, attributes }];
+        AttributeInfo::Record(RecordAttribute { components_count: 1, components })
+    };
+    let inner = Attributes::from(vec![(record_index, record(Attributes::new()))]);
+    class_file.attributes = Attributes::from(vec![(record_index, record(inner))]);
+
+    let bytes = encode(&class_file).expect("class encodes");
+    let decoded = decode(&bytes).expect("class decodes");
+    let components = decoded.attributes.values().find_map(|attribute| match attribute {
+        AttributeInfo::Record(record) => Some(&record.components),
+        _ => None,
+    });
+    let component = &components.expect("class has a Record")[0];
+    assert!(matches!(component.attributes.values().next(), Some(AttributeInfo::Unknown(_))));
+    assert_eq!(encode(&decoded).expect("class encodes"), bytes);
+}
+
+#[test]
+fn remapping_renames_local_variables_records_and_enclosing_methods() {
+    let corpus = corpus();
+    let mut classes: Vec<_> = corpus.iter().map(|(_, bytes)| decode(bytes).expect("class decodes")).collect();
+    let mut mappings = Mappings::new();
+    mappings.add_class("corpus/Shapes$Shape", "corpus/Figure");
+    mappings.add_class("java/util/List", "corpus/Sequence");
+    mappings.add_method("corpus/Shapes", "summary", "(Ljava/lang/String;)Ljava/util/function/Supplier;", "overview");
+    mappings.add_field("corpus/Shapes$Square", "labels", "tags");
+    remap_classes(&mut classes, &mappings);
+
+    let class = |name: &str| {
+        classes
+            .iter()
+            .find(|class_file| get_class_name(&class_file.constant_pool, class_file.this_class) == Some(name))
+            .expect("class is in the corpus")
+    };
+    let local = |class_file: &JavaClassFile, method: &str, descriptor: &str, name: &str| {
+        let method = class_file.method(method, descriptor).expect("method exists");
+        let code = CodeView::new(method, &class_file.constant_pool).expect("code decodes").expect("method has code");
+        let locals = code.locals().expect("locals decode");
+        let local = locals.into_iter().find(|local| local.name == name).expect("local exists");
+        (local.descriptor.map(str::to_string), local.signature.map(str::to_string))
+    };
+
+    let enclosing_method = class("corpus/Shapes$1").enclosing_method().expect("class has EnclosingMethod");
+    assert_eq!(enclosing_method.method, Some(("overview", "(Ljava/lang/String;)Ljava/util/function/Supplier;")));
+
+    let square = class("corpus/Shapes$Square");
+    let components = square.record_components().expect("class is a record");
+    assert_eq!(components[1].name, "tags");
+    assert_eq!(components[1].descriptor, "Lcorpus/Sequence;");
+    assert_eq!(components[1].signature, Some("Lcorpus/Sequence<Ljava/lang/String;>;"));
+
+    let shapes = class("corpus/Shapes");
+    assert_eq!(local(shapes, "total", "()D", "shape").0.as_deref(), Some("Lcorpus/Figure;"));
+    let (_, signature) = local(shapes, "overview", "(Ljava/lang/String;)Ljava/util/function/Supplier;", "name");
+    assert_eq!(signature.as_deref(), Some("Ljava/util/function/Function<Lcorpus/Figure;Ljava/lang/String;>;"));
+
+    for class_file in &classes {
+        decode(&encode(class_file).expect("class encodes")).expect("remapped class decodes");
+    }
+}
+
+#[test]
+fn member_views_resolve_names() {
+    let (_, bytes) =
+        corpus().into_iter().find(|(path, _)| path.ends_with("Strings.class")).expect("corpus has Strings");
+    let mut class_file = decode(&bytes).expect("class decodes");
+
+    let fields: Vec<_> = class_file.field_views().into_iter().map(|field| (field.name, field.descriptor)).collect();
+    assert_eq!(fields, [
+        (Some("LONE_SURROGATE"), Some("Ljava/lang/String;")),
+        (Some("SUPPLEMENTARY"), Some("Ljava/lang/String;")),
+        (Some("NUL"), Some("Ljava/lang/String;")),
+    ]);
+    let methods = class_file.method_views();
+    assert_eq!(methods.len(), 2);
+    assert_eq!(methods.iter().next_back().and_then(|method| method.name), Some("concatenated"));
+
+    for method in class_file.method_views_mut() {
+        if method.name == Some("concatenated") {
+            method.member.access_flags |= MethodAccessFlag::Synchronized as u16;
+        }
+    }
+    let method = class_file.method("concatenated", "()Ljava/lang/String;").expect("method exists");
+    assert!(method.access_flags & MethodAccessFlag::Synchronized as u16 != 0);
+
+    // Views resolve against the constant pool, so an unresolvable name is reported as such.
+    let mut fields = class_file.field_views_mut();
+    fields.iter_mut().next().expect("class has fields").member.name_index = 0;
+    assert_eq!(fields.iter().next().map(|field| field.name), Some(None));
+}
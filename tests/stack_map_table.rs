@@ -0,0 +1,53 @@
+//! StackMapTable frames survive a round trip in the form they were written.
+
+use java_classfile::{decode, encode, types::*};
+
+/// Builds a class whose `run` method has frames in a longer form than javac would write.
+fn class_with_frames() -> Vec<u8> {
+    let mut class_file = JavaClassFile::empty();
+    let mut constants = ConstantPoolBuilder::extending(&class_file.constant_pool);
+    let code_index = constants.utf8("Code") as u16;
+    let stack_map_table_index = constants.utf8("StackMapTable") as u16;
+    class_file.constant_pool = constants.build();
+
+    let frames = vec![
+        // Could be a same_frame.
+        StackMapFrame::SameFrameExtended(SameFrameExtended::new(1)),
+        // Could be a same_frame too, as it repeats the empty locals of the previous frame.
+        StackMapFrame::FullFrame(FullFrame::new(0, Vec::new(), Vec::new()).unwrap()),
+        StackMapFrame::SameLocals1StackItemFrameExtended(SameLocals1StackItemFrameExtended::new(
+            0,
+            VerificationTypeInfo::Integer,
+        )),
+    ];
+    let mut code_attributes = Attributes::default();
+    let stack_map_table = StackMapTableAttribute::new(frames).unwrap();
+    code_attributes.push(stack_map_table_index, AttributeInfo::StackMapTable(stack_map_table));
+    // nop, nop, iconst_0, pop, return
+    let code = CodeAttribute::new(1, 0, &[0x00, 0x00, 0x03, 0x57, 0xb1], Vec::new(), code_attributes).unwrap();
+
+    let method = class_file.method_entry("run", "()V").or_insert(MethodAccessFlag::Static as u16);
+    method.attributes.push(code_index, AttributeInfo::Code(code));
+    encode(&class_file).expect("class encodes")
+}
+
+#[test]
+fn frames_keep_their_encoding() {
+    let bytes = class_with_frames();
+    let class_file = decode(&bytes).expect("class decodes");
+    assert_eq!(encode(&class_file).expect("class encodes"), bytes);
+
+    let code = class_file.methods[0].code().expect("method has code");
+    let frames = code.attributes.values().find_map(|attribute| match attribute {
+        AttributeInfo::StackMapTable(attribute) => Some(&attribute.entries),
+        _ => None,
+    });
+    assert!(matches!(
+        frames.expect("code has a StackMapTable").as_slice(),
+        [
+            StackMapFrame::SameFrameExtended(SameFrameExtended { frame_type: 251, offset_delta: 1 }),
+            StackMapFrame::FullFrame(FullFrame { frame_type: 255, offset_delta: 0, .. }),
+            StackMapFrame::SameLocals1StackItemFrameExtended(SameLocals1StackItemFrameExtended { frame_type: 247, .. }),
+        ]
+    ));
+}
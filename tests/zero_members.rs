@@ -0,0 +1,72 @@
+//! Classes without interfaces, fields or methods.
+//!
+//! The fixtures are compiled with `javac --release 17` from `tests/fixtures/src`.
+
+use java_classfile::{decode, encode, types::*, validate::*};
+
+const EMPTY_INTERFACE: &[u8] = include_bytes!("fixtures/Empty.class");
+const MODULE_INFO: &[u8] = include_bytes!("fixtures/module-info.class");
+const ANNOTATION_TYPE: &[u8] = include_bytes!("fixtures/Marker.class");
+
+fn assert_round_trip(bytes: &[u8]) -> JavaClassFile<'_> {
+    let class_file = decode(bytes).expect("fixture decodes");
+    assert_eq!(encode(&class_file).expect("class encodes"), bytes);
+    assert!(class_file.verify_format().is_empty());
+    assert!(validate_access_flags(&class_file, ValidationMode::Strict).is_empty());
+    class_file
+}
+
+#[test]
+fn interface_without_members() {
+    let class_file = assert_round_trip(EMPTY_INTERFACE);
+    assert!(ClassAccessFlag::Interface.test(class_file.access_flags));
+    assert!(class_file.interfaces.is_empty());
+    assert!(class_file.fields.is_empty());
+    assert!(class_file.methods.is_empty());
+}
+
+#[test]
+fn module_info_without_methods() {
+    let class_file = assert_round_trip(MODULE_INFO);
+    assert_eq!(class_file.access_flags, ClassAccessFlag::Module as u16);
+    assert_eq!(class_file.super_class, 0);
+    assert!(class_file.fields.is_empty());
+    assert!(class_file.methods.is_empty());
+    assert!(class_file.attributes.values().any(|attribute| matches!(attribute, AttributeInfo::Module(_))));
+}
+
+#[test]
+fn annotation_type() {
+    let class_file = assert_round_trip(ANNOTATION_TYPE);
+    assert!(ClassAccessFlag::Annotation.test(class_file.access_flags));
+    assert!(class_file.fields.is_empty());
+    assert_eq!(class_file.methods.len(), 2);
+    assert!(class_file
+        .methods
+        .iter()
+        .all(|method| MethodAccessFlag::Abstract.test(method.access_flags)));
+}
+
+#[test]
+fn empty_class_round_trips() {
+    let bytes = encode(&JavaClassFile::empty()).expect("class encodes");
+    let class_file = assert_round_trip(&bytes);
+    assert_eq!(get_class_name(&class_file.constant_pool, class_file.this_class), Some("Empty"));
+    assert_eq!(get_class_name(&class_file.constant_pool, class_file.super_class), Some("java/lang/Object"));
+    assert_eq!(class_file.major_version, 52);
+}
+
+#[test]
+fn empty_constant_pool_encodes_a_valid_count() {
+    let class_file = JavaClassFile {
+        constant_pool: Vec::new(),
+        this_class: 0,
+        super_class: 0,
+        ..JavaClassFile::empty()
+    };
+    let bytes = encode(&class_file).expect("class encodes");
+    assert_eq!(&bytes[8..10], &[0, 1]);
+    let decoded = decode(&bytes).expect("decodes");
+    assert_eq!(decoded.constant_pool.len(), 1);
+    assert_eq!(encode(&decoded).expect("class encodes"), bytes);
+}